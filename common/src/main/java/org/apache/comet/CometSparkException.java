@@ -0,0 +1,61 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one
+ * or more contributor license agreements.  See the NOTICE file
+ * distributed with this work for additional information
+ * regarding copyright ownership.  The ASF licenses this file
+ * to you under the Apache License, Version 2.0 (the
+ * "License"); you may not use this file except in compliance
+ * with the License.  You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+ * KIND, either express or implied.  See the License for the
+ * specific language governing permissions and limitations
+ * under the License.
+ */
+
+package org.apache.comet;
+
+import java.util.HashMap;
+import java.util.Map;
+
+/**
+ * Exception thrown from Comet native side for an error that Spark raises with an error class, e.g.,
+ * an ANSI mode failure. It is converted to the Spark exception of the error class, and its message
+ * is the one formatted by Spark 3.4.
+ */
+public class CometSparkException extends CometNativeException {
+  private final String errorClass;
+  private final Map<String, String> messageParameters;
+  private final String sqlState;
+
+  public CometSparkException(
+      String message,
+      String errorClass,
+      String[] parameterNames,
+      String[] parameterValues,
+      String sqlState) {
+    super(message);
+    this.errorClass = errorClass;
+    this.messageParameters = new HashMap<>();
+    for (int i = 0; i < parameterNames.length; i++) {
+      messageParameters.put(parameterNames[i], parameterValues[i]);
+    }
+    this.sqlState = sqlState;
+  }
+
+  public String getErrorClass() {
+    return errorClass;
+  }
+
+  public Map<String, String> getMessageParameters() {
+    return messageParameters;
+  }
+
+  public String getSqlState() {
+    return sqlState;
+  }
+}
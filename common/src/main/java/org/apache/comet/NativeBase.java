@@ -27,6 +27,10 @@ import java.io.InputStream;
 import java.io.InputStreamReader;
 import java.nio.file.Files;
 import java.nio.file.StandardCopyOption;
+import java.util.Arrays;
+import java.util.Collections;
+import java.util.HashSet;
+import java.util.Set;
 
 import org.slf4j.Logger;
 import org.slf4j.LoggerFactory;
@@ -41,11 +45,38 @@ public abstract class NativeBase {
   static final String ARROW_UNSAFE_MEMORY_ACCESS = "arrow.enable_unsafe_memory_access";
   static final String ARROW_NULL_CHECK_FOR_GET = "arrow.enable_null_check_for_get";
 
+  /**
+   * The version of the JNI interface of the native library this jar is built against. The native
+   * library is only used if it implements the same version. Native methods added without changing
+   * the existing ones don't bump the version, but are probed with {@link #hasCapability}.
+   */
+  static final int ABI_VERSION = 1;
+
+  /** Whether the native library can decode native shuffle blocks, i.e., the native reader. */
+  public static final String CAPABILITY_NATIVE_SHUFFLE_READER = "native_shuffle_reader";
+  /** Whether the native library can coalesce the batches decoded from native shuffle blocks. */
+  public static final String CAPABILITY_SHUFFLE_COALESCER = "shuffle_coalescer";
+  /** Whether the native library can map broadcast relations into memory shared by tasks. */
+  public static final String CAPABILITY_MAPPED_BROADCAST = "mapped_broadcast";
+  /** Whether the native library applies the resource profiles of native plans. */
+  public static final String CAPABILITY_RESOURCE_PROFILE = "resource_profile";
+  /** Whether the native library can offload native operators to registered providers. */
+  public static final String CAPABILITY_OPERATOR_OFFLOAD = "operator_offload";
+  /** Whether the native library can read data files natively, i.e., the native file scan. */
+  public static final String CAPABILITY_NATIVE_FILE_SCAN = "native_file_scan";
+  /** Whether native shuffle writers can encrypt the shuffle data with the IO encryption key. */
+  public static final String CAPABILITY_SHUFFLE_ENCRYPTION = "shuffle_encryption";
+  /** Whether the scans of a native plan can share the batches of an input, e.g., a reused one. */
+  public static final String CAPABILITY_SHARED_SCAN = "shared_scan";
+  /** Whether native sort merge joins can join the rows with the same keys by a band condition. */
+  public static final String CAPABILITY_BAND_JOIN = "band_join";
+
   private static final Logger LOG = LoggerFactory.getLogger(NativeBase.class);
   private static final String NATIVE_LIB_NAME = "comet";
 
   private static final String libraryToLoad = System.mapLibraryName(NATIVE_LIB_NAME);
   private static boolean loaded = false;
+  private static Set<String> capabilities = Collections.emptySet();
   private static final String searchPattern = "libcomet-";
 
   static {
@@ -58,6 +89,14 @@ public abstract class NativeBase {
     return loaded;
   }
 
+  /**
+   * Returns whether the loaded native library has the given optional feature, so that the plugin
+   * can work with a native library of another release implementing the same ABI version.
+   */
+  public static synchronized boolean hasCapability(String capability) {
+    return loaded && capabilities.contains(capability);
+  }
+
   // Only for testing
   static synchronized void setLoaded(boolean b) {
     loaded = b;
@@ -88,6 +127,11 @@ public abstract class NativeBase {
       bundleLoadLibrary();
     }
 
+    if (!probeNativeLibrary()) {
+      loaded = false;
+      return;
+    }
+
     initWithLogConf();
     // Only set the Arrow properties when debugging mode is off
     if (!(boolean) CometConf.COMET_DEBUG_ENABLED().get()) {
@@ -142,6 +186,36 @@ public abstract class NativeBase {
     }
   }
 
+  /**
+   * Checks the ABI version of the loaded native library, and gets its capabilities. Returns false
+   * if the library implements another ABI version, in which case Comet is disabled.
+   */
+  private static boolean probeNativeLibrary() {
+    int nativeVersion;
+    String[] nativeCapabilities;
+    try {
+      nativeVersion = abiVersion();
+      nativeCapabilities = capabilities();
+    } catch (UnsatisfiedLinkError e) {
+      // The native library predates the versioned ABI
+      nativeVersion = 0;
+      nativeCapabilities = new String[0];
+    }
+
+    if (nativeVersion != ABI_VERSION) {
+      LOG.warn(
+          "Comet is disabled. The native library implements JNI ABI version {}, while the Comet "
+              + "jar requires version {}. Please use the native library of the same release.",
+          nativeVersion,
+          ABI_VERSION);
+      return false;
+    }
+
+    capabilities = Collections.unmodifiableSet(new HashSet<>(Arrays.asList(nativeCapabilities)));
+    LOG.info("Comet native library capabilities: {}", capabilities);
+    return true;
+  }
+
   private static void initWithLogConf() {
     String logConfPath = System.getProperty(LOG_CONF_PATH(), Utils.getConfPath(LOG_CONF_NAME()));
 
@@ -275,4 +349,10 @@ public abstract class NativeBase {
    * @param logConfPath location to the native log configuration file
    */
   static native void init(String logConfPath);
+
+  /** Returns the version of the JNI interface implemented by the native library. */
+  static native int abiVersion();
+
+  /** Returns the optional features of the native library. */
+  static native String[] capabilities();
 }
@@ -36,7 +36,7 @@ public abstract class AbstractColumnReader implements AutoCloseable {
   /** The Spark data type. */
   protected final DataType type;
 
-  /** Parquet column descriptor. */
+  /** Parquet column descriptor, null for a struct column. */
   protected final ColumnDescriptor descriptor;
 
   /**
@@ -68,7 +68,9 @@ public abstract class AbstractColumnReader implements AutoCloseable {
     this.descriptor = descriptor;
     this.useDecimal128 = useDecimal128;
     this.useLegacyDateTimestamp = useLegacyDateTimestamp;
-    TypeUtil.checkParquetType(descriptor, type);
+    if (descriptor != null) {
+      TypeUtil.checkParquetType(descriptor, type);
+    }
   }
 
   public ColumnDescriptor getDescriptor() {
@@ -109,8 +111,14 @@ public abstract class AbstractColumnReader implements AutoCloseable {
     DataType readType = (boolean) CometConf.COMET_SCHEMA_EVOLUTION_ENABLED().get() ? type : null;
     boolean useLegacyDateTimestampOrNTZ =
         useLegacyDateTimestamp || type == TimestampNTZType$.MODULE$;
+    boolean validateUtf8 = (boolean) CometConf.COMET_PARQUET_UTF8_VALIDATION_ENABLED().get();
     nativeHandle =
         Utils.initColumnReader(
-            descriptor, readType, batchSize, useDecimal128, useLegacyDateTimestampOrNTZ);
+            descriptor,
+            readType,
+            batchSize,
+            useDecimal128,
+            useLegacyDateTimestampOrNTZ,
+            validateUtf8);
   }
 }
@@ -25,7 +25,6 @@ import java.net.URI;
 import java.net.URISyntaxException;
 import java.util.Arrays;
 import java.util.HashMap;
-import java.util.List;
 import java.util.Map;
 import java.util.concurrent.Callable;
 import java.util.concurrent.ExecutorService;
@@ -46,7 +45,6 @@ import org.apache.hadoop.mapreduce.RecordReader;
 import org.apache.hadoop.mapreduce.TaskAttemptContext;
 import org.apache.parquet.HadoopReadOptions;
 import org.apache.parquet.ParquetReadOptions;
-import org.apache.parquet.Preconditions;
 import org.apache.parquet.column.ColumnDescriptor;
 import org.apache.parquet.column.page.PageReadStore;
 import org.apache.parquet.hadoop.metadata.ParquetMetadata;
@@ -262,30 +260,43 @@ public class BatchReader extends RecordReader<Void, ColumnarBatch> implements Cl
       requestedSchema =
           CometParquetReadSupport.clipParquetSchema(
               requestedSchema, sparkSchema, isCaseSensitive, useFieldId, ignoreMissingIds);
-      if (requestedSchema.getColumns().size() != sparkSchema.size()) {
+      if (requestedSchema.getFieldCount() != sparkSchema.size()) {
         throw new IllegalArgumentException(
             String.format(
                 "Spark schema has %d columns while " + "Parquet schema has %d columns",
-                sparkSchema.size(), requestedSchema.getColumns().size()));
+                sparkSchema.size(), requestedSchema.getFieldCount()));
       }
     }
 
     totalRowCount = fileReader.getRecordCount();
-    List<ColumnDescriptor> columns = requestedSchema.getColumns();
-    int numColumns = columns.size();
+    int numFields = requestedSchema.getFieldCount();
+    int numColumns = numFields;
     if (partitionSchema != null) numColumns += partitionSchema.size();
     columnReaders = new AbstractColumnReader[numColumns];
 
     // Initialize missing columns and use null vectors for them
-    missingColumns = new boolean[columns.size()];
-    List<String[]> paths = requestedSchema.getPaths();
+    missingColumns = new boolean[numFields];
     StructField[] nonPartitionFields = sparkSchema.fields();
-    for (int i = 0; i < requestedSchema.getFieldCount(); i++) {
+    for (int i = 0; i < numFields; i++) {
       Type t = requestedSchema.getFields().get(i);
-      Preconditions.checkState(
-          t.isPrimitive() && !t.isRepetition(Type.Repetition.REPEATED),
-          "Complex type is not supported");
-      String[] colPath = paths.get(i);
+      String[] colPath = new String[] {t.getName()};
+      DataType dataType = nonPartitionFields[i].dataType();
+      // The fields of a struct missing in the file are read as nulls by its reader
+      NestedColumnReader nestedReader =
+          NestedColumnReader.create(
+              dataType,
+              t,
+              colPath,
+              requestedSchema,
+              fileSchema,
+              capacity,
+              useDecimal128,
+              useLegacyDateTimestamp);
+      if (nestedReader != null) {
+        columnReaders[i] = nestedReader;
+        missingColumns[i] = false;
+        continue;
+      }
       if (nonPartitionFields[i].name().equals(ShimFileFormat.ROW_INDEX_TEMPORARY_COLUMN_NAME())) {
         // Values of ROW_INDEX_TEMPORARY_COLUMN_NAME column are always populated with
         // generated row indexes, rather than read from the file.
@@ -296,12 +307,12 @@ public class BatchReader extends RecordReader<Void, ColumnarBatch> implements Cl
         missingColumns[i] = true;
       } else if (fileSchema.containsPath(colPath)) {
         ColumnDescriptor fd = fileSchema.getColumnDescription(colPath);
-        if (!fd.equals(columns.get(i))) {
+        if (!fd.equals(requestedSchema.getColumnDescription(colPath))) {
           throw new UnsupportedOperationException("Schema evolution is not supported");
         }
         missingColumns[i] = false;
       } else {
-        if (columns.get(i).getMaxDefinitionLevel() == 0) {
+        if (requestedSchema.getColumnDescription(colPath).getMaxDefinitionLevel() == 0) {
           throw new IOException(
               "Required column '"
                   + Arrays.toString(colPath)
@@ -319,8 +330,8 @@ public class BatchReader extends RecordReader<Void, ColumnarBatch> implements Cl
     // Initialize constant readers for partition columns
     if (partitionSchema != null) {
       StructField[] partitionFields = partitionSchema.fields();
-      for (int i = columns.size(); i < columnReaders.length; i++) {
-        int fieldIndex = i - columns.size();
+      for (int i = numFields; i < columnReaders.length; i++) {
+        int fieldIndex = i - numFields;
         StructField field = partitionFields[fieldIndex];
         ConstantColumnReader reader =
             new ConstantColumnReader(field, capacity, partitionValues, fieldIndex, useDecimal128);
@@ -552,10 +563,16 @@ public class BatchReader extends RecordReader<Void, ColumnarBatch> implements Cl
       numRowGroupsMetric.add(1);
     }
 
-    List<ColumnDescriptor> columns = requestedSchema.getColumns();
-    for (int i = 0; i < columns.size(); i++) {
+    for (int i = 0; i < missingColumns.length; i++) {
       if (missingColumns[i]) continue;
+      if (columnReaders[i] instanceof NestedColumnReader) {
+        ((NestedColumnReader) columnReaders[i]).setRowGroup(rowGroupReader);
+        continue;
+      }
       if (columnReaders[i] != null) columnReaders[i].close();
+      ColumnDescriptor column =
+          requestedSchema.getColumnDescription(
+              new String[] {requestedSchema.getFields().get(i).getName()});
       // TODO: handle tz, datetime & int96 rebase
       // TODO: consider passing page reader via ctor - however we need to fix the shading issue
       //   from Iceberg side.
@@ -563,12 +580,12 @@ public class BatchReader extends RecordReader<Void, ColumnarBatch> implements Cl
       ColumnReader reader =
           Utils.getColumnReader(
               dataType,
-              columns.get(i),
+              column,
               capacity,
               useDecimal128,
               useLazyMaterialization,
               useLegacyDateTimestamp);
-      reader.setPageReader(rowGroupReader.getPageReader(columns.get(i)));
+      reader.setPageReader(rowGroupReader.getPageReader(column));
       columnReaders[i] = reader;
     }
     totalRowsLoaded += rowGroupReader.getRowCount();
@@ -306,6 +306,9 @@ public class ColumnReader extends AbstractColumnReader {
       case PLAIN:
       case RLE_DICTIONARY:
       case PLAIN_DICTIONARY:
+      case DELTA_BINARY_PACKED:
+      case DELTA_LENGTH_BYTE_ARRAY:
+      case DELTA_BYTE_ARRAY:
         return true;
       default:
         return false;
@@ -63,6 +63,10 @@ public class CometInputFile implements InputFile {
     return stat.getLen();
   }
 
+  public long getModificationTime() {
+    return stat.getModificationTime();
+  }
+
   public Configuration getConf() {
     return this.conf;
   }
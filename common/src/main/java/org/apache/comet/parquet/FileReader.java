@@ -190,6 +190,16 @@ public class FileReader implements Closeable {
     return this.f;
   }
 
+  /** Adds `value` to the metric `name` of this reader if there is the metric. */
+  void addMetric(String name, long value) {
+    if (metrics != null && value != 0) {
+      SQLMetric metric = metrics.get(name);
+      if (metric != null) {
+        metric.add(value);
+      }
+    }
+  }
+
   /** Returns the Parquet options for reading the file. */
   public ParquetReadOptions getOptions() {
     return this.options;
@@ -715,7 +725,18 @@ public class FileReader implements Closeable {
       if (options.useBloomFilter()) {
         levels.add(BLOOMFILTER);
       }
-      return RowGroupFilter.filterRowGroups(levels, recordFilter, blocks, this);
+      List<BlockMetaData> filteredBlocks =
+          RowGroupFilter.filterRowGroups(levels, recordFilter, blocks, this);
+      long skippedRows = 0;
+      for (BlockMetaData block : blocks) {
+        skippedRows += block.getRowCount();
+      }
+      for (BlockMetaData block : filteredBlocks) {
+        skippedRows -= block.getRowCount();
+      }
+      addMetric("ParquetRowGroupsSkipped", blocks.size() - filteredBlocks.size());
+      addMetric("ParquetRowsSkipped", skippedRows);
+      return filteredBlocks;
     }
 
     return blocks;
@@ -0,0 +1,152 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one
+ * or more contributor license agreements.  See the NOTICE file
+ * distributed with this work for additional information
+ * regarding copyright ownership.  The ASF licenses this file
+ * to you under the Apache License, Version 2.0 (the
+ * "License"); you may not use this file except in compliance
+ * with the License.  You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+ * KIND, either express or implied.  See the License for the
+ * specific language governing permissions and limitations
+ * under the License.
+ */
+
+package org.apache.comet.parquet;
+
+import java.io.IOException;
+import java.util.LinkedHashMap;
+import java.util.Map;
+import java.util.Objects;
+
+import org.apache.parquet.hadoop.metadata.ParquetMetadata;
+
+/**
+ * A LRU cache of parsed Parquet footers, shared by all the tasks of an executor, so that repeated
+ * scans of the same files, e.g., iterative ML workloads or re-scans by AQE, skip fetching and
+ * parsing the footers again.
+ *
+ * <p>The footers are keyed by the file path, the modification time and the length of the file,
+ * so a file which has been overwritten is never served a stale footer. The range of the file split
+ * is part of the key as well, since the row groups of a footer are filtered by the range.
+ */
+public class FooterCache {
+  private static final FooterCache INSTANCE = new FooterCache();
+
+  private final LinkedHashMap<Key, ParquetMetadata> footers;
+  private volatile int maxEntries;
+  private long hits;
+  private long misses;
+
+  FooterCache() {
+    this.footers =
+        new LinkedHashMap<Key, ParquetMetadata>(16, 0.75f, true) {
+          @Override
+          protected boolean removeEldestEntry(Map.Entry<Key, ParquetMetadata> eldest) {
+            return size() > maxEntries;
+          }
+        };
+  }
+
+  /** Returns the footer cache of this executor. */
+  public static FooterCache get() {
+    return INSTANCE;
+  }
+
+  /** Loads a footer on a cache miss. */
+  public interface Loader {
+    ParquetMetadata load() throws IOException;
+  }
+
+  /**
+   * Returns the cached footer of `key`, or loads and caches it with `loader` if absent. The cache
+   * keeps at most `maxEntries` footers, evicting the least recently used ones.
+   *
+   * <p>The footer is loaded outside of the lock so that tasks reading different files don't wait
+   * for each other. Two tasks missing the same footer at the same time may both load it.
+   */
+  public ParquetMetadata getOrLoad(Key key, int maxEntries, Loader loader) throws IOException {
+    synchronized (this) {
+      this.maxEntries = maxEntries;
+      ParquetMetadata footer = footers.get(key);
+      if (footer != null) {
+        hits++;
+        return footer;
+      }
+      misses++;
+    }
+    ParquetMetadata footer = loader.load();
+    synchronized (this) {
+      footers.put(key, footer);
+    }
+    return footer;
+  }
+
+  /** Returns the number of cache hits since the executor started. */
+  public synchronized long hitCount() {
+    return hits;
+  }
+
+  /** Returns the number of cache misses since the executor started. */
+  public synchronized long missCount() {
+    return misses;
+  }
+
+  /** Returns the number of cached footers. */
+  public synchronized int size() {
+    return footers.size();
+  }
+
+  /** Removes all the cached footers. */
+  public synchronized void clear() {
+    footers.clear();
+  }
+
+  /** The identity of a footer read from a range of a file. */
+  public static final class Key {
+    private final String path;
+    private final long modificationTime;
+    private final long length;
+    private final long rangeStart;
+    private final long rangeEnd;
+
+    public Key(String path, long modificationTime, long length, long rangeStart, long rangeEnd) {
+      this.path = path;
+      this.modificationTime = modificationTime;
+      this.length = length;
+      this.rangeStart = rangeStart;
+      this.rangeEnd = rangeEnd;
+    }
+
+    @Override
+    public boolean equals(Object obj) {
+      if (this == obj) {
+        return true;
+      } else if (obj instanceof Key) {
+        Key other = (Key) obj;
+        return modificationTime == other.modificationTime
+            && length == other.length
+            && rangeStart == other.rangeStart
+            && rangeEnd == other.rangeEnd
+            && path.equals(other.path);
+      } else {
+        return false;
+      }
+    }
+
+    @Override
+    public int hashCode() {
+      return Objects.hash(path, modificationTime, length, rangeStart, rangeEnd);
+    }
+
+    @Override
+    public String toString() {
+      return path + "@" + modificationTime + "[" + rangeStart + ", " + rangeEnd + ")";
+    }
+  }
+}
@@ -30,11 +30,15 @@ import org.apache.parquet.ParquetReadOptions;
 import org.apache.parquet.hadoop.metadata.ParquetMetadata;
 import org.apache.spark.sql.execution.datasources.PartitionedFile;
 
+import org.apache.comet.CometConf;
+
 /**
  * Copied from Spark's `ParquetFooterReader` in order to avoid shading issue around Parquet.
  *
  * <p>`FooterReader` is a util class which encapsulates the helper methods of reading parquet file
  * footer.
+ *
+ * <p>The footers are cached by {@link FooterCache} if enabled.
  */
 public class FooterReader {
   public static ParquetMetadata readFooter(Configuration configuration, PartitionedFile file)
@@ -48,6 +52,33 @@ public class FooterReader {
             .withRange(start, start + length)
             .build();
     ReadOptions cometReadOptions = ReadOptions.builder(configuration).build();
+
+    boolean cacheEnabled =
+        configuration.getBoolean(
+            CometConf.COMET_PARQUET_FOOTER_CACHE_ENABLED().key(),
+            (Boolean) CometConf.COMET_PARQUET_FOOTER_CACHE_ENABLED().defaultValue().get());
+    // The decryptor of an encrypted footer is bound to the read options, so it is not cached
+    if (!cacheEnabled || readOptions.getDecryptionProperties() != null) {
+      return readFooter(inputFile, readOptions, cometReadOptions);
+    }
+    int maxEntries =
+        configuration.getInt(
+            CometConf.COMET_PARQUET_FOOTER_CACHE_MAX_ENTRIES().key(),
+            (Integer) CometConf.COMET_PARQUET_FOOTER_CACHE_MAX_ENTRIES().defaultValue().get());
+    FooterCache.Key key =
+        new FooterCache.Key(
+            inputFile.toString(),
+            inputFile.getModificationTime(),
+            inputFile.getLength(),
+            start,
+            start + length);
+    return FooterCache.get()
+        .getOrLoad(key, maxEntries, () -> readFooter(inputFile, readOptions, cometReadOptions));
+  }
+
+  private static ParquetMetadata readFooter(
+      CometInputFile inputFile, ParquetReadOptions readOptions, ReadOptions cometReadOptions)
+      throws IOException {
     // Use try-with-resources to ensure fd is closed.
     try (FileReader fileReader = new FileReader(inputFile, readOptions, cometReadOptions)) {
       return fileReader.getFooter();
@@ -0,0 +1,199 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one
+ * or more contributor license agreements.  See the NOTICE file
+ * distributed with this work for additional information
+ * regarding copyright ownership.  The ASF licenses this file
+ * to you under the Apache License, Version 2.0 (the
+ * "License"); you may not use this file except in compliance
+ * with the License.  You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+ * KIND, either express or implied.  See the License for the
+ * specific language governing permissions and limitations
+ * under the License.
+ */
+
+package org.apache.comet.parquet;
+
+import java.io.IOException;
+
+import org.apache.arrow.vector.FieldVector;
+import org.apache.parquet.column.ColumnDescriptor;
+import org.apache.parquet.column.page.PageReadStore;
+import org.apache.parquet.schema.GroupType;
+import org.apache.parquet.schema.LogicalTypeAnnotation.ListLogicalTypeAnnotation;
+import org.apache.parquet.schema.MessageType;
+import org.apache.parquet.schema.Type;
+import org.apache.spark.sql.types.ArrayType;
+import org.apache.spark.sql.types.StructType;
+
+import org.apache.comet.vector.CometListVector;
+import org.apache.comet.vector.CometVector;
+
+/**
+ * A column reader for a Parquet list column, i.e., a group annotated as a list, in the standard
+ * 3-level layout or in a legacy 2-level layout, or a repeated field which isn't in a list. Its
+ * element is read by a column reader if primitive, or by a struct, list or map reader, from which
+ * the lists are reconstructed natively with the definition and repetition levels of the Parquet
+ * columns under it.
+ */
+public class ListColumnReader extends NestedColumnReader {
+  private final ArrayType arrayType;
+
+  /** The reader of the element, which is recreated for each row group if primitive. */
+  private AbstractColumnReader elementReader;
+
+  /** The Parquet column of the element if primitive, or null. */
+  private final ColumnDescriptor elementColumn;
+
+  /** The maximum definition level of the list, under which it is null. */
+  private final int definitionLevel;
+
+  /** The maximum repetition level of the repeated field of the list. */
+  private final int repetitionLevel;
+
+  public ListColumnReader(
+      ArrayType type,
+      Type parquetType,
+      String[] path,
+      MessageType requestedSchema,
+      MessageType fileSchema,
+      int capacity,
+      boolean useDecimal128,
+      boolean useLegacyDateTimestamp) {
+    super(type, capacity, useDecimal128, useLegacyDateTimestamp);
+    this.arrayType = type;
+
+    Type repeatedType;
+    String[] repeatedPath;
+    if (parquetType.isRepetition(Type.Repetition.REPEATED)) {
+      // A repeated field which isn't in a list is a list of required elements, which is never
+      // null, so it has no definition level of its own
+      repeatedType = parquetType;
+      repeatedPath = path;
+      this.definitionLevel = requestedSchema.getMaxDefinitionLevel(path) - 1;
+    } else {
+      GroupType listType = parquetType.asGroupType();
+      if (listType.getFieldCount() != 1
+          || !listType.getType(0).isRepetition(Type.Repetition.REPEATED)) {
+        throw new UnsupportedOperationException("Invalid list type: " + parquetType);
+      }
+      repeatedType = listType.getType(0);
+      repeatedPath = append(path, repeatedType.getName());
+      this.definitionLevel = requestedSchema.getMaxDefinitionLevel(path);
+    }
+    this.repetitionLevel = requestedSchema.getMaxRepetitionLevel(repeatedPath);
+
+    Type elementType;
+    String[] elementPath;
+    if (repeatedType == parquetType || isElementType(repeatedType, parquetType.getName())) {
+      // The repeated field is the element itself, in a legacy 2-level layout or without a list
+      elementType = repeatedType;
+      elementPath = repeatedPath;
+    } else {
+      elementType = repeatedType.asGroupType().getType(0);
+      elementPath = append(repeatedPath, elementType.getName());
+    }
+
+    if (repeatedType != elementType) {
+      elementReader =
+          NestedColumnReader.create(
+              type.elementType(),
+              elementType,
+              elementPath,
+              requestedSchema,
+              fileSchema,
+              capacity,
+              useDecimal128,
+              useLegacyDateTimestamp);
+    } else if (!elementType.isPrimitive() && type.elementType() instanceof StructType) {
+      // The repeated group is the struct element, which is required
+      elementReader =
+          new StructColumnReader(
+              (StructType) type.elementType(),
+              elementType.asGroupType(),
+              elementPath,
+              requestedSchema,
+              fileSchema,
+              capacity,
+              useDecimal128,
+              useLegacyDateTimestamp);
+    } else if (!elementType.isPrimitive()) {
+      throw new UnsupportedOperationException("Complex type is not supported");
+    }
+    elementColumn =
+        elementReader == null
+            ? NestedColumnReader.repeatedColumn(elementPath, requestedSchema, fileSchema)
+            : null;
+  }
+
+  /** Whether the given Parquet type is read as a list. */
+  static boolean isList(Type type) {
+    return type.isRepetition(Type.Repetition.REPEATED)
+        || (!type.isPrimitive()
+            && type.getLogicalTypeAnnotation() instanceof ListLogicalTypeAnnotation);
+  }
+
+  /**
+   * Whether the repeated field of a list is its element, in a legacy 2-level layout, following the
+   * backward-compatibility rules of the Parquet format, like Spark.
+   */
+  private static boolean isElementType(Type repeatedType, String listName) {
+    return repeatedType.isPrimitive()
+        || repeatedType.asGroupType().getFieldCount() > 1
+        || repeatedType.getName().equals("array")
+        || repeatedType.getName().equals(listName + "_tuple");
+  }
+
+  @Override
+  public void setRowGroup(PageReadStore rowGroup) throws IOException {
+    if (elementReader instanceof NestedColumnReader) {
+      ((NestedColumnReader) elementReader).setRowGroup(rowGroup);
+    } else {
+      if (elementReader != null) elementReader.close();
+      elementReader = newColumnReader(arrayType.elementType(), elementColumn, rowGroup);
+    }
+
+    closeNative();
+    nativeHandle =
+        Native.initListReader(
+            elementReader.nativeHandle,
+            NestedColumnReader.nativeKind(elementReader),
+            definitionLevel,
+            repetitionLevel);
+  }
+
+  @Override
+  public void readBatch(int total) {
+    elementReader.readBatch(total);
+    this.numRows = total;
+  }
+
+  @Override
+  protected long[] nativeBatch(int numRows) {
+    return Native.currentListBatch(nativeHandle, numRows);
+  }
+
+  @Override
+  protected CometVector wrapVector(FieldVector vector) {
+    return new CometListVector(vector, useDecimal128);
+  }
+
+  @Override
+  public void close() {
+    super.close();
+    if (elementReader != null) elementReader.close();
+    closeNative();
+  }
+
+  private void closeNative() {
+    if (nativeHandle != 0) {
+      Native.closeListReader(nativeHandle);
+      nativeHandle = 0;
+    }
+  }
+}
@@ -0,0 +1,192 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one
+ * or more contributor license agreements.  See the NOTICE file
+ * distributed with this work for additional information
+ * regarding copyright ownership.  The ASF licenses this file
+ * to you under the Apache License, Version 2.0 (the
+ * "License"); you may not use this file except in compliance
+ * with the License.  You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+ * KIND, either express or implied.  See the License for the
+ * specific language governing permissions and limitations
+ * under the License.
+ */
+
+package org.apache.comet.parquet;
+
+import java.io.IOException;
+
+import org.apache.arrow.vector.FieldVector;
+import org.apache.parquet.column.ColumnDescriptor;
+import org.apache.parquet.column.page.PageReadStore;
+import org.apache.parquet.schema.GroupType;
+import org.apache.parquet.schema.LogicalTypeAnnotation;
+import org.apache.parquet.schema.LogicalTypeAnnotation.MapKeyValueTypeAnnotation;
+import org.apache.parquet.schema.LogicalTypeAnnotation.MapLogicalTypeAnnotation;
+import org.apache.parquet.schema.MessageType;
+import org.apache.parquet.schema.Type;
+import org.apache.spark.sql.types.DataType;
+import org.apache.spark.sql.types.MapType;
+
+import org.apache.comet.vector.CometMapVector;
+import org.apache.comet.vector.CometVector;
+
+/**
+ * A column reader for a Parquet map column, i.e., a group annotated as a map, or as a key-value
+ * group in legacy files, whose repeated key-value group has a key and a value field. The keys and
+ * values are read by column readers if primitive, or by struct, list or map readers, from which
+ * the maps are reconstructed natively with the definition and repetition levels of the Parquet
+ * columns under it.
+ */
+public class MapColumnReader extends NestedColumnReader {
+  private final MapType mapType;
+
+  /** The readers of the keys and values, which are recreated for each row group if primitive. */
+  private AbstractColumnReader keyReader;
+
+  private AbstractColumnReader valueReader;
+
+  /** The Parquet columns of the keys and values if primitive, or null. */
+  private final ColumnDescriptor keyColumn;
+
+  private final ColumnDescriptor valueColumn;
+
+  /** The maximum definition level of the map, under which it is null. */
+  private final int definitionLevel;
+
+  /** The maximum repetition level of the key-value group of the map. */
+  private final int repetitionLevel;
+
+  public MapColumnReader(
+      MapType type,
+      GroupType parquetType,
+      String[] path,
+      MessageType requestedSchema,
+      MessageType fileSchema,
+      int capacity,
+      boolean useDecimal128,
+      boolean useLegacyDateTimestamp) {
+    super(type, capacity, useDecimal128, useLegacyDateTimestamp);
+    this.mapType = type;
+
+    if (parquetType.getFieldCount() != 1
+        || parquetType.getType(0).isPrimitive()
+        || !parquetType.getType(0).isRepetition(Type.Repetition.REPEATED)
+        || parquetType.getType(0).asGroupType().getFieldCount() != 2) {
+      throw new UnsupportedOperationException("Invalid map type: " + parquetType);
+    }
+    GroupType keyValueType = parquetType.getType(0).asGroupType();
+    String[] keyValuePath = append(path, keyValueType.getName());
+    this.definitionLevel = requestedSchema.getMaxDefinitionLevel(path);
+    this.repetitionLevel = requestedSchema.getMaxRepetitionLevel(keyValuePath);
+
+    Type keyType = keyValueType.getType(0);
+    String[] keyPath = append(keyValuePath, keyType.getName());
+    keyReader =
+        NestedColumnReader.create(
+            type.keyType(),
+            keyType,
+            keyPath,
+            requestedSchema,
+            fileSchema,
+            capacity,
+            useDecimal128,
+            useLegacyDateTimestamp);
+    keyColumn =
+        keyReader == null
+            ? NestedColumnReader.repeatedColumn(keyPath, requestedSchema, fileSchema)
+            : null;
+
+    Type valueType = keyValueType.getType(1);
+    String[] valuePath = append(keyValuePath, valueType.getName());
+    valueReader =
+        NestedColumnReader.create(
+            type.valueType(),
+            valueType,
+            valuePath,
+            requestedSchema,
+            fileSchema,
+            capacity,
+            useDecimal128,
+            useLegacyDateTimestamp);
+    valueColumn =
+        valueReader == null
+            ? NestedColumnReader.repeatedColumn(valuePath, requestedSchema, fileSchema)
+            : null;
+  }
+
+  /** Whether the given Parquet type is read as a map. */
+  static boolean isMap(Type type) {
+    if (type.isPrimitive() || type.isRepetition(Type.Repetition.REPEATED)) {
+      return false;
+    }
+    LogicalTypeAnnotation annotation = type.getLogicalTypeAnnotation();
+    return annotation instanceof MapLogicalTypeAnnotation
+        || annotation instanceof MapKeyValueTypeAnnotation;
+  }
+
+  @Override
+  public void setRowGroup(PageReadStore rowGroup) throws IOException {
+    keyReader = setRowGroup(keyReader, mapType.keyType(), keyColumn, rowGroup);
+    valueReader = setRowGroup(valueReader, mapType.valueType(), valueColumn, rowGroup);
+
+    closeNative();
+    nativeHandle =
+        Native.initMapReader(
+            keyReader.nativeHandle,
+            NestedColumnReader.nativeKind(keyReader),
+            valueReader.nativeHandle,
+            NestedColumnReader.nativeKind(valueReader),
+            definitionLevel,
+            repetitionLevel);
+  }
+
+  /** Sets the row group of the given reader of the keys or values, or recreates it if primitive. */
+  private AbstractColumnReader setRowGroup(
+      AbstractColumnReader reader, DataType type, ColumnDescriptor column, PageReadStore rowGroup)
+      throws IOException {
+    if (reader instanceof NestedColumnReader) {
+      ((NestedColumnReader) reader).setRowGroup(rowGroup);
+      return reader;
+    }
+    if (reader != null) reader.close();
+    return newColumnReader(type, column, rowGroup);
+  }
+
+  @Override
+  public void readBatch(int total) {
+    keyReader.readBatch(total);
+    valueReader.readBatch(total);
+    this.numRows = total;
+  }
+
+  @Override
+  protected long[] nativeBatch(int numRows) {
+    return Native.currentMapBatch(nativeHandle, numRows);
+  }
+
+  @Override
+  protected CometVector wrapVector(FieldVector vector) {
+    return new CometMapVector(vector, useDecimal128);
+  }
+
+  @Override
+  public void close() {
+    super.close();
+    if (keyReader != null) keyReader.close();
+    if (valueReader != null) valueReader.close();
+    closeNative();
+  }
+
+  private void closeNative() {
+    if (nativeHandle != 0) {
+      Native.closeMapReader(nativeHandle);
+      nativeHandle = 0;
+    }
+  }
+}
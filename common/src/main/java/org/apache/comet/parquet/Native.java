@@ -60,6 +60,7 @@ public final class Native extends NativeBase {
    * @param batchSize the batch size for the columnar read
    * @param useDecimal128 whether to always return 128 bit decimal regardless of precision
    * @param useLegacyDateTimestampOrNTZ whether to read legacy dates/timestamps as it is
+   * @param validateUtf8 whether to check that the values of string columns are valid UTF-8
    * @return a pointer to a native Parquet column reader created
    */
   public static native long initColumnReader(
@@ -79,7 +80,8 @@ public final class Native extends NativeBase {
       boolean isAdjustedUtc,
       int batchSize,
       boolean useDecimal128,
-      boolean useLegacyDateTimestampOrNTZ);
+      boolean useLegacyDateTimestampOrNTZ,
+      boolean validateUtf8);
 
   /**
    * Pass a Parquet dictionary page to the native column reader. Note this should only be called
@@ -232,4 +234,108 @@ public final class Native extends NativeBase {
    * @param handle the handle to the native Parquet column reader
    */
   public static native void closeColumnReader(long handle);
+
+  /**
+   * Creates a native reader of a Parquet struct column, whose fields are read by the given native
+   * readers. The struct reader doesn't own the readers of its fields.
+   *
+   * @param names the names of the fields of the struct
+   * @param handles the handles to the native readers of the fields
+   * @param kinds whether each field is read by a native Parquet column reader (0), struct reader
+   *     (1), list reader (2) or map reader (3)
+   * @param definitionLevel the maximum definition level of the struct, under which it is null
+   * @return a pointer to a native Parquet struct reader created
+   */
+  public static native long initStructReader(
+      String[] names, long[] handles, int[] kinds, int definitionLevel);
+
+  /**
+   * Returns the current batch of structs, reconstructed from the current batches of the readers
+   * of their fields.
+   *
+   * @param handle the handle to the native Parquet struct reader
+   * @param numRows the number of rows read by the readers of the fields
+   * @return a long array with 2 elements, the first is the address to native Arrow array, and the
+   *     second is the address to the Arrow schema.
+   */
+  public static native long[] currentStructBatch(long handle, int numRows);
+
+  /**
+   * Closes the native Parquet struct reader, but not the readers of its fields.
+   *
+   * @param handle the handle to the native Parquet struct reader
+   */
+  public static native void closeStructReader(long handle);
+
+  /**
+   * Creates a native reader of a Parquet list column, whose elements are read by the given native
+   * reader. The list reader doesn't own the reader of its elements.
+   *
+   * @param elementHandle the handle to the native reader of the elements
+   * @param elementKind whether the elements are read by a native Parquet column reader (0),
+   *     struct reader (1), list reader (2) or map reader (3)
+   * @param definitionLevel the maximum definition level of the list, under which it is null
+   * @param repetitionLevel the maximum repetition level of the repeated field of the list
+   * @return a pointer to a native Parquet list reader created
+   */
+  public static native long initListReader(
+      long elementHandle, int elementKind, int definitionLevel, int repetitionLevel);
+
+  /**
+   * Returns the current batch of lists, reconstructed from the current batch of the reader of
+   * their elements.
+   *
+   * @param handle the handle to the native Parquet list reader
+   * @param numRows the number of rows read by the reader of the elements
+   * @return a long array with 2 elements, the first is the address to native Arrow array, and the
+   *     second is the address to the Arrow schema.
+   */
+  public static native long[] currentListBatch(long handle, int numRows);
+
+  /**
+   * Closes the native Parquet list reader, but not the reader of its elements.
+   *
+   * @param handle the handle to the native Parquet list reader
+   */
+  public static native void closeListReader(long handle);
+
+  /**
+   * Creates a native reader of a Parquet map column, whose keys and values are read by the given
+   * native readers. The map reader doesn't own the readers of its keys and values.
+   *
+   * @param keyHandle the handle to the native reader of the keys
+   * @param keyKind whether the keys are read by a native Parquet column reader (0), struct reader
+   *     (1), list reader (2) or map reader (3)
+   * @param valueHandle the handle to the native reader of the values
+   * @param valueKind whether the values are read by a native Parquet column reader (0), struct
+   *     reader (1), list reader (2) or map reader (3)
+   * @param definitionLevel the maximum definition level of the map, under which it is null
+   * @param repetitionLevel the maximum repetition level of the key-value group of the map
+   * @return a pointer to a native Parquet map reader created
+   */
+  public static native long initMapReader(
+      long keyHandle,
+      int keyKind,
+      long valueHandle,
+      int valueKind,
+      int definitionLevel,
+      int repetitionLevel);
+
+  /**
+   * Returns the current batch of maps, reconstructed from the current batches of the readers of
+   * their keys and values.
+   *
+   * @param handle the handle to the native Parquet map reader
+   * @param numRows the number of rows read by the readers of the keys and values
+   * @return a long array with 2 elements, the first is the address to native Arrow array, and the
+   *     second is the address to the Arrow schema.
+   */
+  public static native long[] currentMapBatch(long handle, int numRows);
+
+  /**
+   * Closes the native Parquet map reader, but not the readers of its keys and values.
+   *
+   * @param handle the handle to the native Parquet map reader
+   */
+  public static native void closeMapReader(long handle);
 }
@@ -0,0 +1,211 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one
+ * or more contributor license agreements.  See the NOTICE file
+ * distributed with this work for additional information
+ * regarding copyright ownership.  The ASF licenses this file
+ * to you under the Apache License, Version 2.0 (the
+ * "License"); you may not use this file except in compliance
+ * with the License.  You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+ * KIND, either express or implied.  See the License for the
+ * specific language governing permissions and limitations
+ * under the License.
+ */
+
+package org.apache.comet.parquet;
+
+import java.io.IOException;
+import java.util.Arrays;
+
+import org.apache.arrow.c.ArrowArray;
+import org.apache.arrow.c.ArrowSchema;
+import org.apache.arrow.c.Data;
+import org.apache.arrow.memory.BufferAllocator;
+import org.apache.arrow.memory.RootAllocator;
+import org.apache.arrow.vector.FieldVector;
+import org.apache.parquet.column.ColumnDescriptor;
+import org.apache.parquet.column.page.PageReadStore;
+import org.apache.parquet.schema.MessageType;
+import org.apache.parquet.schema.Type;
+import org.apache.spark.sql.types.ArrayType;
+import org.apache.spark.sql.types.DataType;
+import org.apache.spark.sql.types.MapType;
+import org.apache.spark.sql.types.StructType;
+
+import org.apache.comet.vector.CometVector;
+
+/**
+ * A column reader for a Parquet nested column, i.e., a struct, a list or a map. The Parquet
+ * columns under it are read by column readers, which are recreated for each row group, and its
+ * values are reconstructed natively from the values and levels read by them.
+ */
+public abstract class NestedColumnReader extends AbstractColumnReader {
+  private static final BufferAllocator ALLOCATOR = new RootAllocator();
+
+  /** The kinds of the native readers of nested fields, as passed to the native side. */
+  static final int COLUMN_READER = 0;
+  static final int STRUCT_READER = 1;
+  static final int LIST_READER = 2;
+  static final int MAP_READER = 3;
+
+  protected final int capacity;
+
+  /** The number of rows read in the current batch. */
+  protected int numRows;
+
+  private CometVector currentVector;
+
+  protected NestedColumnReader(
+      DataType type, int capacity, boolean useDecimal128, boolean useLegacyDateTimestamp) {
+    super(type, null, useDecimal128, useLegacyDateTimestamp);
+    this.capacity = capacity;
+    this.batchSize = capacity;
+  }
+
+  /**
+   * Sets the row group to read the Parquet columns under this column from. Expects to call
+   * `readBatch` after this.
+   */
+  public abstract void setRowGroup(PageReadStore rowGroup) throws IOException;
+
+  /**
+   * Returns the addresses of the native Arrow array and schema of the current batch of `numRows`
+   * rows.
+   */
+  protected abstract long[] nativeBatch(int numRows);
+
+  /** Wraps the Arrow vector of the current batch. */
+  protected abstract CometVector wrapVector(FieldVector vector);
+
+  @Override
+  public CometVector currentBatch() {
+    // Close the previous vector first to release struct memory allocated to import Arrow array &
+    // schema from native side, through the C data interface
+    if (currentVector != null) {
+      currentVector.close();
+    }
+
+    long[] addresses = nativeBatch(numRows);
+    try (ArrowArray array = ArrowArray.wrap(addresses[0]);
+        ArrowSchema schema = ArrowSchema.wrap(addresses[1])) {
+      FieldVector vector = Data.importVector(ALLOCATOR, array, schema, null);
+      currentVector = wrapVector(vector);
+      return currentVector;
+    }
+  }
+
+  @Override
+  protected void initNative() {
+    // The native reader is created for each row group, by `setRowGroup`
+  }
+
+  @Override
+  public void close() {
+    if (currentVector != null) {
+      currentVector.close();
+      currentVector = null;
+    }
+  }
+
+  /**
+   * Creates the reader of a nested field with the given Spark and Parquet types at `path`, or
+   * returns null if the field is primitive.
+   */
+  static NestedColumnReader create(
+      DataType type,
+      Type parquetType,
+      String[] path,
+      MessageType requestedSchema,
+      MessageType fileSchema,
+      int capacity,
+      boolean useDecimal128,
+      boolean useLegacyDateTimestamp) {
+    if (StructColumnReader.isStruct(parquetType) && type instanceof StructType) {
+      return new StructColumnReader(
+          (StructType) type,
+          parquetType.asGroupType(),
+          path,
+          requestedSchema,
+          fileSchema,
+          capacity,
+          useDecimal128,
+          useLegacyDateTimestamp);
+    }
+    if (ListColumnReader.isList(parquetType) && type instanceof ArrayType) {
+      return new ListColumnReader(
+          (ArrayType) type,
+          parquetType,
+          path,
+          requestedSchema,
+          fileSchema,
+          capacity,
+          useDecimal128,
+          useLegacyDateTimestamp);
+    }
+    if (MapColumnReader.isMap(parquetType) && type instanceof MapType) {
+      return new MapColumnReader(
+          (MapType) type,
+          parquetType.asGroupType(),
+          path,
+          requestedSchema,
+          fileSchema,
+          capacity,
+          useDecimal128,
+          useLegacyDateTimestamp);
+    }
+    if (!parquetType.isPrimitive() || parquetType.isRepetition(Type.Repetition.REPEATED)) {
+      throw new UnsupportedOperationException("Complex type is not supported");
+    }
+    return null;
+  }
+
+  /** Returns the kind of the native reader of the given reader of a nested field. */
+  static int nativeKind(AbstractColumnReader reader) {
+    if (reader instanceof StructColumnReader) {
+      return STRUCT_READER;
+    } else if (reader instanceof ListColumnReader) {
+      return LIST_READER;
+    } else if (reader instanceof MapColumnReader) {
+      return MAP_READER;
+    } else {
+      return COLUMN_READER;
+    }
+  }
+
+  /**
+   * Returns the Parquet column of a primitive field at `path` in a list or a map, which must be
+   * in the file: a missing field would be read as a constant column, which has a value per row
+   * rather than per element.
+   */
+  static ColumnDescriptor repeatedColumn(
+      String[] path, MessageType requestedSchema, MessageType fileSchema) {
+    if (!fileSchema.containsPath(path)) {
+      throw new UnsupportedOperationException("Schema evolution is not supported");
+    }
+    ColumnDescriptor column = requestedSchema.getColumnDescription(path);
+    if (!fileSchema.getColumnDescription(path).equals(column)) {
+      throw new UnsupportedOperationException("Schema evolution is not supported");
+    }
+    return column;
+  }
+
+  /** Creates a column reader of the given Parquet column in the given row group. */
+  protected ColumnReader newColumnReader(
+      DataType type, ColumnDescriptor column, PageReadStore rowGroup) throws IOException {
+    ColumnReader reader =
+        new ColumnReader(type, column, capacity, useDecimal128, useLegacyDateTimestamp);
+    reader.setPageReader(rowGroup.getPageReader(column));
+    return reader;
+  }
+
+  static String[] append(String[] path, String name) {
+    String[] result = Arrays.copyOf(path, path.length + 1);
+    result[path.length] = name;
+    return result;
+  }
+}
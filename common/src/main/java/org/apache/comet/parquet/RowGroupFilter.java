@@ -90,6 +90,9 @@ public class RowGroupFilter implements Visitor<List<BlockMetaData>> {
             filterPredicate.accept(
                 new BloomFilterReader(
                     block, reader.getFileMetaData().getFileDecryptor(), reader.getInputStream()));
+        if (drop) {
+          reader.addMetric("ParquetRowGroupsSkippedByBloomFilter", 1);
+        }
       }
 
       if (!drop) {
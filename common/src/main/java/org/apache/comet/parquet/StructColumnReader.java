@@ -0,0 +1,170 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one
+ * or more contributor license agreements.  See the NOTICE file
+ * distributed with this work for additional information
+ * regarding copyright ownership.  The ASF licenses this file
+ * to you under the Apache License, Version 2.0 (the
+ * "License"); you may not use this file except in compliance
+ * with the License.  You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+ * KIND, either express or implied.  See the License for the
+ * specific language governing permissions and limitations
+ * under the License.
+ */
+
+package org.apache.comet.parquet;
+
+import java.io.IOException;
+import java.util.Arrays;
+
+import org.apache.arrow.vector.FieldVector;
+import org.apache.parquet.column.ColumnDescriptor;
+import org.apache.parquet.column.page.PageReadStore;
+import org.apache.parquet.schema.GroupType;
+import org.apache.parquet.schema.MessageType;
+import org.apache.parquet.schema.Type;
+import org.apache.spark.sql.types.StructField;
+import org.apache.spark.sql.types.StructType;
+
+import org.apache.comet.vector.CometStructVector;
+import org.apache.comet.vector.CometVector;
+
+/**
+ * A column reader for a Parquet struct column, i.e., a group which is neither repeated nor a list
+ * or a map. Its primitive fields are read by column readers, and its nested groups by struct, list
+ * or map readers, from which the structs are reconstructed natively with the definition levels of
+ * the Parquet columns under it.
+ *
+ * <p>The fields missing in the file are read as constant columns, and if all of them are missing,
+ * all the structs are null. This is only supported for a struct which isn't in a list or a map.
+ */
+public class StructColumnReader extends NestedColumnReader {
+  private final StructType structType;
+
+  /** The readers of the fields, which are recreated for each row group if read from the file. */
+  private final AbstractColumnReader[] fieldReaders;
+
+  /** The Parquet columns of the primitive fields read from the file, or null. */
+  private final ColumnDescriptor[] fieldColumns;
+
+  /** The maximum definition level of the struct, under which it is null. */
+  private final int definitionLevel;
+
+  public StructColumnReader(
+      StructType type,
+      GroupType parquetType,
+      String[] path,
+      MessageType requestedSchema,
+      MessageType fileSchema,
+      int capacity,
+      boolean useDecimal128,
+      boolean useLegacyDateTimestamp) {
+    super(type, capacity, useDecimal128, useLegacyDateTimestamp);
+    this.structType = type;
+    this.definitionLevel = requestedSchema.getMaxDefinitionLevel(path);
+    boolean isRepeated = requestedSchema.getMaxRepetitionLevel(path) > 0;
+
+    StructField[] fields = type.fields();
+    fieldReaders = new AbstractColumnReader[fields.length];
+    fieldColumns = new ColumnDescriptor[fields.length];
+    for (int i = 0; i < fields.length; i++) {
+      Type fieldType = parquetType.getType(i);
+      String[] fieldPath = Arrays.copyOf(path, path.length + 1);
+      fieldPath[path.length] = fieldType.getName();
+
+      fieldReaders[i] =
+          NestedColumnReader.create(
+              fields[i].dataType(),
+              fieldType,
+              fieldPath,
+              requestedSchema,
+              fileSchema,
+              capacity,
+              useDecimal128,
+              useLegacyDateTimestamp);
+      if (fieldReaders[i] != null) {
+        continue;
+      }
+      if (fileSchema.containsPath(fieldPath)) {
+        ColumnDescriptor column = requestedSchema.getColumnDescription(fieldPath);
+        if (!fileSchema.getColumnDescription(fieldPath).equals(column)) {
+          throw new UnsupportedOperationException("Schema evolution is not supported");
+        }
+        fieldColumns[i] = column;
+      } else if (isRepeated) {
+        // A constant column has a value per row, rather than per struct in a list
+        throw new UnsupportedOperationException("Schema evolution is not supported");
+      } else {
+        fieldReaders[i] = new ConstantColumnReader(fields[i], capacity, useDecimal128);
+      }
+    }
+  }
+
+  /** Whether the given Parquet type is read as a struct. */
+  static boolean isStruct(Type type) {
+    return !type.isPrimitive()
+        && !type.isRepetition(Type.Repetition.REPEATED)
+        && type.getLogicalTypeAnnotation() == null;
+  }
+
+  @Override
+  public void setRowGroup(PageReadStore rowGroup) throws IOException {
+    StructField[] fields = structType.fields();
+    long[] handles = new long[fields.length];
+    int[] kinds = new int[fields.length];
+    String[] names = new String[fields.length];
+    for (int i = 0; i < fields.length; i++) {
+      if (fieldReaders[i] instanceof NestedColumnReader) {
+        ((NestedColumnReader) fieldReaders[i]).setRowGroup(rowGroup);
+      } else if (fieldColumns[i] != null) {
+        if (fieldReaders[i] != null) fieldReaders[i].close();
+        fieldReaders[i] = newColumnReader(fields[i].dataType(), fieldColumns[i], rowGroup);
+      }
+      handles[i] = fieldReaders[i].nativeHandle;
+      kinds[i] = NestedColumnReader.nativeKind(fieldReaders[i]);
+      names[i] = fields[i].name();
+    }
+
+    closeNative();
+    nativeHandle = Native.initStructReader(names, handles, kinds, definitionLevel);
+  }
+
+  @Override
+  public void readBatch(int total) {
+    for (AbstractColumnReader reader : fieldReaders) {
+      reader.readBatch(total);
+    }
+    this.numRows = total;
+  }
+
+  @Override
+  protected long[] nativeBatch(int numRows) {
+    return Native.currentStructBatch(nativeHandle, numRows);
+  }
+
+  @Override
+  protected CometVector wrapVector(FieldVector vector) {
+    return new CometStructVector(vector, useDecimal128);
+  }
+
+  @Override
+  public void close() {
+    super.close();
+    for (AbstractColumnReader reader : fieldReaders) {
+      if (reader != null) reader.close();
+    }
+    closeNative();
+  }
+
+  private void closeNative() {
+    if (nativeHandle != 0) {
+      Native.closeStructReader(nativeHandle);
+      nativeHandle = 0;
+    }
+  }
+}
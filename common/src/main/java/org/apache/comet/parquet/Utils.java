@@ -66,13 +66,16 @@ public class Utils {
    * @param useLegacyDateTimestampOrNTZ whether to read dates/timestamps that were written in the
    *     legacy hybrid Julian + Gregorian calendar as it is. If false, throw exceptions instead. If
    *     the spark type is TimestampNTZ, this should be true.
+   * @param validateUtf8 whether to check that the values of string columns are valid UTF-8. If
+   *     false, the values are read as they are.
    */
   public static long initColumnReader(
       ColumnDescriptor descriptor,
       DataType readType,
       int batchSize,
       boolean useDecimal128,
-      boolean useLegacyDateTimestampOrNTZ) {
+      boolean useLegacyDateTimestampOrNTZ,
+      boolean validateUtf8) {
     PrimitiveType primitiveType = descriptor.getPrimitiveType();
     int primitiveTypeId = getPhysicalTypeId(primitiveType.getPrimitiveTypeName());
     LogicalTypeAnnotation annotation = primitiveType.getLogicalTypeAnnotation();
@@ -131,7 +134,8 @@ public class Utils {
         isAdjustedUtc,
         batchSize,
         useDecimal128,
-        useLegacyDateTimestampOrNTZ);
+        useLegacyDateTimestampOrNTZ,
+        validateUtf8);
   }
 
   static class TypePromotionInfo {
@@ -161,12 +161,361 @@ object CometConf {
       .booleanConf
       .createWithDefault(false)
 
-  val COMET_EXEC_SHUFFLE_CODEC: ConfigEntry[String] = conf(
+  val COMET_EXEC_BROADCAST_NATIVE_SERIALIZATION_ENABLED: ConfigEntry[Boolean] =
+    conf(s"$COMET_EXEC_CONFIG_PREFIX.broadcast.nativeSerialization.enabled")
+      .doc(
+        "Whether to serialize the batches broadcasted by Comet broadcast exchange into " +
+          "compressed Arrow IPC bytes natively, and deserialize them natively on executors. " +
+          "Otherwise, they are serialized by JVM with the Spark compression codec. By default, " +
+          "this config is true.")
+      .booleanConf
+      .createWithDefault(true)
+
+  val COMET_EXEC_BROADCAST_MMAP_ENABLED: ConfigEntry[Boolean] =
+    conf(s"$COMET_EXEC_CONFIG_PREFIX.broadcast.mmap.enabled")
+      .doc(
+        "Whether to decompress large broadcast relations once on each executor into a local " +
+          "file, which is memory-mapped and shared by all the tasks of the executor without " +
+          "copying. This only applies when " +
+          s"${COMET_EXEC_BROADCAST_NATIVE_SERIALIZATION_ENABLED.key} is true. By default, this " +
+          "config is false.")
+      .booleanConf
+      .createWithDefault(false)
+
+  val COMET_EXEC_BROADCAST_MMAP_THRESHOLD: ConfigEntry[Long] =
+    conf(s"$COMET_EXEC_CONFIG_PREFIX.broadcast.mmap.threshold")
+      .doc(
+        "The minimum serialized size of a broadcast relation to memory-map it on executors " +
+          s"when ${COMET_EXEC_BROADCAST_MMAP_ENABLED.key} is true. Default value is 64MB.")
+      .bytesConf(ByteUnit.BYTE)
+      .checkValue(_ >= 0, "The broadcast mmap threshold must not be negative")
+      .createWithDefault(64L * 1024 * 1024)
+
+  val COMET_EXEC_BROADCAST_SHARED_HASH_TABLE_ENABLED: ConfigEntry[Boolean] =
+    conf(s"$COMET_EXEC_CONFIG_PREFIX.broadcast.sharedHashTable.enabled")
+      .doc(
+        "Whether the tasks of an executor joining the same memory-mapped broadcast relation " +
+          "share the hash table built from it, instead of building it in every task. This " +
+          "only applies to inner and right outer joins building the left side, when " +
+          s"${COMET_EXEC_BROADCAST_MMAP_ENABLED.key} is true. The shared hash table is not " +
+          "accounted in the task memory. By default, this config is false.")
+      .booleanConf
+      .createWithDefault(false)
+
+  val COMET_EXEC_REUSED_EXCHANGE_SHARED_ENABLED: ConfigEntry[Boolean] =
+    conf(s"$COMET_EXEC_CONFIG_PREFIX.reusedExchange.shared.enabled")
+      .doc(
+        "Whether a native plan reading the same exchange several times, e.g., a reused " +
+          "shuffle on both sides of a self-join, reads it only once and shares its batches " +
+          "between the readers. The batches are kept in memory until all the readers read " +
+          "them, which is not accounted in the task memory. By default, this config is true.")
+      .booleanConf
+      .createWithDefault(true)
+
+  val COMET_EXEC_SHUFFLE_CODEC: OptionalConfigEntry[String] = conf(
     s"$COMET_EXEC_CONFIG_PREFIX.shuffle.codec")
     .doc(
-      "The codec of Comet native shuffle used to compress shuffle data. Only zstd is supported.")
+      "The codec of Comet native shuffle used to compress shuffle data. Supported codecs are " +
+        "lz4, snappy and zstd, which can be read by Spark reducers. If this is not specified, " +
+        "spark.io.compression.codec is used, or zstd if it is not supported. The compression " +
+        "level of zstd is spark.io.compression.zstd.level.")
     .stringConf
-    .createWithDefault("zstd")
+    .createOptional
+
+  val COMET_EXEC_SHUFFLE_NATIVE_READER_ENABLED: ConfigEntry[Boolean] =
+    conf(s"$COMET_EXEC_CONFIG_PREFIX.shuffle.nativeReader.enabled")
+      .doc(
+        "Whether the reducers of Comet native shuffle decompress and decode the fetched " +
+          "blocks natively into Arrow arrays, which are exported to the JVM without being " +
+          "copied, instead of deserializing them on the JVM. By default, this config is false.")
+      .booleanConf
+      .createWithDefault(false)
+
+  val COMET_EXEC_SHUFFLE_COALESCE_ENABLED: ConfigEntry[Boolean] =
+    conf(s"$COMET_EXEC_CONFIG_PREFIX.shuffle.coalesce.enabled")
+      .doc(
+        "Whether the native shuffle reader coalesces the small batches decoded from the " +
+          "fetched blocks, usually one per map, into larger batches before the operators " +
+          "consuming them, e.g., joins and aggregates. This only applies when " +
+          s"${COMET_EXEC_SHUFFLE_NATIVE_READER_ENABLED.key} is true. By default, this config " +
+          "is true.")
+      .booleanConf
+      .createWithDefault(true)
+
+  val COMET_EXEC_SHUFFLE_COALESCE_TARGET_ROWS: OptionalConfigEntry[Int] =
+    conf(s"$COMET_EXEC_CONFIG_PREFIX.shuffle.coalesce.targetRows")
+      .doc(
+        "The number of rows which the native shuffle reader coalesces the batches up to, " +
+          s"when ${COMET_EXEC_SHUFFLE_COALESCE_ENABLED.key} is true. If this is not specified, " +
+          "it is 'spark.comet.batchSize'.")
+      .intConf
+      .checkValue(_ > 0, "The target rows of shuffle coalescing must be positive.")
+      .createOptional
+
+  val COMET_EXEC_SHUFFLE_COALESCE_TARGET_BYTES: ConfigEntry[Long] =
+    conf(s"$COMET_EXEC_CONFIG_PREFIX.shuffle.coalesce.targetBytes")
+      .doc(
+        "The size in bytes which the native shuffle reader coalesces the batches up to, " +
+          s"when ${COMET_EXEC_SHUFFLE_COALESCE_ENABLED.key} is true. A coalesced batch is " +
+          "complete once it reaches either the target rows or the target bytes. Default value " +
+          "is 16MB.")
+      .bytesConf(ByteUnit.BYTE)
+      .checkValue(_ > 0, "The target bytes of shuffle coalescing must be positive.")
+      .createWithDefault(16L * 1024 * 1024)
+
+  val COMET_EXEC_SHUFFLE_COMBINE_ENABLED: ConfigEntry[Boolean] =
+    conf(s"$COMET_EXEC_CONFIG_PREFIX.shuffle.combine.enabled")
+      .doc(
+        "Whether Comet native shuffle combines the rows of the same grouping keys written by " +
+          "a native partial aggregation, like a map-side combiner. The shuffle writer merges " +
+          "the partial aggregation states of the rows in a bounded hash table before writing " +
+          "them, which reduces the shuffled data when the keys are duplicated across the " +
+          "batches of the partial aggregation, e.g., when it skips aggregating or spills. By " +
+          "default, this config is false.")
+      .booleanConf
+      .createWithDefault(false)
+
+  val COMET_EXEC_SHUFFLE_COMBINE_MAX_GROUPS: ConfigEntry[Int] =
+    conf(s"$COMET_EXEC_CONFIG_PREFIX.shuffle.combine.maxGroups")
+      .doc(
+        "The maximum number of grouping keys held by the combiner of a native shuffle writer, " +
+          s"when ${COMET_EXEC_SHUFFLE_COMBINE_ENABLED.key} is true. Once the combiner has as " +
+          "many keys, the combined rows are written and the combiner starts over.")
+      .intConf
+      .checkValue(_ > 0, "The maximum number of groups of shuffle combining must be positive.")
+      .createWithDefault(65536)
+
+  val COMET_EXEC_SHUFFLE_SORT_BASED_ENABLED: ConfigEntry[Boolean] =
+    conf(s"$COMET_EXEC_CONFIG_PREFIX.shuffle.sortBased.enabled")
+      .doc(
+        "Whether Comet native shuffle buffers the input rows and sorts them by partition id " +
+          "when spilling, like Spark's UnsafeShuffleWriter, instead of copying the rows into " +
+          "per-partition buffers. This keeps the memory usage of shuffles with many " +
+          "partitions within the memory pool. By default, this config is false.")
+      .booleanConf
+      .createWithDefault(false)
+
+  val COMET_EXEC_SHUFFLE_UNIFIED_MEMORY_ENABLED: ConfigEntry[Boolean] =
+    conf(s"$COMET_EXEC_CONFIG_PREFIX.shuffle.unifiedMemory.enabled")
+      .doc(
+        "Whether Comet native shuffle acquires the memory of its buffers from Spark's task " +
+          "memory manager in the memory mode of the task, so that native shuffle and JVM " +
+          "operators don't overcommit the executor memory. Native shuffle spills its buffers " +
+          "if Spark cannot grant memory to it, or asks it to free memory for other consumers. " +
+          "By default, this config is true.")
+      .booleanConf
+      .createWithDefault(true)
+
+  val COMET_EXEC_IO_PARALLELISM: OptionalConfigEntry[Int] =
+    conf(s"$COMET_EXEC_CONFIG_PREFIX.io.parallelism")
+      .doc(
+        "The number of threads of the native IO runtime shared by all the native plans of an " +
+          "executor, used for the disk IO of native shuffle writers and native file scans. It " +
+          "is separate from the compute thread pool, so that long-running computation doesn't " +
+          "starve IO and vice versa. If this is not specified, it is the number of CPU cores.")
+      .intConf
+      .checkValue(_ > 0, "The number of IO threads must be positive.")
+      .createOptional
+
+  val COMET_EXEC_COMPUTE_THREADS: OptionalConfigEntry[Int] =
+    conf(s"$COMET_EXEC_CONFIG_PREFIX.compute.threads")
+      .doc(
+        "The number of threads of the compute thread pool shared by all the native plans of " +
+          "an executor. If this is not specified, it is the number of CPU cores.")
+      .intConf
+      .checkValue(_ > 0, "The number of compute threads must be positive.")
+      .createOptional
+
+  val COMET_EXEC_COMPUTE_PIN_THREADS: ConfigEntry[Boolean] =
+    conf(s"$COMET_EXEC_CONFIG_PREFIX.compute.pinThreads")
+      .doc(
+        "Whether to pin the threads of the native compute thread pool to CPU cores. This can " +
+          "reduce context switches and improve cache locality when the executor owns the " +
+          "cores of the host. By default, this config is false.")
+      .booleanConf
+      .createWithDefault(false)
+
+  val COMET_EXEC_SPILL_DISK_LIMIT: OptionalConfigEntry[Long] =
+    conf(s"$COMET_EXEC_CONFIG_PREFIX.spill.diskLimit")
+      .doc(
+        "The maximum disk space that the spill files of the native shuffle writers of a " +
+          "native plan can use. A native shuffle writer fails once its spills would exceed " +
+          "the limit. The spills of other native operators, e.g., sorts and aggregations, " +
+          "aren't limited. Native spill files are created in the local directories of the " +
+          "executor, i.e., 'spark.local.dir' or the directories of the cluster manager. If " +
+          "this is not specified, the disk space is not limited.")
+      .bytesConf(ByteUnit.BYTE)
+      .checkValue(_ > 0, "The spill disk limit must be positive.")
+      .createOptional
+
+  val COMET_EXEC_RESOURCE_PROFILE_NAME: OptionalConfigEntry[String] =
+    conf(s"$COMET_EXEC_CONFIG_PREFIX.resourceProfile.name")
+      .doc(
+        "The name of the resource profile of the native plans of a query. The native plans " +
+          "of all the queries with the same profile on an executor share the caps of " +
+          "'spark.comet.exec.resourceProfile.maxIoRequests' and " +
+          "'spark.comet.exec.resourceProfile.decodeThreads', so that heavy batch queries can " +
+          "be throttled while interactive queries stay responsive. The caps of a profile are " +
+          "fixed by the first plan of the profile on the executor. If this is not specified " +
+          "while a cap is set, the profile is 'default'.")
+      .stringConf
+      .createOptional
+
+  val COMET_EXEC_RESOURCE_PROFILE_MAX_IO_REQUESTS: OptionalConfigEntry[Int] =
+    conf(s"$COMET_EXEC_CONFIG_PREFIX.resourceProfile.maxIoRequests")
+      .doc(
+        "The maximum number of concurrent IO requests, such as shuffle spills, of the native " +
+          "plans of the resource profile on an executor. If this is not specified, the IO " +
+          "requests are not limited.")
+      .intConf
+      .checkValue(_ > 0, "The maximum number of IO requests must be positive.")
+      .createOptional
+
+  val COMET_EXEC_RESOURCE_PROFILE_DECODE_THREADS: OptionalConfigEntry[Int] =
+    conf(s"$COMET_EXEC_CONFIG_PREFIX.resourceProfile.decodeThreads")
+      .doc(
+        "The maximum number of task threads executing the native plans of the resource " +
+          "profile at the same time on an executor. The other tasks of the profile wait for " +
+          "their turn. If this is not specified, the threads are not limited.")
+      .intConf
+      .checkValue(_ > 0, "The number of decode threads must be positive.")
+      .createOptional
+
+  val COMET_EXEC_RESOURCE_PROFILE_MEMORY_FRACTION: OptionalConfigEntry[Double] =
+    conf(s"$COMET_EXEC_CONFIG_PREFIX.resourceProfile.memoryFraction")
+      .doc(
+        "Overrides 'spark.comet.exec.memoryFraction' for the native plans of the resource " +
+          "profile. If this is not specified, 'spark.comet.exec.memoryFraction' is used.")
+      .doubleConf
+      .checkValue(f => f > 0 && f <= 1, "The memory fraction must be in (0, 1].")
+      .createOptional
+
+  val COMET_EXEC_OFFLOAD_PROVIDER: OptionalConfigEntry[String] =
+    conf(s"$COMET_EXEC_CONFIG_PREFIX.offload.provider")
+      .doc(
+        "The name of the operator provider to offload native operators to, e.g., one backed " +
+          "by GPU kernels. The provider must be registered by a library linked with the Comet " +
+          "native library. Comet keeps the native operators which the provider doesn't " +
+          "offload, or all of them if the provider is not registered. This is experimental.")
+      .stringConf
+      .createOptional
+
+  val COMET_EXEC_OFFLOAD_OPERATORS: OptionalConfigEntry[String] =
+    conf(s"$COMET_EXEC_CONFIG_PREFIX.offload.operators")
+      .doc(
+        "Comma separated names of the native operators to offload to " +
+          "'spark.comet.exec.offload.provider', e.g., 'ProjectionExec,AggregateExec'. If this " +
+          "is not specified, all the native operators are offered to the provider.")
+      .stringConf
+      .createOptional
+
+  val COMET_EXEC_PARTIAL_AGG_SKIP_ENABLED: ConfigEntry[Boolean] =
+    conf(s"$COMET_EXEC_CONFIG_PREFIX.partialAgg.skip.enabled")
+      .doc(
+        "Whether to skip native partial aggregation when the grouping keys turn out to have " +
+          "high cardinality, like Spark skips partial aggregation. In that case, the partial " +
+          "aggregation aggregates each input batch on its own instead of building a hash table " +
+          "for all the input. By default, this config is false.")
+      .booleanConf
+      .createWithDefault(false)
+
+  val COMET_EXEC_PARTIAL_AGG_SKIP_PROBE_ROWS: ConfigEntry[Int] =
+    conf(s"$COMET_EXEC_CONFIG_PREFIX.partialAgg.skip.probeRows")
+      .doc(
+        "The number of input rows of a native partial aggregation to probe the cardinality of " +
+          "the grouping keys, when 'spark.comet.exec.partialAgg.skip.enabled' is true.")
+      .intConf
+      .checkValue(_ > 0, "The number of probe rows must be positive")
+      .createWithDefault(100000)
+
+  val COMET_EXEC_PARTIAL_AGG_SKIP_RATIO_THRESHOLD: ConfigEntry[Double] =
+    conf(s"$COMET_EXEC_CONFIG_PREFIX.partialAgg.skip.ratioThreshold")
+      .doc(
+        "The minimum ratio of distinct grouping keys to probed input rows to skip native " +
+          "partial aggregation, when 'spark.comet.exec.partialAgg.skip.enabled' is true.")
+      .doubleConf
+      .checkValue(
+        ratio => ratio > 0.0 && ratio <= 1.0,
+        "The ratio threshold must be in (0, 1]")
+      .createWithDefault(0.8)
+
+  val COMET_EXEC_DICTIONARY_ENCODING_ENABLED: ConfigEntry[Boolean] =
+    conf(s"$COMET_EXEC_CONFIG_PREFIX.dictionaryEncoding.enabled")
+      .doc(
+        "Whether to encode the low-cardinality string columns read by native shuffle writers " +
+          "and the build side of native hash joins as dictionaries. The cardinality of a " +
+          "column is estimated from the first rows of its input, and a dictionary encoded " +
+          "column is hashed, copied and written once per distinct value. By default, this " +
+          "config is false.")
+      .booleanConf
+      .createWithDefault(false)
+
+  val COMET_EXEC_DICTIONARY_ENCODING_SAMPLE_ROWS: ConfigEntry[Int] =
+    conf(s"$COMET_EXEC_CONFIG_PREFIX.dictionaryEncoding.sampleRows")
+      .doc(
+        "The number of input rows sampled to estimate the cardinality of a string column, " +
+          "when 'spark.comet.exec.dictionaryEncoding.enabled' is true.")
+      .intConf
+      .checkValue(_ > 0, "The number of sample rows must be positive")
+      .createWithDefault(1024)
+
+  val COMET_EXEC_DICTIONARY_ENCODING_MAX_RATIO: ConfigEntry[Double] =
+    conf(s"$COMET_EXEC_CONFIG_PREFIX.dictionaryEncoding.maxRatio")
+      .doc(
+        "The maximum ratio of distinct values to sampled rows to encode a string column as a " +
+          "dictionary, when 'spark.comet.exec.dictionaryEncoding.enabled' is true.")
+      .doubleConf
+      .checkValue(ratio => ratio > 0.0 && ratio <= 1.0, "The maximum ratio must be in (0, 1]")
+      .createWithDefault(0.1)
+
+  val COMET_EXEC_SEQUENTIAL_FLOAT_AGG_ENABLED: ConfigEntry[Boolean] =
+    conf(s"$COMET_EXEC_CONFIG_PREFIX.aggregate.sequentialFloatSum.enabled")
+      .doc(
+        "Whether native sum and average of floating-point values add the values one by one in " +
+          "the order of the input rows like Spark, instead of with vectorized kernels which " +
+          "reorder the additions. The results are then stable across retries for the same " +
+          "input order, and the same as Spark's if the rows are aggregated in the same order, " +
+          "at some cost of performance. By default, this config is false.")
+      .booleanConf
+      .createWithDefault(false)
+
+  val COMET_METRICS_PROMETHEUS_HOST: ConfigEntry[String] =
+    conf("spark.comet.metrics.prometheus.host")
+      .doc(
+        "The address to bind the executor-level native metrics exporter to. By default, the " +
+          "metrics are only served on the loopback interface. Set this to `0.0.0.0` to serve " +
+          "them on all the interfaces.")
+      .stringConf
+      .createWithDefault("127.0.0.1")
+
+  val COMET_METRICS_PROMETHEUS_PORT: OptionalConfigEntry[Int] =
+    conf("spark.comet.metrics.prometheus.port")
+      .doc(
+        "The port to serve executor-level native metrics in the Prometheus text format on, at " +
+          "path `/metrics`. This requires the Comet native library to be built with the " +
+          "`prometheus` feature. If this is not specified, the metrics are not served.")
+      .intConf
+      .checkValue(p => p > 0 && p < 65536, "The port must be in (0, 65536).")
+      .createOptional
+
+  val COMET_SUMMARY_EVENT_LOG_ENABLED: ConfigEntry[Boolean] =
+    conf("spark.comet.summary.eventLog.enabled")
+      .doc(
+        "Whether to post the execution summary of each query produced by " +
+          "`org.apache.spark.sql.comet.CometQuerySummaryListener` to the Spark listener bus, " +
+          "so that it is written to the Spark event log. By default, this config is true.")
+      .booleanConf
+      .createWithDefault(true)
+
+  val COMET_SUMMARY_RETAINED: ConfigEntry[Int] =
+    conf("spark.comet.summary.retained")
+      .doc(
+        "The number of the latest query execution summaries kept on the driver by " +
+          "`org.apache.spark.sql.comet.CometQuerySummaryListener`. By default, this config " +
+          "is 100.")
+      .intConf
+      .checkValue(n => n >= 0, "The number of retained summaries must not be negative.")
+      .createWithDefault(100)
 
   val COMET_COLUMNAR_SHUFFLE_ASYNC_ENABLED: ConfigEntry[Boolean] = conf(
     "spark.comet.columnar.shuffle.async.enabled")
@@ -267,6 +616,36 @@ object CometConf {
       .booleanConf
       .createWithDefault(false)
 
+  val COMET_DEBUG_VALIDATE_BATCHES: ConfigEntry[Boolean] =
+    conf("spark.comet.debug.validateBatches")
+      .doc(
+        "Whether to validate Arrow invariants (e.g., offsets, null counts, UTF-8 validity " +
+          "and schema) of the batches produced by every native operator. On violation, the " +
+          "query fails with an error naming the operator that produced the invalid batch. " +
+          "This is expensive and should only be enabled for debugging purpose. By default, " +
+          "this config is false.")
+      .booleanConf
+      .createWithDefault(false)
+
+  val COMET_DEBUG_TAP_OPERATOR: OptionalConfigEntry[String] =
+    conf("spark.comet.debug.tap.operator")
+      .doc(
+        "The name of a native operator, e.g., `SortExec`, whose output batches are mirrored to " +
+          "Arrow IPC stream files for inspecting intermediate results of native plans. The " +
+          "files are written to `spark.comet.debug.tap.dir` on executors. This should only be " +
+          "enabled for debugging purpose.")
+      .stringConf
+      .createOptional
+
+  val COMET_DEBUG_TAP_DIR: OptionalConfigEntry[String] =
+    conf("spark.comet.debug.tap.dir")
+      .doc(
+        "The local directory on executors to write the files of " +
+          "`spark.comet.debug.tap.operator` to. If this is not specified, the temporary " +
+          "directory of the executor is used.")
+      .stringConf
+      .createOptional
+
   val COMET_BATCH_SIZE: ConfigEntry[Int] = conf("spark.comet.batchSize")
     .doc("The columnar batch size, i.e., the maximum number of rows that a batch can contain.")
     .intConf
@@ -287,12 +666,61 @@ object CometConf {
     .booleanConf
     .createWithDefault(false)
 
+  val COMET_PARQUET_FOOTER_CACHE_ENABLED: ConfigEntry[Boolean] =
+    conf("spark.comet.parquet.footerCache.enabled")
+      .doc(
+        "Whether to cache the parsed Parquet footers in each executor, keyed by the file path, " +
+          "modification time and length, so that repeated scans of the same files skip " +
+          "fetching and parsing the footers. By default is disabled.")
+      .booleanConf
+      .createWithDefault(false)
+
+  val COMET_PARQUET_FOOTER_CACHE_MAX_ENTRIES: ConfigEntry[Int] =
+    conf("spark.comet.parquet.footerCache.maxEntries")
+      .doc(
+        "The maximum number of Parquet footers cached in each executor. Effective if " +
+          s"${COMET_PARQUET_FOOTER_CACHE_ENABLED.key} is enabled. By default it is 1000.")
+      .intConf
+      .checkValue(v => v > 0, "The maximum number of cached footers must be positive.")
+      .createWithDefault(1000)
+
+  val COMET_PARQUET_UTF8_VALIDATION_ENABLED: ConfigEntry[Boolean] =
+    conf("spark.comet.parquet.utf8Validation.enabled")
+      .doc(
+        "Whether to check that the values of Parquet string columns are valid UTF-8 when " +
+          "reading them, and fail the scan on invalid values. Disabling it speeds up scans of " +
+          "string-heavy Parquet files from trusted sources, whose values are then read as they " +
+          "are like Spark. By default is enabled.")
+      .booleanConf
+      .createWithDefault(true)
+
   val COMET_SCAN_PREFETCH_ENABLED: ConfigEntry[Boolean] =
     conf("spark.comet.scan.preFetch.enabled")
       .doc("Whether to enable pre-fetching feature of CometScan. By default is disabled.")
       .booleanConf
       .createWithDefault(false)
 
+  val COMET_SCAN_LIMIT_PUSHDOWN_ENABLED: ConfigEntry[Boolean] =
+    conf("spark.comet.scan.limitPushdown.enabled")
+      .doc(
+        "Whether to push the limits of partitions, e.g., of queries like `SELECT * FROM t " +
+          "LIMIT 100`, into CometScan, so that it stops reading the files once it produces " +
+          "enough rows. By default is enabled.")
+      .booleanConf
+      .createWithDefault(true)
+
+  val COMET_SCAN_NATIVE_FORMATS: ConfigEntry[Seq[String]] =
+    conf("spark.comet.scan.nativeFormats")
+      .doc(
+        "A comma-separated list of the file formats, among `csv` and `json`, whose Spark file " +
+          "scans are read by the native file scan of Comet, so that the operators on top of " +
+          "them are executed natively. Scans of partitioned or bucketed tables, and scans with " +
+          "unsupported types or options, are not converted. Experimental. By default, no " +
+          "format is read natively.")
+      .stringConf
+      .toSequence
+      .createWithDefault(Seq.empty)
+
   val COMET_SCAN_PREFETCH_THREAD_NUM: ConfigEntry[Int] =
     conf("spark.comet.scan.preFetch.threadNum")
       .doc(
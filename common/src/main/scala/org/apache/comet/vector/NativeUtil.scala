@@ -21,7 +21,7 @@ package org.apache.comet.vector
 
 import scala.collection.mutable
 
-import org.apache.arrow.c.{ArrowArray, ArrowImporter, ArrowSchema, CDataDictionaryProvider, Data}
+import org.apache.arrow.c.{ArrowArray, ArrowImporter, ArrowSchema, BaseStruct, CDataDictionaryProvider, Data}
 import org.apache.arrow.memory.RootAllocator
 import org.apache.arrow.vector.VectorSchemaRoot
 import org.apache.arrow.vector.dictionary.DictionaryProvider
@@ -47,7 +47,31 @@ class NativeUtil {
    *   a list containing number of rows + pairs of memory addresses in the format of (address of
    *   Arrow array, address of Arrow schema)
    */
-  def exportBatch(batch: ColumnarBatch): Array[Long] = {
+  def exportBatch(batch: ColumnarBatch): Array[Long] =
+    exportBatch(batch, mutable.ArrayBuffer.empty[BaseStruct])
+
+  /**
+   * Exports a Comet `ColumnarBatch` like `exportBatch`, and calls `func` with the exported
+   * addresses, which is expected to move the exported Arrow arrays and schemas, e.g., by
+   * importing them natively. The Arrow arrays and schemas are released and closed once `func`
+   * returns or fails.
+   */
+  def withExportedBatch[T](batch: ColumnarBatch)(func: Array[Long] => T): T = {
+    val structs = mutable.ArrayBuffer.empty[BaseStruct]
+    try {
+      func(exportBatch(batch, structs))
+    } finally {
+      structs.foreach { struct =>
+        struct.release()
+        struct.close()
+      }
+    }
+  }
+
+  /** Exports the given batch, and adds the allocated Arrow arrays and schemas to `structs`. */
+  private def exportBatch(
+      batch: ColumnarBatch,
+      structs: mutable.ArrayBuffer[BaseStruct]): Array[Long] = {
     val exportedVectors = mutable.ArrayBuffer.empty[Long]
     exportedVectors += batch.numRows()
 
@@ -64,6 +88,8 @@ class NativeUtil {
 
           val arrowSchema = ArrowSchema.allocateNew(allocator)
           val arrowArray = ArrowArray.allocateNew(allocator)
+          structs += arrowSchema
+          structs += arrowArray
           Data.exportVector(
             allocator,
             getFieldVector(valueVector),
@@ -112,6 +138,47 @@ class NativeUtil {
     arrayVectors.toSeq
   }
 
+  /**
+   * Allocates Arrow arrays and schemas for a batch of `numCols` columns, lets the native side
+   * move the columns into them with `func`, and imports them as a Comet columnar batch.
+   *
+   * @param numCols
+   *   the number of columns of the batch
+   * @param func
+   *   the function which takes the addresses of the Arrow arrays and schemas, and returns the
+   *   number of rows
+   * @return
+   *   the imported batch
+   */
+  def importBatch(numCols: Int, func: (Array[Long], Array[Long]) => Long): ColumnarBatch = {
+    val arrowArrays = (0 until numCols).map(_ => ArrowArray.allocateNew(allocator))
+    val arrowSchemas = (0 until numCols).map(_ => ArrowSchema.allocateNew(allocator))
+
+    val numRows =
+      try {
+        func(
+          arrowArrays.map(_.memoryAddress()).toArray,
+          arrowSchemas.map(_.memoryAddress()).toArray)
+      } catch {
+        case e: Throwable =>
+          arrowArrays.foreach(_.close())
+          arrowSchemas.foreach(_.close())
+          throw e
+      }
+
+    val arrayVectors = arrowArrays.zip(arrowSchemas).map { case (arrowArray, arrowSchema) =>
+      val vector = CometVector.getVector(
+        importer.importVector(arrowArray, arrowSchema, dictionaryProvider),
+        true,
+        dictionaryProvider)
+
+      arrowArray.close()
+      arrowSchema.close()
+      vector
+    }
+    new ColumnarBatch(arrayVectors.toArray, numRows.toInt)
+  }
+
   /**
    * Takes zero-copy slices of the input batch with given start index and maximum number of rows.
    *
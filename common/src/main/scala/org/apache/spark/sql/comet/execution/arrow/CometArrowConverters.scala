@@ -19,8 +19,13 @@
 
 package org.apache.spark.sql.comet.execution.arrow
 
+import java.io.ByteArrayOutputStream
+import java.nio.channels.Channels
+
 import org.apache.arrow.memory.{BufferAllocator, RootAllocator}
 import org.apache.arrow.vector.VectorSchemaRoot
+import org.apache.arrow.vector.dictionary.DictionaryProvider
+import org.apache.arrow.vector.ipc.ArrowStreamWriter
 import org.apache.spark.TaskContext
 import org.apache.spark.internal.Logging
 import org.apache.spark.sql.catalyst.InternalRow
@@ -128,4 +133,34 @@ object CometArrowConverters extends Logging {
       context: TaskContext): Iterator[ColumnarBatch] = {
     new ArrowBatchIterator(rowIter, schema, maxRecordsPerBatch, timeZoneId, context)
   }
+
+  /**
+   * Encodes the rows as an Arrow IPC stream of a single batch, e.g., to ship a local relation
+   * within the native plan.
+   */
+  def toArrowStream(
+      rows: Seq[InternalRow],
+      schema: StructType,
+      timeZoneId: String): Array[Byte] = {
+    val allocator = rootAllocator.newChildAllocator("toArrowStream", 0, Long.MaxValue)
+    val root = VectorSchemaRoot.create(Utils.toArrowSchema(schema, timeZoneId), allocator)
+    try {
+      val arrowWriter = ArrowWriter.create(root)
+      rows.foreach(arrowWriter.write)
+      arrowWriter.finish()
+
+      val out = new ByteArrayOutputStream()
+      val writer = new ArrowStreamWriter(
+        root,
+        new DictionaryProvider.MapDictionaryProvider(),
+        Channels.newChannel(out))
+      writer.start()
+      writer.writeBatch()
+      writer.end()
+      out.toByteArray
+    } finally {
+      root.close()
+      allocator.close()
+    }
+  }
 }
@@ -36,11 +36,15 @@ import org.apache.spark.storage.BlockId
 import org.apache.spark.storage.BlockManager
 import org.apache.spark.storage.BlockManagerId
 import org.apache.spark.storage.ShuffleBlockFetcherIterator
+import org.apache.spark.sql.vectorized.ColumnarBatch
 import org.apache.spark.util.CompletionIterator
 
 /**
  * Shuffle reader that reads data from the block manager. It reads Arrow-serialized data (IPC
  * format) and returns an iterator of ColumnarBatch.
+ *
+ * If `nativeDecoder` is set, it decodes the fetched streams of the blocks into batches natively
+ * instead of deserializing them on the JVM, which may coalesce the batches of different blocks.
  */
 class CometBlockStoreShuffleReader[K, C](
     handle: BaseShuffleHandle[K, _, C],
@@ -50,7 +54,8 @@ class CometBlockStoreShuffleReader[K, C](
     serializerManager: SerializerManager = SparkEnv.get.serializerManager,
     blockManager: BlockManager = SparkEnv.get.blockManager,
     mapOutputTracker: MapOutputTracker = SparkEnv.get.mapOutputTracker,
-    shouldBatchFetch: Boolean = false)
+    shouldBatchFetch: Boolean = false,
+    nativeDecoder: Option[Iterator[InputStream] => Iterator[ColumnarBatch]] = None)
     extends ShuffleReader[K, C]
     with Logging {
 
@@ -64,14 +69,9 @@ class CometBlockStoreShuffleReader[K, C](
       mapOutputTracker,
       // To tackle Scala issue between Seq and scala.collection.Seq
       blocksByAddress.map(pair => (pair._1, pair._2.toSeq)),
-      (_, inputStream) => {
-        if (dep.shuffleType == CometColumnarShuffle) {
-          // Only columnar shuffle supports encryption
-          serializerManager.wrapForEncryption(inputStream)
-        } else {
-          inputStream
-        }
-      },
+      // Both columnar and native shuffle encrypt the blocks like Spark if IO encryption is
+      // enabled, which are decrypted before they are deserialized or decoded natively
+      (_, inputStream) => serializerManager.wrapForEncryption(inputStream),
       // Note: we use getSizeAsMb when no suffix is provided for backwards compatibility
       SparkEnv.get.conf.get(config.REDUCER_MAX_SIZE_IN_FLIGHT) * 1024 * 1024,
       SparkEnv.get.conf.get(config.REDUCER_MAX_REQS_IN_FLIGHT),
@@ -88,30 +88,34 @@ class CometBlockStoreShuffleReader[K, C](
 
   /** Read the combined key-values for this reduce task */
   override def read(): Iterator[Product2[K, C]] = {
-    val recordIter = fetchIterator
-      .flatMap { case (_, inputStream) =>
-        var currentReadIterator: ArrowReaderIterator = null
-
-        // Closes last read iterator after the task is finished.
-        // We need to close read iterator during iterating input streams,
-        // instead of one callback per read iterator. Otherwise if there are too many
-        // read iterators, it may blow up the call stack and cause OOM.
-        context.addTaskCompletionListener[Unit] { _ =>
-          if (currentReadIterator != null) {
-            currentReadIterator.close()
-          }
-        }
-
-        IpcInputStreamIterator(inputStream, decompressingNeeded = true, context)
-          .flatMap { channel =>
+    val recordIter = nativeDecoder match {
+      case Some(decode) =>
+        decode(fetchIterator.map(_._2)).map((0, _)) // use 0 as key since it's not used
+      case None =>
+        fetchIterator.flatMap { case (_, inputStream) =>
+          var currentReadIterator: ArrowReaderIterator = null
+
+          // Closes last read iterator after the task is finished.
+          // We need to close read iterator during iterating input streams,
+          // instead of one callback per read iterator. Otherwise if there are too many
+          // read iterators, it may blow up the call stack and cause OOM.
+          context.addTaskCompletionListener[Unit] { _ =>
             if (currentReadIterator != null) {
-              // Closes previous read iterator.
               currentReadIterator.close()
             }
-            currentReadIterator = new ArrowReaderIterator(channel, this.getClass.getSimpleName)
-            currentReadIterator.map((0, _)) // use 0 as key since it's not used
           }
-      }
+
+          IpcInputStreamIterator(inputStream, decompressingNeeded = true, context)
+            .flatMap { channel =>
+              if (currentReadIterator != null) {
+                // Closes previous read iterator.
+                currentReadIterator.close()
+              }
+              currentReadIterator = new ArrowReaderIterator(channel, this.getClass.getSimpleName)
+              currentReadIterator.map((0, _)) // use 0 as key since it's not used
+            }
+        }
+    }
 
     // Update the context task metrics for each record read.
     val metricIter = CompletionIterator[(Any, Any), Iterator[(Any, Any)]](
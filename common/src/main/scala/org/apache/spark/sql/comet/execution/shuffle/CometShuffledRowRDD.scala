@@ -21,6 +21,7 @@ package org.apache.spark.sql.comet.execution.shuffle
 
 import org.apache.spark.{Dependency, MapOutputTrackerMaster, Partition, Partitioner, ShuffleDependency, SparkEnv, TaskContext}
 import org.apache.spark.rdd.RDD
+import org.apache.spark.shuffle.{ShuffleHandle, ShuffleReader, ShuffleReadMetricsReporter}
 import org.apache.spark.shuffle.sort.SortShuffleManager
 import org.apache.spark.sql.execution.{CoalescedMapperPartitionSpec, CoalescedPartitioner, CoalescedPartitionSpec, PartialMapperPartitionSpec, PartialReducerPartitionSpec, ShufflePartitionSpec}
 import org.apache.spark.sql.execution.metric.{SQLMetric, SQLShuffleReadMetricsReporter}
@@ -31,18 +32,30 @@ import org.apache.spark.sql.vectorized.ColumnarBatch
  * Different from [[org.apache.spark.sql.execution.ShuffledRowRDD]], this RDD is specialized for
  * reading shuffled data through [[CometBlockStoreShuffleReader]]. The shuffled data is read in an
  * iterator of [[Product2[Int, ColumnarBatch]]] instead of [[Product2[Int, InternalRow]]].
+ *
+ * Each partition reads the blocks of a list of partition specs, usually a single one of AQE. With
+ * `CometShuffleManager`, the block ranges of the specs are read by a single reader, whose
+ * fetched blocks are read as one stream, e.g., decoded and coalesced by one native decoder.
  */
 class CometShuffledBatchRDD(
     var dependency: ShuffleDependency[Int, _, _],
     metrics: Map[String, SQLMetric],
-    partitionSpecs: Array[ShufflePartitionSpec])
+    partitionSpecs: Array[Seq[ShufflePartitionSpec]])
     extends RDD[ColumnarBatch](dependency.rdd.context, Nil) {
 
+  def this(
+      dependency: ShuffleDependency[Int, _, _],
+      metrics: Map[String, SQLMetric],
+      partitionSpecs: Array[ShufflePartitionSpec]) = {
+    this(dependency, metrics, partitionSpecs.map(Seq(_)))
+  }
+
   def this(dependency: ShuffleDependency[Int, _, _], metrics: Map[String, SQLMetric]) = {
     this(
       dependency,
       metrics,
-      Array.tabulate(dependency.partitioner.numPartitions)(i => CoalescedPartitionSpec(i, i + 1)))
+      Array.tabulate[ShufflePartitionSpec](dependency.partitioner.numPartitions)(i =>
+        CoalescedPartitionSpec(i, i + 1)))
   }
 
   dependency.rdd.context.setLocalProperty(
@@ -51,9 +64,10 @@ class CometShuffledBatchRDD(
 
   override def getDependencies: Seq[Dependency[_]] = List(dependency)
 
-  override val partitioner: Option[Partitioner] =
-    if (partitionSpecs.forall(_.isInstanceOf[CoalescedPartitionSpec])) {
-      val indices = partitionSpecs.map(_.asInstanceOf[CoalescedPartitionSpec].startReducerIndex)
+  override val partitioner: Option[Partitioner] = {
+    val coalesced = partitionSpecs.collect { case Seq(spec: CoalescedPartitionSpec) => spec }
+    if (coalesced.length == partitionSpecs.length) {
+      val indices = coalesced.map(_.startReducerIndex)
       // TODO this check is based on assumptions of callers' behavior but is sufficient for now.
       if (indices.toSet.size == partitionSpecs.length) {
         Some(new CoalescedPartitioner(dependency.partitioner, indices))
@@ -63,6 +77,7 @@ class CometShuffledBatchRDD(
     } else {
       None
     }
+  }
 
   override def getPartitions: Array[Partition] = {
     Array.tabulate[Partition](partitionSpecs.length) { i =>
@@ -72,7 +87,7 @@ class CometShuffledBatchRDD(
 
   override def getPreferredLocations(partition: Partition): Seq[String] = {
     val tracker = SparkEnv.get.mapOutputTracker.asInstanceOf[MapOutputTrackerMaster]
-    partition.asInstanceOf[ShuffledRowRDDPartition].spec match {
+    partition.asInstanceOf[ShuffledRowRDDPartition].specs.flatMap {
       case CoalescedPartitionSpec(startReducerIndex, endReducerIndex, _) =>
         // TODO order by partition size.
         startReducerIndex.until(endReducerIndex).flatMap { reducerIndex =>
@@ -87,7 +102,7 @@ class CometShuffledBatchRDD(
 
       case CoalescedMapperPartitionSpec(startMapIndex, endMapIndex, _) =>
         tracker.getMapLocation(dependency, startMapIndex, endMapIndex)
-    }
+    }.distinct
   }
 
   override def compute(split: Partition, context: TaskContext): Iterator[ColumnarBatch] = {
@@ -95,52 +110,29 @@ class CometShuffledBatchRDD(
     // `SQLShuffleReadMetricsReporter` will update its own metrics for SQL exchange operator,
     // as well as the `tempMetrics` for basic shuffle metrics.
     val sqlMetricsReporter = new SQLShuffleReadMetricsReporter(tempMetrics, metrics)
-    val reader = split.asInstanceOf[ShuffledRowRDDPartition].spec match {
-      case CoalescedPartitionSpec(startReducerIndex, endReducerIndex, _) =>
-        SparkEnv.get.shuffleManager
-          .getReader(
-            dependency.shuffleHandle,
-            startReducerIndex,
-            endReducerIndex,
-            context,
-            sqlMetricsReporter)
-          .asInstanceOf[CometBlockStoreShuffleReader[_, _]]
-
-      case PartialReducerPartitionSpec(reducerIndex, startMapIndex, endMapIndex, _) =>
-        SparkEnv.get.shuffleManager
-          .getReader(
-            dependency.shuffleHandle,
-            startMapIndex,
-            endMapIndex,
-            reducerIndex,
-            reducerIndex + 1,
-            context,
-            sqlMetricsReporter)
-          .asInstanceOf[CometBlockStoreShuffleReader[_, _]]
-
-      case PartialMapperPartitionSpec(mapIndex, startReducerIndex, endReducerIndex) =>
-        SparkEnv.get.shuffleManager
-          .getReader(
-            dependency.shuffleHandle,
-            mapIndex,
-            mapIndex + 1,
-            startReducerIndex,
-            endReducerIndex,
-            context,
-            sqlMetricsReporter)
-          .asInstanceOf[CometBlockStoreShuffleReader[_, _]]
-
-      case CoalescedMapperPartitionSpec(startMapIndex, endMapIndex, numReducers) =>
-        SparkEnv.get.shuffleManager
-          .getReader(
-            dependency.shuffleHandle,
-            startMapIndex,
-            endMapIndex,
-            0,
-            numReducers,
-            context,
-            sqlMetricsReporter)
-          .asInstanceOf[CometBlockStoreShuffleReader[_, _]]
+    val ranges = split.asInstanceOf[ShuffledRowRDDPartition].specs.map(ShuffleBlockRange.fromSpec)
+    val reader = SparkEnv.get.shuffleManager match {
+      case manager: ShuffleBlockRangesReader =>
+        manager.getBlockRangesReader(
+          dependency.shuffleHandle,
+          ranges,
+          context,
+          sqlMetricsReporter)
+      case manager =>
+        ranges match {
+          case Seq(range) =>
+            manager.getReader(
+              dependency.shuffleHandle,
+              range.startMapIndex,
+              range.endMapIndex,
+              range.startReducerIndex,
+              range.endReducerIndex,
+              context,
+              sqlMetricsReporter)
+          case _ =>
+            throw new UnsupportedOperationException(
+              s"Shuffle manager ${manager.getClass.getName} can't read several block ranges")
+        }
     }
 
     // TODO: Reads IPC by native code
@@ -156,4 +148,42 @@ class CometShuffledBatchRDD(
 /**
  * The [[Partition]] used by [[CometShuffledRowRDD]].
  */
-final case class ShuffledRowRDDPartition(index: Int, spec: ShufflePartitionSpec) extends Partition
+final case class ShuffledRowRDDPartition(index: Int, specs: Seq[ShufflePartitionSpec])
+    extends Partition
+
+/**
+ * The blocks of a shuffle written by the maps in `[startMapIndex, endMapIndex)` for the reducers
+ * in `[startReducerIndex, endReducerIndex)`.
+ */
+case class ShuffleBlockRange(
+    startReducerIndex: Int,
+    endReducerIndex: Int,
+    startMapIndex: Int,
+    endMapIndex: Int)
+
+object ShuffleBlockRange {
+
+  /** The blocks read by the given partition spec of AQE. */
+  def fromSpec(spec: ShufflePartitionSpec): ShuffleBlockRange = spec match {
+    case CoalescedPartitionSpec(startReducerIndex, endReducerIndex, _) =>
+      ShuffleBlockRange(startReducerIndex, endReducerIndex, 0, Int.MaxValue)
+    case PartialReducerPartitionSpec(reducerIndex, startMapIndex, endMapIndex, _) =>
+      ShuffleBlockRange(reducerIndex, reducerIndex + 1, startMapIndex, endMapIndex)
+    case PartialMapperPartitionSpec(mapIndex, startReducerIndex, endReducerIndex) =>
+      ShuffleBlockRange(startReducerIndex, endReducerIndex, mapIndex, mapIndex + 1)
+    case CoalescedMapperPartitionSpec(startMapIndex, endMapIndex, numReducers) =>
+      ShuffleBlockRange(0, numReducers, startMapIndex, endMapIndex)
+  }
+}
+
+/**
+ * A [[org.apache.spark.shuffle.ShuffleManager]] which can read several ranges of the blocks of a
+ * shuffle with a single reader.
+ */
+trait ShuffleBlockRangesReader {
+  def getBlockRangesReader[K, C](
+      handle: ShuffleHandle,
+      ranges: Seq[ShuffleBlockRange],
+      context: TaskContext,
+      metrics: ShuffleReadMetricsReporter): ShuffleReader[K, C]
+}
@@ -20,6 +20,7 @@
 package org.apache.spark.sql.comet.execution.shuffle
 
 import java.io.EOFException
+import java.io.IOException
 import java.io.InputStream
 import java.nio.ByteBuffer
 import java.nio.ByteOrder
@@ -45,6 +46,8 @@ case class IpcInputStreamIterator(
 
   private val ipcLengthsBuf = ByteBuffer.allocate(8).order(ByteOrder.LITTLE_ENDIAN)
 
+  private lazy val codec = ShuffleUtils.compressionCodecForShuffling
+
   // NOTE:
   // since all ipcs are sharing the same input stream and channel, the second
   // hasNext() must be called after the first ipc has been completely processed.
@@ -90,6 +93,11 @@ case class IpcInputStreamIterator(
 
     ipcLengthsBuf.flip()
     currentIpcLength = ipcLengthsBuf.getLong
+    // The IPCs of a block merged by push-based shuffle follow each other like the IPCs of a
+    // single block, so a bad length means the stream is corrupted, not misaligned merged blocks
+    if (currentIpcLength < 0) {
+      throw new IOException(s"Data corrupt: invalid length $currentIpcLength of compressed ipc")
+    }
 
     // Skips empty IPC
     if (currentIpcLength == 0) {
@@ -110,7 +118,7 @@ case class IpcInputStreamIterator(
     currentLimitedInputStream = is
 
     if (decompressingNeeded) {
-      val zs = ShuffleUtils.compressionCodecForShuffling.compressedInputStream(is)
+      val zs = codec.compressedInputStream(is)
       Channels.newChannel(zs)
     } else {
       Channels.newChannel(is)
@@ -19,24 +19,45 @@
 
 package org.apache.spark.sql.comet.execution.shuffle
 
+import scala.util.Try
+
 import org.apache.spark.SparkEnv
 import org.apache.spark.internal.Logging
-import org.apache.spark.internal.config.IO_COMPRESSION_CODEC
+import org.apache.spark.internal.config.{IO_COMPRESSION_CODEC, IO_COMPRESSION_ZSTD_LEVEL}
 import org.apache.spark.io.CompressionCodec
 import org.apache.spark.sql.internal.SQLConf
 
 import org.apache.comet.CometConf
 
 private[spark] object ShuffleUtils extends Logging {
-  lazy val compressionCodecForShuffling: CompressionCodec = {
+
+  /** The codecs which Comet native shuffle compresses data with like Spark */
+  val supportedCodecs: Set[String] = Set("lz4", "snappy", "zstd")
+
+  /**
+   * The short name of the codec used to compress shuffle data, which is
+   * `spark.comet.exec.shuffle.codec` if it is specified, or `spark.io.compression.codec`. The
+   * map and reduce tasks of a shuffle get the same codec from the same SQL configs.
+   */
+  def shuffleCodecName: String = {
     val sparkConf = SparkEnv.get.conf
-    val codecName = CometConf.COMET_EXEC_SHUFFLE_CODEC.get(SQLConf.get)
+    val configured = CometConf.COMET_EXEC_SHUFFLE_CODEC
+      .get(SQLConf.get)
+      .getOrElse(sparkConf.get(IO_COMPRESSION_CODEC))
+    // The codec may be specified by its class name
+    val codecName = Try(CompressionCodec.getShortName(configured)).getOrElse(configured)
 
-    // only zstd compression is supported at the moment
-    if (codecName != "zstd") {
-      logWarning(
-        s"Overriding config ${IO_COMPRESSION_CODEC}=${codecName} in shuffling, force using zstd")
+    if (supportedCodecs.contains(codecName)) {
+      codecName
+    } else {
+      logWarning(s"Overriding unsupported codec $codecName in shuffling, force using zstd")
+      "zstd"
     }
-    CompressionCodec.createCodec(sparkConf, "zstd")
   }
+
+  /** The compression level of zstd, which is `spark.io.compression.zstd.level` */
+  def shuffleZstdLevel: Int = SparkEnv.get.conf.get(IO_COMPRESSION_ZSTD_LEVEL)
+
+  def compressionCodecForShuffling: CompressionCodec =
+    CompressionCodec.createCodec(SparkEnv.get.conf, shuffleCodecName)
 }
@@ -32,7 +32,7 @@ import org.apache.arrow.vector.dictionary.DictionaryProvider
 import org.apache.arrow.vector.ipc.ArrowStreamWriter
 import org.apache.arrow.vector.types._
 import org.apache.arrow.vector.types.pojo.{ArrowType, Field, FieldType, Schema}
-import org.apache.spark.{SparkEnv, SparkException}
+import org.apache.spark.{SparkConf, SparkEnv, SparkException}
 import org.apache.spark.io.CompressionCodec
 import org.apache.spark.sql.types._
 import org.apache.spark.sql.vectorized.ColumnarBatch
@@ -59,6 +59,11 @@ object Utils {
     org.apache.spark.util.Utils.getSimpleName(cls)
   }
 
+  /** bridges the function call to Spark's Util */
+  def getConfiguredLocalDirs(conf: SparkConf): Array[String] = {
+    org.apache.spark.util.Utils.getConfiguredLocalDirs(conf)
+  }
+
   def fromArrowField(field: Field): DataType = {
     field.getType match {
       case _: ArrowType.Map =>
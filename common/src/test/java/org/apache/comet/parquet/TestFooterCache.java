@@ -0,0 +1,73 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one
+ * or more contributor license agreements.  See the NOTICE file
+ * distributed with this work for additional information
+ * regarding copyright ownership.  The ASF licenses this file
+ * to you under the Apache License, Version 2.0 (the
+ * "License"); you may not use this file except in compliance
+ * with the License.  You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+ * KIND, either express or implied.  See the License for the
+ * specific language governing permissions and limitations
+ * under the License.
+ */
+
+
+package org.apache.comet.parquet;
+
+import java.util.ArrayList;
+import java.util.HashMap;
+
+import org.junit.Test;
+
+import org.apache.parquet.hadoop.metadata.FileMetaData;
+import org.apache.parquet.hadoop.metadata.ParquetMetadata;
+import org.apache.parquet.schema.MessageTypeParser;
+
+import static org.junit.Assert.*;
+
+public class TestFooterCache {
+  private int loads = 0;
+
+  private ParquetMetadata load() {
+    loads++;
+    FileMetaData fileMetaData =
+        new FileMetaData(
+            MessageTypeParser.parseMessageType("message m { required int32 a; }"),
+            new HashMap<>(),
+            "test");
+    return new ParquetMetadata(fileMetaData, new ArrayList<>());
+  }
+
+  @Test
+  public void testFooterCache() throws Exception {
+    FooterCache cache = new FooterCache();
+    FooterCache.Key key = new FooterCache.Key("file:/a.parquet", 1L, 100L, 0L, 100L);
+
+    ParquetMetadata footer = cache.getOrLoad(key, 2, this::load);
+    assertSame(
+        footer,
+        cache.getOrLoad(new FooterCache.Key("file:/a.parquet", 1L, 100L, 0L, 100L), 2, this::load));
+    assertEquals(1, loads);
+    assertEquals(1, cache.hitCount());
+    assertEquals(1, cache.missCount());
+
+    // A file which has been overwritten, or another split of the file, is loaded again
+    cache.getOrLoad(new FooterCache.Key("file:/a.parquet", 2L, 100L, 0L, 100L), 2, this::load);
+    cache.getOrLoad(new FooterCache.Key("file:/a.parquet", 1L, 100L, 0L, 50L), 2, this::load);
+    assertEquals(3, loads);
+
+    // The least recently used footers are evicted
+    assertEquals(2, cache.size());
+    assertNotSame(footer, cache.getOrLoad(key, 2, this::load));
+    assertEquals(4, loads);
+
+    cache.clear();
+    assertEquals(0, cache.size());
+  }
+}
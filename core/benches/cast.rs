@@ -0,0 +1,99 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+#[path = "common.rs"]
+mod common;
+
+use arrow::datatypes::{Float64Type, Int32Type};
+use arrow_array::{ArrayRef, RecordBatch, StringArray};
+use arrow_schema::{DataType, Field, Schema};
+use comet::execution::datafusion::expressions::cast::{Cast, EvalMode};
+use common::*;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use datafusion_physical_expr::{expressions::Column, PhysicalExpr};
+use rand::Rng;
+use std::sync::Arc;
+
+const BATCH_SIZE: usize = 1024 * 8;
+const NULL_FRACTION: f32 = 0.1;
+
+/// Creates a string array with values which are valid for casting to boolean, mixed with a few
+/// invalid ones.
+fn create_boolean_string_array(size: usize) -> StringArray {
+    let values = ["true", "false", " T ", "no", "1", "0", "yes", "invalid"];
+    let mut rng = seedable_rng();
+    (0..size)
+        .map(|_| {
+            if rng.gen::<f32>() < NULL_FRACTION {
+                None
+            } else {
+                Some(values[rng.gen_range(0..values.len())])
+            }
+        })
+        .collect()
+}
+
+fn criterion_benchmark(c: &mut Criterion) {
+    let mut group = c.benchmark_group("cast");
+
+    let int32: ArrayRef = Arc::new(create_primitive_array::<Int32Type>(
+        BATCH_SIZE,
+        NULL_FRACTION,
+    ));
+    let float64: ArrayRef = Arc::new(create_primitive_array::<Float64Type>(
+        BATCH_SIZE,
+        NULL_FRACTION,
+    ));
+    let bool_strings: ArrayRef = Arc::new(create_boolean_string_array(BATCH_SIZE));
+    let int_strings = arrow::compute::cast(&int32, &DataType::Utf8).unwrap();
+
+    let cases: Vec<(&str, ArrayRef, DataType)> = vec![
+        ("i32_to_i64", int32.clone(), DataType::Int64),
+        ("i32_to_string", int32, DataType::Utf8),
+        ("f64_to_string", float64.clone(), DataType::Utf8),
+        ("f64_to_i32", float64, DataType::Int32),
+        ("string_to_bool", bool_strings, DataType::Boolean),
+        ("string_to_i32", int_strings, DataType::Int32),
+    ];
+
+    for (name, array, to_type) in cases {
+        let schema = Schema::new(vec![Field::new("a", array.data_type().clone(), true)]);
+        let batch = RecordBatch::try_new(Arc::new(schema), vec![array]).unwrap();
+        let child: Arc<dyn PhysicalExpr> = Arc::new(Column::new("a", 0));
+
+        for eval_mode in [EvalMode::Legacy, EvalMode::Try] {
+            let cast = Cast::new_without_timezone(child.clone(), to_type.clone(), eval_mode);
+            group.bench_function(
+                BenchmarkId::new(format!("{}_{:?}", name, eval_mode), BATCH_SIZE),
+                |b| {
+                    b.iter(|| cast.evaluate(&batch).unwrap());
+                },
+            );
+        }
+    }
+}
+
+fn config() -> Criterion {
+    Criterion::default()
+}
+
+criterion_group! {
+    name = benches;
+    config = config();
+    targets = criterion_benchmark
+}
+criterion_main!(benches);
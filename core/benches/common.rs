@@ -16,12 +16,12 @@
 // under the License.
 
 use arrow::{
-    array::{DictionaryArray, Int64Array, PrimitiveArray},
+    array::{DictionaryArray, Int64Array, PrimitiveArray, StringArray},
     datatypes::{ArrowPrimitiveType, Int32Type},
 };
 use arrow_schema::ArrowError;
 use rand::{
-    distributions::{Distribution, Standard},
+    distributions::{Alphanumeric, Distribution, Standard},
     rngs::StdRng,
     Rng, SeedableRng,
 };
@@ -83,3 +83,51 @@ where
         .collect();
     DictionaryArray::try_new(keys, Arc::new(values))
 }
+
+/// Creates a string array with random ASCII alphanumeric values, whose lengths are uniformly
+/// distributed in `[min_len, max_len)`.
+#[allow(dead_code)]
+pub fn create_string_array(
+    size: usize,
+    null_density: f32,
+    min_len: usize,
+    max_len: usize,
+) -> StringArray {
+    let mut rng = seedable_rng();
+    (0..size)
+        .map(|_| {
+            if rng.gen::<f32>() < null_density {
+                None
+            } else {
+                let len = rng.gen_range(min_len..max_len);
+                let value: String = (&mut rng)
+                    .sample_iter(&Alphanumeric)
+                    .take(len)
+                    .map(char::from)
+                    .collect();
+                Some(value)
+            }
+        })
+        .collect()
+}
+
+/// Creates a string array whose values are picked from `num_distinct` random strings, to
+/// model low cardinality columns.
+#[allow(dead_code)]
+pub fn create_low_cardinality_string_array(
+    size: usize,
+    null_density: f32,
+    num_distinct: usize,
+) -> StringArray {
+    let values = create_string_array(num_distinct, 0.0, 4, 16);
+    let mut rng = seedable_rng();
+    (0..size)
+        .map(|_| {
+            if rng.gen::<f32>() < null_density {
+                None
+            } else {
+                Some(values.value(rng.gen_range(0..num_distinct)).to_string())
+            }
+        })
+        .collect()
+}
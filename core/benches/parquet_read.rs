@@ -163,7 +163,7 @@ impl TestColumnReader {
         mut page_iter: impl PageIterator + 'static,
         total_num_values: usize,
     ) -> Self {
-        let reader = ColumnReader::get(cd, promotion_info, batch_size, false, false);
+        let reader = ColumnReader::get(cd, promotion_info, batch_size, false, false, false);
         let first = page_iter.next().unwrap().unwrap();
         Self {
             inner: reader,
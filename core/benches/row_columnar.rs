@@ -16,8 +16,9 @@
 // under the License.
 
 use arrow::datatypes::DataType as ArrowDataType;
-use comet::execution::shuffle::row::{
-    process_sorted_row_partition, SparkUnsafeObject, SparkUnsafeRow,
+use comet::execution::{
+    config::CompressionCodec,
+    shuffle::row::{process_sorted_row_partition, SparkUnsafeObject, SparkUnsafeRow},
 };
 use criterion::{criterion_group, criterion_main, Criterion};
 use tempfile::Builder;
@@ -74,6 +75,7 @@ fn benchmark(c: &mut Criterion) {
                 &schema,
                 tempfile.path().to_str().unwrap().to_string(),
                 1.0,
+                &CompressionCodec::Zstd(1),
                 false,
                 0,
                 None,
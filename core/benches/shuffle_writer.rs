@@ -0,0 +1,114 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+#[path = "common.rs"]
+mod common;
+
+use arrow::datatypes::{Float64Type, Int32Type};
+use arrow_array::{ArrayRef, RecordBatch};
+use arrow_schema::{DataType, Field, Schema};
+use comet::execution::datafusion::shuffle_writer::ShuffleWriterExec;
+use common::*;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use datafusion::{
+    physical_plan::{common::collect, memory::MemoryExec, ExecutionPlan, Partitioning},
+    prelude::SessionContext,
+};
+use datafusion_physical_expr::expressions::Column;
+use std::sync::Arc;
+
+const BATCH_SIZE: usize = 1024 * 8;
+const NUM_BATCHES: usize = 10;
+const NULL_FRACTION: f32 = 0.1;
+
+fn create_batch() -> RecordBatch {
+    let schema = Schema::new(vec![
+        Field::new("c0", DataType::Int64, true),
+        Field::new("c1", DataType::Int32, true),
+        Field::new("c2", DataType::Float64, true),
+        Field::new("c3", DataType::Utf8, true),
+    ]);
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(create_int64_array(BATCH_SIZE, NULL_FRACTION, 0, i64::MAX)),
+        Arc::new(create_primitive_array::<Int32Type>(
+            BATCH_SIZE,
+            NULL_FRACTION,
+        )),
+        Arc::new(create_primitive_array::<Float64Type>(
+            BATCH_SIZE,
+            NULL_FRACTION,
+        )),
+        Arc::new(create_string_array(BATCH_SIZE, NULL_FRACTION, 4, 32)),
+    ];
+    RecordBatch::try_new(Arc::new(schema), columns).unwrap()
+}
+
+fn criterion_benchmark(c: &mut Criterion) {
+    let mut group = c.benchmark_group("shuffle_writer");
+
+    let batch = create_batch();
+    let batches = vec![batch.clone(); NUM_BATCHES];
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let dir = tempfile::tempdir().unwrap();
+    let data_file = dir.path().join("shuffle.data");
+    let index_file = dir.path().join("shuffle.index");
+
+    let partitionings = vec![
+        ("single", Partitioning::UnknownPartitioning(1)),
+        (
+            "hash_16",
+            Partitioning::Hash(vec![Arc::new(Column::new("c0", 0))], 16),
+        ),
+        (
+            "hash_200",
+            Partitioning::Hash(vec![Arc::new(Column::new("c0", 0))], 200),
+        ),
+        (
+            "hash_200_string_key",
+            Partitioning::Hash(vec![Arc::new(Column::new("c3", 3))], 200),
+        ),
+    ];
+
+    for (name, partitioning) in partitionings {
+        group.bench_function(BenchmarkId::new(name, BATCH_SIZE * NUM_BATCHES), |b| {
+            b.iter(|| {
+                let input = MemoryExec::try_new(&[batches.clone()], batch.schema(), None).unwrap();
+                let exec = ShuffleWriterExec::try_new(
+                    Arc::new(input),
+                    partitioning.clone(),
+                    data_file.to_str().unwrap().to_string(),
+                    index_file.to_str().unwrap().to_string(),
+                )
+                .unwrap();
+                let task_ctx = SessionContext::new().task_ctx();
+                let stream = exec.execute(0, task_ctx).unwrap();
+                runtime.block_on(collect(stream)).unwrap();
+            });
+        });
+    }
+}
+
+fn config() -> Criterion {
+    Criterion::default().sample_size(10)
+}
+
+criterion_group! {
+    name = benches;
+    config = config();
+    targets = criterion_benchmark
+}
+criterion_main!(benches);
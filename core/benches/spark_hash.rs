@@ -0,0 +1,123 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+#[path = "common.rs"]
+mod common;
+
+use arrow::datatypes::{Decimal128Type, Float64Type, Int32Type};
+use arrow_array::ArrayRef;
+use comet::execution::datafusion::spark_hash::{compute_partition_ids, create_hashes, pmod};
+use common::*;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use std::sync::Arc;
+
+const BATCH_SIZE: usize = 1024 * 8;
+const NULL_FRACTION: f32 = 0.1;
+// The seed Spark uses for hash partitioning
+const SEED: u32 = 42;
+
+fn criterion_benchmark(c: &mut Criterion) {
+    let mut group = c.benchmark_group("spark_hash");
+
+    let int32: ArrayRef = Arc::new(create_primitive_array::<Int32Type>(BATCH_SIZE, 0.0));
+    let int32_null: ArrayRef = Arc::new(create_primitive_array::<Int32Type>(
+        BATCH_SIZE,
+        NULL_FRACTION,
+    ));
+    let int64: ArrayRef = Arc::new(create_int64_array(BATCH_SIZE, 0.0, 0, i64::MAX));
+    let int64_null: ArrayRef = Arc::new(create_int64_array(BATCH_SIZE, NULL_FRACTION, 0, i64::MAX));
+    let float64: ArrayRef = Arc::new(create_primitive_array::<Float64Type>(BATCH_SIZE, 0.0));
+    let decimal: ArrayRef = Arc::new(
+        create_primitive_array::<Decimal128Type>(BATCH_SIZE, NULL_FRACTION)
+            .with_precision_and_scale(38, 10)
+            .unwrap(),
+    );
+    let short_strings: ArrayRef = Arc::new(create_string_array(BATCH_SIZE, NULL_FRACTION, 1, 16));
+    let long_strings: ArrayRef = Arc::new(create_string_array(BATCH_SIZE, NULL_FRACTION, 64, 256));
+    let dict: ArrayRef =
+        Arc::new(create_dictionary_array::<Int32Type>(BATCH_SIZE, 100, 0.0).unwrap());
+
+    let inputs: Vec<(&str, Vec<ArrayRef>)> = vec![
+        ("int32", vec![int32.clone()]),
+        ("int32_null", vec![int32_null]),
+        ("int64", vec![int64.clone()]),
+        ("int64_null", vec![int64_null]),
+        ("float64", vec![float64]),
+        ("decimal128", vec![decimal]),
+        ("short_strings", vec![short_strings.clone()]),
+        ("long_strings", vec![long_strings]),
+        ("dictionary", vec![dict]),
+        ("int32_int64_string", vec![int32, int64, short_strings]),
+    ];
+
+    for (name, columns) in &inputs {
+        group.bench_function(BenchmarkId::new(*name, BATCH_SIZE), |b| {
+            let mut hashes = vec![SEED; BATCH_SIZE];
+            b.iter(|| {
+                hashes.fill(SEED);
+                create_hashes(columns, &mut hashes).unwrap();
+            });
+        });
+    }
+    group.finish();
+
+    // Per-row hash and pmod, as the shuffle writer computed partition ids before, against
+    // `compute_partition_ids` with and without a power-of-two number of partitions
+    let mut group = c.benchmark_group("partition_ids");
+    for (name, columns) in inputs.iter().filter(|(name, _)| *name == "int32") {
+        for num_partitions in [200, 256] {
+            group.bench_function(
+                BenchmarkId::new(format!("{}_hash_pmod", name), num_partitions),
+                |b| {
+                    let mut hashes = vec![SEED; BATCH_SIZE];
+                    let mut partition_ids = vec![0_u64; BATCH_SIZE];
+                    b.iter(|| {
+                        hashes.fill(SEED);
+                        create_hashes(columns, &mut hashes)
+                            .unwrap()
+                            .iter()
+                            .enumerate()
+                            .for_each(|(idx, hash)| {
+                                partition_ids[idx] = pmod(*hash, num_partitions) as u64
+                            });
+                    });
+                },
+            );
+            group.bench_function(
+                BenchmarkId::new(format!("{}_compute_partition_ids", name), num_partitions),
+                |b| {
+                    let mut partition_ids = vec![0_u32; BATCH_SIZE];
+                    b.iter(|| {
+                        compute_partition_ids(columns, num_partitions, &mut partition_ids).unwrap();
+                    });
+                },
+            );
+        }
+    }
+    group.finish();
+}
+
+fn config() -> Criterion {
+    Criterion::default()
+}
+
+criterion_group! {
+    name = benches;
+    config = config();
+    targets = criterion_benchmark
+}
+criterion_main!(benches);
@@ -0,0 +1,125 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+#[path = "common.rs"]
+mod common;
+
+use arrow::datatypes::Int32Type;
+use arrow_array::{ArrayRef, RecordBatch};
+use arrow_schema::{DataType, Field, Schema};
+use comet::execution::datafusion::expressions::strings::{
+    Contains, EndsWith, Like, StartsWith, StringSpaceExec, SubstringExec,
+};
+use common::*;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use datafusion_common::ScalarValue;
+use datafusion_physical_expr::{
+    expressions::{Column, Literal},
+    PhysicalExpr,
+};
+use std::sync::Arc;
+
+const BATCH_SIZE: usize = 1024 * 8;
+const NULL_FRACTION: f32 = 0.1;
+
+fn criterion_benchmark(c: &mut Criterion) {
+    let mut group = c.benchmark_group("strings");
+
+    // Short and long random strings, and low cardinality strings as seen in dimension columns
+    let inputs: Vec<(&str, ArrayRef)> = vec![
+        (
+            "short",
+            Arc::new(create_string_array(BATCH_SIZE, NULL_FRACTION, 1, 16)),
+        ),
+        (
+            "long",
+            Arc::new(create_string_array(BATCH_SIZE, NULL_FRACTION, 64, 256)),
+        ),
+        (
+            "low_cardinality",
+            Arc::new(create_low_cardinality_string_array(
+                BATCH_SIZE,
+                NULL_FRACTION,
+                16,
+            )),
+        ),
+    ];
+
+    for (name, array) in inputs {
+        let schema = Schema::new(vec![Field::new("a", DataType::Utf8, true)]);
+        let batch = RecordBatch::try_new(Arc::new(schema), vec![array]).unwrap();
+        let column: Arc<dyn PhysicalExpr> = Arc::new(Column::new("a", 0));
+        let pattern: Arc<dyn PhysicalExpr> =
+            Arc::new(Literal::new(ScalarValue::Utf8(Some("ab".to_string()))));
+        let like_pattern: Arc<dyn PhysicalExpr> =
+            Arc::new(Literal::new(ScalarValue::Utf8(Some("%ab%".to_string()))));
+
+        let exprs: Vec<(&str, Arc<dyn PhysicalExpr>)> = vec![
+            (
+                "substring",
+                Arc::new(SubstringExec::new(column.clone(), 2, 5)),
+            ),
+            (
+                "contains",
+                Arc::new(Contains::new(column.clone(), pattern.clone())),
+            ),
+            (
+                "starts_with",
+                Arc::new(StartsWith::new(column.clone(), pattern.clone())),
+            ),
+            (
+                "ends_with",
+                Arc::new(EndsWith::new(column.clone(), pattern)),
+            ),
+            ("like", Arc::new(Like::new(column, like_pattern))),
+        ];
+
+        for (expr_name, expr) in exprs {
+            group.bench_function(
+                BenchmarkId::new(format!("{}_{}", expr_name, name), BATCH_SIZE),
+                |b| {
+                    b.iter(|| expr.evaluate(&batch).unwrap());
+                },
+            );
+        }
+    }
+
+    // `space` with small lengths
+    let lengths: ArrayRef = Arc::new(
+        create_primitive_array::<Int32Type>(BATCH_SIZE, NULL_FRACTION)
+            .iter()
+            .map(|v| v.map(|v| v.rem_euclid(32)))
+            .collect::<arrow_array::Int32Array>(),
+    );
+    let schema = Schema::new(vec![Field::new("a", DataType::Int32, true)]);
+    let batch = RecordBatch::try_new(Arc::new(schema), vec![lengths]).unwrap();
+    let space = StringSpaceExec::new(Arc::new(Column::new("a", 0)));
+    group.bench_function(BenchmarkId::new("string_space", BATCH_SIZE), |b| {
+        b.iter(|| space.evaluate(&batch).unwrap());
+    });
+}
+
+fn config() -> Criterion {
+    Criterion::default()
+}
+
+criterion_group! {
+    name = benches;
+    config = config();
+    targets = criterion_benchmark
+}
+criterion_main!(benches);
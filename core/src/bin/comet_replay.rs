@@ -0,0 +1,235 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! `comet-replay` executes a serialized Comet native plan outside of Spark.
+//!
+//! The plan is the serialized `Operator` protobuf which is passed to `Native.createPlan` by
+//! `CometExecIterator`. The input of each `Scan` operator in the plan is read from a local
+//! Parquet file, in the order the scans appear in the plan (depth-first, left to right). Columns
+//! of the Parquet file are matched to the scan fields by position, and are cast to the scan
+//! field types if necessary.
+//!
+//! Usage:
+//!
+//! ```text
+//! comet-replay <plan-file> [--input <parquet-file>]... [--batch-size <n>] [--print-rows <n>]
+//! ```
+
+use std::{fs::File, sync::Arc, task::Poll, time::Instant};
+
+use arrow::{compute::cast, util::pretty::pretty_format_batches};
+use arrow_array::{ArrayRef, RecordBatch};
+use comet::execution::{
+    datafusion::planner::PhysicalPlanner,
+    operators::{InputBatch, ScanExec},
+    serde::deserialize_op,
+};
+use datafusion::{
+    physical_plan::display::DisplayableExecutionPlan,
+    prelude::{SessionConfig, SessionContext},
+};
+use futures::{poll, StreamExt};
+use parquet::arrow::arrow_reader::{ParquetRecordBatchReader, ParquetRecordBatchReaderBuilder};
+
+type ReplayResult<T> = Result<T, Box<dyn std::error::Error>>;
+
+const USAGE: &str = "Usage: comet-replay <plan-file> [--input <parquet-file>]... \
+                     [--batch-size <n>] [--print-rows <n>]";
+
+struct ReplayArgs {
+    plan_file: String,
+    inputs: Vec<String>,
+    batch_size: usize,
+    print_rows: usize,
+}
+
+impl ReplayArgs {
+    fn parse(mut args: impl Iterator<Item = String>) -> ReplayResult<Self> {
+        let mut plan_file = None;
+        let mut inputs = vec![];
+        let mut batch_size = 8192;
+        let mut print_rows = 20;
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--input" => inputs.push(args.next().ok_or(USAGE)?),
+                "--batch-size" => batch_size = args.next().ok_or(USAGE)?.parse()?,
+                "--print-rows" => print_rows = args.next().ok_or(USAGE)?.parse()?,
+                "-h" | "--help" => return Err(USAGE.into()),
+                _ if plan_file.is_none() && !arg.starts_with("--") => plan_file = Some(arg),
+                _ => return Err(format!("Unexpected argument '{}'. {}", arg, USAGE).into()),
+            }
+        }
+
+        Ok(Self {
+            plan_file: plan_file.ok_or(USAGE)?,
+            inputs,
+            batch_size,
+            print_rows,
+        })
+    }
+}
+
+/// Feeds batches read from a Parquet file into a `ScanExec`.
+struct ScanInput {
+    scan: ScanExec,
+    reader: ParquetRecordBatchReader,
+}
+
+impl ScanInput {
+    /// Whether the scan has consumed its current input batch.
+    fn needs_input(&self) -> bool {
+        self.scan.batch.try_lock().unwrap().is_none()
+    }
+
+    /// Sets the next input batch of the scan, or EOF if the file is exhausted.
+    fn feed_next(&mut self) -> ReplayResult<()> {
+        let input = match self.reader.next() {
+            Some(batch) => {
+                let batch = batch?;
+                let num_rows = batch.num_rows();
+                InputBatch::Batch(to_scan_columns(&batch, &self.scan)?, num_rows)
+            }
+            None => InputBatch::EOF,
+        };
+        self.scan.set_input_batch(input);
+        Ok(())
+    }
+}
+
+/// Matches the columns of `batch` to the fields of `scan` by position.
+fn to_scan_columns(batch: &RecordBatch, scan: &ScanExec) -> ReplayResult<Vec<ArrayRef>> {
+    if batch.num_columns() != scan.data_types.len() {
+        return Err(format!(
+            "Scan expects {} columns but the input file has {}",
+            scan.data_types.len(),
+            batch.num_columns()
+        )
+        .into());
+    }
+
+    batch
+        .columns()
+        .iter()
+        .zip(scan.data_types.iter())
+        .map(|(column, data_type)| {
+            if column.data_type() == data_type {
+                Ok(column.clone())
+            } else {
+                Ok(cast(column, data_type)?)
+            }
+        })
+        .collect()
+}
+
+fn main() -> ReplayResult<()> {
+    let args = ReplayArgs::parse(std::env::args().skip(1))?;
+
+    let bytes = std::fs::read(&args.plan_file)?;
+    let spark_plan = deserialize_op(&bytes)?;
+
+    let session_config = SessionConfig::new().with_batch_size(args.batch_size);
+    let session_ctx = Arc::new(SessionContext::new_with_config(session_config));
+
+    // The planner creates scans without JVM input sources, so we can feed them directly.
+    let planner = PhysicalPlanner::new(session_ctx.clone());
+    let (scans, root_op) = planner.create_plan(&spark_plan, &mut vec![])?;
+
+    if scans.len() != args.inputs.len() {
+        return Err(format!(
+            "The plan has {} scans but {} input files are given",
+            scans.len(),
+            args.inputs.len()
+        )
+        .into());
+    }
+
+    let mut scan_inputs = scans
+        .into_iter()
+        .zip(args.inputs.iter())
+        .map(|(scan, path)| {
+            let reader = ParquetRecordBatchReaderBuilder::try_new(File::open(path)?)?
+                .with_batch_size(args.batch_size)
+                .build()?;
+            Ok(ScanInput { scan, reader })
+        })
+        .collect::<ReplayResult<Vec<_>>>()?;
+
+    // Like the first batch pulled from JVM when creating a scan in `createPlan`
+    for input in scan_inputs.iter_mut() {
+        input.feed_next()?;
+    }
+
+    println!(
+        "Native plan:\n{}",
+        DisplayableExecutionPlan::new(root_op.as_ref()).indent(true)
+    );
+
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()?;
+
+    let start = Instant::now();
+    let (num_batches, num_rows, printed) = runtime.block_on(async {
+        let mut stream = root_op.execute(0, session_ctx.task_ctx())?;
+        let mut num_batches = 0;
+        let mut num_rows = 0;
+        let mut printed: Vec<RecordBatch> = vec![];
+
+        loop {
+            match poll!(stream.next()) {
+                Poll::Ready(Some(batch)) => {
+                    let batch = batch?;
+                    num_batches += 1;
+                    num_rows += batch.num_rows();
+
+                    let printed_rows: usize = printed.iter().map(|b| b.num_rows()).sum();
+                    if printed_rows < args.print_rows {
+                        let len = batch.num_rows().min(args.print_rows - printed_rows);
+                        printed.push(batch.slice(0, len));
+                    }
+                }
+                Poll::Ready(None) => break,
+                // Same as `executePlan`, operators return pending when they need more input.
+                Poll::Pending => {
+                    for input in scan_inputs.iter_mut().filter(|input| input.needs_input()) {
+                        input.feed_next()?;
+                    }
+                }
+            }
+        }
+
+        ReplayResult::Ok((num_batches, num_rows, printed))
+    })?;
+    let elapsed = start.elapsed();
+
+    if !printed.is_empty() {
+        println!("{}", pretty_format_batches(&printed)?);
+    }
+    println!(
+        "Returned {} rows in {} batches in {:.3} s",
+        num_rows,
+        num_batches,
+        elapsed.as_secs_f64()
+    );
+    println!(
+        "Metrics:\n{}",
+        DisplayableExecutionPlan::with_metrics(root_op.as_ref()).indent(true)
+    );
+
+    Ok(())
+}
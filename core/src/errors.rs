@@ -37,7 +37,10 @@ use std::{
 use jni::sys::{jboolean, jbyte, jchar, jdouble, jfloat, jint, jlong, jobject, jshort};
 
 use crate::execution::operators::ExecutionError;
-use jni::JNIEnv;
+use jni::{
+    objects::{JObject, JThrowable, JValue},
+    JNIEnv,
+};
 use lazy_static::lazy_static;
 use parquet::errors::ParquetError;
 use thiserror::Error;
@@ -72,6 +75,47 @@ pub enum CometError {
         to_type: String,
     },
 
+    #[error("[CAST_OVERFLOW] The value {value} of the type \"{from_type}\" cannot be cast to \"{to_type}\" \
+        due to an overflow. Use `try_cast` to tolerate overflow and return NULL instead. If \
+        necessary set \"spark.sql.ansi.enabled\" to \"false\" to bypass this error.")]
+    CastOverFlow {
+        value: String,
+        from_type: String,
+        to_type: String,
+    },
+
+    #[error(
+        "[DIVIDE_BY_ZERO] Division by zero. Use `try_divide` to tolerate divisor being 0 and \
+        return NULL instead. If necessary set \"spark.sql.ansi.enabled\" to \"false\" to bypass \
+        this error."
+    )]
+    DivideByZero,
+
+    #[error(
+        "[ARITHMETIC_OVERFLOW] {msg}. Use `try_divide` to tolerate overflow and return NULL \
+        instead. If necessary set \"spark.sql.ansi.enabled\" to \"false\" to bypass this error."
+    )]
+    ArithmeticOverflow { msg: String },
+
+    #[error(
+        "[NUMERIC_VALUE_OUT_OF_RANGE] {value} cannot be represented as Decimal({precision}, \
+        {scale}). If necessary set \"spark.sql.ansi.enabled\" to \"false\" to bypass this error, \
+        and return NULL instead."
+    )]
+    NumericValueOutOfRange {
+        value: String,
+        precision: u8,
+        scale: i8,
+    },
+
+    #[error(
+        "[INVALID_ARRAY_INDEX] The index {index} is out of bounds. The array has {array_size} \
+        elements. Use the SQL function `get()` to tolerate accessing element at invalid index \
+        and return NULL instead. If necessary set \"spark.sql.ansi.enabled\" to \"false\" to \
+        bypass this error."
+    )]
+    InvalidArrayIndex { index: i32, array_size: i32 },
+
     #[error(transparent)]
     Arrow {
         #[from]
@@ -170,11 +214,131 @@ impl From<CometError> for DataFusionError {
     fn from(value: CometError) -> Self {
         match value {
             CometError::DataFusion { source } => source,
-            _ => DataFusionError::Execution(value.to_string()),
+            // Keeps the error class through DataFusion, so that it is thrown to the JVM
+            _ => match value.spark_error() {
+                Some(spark_error) => DataFusionError::External(Box::new(spark_error)),
+                None => DataFusionError::Execution(value.to_string()),
+            },
         }
     }
 }
 
+/// An error raised natively with the error class, message parameters and SQLSTATE of the
+/// equivalent Spark error. It is thrown to the JVM as `CometSparkException`, which is converted
+/// to the Spark exception of the error class, so that user code can't tell it from the error
+/// raised by Spark.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SparkError {
+    pub error_class: &'static str,
+    pub message_parameters: Vec<(&'static str, String)>,
+    pub sql_state: &'static str,
+    /// The message formatted like Spark 3.4, which is kept if the Spark version doesn't support
+    /// error classes.
+    pub message: String,
+}
+
+impl std::fmt::Display for SparkError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for SparkError {}
+
+/// The message parameter of the ANSI mode config, which Spark quotes like other configs.
+const ANSI_CONFIG: &str = "\"spark.sql.ansi.enabled\"";
+
+impl CometError {
+    /// Returns the Spark error of this error if it has an error class, including the errors of
+    /// Comet expressions wrapped by DataFusion.
+    pub fn spark_error(&self) -> Option<SparkError> {
+        let (error_class, message_parameters, sql_state) = match self {
+            CometError::CastInvalidValue {
+                value,
+                from_type,
+                to_type,
+            } => (
+                "CAST_INVALID_INPUT",
+                vec![
+                    ("expression", format!("'{}'", value)),
+                    ("sourceType", format!("\"{}\"", from_type)),
+                    ("targetType", format!("\"{}\"", to_type)),
+                    ("ansiConfig", ANSI_CONFIG.to_string()),
+                ],
+                "22018",
+            ),
+            CometError::CastOverFlow {
+                value,
+                from_type,
+                to_type,
+            } => (
+                "CAST_OVERFLOW",
+                vec![
+                    ("value", value.clone()),
+                    ("sourceType", format!("\"{}\"", from_type)),
+                    ("targetType", format!("\"{}\"", to_type)),
+                    ("ansiConfig", ANSI_CONFIG.to_string()),
+                ],
+                "22003",
+            ),
+            CometError::DivideByZero => (
+                "DIVIDE_BY_ZERO",
+                vec![("config", ANSI_CONFIG.to_string())],
+                "22012",
+            ),
+            CometError::ArithmeticOverflow { msg } => (
+                "ARITHMETIC_OVERFLOW",
+                vec![
+                    ("message", msg.clone()),
+                    (
+                        "alternative",
+                        " Use `try_divide` to tolerate overflow and return NULL instead."
+                            .to_string(),
+                    ),
+                    ("config", ANSI_CONFIG.to_string()),
+                ],
+                "22003",
+            ),
+            CometError::NumericValueOutOfRange {
+                value,
+                precision,
+                scale,
+            } => (
+                "NUMERIC_VALUE_OUT_OF_RANGE",
+                vec![
+                    ("value", value.clone()),
+                    ("precision", precision.to_string()),
+                    ("scale", scale.to_string()),
+                    ("config", ANSI_CONFIG.to_string()),
+                ],
+                "22003",
+            ),
+            CometError::InvalidArrayIndex { index, array_size } => (
+                "INVALID_ARRAY_INDEX",
+                vec![
+                    ("indexValue", index.to_string()),
+                    ("arraySize", array_size.to_string()),
+                    ("ansiConfig", ANSI_CONFIG.to_string()),
+                ],
+                "22003",
+            ),
+            CometError::DataFusion { source } => {
+                return match source.find_root() {
+                    DataFusionError::External(e) => e.downcast_ref::<SparkError>().cloned(),
+                    _ => None,
+                }
+            }
+            _ => return None,
+        };
+        Some(SparkError {
+            error_class,
+            message_parameters,
+            sql_state,
+            message: self.to_string(),
+        })
+    }
+}
+
 impl From<CometError> for ExecutionError {
     fn from(value: CometError) -> Self {
         match value {
@@ -199,6 +363,13 @@ impl jni::errors::ToException for CometError {
                 class: "org/apache/spark/SparkException".to_string(),
                 msg: self.to_string(),
             },
+            CometError::DivideByZero
+            | CometError::CastOverFlow { .. }
+            | CometError::ArithmeticOverflow { .. }
+            | CometError::NumericValueOutOfRange { .. } => Exception {
+                class: "java/lang/ArithmeticException".to_string(),
+                msg: self.to_string(),
+            },
             CometError::NumberIntFormat { source: s } => Exception {
                 class: "java/lang/NumberFormatException".to_string(),
                 msg: s.to_string(),
@@ -340,7 +511,10 @@ pub fn unwrap_or_throw_default<T: JNIDefault>(
                 CometError::Panic { msg: _ } => PANIC_BACKTRACE.lock().unwrap().take(),
                 _ => None,
             };
-            throw_exception(env, &err, backtrace);
+            match err.spark_error() {
+                Some(spark_error) => throw_spark_error(env, &spark_error),
+                None => throw_exception(env, &err, backtrace),
+            }
             T::default()
         }
     }
@@ -362,6 +536,46 @@ fn throw_exception<E: ToException>(env: &mut JNIEnv, error: &E, backtrace: Optio
     }
 }
 
+/// Throws `CometSparkException` carrying the error class and message parameters of the error.
+fn throw_spark_error(env: &mut JNIEnv, error: &SparkError) {
+    if env.exception_check().is_ok() {
+        let exception = new_spark_exception(env, error).expect("Created exception");
+        env.throw(exception).expect("Thrown exception")
+    }
+}
+
+fn new_spark_exception<'local>(
+    env: &mut JNIEnv<'local>,
+    error: &SparkError,
+) -> jni::errors::Result<JThrowable<'local>> {
+    let string_class = env.find_class("java/lang/String")?;
+    let num_parameters = error.message_parameters.len() as i32;
+    let names = env.new_object_array(num_parameters, &string_class, JObject::null())?;
+    let values = env.new_object_array(num_parameters, &string_class, JObject::null())?;
+    for (i, (name, value)) in error.message_parameters.iter().enumerate() {
+        let name = env.new_string(name)?;
+        env.set_object_array_element(&names, i as i32, name)?;
+        let value = env.new_string(value)?;
+        env.set_object_array_element(&values, i as i32, value)?;
+    }
+    let message = env.new_string(&error.message)?;
+    let error_class = env.new_string(error.error_class)?;
+    let sql_state = env.new_string(error.sql_state)?;
+    let exception = env.new_object(
+        "org/apache/comet/CometSparkException",
+        "(Ljava/lang/String;Ljava/lang/String;[Ljava/lang/String;[Ljava/lang/String;\
+        Ljava/lang/String;)V",
+        &[
+            JValue::Object(&message),
+            JValue::Object(&error_class),
+            JValue::Object(&names),
+            JValue::Object(&values),
+            JValue::Object(&sql_state),
+        ],
+    )?;
+    Ok(JThrowable::from(exception))
+}
+
 #[derive(Debug, Error)]
 enum StacktraceError {
     #[error("Unable to initialize message: {0}")]
@@ -687,6 +901,33 @@ mod tests {
         assert_eq!(expected_string.trim(), stacktrace_string.as_str());
     }
 
+    /// Test that the error class of an error is kept when it is wrapped by DataFusion.
+    #[test]
+    pub fn spark_error_through_datafusion() {
+        let error = CometError::CastOverFlow {
+            value: "2147483648L".to_string(),
+            from_type: "BIGINT".to_string(),
+            to_type: "INT".to_string(),
+        };
+        let expected = error.spark_error().unwrap();
+        assert_eq!(expected.error_class, "CAST_OVERFLOW");
+        assert_eq!(expected.sql_state, "22003");
+        assert_eq!(
+            expected.message_parameters[1],
+            ("sourceType", "\"BIGINT\"".to_string())
+        );
+
+        let wrapped = DataFusionError::Context(
+            "Evaluating cast".to_string(),
+            Box::new(DataFusionError::from(error)),
+        );
+        let actual = CometError::from(wrapped).spark_error();
+        assert_eq!(actual, Some(expected));
+
+        let error = CometError::from(DataFusionError::Execution("failed".to_string()));
+        assert_eq!(error.spark_error(), None);
+    }
+
     fn read_resource(path: &str) -> Result<String, io::Error> {
         let mut path_buf = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
         path_buf.push(path);
@@ -0,0 +1,372 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Native allocation tracking, enabled by the `alloc_tracking` feature.
+//!
+//! When enabled, the global allocator records every allocation made while a native operator is
+//! being polled, together with the operator name and a backtrace of the allocation site. When a
+//! native plan is released, allocations attributed to it which are still alive are reported as
+//! leaks. Allocations made outside of operator polling (e.g., on tokio worker threads spawned by
+//! operators) are not tracked.
+//!
+//! This is very expensive and is only meant for debugging off-heap memory leaks.
+
+use std::{
+    alloc::{GlobalAlloc, Layout},
+    any::Any,
+    backtrace::Backtrace,
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+};
+
+use arrow_array::RecordBatch;
+use arrow_schema::SchemaRef;
+use datafusion::{execution::TaskContext, physical_plan::*};
+use datafusion_common::Result as DataFusionResult;
+use futures::{Stream, StreamExt};
+use log::warn;
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+
+/// A global allocator wrapping `inner`, which records the allocations made inside an
+/// operator scope. See [`enter_operator`].
+pub struct TrackingAllocator<A> {
+    inner: A,
+}
+
+impl<A> TrackingAllocator<A> {
+    pub const fn new(inner: A) -> Self {
+        Self { inner }
+    }
+}
+
+unsafe impl<A: GlobalAlloc> GlobalAlloc for TrackingAllocator<A> {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = self.inner.alloc(layout);
+        if !ptr.is_null() {
+            record_allocation(ptr, layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let ptr = self.inner.alloc_zeroed(layout);
+        if !ptr.is_null() {
+            record_allocation(ptr, layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        if NUM_TRACKED.load(Ordering::Relaxed) > 0 {
+            forget_allocation(ptr);
+        }
+        self.inner.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = self.inner.realloc(ptr, layout, new_size);
+        if !new_ptr.is_null() {
+            move_allocation(ptr, new_ptr, new_size);
+        }
+        new_ptr
+    }
+}
+
+/// A live allocation made inside an operator scope.
+struct AllocationRecord {
+    size: usize,
+    exec_context_id: i64,
+    operator: Arc<str>,
+    backtrace: Backtrace,
+}
+
+/// Live allocations keyed by their addresses.
+static LIVE_ALLOCATIONS: Lazy<Mutex<HashMap<usize, AllocationRecord>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Number of entries in `LIVE_ALLOCATIONS`, to skip the lookup on deallocation when nothing
+/// is tracked.
+static NUM_TRACKED: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    /// Whether the current thread is running tracker code. Allocations made by the tracker
+    /// itself must not be tracked, otherwise we'd recurse into the allocator.
+    static IN_TRACKER: Cell<bool> = const { Cell::new(false) };
+
+    /// The stack of operators being polled on the current thread. The innermost one owns new
+    /// allocations.
+    static OPERATOR_SCOPES: RefCell<Vec<(i64, Arc<str>)>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Runs `f` with allocation tracking disabled on the current thread. Returns `None` without
+/// running `f` if the current thread is already in the tracker, or is being torn down.
+fn without_tracking<R>(f: impl FnOnce() -> R) -> Option<R> {
+    IN_TRACKER
+        .try_with(|in_tracker| {
+            if in_tracker.get() {
+                return None;
+            }
+            in_tracker.set(true);
+            let result = f();
+            in_tracker.set(false);
+            Some(result)
+        })
+        .ok()
+        .flatten()
+}
+
+fn record_allocation(ptr: *mut u8, size: usize) {
+    without_tracking(|| {
+        let scope = OPERATOR_SCOPES
+            .try_with(|scopes| scopes.borrow().last().cloned())
+            .ok()
+            .flatten();
+        if let Some((exec_context_id, operator)) = scope {
+            let record = AllocationRecord {
+                size,
+                exec_context_id,
+                operator,
+                backtrace: Backtrace::force_capture(),
+            };
+            if LIVE_ALLOCATIONS
+                .lock()
+                .insert(ptr as usize, record)
+                .is_none()
+            {
+                NUM_TRACKED.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    });
+}
+
+fn forget_allocation(ptr: *mut u8) {
+    without_tracking(|| {
+        let removed = LIVE_ALLOCATIONS.lock().remove(&(ptr as usize));
+        if removed.is_some() {
+            NUM_TRACKED.fetch_sub(1, Ordering::Relaxed);
+        }
+    });
+}
+
+/// Keeps the original owner of a reallocated buffer. Buffers which were not tracked before are
+/// attributed to the current operator, if any.
+fn move_allocation(old_ptr: *mut u8, new_ptr: *mut u8, new_size: usize) {
+    let moved = without_tracking(|| {
+        let mut live = LIVE_ALLOCATIONS.lock();
+        match live.remove(&(old_ptr as usize)) {
+            Some(mut record) => {
+                record.size = new_size;
+                live.insert(new_ptr as usize, record);
+                true
+            }
+            None => false,
+        }
+    });
+    if moved == Some(false) {
+        record_allocation(new_ptr, new_size);
+    }
+}
+
+/// Allocations made on the current thread are attributed to the given operator until the
+/// returned scope is dropped.
+pub fn enter_operator(exec_context_id: i64, operator: &Arc<str>) -> OperatorScope {
+    let pushed = without_tracking(|| {
+        OPERATOR_SCOPES
+            .try_with(|scopes| {
+                scopes
+                    .borrow_mut()
+                    .push((exec_context_id, operator.clone()))
+            })
+            .is_ok()
+    })
+    .unwrap_or(false);
+    OperatorScope { pushed }
+}
+
+/// Guard returned by [`enter_operator`].
+pub struct OperatorScope {
+    pushed: bool,
+}
+
+impl Drop for OperatorScope {
+    fn drop(&mut self) {
+        if self.pushed {
+            without_tracking(|| {
+                let _ = OPERATOR_SCOPES.try_with(|scopes| scopes.borrow_mut().pop());
+            });
+        }
+    }
+}
+
+/// Removes the allocations attributed to the given native plan which are still alive, and
+/// reports them as leaks. Returns the number of leaked bytes.
+///
+/// This should be called after all the resources of the plan are dropped.
+pub fn report_leaks(exec_context_id: i64) -> usize {
+    let leaks = without_tracking(|| {
+        let mut live = LIVE_ALLOCATIONS.lock();
+        let addresses = live
+            .iter()
+            .filter(|(_, record)| record.exec_context_id == exec_context_id)
+            .map(|(address, _)| *address)
+            .collect::<Vec<_>>();
+        let leaks = addresses
+            .iter()
+            .filter_map(|address| live.remove(address))
+            .collect::<Vec<_>>();
+        NUM_TRACKED.fetch_sub(leaks.len(), Ordering::Relaxed);
+        leaks
+    })
+    .unwrap_or_default();
+
+    let leaked_bytes = leaks.iter().map(|record| record.size).sum();
+    if !leaks.is_empty() {
+        warn!(
+            "Found {} leaked allocations ({} bytes) at the shutdown of native plan {}",
+            leaks.len(),
+            leaked_bytes,
+            exec_context_id
+        );
+        for record in leaks {
+            warn!(
+                "Leaked {} bytes allocated by {}:\n{}",
+                record.size, record.operator, record.backtrace
+            );
+        }
+    }
+    leaked_bytes
+}
+
+/// An execution node attributing the allocations made while polling its input operator to
+/// that operator. It doesn't modify input batches.
+#[derive(Debug)]
+pub struct AllocTrackingExec {
+    input: Arc<dyn ExecutionPlan>,
+    exec_context_id: i64,
+    /// Name of the input operator
+    operator: Arc<str>,
+}
+
+impl AllocTrackingExec {
+    pub fn new(input: Arc<dyn ExecutionPlan>, exec_context_id: i64) -> Self {
+        let description = displayable(input.as_ref()).one_line().to_string();
+        let operator: Arc<str> = description
+            .split(':')
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .into();
+        Self {
+            input,
+            exec_context_id,
+            operator,
+        }
+    }
+
+    /// The operator whose allocations are tracked by this node.
+    pub fn input(&self) -> &Arc<dyn ExecutionPlan> {
+        &self.input
+    }
+}
+
+impl DisplayAs for AllocTrackingExec {
+    fn fmt_as(&self, t: DisplayFormatType, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match t {
+            DisplayFormatType::Default | DisplayFormatType::Verbose => {
+                write!(f, "AllocTrackingExec")
+            }
+        }
+    }
+}
+
+impl ExecutionPlan for AllocTrackingExec {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn schema(&self) -> SchemaRef {
+        self.input.schema()
+    }
+
+    fn children(&self) -> Vec<Arc<dyn ExecutionPlan>> {
+        vec![self.input.clone()]
+    }
+
+    fn with_new_children(
+        self: Arc<Self>,
+        children: Vec<Arc<dyn ExecutionPlan>>,
+    ) -> DataFusionResult<Arc<dyn ExecutionPlan>> {
+        Ok(Arc::new(AllocTrackingExec::new(
+            children[0].clone(),
+            self.exec_context_id,
+        )))
+    }
+
+    fn execute(
+        &self,
+        partition: usize,
+        context: Arc<TaskContext>,
+    ) -> DataFusionResult<SendableRecordBatchStream> {
+        let child_stream = {
+            let _scope = enter_operator(self.exec_context_id, &self.operator);
+            self.input.execute(partition, context)?
+        };
+        Ok(Box::pin(AllocTrackingStream {
+            schema: self.schema(),
+            exec_context_id: self.exec_context_id,
+            operator: self.operator.clone(),
+            child_stream,
+        }))
+    }
+
+    fn statistics(&self) -> DataFusionResult<Statistics> {
+        self.input.statistics()
+    }
+
+    fn properties(&self) -> &PlanProperties {
+        self.input.properties()
+    }
+}
+
+struct AllocTrackingStream {
+    schema: SchemaRef,
+    exec_context_id: i64,
+    operator: Arc<str>,
+    child_stream: SendableRecordBatchStream,
+}
+
+impl Stream for AllocTrackingStream {
+    type Item = DataFusionResult<RecordBatch>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let _scope = enter_operator(self.exec_context_id, &self.operator);
+        self.child_stream.poll_next_unpin(cx)
+    }
+}
+
+impl RecordBatchStream for AllocTrackingStream {
+    fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+}
@@ -0,0 +1,347 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Native serialization of the batches broadcasted by Comet broadcast exchange.
+//!
+//! A serialized batch is a block of the native shuffle writer, i.e., the length of the block in
+//! 8 little-endian bytes followed by the zstd compressed Arrow IPC stream of the batch. Empty
+//! batches are serialized into no bytes.
+//!
+//! Large broadcast relations can also be decompressed once per executor into an Arrow IPC file,
+//! which is memory-mapped and shared by all the tasks of the executor. See [`MappedBroadcast`].
+
+use std::{
+    any::Any,
+    collections::HashMap,
+    io::{BufWriter, Cursor, Write},
+    path::Path,
+    ptr::NonNull,
+    sync::{Arc, Mutex, Weak},
+};
+
+use arrow::{
+    buffer::Buffer,
+    error::ArrowError,
+    ipc::{
+        convert::fb_to_schema,
+        reader::{read_footer_length, FileDecoder, StreamReader},
+        root_as_footer,
+        writer::FileWriter,
+    },
+};
+use arrow_array::RecordBatch;
+use lazy_static::lazy_static;
+use memmap2::Mmap;
+use tempfile::NamedTempFile;
+
+use crate::{
+    errors::CometError,
+    execution::{
+        config::{CompressionCodec, DEFAULT_ZSTD_LEVEL},
+        datafusion::shuffle_writer::write_ipc_compressed,
+        shuffle::codec::decompression_reader,
+    },
+};
+
+lazy_static! {
+    /// The broadcast relations mapped by the tasks of this executor, by broadcast id
+    static ref MAPPED_BROADCASTS: Mutex<HashMap<i64, Weak<MappedBroadcast>>> =
+        Mutex::new(HashMap::new());
+}
+
+/// The codec of the serialized batches, which are only deserialized natively
+const BROADCAST_CODEC: CompressionCodec = CompressionCodec::Zstd(DEFAULT_ZSTD_LEVEL);
+
+/// Serializes the given batch into compressed Arrow IPC bytes.
+pub fn serialize_batch(batch: &RecordBatch) -> Result<Vec<u8>, CometError> {
+    let mut cursor = Cursor::new(Vec::new());
+    write_ipc_compressed(batch, &mut cursor, &BROADCAST_CODEC)?;
+    Ok(cursor.into_inner())
+}
+
+/// Deserializes the batches serialized by [`serialize_batch`], which may be concatenated.
+pub fn deserialize_batches(bytes: &[u8]) -> Result<Vec<RecordBatch>, CometError> {
+    deserialize_batches_with_codec(bytes, &BROADCAST_CODEC)
+}
+
+/// Deserializes the batches written by [`write_ipc_compressed`] with the given codec, e.g., the
+/// blocks of a shuffle partition, which may be concatenated.
+pub(crate) fn deserialize_batches_with_codec(
+    bytes: &[u8],
+    codec: &CompressionCodec,
+) -> Result<Vec<RecordBatch>, CometError> {
+    let mut batches = vec![];
+    let mut offset = 0;
+
+    while offset < bytes.len() {
+        if bytes.len() - offset < 8 {
+            return Err(CometError::Internal(format!(
+                "Invalid serialized batch: expected the block length at offset {} of {} bytes",
+                offset,
+                bytes.len()
+            )));
+        }
+        let mut length = [0u8; 8];
+        length.copy_from_slice(&bytes[offset..offset + 8]);
+        let length = u64::from_le_bytes(length) as usize;
+        offset += 8;
+
+        if bytes.len() - offset < length {
+            return Err(CometError::Internal(format!(
+                "Invalid serialized batch: block of {} bytes at offset {} exceeds {} bytes",
+                length,
+                offset,
+                bytes.len()
+            )));
+        }
+        batches.extend(decode_block(&bytes[offset..offset + length], codec)?);
+        offset += length;
+    }
+
+    Ok(batches)
+}
+
+/// Decodes the batches of a block written by [`write_ipc_compressed`] with the given codec,
+/// without its length, i.e., the compressed Arrow IPC stream.
+pub(crate) fn decode_block(
+    block: &[u8],
+    codec: &CompressionCodec,
+) -> Result<Vec<RecordBatch>, CometError> {
+    let reader = StreamReader::try_new(decompression_reader(codec, block)?, None)?;
+    Ok(reader.collect::<Result<Vec<_>, _>>()?)
+}
+
+/// A broadcast relation decompressed into an Arrow IPC file, whose batches are Arrow arrays over
+/// the memory-mapped file without copying.
+///
+/// The relation is shared by the tasks of the executor which use it at the same time, and the
+/// file is deleted once no task uses it. Arrays exported to JVM keep the mapped region alive even
+/// after the file is deleted.
+pub struct MappedBroadcast {
+    batches: Vec<RecordBatch>,
+    /// The states built from the relation and shared by the tasks using it, by key
+    shared_states: Mutex<HashMap<String, Arc<dyn Any + Send + Sync>>>,
+    _file: Option<NamedTempFile>,
+}
+
+impl MappedBroadcast {
+    /// Returns the given broadcast relation if it is mapped by any task of this executor.
+    pub fn get(broadcast_id: i64) -> Option<Arc<MappedBroadcast>> {
+        let mapped = MAPPED_BROADCASTS.lock().unwrap();
+        mapped.get(&broadcast_id).and_then(Weak::upgrade)
+    }
+
+    /// Returns the given broadcast relation, which is mapped from the serialized `chunks` into a
+    /// file under `dir` if no task of this executor maps it yet.
+    pub fn get_or_map(
+        broadcast_id: i64,
+        chunks: &[Vec<u8>],
+        dir: &Path,
+    ) -> Result<Arc<MappedBroadcast>, CometError> {
+        // Holds the lock while mapping, so concurrent tasks map the relation only once
+        let mut mapped = MAPPED_BROADCASTS.lock().unwrap();
+        if let Some(relation) = mapped.get(&broadcast_id).and_then(Weak::upgrade) {
+            return Ok(relation);
+        }
+
+        let relation = Arc::new(Self::map(chunks, dir)?);
+        mapped.retain(|_, relation| relation.strong_count() > 0);
+        mapped.insert(broadcast_id, Arc::downgrade(&relation));
+        Ok(relation)
+    }
+
+    /// The batches of the relation.
+    pub fn batches(&self) -> &[RecordBatch] {
+        &self.batches
+    }
+
+    /// Returns the state built from the relation under `key`, e.g., the hash table of a join,
+    /// which is created by `create` if no task using the relation has created it yet.
+    pub fn shared_state<T: Any + Send + Sync, E>(
+        &self,
+        key: String,
+        create: impl FnOnce() -> Result<Arc<T>, E>,
+    ) -> Result<Arc<T>, E> {
+        let mut states = self.shared_states.lock().unwrap();
+        if let Some(state) = states
+            .get(&key)
+            .and_then(|s| s.clone().downcast::<T>().ok())
+        {
+            return Ok(state);
+        }
+        let state = create()?;
+        states.insert(key, state.clone());
+        Ok(state)
+    }
+
+    fn map(chunks: &[Vec<u8>], dir: &Path) -> Result<Self, CometError> {
+        let mut file = tempfile::Builder::new()
+            .prefix("comet-broadcast-")
+            .suffix(".arrow")
+            .tempfile_in(dir)?;
+
+        let mut writer: Option<FileWriter<BufWriter<&mut std::fs::File>>> = None;
+        for chunk in chunks {
+            for batch in deserialize_batches(chunk)? {
+                if writer.is_none() {
+                    writer = Some(FileWriter::try_new(
+                        BufWriter::new(file.as_file_mut()),
+                        &batch.schema(),
+                    )?);
+                }
+                writer.as_mut().unwrap().write(&batch)?;
+            }
+        }
+        let Some(mut writer) = writer else {
+            // The relation is empty
+            return Ok(Self {
+                batches: vec![],
+                shared_states: Mutex::new(HashMap::new()),
+                _file: None,
+            });
+        };
+        writer.finish()?;
+        writer.into_inner()?.flush()?;
+
+        // SAFETY: the file is private to this relation and is not modified after mapping.
+        let mmap = unsafe { Mmap::map(file.as_file())? };
+        let len = mmap.len();
+        let ptr = NonNull::new(mmap.as_ptr() as *mut u8)
+            .ok_or_else(|| CometError::Internal("Failed to map broadcast file".to_string()))?;
+        // SAFETY: the buffer owns the mapping, which is valid for `len` bytes.
+        let buffer = unsafe { Buffer::from_custom_allocation(ptr, len, Arc::new(mmap)) };
+
+        Ok(Self {
+            batches: read_ipc_file(&buffer)?,
+            shared_states: Mutex::new(HashMap::new()),
+            _file: Some(file),
+        })
+    }
+}
+
+/// Reads the batches of an Arrow IPC file in `buffer` without copying their data.
+fn read_ipc_file(buffer: &Buffer) -> Result<Vec<RecordBatch>, ArrowError> {
+    if buffer.len() < 10 {
+        return Err(ArrowError::IpcError("Invalid Arrow IPC file".to_string()));
+    }
+    let trailer_start = buffer.len() - 10;
+    let footer_len = read_footer_length(buffer[trailer_start..].try_into().unwrap())?;
+    let footer = root_as_footer(&buffer[trailer_start - footer_len..trailer_start])
+        .map_err(|e| ArrowError::IpcError(format!("Invalid Arrow IPC footer: {}", e)))?;
+    let schema = footer
+        .schema()
+        .ok_or_else(|| ArrowError::IpcError("Missing schema in Arrow IPC file".to_string()))?;
+
+    let mut decoder = FileDecoder::new(Arc::new(fb_to_schema(schema)), footer.version());
+    let block_data = |block: &arrow::ipc::Block| {
+        let block_len = block.bodyLength() as usize + block.metaDataLength() as usize;
+        buffer.slice_with_length(block.offset() as usize, block_len)
+    };
+
+    for block in footer.dictionaries().iter().flatten() {
+        decoder.read_dictionary(block, &block_data(block))?;
+    }
+
+    let mut batches = vec![];
+    for block in footer.recordBatches().iter().flatten() {
+        if let Some(batch) = decoder.read_record_batch(block, &block_data(block))? {
+            batches.push(batch);
+        }
+    }
+    Ok(batches)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use arrow_array::{Int32Array, RecordBatch, StringArray};
+    use arrow_schema::{DataType, Field, Schema};
+
+    use super::{deserialize_batches, serialize_batch, MappedBroadcast};
+
+    #[test]
+    fn test_roundtrip() {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("a", DataType::Int32, true),
+            Field::new("b", DataType::Utf8, true),
+        ]));
+        let batch = RecordBatch::try_new(
+            schema.clone(),
+            vec![
+                Arc::new(Int32Array::from(vec![Some(1), None, Some(3)])),
+                Arc::new(StringArray::from(vec![Some("a"), Some("b"), None])),
+            ],
+        )
+        .unwrap();
+
+        let mut bytes = serialize_batch(&batch).unwrap();
+        bytes.extend(serialize_batch(&batch.slice(1, 2)).unwrap());
+        bytes.extend(serialize_batch(&batch.slice(0, 0)).unwrap());
+
+        let batches = deserialize_batches(&bytes).unwrap();
+        assert_eq!(batches, vec![batch.clone(), batch.slice(1, 2)]);
+
+        // Truncated bytes
+        assert!(deserialize_batches(&bytes[..bytes.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn test_mapped_broadcast() {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("a", DataType::Int32, true),
+            Field::new("b", DataType::Utf8, true),
+        ]));
+        let batch = RecordBatch::try_new(
+            schema.clone(),
+            vec![
+                Arc::new(Int32Array::from(vec![Some(1), None, Some(3)])),
+                Arc::new(StringArray::from(vec![Some("a"), Some("b"), None])),
+            ],
+        )
+        .unwrap();
+        let chunks = vec![
+            serialize_batch(&batch).unwrap(),
+            vec![],
+            serialize_batch(&batch.slice(1, 2)).unwrap(),
+        ];
+        let dir = tempfile::tempdir().unwrap();
+
+        let relation = MappedBroadcast::get_or_map(1, &chunks, dir.path()).unwrap();
+        assert_eq!(relation.batches(), &[batch.clone(), batch.slice(1, 2)]);
+
+        // Tasks share the mapped relation while it is used
+        let shared = MappedBroadcast::get(1).unwrap();
+        assert!(Arc::ptr_eq(&relation, &shared));
+        assert!(Arc::ptr_eq(
+            &relation,
+            &MappedBroadcast::get_or_map(1, &[], dir.path()).unwrap()
+        ));
+
+        // The file is deleted once the relation is not used, while its arrays are still valid
+        let batches = relation.batches().to_vec();
+        drop(relation);
+        drop(shared);
+        assert!(MappedBroadcast::get(1).is_none());
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+        assert_eq!(batches, vec![batch.clone(), batch.slice(1, 2)]);
+
+        // Empty relation
+        let relation = MappedBroadcast::get_or_map(2, &[vec![]], dir.path()).unwrap();
+        assert!(relation.batches().is_empty());
+    }
+}
@@ -0,0 +1,717 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Typed configs of native execution.
+//!
+//! The configs are passed as a string map from `CometExecIterator` when a native plan is created,
+//! and are parsed and validated once into [`NativeConfig`].
+
+use std::{collections::HashMap, fmt::Display, str::FromStr};
+
+use log::warn;
+
+use crate::errors::{CometError, CometResult};
+
+pub const BATCH_SIZE: &str = "batch_size";
+pub const PARTITION_INDEX: &str = "partition_index";
+pub const USE_UNIFIED_MEMORY_MANAGER: &str = "use_unified_memory_manager";
+pub const USE_UNIFIED_SHUFFLE_MEMORY: &str = "use_unified_shuffle_memory";
+pub const MEMORY_LIMIT: &str = "memory_limit";
+pub const MEMORY_FRACTION: &str = "memory_fraction";
+pub const SHUFFLE_CODEC: &str = "shuffle_codec";
+pub const IO_PARALLELISM: &str = "io_parallelism";
+pub const COMPUTE_THREADS: &str = "compute_threads";
+pub const PIN_COMPUTE_THREADS: &str = "pin_compute_threads";
+pub const PARTIAL_AGG_SKIP_ENABLED: &str = "partial_agg_skip_enabled";
+pub const PARTIAL_AGG_SKIP_PROBE_ROWS: &str = "partial_agg_skip_probe_rows";
+pub const PARTIAL_AGG_SKIP_RATIO_THRESHOLD: &str = "partial_agg_skip_ratio_threshold";
+pub const DICTIONARY_ENCODING_ENABLED: &str = "dictionary_encoding_enabled";
+pub const DICTIONARY_ENCODING_SAMPLE_ROWS: &str = "dictionary_encoding_sample_rows";
+pub const DICTIONARY_ENCODING_MAX_RATIO: &str = "dictionary_encoding_max_ratio";
+pub const METRICS_EXPORTER_HOST: &str = "metrics_exporter_host";
+pub const METRICS_EXPORTER_PORT: &str = "metrics_exporter_port";
+pub const SPILL_DIRS: &str = "spill_dirs";
+pub const SPILL_DISK_LIMIT: &str = "spill_disk_limit";
+pub const DEBUG_NATIVE: &str = "debug_native";
+pub const DEBUG_VALIDATE_BATCHES: &str = "debug_validate_batches";
+pub const DEBUG_TAP_OPERATOR: &str = "debug_tap_operator";
+pub const DEBUG_TAP_DIR: &str = "debug_tap_dir";
+pub const RESOURCE_PROFILE: &str = "resource_profile";
+pub const RESOURCE_PROFILE_MAX_IO_REQUESTS: &str = "resource_profile_max_io_requests";
+pub const RESOURCE_PROFILE_DECODE_THREADS: &str = "resource_profile_decode_threads";
+pub const RESOURCE_PROFILE_MEMORY_FRACTION: &str = "resource_profile_memory_fraction";
+pub const OFFLOAD_PROVIDER: &str = "offload_provider";
+pub const OFFLOAD_OPERATORS: &str = "offload_operators";
+
+/// The name of the resource profile of the plans which set limits without a profile name
+pub const DEFAULT_RESOURCE_PROFILE: &str = "default";
+
+/// Keys prefixed with this are passed to the DataFusion session config as they are.
+pub const DATAFUSION_CONFIG_PREFIX: &str = "datafusion.";
+
+/// All the keys known by the native side, except for DataFusion configs.
+const KNOWN_KEYS: [&str; 30] = [
+    BATCH_SIZE,
+    PARTITION_INDEX,
+    USE_UNIFIED_MEMORY_MANAGER,
+    USE_UNIFIED_SHUFFLE_MEMORY,
+    MEMORY_LIMIT,
+    MEMORY_FRACTION,
+    SHUFFLE_CODEC,
+    IO_PARALLELISM,
+    COMPUTE_THREADS,
+    PIN_COMPUTE_THREADS,
+    PARTIAL_AGG_SKIP_ENABLED,
+    PARTIAL_AGG_SKIP_PROBE_ROWS,
+    PARTIAL_AGG_SKIP_RATIO_THRESHOLD,
+    DICTIONARY_ENCODING_ENABLED,
+    DICTIONARY_ENCODING_SAMPLE_ROWS,
+    DICTIONARY_ENCODING_MAX_RATIO,
+    METRICS_EXPORTER_HOST,
+    METRICS_EXPORTER_PORT,
+    SPILL_DIRS,
+    SPILL_DISK_LIMIT,
+    DEBUG_NATIVE,
+    DEBUG_VALIDATE_BATCHES,
+    DEBUG_TAP_OPERATOR,
+    DEBUG_TAP_DIR,
+    RESOURCE_PROFILE,
+    RESOURCE_PROFILE_MAX_IO_REQUESTS,
+    RESOURCE_PROFILE_DECODE_THREADS,
+    RESOURCE_PROFILE_MEMORY_FRACTION,
+    OFFLOAD_PROVIDER,
+    OFFLOAD_OPERATORS,
+];
+
+/// Codecs used to compress native shuffle data. The compressed data is framed like the Spark
+/// `CompressionCodec` of the same name, so that it can be read by the JVM.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompressionCodec {
+    Lz4,
+    Snappy,
+    /// Zstandard with the given compression level
+    Zstd(i32),
+}
+
+/// The default compression level of Zstandard, same as `spark.io.compression.zstd.level`.
+pub const DEFAULT_ZSTD_LEVEL: i32 = 1;
+
+impl CompressionCodec {
+    /// Returns the codec of the given name, where `zstd_level` is only used by Zstandard.
+    pub fn try_new(name: &str, zstd_level: i32) -> CometResult<Self> {
+        match name.parse::<CompressionCodec>() {
+            Ok(CompressionCodec::Zstd(_)) => {
+                if !zstd::compression_level_range().contains(&zstd_level) {
+                    return Err(CometError::Config(format!(
+                        "Invalid zstd compression level {}",
+                        zstd_level
+                    )));
+                }
+                Ok(CompressionCodec::Zstd(zstd_level))
+            }
+            Ok(codec) => Ok(codec),
+            Err(e) => Err(CometError::Config(e)),
+        }
+    }
+}
+
+impl FromStr for CompressionCodec {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "lz4" => Ok(CompressionCodec::Lz4),
+            "snappy" => Ok(CompressionCodec::Snappy),
+            "zstd" => Ok(CompressionCodec::Zstd(DEFAULT_ZSTD_LEVEL)),
+            other => Err(format!("unsupported codec '{}'", other)),
+        }
+    }
+}
+
+impl Display for CompressionCodec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CompressionCodec::Lz4 => write!(f, "lz4"),
+            CompressionCodec::Snappy => write!(f, "snappy"),
+            CompressionCodec::Zstd(_) => write!(f, "zstd"),
+        }
+    }
+}
+
+/// The resource caps of the native plans of a query, set per query by the JVM side, so that heavy
+/// queries can be throttled while others on the same executor stay responsive. The IO and thread
+/// caps are shared by all the native plans of the same profile name on the executor.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResourceProfile {
+    pub name: String,
+    /// The maximum number of IO requests, e.g., shuffle spills, in flight at the same time
+    pub max_io_requests: Option<usize>,
+    /// The maximum number of task threads executing native plans at the same time
+    pub decode_threads: Option<usize>,
+    /// Overrides `memory_fraction` of the native plans of the profile
+    pub memory_fraction: Option<f64>,
+}
+
+/// Configs of a native plan.
+#[derive(Debug, Clone)]
+pub struct NativeConfig {
+    /// The maximum number of rows of the batches produced by native operators
+    pub batch_size: usize,
+    /// The index of the RDD partition computed by the native plan. Like Spark, nondeterministic
+    /// expressions are seeded with it, so that task retries produce the same output.
+    pub partition_index: i32,
+    /// Whether to acquire memory from Spark unified memory manager. Otherwise the memory pool of
+    /// DataFusion is used, limited by `memory_limit` and `memory_fraction`.
+    pub use_unified_memory_manager: bool,
+    /// Whether native shuffle writers acquire the memory of their buffers from Spark task memory
+    /// manager, which asks them to spill when other consumers of the task need memory. Otherwise
+    /// they share the memory pool of the other native operators.
+    pub use_unified_shuffle_memory: bool,
+    /// The memory limit of the DataFusion memory pool in bytes
+    pub memory_limit: Option<usize>,
+    /// The fraction of `memory_limit` that native operators can use
+    pub memory_fraction: f64,
+    /// The codec used to compress native shuffle data
+    pub shuffle_codec: CompressionCodec,
+    /// The number of threads of the executor-wide IO runtime, which is separate from the compute
+    /// thread pool. Defaults to the number of CPU cores.
+    pub io_parallelism: Option<usize>,
+    /// The number of threads of the executor-wide compute thread pool. Defaults to the number
+    /// of CPU cores.
+    pub compute_threads: Option<usize>,
+    /// Whether to pin the threads of the compute thread pool to CPU cores
+    pub pin_compute_threads: bool,
+    /// Whether to skip partial aggregation when the grouping keys have high cardinality
+    pub partial_agg_skip_enabled: bool,
+    /// Number of input rows of a partial aggregation to probe the cardinality of grouping keys
+    pub partial_agg_skip_probe_rows: usize,
+    /// The minimum ratio of distinct grouping keys to probed rows to skip partial aggregation
+    pub partial_agg_skip_ratio_threshold: f64,
+    /// Whether to encode the low-cardinality string columns written by shuffles and held by the
+    /// build sides of hash joins as dictionaries
+    pub dictionary_encoding_enabled: bool,
+    /// Number of rows sampled to estimate the cardinality of a string column
+    pub dictionary_encoding_sample_rows: usize,
+    /// The maximum ratio of distinct values to sampled rows to encode a string column
+    pub dictionary_encoding_max_ratio: f64,
+    /// The address to bind the executor-level native metrics exporter to. Only used with the
+    /// `prometheus` feature.
+    pub metrics_exporter_host: String,
+    /// The port to serve executor-level native metrics on. Only used with the `prometheus`
+    /// feature.
+    pub metrics_exporter_port: Option<u16>,
+    /// The local directories of the Spark executor to create spill files in, separated by commas.
+    /// Defaults to the temporary directory of the OS.
+    pub spill_dirs: Vec<String>,
+    /// The maximum disk space in bytes that the spill files of the native shuffle writers of a
+    /// native plan can use
+    pub spill_disk_limit: Option<usize>,
+    /// Whether to enable additional debugging checks & messages
+    pub debug_native: bool,
+    /// Whether to validate the output batches of every native operator
+    pub debug_validate_batches: bool,
+    /// Name of the native operators whose output batches are mirrored to Arrow IPC files
+    pub debug_tap_operator: Option<String>,
+    /// The directory to write the debug tap files to. Defaults to the temporary directory.
+    pub debug_tap_dir: Option<String>,
+    /// The resource profile of the query, if it sets any cap
+    pub resource_profile: Option<ResourceProfile>,
+    /// The name of the registered operator provider to offload native operators to
+    pub offload_provider: Option<String>,
+    /// Name of the native operators to offload, e.g., `ProjectionExec`. All the operators are
+    /// offloaded if empty.
+    pub offload_operators: Vec<String>,
+    /// DataFusion session configs, sorted by key
+    pub datafusion_configs: Vec<(String, String)>,
+}
+
+impl Default for NativeConfig {
+    fn default() -> Self {
+        Self {
+            batch_size: 8192,
+            partition_index: 0,
+            use_unified_memory_manager: false,
+            use_unified_shuffle_memory: false,
+            memory_limit: None,
+            memory_fraction: 0.7,
+            shuffle_codec: CompressionCodec::Zstd(DEFAULT_ZSTD_LEVEL),
+            io_parallelism: None,
+            compute_threads: None,
+            pin_compute_threads: false,
+            partial_agg_skip_enabled: false,
+            partial_agg_skip_probe_rows: 100000,
+            partial_agg_skip_ratio_threshold: 0.8,
+            dictionary_encoding_enabled: false,
+            dictionary_encoding_sample_rows: 1024,
+            dictionary_encoding_max_ratio: 0.1,
+            metrics_exporter_host: "127.0.0.1".to_string(),
+            metrics_exporter_port: None,
+            spill_dirs: vec![],
+            spill_disk_limit: None,
+            debug_native: false,
+            debug_validate_batches: false,
+            debug_tap_operator: None,
+            debug_tap_dir: None,
+            resource_profile: None,
+            offload_provider: None,
+            offload_operators: vec![],
+            datafusion_configs: vec![],
+        }
+    }
+}
+
+impl NativeConfig {
+    /// Parses and validates the configs passed from the JVM side. Unknown keys are ignored with
+    /// a warning.
+    pub fn try_new(conf: &HashMap<String, String>) -> CometResult<Self> {
+        let default = Self::default();
+
+        for key in conf.keys() {
+            if !KNOWN_KEYS.contains(&key.as_str()) && !key.starts_with(DATAFUSION_CONFIG_PREFIX) {
+                warn!("Ignoring unknown native config '{}'", key);
+            }
+        }
+
+        let batch_size = parse::<usize>(conf, BATCH_SIZE)?.ok_or_else(|| {
+            CometError::Config(format!(
+                "Config '{}' is not specified from Comet JVM side",
+                BATCH_SIZE
+            ))
+        })?;
+        if batch_size == 0 {
+            return Err(invalid_value(BATCH_SIZE, batch_size, "must be positive"));
+        }
+
+        let memory_fraction =
+            parse::<f64>(conf, MEMORY_FRACTION)?.unwrap_or(default.memory_fraction);
+        if !(memory_fraction > 0.0 && memory_fraction <= 1.0) {
+            return Err(invalid_value(
+                MEMORY_FRACTION,
+                memory_fraction,
+                "must be in (0, 1]",
+            ));
+        }
+
+        let io_parallelism = parse::<usize>(conf, IO_PARALLELISM)?;
+        if io_parallelism == Some(0) {
+            return Err(invalid_value(IO_PARALLELISM, 0, "must be positive"));
+        }
+
+        let compute_threads = parse::<usize>(conf, COMPUTE_THREADS)?;
+        if compute_threads == Some(0) {
+            return Err(invalid_value(COMPUTE_THREADS, 0, "must be positive"));
+        }
+
+        let partial_agg_skip_probe_rows = parse::<usize>(conf, PARTIAL_AGG_SKIP_PROBE_ROWS)?
+            .unwrap_or(default.partial_agg_skip_probe_rows);
+        if partial_agg_skip_probe_rows == 0 {
+            return Err(invalid_value(
+                PARTIAL_AGG_SKIP_PROBE_ROWS,
+                0,
+                "must be positive",
+            ));
+        }
+
+        let partial_agg_skip_ratio_threshold =
+            parse::<f64>(conf, PARTIAL_AGG_SKIP_RATIO_THRESHOLD)?
+                .unwrap_or(default.partial_agg_skip_ratio_threshold);
+        if !(partial_agg_skip_ratio_threshold > 0.0 && partial_agg_skip_ratio_threshold <= 1.0) {
+            return Err(invalid_value(
+                PARTIAL_AGG_SKIP_RATIO_THRESHOLD,
+                partial_agg_skip_ratio_threshold,
+                "must be in (0, 1]",
+            ));
+        }
+
+        let dictionary_encoding_sample_rows =
+            parse::<usize>(conf, DICTIONARY_ENCODING_SAMPLE_ROWS)?
+                .unwrap_or(default.dictionary_encoding_sample_rows);
+        if dictionary_encoding_sample_rows == 0 {
+            return Err(invalid_value(
+                DICTIONARY_ENCODING_SAMPLE_ROWS,
+                0,
+                "must be positive",
+            ));
+        }
+
+        let dictionary_encoding_max_ratio = parse::<f64>(conf, DICTIONARY_ENCODING_MAX_RATIO)?
+            .unwrap_or(default.dictionary_encoding_max_ratio);
+        if !(dictionary_encoding_max_ratio > 0.0 && dictionary_encoding_max_ratio <= 1.0) {
+            return Err(invalid_value(
+                DICTIONARY_ENCODING_MAX_RATIO,
+                dictionary_encoding_max_ratio,
+                "must be in (0, 1]",
+            ));
+        }
+
+        let resource_profile = parse_resource_profile(conf)?;
+
+        let spill_dirs = parse_list(conf, SPILL_DIRS);
+
+        let mut datafusion_configs = conf
+            .iter()
+            .filter(|(key, _)| key.starts_with(DATAFUSION_CONFIG_PREFIX))
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect::<Vec<_>>();
+        datafusion_configs.sort();
+
+        Ok(Self {
+            batch_size,
+            partition_index: parse(conf, PARTITION_INDEX)?.unwrap_or(default.partition_index),
+            use_unified_memory_manager: parse(conf, USE_UNIFIED_MEMORY_MANAGER)?
+                .unwrap_or(default.use_unified_memory_manager),
+            use_unified_shuffle_memory: parse(conf, USE_UNIFIED_SHUFFLE_MEMORY)?
+                .unwrap_or(default.use_unified_shuffle_memory),
+            memory_limit: parse(conf, MEMORY_LIMIT)?,
+            memory_fraction,
+            shuffle_codec: parse(conf, SHUFFLE_CODEC)?.unwrap_or(default.shuffle_codec),
+            io_parallelism,
+            compute_threads,
+            pin_compute_threads: parse(conf, PIN_COMPUTE_THREADS)?
+                .unwrap_or(default.pin_compute_threads),
+            partial_agg_skip_enabled: parse(conf, PARTIAL_AGG_SKIP_ENABLED)?
+                .unwrap_or(default.partial_agg_skip_enabled),
+            partial_agg_skip_probe_rows,
+            partial_agg_skip_ratio_threshold,
+            dictionary_encoding_enabled: parse(conf, DICTIONARY_ENCODING_ENABLED)?
+                .unwrap_or(default.dictionary_encoding_enabled),
+            dictionary_encoding_sample_rows,
+            dictionary_encoding_max_ratio,
+            metrics_exporter_host: parse(conf, METRICS_EXPORTER_HOST)?
+                .unwrap_or(default.metrics_exporter_host),
+            metrics_exporter_port: parse(conf, METRICS_EXPORTER_PORT)?,
+            spill_dirs,
+            spill_disk_limit: parse(conf, SPILL_DISK_LIMIT)?,
+            debug_native: parse(conf, DEBUG_NATIVE)?.unwrap_or(default.debug_native),
+            debug_validate_batches: parse(conf, DEBUG_VALIDATE_BATCHES)?
+                .unwrap_or(default.debug_validate_batches),
+            debug_tap_operator: parse(conf, DEBUG_TAP_OPERATOR)?,
+            debug_tap_dir: parse(conf, DEBUG_TAP_DIR)?,
+            resource_profile,
+            offload_provider: parse(conf, OFFLOAD_PROVIDER)?,
+            offload_operators: parse_list(conf, OFFLOAD_OPERATORS),
+            datafusion_configs,
+        })
+    }
+
+    /// Returns the effective values of all the configs which are set or have a default value.
+    pub fn entries(&self) -> Vec<(String, String)> {
+        let mut entries = vec![
+            (BATCH_SIZE, Some(self.batch_size.to_string())),
+            (PARTITION_INDEX, Some(self.partition_index.to_string())),
+            (
+                USE_UNIFIED_MEMORY_MANAGER,
+                Some(self.use_unified_memory_manager.to_string()),
+            ),
+            (
+                USE_UNIFIED_SHUFFLE_MEMORY,
+                Some(self.use_unified_shuffle_memory.to_string()),
+            ),
+            (MEMORY_LIMIT, self.memory_limit.map(|v| v.to_string())),
+            (MEMORY_FRACTION, Some(self.memory_fraction.to_string())),
+            (SHUFFLE_CODEC, Some(self.shuffle_codec.to_string())),
+            (IO_PARALLELISM, self.io_parallelism.map(|v| v.to_string())),
+            (COMPUTE_THREADS, self.compute_threads.map(|v| v.to_string())),
+            (
+                PIN_COMPUTE_THREADS,
+                Some(self.pin_compute_threads.to_string()),
+            ),
+            (
+                PARTIAL_AGG_SKIP_ENABLED,
+                Some(self.partial_agg_skip_enabled.to_string()),
+            ),
+            (
+                PARTIAL_AGG_SKIP_PROBE_ROWS,
+                Some(self.partial_agg_skip_probe_rows.to_string()),
+            ),
+            (
+                PARTIAL_AGG_SKIP_RATIO_THRESHOLD,
+                Some(self.partial_agg_skip_ratio_threshold.to_string()),
+            ),
+            (
+                DICTIONARY_ENCODING_ENABLED,
+                Some(self.dictionary_encoding_enabled.to_string()),
+            ),
+            (
+                DICTIONARY_ENCODING_SAMPLE_ROWS,
+                Some(self.dictionary_encoding_sample_rows.to_string()),
+            ),
+            (
+                DICTIONARY_ENCODING_MAX_RATIO,
+                Some(self.dictionary_encoding_max_ratio.to_string()),
+            ),
+            (
+                METRICS_EXPORTER_HOST,
+                Some(self.metrics_exporter_host.clone()),
+            ),
+            (
+                METRICS_EXPORTER_PORT,
+                self.metrics_exporter_port.map(|v| v.to_string()),
+            ),
+            (
+                SPILL_DIRS,
+                Some(self.spill_dirs.join(",")).filter(|dirs| !dirs.is_empty()),
+            ),
+            (
+                SPILL_DISK_LIMIT,
+                self.spill_disk_limit.map(|v| v.to_string()),
+            ),
+            (DEBUG_NATIVE, Some(self.debug_native.to_string())),
+            (
+                DEBUG_VALIDATE_BATCHES,
+                Some(self.debug_validate_batches.to_string()),
+            ),
+            (DEBUG_TAP_OPERATOR, self.debug_tap_operator.clone()),
+            (DEBUG_TAP_DIR, self.debug_tap_dir.clone()),
+            (
+                RESOURCE_PROFILE,
+                self.resource_profile.as_ref().map(|p| p.name.clone()),
+            ),
+            (
+                RESOURCE_PROFILE_MAX_IO_REQUESTS,
+                self.resource_profile
+                    .as_ref()
+                    .and_then(|p| p.max_io_requests)
+                    .map(|v| v.to_string()),
+            ),
+            (
+                RESOURCE_PROFILE_DECODE_THREADS,
+                self.resource_profile
+                    .as_ref()
+                    .and_then(|p| p.decode_threads)
+                    .map(|v| v.to_string()),
+            ),
+            (
+                RESOURCE_PROFILE_MEMORY_FRACTION,
+                self.resource_profile
+                    .as_ref()
+                    .and_then(|p| p.memory_fraction)
+                    .map(|v| v.to_string()),
+            ),
+            (OFFLOAD_PROVIDER, self.offload_provider.clone()),
+            (
+                OFFLOAD_OPERATORS,
+                Some(self.offload_operators.join(",")).filter(|ops| !ops.is_empty()),
+            ),
+        ]
+        .into_iter()
+        .filter_map(|(key, value)| value.map(|value| (key.to_string(), value)))
+        .collect::<Vec<_>>();
+        entries.extend(self.datafusion_configs.iter().cloned());
+        entries
+    }
+
+    /// Returns the fraction of `memory_limit` that the native operators of this plan can use,
+    /// which may be capped by the resource profile.
+    pub fn effective_memory_fraction(&self) -> f64 {
+        self.resource_profile
+            .as_ref()
+            .and_then(|p| p.memory_fraction)
+            .unwrap_or(self.memory_fraction)
+    }
+
+    /// Returns the effective value of the given config, or `None` if it is neither set nor has a
+    /// default value.
+    pub fn get(&self, key: &str) -> Option<String> {
+        self.entries()
+            .into_iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, value)| value)
+    }
+}
+
+/// Parses the resource profile, which is only set if any of its caps is set.
+fn parse_resource_profile(conf: &HashMap<String, String>) -> CometResult<Option<ResourceProfile>> {
+    let max_io_requests = parse::<usize>(conf, RESOURCE_PROFILE_MAX_IO_REQUESTS)?;
+    if max_io_requests == Some(0) {
+        return Err(invalid_value(
+            RESOURCE_PROFILE_MAX_IO_REQUESTS,
+            0,
+            "must be positive",
+        ));
+    }
+
+    let decode_threads = parse::<usize>(conf, RESOURCE_PROFILE_DECODE_THREADS)?;
+    if decode_threads == Some(0) {
+        return Err(invalid_value(
+            RESOURCE_PROFILE_DECODE_THREADS,
+            0,
+            "must be positive",
+        ));
+    }
+
+    let memory_fraction = parse::<f64>(conf, RESOURCE_PROFILE_MEMORY_FRACTION)?;
+    if let Some(fraction) = memory_fraction.filter(|f| !(*f > 0.0 && *f <= 1.0)) {
+        return Err(invalid_value(
+            RESOURCE_PROFILE_MEMORY_FRACTION,
+            fraction,
+            "must be in (0, 1]",
+        ));
+    }
+
+    if max_io_requests.is_none() && decode_threads.is_none() && memory_fraction.is_none() {
+        return Ok(None);
+    }
+    Ok(Some(ResourceProfile {
+        name: parse(conf, RESOURCE_PROFILE)?
+            .unwrap_or_else(|| DEFAULT_RESOURCE_PROFILE.to_string()),
+        max_io_requests,
+        decode_threads,
+        memory_fraction,
+    }))
+}
+
+fn parse<T>(conf: &HashMap<String, String>, key: &str) -> CometResult<Option<T>>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    conf.get(key)
+        .map(|value| {
+            value
+                .trim()
+                .parse::<T>()
+                .map_err(|e| invalid_value(key, value, e))
+        })
+        .transpose()
+}
+
+/// Parses the comma separated values of the given config, skipping empty ones.
+fn parse_list(conf: &HashMap<String, String>, key: &str) -> Vec<String> {
+    conf.get(key)
+        .map(|values| {
+            values
+                .split(',')
+                .map(str::trim)
+                .filter(|value| !value.is_empty())
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn invalid_value(key: &str, value: impl Display, reason: impl Display) -> CometError {
+    CometError::Config(format!(
+        "Invalid value '{}' of config '{}': {}",
+        value, key, reason
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    fn conf(entries: &[(&str, &str)]) -> HashMap<String, String> {
+        entries
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_native_config() {
+        let config = NativeConfig::try_new(&conf(&[
+            (BATCH_SIZE, "1024"),
+            (MEMORY_LIMIT, "1000000"),
+            (DEBUG_NATIVE, "true"),
+            (SPILL_DIRS, "/tmp/a, /tmp/b"),
+            (OFFLOAD_OPERATORS, "ProjectionExec,,AggregateExec"),
+            ("datafusion.sql_parser.parse_float_as_decimal", "true"),
+            ("unknown_key", "ignored"),
+        ]))
+        .unwrap();
+
+        assert_eq!(config.batch_size, 1024);
+        assert_eq!(config.memory_limit, Some(1000000));
+        assert_eq!(config.memory_fraction, 0.7);
+        assert!(config.debug_native);
+        assert!(!config.debug_validate_batches);
+        assert_eq!(config.get(SHUFFLE_CODEC), Some("zstd".to_string()));
+        assert_eq!(config.get(IO_PARALLELISM), None);
+        assert_eq!(config.spill_dirs, vec!["/tmp/a", "/tmp/b"]);
+        assert_eq!(config.get(SPILL_DISK_LIMIT), None);
+        assert_eq!(config.metrics_exporter_host, "127.0.0.1");
+        assert_eq!(config.offload_provider, None);
+        assert_eq!(
+            config.get(OFFLOAD_OPERATORS),
+            Some("ProjectionExec,AggregateExec".to_string())
+        );
+        assert_eq!(
+            config.get("datafusion.sql_parser.parse_float_as_decimal"),
+            Some("true".to_string())
+        );
+        assert_eq!(config.get("unknown_key"), None);
+    }
+
+    #[test]
+    fn test_invalid_native_config() {
+        assert!(NativeConfig::try_new(&conf(&[])).is_err());
+        assert!(NativeConfig::try_new(&conf(&[(BATCH_SIZE, "0")])).is_err());
+        assert!(NativeConfig::try_new(&conf(&[(BATCH_SIZE, "abc")])).is_err());
+        assert!(
+            NativeConfig::try_new(&conf(&[(BATCH_SIZE, "1"), (MEMORY_FRACTION, "1.5")])).is_err()
+        );
+        assert!(
+            NativeConfig::try_new(&conf(&[(BATCH_SIZE, "1"), (SHUFFLE_CODEC, "lzo")])).is_err()
+        );
+        assert!(NativeConfig::try_new(&conf(&[(BATCH_SIZE, "1"), (IO_PARALLELISM, "0")])).is_err());
+        assert!(NativeConfig::try_new(&conf(&[
+            (BATCH_SIZE, "1"),
+            (PARTIAL_AGG_SKIP_RATIO_THRESHOLD, "0")
+        ]))
+        .is_err());
+        assert!(NativeConfig::try_new(&conf(&[
+            (BATCH_SIZE, "1"),
+            (DICTIONARY_ENCODING_MAX_RATIO, "2")
+        ]))
+        .is_err());
+        assert!(NativeConfig::try_new(&conf(&[
+            (BATCH_SIZE, "1"),
+            (RESOURCE_PROFILE_DECODE_THREADS, "0")
+        ]))
+        .is_err());
+    }
+
+    #[test]
+    fn test_resource_profile() {
+        let config =
+            NativeConfig::try_new(&conf(&[(BATCH_SIZE, "1"), (RESOURCE_PROFILE, "etl")])).unwrap();
+        // A profile without caps is not set
+        assert_eq!(config.resource_profile, None);
+        assert_eq!(config.effective_memory_fraction(), 0.7);
+
+        let config = NativeConfig::try_new(&conf(&[
+            (BATCH_SIZE, "1"),
+            (RESOURCE_PROFILE_MAX_IO_REQUESTS, "4"),
+            (RESOURCE_PROFILE_MEMORY_FRACTION, "0.2"),
+        ]))
+        .unwrap();
+        assert_eq!(
+            config.resource_profile,
+            Some(ResourceProfile {
+                name: DEFAULT_RESOURCE_PROFILE.to_string(),
+                max_io_requests: Some(4),
+                decode_threads: None,
+                memory_fraction: Some(0.2),
+            })
+        );
+        assert_eq!(config.effective_memory_fraction(), 0.2);
+        assert_eq!(config.get(RESOURCE_PROFILE), Some("default".to_string()));
+    }
+}
@@ -0,0 +1,248 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Type coercion of decimal arithmetic like Spark's `DecimalPrecision` rule.
+//!
+//! The native planner uses it to derive the result type of a decimal arithmetic expression whose
+//! return type is not set by the JVM side, and to widen its integral operand when the other one
+//! is a decimal, so that the kernels always see two decimals.
+
+use std::{cmp, sync::Arc};
+
+use arrow_schema::{DataType, Schema, DECIMAL128_MAX_PRECISION, DECIMAL128_MAX_SCALE};
+use datafusion::physical_expr::{expressions::Literal, PhysicalExpr};
+use datafusion_common::{Result, ScalarValue};
+
+use crate::execution::datafusion::expressions::cast::{Cast, EvalMode};
+
+/// Spark's `DecimalType.MINIMUM_ADJUSTED_SCALE`, the minimum scale kept when the precision of a
+/// result exceeds the maximum precision.
+const MINIMUM_ADJUSTED_SCALE: i8 = 6;
+
+/// The decimal arithmetic operators whose result type depends on the types of the operands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum DecimalOp {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Remainder,
+}
+
+/// Returns the result type of `Decimal(p1, s1) op Decimal(p2, s2)`, like `resultDecimalType` of
+/// Spark's arithmetic expressions. If `allow_precision_loss`, i.e.,
+/// `spark.sql.decimalOperations.allowPrecisionLoss`, the scale is reduced to fit the integral
+/// digits when the precision exceeds 38, otherwise the precision and the scale are truncated.
+pub(crate) fn decimal_result_type(
+    op: DecimalOp,
+    (p1, s1): (u8, i8),
+    (p2, s2): (u8, i8),
+    allow_precision_loss: bool,
+) -> DataType {
+    let (p1, s1, p2, s2) = (p1 as i32, s1 as i32, p2 as i32, s2 as i32);
+    let (precision, scale) = match op {
+        DecimalOp::Add | DecimalOp::Subtract => {
+            let scale = cmp::max(s1, s2);
+            (cmp::max(p1 - s1, p2 - s2) + scale + 1, scale)
+        }
+        DecimalOp::Multiply => (p1 + p2 + 1, s1 + s2),
+        DecimalOp::Divide if allow_precision_loss => {
+            let scale = cmp::max(MINIMUM_ADJUSTED_SCALE as i32, s1 + p2 + 1);
+            (p1 - s1 + s2 + scale, scale)
+        }
+        DecimalOp::Divide => {
+            let max_scale = DECIMAL128_MAX_SCALE as i32;
+            let mut int_digits = cmp::min(max_scale, p1 - s1 + s2);
+            let mut dec_digits = cmp::min(
+                max_scale,
+                cmp::max(MINIMUM_ADJUSTED_SCALE as i32, s1 + p2 + 1),
+            );
+            let diff = int_digits + dec_digits - max_scale;
+            if diff > 0 {
+                dec_digits -= diff / 2 + 1;
+                int_digits = max_scale - dec_digits;
+            }
+            (int_digits + dec_digits, dec_digits)
+        }
+        DecimalOp::Remainder => {
+            let scale = cmp::max(s1, s2);
+            (cmp::min(p1 - s1, p2 - s2) + scale, scale)
+        }
+    };
+    if allow_precision_loss {
+        adjust_precision_scale(precision, scale)
+    } else {
+        bounded(precision, scale)
+    }
+}
+
+/// Spark's `DecimalType.adjustPrecisionScale`, which keeps the integral digits of a result whose
+/// precision exceeds 38 at the cost of its scale, down to 6 digits.
+fn adjust_precision_scale(precision: i32, scale: i32) -> DataType {
+    let max_precision = DECIMAL128_MAX_PRECISION as i32;
+    if precision <= max_precision {
+        DataType::Decimal128(precision as u8, scale as i8)
+    } else if scale < 0 {
+        DataType::Decimal128(DECIMAL128_MAX_PRECISION, scale as i8)
+    } else {
+        let int_digits = precision - scale;
+        let min_scale = cmp::min(scale, MINIMUM_ADJUSTED_SCALE as i32);
+        let scale = cmp::max(max_precision - int_digits, min_scale);
+        DataType::Decimal128(DECIMAL128_MAX_PRECISION, scale as i8)
+    }
+}
+
+/// Spark's `DecimalType.bounded`.
+fn bounded(precision: i32, scale: i32) -> DataType {
+    DataType::Decimal128(
+        cmp::min(precision, DECIMAL128_MAX_PRECISION as i32) as u8,
+        cmp::min(scale, DECIMAL128_MAX_SCALE as i32) as i8,
+    )
+}
+
+/// Returns the decimal type an integral operand is widened to, if `expr` is one. Like Spark's
+/// `DecimalPrecision.nondecimalAndDecimal`, a literal takes the minimum precision of its value,
+/// e.g., `Decimal(1, 0)` for `5`, so that `decimal_col * 5` doesn't needlessly lose scale.
+fn integral_decimal_type(
+    expr: &Arc<dyn PhysicalExpr>,
+    input_schema: &Schema,
+) -> Result<Option<(u8, i8)>> {
+    if let Some(literal) = expr.as_any().downcast_ref::<Literal>() {
+        let value = match literal.value() {
+            ScalarValue::Int16(Some(v)) => Some(*v as i64),
+            ScalarValue::Int32(Some(v)) => Some(*v as i64),
+            ScalarValue::Int64(Some(v)) => Some(*v),
+            _ => None,
+        };
+        if let Some(v) = value {
+            let digits = v.unsigned_abs().checked_ilog10().map_or(1, |d| d + 1);
+            return Ok(Some((digits as u8, 0)));
+        }
+    }
+    let precision = match expr.data_type(input_schema)? {
+        DataType::Int8 => 3,
+        DataType::Int16 => 5,
+        DataType::Int32 => 10,
+        DataType::Int64 => 20,
+        _ => return Ok(None),
+    };
+    Ok(Some((precision, 0)))
+}
+
+/// Casts the integral operand of a binary arithmetic expression to a decimal if the other operand
+/// is a decimal. Other operands are returned as they are.
+pub(crate) fn widen_decimal_operands(
+    left: Arc<dyn PhysicalExpr>,
+    right: Arc<dyn PhysicalExpr>,
+    input_schema: &Schema,
+) -> Result<(Arc<dyn PhysicalExpr>, Arc<dyn PhysicalExpr>)> {
+    let widen = |expr: Arc<dyn PhysicalExpr>| -> Result<Arc<dyn PhysicalExpr>> {
+        Ok(match integral_decimal_type(&expr, input_schema)? {
+            Some((precision, scale)) => Arc::new(Cast::new_without_timezone(
+                expr,
+                DataType::Decimal128(precision, scale),
+                EvalMode::Legacy,
+            )),
+            None => expr,
+        })
+    };
+    match (
+        left.data_type(input_schema)?,
+        right.data_type(input_schema)?,
+    ) {
+        (DataType::Decimal128(_, _), DataType::Decimal128(_, _)) => Ok((left, right)),
+        (DataType::Decimal128(_, _), _) => Ok((left, widen(right)?)),
+        (_, DataType::Decimal128(_, _)) => Ok((widen(left)?, right)),
+        _ => Ok((left, right)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow_schema::Field;
+    use datafusion::physical_expr::expressions::Column;
+
+    use super::*;
+
+    #[test]
+    fn test_decimal_result_types() {
+        // The expected types are the ones of Spark 3.4
+        let cases = [
+            (DecimalOp::Add, (10, 2), (5, 3), true, (12, 3)),
+            (DecimalOp::Subtract, (38, 10), (38, 0), true, (38, 6)),
+            (DecimalOp::Subtract, (38, 10), (38, 0), false, (38, 10)),
+            (DecimalOp::Multiply, (10, 2), (5, 3), true, (16, 5)),
+            (DecimalOp::Multiply, (38, 18), (38, 18), true, (38, 6)),
+            (DecimalOp::Multiply, (38, 18), (38, 18), false, (38, 36)),
+            (DecimalOp::Divide, (5, 2), (3, 2), true, (11, 6)),
+            (DecimalOp::Divide, (38, 18), (38, 18), true, (38, 6)),
+            (DecimalOp::Divide, (38, 18), (38, 18), false, (38, 18)),
+            (DecimalOp::Remainder, (10, 2), (5, 3), true, (5, 3)),
+        ];
+        for (op, left, right, allow_precision_loss, (p, s)) in cases {
+            assert_eq!(
+                decimal_result_type(op, left, right, allow_precision_loss),
+                DataType::Decimal128(p, s),
+                "{:?} of {:?} and {:?}",
+                op,
+                left,
+                right
+            );
+        }
+    }
+
+    #[test]
+    fn test_widen_integral_operands() -> Result<()> {
+        let schema = Schema::new(vec![
+            Field::new("d", DataType::Decimal128(10, 2), true),
+            Field::new("i", DataType::Int32, true),
+            Field::new("l", DataType::Int64, true),
+        ]);
+        let column = |name: &str, index: usize| -> Arc<dyn PhysicalExpr> {
+            Arc::new(Column::new(name, index))
+        };
+        let literal =
+            |value: ScalarValue| -> Arc<dyn PhysicalExpr> { Arc::new(Literal::new(value)) };
+        let widened_types = |left, right| -> Result<(DataType, DataType)> {
+            let (left, right) = widen_decimal_operands(left, right, &schema)?;
+            Ok((left.data_type(&schema)?, right.data_type(&schema)?))
+        };
+
+        assert_eq!(
+            widened_types(column("d", 0), column("i", 1))?,
+            (DataType::Decimal128(10, 2), DataType::Decimal128(10, 0))
+        );
+        assert_eq!(
+            widened_types(column("l", 2), column("d", 0))?,
+            (DataType::Decimal128(20, 0), DataType::Decimal128(10, 2))
+        );
+        assert_eq!(
+            widened_types(literal(ScalarValue::Int32(Some(-123))), column("d", 0))?,
+            (DataType::Decimal128(3, 0), DataType::Decimal128(10, 2))
+        );
+        assert_eq!(
+            widened_types(column("d", 0), literal(ScalarValue::Int64(Some(0))))?,
+            (DataType::Decimal128(10, 2), DataType::Decimal128(1, 0))
+        );
+        // Non-decimal arithmetic is left as it is
+        assert_eq!(
+            widened_types(column("i", 1), column("l", 2))?,
+            (DataType::Int32, DataType::Int64)
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,536 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use std::{any::Any, sync::Arc};
+
+use arrow::compute::cast;
+use arrow_array::{cast::AsArray, types::Float64Type, ArrayRef, BinaryArray, Float64Array};
+use arrow_schema::{DataType, Field};
+use datafusion::logical_expr::Accumulator;
+use datafusion_common::{
+    downcast_value, utils::array_into_list_array, DataFusionError, Result, ScalarValue,
+};
+use datafusion_physical_expr::{expressions::format_state_name, AggregateExpr, PhysicalExpr};
+
+use crate::execution::datafusion::expressions::utils::down_cast_any_ref;
+
+/// The compress threshold of Spark `QuantileSummaries`
+const DEFAULT_COMPRESS_THRESHOLD: i32 = 10000;
+
+/// The size of the buffer of inserted values in Spark `QuantileSummaries`
+const DEFAULT_HEAD_SIZE: usize = 50000;
+
+/// APPROX_PERCENTILE aggregate expression, i.e., Spark `ApproximatePercentile`.
+///
+/// The percentiles are computed by the same Greenwald-Khanna summaries as Spark
+/// `QuantileSummaries`, and the intermediate state is serialized in the same format as the
+/// `PercentileDigest` aggregation buffer of Spark, so the partial and final aggregations can be
+/// executed by either Comet or Spark.
+#[derive(Debug)]
+pub struct ApproxPercentile {
+    name: String,
+    expr: Arc<dyn PhysicalExpr>,
+    /// The type of the input values, which is also the type of the percentiles
+    data_type: DataType,
+    percentages: Vec<f64>,
+    /// Whether the result is an array of percentiles, i.e., the percentage is an array
+    return_array: bool,
+    /// The relative error of the summaries, i.e., `1 / accuracy`
+    relative_error: f64,
+}
+
+impl ApproxPercentile {
+    pub fn new(
+        expr: Arc<dyn PhysicalExpr>,
+        name: impl Into<String>,
+        data_type: DataType,
+        percentages: Vec<f64>,
+        return_array: bool,
+        relative_error: f64,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            expr,
+            data_type,
+            percentages,
+            return_array,
+            relative_error,
+        }
+    }
+}
+
+impl AggregateExpr for ApproxPercentile {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn field(&self) -> Result<Field> {
+        if self.return_array {
+            Ok(Field::new_list(
+                &self.name,
+                Field::new("item", self.data_type.clone(), true),
+                true,
+            ))
+        } else {
+            Ok(Field::new(&self.name, self.data_type.clone(), true))
+        }
+    }
+
+    fn create_accumulator(&self) -> Result<Box<dyn Accumulator>> {
+        Ok(Box::new(ApproxPercentileAccumulator {
+            summaries: QuantileSummaries::new(self.relative_error),
+            data_type: self.data_type.clone(),
+            percentages: self.percentages.clone(),
+            return_array: self.return_array,
+        }))
+    }
+
+    fn state_fields(&self) -> Result<Vec<Field>> {
+        Ok(vec![Field::new(
+            format_state_name(&self.name, "buffer"),
+            DataType::Binary,
+            true,
+        )])
+    }
+
+    fn expressions(&self) -> Vec<Arc<dyn PhysicalExpr>> {
+        vec![self.expr.clone()]
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl PartialEq<dyn Any> for ApproxPercentile {
+    fn eq(&self, other: &dyn Any) -> bool {
+        down_cast_any_ref(other)
+            .downcast_ref::<Self>()
+            .map(|x| {
+                self.name == x.name
+                    && self.data_type == x.data_type
+                    && self.percentages == x.percentages
+                    && self.return_array == x.return_array
+                    && self.relative_error == x.relative_error
+                    && self.expr.eq(&x.expr)
+            })
+            .unwrap_or(false)
+    }
+}
+
+#[derive(Debug)]
+struct ApproxPercentileAccumulator {
+    summaries: QuantileSummaries,
+    data_type: DataType,
+    percentages: Vec<f64>,
+    return_array: bool,
+}
+
+impl Accumulator for ApproxPercentileAccumulator {
+    fn update_batch(&mut self, values: &[ArrayRef]) -> Result<()> {
+        // Like Spark, the values are summarized as doubles
+        let values = cast(&values[0], &DataType::Float64)?;
+        for value in values.as_primitive::<Float64Type>().iter().flatten() {
+            self.summaries.insert(value);
+        }
+        Ok(())
+    }
+
+    fn merge_batch(&mut self, states: &[ArrayRef]) -> Result<()> {
+        let buffers = downcast_value!(states[0], BinaryArray);
+        for bytes in buffers.iter().flatten() {
+            self.summaries.merge(QuantileSummaries::deserialize(bytes)?);
+        }
+        Ok(())
+    }
+
+    fn state(&mut self) -> Result<Vec<ScalarValue>> {
+        self.summaries.compress();
+        Ok(vec![ScalarValue::Binary(Some(self.summaries.serialize()))])
+    }
+
+    fn evaluate(&mut self) -> Result<ScalarValue> {
+        self.summaries.compress();
+        let percentiles = match self.summaries.query(&self.percentages) {
+            Some(percentiles) => percentiles,
+            None if self.return_array => {
+                return ScalarValue::try_from(&DataType::new_list(self.data_type.clone(), true))
+            }
+            None => return ScalarValue::try_from(&self.data_type),
+        };
+
+        // Spark converts the double percentiles to the input type, e.g., truncates them to longs
+        let percentiles = cast(&Float64Array::from(percentiles), &self.data_type)?;
+        if self.return_array {
+            Ok(ScalarValue::List(Arc::new(array_into_list_array(
+                percentiles,
+            ))))
+        } else {
+            ScalarValue::try_from_array(&percentiles, 0)
+        }
+    }
+
+    fn size(&self) -> usize {
+        std::mem::size_of_val(self)
+            + self.summaries.sampled.capacity() * std::mem::size_of::<Stats>()
+            + self.summaries.head_sampled.capacity() * std::mem::size_of::<f64>()
+            + self.percentages.capacity() * std::mem::size_of::<f64>()
+    }
+}
+
+/// A sample of [`QuantileSummaries`]. `g` is the difference between the minimum ranks of this
+/// sample and the previous one, and `delta` is the difference between the maximum and minimum
+/// ranks of this sample.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Stats {
+    value: f64,
+    g: i64,
+    delta: i64,
+}
+
+/// The Greenwald-Khanna summaries of Spark `QuantileSummaries`. The algorithm is followed step
+/// by step, so the summaries and the percentiles are the same as Spark's.
+#[derive(Debug)]
+struct QuantileSummaries {
+    compress_threshold: i32,
+    relative_error: f64,
+    sampled: Vec<Stats>,
+    count: i64,
+    compressed: bool,
+    /// The inserted values which are not added into `sampled` yet
+    head_sampled: Vec<f64>,
+}
+
+impl QuantileSummaries {
+    fn new(relative_error: f64) -> Self {
+        Self {
+            compress_threshold: DEFAULT_COMPRESS_THRESHOLD,
+            relative_error,
+            sampled: vec![],
+            count: 0,
+            compressed: true,
+            head_sampled: vec![],
+        }
+    }
+
+    fn insert(&mut self, value: f64) {
+        self.head_sampled.push(value);
+        self.compressed = false;
+        if self.head_sampled.len() >= DEFAULT_HEAD_SIZE {
+            self.insert_head_buffer();
+            if self.sampled.len() >= self.compress_threshold as usize {
+                self.compress();
+            }
+        }
+    }
+
+    fn insert_head_buffer(&mut self) {
+        if self.head_sampled.is_empty() {
+            return;
+        }
+        let mut sorted = std::mem::take(&mut self.head_sampled);
+        sorted.sort_by(f64::total_cmp);
+
+        let mut new_samples = Vec::with_capacity(self.sampled.len() + sorted.len());
+        let mut sample_idx = 0;
+        for (ops_idx, &value) in sorted.iter().enumerate() {
+            // Adds all the samples before the next value
+            while sample_idx < self.sampled.len() && self.sampled[sample_idx].value <= value {
+                new_samples.push(self.sampled[sample_idx]);
+                sample_idx += 1;
+            }
+
+            self.count += 1;
+            // The first and the last samples are exact
+            let delta = if new_samples.is_empty()
+                || (sample_idx == self.sampled.len() && ops_idx == sorted.len() - 1)
+            {
+                0
+            } else {
+                (2.0 * self.relative_error * self.count as f64).floor() as i64
+            };
+            new_samples.push(Stats { value, g: 1, delta });
+        }
+        new_samples.extend_from_slice(&self.sampled[sample_idx..]);
+
+        self.sampled = new_samples;
+        self.compressed = false;
+    }
+
+    fn compress(&mut self) {
+        if self.compressed {
+            return;
+        }
+        self.insert_head_buffer();
+        let merge_threshold = 2.0 * self.relative_error * self.count as f64;
+        self.sampled = compress_samples(&self.sampled, merge_threshold);
+        self.compressed = true;
+    }
+
+    /// Merges the compressed summaries `other` into this one.
+    fn merge(&mut self, other: QuantileSummaries) {
+        self.compress();
+        if other.count == 0 {
+            return;
+        }
+        if self.count == 0 {
+            *self = other;
+            return;
+        }
+
+        // Samples interleaving the other side suffer from the lack of precision of it
+        let additional_self_delta =
+            (2.0 * other.relative_error * other.count as f64).floor() as i64;
+        let additional_other_delta = (2.0 * self.relative_error * self.count as f64).floor() as i64;
+
+        let mut merged = Vec::with_capacity(self.sampled.len() + other.sampled.len());
+        let (mut self_idx, mut other_idx) = (0, 0);
+        while self_idx < self.sampled.len() && other_idx < other.sampled.len() {
+            let self_sample = self.sampled[self_idx];
+            let other_sample = other.sampled[other_idx];
+            let (mut next, additional_delta) = if self_sample.value < other_sample.value {
+                self_idx += 1;
+                let delta = if other_idx > 0 {
+                    additional_self_delta
+                } else {
+                    0
+                };
+                (self_sample, delta)
+            } else {
+                other_idx += 1;
+                let delta = if self_idx > 0 {
+                    additional_other_delta
+                } else {
+                    0
+                };
+                (other_sample, delta)
+            };
+            next.delta += additional_delta;
+            merged.push(next);
+        }
+        merged.extend_from_slice(&self.sampled[self_idx..]);
+        merged.extend_from_slice(&other.sampled[other_idx..]);
+
+        self.relative_error = self.relative_error.max(other.relative_error);
+        self.count += other.count;
+        self.compress_threshold = other.compress_threshold;
+        self.sampled = compress_samples(&merged, 2.0 * self.relative_error * self.count as f64);
+    }
+
+    /// Returns the approximate percentiles of the compressed summaries, or `None` if there is no
+    /// value.
+    fn query(&self, percentages: &[f64]) -> Option<Vec<f64>> {
+        if self.sampled.is_empty() {
+            return None;
+        }
+        let target_error = (self
+            .sampled
+            .iter()
+            .map(|s| s.delta + s.g)
+            .max()
+            .unwrap_or(i64::MIN)
+            / 2) as f64;
+
+        let mut index = 0;
+        let mut min_rank = self.sampled[0].g;
+        let mut result = vec![0.0; percentages.len()];
+        let mut sorted = percentages.iter().enumerate().collect::<Vec<_>>();
+        sorted.sort_by(|a, b| a.1.total_cmp(b.1));
+
+        for (pos, &percentage) in sorted {
+            result[pos] = if percentage <= self.relative_error {
+                self.sampled[0].value
+            } else if percentage >= 1.0 - self.relative_error {
+                self.sampled[self.sampled.len() - 1].value
+            } else {
+                let (new_index, new_min_rank, value) =
+                    self.find_approx_quantile(index, min_rank, target_error, percentage);
+                index = new_index;
+                min_rank = new_min_rank;
+                value
+            };
+        }
+        Some(result)
+    }
+
+    fn find_approx_quantile(
+        &self,
+        index: usize,
+        min_rank_at_index: i64,
+        target_error: f64,
+        percentage: f64,
+    ) -> (usize, i64, f64) {
+        let rank = (percentage * self.count as f64).ceil() as i64 as f64;
+        let mut min_rank = min_rank_at_index;
+        for i in index..self.sampled.len() - 1 {
+            let sample = self.sampled[i];
+            let max_rank = min_rank + sample.delta;
+            if max_rank as f64 - target_error <= rank && rank <= min_rank as f64 + target_error {
+                return (i, min_rank, sample.value);
+            }
+            min_rank += self.sampled[i + 1].g;
+        }
+        (
+            self.sampled.len() - 1,
+            0,
+            self.sampled[self.sampled.len() - 1].value,
+        )
+    }
+
+    /// Serializes the compressed summaries like Spark `PercentileDigestSerializer`, i.e., the
+    /// compress threshold, relative error, count and samples in big-endian.
+    fn serialize(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(24 + self.sampled.len() * 24);
+        bytes.extend_from_slice(&self.compress_threshold.to_be_bytes());
+        bytes.extend_from_slice(&self.relative_error.to_be_bytes());
+        bytes.extend_from_slice(&self.count.to_be_bytes());
+        bytes.extend_from_slice(&(self.sampled.len() as i32).to_be_bytes());
+        for sample in &self.sampled {
+            bytes.extend_from_slice(&sample.value.to_be_bytes());
+            bytes.extend_from_slice(&sample.g.to_be_bytes());
+            bytes.extend_from_slice(&sample.delta.to_be_bytes());
+        }
+        bytes
+    }
+
+    /// Deserializes the summaries serialized by [`Self::serialize`] or Spark.
+    fn deserialize(bytes: &[u8]) -> Result<Self> {
+        let corrupted = || DataFusionError::Execution("Corrupted percentile buffer".to_string());
+        let mut offset = 0;
+        let mut next = |len: usize| -> Result<&[u8]> {
+            let slice = bytes.get(offset..offset + len).ok_or_else(corrupted)?;
+            offset += len;
+            Ok(slice)
+        };
+        let compress_threshold = i32::from_be_bytes(next(4)?.try_into().unwrap());
+        let relative_error = f64::from_be_bytes(next(8)?.try_into().unwrap());
+        let count = i64::from_be_bytes(next(8)?.try_into().unwrap());
+        let num_samples = i32::from_be_bytes(next(4)?.try_into().unwrap());
+        let sampled = (0..num_samples.max(0))
+            .map(|_| {
+                Ok(Stats {
+                    value: f64::from_be_bytes(next(8)?.try_into().unwrap()),
+                    g: i64::from_be_bytes(next(8)?.try_into().unwrap()),
+                    delta: i64::from_be_bytes(next(8)?.try_into().unwrap()),
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self {
+            compress_threshold,
+            relative_error,
+            sampled,
+            count,
+            compressed: true,
+            head_sampled: vec![],
+        })
+    }
+}
+
+/// Merges the adjacent samples whose uncertainty is below `merge_threshold`, like Spark
+/// `QuantileSummaries.compressImmut`. The minimum and maximum samples are always kept.
+fn compress_samples(samples: &[Stats], merge_threshold: f64) -> Vec<Stats> {
+    if samples.is_empty() {
+        return vec![];
+    }
+    // The samples are collected from the last one in reverse order
+    let mut res = vec![];
+    let mut head = samples[samples.len() - 1];
+    for i in (1..samples.len().saturating_sub(1)).rev() {
+        let sample = samples[i];
+        if ((sample.g + head.g + head.delta) as f64) < merge_threshold {
+            head.g += sample.g;
+        } else {
+            res.push(head);
+            head = sample;
+        }
+    }
+    res.push(head);
+    if samples[0].value <= head.value && samples.len() > 1 {
+        res.push(samples[0]);
+    }
+    res.reverse();
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{compress_samples, QuantileSummaries, Stats};
+
+    #[test]
+    fn test_percentiles() {
+        // Two partial summaries merged into a final one
+        let mut partial1 = QuantileSummaries::new(0.01);
+        let mut partial2 = QuantileSummaries::new(0.01);
+        for i in 0..1000 {
+            partial1.insert(i as f64);
+            partial2.insert((1000 + i) as f64);
+        }
+        partial1.compress();
+        partial2.compress();
+
+        let mut fin = QuantileSummaries::new(0.01);
+        fin.merge(QuantileSummaries::deserialize(&partial1.serialize()).unwrap());
+        fin.merge(QuantileSummaries::deserialize(&partial2.serialize()).unwrap());
+        assert_eq!(fin.count, 2000);
+
+        let percentiles = fin.query(&[0.5, 0.0, 1.0, 0.25]).unwrap();
+        assert_eq!(percentiles[1], 0.0);
+        assert_eq!(percentiles[2], 1999.0);
+        for (percentile, expected) in [(percentiles[0], 1000.0), (percentiles[3], 500.0)] {
+            assert!((percentile - expected).abs() <= 0.01 * 2000.0);
+        }
+
+        assert!(QuantileSummaries::new(0.01).query(&[0.5]).is_none());
+    }
+
+    #[test]
+    fn test_compress_samples() {
+        let stats = |value: f64, g: i64, delta: i64| Stats { value, g, delta };
+        let samples = vec![
+            stats(1.0, 1, 0),
+            stats(2.0, 1, 0),
+            stats(3.0, 1, 0),
+            stats(4.0, 1, 0),
+        ];
+        // The middle samples are merged into the last one
+        assert_eq!(
+            compress_samples(&samples, 10.0),
+            vec![stats(1.0, 1, 0), stats(4.0, 3, 0)]
+        );
+        assert_eq!(compress_samples(&samples, 0.0), samples);
+    }
+
+    #[test]
+    fn test_spark_buffer_format() {
+        let mut summaries = QuantileSummaries::new(0.5);
+        summaries.insert(2.0);
+        summaries.compress();
+
+        let mut expected = vec![];
+        expected.extend_from_slice(&10000_i32.to_be_bytes());
+        expected.extend_from_slice(&0.5_f64.to_be_bytes());
+        expected.extend_from_slice(&1_i64.to_be_bytes());
+        expected.extend_from_slice(&1_i32.to_be_bytes());
+        expected.extend_from_slice(&2.0_f64.to_be_bytes());
+        expected.extend_from_slice(&1_i64.to_be_bytes());
+        expected.extend_from_slice(&0_i64.to_be_bytes());
+        assert_eq!(summaries.serialize(), expected);
+
+        assert!(QuantileSummaries::deserialize(&expected[..expected.len() - 1]).is_err());
+    }
+}
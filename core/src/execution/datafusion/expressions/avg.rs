@@ -15,7 +15,10 @@
 // specific language governing permissions and limitations
 // under the License.
 
-use crate::execution::datafusion::expressions::utils::down_cast_any_ref;
+use crate::execution::datafusion::expressions::{
+    sequential_sum::sequential_sum,
+    utils::{down_cast_any_ref, filter_nulls},
+};
 use arrow::compute::sum;
 use arrow_array::{
     builder::PrimitiveBuilder,
@@ -42,6 +45,9 @@ pub struct Avg {
     expr: Arc<dyn PhysicalExpr>,
     input_data_type: DataType,
     result_data_type: DataType,
+    /// Whether the values are added one by one in order without grouping keys, see
+    /// [`super::sequential_sum::SequentialSum`]
+    sequential: bool,
 }
 
 impl Avg {
@@ -54,8 +60,14 @@ impl Avg {
             expr,
             input_data_type: data_type,
             result_data_type,
+            sequential: false,
         }
     }
+
+    pub fn with_sequential(mut self, sequential: bool) -> Self {
+        self.sequential = sequential;
+        self
+    }
 }
 
 impl AggregateExpr for Avg {
@@ -71,7 +83,10 @@ impl AggregateExpr for Avg {
     fn create_accumulator(&self) -> Result<Box<dyn Accumulator>> {
         // instantiate specialized accumulator based for the type
         match (&self.input_data_type, &self.result_data_type) {
-            (Float64, Float64) => Ok(Box::<AvgAccumulator>::default()),
+            (Float64, Float64) => Ok(Box::new(AvgAccumulator {
+                sequential: self.sequential,
+                ..Default::default()
+            })),
             _ => not_impl_err!(
                 "AvgAccumulator for ({} --> {})",
                 self.input_data_type,
@@ -132,6 +147,7 @@ impl PartialEq<dyn Any> for Avg {
                 self.name == x.name
                     && self.input_data_type == x.input_data_type
                     && self.result_data_type == x.result_data_type
+                    && self.sequential == x.sequential
                     && self.expr.eq(&x.expr)
             })
             .unwrap_or(false)
@@ -143,6 +159,17 @@ impl PartialEq<dyn Any> for Avg {
 pub struct AvgAccumulator {
     sum: Option<f64>,
     count: i64,
+    sequential: bool,
+}
+
+impl AvgAccumulator {
+    fn add(&mut self, values: &PrimitiveArray<Float64Type>) {
+        if self.sequential {
+            self.sum = sequential_sum(self.sum, values);
+        } else if let Some(x) = sum(values) {
+            *self.sum.get_or_insert(0.) += x;
+        }
+    }
 }
 
 impl Accumulator for AvgAccumulator {
@@ -156,10 +183,8 @@ impl Accumulator for AvgAccumulator {
     fn update_batch(&mut self, values: &[ArrayRef]) -> Result<()> {
         let values = values[0].as_primitive::<Float64Type>();
         self.count += (values.len() - values.null_count()) as i64;
-        let v = self.sum.get_or_insert(0.);
-        if let Some(x) = sum(values) {
-            *v += x;
-        }
+        self.sum.get_or_insert(0.);
+        self.add(values);
         Ok(())
     }
 
@@ -168,10 +193,7 @@ impl Accumulator for AvgAccumulator {
         self.count += sum(states[1].as_primitive::<Int64Type>()).unwrap_or_default();
 
         // sums are summed
-        if let Some(x) = sum(states[0].as_primitive::<Float64Type>()) {
-            let v = self.sum.get_or_insert(0.);
-            *v += x;
-        }
+        self.add(states[0].as_primitive::<Float64Type>());
         Ok(())
     }
 
@@ -240,11 +262,12 @@ where
         &mut self,
         values: &[ArrayRef],
         group_indices: &[usize],
-        _opt_filter: Option<&arrow_array::BooleanArray>,
+        opt_filter: Option<&arrow_array::BooleanArray>,
         total_num_groups: usize,
     ) -> Result<()> {
         assert_eq!(values.len(), 1, "single argument to update_batch");
-        let values = values[0].as_primitive::<T>();
+        let values = filter_nulls(&values[0], opt_filter)?;
+        let values = values.as_primitive::<T>();
         let data = values.values();
 
         // increment counts, update sums
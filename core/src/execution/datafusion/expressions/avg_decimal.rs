@@ -15,7 +15,7 @@
 // specific language governing permissions and limitations
 // under the License.
 
-use crate::execution::datafusion::expressions::utils::down_cast_any_ref;
+use crate::execution::datafusion::expressions::utils::{down_cast_any_ref, filter_nulls};
 use arrow::{array::BooleanBufferBuilder, buffer::NullBuffer, compute::sum};
 use arrow_array::{
     builder::PrimitiveBuilder,
@@ -383,11 +383,12 @@ impl GroupsAccumulator for AvgDecimalGroupsAccumulator {
         &mut self,
         values: &[ArrayRef],
         group_indices: &[usize],
-        _opt_filter: Option<&arrow_array::BooleanArray>,
+        opt_filter: Option<&arrow_array::BooleanArray>,
         total_num_groups: usize,
     ) -> Result<()> {
         assert_eq!(values.len(), 1, "single argument to update_batch");
-        let values = values[0].as_primitive::<Decimal128Type>();
+        let values = filter_nulls(&values[0], opt_filter)?;
+        let values = values.as_primitive::<Decimal128Type>();
         let data = values.values();
 
         // increment counts, update sums
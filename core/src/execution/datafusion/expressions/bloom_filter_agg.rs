@@ -0,0 +1,145 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use std::{any::Any, sync::Arc};
+
+use arrow_array::{ArrayRef, BinaryArray, Int64Array};
+use arrow_schema::{DataType, Field};
+use datafusion::logical_expr::Accumulator;
+use datafusion_common::{downcast_value, DataFusionError, Result, ScalarValue};
+use datafusion_physical_expr::{expressions::format_state_name, AggregateExpr, PhysicalExpr};
+
+use crate::execution::datafusion::{
+    expressions::utils::down_cast_any_ref, util::spark_bloom_filter::SparkBloomFilter,
+};
+
+/// Spark `BloomFilterAggregate`, which builds the Bloom filters of the runtime filters injected
+/// by Spark from the 64-bit hashes of the join keys.
+///
+/// Both the intermediate state and the result are serialized in the same format as Spark's
+/// `BloomFilter.writeTo`, so the partial and final aggregations can be executed by either Comet
+/// or Spark, and the result can be tested by `BloomFilterMightContain` on both sides.
+#[derive(Debug)]
+pub struct BloomFilterAgg {
+    name: String,
+    expr: Arc<dyn PhysicalExpr>,
+    expected_num_items: i64,
+    num_bits: i64,
+}
+
+impl BloomFilterAgg {
+    pub fn new(
+        expr: Arc<dyn PhysicalExpr>,
+        name: impl Into<String>,
+        expected_num_items: i64,
+        num_bits: i64,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            expr,
+            expected_num_items,
+            num_bits,
+        }
+    }
+}
+
+impl AggregateExpr for BloomFilterAgg {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn field(&self) -> Result<Field> {
+        Ok(Field::new(&self.name, DataType::Binary, true))
+    }
+
+    fn create_accumulator(&self) -> Result<Box<dyn Accumulator>> {
+        Ok(Box::new(BloomFilterAccumulator {
+            bloom_filter: SparkBloomFilter::new_empty(self.expected_num_items, self.num_bits),
+        }))
+    }
+
+    fn state_fields(&self) -> Result<Vec<Field>> {
+        Ok(vec![Field::new(
+            format_state_name(&self.name, "bloom_filter"),
+            DataType::Binary,
+            true,
+        )])
+    }
+
+    fn expressions(&self) -> Vec<Arc<dyn PhysicalExpr>> {
+        vec![self.expr.clone()]
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl PartialEq<dyn Any> for BloomFilterAgg {
+    fn eq(&self, other: &dyn Any) -> bool {
+        down_cast_any_ref(other)
+            .downcast_ref::<Self>()
+            .map(|x| {
+                self.name == x.name
+                    && self.expected_num_items == x.expected_num_items
+                    && self.num_bits == x.num_bits
+                    && self.expr.eq(&x.expr)
+            })
+            .unwrap_or(false)
+    }
+}
+
+#[derive(Debug)]
+struct BloomFilterAccumulator {
+    bloom_filter: SparkBloomFilter,
+}
+
+impl Accumulator for BloomFilterAccumulator {
+    fn update_batch(&mut self, values: &[ArrayRef]) -> Result<()> {
+        // Like Spark, null values are skipped
+        self.bloom_filter
+            .put_longs(downcast_value!(values[0], Int64Array));
+        Ok(())
+    }
+
+    fn merge_batch(&mut self, states: &[ArrayRef]) -> Result<()> {
+        let buffers = downcast_value!(states[0], BinaryArray);
+        for bytes in buffers.iter().flatten() {
+            self.bloom_filter.merge(&SparkBloomFilter::new(bytes));
+        }
+        Ok(())
+    }
+
+    fn state(&mut self) -> Result<Vec<ScalarValue>> {
+        Ok(vec![ScalarValue::Binary(Some(
+            self.bloom_filter.to_bytes(),
+        ))])
+    }
+
+    fn evaluate(&mut self) -> Result<ScalarValue> {
+        // Like Spark, the result is null if nothing has been put into the filter
+        if self.bloom_filter.cardinality() == 0 {
+            Ok(ScalarValue::Binary(None))
+        } else {
+            Ok(ScalarValue::Binary(Some(self.bloom_filter.to_bytes())))
+        }
+    }
+
+    fn size(&self) -> usize {
+        std::mem::size_of_val(self) + self.bloom_filter.size()
+    }
+}
@@ -0,0 +1,337 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use std::{
+    any::Any,
+    fmt::{Display, Formatter},
+    hash::{Hash, Hasher},
+    sync::Arc,
+};
+
+use arrow::{
+    compute::{
+        cast, filter, filter_record_batch, interleave, kernels::filter::prep_null_mask_filter, not,
+    },
+    datatypes::{DataType, Schema, UInt32Type},
+    record_batch::RecordBatch,
+};
+use arrow_array::{new_null_array, Array, ArrayRef, AsArray, UInt32Array};
+use datafusion::logical_expr::ColumnarValue;
+use datafusion_common::{cast::as_boolean_array, Result};
+use datafusion_physical_expr::PhysicalExpr;
+
+use crate::execution::datafusion::expressions::utils::down_cast_any_ref;
+
+/// Spark `CaseWhen` expression, i.e., `CASE WHEN c1 THEN v1 WHEN c2 THEN v2 ... ELSE e END`.
+///
+/// The branches are evaluated lazily on shrinking selections of the batch: a condition is only
+/// evaluated on the rows which are not matched by the previous conditions, and a value only on the
+/// rows matched by its condition. So wide CASE expressions don't evaluate every branch for every
+/// row, and like Spark, a value doesn't fail on the rows its condition excludes, e.g., in
+/// `CASE WHEN b != 0 THEN a / b END` in ANSI mode.
+#[derive(Debug, Hash)]
+pub struct CaseWhenExpr {
+    when_then: Vec<(Arc<dyn PhysicalExpr>, Arc<dyn PhysicalExpr>)>,
+    else_expr: Option<Arc<dyn PhysicalExpr>>,
+}
+
+impl CaseWhenExpr {
+    pub fn new(
+        when_then: Vec<(Arc<dyn PhysicalExpr>, Arc<dyn PhysicalExpr>)>,
+        else_expr: Option<Arc<dyn PhysicalExpr>>,
+    ) -> Self {
+        Self {
+            when_then,
+            else_expr,
+        }
+    }
+
+    fn values(&self) -> impl Iterator<Item = &Arc<dyn PhysicalExpr>> {
+        self.when_then
+            .iter()
+            .map(|(_, then)| then)
+            .chain(self.else_expr.iter())
+    }
+}
+
+impl Display for CaseWhenExpr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "CaseWhen [")?;
+        for (when, then) in &self.when_then {
+            write!(f, "WHEN {} THEN {} ", when, then)?;
+        }
+        if let Some(else_expr) = &self.else_expr {
+            write!(f, "ELSE {} ", else_expr)?;
+        }
+        write!(f, "END]")
+    }
+}
+
+impl PartialEq<dyn Any> for CaseWhenExpr {
+    fn eq(&self, other: &dyn Any) -> bool {
+        down_cast_any_ref(other)
+            .downcast_ref::<Self>()
+            .map(|x| {
+                self.when_then.len() == x.when_then.len()
+                    && self
+                        .when_then
+                        .iter()
+                        .zip(x.when_then.iter())
+                        .all(|((w1, t1), (w2, t2))| w1.eq(w2) && t1.eq(t2))
+                    && match (&self.else_expr, &x.else_expr) {
+                        (Some(e1), Some(e2)) => e1.eq(e2),
+                        (None, None) => true,
+                        _ => false,
+                    }
+            })
+            .unwrap_or(false)
+    }
+}
+
+impl PhysicalExpr for CaseWhenExpr {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn data_type(&self, input_schema: &Schema) -> Result<DataType> {
+        // A null literal value may have the null type
+        let mut data_type = DataType::Null;
+        for value in self.values() {
+            data_type = value.data_type(input_schema)?;
+            if data_type != DataType::Null {
+                break;
+            }
+        }
+        Ok(data_type)
+    }
+
+    fn nullable(&self, input_schema: &Schema) -> Result<bool> {
+        if self.else_expr.is_none() {
+            return Ok(true);
+        }
+        for value in self.values() {
+            if value.nullable(input_schema)? {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    fn evaluate(&self, batch: &RecordBatch) -> Result<ColumnarValue> {
+        let data_type = self.data_type(&batch.schema())?;
+        let num_rows = batch.num_rows();
+
+        // The values of the matched rows of each branch, after a single null value for the rows
+        // matched by no branch. For each row, the index of its branch in `values` and its index in
+        // the values of the branch.
+        let mut values: Vec<ArrayRef> = vec![new_null_array(&data_type, 1)];
+        let mut indices: Vec<(usize, usize)> = vec![(0, 0); num_rows];
+
+        // The rows not matched yet, and their indices in `batch`
+        let mut remaining = batch.clone();
+        let mut remaining_rows = UInt32Array::from_iter_values(0..num_rows as u32);
+
+        for (when, then) in &self.when_then {
+            if remaining.num_rows() == 0 {
+                break;
+            }
+            let matched = when
+                .evaluate(&remaining)?
+                .into_array(remaining.num_rows())?;
+            // A null condition doesn't match
+            let matched = prep_null_mask_filter(as_boolean_array(&matched)?);
+            let matched_count = matched.true_count();
+            if matched_count == 0 {
+                continue;
+            }
+
+            let all_matched = matched_count == remaining.num_rows();
+            let matched_batch = if all_matched {
+                remaining.clone()
+            } else {
+                filter_record_batch(&remaining, &matched)?
+            };
+            let value = then.evaluate(&matched_batch)?.into_array(matched_count)?;
+            values.push(cast_to(value, &data_type)?);
+            let branch = values.len() - 1;
+            for (value_index, row) in matched.values().set_indices().enumerate() {
+                indices[remaining_rows.value(row) as usize] = (branch, value_index);
+            }
+
+            if all_matched {
+                remaining = RecordBatch::new_empty(batch.schema());
+                remaining_rows = UInt32Array::from(Vec::<u32>::new());
+            } else {
+                let unmatched = not(&matched)?;
+                remaining = filter_record_batch(&remaining, &unmatched)?;
+                remaining_rows = filter(&remaining_rows, &unmatched)?
+                    .as_primitive::<UInt32Type>()
+                    .clone();
+            }
+        }
+
+        if let Some(else_expr) = &self.else_expr {
+            if remaining.num_rows() > 0 {
+                let value = else_expr
+                    .evaluate(&remaining)?
+                    .into_array(remaining.num_rows())?;
+                values.push(cast_to(value, &data_type)?);
+                let branch = values.len() - 1;
+                for (value_index, row) in remaining_rows.values().iter().enumerate() {
+                    indices[*row as usize] = (branch, value_index);
+                }
+            }
+        }
+
+        // All the rows are matched by a single branch, so its values are in the order of the rows
+        if values.len() == 2 && values[1].len() == num_rows {
+            return Ok(ColumnarValue::Array(values.swap_remove(1)));
+        }
+        let values = values.iter().map(|v| v.as_ref()).collect::<Vec<_>>();
+        Ok(ColumnarValue::Array(interleave(&values, &indices)?))
+    }
+
+    fn children(&self) -> Vec<Arc<dyn PhysicalExpr>> {
+        let mut children = Vec::with_capacity(self.when_then.len() * 2 + 1);
+        for (when, then) in &self.when_then {
+            children.push(when.clone());
+            children.push(then.clone());
+        }
+        if let Some(else_expr) = &self.else_expr {
+            children.push(else_expr.clone());
+        }
+        children
+    }
+
+    fn with_new_children(
+        self: Arc<Self>,
+        children: Vec<Arc<dyn PhysicalExpr>>,
+    ) -> Result<Arc<dyn PhysicalExpr>> {
+        let when_then = children
+            .chunks_exact(2)
+            .map(|pair| (pair[0].clone(), pair[1].clone()))
+            .collect();
+        let else_expr = if children.len() % 2 == 1 {
+            children.last().cloned()
+        } else {
+            None
+        };
+        Ok(Arc::new(CaseWhenExpr::new(when_then, else_expr)))
+    }
+
+    fn dyn_hash(&self, state: &mut dyn Hasher) {
+        let mut s = state;
+        self.hash(&mut s);
+    }
+}
+
+/// Casts the values of a branch to the type of the expression, e.g., a dictionary-encoded column
+/// or a null literal.
+fn cast_to(value: ArrayRef, data_type: &DataType) -> Result<ArrayRef> {
+    if value.data_type() == data_type {
+        Ok(value)
+    } else {
+        Ok(cast(&value, data_type)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use arrow_array::{Array, Int32Array, RecordBatch};
+    use arrow_schema::{DataType, Field, Schema};
+    use datafusion::{
+        logical_expr::Operator,
+        physical_expr::{
+            expressions::{BinaryExpr, Column, Literal},
+            PhysicalExpr,
+        },
+    };
+    use datafusion_common::ScalarValue;
+
+    use super::CaseWhenExpr;
+    use crate::execution::datafusion::expressions::div_rem::{DivRemExpr, DivRemOp};
+
+    fn literal(value: i32) -> Arc<dyn PhysicalExpr> {
+        Arc::new(Literal::new(ScalarValue::Int32(Some(value))))
+    }
+
+    #[test]
+    fn test_case_when() {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("a", DataType::Int32, true),
+            Field::new("b", DataType::Int32, true),
+        ]));
+        let batch = RecordBatch::try_new(
+            schema,
+            vec![
+                Arc::new(Int32Array::from(vec![Some(10), Some(20), None, Some(40)])),
+                Arc::new(Int32Array::from(vec![Some(2), Some(0), Some(5), None])),
+            ],
+        )
+        .unwrap();
+        let a: Arc<dyn PhysicalExpr> = Arc::new(Column::new("a", 0));
+        let b: Arc<dyn PhysicalExpr> = Arc::new(Column::new("b", 1));
+
+        // CASE WHEN b != 0 THEN a / b WHEN a > 15 THEN -1 END: the division fails in ANSI mode if
+        // it is evaluated on the second row
+        let divide = Arc::new(DivRemExpr::new(
+            a.clone(),
+            b.clone(),
+            DivRemOp::IntegralDivide,
+            DataType::Int32,
+            true,
+        ));
+        let case_when = CaseWhenExpr::new(
+            vec![
+                (
+                    Arc::new(BinaryExpr::new(b.clone(), Operator::NotEq, literal(0))),
+                    divide,
+                ),
+                (
+                    Arc::new(BinaryExpr::new(a.clone(), Operator::Gt, literal(15))),
+                    literal(-1),
+                ),
+            ],
+            None,
+        );
+        let result = case_when
+            .evaluate(&batch)
+            .unwrap()
+            .into_array(batch.num_rows())
+            .unwrap();
+        let expected = Int32Array::from(vec![Some(5), Some(-1), None, Some(-1)]);
+        assert_eq!(result.as_ref(), &expected as &dyn Array);
+
+        // The else value applies to the rows not matched, including null conditions
+        let case_when = CaseWhenExpr::new(
+            vec![(
+                Arc::new(BinaryExpr::new(b.clone(), Operator::Gt, literal(1))),
+                a.clone(),
+            )],
+            Some(literal(0)),
+        );
+        let result = case_when
+            .evaluate(&batch)
+            .unwrap()
+            .into_array(batch.num_rows())
+            .unwrap();
+        let expected = Int32Array::from(vec![Some(10), Some(0), None, Some(0)]);
+        assert_eq!(result.as_ref(), &expected as &dyn Array);
+    }
+}
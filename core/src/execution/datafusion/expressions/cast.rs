@@ -24,18 +24,33 @@ use std::{
 
 use crate::errors::{CometError, CometResult};
 use arrow::{
-    compute::{cast_with_options, CastOptions},
+    compute::{cast_with_options, unary, CastOptions},
     record_batch::RecordBatch,
+    temporal_conversions::as_datetime,
     util::display::FormatOptions,
 };
-use arrow_array::{Array, ArrayRef, BooleanArray, GenericStringArray, OffsetSizeTrait};
+use arrow_array::{
+    builder::PrimitiveBuilder,
+    cast::AsArray,
+    types::{
+        ArrowPrimitiveType, Float32Type, Float64Type, Int16Type, Int32Type, Int64Type, Int8Type,
+        TimestampMicrosecondType,
+    },
+    Array, ArrayRef, BooleanArray, GenericStringArray, OffsetSizeTrait, PrimitiveArray,
+    StringArray,
+};
 use arrow_schema::{DataType, Schema};
+use chrono::TimeZone;
 use datafusion::logical_expr::ColumnarValue;
 use datafusion_common::{internal_err, Result as DataFusionResult, ScalarValue};
 use datafusion_physical_expr::PhysicalExpr;
+use num::{integer::div_floor, traits::AsPrimitive, NumCast};
 
-use crate::execution::datafusion::expressions::utils::{
-    array_with_timezone, down_cast_any_ref, spark_cast,
+use crate::execution::{
+    datafusion::expressions::utils::{
+        array_with_timezone, down_cast_any_ref, spark_cast, MICROS_PER_SECOND,
+    },
+    timezone::Tz,
 };
 
 static TIMESTAMP_FORMAT: Option<&str> = Some("%Y-%m-%d %H:%M:%S%.f");
@@ -103,12 +118,170 @@ impl Cast {
             (DataType::LargeUtf8, DataType::Boolean) => {
                 Self::spark_cast_utf8_to_boolean::<i64>(&array, self.eval_mode)?
             }
+            (DataType::Binary, DataType::Utf8) => Self::spark_cast_binary_to_string(&array)?,
+            (DataType::Dictionary(_, value_type), DataType::Boolean | DataType::Utf8)
+                if matches!(
+                    (value_type.as_ref(), to_type),
+                    (DataType::Utf8, DataType::Boolean) | (DataType::Binary, DataType::Utf8)
+                ) =>
+            {
+                // Unpacks the dictionary first, so that only the values in use are cast, which
+                // may be invalid in ANSI mode
+                let array = cast_with_options(&array, value_type, &CAST_OPTIONS)?;
+                match value_type.as_ref() {
+                    DataType::Utf8 => {
+                        Self::spark_cast_utf8_to_boolean::<i32>(&array, self.eval_mode)?
+                    }
+                    _ => Self::spark_cast_binary_to_string(&array)?,
+                }
+            }
+            (DataType::Timestamp(_, _), _) if to_type.is_numeric() => {
+                self.spark_cast_timestamp_to_numeric(&array)?
+            }
+            (DataType::Dictionary(_, value_type), _)
+                if matches!(value_type.as_ref(), DataType::Timestamp(_, _))
+                    && to_type.is_numeric() =>
+            {
+                let array = cast_with_options(&array, value_type, &CAST_OPTIONS)?;
+                self.spark_cast_timestamp_to_numeric(&array)?
+            }
+            (_, DataType::Timestamp(_, _)) if from_type.is_numeric() => {
+                self.spark_cast_numeric_to_timestamp(&array)?
+            }
             _ => cast_with_options(&array, to_type, &CAST_OPTIONS)?,
         };
         let result = spark_cast(cast_result, from_type, to_type);
         Ok(result)
     }
 
+    /// Casts timestamps to numbers of seconds since the epoch like Spark, which are fractional for
+    /// floating-point types. Spark truncates the seconds to smaller integral types, or throws an
+    /// overflow error in ANSI mode.
+    fn spark_cast_timestamp_to_numeric(&self, array: &ArrayRef) -> CometResult<ArrayRef> {
+        let array = array.as_primitive::<TimestampMicrosecondType>();
+        let result: ArrayRef = match &self.data_type {
+            DataType::Int64 => Arc::new(unary::<_, _, Int64Type>(array, |micros| {
+                div_floor(micros, MICROS_PER_SECOND)
+            })),
+            DataType::Int32 => self.spark_cast_timestamp_to_int::<Int32Type>(array, "INT")?,
+            DataType::Int16 => self.spark_cast_timestamp_to_int::<Int16Type>(array, "SMALLINT")?,
+            DataType::Int8 => self.spark_cast_timestamp_to_int::<Int8Type>(array, "TINYINT")?,
+            DataType::Float64 => Arc::new(unary::<_, _, Float64Type>(array, |micros| {
+                micros as f64 / MICROS_PER_SECOND as f64
+            })),
+            DataType::Float32 => Arc::new(unary::<_, _, Float32Type>(array, |micros| {
+                (micros as f64 / MICROS_PER_SECOND as f64) as f32
+            })),
+            to_type => cast_with_options(array, to_type, &CAST_OPTIONS)?,
+        };
+        Ok(result)
+    }
+
+    fn spark_cast_timestamp_to_int<T>(
+        &self,
+        array: &PrimitiveArray<TimestampMicrosecondType>,
+        type_name: &str,
+    ) -> CometResult<ArrayRef>
+    where
+        T: ArrowPrimitiveType,
+        T::Native: NumCast,
+        i64: AsPrimitive<T::Native>,
+    {
+        let mut builder = PrimitiveBuilder::<T>::with_capacity(array.len());
+        for micros in array.iter() {
+            let Some(micros) = micros else {
+                builder.append_null();
+                continue;
+            };
+            let seconds = div_floor(micros, MICROS_PER_SECOND);
+            match (<T::Native as NumCast>::from(seconds), self.eval_mode) {
+                (Some(value), _) => builder.append_value(value),
+                (None, EvalMode::Legacy) => builder.append_value(seconds.as_()),
+                (None, EvalMode::Try) => builder.append_null(),
+                (None, EvalMode::Ansi) => {
+                    return Err(CometError::CastOverFlow {
+                        value: self.timestamp_sql_value(micros),
+                        from_type: "TIMESTAMP".to_string(),
+                        to_type: type_name.to_string(),
+                    })
+                }
+            }
+        }
+        Ok(Arc::new(builder.finish()))
+    }
+
+    /// Casts numbers of seconds since the epoch to timestamps like Spark. Integral seconds
+    /// saturate on overflow, while fractional seconds are null if not a number or infinite, and
+    /// throw errors in ANSI mode if they are or overflow.
+    fn spark_cast_numeric_to_timestamp(&self, array: &ArrayRef) -> CometResult<ArrayRef> {
+        let result: PrimitiveArray<TimestampMicrosecondType> = match array.data_type() {
+            DataType::Int8 | DataType::Int16 | DataType::Int32 | DataType::Int64 => {
+                let seconds = cast_with_options(array, &DataType::Int64, &CAST_OPTIONS)?;
+                unary(seconds.as_primitive::<Int64Type>(), |seconds| {
+                    seconds.saturating_mul(MICROS_PER_SECOND)
+                })
+            }
+            DataType::Float32 | DataType::Float64 => {
+                let seconds = cast_with_options(array, &DataType::Float64, &CAST_OPTIONS)?;
+                let mut builder =
+                    PrimitiveBuilder::<TimestampMicrosecondType>::with_capacity(array.len());
+                for seconds in seconds.as_primitive::<Float64Type>().iter() {
+                    match seconds.map(|seconds| self.seconds_to_micros(seconds)) {
+                        Some(micros) => builder.append_option(micros?),
+                        None => builder.append_null(),
+                    }
+                }
+                builder.finish()
+            }
+            _ => {
+                return Ok(cast_with_options(array, &self.data_type, &CAST_OPTIONS)?);
+            }
+        };
+        Ok(Arc::new(result.with_data_type(self.data_type.clone())))
+    }
+
+    fn seconds_to_micros(&self, seconds: f64) -> CometResult<Option<i64>> {
+        if seconds.is_nan() || seconds.is_infinite() {
+            return match self.eval_mode {
+                EvalMode::Ansi => Err(CometError::CastInvalidValue {
+                    value: double_sql_value(seconds),
+                    from_type: "DOUBLE".to_string(),
+                    to_type: "TIMESTAMP".to_string(),
+                }),
+                _ => Ok(None),
+            };
+        }
+        let micros = seconds * MICROS_PER_SECOND as f64;
+        if self.eval_mode != EvalMode::Legacy
+            && (micros.floor() > i64::MAX as f64 || micros.ceil() < i64::MIN as f64)
+        {
+            return match self.eval_mode {
+                EvalMode::Ansi => Err(CometError::CastOverFlow {
+                    value: double_sql_value(micros),
+                    from_type: "DOUBLE".to_string(),
+                    to_type: "BIGINT".to_string(),
+                }),
+                _ => Ok(None),
+            };
+        }
+        // Like Java, the conversion saturates on overflow
+        Ok(Some(micros as i64))
+    }
+
+    /// The timestamp in the session time zone as a SQL literal, as in the error messages of Spark.
+    fn timestamp_sql_value(&self, micros: i64) -> String {
+        let tz: Tz = self.timezone.parse().unwrap();
+        match as_datetime::<TimestampMicrosecondType>(micros) {
+            Some(datetime) => {
+                let datetime = tz.from_utc_datetime(&datetime);
+                let formatted = datetime.format("%Y-%m-%d %H:%M:%S%.6f").to_string();
+                let formatted = formatted.trim_end_matches('0').trim_end_matches('.');
+                format!("TIMESTAMP '{}'", formatted)
+            }
+            None => micros.to_string(),
+        }
+    }
+
     fn spark_cast_utf8_to_boolean<OffsetSize>(
         from: &dyn Array,
         eval_mode: EvalMode,
@@ -124,7 +297,12 @@ impl Cast {
         let output_array = array
             .iter()
             .map(|value| match value {
-                Some(value) => match value.to_ascii_lowercase().trim() {
+                // Like `UTF8String.trimAll` of Spark
+                Some(value) => match value
+                    .trim_matches(|c: char| c <= ' ' || c == '\x7f')
+                    .to_ascii_lowercase()
+                    .as_str()
+                {
                     "t" | "true" | "y" | "yes" | "1" => Ok(Some(true)),
                     "f" | "false" | "n" | "no" | "0" => Ok(Some(false)),
                     _ if eval_mode == EvalMode::Ansi => Err(CometError::CastInvalidValue {
@@ -140,6 +318,38 @@ impl Cast {
 
         Ok(Arc::new(output_array))
     }
+
+    /// Casts binary to string like Spark, which takes the bytes as UTF-8. Unlike Arrow, which
+    /// returns null for invalid UTF-8, the invalid sequences are replaced with U+FFFD, as JVM
+    /// decodes the string bytes of Spark.
+    fn spark_cast_binary_to_string(from: &dyn Array) -> CometResult<ArrayRef> {
+        let array = from.as_binary::<i32>();
+        let output_array = match StringArray::try_from_binary(array.clone()) {
+            Ok(strings) => strings,
+            Err(_) => array
+                .iter()
+                .map(|value| value.map(String::from_utf8_lossy))
+                .collect::<StringArray>(),
+        };
+        Ok(Arc::new(output_array))
+    }
+}
+
+/// The double as a SQL literal like Spark, e.g., `1.0E20D`.
+fn double_sql_value(value: f64) -> String {
+    if value.is_nan() {
+        "NaND".to_string()
+    } else if value.is_infinite() {
+        format!("{}InfinityD", if value < 0.0 { "-" } else { "" })
+    } else {
+        let formatted = format!("{:E}", value);
+        match formatted.split_once('E') {
+            Some((mantissa, exponent)) if !mantissa.contains('.') => {
+                format!("{}.0E{}D", mantissa, exponent)
+            }
+            _ => format!("{}D", formatted),
+        }
+    }
 }
 
 impl Display for Cast {
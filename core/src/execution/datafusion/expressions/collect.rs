@@ -0,0 +1,477 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use std::{any::Any, collections::HashSet, sync::Arc};
+
+use arrow::{
+    compute::{concat, filter, is_not_null, take},
+    row::{RowConverter, SortField},
+};
+use arrow_array::{
+    cast::AsArray, new_empty_array, types::*, Array, ArrayRef, BinaryArray, BooleanArray,
+    Date32Array, Decimal128Array, Float32Array, Float64Array, Int16Array, Int32Array, Int64Array,
+    Int8Array, StringArray, TimestampMicrosecondArray, UInt32Array,
+};
+use arrow_schema::{DataType, Field, TimeUnit};
+use datafusion::logical_expr::Accumulator;
+use datafusion_common::{
+    downcast_value, utils::array_into_list_array, DataFusionError, Result, ScalarValue,
+};
+use datafusion_physical_expr::{expressions::format_state_name, AggregateExpr, PhysicalExpr};
+
+use crate::execution::datafusion::expressions::utils::down_cast_any_ref;
+
+/// COLLECT_LIST and COLLECT_SET aggregate expressions, which Spark executes with
+/// `ObjectHashAggregateExec` as their aggregation buffer is not fixed-size.
+///
+/// Null input values are ignored, and the result of a group without non-null values is an empty
+/// list, like Spark. The intermediate state is serialized in the same format as the aggregation
+/// buffer of Spark, i.e., an `UnsafeRow` with a single `UnsafeArrayData` field, so the partial and
+/// final aggregations can be executed by either Comet or Spark.
+#[derive(Debug)]
+pub struct Collect {
+    name: String,
+    expr: Arc<dyn PhysicalExpr>,
+    /// The type of the collected values
+    data_type: DataType,
+    /// Whether to remove duplicated values, i.e., COLLECT_SET
+    distinct: bool,
+}
+
+impl Collect {
+    pub fn new(
+        expr: Arc<dyn PhysicalExpr>,
+        name: impl Into<String>,
+        data_type: DataType,
+        distinct: bool,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            expr,
+            data_type,
+            distinct,
+        }
+    }
+}
+
+impl AggregateExpr for Collect {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn field(&self) -> Result<Field> {
+        Ok(Field::new_list(
+            &self.name,
+            Field::new("item", self.data_type.clone(), true),
+            false,
+        ))
+    }
+
+    fn create_accumulator(&self) -> Result<Box<dyn Accumulator>> {
+        Ok(Box::new(CollectAccumulator::new(
+            self.data_type.clone(),
+            self.distinct,
+        )))
+    }
+
+    fn state_fields(&self) -> Result<Vec<Field>> {
+        Ok(vec![Field::new(
+            format_state_name(&self.name, "buffer"),
+            DataType::Binary,
+            true,
+        )])
+    }
+
+    fn expressions(&self) -> Vec<Arc<dyn PhysicalExpr>> {
+        vec![self.expr.clone()]
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl PartialEq<dyn Any> for Collect {
+    fn eq(&self, other: &dyn Any) -> bool {
+        down_cast_any_ref(other)
+            .downcast_ref::<Self>()
+            .map(|x| {
+                self.name == x.name
+                    && self.data_type == x.data_type
+                    && self.distinct == x.distinct
+                    && self.expr.eq(&x.expr)
+            })
+            .unwrap_or(false)
+    }
+}
+
+/// An accumulator collecting the values of a group into Arrow arrays.
+///
+/// The non-null input values are kept as the (sliced) input arrays, and are concatenated only
+/// when the state or the result is requested.
+#[derive(Debug)]
+struct CollectAccumulator {
+    data_type: DataType,
+    distinct: bool,
+    values: Vec<ArrayRef>,
+}
+
+impl CollectAccumulator {
+    fn new(data_type: DataType, distinct: bool) -> Self {
+        Self {
+            data_type,
+            distinct,
+            values: vec![],
+        }
+    }
+
+    fn append(&mut self, values: &ArrayRef) -> Result<()> {
+        let values = if values.null_count() > 0 {
+            filter(values, &is_not_null(values)?)?
+        } else {
+            values.clone()
+        };
+        if !values.is_empty() {
+            self.values.push(values);
+        }
+        Ok(())
+    }
+
+    /// Concatenates the collected values into one array. For COLLECT_SET, the first occurrence
+    /// of each value is kept.
+    fn collected(&mut self) -> Result<ArrayRef> {
+        let values = match self.values.len() {
+            0 => new_empty_array(&self.data_type),
+            1 => self.values[0].clone(),
+            _ => {
+                let arrays = self.values.iter().map(|a| a.as_ref()).collect::<Vec<_>>();
+                concat(&arrays)?
+            }
+        };
+
+        let values = if self.distinct && values.len() > 1 {
+            let converter = RowConverter::new(vec![SortField::new(self.data_type.clone())])?;
+            let rows = converter.convert_columns(&[values.clone()])?;
+            let mut seen = HashSet::with_capacity(rows.num_rows());
+            let indices = rows
+                .iter()
+                .enumerate()
+                .filter(|(_, row)| seen.insert(*row))
+                .map(|(idx, _)| idx as u32)
+                .collect::<UInt32Array>();
+            take(&values, &indices, None)?
+        } else {
+            values
+        };
+
+        // Keeps the compacted values, so that they are not concatenated again
+        self.values = vec![values.clone()];
+        Ok(values)
+    }
+}
+
+impl Accumulator for CollectAccumulator {
+    fn update_batch(&mut self, values: &[ArrayRef]) -> Result<()> {
+        self.append(&values[0])
+    }
+
+    fn merge_batch(&mut self, states: &[ArrayRef]) -> Result<()> {
+        let buffers = downcast_value!(states[0], BinaryArray);
+        for bytes in buffers.iter().flatten() {
+            self.append(&decode(bytes, &self.data_type)?)?;
+        }
+        Ok(())
+    }
+
+    fn state(&mut self) -> Result<Vec<ScalarValue>> {
+        let values = self.collected()?;
+        Ok(vec![ScalarValue::Binary(Some(encode(&values)?))])
+    }
+
+    fn evaluate(&mut self) -> Result<ScalarValue> {
+        let values = self.collected()?;
+        Ok(ScalarValue::List(Arc::new(array_into_list_array(values))))
+    }
+
+    fn size(&self) -> usize {
+        std::mem::size_of_val(self)
+            + self
+                .values
+                .iter()
+                .map(|values| values.get_array_memory_size())
+                .sum::<usize>()
+    }
+}
+
+/// Returns the size of an element in the fixed-length region of `UnsafeArrayData`. Values of
+/// variable-length types are stored in the variable-length region, and the fixed-length region
+/// has their offset and size.
+fn element_size(data_type: &DataType) -> Result<usize> {
+    match data_type {
+        DataType::Boolean | DataType::Int8 => Ok(1),
+        DataType::Int16 => Ok(2),
+        DataType::Int32 | DataType::Float32 | DataType::Date32 => Ok(4),
+        DataType::Int64 | DataType::Float64 | DataType::Timestamp(TimeUnit::Microsecond, _) => {
+            Ok(8)
+        }
+        // Spark stores decimals with precision up to 18 as their unscaled long values
+        DataType::Decimal128(precision, _) if *precision <= 18 => Ok(8),
+        DataType::Utf8 | DataType::Binary => Ok(8),
+        other => Err(DataFusionError::NotImplemented(format!(
+            "Unsupported data type {} of collected values",
+            other
+        ))),
+    }
+}
+
+fn round_to_word(num_bytes: usize) -> usize {
+    (num_bytes + 7) / 8 * 8
+}
+
+fn null_bits_width(num_elements: usize) -> usize {
+    (num_elements + 63) / 64 * 8
+}
+
+fn read_i64(bytes: &[u8], offset: usize) -> Result<i64> {
+    bytes
+        .get(offset..offset + 8)
+        .map(|b| i64::from_le_bytes(b.try_into().unwrap()))
+        .ok_or_else(|| DataFusionError::Execution("Corrupted collect buffer".to_string()))
+}
+
+/// Serializes the collected values like Spark serializes the aggregation buffer of `Collect`.
+fn encode(values: &ArrayRef) -> Result<Vec<u8>> {
+    let num_elements = values.len();
+    let size = element_size(values.data_type())?;
+    let header = 8 + null_bits_width(num_elements);
+
+    // `UnsafeArrayData`: the number of elements, null bits, fixed-length and variable-length
+    // regions
+    let mut array = vec![0u8; header + round_to_word(size * num_elements)];
+    array[..8].copy_from_slice(&(num_elements as i64).to_le_bytes());
+    for i in 0..num_elements {
+        if values.is_null(i) {
+            array[8 + i / 8] |= 1 << (i % 8);
+        }
+    }
+
+    macro_rules! put_fixed {
+        ($values:expr, $to_bytes:expr) => {
+            for (i, value) in $values.iter().enumerate() {
+                if let Some(value) = value {
+                    array[header + i * size..][..size].copy_from_slice(&$to_bytes(value));
+                }
+            }
+        };
+    }
+    macro_rules! put_variable {
+        ($values:expr) => {
+            for (i, value) in $values.iter().enumerate() {
+                if let Some(value) = value {
+                    let value: &[u8] = value.as_ref();
+                    let offset_and_size = ((array.len() as i64) << 32) | value.len() as i64;
+                    array[header + i * 8..][..8].copy_from_slice(&offset_and_size.to_le_bytes());
+                    array.extend_from_slice(value);
+                    array.resize(round_to_word(array.len()), 0);
+                }
+            }
+        };
+    }
+
+    match values.data_type() {
+        DataType::Boolean => put_fixed!(values.as_boolean(), |v: bool| [v as u8]),
+        DataType::Int8 => put_fixed!(values.as_primitive::<Int8Type>(), i8::to_le_bytes),
+        DataType::Int16 => put_fixed!(values.as_primitive::<Int16Type>(), i16::to_le_bytes),
+        DataType::Int32 => put_fixed!(values.as_primitive::<Int32Type>(), i32::to_le_bytes),
+        DataType::Int64 => put_fixed!(values.as_primitive::<Int64Type>(), i64::to_le_bytes),
+        DataType::Float32 => put_fixed!(values.as_primitive::<Float32Type>(), f32::to_le_bytes),
+        DataType::Float64 => put_fixed!(values.as_primitive::<Float64Type>(), f64::to_le_bytes),
+        DataType::Date32 => put_fixed!(values.as_primitive::<Date32Type>(), i32::to_le_bytes),
+        DataType::Timestamp(_, _) => put_fixed!(
+            values.as_primitive::<TimestampMicrosecondType>(),
+            i64::to_le_bytes
+        ),
+        DataType::Decimal128(_, _) => {
+            put_fixed!(values.as_primitive::<Decimal128Type>(), |v: i128| (v
+                as i64)
+                .to_le_bytes())
+        }
+        DataType::Utf8 => put_variable!(values.as_string::<i32>()),
+        DataType::Binary => put_variable!(values.as_binary::<i32>()),
+        _ => unreachable!(),
+    }
+
+    // `UnsafeRow` with the array as its only field: null bits, offset and size of the array
+    let mut row = Vec::with_capacity(16 + array.len());
+    row.extend_from_slice(&0_i64.to_le_bytes());
+    row.extend_from_slice(&((16_i64 << 32) | array.len() as i64).to_le_bytes());
+    row.extend_from_slice(&array);
+    Ok(row)
+}
+
+/// Deserializes the values serialized by [`encode`] or Spark.
+fn decode(row: &[u8], data_type: &DataType) -> Result<ArrayRef> {
+    let size = element_size(data_type)?;
+    let offset_and_size = read_i64(row, 8)?;
+    let offset = (offset_and_size >> 32) as usize;
+    let array = row
+        .get(offset..offset + (offset_and_size & 0xFFFFFFFF) as usize)
+        .ok_or_else(|| DataFusionError::Execution("Corrupted collect buffer".to_string()))?;
+
+    let num_elements = read_i64(array, 0)? as usize;
+    let header = 8 + null_bits_width(num_elements);
+    if array.len() < header + round_to_word(size * num_elements) {
+        return Err(DataFusionError::Execution(
+            "Corrupted collect buffer".to_string(),
+        ));
+    }
+    let is_valid = |i: usize| array[8 + i / 8] & (1 << (i % 8)) == 0;
+    let fixed = |i: usize| &array[header + i * size..][..size];
+    let variable = |i: usize| -> Result<&[u8]> {
+        let offset_and_size = read_i64(array, header + i * 8)?;
+        let offset = (offset_and_size >> 32) as usize;
+        array
+            .get(offset..offset + (offset_and_size & 0xFFFFFFFF) as usize)
+            .ok_or_else(|| DataFusionError::Execution("Corrupted collect buffer".to_string()))
+    };
+
+    macro_rules! get_fixed {
+        ($array_type:ty, $from_bytes:expr) => {
+            Arc::new(<$array_type>::from_iter((0..num_elements).map(|i| {
+                is_valid(i).then(|| $from_bytes(fixed(i).try_into().unwrap()))
+            }))) as ArrayRef
+        };
+    }
+
+    let values = match data_type {
+        DataType::Boolean => get_fixed!(BooleanArray, |b: [u8; 1]| b[0] != 0),
+        DataType::Int8 => get_fixed!(Int8Array, i8::from_le_bytes),
+        DataType::Int16 => get_fixed!(Int16Array, i16::from_le_bytes),
+        DataType::Int32 => get_fixed!(Int32Array, i32::from_le_bytes),
+        DataType::Int64 => get_fixed!(Int64Array, i64::from_le_bytes),
+        DataType::Float32 => get_fixed!(Float32Array, f32::from_le_bytes),
+        DataType::Float64 => get_fixed!(Float64Array, f64::from_le_bytes),
+        DataType::Date32 => get_fixed!(Date32Array, i32::from_le_bytes),
+        DataType::Timestamp(_, timezone) => Arc::new(
+            TimestampMicrosecondArray::from_iter(
+                (0..num_elements)
+                    .map(|i| is_valid(i).then(|| i64::from_le_bytes(fixed(i).try_into().unwrap()))),
+            )
+            .with_timezone_opt(timezone.clone()),
+        ),
+        DataType::Decimal128(precision, scale) => Arc::new(
+            Decimal128Array::from_iter((0..num_elements).map(|i| {
+                is_valid(i).then(|| i64::from_le_bytes(fixed(i).try_into().unwrap()) as i128)
+            }))
+            .with_precision_and_scale(*precision, *scale)?,
+        ),
+        DataType::Utf8 => {
+            let values = (0..num_elements)
+                .map(|i| {
+                    is_valid(i)
+                        .then(|| {
+                            variable(i).and_then(|bytes| {
+                                std::str::from_utf8(bytes)
+                                    .map_err(|e| DataFusionError::Execution(e.to_string()))
+                            })
+                        })
+                        .transpose()
+                })
+                .collect::<Result<Vec<_>>>()?;
+            Arc::new(StringArray::from(values))
+        }
+        DataType::Binary => {
+            let values = (0..num_elements)
+                .map(|i| is_valid(i).then(|| variable(i)).transpose())
+                .collect::<Result<Vec<_>>>()?;
+            Arc::new(BinaryArray::from(values))
+        }
+        _ => unreachable!(),
+    };
+    Ok(values)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use arrow_array::{cast::AsArray, types::Int32Type, ArrayRef, Int32Array, StringArray};
+    use arrow_schema::DataType;
+    use datafusion::logical_expr::Accumulator;
+    use datafusion_common::ScalarValue;
+
+    use super::{decode, encode, CollectAccumulator};
+
+    fn collect(distinct: bool) -> Vec<i32> {
+        let input: ArrayRef = Arc::new(Int32Array::from(vec![Some(3), None, Some(1), Some(3)]));
+
+        // Two partial aggregations merged into a final one
+        let mut partial1 = CollectAccumulator::new(DataType::Int32, distinct);
+        let mut partial2 = CollectAccumulator::new(DataType::Int32, distinct);
+        partial1.update_batch(&[input.clone()]).unwrap();
+        partial2.update_batch(&[input.slice(2, 2)]).unwrap();
+
+        let mut state = partial1.state().unwrap();
+        state.extend(partial2.state().unwrap());
+        let state = ScalarValue::iter_to_array(state).unwrap();
+
+        let mut fin = CollectAccumulator::new(DataType::Int32, distinct);
+        fin.merge_batch(&[state]).unwrap();
+        match fin.evaluate().unwrap() {
+            ScalarValue::List(list) => list.value(0).as_primitive::<Int32Type>().values().to_vec(),
+            other => panic!("Unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_collect_list() {
+        assert_eq!(collect(false), vec![3, 1, 3, 1, 3]);
+    }
+
+    #[test]
+    fn test_collect_set() {
+        assert_eq!(collect(true), vec![3, 1]);
+    }
+
+    #[test]
+    fn test_collect_no_values() {
+        let mut acc = CollectAccumulator::new(DataType::Int32, false);
+        acc.update_batch(&[Arc::new(Int32Array::from(vec![None]))])
+            .unwrap();
+        match acc.evaluate().unwrap() {
+            ScalarValue::List(list) => assert_eq!(list.value(0).len(), 0),
+            other => panic!("Unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_spark_buffer_format() {
+        let values: ArrayRef = Arc::new(StringArray::from(vec!["a", "bcd"]));
+        let bytes = encode(&values).unwrap();
+
+        // `UnsafeRow` of the array, which is 8 (number of elements) + 8 (null bits) + 16 (offsets
+        // and sizes) + 8 + 8 (words of the 2 strings) bytes
+        let mut expected = vec![0_i64, (16 << 32) | 48, 2, 0, (32 << 32) | 1, (40 << 32) | 3]
+            .into_iter()
+            .flat_map(i64::to_le_bytes)
+            .collect::<Vec<_>>();
+        expected.extend_from_slice(b"a\0\0\0\0\0\0\0bcd\0\0\0\0\0");
+        assert_eq!(bytes, expected);
+
+        assert_eq!(&decode(&bytes, &DataType::Utf8).unwrap(), &values);
+    }
+}
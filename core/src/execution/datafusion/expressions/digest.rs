@@ -0,0 +1,250 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Spark digest functions of binary and string values, i.e., `crc32`, `md5`, `sha1` and `sha2`.
+
+use std::sync::Arc;
+
+use arrow::{
+    buffer::{Buffer, OffsetBuffer},
+    compute::cast,
+};
+use arrow_array::{
+    cast::AsArray, new_null_array, Array, ArrayAccessor, ArrayRef, Int64Array, StringArray,
+};
+use arrow_schema::DataType;
+use datafusion::physical_plan::ColumnarValue;
+use datafusion_common::{exec_err, Result, ScalarValue};
+use digest::Digest;
+use md5::Md5;
+use sha1::Sha1;
+use sha2::{Sha224, Sha256, Sha384, Sha512};
+
+const HEX_DIGITS: &[u8; 16] = b"0123456789abcdef";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DigestFunction {
+    Crc32,
+    Md5,
+    Sha1,
+    Sha224,
+    Sha256,
+    Sha384,
+    Sha512,
+}
+
+impl DigestFunction {
+    /// The digest function of a scalar function name. The `sha2` function of Spark is one of
+    /// `sha224`, `sha256`, `sha384` and `sha512` depending on the number of bits.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "crc32" => Some(Self::Crc32),
+            "md5" => Some(Self::Md5),
+            "sha1" => Some(Self::Sha1),
+            "sha224" => Some(Self::Sha224),
+            "sha256" => Some(Self::Sha256),
+            "sha384" => Some(Self::Sha384),
+            "sha512" => Some(Self::Sha512),
+            _ => None,
+        }
+    }
+
+    /// The checksum of `crc32` is a long, and the digests are lowercase hex strings like Spark.
+    fn return_type(&self) -> DataType {
+        match self {
+            Self::Crc32 => DataType::Int64,
+            _ => DataType::Utf8,
+        }
+    }
+
+    fn evaluate(&self, array: &ArrayRef) -> Result<ArrayRef> {
+        match array.data_type() {
+            DataType::Binary => Ok(self.evaluate_bytes(array.as_binary::<i32>())),
+            DataType::LargeBinary => Ok(self.evaluate_bytes(array.as_binary::<i64>())),
+            DataType::Utf8 => Ok(self.evaluate_bytes(array.as_string::<i32>())),
+            DataType::LargeUtf8 => Ok(self.evaluate_bytes(array.as_string::<i64>())),
+            DataType::Dictionary(_, _) => {
+                // Hashes each dictionary value only once
+                let dict = array.as_any_dictionary();
+                let values = self.evaluate(dict.values())?;
+                Ok(cast(&dict.with_values(values), &self.return_type())?)
+            }
+            DataType::Null => Ok(new_null_array(&self.return_type(), array.len())),
+            other => exec_err!("Unsupported data type {other:?} for function {self:?}"),
+        }
+    }
+
+    fn evaluate_bytes<A>(&self, array: A) -> ArrayRef
+    where
+        A: ArrayAccessor,
+        A::Item: AsRef<[u8]>,
+    {
+        match self {
+            Self::Crc32 => crc32_checksums(array),
+            Self::Md5 => hex_digests::<Md5, _>(array),
+            Self::Sha1 => hex_digests::<Sha1, _>(array),
+            Self::Sha224 => hex_digests::<Sha224, _>(array),
+            Self::Sha256 => hex_digests::<Sha256, _>(array),
+            Self::Sha384 => hex_digests::<Sha384, _>(array),
+            Self::Sha512 => hex_digests::<Sha512, _>(array),
+        }
+    }
+}
+
+/// Spark `Crc32`, `Md5`, `Sha1` and `Sha2` of a binary or string value. The result of a null
+/// value is null.
+pub fn spark_digest(args: &[ColumnarValue], function: DigestFunction) -> Result<ColumnarValue> {
+    match &args[0] {
+        ColumnarValue::Array(array) => Ok(ColumnarValue::Array(function.evaluate(array)?)),
+        ColumnarValue::Scalar(scalar) => {
+            let result = function.evaluate(&scalar.to_array()?)?;
+            Ok(ColumnarValue::Scalar(ScalarValue::try_from_array(
+                &result, 0,
+            )?))
+        }
+    }
+}
+
+fn crc32_checksums<A>(array: A) -> ArrayRef
+where
+    A: ArrayAccessor,
+    A::Item: AsRef<[u8]>,
+{
+    let checksums = (0..array.len())
+        .map(|i| {
+            if array.is_valid(i) {
+                crc32fast::hash(array.value(i).as_ref()) as i64
+            } else {
+                0
+            }
+        })
+        .collect();
+    Arc::new(Int64Array::new(checksums, array.nulls().cloned()))
+}
+
+/// Computes the digests of the values into a string array directly. Every digest has the same
+/// number of hex digits, so the offsets are known upfront.
+fn hex_digests<D: Digest, A>(array: A) -> ArrayRef
+where
+    A: ArrayAccessor,
+    A::Item: AsRef<[u8]>,
+{
+    let hex_len = <D as Digest>::output_size() * 2;
+    let mut hex = Vec::with_capacity((array.len() - array.null_count()) * hex_len);
+    for i in 0..array.len() {
+        if array.is_valid(i) {
+            for byte in D::digest(array.value(i).as_ref()) {
+                hex.push(HEX_DIGITS[(byte >> 4) as usize]);
+                hex.push(HEX_DIGITS[(byte & 0xf) as usize]);
+            }
+        }
+    }
+    let offsets = OffsetBuffer::<i32>::from_lengths((0..array.len()).map(|i| {
+        if array.is_valid(i) {
+            hex_len
+        } else {
+            0
+        }
+    }));
+    Arc::new(StringArray::new(
+        offsets,
+        Buffer::from_vec(hex),
+        array.nulls().cloned(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use arrow_array::{
+        types::Int32Type, Array, ArrayRef, BinaryArray, DictionaryArray, Int64Array, StringArray,
+    };
+    use datafusion::physical_plan::ColumnarValue;
+    use datafusion_common::ScalarValue;
+
+    use super::{spark_digest, DigestFunction};
+
+    fn digest(function: &str, array: ArrayRef) -> ArrayRef {
+        let function = DigestFunction::from_name(function).unwrap();
+        match spark_digest(&[ColumnarValue::Array(array)], function).unwrap() {
+            ColumnarValue::Array(result) => result,
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn test_digests() {
+        let binary: ArrayRef = Arc::new(BinaryArray::from(vec![
+            Some("Spark".as_bytes()),
+            None,
+            Some("".as_bytes()),
+        ]));
+        // The expected results are from Spark, e.g., `SELECT md5('Spark')`
+        assert_eq!(
+            digest("crc32", binary.clone()).as_ref(),
+            &Int64Array::from(vec![Some(1557323817), None, Some(0)]) as &dyn Array
+        );
+        assert_eq!(
+            digest("md5", binary.clone()).as_ref(),
+            &StringArray::from(vec![
+                Some("8cde774d6f7333752ed72cacddb05126"),
+                None,
+                Some("d41d8cd98f00b204e9800998ecf8427e"),
+            ]) as &dyn Array
+        );
+        assert_eq!(
+            digest("sha1", binary.clone()).as_ref(),
+            &StringArray::from(vec![
+                Some("85f5955f4b27a9a4c2aab6ffe5d7189fc298b92c"),
+                None,
+                Some("da39a3ee5e6b4b0d3255bfef95601890afd80709"),
+            ]) as &dyn Array
+        );
+        assert_eq!(
+            digest("sha256", binary).as_ref(),
+            &StringArray::from(vec![
+                Some("529bc3b07127ecb7e53a4dcf1991d9152c24537d919178022b2c42657f79a26b"),
+                None,
+                Some("e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"),
+            ]) as &dyn Array
+        );
+
+        // Dictionary-encoded strings are hashed like their values
+        let dict: DictionaryArray<Int32Type> = vec![Some("Spark"), None, Some("Spark")]
+            .into_iter()
+            .collect();
+        assert_eq!(
+            digest("md5", Arc::new(dict)).as_ref(),
+            &StringArray::from(vec![
+                Some("8cde774d6f7333752ed72cacddb05126"),
+                None,
+                Some("8cde774d6f7333752ed72cacddb05126"),
+            ]) as &dyn Array
+        );
+
+        let result = spark_digest(
+            &[ColumnarValue::Scalar(ScalarValue::Utf8(None))],
+            DigestFunction::Sha512,
+        )
+        .unwrap();
+        assert!(matches!(
+            result,
+            ColumnarValue::Scalar(ScalarValue::Utf8(None))
+        ));
+    }
+}
@@ -0,0 +1,575 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use std::{
+    any::Any,
+    fmt::{Display, Formatter},
+    hash::{Hash, Hasher},
+    sync::Arc,
+};
+
+use arrow::{
+    compute::kernels::cast::cast,
+    datatypes::{
+        Decimal128Type, DecimalType, Float32Type, Float64Type, Int16Type, Int32Type, Int64Type,
+        Int8Type,
+    },
+    record_batch::RecordBatch,
+};
+use arrow_array::{
+    Array, ArrayRef, ArrowNativeTypeOp, ArrowPrimitiveType, AsArray, Decimal128Array, Int64Array,
+    PrimitiveArray,
+};
+use arrow_schema::{DataType, Schema};
+use datafusion::logical_expr::ColumnarValue;
+use datafusion_common::{DataFusionError, Result};
+use datafusion_physical_expr::PhysicalExpr;
+use num::{bigint::Sign, BigInt, Signed, ToPrimitive};
+
+use crate::{errors::CometError, execution::datafusion::expressions::utils::down_cast_any_ref};
+
+/// The Spark operators implemented by [`DivRemExpr`].
+#[derive(Debug, Hash, Clone, Copy, PartialEq, Eq)]
+pub enum DivRemOp {
+    /// `/`
+    Divide,
+    /// `div`, which returns the integral part of the quotient as a long
+    IntegralDivide,
+    /// `%`
+    Remainder,
+}
+
+impl Display for DivRemOp {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DivRemOp::Divide => write!(f, "/"),
+            DivRemOp::IntegralDivide => write!(f, "div"),
+            DivRemOp::Remainder => write!(f, "%"),
+        }
+    }
+}
+
+/// Spark `Divide`, `IntegralDivide` and `Remainder` expressions. Unlike the DataFusion operators,
+/// they return null when the divisor is zero, or fail with Spark's `DIVIDE_BY_ZERO` error in ANSI
+/// mode. Integer division and remainder wrap around on overflow as in Java, and decimal results
+/// are computed with the precision and scale of `data_type` following Spark rules.
+#[derive(Debug)]
+pub struct DivRemExpr {
+    pub left: Arc<dyn PhysicalExpr>,
+    pub right: Arc<dyn PhysicalExpr>,
+    pub op: DivRemOp,
+    pub data_type: DataType,
+    pub fail_on_error: bool,
+}
+
+impl DivRemExpr {
+    pub fn new(
+        left: Arc<dyn PhysicalExpr>,
+        right: Arc<dyn PhysicalExpr>,
+        op: DivRemOp,
+        data_type: DataType,
+        fail_on_error: bool,
+    ) -> Self {
+        Self {
+            left,
+            right,
+            op,
+            data_type,
+            fail_on_error,
+        }
+    }
+}
+
+impl Display for DivRemExpr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {} {}", self.left, self.op, self.right)
+    }
+}
+
+impl PartialEq<dyn Any> for DivRemExpr {
+    fn eq(&self, other: &dyn Any) -> bool {
+        down_cast_any_ref(other)
+            .downcast_ref::<Self>()
+            .map(|x| {
+                self.left.eq(&x.left)
+                    && self.right.eq(&x.right)
+                    && self.op.eq(&x.op)
+                    && self.data_type.eq(&x.data_type)
+                    && self.fail_on_error.eq(&x.fail_on_error)
+            })
+            .unwrap_or(false)
+    }
+}
+
+impl PhysicalExpr for DivRemExpr {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn data_type(&self, _: &Schema) -> Result<DataType> {
+        Ok(self.data_type.clone())
+    }
+
+    fn nullable(&self, _: &Schema) -> Result<bool> {
+        Ok(true)
+    }
+
+    fn evaluate(&self, batch: &RecordBatch) -> Result<ColumnarValue> {
+        let left = evaluate_to_array(&self.left, batch)?;
+        let right = evaluate_to_array(&self.right, batch)?;
+
+        let result = match (left.data_type(), right.data_type()) {
+            (DataType::Int8, DataType::Int8) => {
+                primitive_div_rem::<Int8Type>(&left, &right, self.op, self.fail_on_error)
+            }
+            (DataType::Int16, DataType::Int16) => {
+                primitive_div_rem::<Int16Type>(&left, &right, self.op, self.fail_on_error)
+            }
+            (DataType::Int32, DataType::Int32) => {
+                primitive_div_rem::<Int32Type>(&left, &right, self.op, self.fail_on_error)
+            }
+            (DataType::Int64, DataType::Int64) => {
+                primitive_div_rem::<Int64Type>(&left, &right, self.op, self.fail_on_error)
+            }
+            (DataType::Float32, DataType::Float32) if self.op != DivRemOp::IntegralDivide => {
+                primitive_div_rem::<Float32Type>(&left, &right, self.op, self.fail_on_error)
+            }
+            (DataType::Float64, DataType::Float64) if self.op != DivRemOp::IntegralDivide => {
+                primitive_div_rem::<Float64Type>(&left, &right, self.op, self.fail_on_error)
+            }
+            (DataType::Decimal128(_, _), DataType::Decimal128(_, _)) => {
+                decimal_div_rem(&left, &right, self.op, &self.data_type, self.fail_on_error)
+            }
+            (l, r) => {
+                return Err(DataFusionError::Execution(format!(
+                    "Unsupported data types for {}: {:?} and {:?}",
+                    self.op, l, r
+                )))
+            }
+        }?;
+
+        if result.data_type() != &self.data_type {
+            return Ok(ColumnarValue::Array(cast(&result, &self.data_type)?));
+        }
+        Ok(ColumnarValue::Array(result))
+    }
+
+    fn children(&self) -> Vec<Arc<dyn PhysicalExpr>> {
+        vec![self.left.clone(), self.right.clone()]
+    }
+
+    fn with_new_children(
+        self: Arc<Self>,
+        children: Vec<Arc<dyn PhysicalExpr>>,
+    ) -> Result<Arc<dyn PhysicalExpr>> {
+        Ok(Arc::new(DivRemExpr::new(
+            children[0].clone(),
+            children[1].clone(),
+            self.op,
+            self.data_type.clone(),
+            self.fail_on_error,
+        )))
+    }
+
+    fn dyn_hash(&self, state: &mut dyn Hasher) {
+        let mut s = state;
+        self.left.hash(&mut s);
+        self.right.hash(&mut s);
+        self.op.hash(&mut s);
+        self.data_type.hash(&mut s);
+        self.fail_on_error.hash(&mut s);
+    }
+}
+
+/// Evaluates `expr` into an array of the batch size, unpacking dictionary-encoded arrays.
+fn evaluate_to_array(expr: &Arc<dyn PhysicalExpr>, batch: &RecordBatch) -> Result<ArrayRef> {
+    let array = expr.evaluate(batch)?.into_array(batch.num_rows())?;
+    match array.data_type() {
+        DataType::Dictionary(_, value_type) => Ok(cast(&array, value_type)?),
+        _ => Ok(array),
+    }
+}
+
+fn primitive_div_rem<T: ArrowPrimitiveType>(
+    left: &ArrayRef,
+    right: &ArrayRef,
+    op: DivRemOp,
+    fail_on_error: bool,
+) -> Result<ArrayRef, CometError> {
+    let left = left.as_primitive::<T>();
+    let right = right.as_primitive::<T>();
+
+    let result = left
+        .iter()
+        .zip(right.iter())
+        .map(|(l, r)| match (l, r) {
+            (Some(_), Some(r)) if r.is_zero() => divide_by_zero(fail_on_error),
+            (Some(l), Some(r)) => match op {
+                DivRemOp::Remainder => Ok(Some(l.mod_wrapping(r))),
+                // Only `MIN / -1` overflows
+                _ if fail_on_error => {
+                    l.div_checked(r)
+                        .map(Some)
+                        .map_err(|_| CometError::ArithmeticOverflow {
+                            msg: format!("Overflow in {}", op_description(op)),
+                        })
+                }
+                _ => Ok(Some(l.div_wrapping(r))),
+            },
+            _ => Ok(None),
+        })
+        .collect::<Result<PrimitiveArray<T>, _>>()?;
+    Ok(Arc::new(result))
+}
+
+fn decimal_div_rem(
+    left: &ArrayRef,
+    right: &ArrayRef,
+    op: DivRemOp,
+    data_type: &DataType,
+    fail_on_error: bool,
+) -> Result<ArrayRef, CometError> {
+    let (s1, s2) = match (left.data_type(), right.data_type()) {
+        (DataType::Decimal128(_, s1), DataType::Decimal128(_, s2)) => (*s1, *s2),
+        _ => unreachable!("decimal_div_rem expects decimal arrays"),
+    };
+    let left = left.as_primitive::<Decimal128Type>();
+    let right = right.as_primitive::<Decimal128Type>();
+    let values = left.iter().zip(right.iter());
+
+    if op == DivRemOp::IntegralDivide {
+        let result = values
+            .map(|(l, r)| match (l, r) {
+                (Some(_), Some(0)) => divide_by_zero(fail_on_error),
+                (Some(l), Some(r)) => {
+                    let quotient = decimal_integral_divide(l, s1, r, s2);
+                    match quotient.to_i64() {
+                        Some(quotient) => Ok(Some(quotient)),
+                        None if fail_on_error => Err(CometError::ArithmeticOverflow {
+                            msg: format!("Overflow in {}", op_description(op)),
+                        }),
+                        // Like `BigDecimal.longValue`, keep the low-order 64 bits
+                        None => Ok(Some(wrap_to_i64(&quotient))),
+                    }
+                }
+                _ => Ok(None),
+            })
+            .collect::<Result<Int64Array, _>>()?;
+        return Ok(Arc::new(result));
+    }
+
+    let (p3, s3) = match data_type {
+        DataType::Decimal128(p3, s3) => (*p3, *s3),
+        dt => {
+            return Err(CometError::Internal(format!(
+                "Expected a decimal result type for decimal {}, but got {}",
+                op_description(op),
+                dt
+            )))
+        }
+    };
+    let result = values
+        .map(|(l, r)| match (l, r) {
+            (Some(_), Some(0)) => divide_by_zero(fail_on_error),
+            (Some(l), Some(r)) => {
+                let value = if op == DivRemOp::Divide {
+                    decimal_divide(l, s1, r, s2, s3)
+                } else {
+                    decimal_remainder(l, s1, r, s2, s3)
+                };
+                match value
+                    .to_i128()
+                    .filter(|v| Decimal128Type::validate_decimal_precision(*v, p3).is_ok())
+                {
+                    Some(value) => Ok(Some(value)),
+                    None if fail_on_error => Err(CometError::NumericValueOutOfRange {
+                        value: format_decimal(&value, s3),
+                        precision: p3,
+                        scale: s3,
+                    }),
+                    None => Ok(None),
+                }
+            }
+            _ => Ok(None),
+        })
+        .collect::<Result<Decimal128Array, _>>()?
+        .with_data_type(DataType::Decimal128(p3, s3));
+    Ok(Arc::new(result))
+}
+
+fn divide_by_zero<T>(fail_on_error: bool) -> Result<Option<T>, CometError> {
+    if fail_on_error {
+        Err(CometError::DivideByZero)
+    } else {
+        Ok(None)
+    }
+}
+
+fn op_description(op: DivRemOp) -> &'static str {
+    match op {
+        DivRemOp::Divide => "divide",
+        DivRemOp::IntegralDivide => "integral divide",
+        DivRemOp::Remainder => "remainder",
+    }
+}
+
+fn pow10(exp: i32) -> BigInt {
+    BigInt::from(10).pow(exp.max(0) as u32)
+}
+
+/// Rescales the unscaled values of two decimals to their common scale.
+fn to_common_scale(l: i128, s1: i8, r: i128, s2: i8) -> (BigInt, BigInt, i8) {
+    let scale = s1.max(s2);
+    let l = BigInt::from(l) * pow10((scale - s1) as i32);
+    let r = BigInt::from(r) * pow10((scale - s2) as i32);
+    (l, r, scale)
+}
+
+// Let Decimal(p3, s3) as return type i.e. Decimal(p1, s1) / Decimal(p2, s2) = Decimal(p3, s3).
+// Conversely, Decimal(p1, s1) = Decimal(p2, s2) * Decimal(p3, s3). This means that, in order to
+// get enough scale that matches with Spark behavior, it requires to widen s1 to s2 + s3 + 1. Since
+// both s2 and s3 are 38 at max., s1 is 77 at max. DataFusion division cannot handle such scale >
+// Decimal256Type::MAX_SCALE. Therefore, we need to implement this decimal division using BigInt.
+fn decimal_divide(l: i128, s1: i8, r: i128, s2: i8, s3: i8) -> BigInt {
+    let l = BigInt::from(l) * pow10(s2 as i32 + s3 as i32 + 1 - s1 as i32);
+    let r = BigInt::from(r) * pow10(s1 as i32 - (s2 as i32 + s3 as i32 + 1));
+    // The quotient has one more digit than the result scale, which is rounded HALF_UP
+    let five = BigInt::from(5);
+    let quotient = l / r;
+    if quotient.is_negative() {
+        (quotient - five) / 10
+    } else {
+        (quotient + five) / 10
+    }
+}
+
+/// The remainder has the sign of the dividend, like `java.math.BigDecimal.remainder`.
+fn decimal_remainder(l: i128, s1: i8, r: i128, s2: i8, s3: i8) -> BigInt {
+    let (l, r, scale) = to_common_scale(l, s1, r, s2);
+    let remainder = l % r;
+    if s3 >= scale {
+        remainder * pow10((s3 - scale) as i32)
+    } else {
+        let divisor = pow10((scale - s3) as i32);
+        let half = &divisor / 2;
+        if remainder.is_negative() {
+            (remainder - half) / divisor
+        } else {
+            (remainder + half) / divisor
+        }
+    }
+}
+
+/// The quotient is truncated towards zero.
+fn decimal_integral_divide(l: i128, s1: i8, r: i128, s2: i8) -> BigInt {
+    let (l, r, _) = to_common_scale(l, s1, r, s2);
+    l / r
+}
+
+fn wrap_to_i64(value: &BigInt) -> i64 {
+    let (sign, digits) = value.to_u64_digits();
+    let low = digits.first().copied().unwrap_or(0) as i64;
+    if sign == Sign::Minus {
+        low.wrapping_neg()
+    } else {
+        low
+    }
+}
+
+/// Formats an unscaled decimal value with the given scale, e.g. for error messages.
+fn format_decimal(value: &BigInt, scale: i8) -> String {
+    if scale <= 0 {
+        return (value * pow10(-scale as i32)).to_string();
+    }
+    let digits = value.abs().to_string();
+    let scale = scale as usize;
+    let digits = if digits.len() <= scale {
+        format!("{}{}", "0".repeat(scale + 1 - digits.len()), digits)
+    } else {
+        digits
+    };
+    let (integral, fractional) = digits.split_at(digits.len() - scale);
+    let sign = if value.is_negative() { "-" } else { "" };
+    format!("{}{}.{}", sign, integral, fractional)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use arrow_array::{
+        Array, ArrayRef, Decimal128Array, Float64Array, Int32Array, Int64Array, RecordBatch,
+    };
+    use arrow_schema::{DataType, Field, Schema};
+    use datafusion::physical_expr::{expressions::Column, PhysicalExpr};
+
+    use super::{DivRemExpr, DivRemOp};
+
+    fn evaluate(
+        left: ArrayRef,
+        right: ArrayRef,
+        op: DivRemOp,
+        data_type: DataType,
+        fail_on_error: bool,
+    ) -> datafusion_common::Result<ArrayRef> {
+        let schema = Schema::new(vec![
+            Field::new("l", left.data_type().clone(), true),
+            Field::new("r", right.data_type().clone(), true),
+        ]);
+        let batch = RecordBatch::try_new(Arc::new(schema), vec![left, right])?;
+        let expr = DivRemExpr::new(
+            Arc::new(Column::new("l", 0)),
+            Arc::new(Column::new("r", 1)),
+            op,
+            data_type,
+            fail_on_error,
+        );
+        expr.evaluate(&batch)?.into_array(batch.num_rows())
+    }
+
+    #[test]
+    fn test_divide_by_zero() {
+        let left: ArrayRef = Arc::new(Int64Array::from(vec![Some(7), Some(-7), None, Some(1)]));
+        let right: ArrayRef = Arc::new(Int64Array::from(vec![Some(2), Some(2), Some(0), Some(0)]));
+
+        let result = evaluate(
+            left.clone(),
+            right.clone(),
+            DivRemOp::IntegralDivide,
+            DataType::Int64,
+            false,
+        )
+        .unwrap();
+        let expected = Int64Array::from(vec![Some(3), Some(-3), None, None]);
+        assert_eq!(result.as_ref(), &expected as &dyn Array);
+
+        // A null dividend returns null even in ANSI mode, but a zero divisor fails
+        let result = evaluate(
+            left.slice(0, 3),
+            right.slice(0, 3),
+            DivRemOp::Remainder,
+            DataType::Int64,
+            true,
+        )
+        .unwrap();
+        let expected = Int64Array::from(vec![Some(1), Some(-1), None]);
+        assert_eq!(result.as_ref(), &expected as &dyn Array);
+        let err = evaluate(left, right, DivRemOp::Remainder, DataType::Int64, true).unwrap_err();
+        assert!(err.to_string().contains("[DIVIDE_BY_ZERO]"));
+
+        let left: ArrayRef = Arc::new(Float64Array::from(vec![1.0, 1.0]));
+        let right: ArrayRef = Arc::new(Float64Array::from(vec![-0.0, 4.0]));
+        let result = evaluate(left, right, DivRemOp::Divide, DataType::Float64, false).unwrap();
+        let expected = Float64Array::from(vec![None, Some(0.25)]);
+        assert_eq!(result.as_ref(), &expected as &dyn Array);
+    }
+
+    #[test]
+    fn test_integer_overflow() {
+        let left: ArrayRef = Arc::new(Int32Array::from(vec![i32::MIN]));
+        let right: ArrayRef = Arc::new(Int32Array::from(vec![-1]));
+        let result = evaluate(
+            left.clone(),
+            right.clone(),
+            DivRemOp::Remainder,
+            DataType::Int32,
+            true,
+        )
+        .unwrap();
+        assert_eq!(result.as_ref(), &Int32Array::from(vec![0]) as &dyn Array);
+
+        let left: ArrayRef = Arc::new(Int64Array::from(vec![i64::MIN]));
+        let right: ArrayRef = Arc::new(Int64Array::from(vec![-1]));
+        let result = evaluate(
+            left.clone(),
+            right.clone(),
+            DivRemOp::IntegralDivide,
+            DataType::Int64,
+            false,
+        )
+        .unwrap();
+        assert_eq!(
+            result.as_ref(),
+            &Int64Array::from(vec![i64::MIN]) as &dyn Array
+        );
+        let err =
+            evaluate(left, right, DivRemOp::IntegralDivide, DataType::Int64, true).unwrap_err();
+        assert!(err.to_string().contains("[ARITHMETIC_OVERFLOW]"));
+    }
+
+    #[test]
+    fn test_decimal() {
+        // 1.00, -2.50 and 10.00 of Decimal(5, 2), divided by 0.30, 0.30 and 0.00 of Decimal(3, 2)
+        let left: ArrayRef = Arc::new(
+            Decimal128Array::from(vec![100, -250, 1000]).with_data_type(DataType::Decimal128(5, 2)),
+        );
+        let right: ArrayRef = Arc::new(
+            Decimal128Array::from(vec![30, 30, 0]).with_data_type(DataType::Decimal128(3, 2)),
+        );
+
+        // Decimal(5, 2) / Decimal(3, 2) is Decimal(11, 6) in Spark
+        let data_type = DataType::Decimal128(11, 6);
+        let result = evaluate(
+            left.clone(),
+            right.clone(),
+            DivRemOp::Divide,
+            data_type.clone(),
+            false,
+        )
+        .unwrap();
+        let expected = Decimal128Array::from(vec![Some(3333333), Some(-8333333), None])
+            .with_data_type(data_type);
+        assert_eq!(result.as_ref(), &expected as &dyn Array);
+
+        let data_type = DataType::Decimal128(3, 2);
+        let result = evaluate(
+            left.clone(),
+            right.clone(),
+            DivRemOp::Remainder,
+            data_type.clone(),
+            false,
+        )
+        .unwrap();
+        let expected =
+            Decimal128Array::from(vec![Some(10), Some(-10), None]).with_data_type(data_type);
+        assert_eq!(result.as_ref(), &expected as &dyn Array);
+
+        let result = evaluate(
+            left.clone(),
+            right.clone(),
+            DivRemOp::IntegralDivide,
+            DataType::Int64,
+            false,
+        )
+        .unwrap();
+        let expected = Int64Array::from(vec![Some(3), Some(-8), None]);
+        assert_eq!(result.as_ref(), &expected as &dyn Array);
+
+        // The quotient overflows the result precision
+        let err = evaluate(
+            left,
+            right,
+            DivRemOp::Divide,
+            DataType::Decimal128(6, 6),
+            true,
+        )
+        .unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("[NUMERIC_VALUE_OUT_OF_RANGE] 3.333333 cannot be represented"));
+    }
+}
@@ -0,0 +1,555 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use std::{
+    any::Any,
+    collections::HashMap,
+    fmt::{Display, Formatter},
+    hash::{Hash, Hasher},
+    sync::Arc,
+};
+
+use arrow::{compute::take, record_batch::RecordBatch};
+use arrow_array::{
+    new_empty_array, Array, ArrayRef, AsArray, BinaryArray, BooleanArray, Date32Array,
+    Decimal128Array, Float32Array, Float64Array, Int16Array, Int32Array, Int64Array, Int8Array,
+    LargeStringArray, StringArray, TimestampMicrosecondArray,
+};
+use arrow_schema::{DataType, Schema, TimeUnit};
+use datafusion::{logical_expr::ColumnarValue, physical_expr::expressions::Literal};
+use datafusion_common::{Result, ScalarValue};
+use datafusion_physical_expr::PhysicalExpr;
+
+use crate::execution::datafusion::{
+    expressions::utils::down_cast_any_ref, spark_hash::create_hashes,
+};
+
+/// The minimum number of literals for a Spark `In` to be evaluated with [`InSetExpr`]. Spark only
+/// converts `In` to `InSet` if there are more than `spark.sql.optimizer.inSetConversionThreshold`
+/// literals, so a list of exactly this many literals may still be an `In`, whose values are
+/// compared like `In`. A Spark `InSet` is evaluated with [`InSetExpr`] whatever its size.
+pub const IN_SET_THRESHOLD: usize = 10;
+
+/// The seed of the murmur3 hashes of the values.
+const HASH_SEED: u32 = 42;
+
+/// Spark `InSet` expression, i.e., an IN list of literals. The literals are hashed once when the
+/// plan is created, so that looking up a row only compares it with the literals of the same
+/// hash. For a dictionary-encoded value, only the dictionary values are looked up.
+///
+/// Like Spark, the result is null if the value is null, or if it is not found and the list
+/// contains a null. The floating-point values of an `InSet` are compared like the boxed values of
+/// its hash set, i.e., by their bits with all the NaNs normalized, so -0.0 is not equal to 0.0.
+/// The ones of an `In` are compared like `In`, where -0.0 is equal to 0.0. In both cases, NaN is
+/// equal to NaN.
+#[derive(Debug)]
+pub struct InSetExpr {
+    value: Arc<dyn PhysicalExpr>,
+    set: Arc<HashedValues>,
+    negated: bool,
+}
+
+impl InSetExpr {
+    /// Creates the expression if all items of `list` are literals of the value type, and either
+    /// the list is the set of a Spark `InSet`, i.e., `in_set` is true, or there are at least
+    /// [`IN_SET_THRESHOLD`] of them. Returns `None` otherwise.
+    pub fn try_new(
+        value: Arc<dyn PhysicalExpr>,
+        list: &[Arc<dyn PhysicalExpr>],
+        negated: bool,
+        in_set: bool,
+        input_schema: &Schema,
+    ) -> Result<Option<Self>> {
+        if !in_set && list.len() < IN_SET_THRESHOLD {
+            return Ok(None);
+        }
+        let data_type = match value.data_type(input_schema)? {
+            DataType::Dictionary(_, value_type) => *value_type,
+            data_type => data_type,
+        };
+        if !is_supported(&data_type) {
+            return Ok(None);
+        }
+
+        let mut literals = Vec::with_capacity(list.len());
+        for item in list {
+            match item.as_any().downcast_ref::<Literal>() {
+                Some(literal) if literal.value().data_type() == data_type => {
+                    literals.push(literal.value().clone())
+                }
+                _ => return Ok(None),
+            }
+        }
+
+        let set = HashedValues::try_new(literals, &data_type, in_set)?;
+        Ok(Some(Self {
+            value,
+            set: Arc::new(set),
+            negated,
+        }))
+    }
+}
+
+impl Display for InSetExpr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let op = if self.negated { "NOT IN" } else { "IN" };
+        write!(
+            f,
+            "{} {} SET ({} values)",
+            self.value,
+            op,
+            self.set.literals.len()
+        )
+    }
+}
+
+impl PartialEq<dyn Any> for InSetExpr {
+    fn eq(&self, other: &dyn Any) -> bool {
+        down_cast_any_ref(other)
+            .downcast_ref::<Self>()
+            .map(|x| {
+                self.value.eq(&x.value)
+                    && self.negated == x.negated
+                    && self.set.literals == x.set.literals
+                    && self.set.in_set == x.set.in_set
+            })
+            .unwrap_or(false)
+    }
+}
+
+impl PhysicalExpr for InSetExpr {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn data_type(&self, _: &Schema) -> Result<DataType> {
+        Ok(DataType::Boolean)
+    }
+
+    fn nullable(&self, input_schema: &Schema) -> Result<bool> {
+        Ok(self.set.contains_null || self.value.nullable(input_schema)?)
+    }
+
+    fn evaluate(&self, batch: &RecordBatch) -> Result<ColumnarValue> {
+        let value = self.value.evaluate(batch)?.into_array(batch.num_rows())?;
+        let result = match value.data_type() {
+            DataType::Dictionary(_, _) => {
+                let dictionary = value.as_any_dictionary();
+                let found = self.set.contains(dictionary.values(), self.negated)?;
+                take(&found, dictionary.keys(), None)?
+            }
+            _ => Arc::new(self.set.contains(&value, self.negated)?),
+        };
+        Ok(ColumnarValue::Array(result))
+    }
+
+    fn children(&self) -> Vec<Arc<dyn PhysicalExpr>> {
+        vec![self.value.clone()]
+    }
+
+    fn with_new_children(
+        self: Arc<Self>,
+        children: Vec<Arc<dyn PhysicalExpr>>,
+    ) -> Result<Arc<dyn PhysicalExpr>> {
+        Ok(Arc::new(Self {
+            value: children[0].clone(),
+            set: Arc::clone(&self.set),
+            negated: self.negated,
+        }))
+    }
+
+    fn dyn_hash(&self, state: &mut dyn Hasher) {
+        let mut s = state;
+        self.value.hash(&mut s);
+        self.negated.hash(&mut s);
+        self.set.literals.hash(&mut s);
+        self.set.in_set.hash(&mut s);
+    }
+}
+
+/// The non-null literals of an IN list, indexed by their murmur3 hashes.
+#[derive(Debug)]
+struct HashedValues {
+    /// The literals of the list, including nulls
+    literals: Vec<ScalarValue>,
+    /// The non-null literals
+    values: ArrayRef,
+    /// The indices in `values` of the literals of each hash
+    buckets: HashMap<u32, Vec<usize>>,
+    contains_null: bool,
+    /// Whether the literals are the set of a Spark `InSet`, whose floating-point values are
+    /// compared by their bits
+    in_set: bool,
+}
+
+impl HashedValues {
+    fn try_new(literals: Vec<ScalarValue>, data_type: &DataType, in_set: bool) -> Result<Self> {
+        let contains_null = literals.iter().any(|literal| literal.is_null());
+        let non_null = literals
+            .iter()
+            .filter(|literal| !literal.is_null())
+            .cloned()
+            .collect::<Vec<_>>();
+        let values = if non_null.is_empty() {
+            new_empty_array(data_type)
+        } else {
+            ScalarValue::iter_to_array(non_null)?
+        };
+
+        let mut hashes = vec![HASH_SEED; values.len()];
+        create_hashes(&[Arc::clone(&values)], &mut hashes)?;
+        let mut buckets: HashMap<u32, Vec<usize>> = HashMap::with_capacity(values.len());
+        for (index, hash) in hashes.into_iter().enumerate() {
+            buckets.entry(hash).or_default().push(index);
+        }
+
+        Ok(Self {
+            literals,
+            values,
+            buckets,
+            contains_null,
+            in_set,
+        })
+    }
+
+    /// Looks up the values of `array`, which must be of the type of the literals.
+    fn contains(&self, array: &ArrayRef, negated: bool) -> Result<BooleanArray> {
+        let mut hashes = vec![HASH_SEED; array.len()];
+        create_hashes(&[Arc::clone(array)], &mut hashes)?;
+        let equal = equal_fn(array.as_ref(), self.values.as_ref(), self.in_set);
+
+        let result = hashes
+            .iter()
+            .enumerate()
+            .map(|(row, hash)| {
+                if array.is_null(row) {
+                    return None;
+                }
+                let found = self.buckets.get(hash).map_or(false, |indices| {
+                    indices.iter().any(|index| equal(row, *index))
+                });
+                if found {
+                    Some(!negated)
+                } else if self.contains_null {
+                    None
+                } else {
+                    Some(negated)
+                }
+            })
+            .collect();
+        Ok(result)
+    }
+}
+
+fn is_supported(data_type: &DataType) -> bool {
+    matches!(
+        data_type,
+        DataType::Int8
+            | DataType::Int16
+            | DataType::Int32
+            | DataType::Int64
+            | DataType::Float32
+            | DataType::Float64
+            | DataType::Date32
+            | DataType::Timestamp(TimeUnit::Microsecond, _)
+            | DataType::Decimal128(_, _)
+            | DataType::Utf8
+            | DataType::LargeUtf8
+            | DataType::Binary
+    )
+}
+
+macro_rules! equal_values {
+    ($left: ident, $right: ident, $array_type: ident) => {{
+        let left = $left.as_any().downcast_ref::<$array_type>().unwrap();
+        let right = $right.as_any().downcast_ref::<$array_type>().unwrap();
+        Box::new(move |i: usize, j: usize| left.value(i) == right.value(j))
+    }};
+}
+
+macro_rules! equal_float_values {
+    ($left: ident, $right: ident, $array_type: ident, $in_set: ident) => {{
+        let left = $left.as_any().downcast_ref::<$array_type>().unwrap();
+        let right = $right.as_any().downcast_ref::<$array_type>().unwrap();
+        if $in_set {
+            // Like the boxed values in the hash set of Spark's `InSet`, i.e., `equals`, which
+            // compares the bits of the values with all the NaNs normalized: NaN is equal to NaN,
+            // and -0.0 is not equal to 0.0
+            Box::new(move |i: usize, j: usize| {
+                let (l, r) = (left.value(i), right.value(j));
+                if l.is_nan() || r.is_nan() {
+                    l.is_nan() && r.is_nan()
+                } else {
+                    l.to_bits() == r.to_bits()
+                }
+            })
+        } else {
+            // Like Spark's `In`: NaN is equal to NaN, and -0.0 is equal to 0.0
+            Box::new(move |i: usize, j: usize| {
+                let (l, r) = (left.value(i), right.value(j));
+                l == r || (l.is_nan() && r.is_nan())
+            })
+        }
+    }};
+}
+
+/// Returns a function comparing a value of `left` with a value of `right`, which are arrays of
+/// the same supported type. The floating-point values are compared like Spark's `InSet` if
+/// `in_set` is true, otherwise like Spark's `In`.
+fn equal_fn<'a>(
+    left: &'a dyn Array,
+    right: &'a dyn Array,
+    in_set: bool,
+) -> Box<dyn Fn(usize, usize) -> bool + 'a> {
+    match left.data_type() {
+        DataType::Int8 => equal_values!(left, right, Int8Array),
+        DataType::Int16 => equal_values!(left, right, Int16Array),
+        DataType::Int32 => equal_values!(left, right, Int32Array),
+        DataType::Int64 => equal_values!(left, right, Int64Array),
+        DataType::Float32 => equal_float_values!(left, right, Float32Array, in_set),
+        DataType::Float64 => equal_float_values!(left, right, Float64Array, in_set),
+        DataType::Date32 => equal_values!(left, right, Date32Array),
+        DataType::Timestamp(TimeUnit::Microsecond, _) => {
+            equal_values!(left, right, TimestampMicrosecondArray)
+        }
+        DataType::Decimal128(_, _) => equal_values!(left, right, Decimal128Array),
+        DataType::Utf8 => equal_values!(left, right, StringArray),
+        DataType::LargeUtf8 => equal_values!(left, right, LargeStringArray),
+        DataType::Binary => equal_values!(left, right, BinaryArray),
+        data_type => unreachable!("Unsupported data type in InSetExpr: {}", data_type),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use arrow_array::{
+        types::Int32Type, Array, BooleanArray, Decimal128Array, DictionaryArray, Float32Array,
+        Float64Array, RecordBatch,
+    };
+    use arrow_schema::{DataType, Field, Schema};
+    use datafusion::physical_expr::{
+        expressions::{Column, Literal},
+        PhysicalExpr,
+    };
+    use datafusion_common::ScalarValue;
+
+    use super::InSetExpr;
+
+    fn evaluate(in_set: &InSetExpr, batch: &RecordBatch) -> BooleanArray {
+        let result = in_set
+            .evaluate(batch)
+            .unwrap()
+            .into_array(batch.num_rows())
+            .unwrap();
+        result
+            .as_any()
+            .downcast_ref::<BooleanArray>()
+            .unwrap()
+            .clone()
+    }
+
+    #[test]
+    fn test_in_set_strings() {
+        let values =
+            DictionaryArray::<Int32Type>::from_iter([Some("v3"), None, Some("x"), Some("v3")]);
+        let schema = Schema::new(vec![Field::new("a", values.data_type().clone(), true)]);
+        let batch = RecordBatch::try_new(Arc::new(schema.clone()), vec![Arc::new(values)]).unwrap();
+
+        let mut list = (0..10)
+            .map(|i| {
+                Arc::new(Literal::new(ScalarValue::Utf8(Some(format!("v{}", i)))))
+                    as Arc<dyn PhysicalExpr>
+            })
+            .collect::<Vec<_>>();
+        let value = Arc::new(Column::new("a", 0));
+
+        // Too few literals
+        assert!(
+            InSetExpr::try_new(value.clone(), &list[..9], false, false, &schema)
+                .unwrap()
+                .is_none()
+        );
+
+        let in_set = InSetExpr::try_new(value.clone(), &list, false, false, &schema)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            evaluate(&in_set, &batch),
+            BooleanArray::from(vec![Some(true), None, Some(false), Some(true)])
+        );
+
+        // A value not in the list is null if the list contains a null
+        list.push(Arc::new(Literal::new(ScalarValue::Utf8(None))));
+        let not_in_set = InSetExpr::try_new(value, &list, true, false, &schema)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            evaluate(&not_in_set, &batch),
+            BooleanArray::from(vec![Some(false), None, None, Some(false)])
+        );
+    }
+
+    #[test]
+    fn test_in_set_decimals() {
+        let data_type = DataType::Decimal128(10, 2);
+        let values = Decimal128Array::from(vec![Some(1234), Some(-5), None])
+            .with_data_type(data_type.clone());
+        let schema = Schema::new(vec![Field::new("a", data_type, true)]);
+        let batch = RecordBatch::try_new(Arc::new(schema.clone()), vec![Arc::new(values)]).unwrap();
+
+        let list = (0..20)
+            .map(|i| {
+                Arc::new(Literal::new(ScalarValue::Decimal128(Some(i * 617), 10, 2)))
+                    as Arc<dyn PhysicalExpr>
+            })
+            .collect::<Vec<_>>();
+        let in_set =
+            InSetExpr::try_new(Arc::new(Column::new("a", 0)), &list, false, false, &schema)
+                .unwrap()
+                .unwrap();
+        assert_eq!(
+            evaluate(&in_set, &batch),
+            BooleanArray::from(vec![Some(true), Some(false), None])
+        );
+    }
+
+    #[test]
+    fn test_in_set_doubles() {
+        let values = Float64Array::from(vec![
+            Some(0.0),
+            Some(-0.0),
+            Some(f64::NAN),
+            Some(-f64::NAN),
+            Some(f64::from_bits(f64::NAN.to_bits() + 1)),
+            Some(3.0),
+            Some(0.5),
+            None,
+        ]);
+        let schema = Schema::new(vec![Field::new("a", DataType::Float64, true)]);
+        let batch = RecordBatch::try_new(Arc::new(schema.clone()), vec![Arc::new(values)]).unwrap();
+        let value = Arc::new(Column::new("a", 0));
+
+        // 0.0, 1.0, ..., 9.0, which Spark doesn't convert to an `InSet`
+        let mut list = (0..10)
+            .map(|i| {
+                Arc::new(Literal::new(ScalarValue::Float64(Some(i as f64))))
+                    as Arc<dyn PhysicalExpr>
+            })
+            .collect::<Vec<_>>();
+        let in_list = InSetExpr::try_new(value.clone(), &list, false, false, &schema)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            evaluate(&in_list, &batch),
+            BooleanArray::from(vec![
+                Some(true),
+                Some(true),
+                Some(false),
+                Some(false),
+                Some(false),
+                Some(true),
+                Some(false),
+                None,
+            ])
+        );
+
+        // The same literals as the set of an `InSet`
+        let in_set = InSetExpr::try_new(value.clone(), &list, false, true, &schema)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            evaluate(&in_set, &batch),
+            BooleanArray::from(vec![
+                Some(true),
+                Some(false),
+                Some(false),
+                Some(false),
+                Some(false),
+                Some(true),
+                Some(false),
+                None,
+            ])
+        );
+
+        // -0.0 and NaN, which matches all the NaNs
+        list[0] = Arc::new(Literal::new(ScalarValue::Float64(Some(-0.0))));
+        list.push(Arc::new(Literal::new(ScalarValue::Float64(Some(f64::NAN)))));
+        let in_set = InSetExpr::try_new(value.clone(), &list, false, true, &schema)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            evaluate(&in_set, &batch),
+            BooleanArray::from(vec![
+                Some(false),
+                Some(true),
+                Some(true),
+                Some(true),
+                Some(true),
+                Some(true),
+                Some(false),
+                None,
+            ])
+        );
+
+        // The set of an `InSet` is looked up whatever its size
+        let in_set = InSetExpr::try_new(value, &list[..1], false, true, &schema)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            evaluate(&in_set, &batch),
+            BooleanArray::from(vec![
+                Some(false),
+                Some(true),
+                Some(false),
+                Some(false),
+                Some(false),
+                Some(false),
+                Some(false),
+                None,
+            ])
+        );
+    }
+
+    #[test]
+    fn test_in_set_floats() {
+        let values = Float32Array::from(vec![Some(0.0), Some(-0.0), Some(f32::NAN), Some(1.0)]);
+        let schema = Schema::new(vec![Field::new("a", DataType::Float32, true)]);
+        let batch = RecordBatch::try_new(Arc::new(schema.clone()), vec![Arc::new(values)]).unwrap();
+
+        // -0.0, NaN, 2.0, ..., 9.0
+        let list = (0..10)
+            .map(|i| {
+                let literal = match i {
+                    0 => -0.0,
+                    1 => -f32::NAN,
+                    i => i as f32,
+                };
+                Arc::new(Literal::new(ScalarValue::Float32(Some(literal)))) as Arc<dyn PhysicalExpr>
+            })
+            .collect::<Vec<_>>();
+        let not_in_set =
+            InSetExpr::try_new(Arc::new(Column::new("a", 0)), &list, true, true, &schema)
+                .unwrap()
+                .unwrap();
+        assert_eq!(
+            evaluate(&not_in_set, &batch),
+            BooleanArray::from(vec![Some(true), Some(false), Some(false), Some(true)])
+        );
+    }
+}
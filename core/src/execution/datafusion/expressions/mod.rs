@@ -17,21 +17,33 @@
 
 //! Native DataFusion expressions
 
+pub mod approx_percentile;
 pub mod bitwise_not;
+pub mod case_when;
 pub mod cast;
 pub mod checkoverflow;
+pub mod collect;
 pub mod if_expr;
+pub mod in_set;
+pub mod nondeterministic;
 mod normalize_nan;
+pub mod percentile;
 pub mod scalar_funcs;
+pub mod sequential_sum;
 pub use normalize_nan::NormalizeNaNAndZero;
 pub mod avg;
 pub mod avg_decimal;
+pub mod bloom_filter_agg;
 pub mod bloom_filter_might_contain;
 pub mod covariance;
+pub mod digest;
+pub mod div_rem;
 pub mod stats;
 pub mod strings;
+pub mod structs;
 pub mod subquery;
 pub mod sum_decimal;
 pub mod temporal;
+pub mod try_sum;
 mod utils;
 pub mod variance;
@@ -0,0 +1,306 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Nondeterministic expressions. Like Spark, their values only depend on the seeds in the plan,
+//! the index of the partition and the number of rows evaluated before, so that task retries and
+//! speculative tasks produce the same output as the first attempt.
+
+use std::{
+    any::Any,
+    fmt::{Display, Formatter},
+    hash::{Hash, Hasher},
+    sync::{Arc, Mutex},
+};
+
+use arrow::{
+    array::{Float64Array, Int64Array},
+    record_batch::RecordBatch,
+};
+use arrow_schema::{DataType, Schema};
+use datafusion::logical_expr::ColumnarValue;
+use datafusion_common::Result;
+use datafusion_physical_expr::PhysicalExpr;
+
+use crate::execution::datafusion::expressions::utils::down_cast_any_ref;
+
+/// The seed of Scala `MurmurHash3.arrayHash` and `MurmurHash3.bytesHash`
+const ARRAY_SEED: i32 = 0x3c074a61;
+
+/// The random number generator of Spark `XORShiftRandom`.
+#[derive(Debug)]
+pub struct XorShiftRandom {
+    seed: i64,
+}
+
+impl XorShiftRandom {
+    pub fn new(init: i64) -> Self {
+        Self {
+            seed: hash_seed(init),
+        }
+    }
+
+    fn next(&mut self, bits: u32) -> i32 {
+        let mut next_seed = self.seed ^ (self.seed << 21);
+        next_seed ^= ((next_seed as u64) >> 35) as i64;
+        next_seed ^= next_seed << 4;
+        self.seed = next_seed;
+        (next_seed & ((1i64 << bits) - 1)) as i32
+    }
+
+    /// Returns the next value in [0, 1), like `java.util.Random.nextDouble`.
+    pub fn next_double(&mut self) -> f64 {
+        let high = (self.next(26) as i64) << 27;
+        let low = self.next(27) as i64;
+        (high + low) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// Spreads the bits of the initial seed like `XORShiftRandom.hashSeed` of Spark.
+fn hash_seed(seed: i64) -> i64 {
+    let bytes = seed.to_be_bytes();
+    let low_bits = scala_bytes_hash(&bytes, ARRAY_SEED);
+    let high_bits = scala_bytes_hash(&bytes, low_bits);
+    ((high_bits as i64) << 32) | (low_bits as i64 & 0xFFFF_FFFF)
+}
+
+/// Scala `MurmurHash3.bytesHash`, which differs from the Murmur3 hash of Spark in the handling
+/// of the trailing bytes.
+fn scala_bytes_hash(data: &[u8], seed: i32) -> i32 {
+    let mut hash = seed;
+    let mut chunks = data.chunks_exact(4);
+    for chunk in &mut chunks {
+        hash = mix(hash, i32::from_le_bytes(chunk.try_into().unwrap()));
+    }
+    let tail = chunks.remainder();
+    if !tail.is_empty() {
+        let k = tail
+            .iter()
+            .enumerate()
+            .fold(0i32, |k, (i, byte)| k ^ ((*byte as i32) << (8 * i)));
+        hash = mix_last(hash, k);
+    }
+    avalanche(hash ^ data.len() as i32)
+}
+
+fn mix_last(hash: i32, data: i32) -> i32 {
+    let k = data
+        .wrapping_mul(0xcc9e2d51u32 as i32)
+        .rotate_left(15)
+        .wrapping_mul(0x1b873593);
+    hash ^ k
+}
+
+fn mix(hash: i32, data: i32) -> i32 {
+    mix_last(hash, data)
+        .rotate_left(13)
+        .wrapping_mul(5)
+        .wrapping_add(0xe6546b64u32 as i32)
+}
+
+fn avalanche(hash: i32) -> i32 {
+    let mut h = hash as u32;
+    h ^= h >> 16;
+    h = h.wrapping_mul(0x85ebca6b);
+    h ^= h >> 13;
+    h = h.wrapping_mul(0xc2b2ae35);
+    h ^= h >> 16;
+    h as i32
+}
+
+/// Spark `Rand`, which returns random values in [0, 1) from a generator seeded with the seed of
+/// the expression plus the partition index.
+#[derive(Debug)]
+pub struct RandExpr {
+    seed: i64,
+    partition_index: i32,
+    rng: Mutex<XorShiftRandom>,
+}
+
+impl RandExpr {
+    pub fn new(seed: i64, partition_index: i32) -> Self {
+        Self {
+            seed,
+            partition_index,
+            rng: Mutex::new(XorShiftRandom::new(
+                seed.wrapping_add(partition_index as i64),
+            )),
+        }
+    }
+}
+
+impl Display for RandExpr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "rand({})", self.seed)
+    }
+}
+
+impl PartialEq<dyn Any> for RandExpr {
+    fn eq(&self, other: &dyn Any) -> bool {
+        down_cast_any_ref(other)
+            .downcast_ref::<Self>()
+            .map(|x| self.seed == x.seed && self.partition_index == x.partition_index)
+            .unwrap_or(false)
+    }
+}
+
+impl PhysicalExpr for RandExpr {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn data_type(&self, _input_schema: &Schema) -> Result<DataType> {
+        Ok(DataType::Float64)
+    }
+
+    fn nullable(&self, _input_schema: &Schema) -> Result<bool> {
+        Ok(false)
+    }
+
+    fn evaluate(&self, batch: &RecordBatch) -> Result<ColumnarValue> {
+        let mut rng = self.rng.lock().unwrap();
+        let values = (0..batch.num_rows())
+            .map(|_| rng.next_double())
+            .collect::<Vec<_>>();
+        Ok(ColumnarValue::Array(Arc::new(Float64Array::from(values))))
+    }
+
+    fn children(&self) -> Vec<Arc<dyn PhysicalExpr>> {
+        vec![]
+    }
+
+    fn with_new_children(
+        self: Arc<Self>,
+        _children: Vec<Arc<dyn PhysicalExpr>>,
+    ) -> Result<Arc<dyn PhysicalExpr>> {
+        Ok(self)
+    }
+
+    fn dyn_hash(&self, state: &mut dyn Hasher) {
+        let mut s = state;
+        self.seed.hash(&mut s);
+        self.partition_index.hash(&mut s);
+    }
+}
+
+/// Spark `MonotonicallyIncreasingID`, which puts the partition index in the upper 31 bits and
+/// the row number within the partition in the lower 33 bits.
+#[derive(Debug)]
+pub struct MonotonicallyIncreasingId {
+    partition_index: i32,
+    count: Mutex<i64>,
+}
+
+impl MonotonicallyIncreasingId {
+    pub fn new(partition_index: i32) -> Self {
+        Self {
+            partition_index,
+            count: Mutex::new(0),
+        }
+    }
+}
+
+impl Display for MonotonicallyIncreasingId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "monotonically_increasing_id()")
+    }
+}
+
+impl PartialEq<dyn Any> for MonotonicallyIncreasingId {
+    fn eq(&self, other: &dyn Any) -> bool {
+        down_cast_any_ref(other)
+            .downcast_ref::<Self>()
+            .map(|x| self.partition_index == x.partition_index)
+            .unwrap_or(false)
+    }
+}
+
+impl PhysicalExpr for MonotonicallyIncreasingId {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn data_type(&self, _input_schema: &Schema) -> Result<DataType> {
+        Ok(DataType::Int64)
+    }
+
+    fn nullable(&self, _input_schema: &Schema) -> Result<bool> {
+        Ok(false)
+    }
+
+    fn evaluate(&self, batch: &RecordBatch) -> Result<ColumnarValue> {
+        let mut count = self.count.lock().unwrap();
+        let start = ((self.partition_index as i64) << 33) + *count;
+        *count += batch.num_rows() as i64;
+        let values = Int64Array::from_iter_values(start..start + batch.num_rows() as i64);
+        Ok(ColumnarValue::Array(Arc::new(values)))
+    }
+
+    fn children(&self) -> Vec<Arc<dyn PhysicalExpr>> {
+        vec![]
+    }
+
+    fn with_new_children(
+        self: Arc<Self>,
+        _children: Vec<Arc<dyn PhysicalExpr>>,
+    ) -> Result<Arc<dyn PhysicalExpr>> {
+        Ok(self)
+    }
+
+    fn dyn_hash(&self, state: &mut dyn Hasher) {
+        let mut s = state;
+        self.partition_index.hash(&mut s);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow::array::{Array, Int32Array};
+    use arrow_schema::Field;
+
+    use super::*;
+
+    fn batch(num_rows: usize) -> RecordBatch {
+        let schema = Arc::new(Schema::new(vec![Field::new("a", DataType::Int32, false)]));
+        let array = Int32Array::from_iter_values(0..num_rows as i32);
+        RecordBatch::try_new(schema, vec![Arc::new(array)]).unwrap()
+    }
+
+    #[test]
+    fn test_rand_like_spark() {
+        // `SELECT rand(0)` of Spark in the first partition
+        let expr = RandExpr::new(0, 0);
+        let result = expr.evaluate(&batch(2)).unwrap().into_array(2).unwrap();
+        let result = result.as_any().downcast_ref::<Float64Array>().unwrap();
+        assert_eq!(result.values(), &[0.7604953758285915, 0.5234194256885571]);
+
+        // The generator is seeded with the seed plus the partition index, i.e., `rand(42)` in the
+        // first partition
+        let expr = RandExpr::new(41, 1);
+        let first = expr.evaluate(&batch(1)).unwrap().into_array(1).unwrap();
+        let first = first.as_any().downcast_ref::<Float64Array>().unwrap();
+        assert_eq!(first.value(0), 0.619189370225301);
+    }
+
+    #[test]
+    fn test_monotonically_increasing_id() {
+        let expr = MonotonicallyIncreasingId::new(2);
+        expr.evaluate(&batch(3)).unwrap();
+        let result = expr.evaluate(&batch(2)).unwrap().into_array(2).unwrap();
+        let result = result.as_any().downcast_ref::<Int64Array>().unwrap();
+        assert_eq!(result.values(), &[(2i64 << 33) + 3, (2i64 << 33) + 4]);
+    }
+}
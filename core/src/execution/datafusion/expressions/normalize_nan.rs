@@ -23,16 +23,21 @@ use std::{
 };
 
 use arrow::{
-    array::{as_primitive_array, ArrayAccessor, ArrayIter, Float32Array, Float64Array},
-    datatypes::{ArrowNativeType, Float32Type, Float64Type},
+    array::{Array, ArrayRef, AsArray, ListArray, StructArray},
+    datatypes::{Float32Type, Float64Type},
     record_batch::RecordBatch,
 };
 use arrow_schema::{DataType, Schema};
 use datafusion::logical_expr::ColumnarValue;
+use datafusion_common::Result;
 use datafusion_physical_expr::PhysicalExpr;
 
 use crate::execution::datafusion::expressions::utils::down_cast_any_ref;
 
+/// Normalizes the floating point values of its child like Spark's `NormalizeNaNAndZero`, i.e.,
+/// all NaNs are the canonical NaN and -0.0 is 0.0, so that equal values are grouped, joined and
+/// hashed the same. The values nested in a struct or a list are normalized too, while the nulls,
+/// e.g., of a null struct, are kept.
 #[derive(Debug, Hash)]
 pub struct NormalizeNaNAndZero {
     pub data_type: DataType,
@@ -50,41 +55,28 @@ impl PhysicalExpr for NormalizeNaNAndZero {
         self
     }
 
-    fn data_type(&self, input_schema: &Schema) -> datafusion_common::Result<DataType> {
+    fn data_type(&self, input_schema: &Schema) -> Result<DataType> {
         self.child.data_type(input_schema)
     }
 
-    fn nullable(&self, input_schema: &Schema) -> datafusion_common::Result<bool> {
+    fn nullable(&self, input_schema: &Schema) -> Result<bool> {
         self.child.nullable(input_schema)
     }
 
-    fn evaluate(&self, batch: &RecordBatch) -> datafusion_common::Result<ColumnarValue> {
+    fn evaluate(&self, batch: &RecordBatch) -> Result<ColumnarValue> {
         let cv = self.child.evaluate(batch)?;
         let array = cv.into_array(batch.num_rows())?;
-
-        match &self.data_type {
-            DataType::Float32 => {
-                let v = eval_typed(as_primitive_array::<Float32Type>(&array));
-                let new_array = Float32Array::from(v);
-                Ok(ColumnarValue::Array(Arc::new(new_array)))
-            }
-            DataType::Float64 => {
-                let v = eval_typed(as_primitive_array::<Float64Type>(&array));
-                let new_array = Float64Array::from(v);
-                Ok(ColumnarValue::Array(Arc::new(new_array)))
-            }
-            dt => panic!("Unexpected data type {:?}", dt),
-        }
+        Ok(ColumnarValue::Array(normalize(&array)?))
     }
 
     fn children(&self) -> Vec<Arc<dyn PhysicalExpr>> {
-        self.child.children()
+        vec![self.child.clone()]
     }
 
     fn with_new_children(
         self: Arc<Self>,
         children: Vec<Arc<dyn PhysicalExpr>>,
-    ) -> datafusion_common::Result<Arc<dyn PhysicalExpr>> {
+    ) -> Result<Arc<dyn PhysicalExpr>> {
         Ok(Arc::new(NormalizeNaNAndZero::new(
             self.data_type.clone(),
             children[0].clone(),
@@ -99,20 +91,57 @@ impl PhysicalExpr for NormalizeNaNAndZero {
     }
 }
 
-fn eval_typed<V: FloatDouble, T: ArrayAccessor<Item = V>>(input: T) -> Vec<Option<V>> {
-    let iter = ArrayIter::new(input);
-    iter.map(|o| {
-        o.map(|v| {
-            if v.is_nan() {
-                v.nan()
-            } else if v.is_neg_zero() {
-                v.zero()
-            } else {
-                v
-            }
-        })
-    })
-    .collect()
+/// Normalizes the floating point values of `array`, including the ones nested in it. The arrays
+/// without floating point values are returned as they are.
+fn normalize(array: &ArrayRef) -> Result<ArrayRef> {
+    let normalized: ArrayRef = match array.data_type() {
+        DataType::Float32 => Arc::new(array.as_primitive::<Float32Type>().unary::<_, Float32Type>(
+            |v| {
+                if v.is_nan() {
+                    f32::NAN
+                } else if v == 0.0 {
+                    0.0
+                } else {
+                    v
+                }
+            },
+        )),
+        DataType::Float64 => Arc::new(array.as_primitive::<Float64Type>().unary::<_, Float64Type>(
+            |v| {
+                if v.is_nan() {
+                    f64::NAN
+                } else if v == 0.0 {
+                    0.0
+                } else {
+                    v
+                }
+            },
+        )),
+        DataType::Struct(fields) => {
+            let struct_array = array.as_struct();
+            let columns = struct_array
+                .columns()
+                .iter()
+                .map(normalize)
+                .collect::<Result<Vec<_>>>()?;
+            Arc::new(StructArray::try_new(
+                fields.clone(),
+                columns,
+                struct_array.nulls().cloned(),
+            )?)
+        }
+        DataType::List(field) => {
+            let list = array.as_list::<i32>();
+            Arc::new(ListArray::try_new(
+                field.clone(),
+                list.offsets().clone(),
+                normalize(list.values())?,
+                list.nulls().cloned(),
+            )?)
+        }
+        _ => array.clone(),
+    };
+    Ok(normalized)
 }
 
 impl Display for NormalizeNaNAndZero {
@@ -130,38 +159,56 @@ impl PartialEq<dyn Any> for NormalizeNaNAndZero {
     }
 }
 
-trait FloatDouble: ArrowNativeType {
-    fn is_nan(&self) -> bool;
-    fn nan(&self) -> Self;
-    fn is_neg_zero(&self) -> bool;
-    fn zero(&self) -> Self;
-}
-
-impl FloatDouble for f32 {
-    fn is_nan(&self) -> bool {
-        f32::is_nan(*self)
-    }
-    fn nan(&self) -> Self {
-        f32::NAN
-    }
-    fn is_neg_zero(&self) -> bool {
-        *self == -0.0
-    }
-    fn zero(&self) -> Self {
-        0.0
-    }
-}
-impl FloatDouble for f64 {
-    fn is_nan(&self) -> bool {
-        f64::is_nan(*self)
-    }
-    fn nan(&self) -> Self {
-        f64::NAN
-    }
-    fn is_neg_zero(&self) -> bool {
-        *self == -0.0
-    }
-    fn zero(&self) -> Self {
-        0.0
+#[cfg(test)]
+mod tests {
+    use arrow::{
+        array::{Float64Array, Int32Array},
+        buffer::NullBuffer,
+        datatypes::*,
+    };
+    use datafusion_physical_expr::expressions::col;
+
+    use super::*;
+
+    #[test]
+    fn normalize_nested_floats() -> Result<()> {
+        let fields = Fields::from(vec![
+            Field::new("f", DataType::Float64, true),
+            Field::new("i", DataType::Int32, true),
+        ]);
+        let struct_type = DataType::Struct(fields.clone());
+        let schema = Schema::new(vec![Field::new("s", struct_type.clone(), true)]);
+
+        let floats = Float64Array::from(vec![
+            Some(f64::from_bits(0x7ff8000000000001)),
+            Some(-0.0),
+            None,
+            Some(-1.5),
+        ]);
+        let ints = Int32Array::from(vec![Some(1), None, Some(3), Some(4)]);
+        let input = StructArray::try_new(
+            fields,
+            vec![Arc::new(floats), Arc::new(ints.clone())],
+            Some(NullBuffer::from(vec![true, true, true, false])),
+        )?;
+        let batch = RecordBatch::try_new(Arc::new(schema.clone()), vec![Arc::new(input)])?;
+
+        let expr = NormalizeNaNAndZero::new(struct_type, col("s", &schema)?);
+        let result = expr.evaluate(&batch)?.into_array(batch.num_rows())?;
+        let result = result.as_struct();
+
+        // The null struct is kept, and so are the values of the fields without floats
+        assert_eq!(
+            result.nulls(),
+            Some(&NullBuffer::from(vec![true, true, true, false]))
+        );
+        assert_eq!(result.column(1).as_primitive::<Int32Type>(), &ints);
+        let floats = result.column(0).as_primitive::<Float64Type>();
+        assert_eq!(floats.value(0).to_bits(), f64::NAN.to_bits());
+        assert_eq!(floats.value(1).to_bits(), 0.0_f64.to_bits());
+        assert!(floats.is_null(2));
+        assert_eq!(floats.value(3), -1.5);
+
+        Ok(())
     }
 }
@@ -0,0 +1,529 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use std::{any::Any, collections::HashMap, sync::Arc};
+
+use arrow::compute::cast;
+use arrow_array::{cast::AsArray, types::*, ArrayRef, BinaryArray, Float64Array};
+use arrow_schema::{DataType, Field};
+use datafusion::logical_expr::Accumulator;
+use datafusion_common::{
+    downcast_value, utils::array_into_list_array, DataFusionError, Result, ScalarValue,
+};
+use datafusion_physical_expr::{expressions::format_state_name, AggregateExpr, PhysicalExpr};
+
+use crate::execution::datafusion::expressions::utils::down_cast_any_ref;
+
+/// The size of a serialized `UnsafeRow` of a key and its count, i.e., the null bits and 2 words
+const ROW_SIZE: i32 = 24;
+
+/// PERCENTILE aggregate expression, i.e., Spark `Percentile`, which computes the exact
+/// percentiles of numeric values by counting the occurrences of each value.
+///
+/// The intermediate state is serialized in the same format as the `OpenHashMap` aggregation
+/// buffer of Spark, i.e., an `UnsafeRow` of each value and its count prefixed by its size, so the
+/// partial and final aggregations can be executed by either Comet or Spark. Only the values of
+/// fixed-width types are supported.
+#[derive(Debug)]
+pub struct Percentile {
+    name: String,
+    expr: Arc<dyn PhysicalExpr>,
+    /// The number of occurrences of each input value
+    frequency: Arc<dyn PhysicalExpr>,
+    /// The type of the input values
+    data_type: DataType,
+    percentages: Vec<f64>,
+    /// Whether the result is an array of percentiles, i.e., the percentage is an array
+    return_array: bool,
+}
+
+impl Percentile {
+    pub fn new(
+        expr: Arc<dyn PhysicalExpr>,
+        frequency: Arc<dyn PhysicalExpr>,
+        name: impl Into<String>,
+        data_type: DataType,
+        percentages: Vec<f64>,
+        return_array: bool,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            expr,
+            frequency,
+            data_type,
+            percentages,
+            return_array,
+        }
+    }
+}
+
+impl AggregateExpr for Percentile {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn field(&self) -> Result<Field> {
+        if self.return_array {
+            Ok(Field::new_list(
+                &self.name,
+                Field::new("item", DataType::Float64, true),
+                true,
+            ))
+        } else {
+            Ok(Field::new(&self.name, DataType::Float64, true))
+        }
+    }
+
+    fn create_accumulator(&self) -> Result<Box<dyn Accumulator>> {
+        Ok(Box::new(PercentileAccumulator::new(
+            self.data_type.clone(),
+            self.percentages.clone(),
+            self.return_array,
+        )))
+    }
+
+    fn state_fields(&self) -> Result<Vec<Field>> {
+        Ok(vec![Field::new(
+            format_state_name(&self.name, "buffer"),
+            DataType::Binary,
+            true,
+        )])
+    }
+
+    fn expressions(&self) -> Vec<Arc<dyn PhysicalExpr>> {
+        vec![self.expr.clone(), self.frequency.clone()]
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl PartialEq<dyn Any> for Percentile {
+    fn eq(&self, other: &dyn Any) -> bool {
+        down_cast_any_ref(other)
+            .downcast_ref::<Self>()
+            .map(|x| {
+                self.name == x.name
+                    && self.data_type == x.data_type
+                    && self.percentages == x.percentages
+                    && self.return_array == x.return_array
+                    && self.expr.eq(&x.expr)
+                    && self.frequency.eq(&x.frequency)
+            })
+            .unwrap_or(false)
+    }
+}
+
+/// An accumulator counting the occurrences of each value of a group.
+///
+/// The values are kept as 64-bit keys, i.e., integers and the unscaled values of decimals as they
+/// are, and floating-point values as their bits. Like the boxed keys of Spark, `-0.0` and `0.0` are
+/// different keys, while all NaNs are the same key.
+#[derive(Debug)]
+struct PercentileAccumulator {
+    data_type: DataType,
+    percentages: Vec<f64>,
+    return_array: bool,
+    counts: HashMap<i64, i64>,
+}
+
+impl PercentileAccumulator {
+    fn new(data_type: DataType, percentages: Vec<f64>, return_array: bool) -> Self {
+        Self {
+            data_type,
+            percentages,
+            return_array,
+            counts: HashMap::new(),
+        }
+    }
+
+    fn add(&mut self, key: i64, count: i64) {
+        *self.counts.entry(key).or_insert(0) += count;
+    }
+
+    /// Converts a key to the double value used to interpolate the percentiles.
+    fn to_double(&self, key: i64) -> f64 {
+        match self.data_type {
+            DataType::Float32 => f32::from_bits(key as u32) as f64,
+            DataType::Float64 => f64::from_bits(key as u64),
+            // Like `Decimal.toDouble` of Spark, the decimal is rounded to the nearest double
+            DataType::Decimal128(_, scale) => {
+                format!("{}e{}", key, -(scale as i32)).parse().unwrap()
+            }
+            _ => key as f64,
+        }
+    }
+
+    /// Computes the percentiles like Spark `PercentileBase`, i.e., interpolates the 2 values
+    /// around the position of each percentage in the sorted values.
+    fn percentiles(&self) -> Vec<f64> {
+        let mut counts = self
+            .counts
+            .iter()
+            .map(|(key, count)| (*key, *count))
+            .collect::<Vec<_>>();
+        match self.data_type {
+            DataType::Float32 | DataType::Float64 => {
+                counts.sort_by(|(a, _), (b, _)| self.to_double(*a).total_cmp(&self.to_double(*b)))
+            }
+            _ => counts.sort_by_key(|(key, _)| *key),
+        }
+
+        let mut accumulated = Vec::with_capacity(counts.len());
+        let mut total = 0_i64;
+        for (key, count) in counts {
+            total += count;
+            accumulated.push((self.to_double(key), total));
+        }
+
+        let max_position = (total - 1) as f64;
+        self.percentages
+            .iter()
+            .map(|percentage| {
+                let position = max_position * percentage;
+                let lower = position.floor();
+                let higher = position.ceil();
+                // The first value whose accumulated count covers the position
+                let value_at = |position: f64| {
+                    let index =
+                        accumulated.partition_point(|(_, count)| *count < position as i64 + 1);
+                    accumulated[index].0
+                };
+                let lower_value = value_at(lower);
+                if higher == lower {
+                    return lower_value;
+                }
+                let higher_value = value_at(higher);
+                if higher_value == lower_value {
+                    lower_value
+                } else {
+                    (higher - position) * lower_value + (position - lower) * higher_value
+                }
+            })
+            .collect()
+    }
+}
+
+impl Accumulator for PercentileAccumulator {
+    fn update_batch(&mut self, values: &[ArrayRef]) -> Result<()> {
+        let frequencies = cast(&values[1], &DataType::Int64)?;
+        let frequencies = frequencies.as_primitive::<Int64Type>();
+
+        macro_rules! update {
+            ($value_type:ty, $to_key:expr) => {{
+                let keys = values[0].as_primitive::<$value_type>();
+                for (key, frequency) in keys.iter().zip(frequencies.iter()) {
+                    match frequency {
+                        Some(frequency) if frequency < 0 => {
+                            return Err(DataFusionError::Execution(format!(
+                                "Negative values found in frequency: {}",
+                                frequency
+                            )))
+                        }
+                        Some(frequency) if frequency > 0 => {
+                            if let Some(key) = key {
+                                self.add($to_key(key), frequency);
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+            }};
+        }
+
+        match self.data_type {
+            DataType::Int8 => update!(Int8Type, |v: i8| v as i64),
+            DataType::Int16 => update!(Int16Type, |v: i16| v as i64),
+            DataType::Int32 => update!(Int32Type, |v: i32| v as i64),
+            DataType::Int64 => update!(Int64Type, |v: i64| v),
+            DataType::Float32 => update!(Float32Type, normalize_nan_f32),
+            DataType::Float64 => update!(Float64Type, normalize_nan_f64),
+            DataType::Decimal128(_, _) => update!(Decimal128Type, |v: i128| v as i64),
+            ref other => {
+                return Err(DataFusionError::NotImplemented(format!(
+                    "Unsupported data type {} of percentile values",
+                    other
+                )))
+            }
+        }
+        Ok(())
+    }
+
+    fn merge_batch(&mut self, states: &[ArrayRef]) -> Result<()> {
+        let buffers = downcast_value!(states[0], BinaryArray);
+        for bytes in buffers.iter().flatten() {
+            for (key, count) in decode(bytes, &self.data_type)? {
+                self.add(key, count);
+            }
+        }
+        Ok(())
+    }
+
+    fn state(&mut self) -> Result<Vec<ScalarValue>> {
+        Ok(vec![ScalarValue::Binary(Some(encode(
+            &self.counts,
+            &self.data_type,
+        )))])
+    }
+
+    fn evaluate(&mut self) -> Result<ScalarValue> {
+        if self.counts.is_empty() {
+            return if self.return_array {
+                ScalarValue::try_from(&DataType::new_list(DataType::Float64, true))
+            } else {
+                Ok(ScalarValue::Float64(None))
+            };
+        }
+
+        let percentiles = self.percentiles();
+        if self.return_array {
+            Ok(ScalarValue::List(Arc::new(array_into_list_array(
+                Arc::new(Float64Array::from(percentiles)),
+            ))))
+        } else {
+            Ok(ScalarValue::Float64(Some(percentiles[0])))
+        }
+    }
+
+    fn size(&self) -> usize {
+        std::mem::size_of_val(self)
+            + self.counts.capacity() * 2 * std::mem::size_of::<i64>()
+            + self.percentages.capacity() * std::mem::size_of::<f64>()
+    }
+}
+
+fn normalize_nan_f32(value: f32) -> i64 {
+    if value.is_nan() {
+        f32::NAN.to_bits() as i64
+    } else {
+        value.to_bits() as i64
+    }
+}
+
+fn normalize_nan_f64(value: f64) -> i64 {
+    if value.is_nan() {
+        f64::NAN.to_bits() as i64
+    } else {
+        value.to_bits() as i64
+    }
+}
+
+/// Returns the word of a key in the fixed-length region of `UnsafeRow`, where values narrower than
+/// 8 bytes are stored in the low bytes of a zeroed word.
+fn key_to_word(key: i64, data_type: &DataType) -> i64 {
+    match data_type {
+        DataType::Int8 => key as u8 as i64,
+        DataType::Int16 => key as u16 as i64,
+        DataType::Int32 => key as u32 as i64,
+        _ => key,
+    }
+}
+
+fn word_to_key(word: i64, data_type: &DataType) -> i64 {
+    match data_type {
+        DataType::Int8 => word as i8 as i64,
+        DataType::Int16 => word as i16 as i64,
+        DataType::Int32 => word as i32 as i64,
+        DataType::Float32 => normalize_nan_f32(f32::from_bits(word as u32)),
+        DataType::Float64 => normalize_nan_f64(f64::from_bits(word as u64)),
+        _ => word,
+    }
+}
+
+/// Serializes the counts like Spark serializes the aggregation buffer of `Percentile`, i.e., with
+/// a `DataOutputStream` whose integers are big-endian, while `UnsafeRow` is little-endian.
+fn encode(counts: &HashMap<i64, i64>, data_type: &DataType) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity((4 + ROW_SIZE as usize) * counts.len() + 4);
+    for (key, count) in counts {
+        bytes.extend_from_slice(&ROW_SIZE.to_be_bytes());
+        bytes.extend_from_slice(&0_i64.to_le_bytes());
+        bytes.extend_from_slice(&key_to_word(*key, data_type).to_le_bytes());
+        bytes.extend_from_slice(&count.to_le_bytes());
+    }
+    bytes.extend_from_slice(&(-1_i32).to_be_bytes());
+    bytes
+}
+
+/// Deserializes the counts serialized by [`encode`] or Spark.
+fn decode(bytes: &[u8], data_type: &DataType) -> Result<Vec<(i64, i64)>> {
+    let corrupted = || DataFusionError::Execution("Corrupted percentile buffer".to_string());
+    let read_i64 = |offset: usize| {
+        bytes
+            .get(offset..offset + 8)
+            .map(|b| i64::from_le_bytes(b.try_into().unwrap()))
+            .ok_or_else(corrupted)
+    };
+
+    let mut counts = vec![];
+    let mut offset = 0;
+    loop {
+        let size = bytes
+            .get(offset..offset + 4)
+            .map(|b| i32::from_be_bytes(b.try_into().unwrap()))
+            .ok_or_else(corrupted)?;
+        if size < 0 {
+            return Ok(counts);
+        }
+        if size != ROW_SIZE {
+            return Err(corrupted());
+        }
+        let key = word_to_key(read_i64(offset + 12)?, data_type);
+        counts.push((key, read_i64(offset + 20)?));
+        offset += 4 + ROW_SIZE as usize;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use arrow_array::{
+        cast::AsArray, types::Float64Type, Array, ArrayRef, Float64Array, Int32Array, Int64Array,
+    };
+    use arrow_schema::DataType;
+    use datafusion::logical_expr::Accumulator;
+    use datafusion_common::ScalarValue;
+
+    use super::{decode, encode, PercentileAccumulator};
+
+    fn percentiles(
+        data_type: DataType,
+        values: ArrayRef,
+        frequencies: Vec<Option<i64>>,
+        percentages: Vec<f64>,
+    ) -> Vec<f64> {
+        let frequencies: ArrayRef = Arc::new(Int64Array::from(frequencies));
+
+        // Two partial aggregations merged into a final one
+        let mut partial1 = PercentileAccumulator::new(data_type.clone(), vec![], true);
+        let mut partial2 = PercentileAccumulator::new(data_type.clone(), vec![], true);
+        let half = values.len() / 2;
+        partial1
+            .update_batch(&[values.slice(0, half), frequencies.slice(0, half)])
+            .unwrap();
+        partial2
+            .update_batch(&[
+                values.slice(half, values.len() - half),
+                frequencies.slice(half, values.len() - half),
+            ])
+            .unwrap();
+
+        let mut state = partial1.state().unwrap();
+        state.extend(partial2.state().unwrap());
+        let state = ScalarValue::iter_to_array(state).unwrap();
+
+        let mut fin = PercentileAccumulator::new(data_type, percentages, true);
+        fin.merge_batch(&[state]).unwrap();
+        match fin.evaluate().unwrap() {
+            ScalarValue::List(list) => list
+                .value(0)
+                .as_primitive::<Float64Type>()
+                .values()
+                .to_vec(),
+            other => panic!("Unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_percentile() {
+        let values: ArrayRef = Arc::new(Int32Array::from(vec![
+            Some(4),
+            None,
+            Some(-1),
+            Some(2),
+            Some(10),
+            Some(3),
+        ]));
+        let frequencies = vec![Some(1), Some(1), Some(1), Some(2), Some(1), None];
+        // The sorted values are [-1, 2, 2, 4, 10]
+        let position = 4.0 * 0.9;
+        assert_eq!(
+            percentiles(
+                DataType::Int32,
+                values,
+                frequencies,
+                vec![0.0, 0.25, 0.5, 0.9, 1.0]
+            ),
+            vec![
+                -1.0,
+                2.0,
+                2.0,
+                (4.0 - position) * 4.0 + (position - 3.0) * 10.0,
+                10.0
+            ]
+        );
+    }
+
+    #[test]
+    fn test_percentile_floats() {
+        let mut acc = PercentileAccumulator::new(DataType::Float64, vec![0.0, 0.5, 1.0], true);
+        let values: ArrayRef = Arc::new(Float64Array::from(vec![
+            f64::NAN,
+            1.0,
+            -0.0,
+            0.0,
+            -f64::NAN,
+            f64::NEG_INFINITY,
+        ]));
+        let frequencies: ArrayRef = Arc::new(Int64Array::from(vec![1; 6]));
+        acc.update_batch(&[values, frequencies]).unwrap();
+        // All NaNs are the same value, while -0.0 and 0.0 are not
+        assert_eq!(acc.counts.len(), 5);
+
+        // The sorted values are [-inf, -0.0, 0.0, 1.0, NaN, NaN]
+        let result = acc.percentiles();
+        assert_eq!(result[0], f64::NEG_INFINITY);
+        assert_eq!(result[1], 0.5);
+        assert!(result[2].is_nan());
+    }
+
+    #[test]
+    fn test_percentile_negative_frequency() {
+        let mut acc = PercentileAccumulator::new(DataType::Int32, vec![0.5], false);
+        let values: ArrayRef = Arc::new(Int32Array::from(vec![1]));
+        let frequencies: ArrayRef = Arc::new(Int64Array::from(vec![-1]));
+        assert!(acc.update_batch(&[values, frequencies]).is_err());
+    }
+
+    #[test]
+    fn test_percentile_no_values() {
+        let mut acc = PercentileAccumulator::new(DataType::Int32, vec![0.5], false);
+        let values: ArrayRef = Arc::new(Int32Array::from(vec![None]));
+        let frequencies: ArrayRef = Arc::new(Int64Array::from(vec![1]));
+        acc.update_batch(&[values, frequencies]).unwrap();
+        assert_eq!(acc.evaluate().unwrap(), ScalarValue::Float64(None));
+    }
+
+    #[test]
+    fn test_spark_buffer_format() {
+        let mut acc = PercentileAccumulator::new(DataType::Int32, vec![], false);
+        acc.add(-2, 3);
+        let bytes = encode(&acc.counts, &DataType::Int32);
+
+        // The size of the `UnsafeRow`, its null bits, the zero-extended int and the long count,
+        // and the end of the rows
+        let mut expected = 24_i32.to_be_bytes().to_vec();
+        expected.extend_from_slice(&[0; 8]);
+        expected.extend_from_slice(&[0xfe, 0xff, 0xff, 0xff, 0, 0, 0, 0]);
+        expected.extend_from_slice(&3_i64.to_le_bytes());
+        expected.extend_from_slice(&(-1_i32).to_be_bytes());
+        assert_eq!(bytes, expected);
+
+        assert_eq!(decode(&bytes, &DataType::Int32).unwrap(), vec![(-2, 3)]);
+    }
+}
@@ -15,24 +15,24 @@
 // specific language governing permissions and limitations
 // under the License.
 
-use std::{
-    any::Any,
-    cmp::min,
-    fmt::{Debug, Write},
-    str::FromStr,
-    sync::Arc,
-};
+use std::{any::Any, cmp::min, fmt::Debug, str::FromStr, sync::Arc};
 
-use crate::execution::datafusion::spark_hash::create_hashes;
+use crate::execution::datafusion::{
+    expressions::digest::{spark_digest, DigestFunction},
+    spark_hash::{create_hashes, create_hive_hashes, create_xxhash64_hashes},
+};
 use arrow::{
     array::{
-        ArrayRef, AsArray, Decimal128Builder, Float32Array, Float64Array, GenericStringArray,
-        Int16Array, Int32Array, Int64Array, Int64Builder, Int8Array, OffsetSizeTrait,
+        ArrayRef, AsArray, Decimal128Builder, Float32Array, Float64Array, GenericListArray,
+        GenericStringArray, Int16Array, Int32Array, Int64Array, Int64Builder, Int8Array,
+        OffsetSizeTrait, UInt64Array,
     },
-    datatypes::{validate_decimal_precision, Decimal128Type, Int64Type},
+    buffer::OffsetBuffer,
+    compute::{cast, take},
+    datatypes::{validate_decimal_precision, ArrowNativeType, Decimal128Type, Int64Type},
 };
-use arrow_array::{Array, ArrowNativeTypeOp, Decimal128Array, StringArray};
-use arrow_schema::DataType;
+use arrow_array::{new_null_array, Array, ArrowNativeTypeOp, Decimal128Array, StringArray};
+use arrow_schema::{DataType, FieldRef};
 use datafusion::{
     execution::FunctionRegistry,
     logical_expr::{
@@ -42,14 +42,11 @@ use datafusion::{
     physical_plan::ColumnarValue,
 };
 use datafusion_common::{
-    cast::{as_binary_array, as_generic_string_array},
-    exec_err, internal_err, DataFusionError, Result as DataFusionResult, ScalarValue,
+    cast::as_generic_string_array, exec_err, internal_err, DataFusionError,
+    Result as DataFusionResult, ScalarValue,
 };
 use datafusion_physical_expr::{math_expressions, udf::ScalarUDF};
-use num::{
-    integer::{div_ceil, div_floor},
-    BigInt, Signed, ToPrimitive,
-};
+use num::integer::{div_ceil, div_floor};
 use unicode_segmentation::UnicodeSegmentation;
 
 macro_rules! make_comet_scalar_udf {
@@ -83,7 +80,10 @@ pub fn create_comet_physical_fun(
     data_type: DataType,
     registry: &dyn FunctionRegistry,
 ) -> Result<ScalarFunctionDefinition, DataFusionError> {
-    let sha2_functions = ["sha224", "sha256", "sha384", "sha512"];
+    if let Some(function) = DigestFunction::from_name(fun_name) {
+        let func = Arc::new(move |args: &[ColumnarValue]| spark_digest(args, function));
+        return make_comet_scalar_udf!(fun_name, func, without data_type);
+    }
     match fun_name {
         "ceil" => {
             make_comet_scalar_udf!("ceil", spark_ceil, data_type)
@@ -95,9 +95,29 @@ pub fn create_comet_physical_fun(
             let func = Arc::new(spark_rpad);
             make_comet_scalar_udf!("rpad", func, without data_type)
         }
+        "repeat" => {
+            let func = Arc::new(spark_repeat);
+            make_comet_scalar_udf!("repeat", func, without data_type)
+        }
+        "array_reverse" => {
+            let func = Arc::new(spark_array_reverse);
+            make_comet_scalar_udf!("array_reverse", func, without data_type)
+        }
         "round" => {
             make_comet_scalar_udf!("round", spark_round, data_type)
         }
+        "trim" | "btrim" => {
+            let func = Arc::new(|args: &[ColumnarValue]| spark_trim(args, TrimSide::Both));
+            make_comet_scalar_udf!(fun_name, func, without data_type)
+        }
+        "ltrim" => {
+            let func = Arc::new(|args: &[ColumnarValue]| spark_trim(args, TrimSide::Leading));
+            make_comet_scalar_udf!("ltrim", func, without data_type)
+        }
+        "rtrim" => {
+            let func = Arc::new(|args: &[ColumnarValue]| spark_trim(args, TrimSide::Trailing));
+            make_comet_scalar_udf!("rtrim", func, without data_type)
+        }
         "unscaled_value" => {
             let func = Arc::new(spark_unscaled_value);
             make_comet_scalar_udf!("unscaled_value", func, without data_type)
@@ -105,22 +125,17 @@ pub fn create_comet_physical_fun(
         "make_decimal" => {
             make_comet_scalar_udf!("make_decimal", spark_make_decimal, data_type)
         }
-        "decimal_div" => {
-            make_comet_scalar_udf!("decimal_div", spark_decimal_div, data_type)
-        }
         "murmur3_hash" => {
             let func = Arc::new(spark_murmur3_hash);
             make_comet_scalar_udf!("murmur3_hash", func, without data_type)
         }
-        sha if sha2_functions.contains(&sha) => {
-            // Spark requires hex string as the result of sha2 functions, we have to wrap the
-            // result of digest functions as hex string
-            let func = registry.udf(sha)?;
-            let wrapped_func = Arc::new(move |args: &[ColumnarValue]| {
-                wrap_digest_result_as_hex_string(args, func.fun())
-            });
-            let spark_func_name = "spark".to_owned() + sha;
-            make_comet_scalar_udf!(spark_func_name, wrapped_func, without data_type)
+        "xxhash64" => {
+            let func = Arc::new(spark_xxhash64);
+            make_comet_scalar_udf!("xxhash64", func, without data_type)
+        }
+        "hive_hash" => {
+            let func = Arc::new(spark_hive_hash);
+            make_comet_scalar_udf!("hive_hash", func, without data_type)
         }
         _ => {
             let fun = BuiltinScalarFunction::from_str(fun_name);
@@ -573,54 +588,203 @@ fn spark_rpad_internal<T: OffsetSizeTrait>(
     Ok(ColumnarValue::Array(Arc::new(result)))
 }
 
-// Let Decimal(p3, s3) as return type i.e. Decimal(p1, s1) / Decimal(p2, s2) = Decimal(p3, s3).
-// Conversely, Decimal(p1, s1) = Decimal(p2, s2) * Decimal(p3, s3). This means that, in order to
-// get enough scale that matches with Spark behavior, it requires to widen s1 to s2 + s3 + 1. Since
-// both s2 and s3 are 38 at max., s1 is 77 at max. DataFusion division cannot handle such scale >
-// Decimal256Type::MAX_SCALE. Therefore, we need to implement this decimal division using BigInt.
-fn spark_decimal_div(
-    args: &[ColumnarValue],
-    data_type: &DataType,
-) -> Result<ColumnarValue, DataFusionError> {
-    let left = &args[0];
-    let right = &args[1];
-    let (p3, s3) = get_precision_scale(data_type);
+/// Similar to DataFusion `repeat`, but returns an empty string if `times` is negative like Spark
+fn spark_repeat(args: &[ColumnarValue]) -> Result<ColumnarValue, DataFusionError> {
+    let repeat = |string: &str, times: i64| string.repeat(times.max(0) as usize);
+    match args {
+        [ColumnarValue::Scalar(ScalarValue::Utf8(string)), ColumnarValue::Scalar(ScalarValue::Int64(times))] => {
+            Ok(ColumnarValue::Scalar(ScalarValue::Utf8(
+                string
+                    .as_deref()
+                    .zip(*times)
+                    .map(|(string, times)| repeat(string, times)),
+            )))
+        }
+        [string, times] => {
+            let num_rows = match (string, times) {
+                (ColumnarValue::Array(array), _) | (_, ColumnarValue::Array(array)) => array.len(),
+                _ => 1,
+            };
+            let strings = to_string_array(&string.clone().into_array(num_rows)?)?;
+            let times = cast(&times.clone().into_array(num_rows)?, &DataType::Int64)?;
+            let result: StringArray = strings
+                .as_string::<i32>()
+                .iter()
+                .zip(times.as_primitive::<Int64Type>().iter())
+                .map(|(string, times)| {
+                    string
+                        .zip(times)
+                        .map(|(string, times)| repeat(string, times))
+                })
+                .collect();
+            Ok(ColumnarValue::Array(Arc::new(result)))
+        }
+        other => Err(DataFusionError::Internal(format!(
+            "Unsupported arguments {other:?} for function repeat",
+        ))),
+    }
+}
 
-    let (left, right): (ArrayRef, ArrayRef) = match (left, right) {
-        (ColumnarValue::Array(l), ColumnarValue::Array(r)) => (l.clone(), r.clone()),
-        (ColumnarValue::Scalar(l), ColumnarValue::Array(r)) => {
-            (l.to_array_of_size(r.len())?, r.clone())
+/// Spark `Reverse` of arrays, which reverses the elements of each array.
+fn spark_array_reverse(args: &[ColumnarValue]) -> Result<ColumnarValue, DataFusionError> {
+    match args {
+        [ColumnarValue::Array(array)] => match array.data_type() {
+            DataType::List(field) => Ok(ColumnarValue::Array(reverse_list(
+                array.as_list::<i32>(),
+                field,
+            )?)),
+            DataType::LargeList(field) => Ok(ColumnarValue::Array(reverse_list(
+                array.as_list::<i64>(),
+                field,
+            )?)),
+            other => Err(DataFusionError::Internal(format!(
+                "Unsupported data type {other:?} for function array_reverse",
+            ))),
+        },
+        other => Err(DataFusionError::Internal(format!(
+            "Unsupported arguments {other:?} for function array_reverse",
+        ))),
+    }
+}
+
+fn reverse_list<O: OffsetSizeTrait>(
+    list: &GenericListArray<O>,
+    field: &FieldRef,
+) -> Result<ArrayRef, DataFusionError> {
+    let offsets = list.offsets();
+    let first = offsets[0];
+    let mut indices = Vec::with_capacity((offsets[offsets.len() - 1] - first).as_usize());
+    for range in offsets.windows(2) {
+        indices.extend(
+            (range[0].as_usize()..range[1].as_usize())
+                .rev()
+                .map(|i| i as u64),
+        );
+    }
+    let values = take(list.values(), &UInt64Array::from(indices), None)?;
+    // The taken values start at 0 even if the list is sliced
+    let offsets = OffsetBuffer::new(offsets.iter().map(|offset| *offset - first).collect());
+    Ok(Arc::new(GenericListArray::<O>::try_new(
+        field.clone(),
+        offsets,
+        values,
+        list.nulls().cloned(),
+    )?))
+}
+
+#[derive(Clone, Copy)]
+enum TrimSide {
+    Both,
+    Leading,
+    Trailing,
+}
+
+impl TrimSide {
+    /// Removes the characters of `trim_str` from the side of `string`. Like Spark, `trim_str` is
+    /// a set of characters rather than a prefix or suffix.
+    #[inline]
+    fn trim<'a>(&self, string: &'a str, trim_str: &str) -> &'a str {
+        let matches = |c: char| trim_str.contains(c);
+        match self {
+            TrimSide::Both => string.trim_matches(matches),
+            TrimSide::Leading => string.trim_start_matches(matches),
+            TrimSide::Trailing => string.trim_end_matches(matches),
         }
-        (ColumnarValue::Array(l), ColumnarValue::Scalar(r)) => {
-            (l.clone(), r.to_array_of_size(l.len())?)
+    }
+}
+
+/// Spark `StringTrim`, `StringTrimBoth`, `StringTrimLeft` and `StringTrimRight`, i.e.,
+/// `trim([BOTH | LEADING | TRAILING] [trim_str] FROM str)`. Without `trim_str`, only spaces are
+/// removed, not other whitespace characters.
+fn spark_trim(args: &[ColumnarValue], side: TrimSide) -> Result<ColumnarValue, DataFusionError> {
+    match args {
+        [src] => spark_trim_scalar_trim_str(src, Some(" "), side),
+        [src, ColumnarValue::Scalar(ScalarValue::Utf8(trim_str))] => {
+            spark_trim_scalar_trim_str(src, trim_str.as_deref(), side)
         }
-        (ColumnarValue::Scalar(l), ColumnarValue::Scalar(r)) => (l.to_array()?, r.to_array()?),
-    };
-    let left = left.as_primitive::<Decimal128Type>();
-    let right = right.as_primitive::<Decimal128Type>();
-    let (_, s1) = get_precision_scale(left.data_type());
-    let (_, s2) = get_precision_scale(right.data_type());
-
-    let ten = BigInt::from(10);
-    let l_exp = ((s2 + s3 + 1) as u32).saturating_sub(s1 as u32);
-    let r_exp = (s1 as u32).saturating_sub((s2 + s3 + 1) as u32);
-    let l_mul = ten.pow(l_exp);
-    let r_mul = ten.pow(r_exp);
-    let five = BigInt::from(5);
-    let zero = BigInt::from(0);
-    let result: Decimal128Array = arrow::compute::kernels::arity::binary(left, right, |l, r| {
-        let l = BigInt::from(l) * &l_mul;
-        let r = BigInt::from(r) * &r_mul;
-        let div = if r.eq(&zero) { zero.clone() } else { &l / &r };
-        let res = if div.is_negative() {
-            div - &five
-        } else {
-            div + &five
-        } / &ten;
-        res.to_i128().unwrap_or(i128::MAX)
-    })?;
-    let result = result.with_data_type(DataType::Decimal128(p3, s3));
-    Ok(ColumnarValue::Array(Arc::new(result)))
+        [src, ColumnarValue::Array(trim_strs)] => {
+            let src = to_string_array(&src.clone().into_array(trim_strs.len())?)?;
+            let trim_strs = to_string_array(trim_strs)?;
+            let result: StringArray = as_generic_string_array::<i32>(&src)?
+                .iter()
+                .zip(as_generic_string_array::<i32>(&trim_strs)?.iter())
+                .map(|(string, trim_str)| {
+                    string
+                        .zip(trim_str)
+                        .map(|(string, trim_str)| side.trim(string, trim_str))
+                })
+                .collect();
+            Ok(ColumnarValue::Array(Arc::new(result)))
+        }
+        other => Err(DataFusionError::Internal(format!(
+            "Unsupported arguments {other:?} for function trim",
+        ))),
+    }
+}
+
+fn spark_trim_scalar_trim_str(
+    src: &ColumnarValue,
+    trim_str: Option<&str>,
+    side: TrimSide,
+) -> Result<ColumnarValue, DataFusionError> {
+    match src {
+        ColumnarValue::Array(array) => Ok(ColumnarValue::Array(match trim_str {
+            Some(trim_str) => spark_trim_array(array, trim_str, side)?,
+            None => new_null_array(&DataType::Utf8, array.len()),
+        })),
+        ColumnarValue::Scalar(ScalarValue::Utf8(string)) => {
+            Ok(ColumnarValue::Scalar(ScalarValue::Utf8(
+                string
+                    .as_deref()
+                    .zip(trim_str)
+                    .map(|(string, trim_str)| side.trim(string, trim_str).to_string()),
+            )))
+        }
+        other => Err(DataFusionError::Internal(format!(
+            "Unsupported argument {other:?} for function trim",
+        ))),
+    }
+}
+
+fn spark_trim_array(
+    array: &ArrayRef,
+    trim_str: &str,
+    side: TrimSide,
+) -> Result<ArrayRef, DataFusionError> {
+    match array.data_type() {
+        DataType::Utf8 => spark_trim_array_internal::<i32>(array, trim_str, side),
+        DataType::LargeUtf8 => spark_trim_array_internal::<i64>(array, trim_str, side),
+        DataType::Dictionary(_, value_type) => {
+            // Trims each dictionary value only once
+            let dict = array.as_any_dictionary();
+            let values = spark_trim_array(dict.values(), trim_str, side)?;
+            Ok(cast(&dict.with_values(values), value_type)?)
+        }
+        other => Err(DataFusionError::Internal(format!(
+            "Unsupported data type {other:?} for function trim",
+        ))),
+    }
+}
+
+fn spark_trim_array_internal<T: OffsetSizeTrait>(
+    array: &ArrayRef,
+    trim_str: &str,
+    side: TrimSide,
+) -> Result<ArrayRef, DataFusionError> {
+    let result: GenericStringArray<T> = as_generic_string_array::<T>(array)?
+        .iter()
+        .map(|string| string.map(|string| side.trim(string, trim_str)))
+        .collect();
+    Ok(Arc::new(result))
+}
+
+/// Casts a string array of another type, e.g., dictionary-encoded, to `Utf8`.
+fn to_string_array(array: &ArrayRef) -> Result<ArrayRef, DataFusionError> {
+    if array.data_type() == &DataType::Utf8 {
+        Ok(array.clone())
+    } else {
+        Ok(cast(array, &DataType::Utf8)?)
+    }
 }
 
 fn spark_murmur3_hash(args: &[ColumnarValue]) -> Result<ColumnarValue, DataFusionError> {
@@ -666,38 +830,65 @@ fn spark_murmur3_hash(args: &[ColumnarValue]) -> Result<ColumnarValue, DataFusio
     }
 }
 
-#[inline]
-fn hex_encode<T: AsRef<[u8]>>(data: T) -> String {
-    let mut s = String::with_capacity(data.as_ref().len() * 2);
-    for b in data.as_ref() {
-        // Writing to a string never errors, so we can unwrap here.
-        write!(&mut s, "{b:02x}").unwrap();
-    }
-    s
-}
-
-fn wrap_digest_result_as_hex_string(
-    args: &[ColumnarValue],
-    digest: ScalarFunctionImplementation,
-) -> Result<ColumnarValue, DataFusionError> {
-    let value = digest(args)?;
-    match value {
-        ColumnarValue::Array(array) => {
-            let binary_array = as_binary_array(&array)?;
-            let string_array: StringArray = binary_array
+fn spark_xxhash64(args: &[ColumnarValue]) -> Result<ColumnarValue, DataFusionError> {
+    let length = args.len();
+    let seed = &args[length - 1];
+    match seed {
+        ColumnarValue::Scalar(ScalarValue::Int64(Some(seed))) => {
+            // iterate over the arguments to find out the length of the array
+            let num_rows = args[0..args.len() - 1]
                 .iter()
-                .map(|opt| opt.map(hex_encode::<_>))
-                .collect();
-            Ok(ColumnarValue::Array(Arc::new(string_array)))
+                .find_map(|arg| match arg {
+                    ColumnarValue::Array(array) => Some(array.len()),
+                    ColumnarValue::Scalar(_) => None,
+                })
+                .unwrap_or(1);
+            let mut hashes: Vec<u64> = vec![*seed as u64; num_rows];
+            let arrays = args[0..args.len() - 1]
+                .iter()
+                .map(|arg| arg.clone().into_array(num_rows))
+                .collect::<Result<Vec<ArrayRef>, _>>()?;
+            create_xxhash64_hashes(&arrays, &mut hashes)?;
+            if num_rows == 1 {
+                Ok(ColumnarValue::Scalar(ScalarValue::Int64(Some(
+                    hashes[0] as i64,
+                ))))
+            } else {
+                let hashes: Vec<i64> = hashes.into_iter().map(|x| x as i64).collect();
+                Ok(ColumnarValue::Array(Arc::new(Int64Array::from(hashes))))
+            }
         }
-        ColumnarValue::Scalar(ScalarValue::Binary(opt)) => Ok(ColumnarValue::Scalar(
-            ScalarValue::Utf8(opt.map(hex_encode::<_>)),
-        )),
         _ => {
-            exec_err!(
-                "digest function should return binary value, but got: {:?}",
-                value.data_type()
+            internal_err!(
+                "The seed of function xxhash64 must be an Int64 scalar value, but got: {:?}.",
+                seed
             )
         }
     }
 }
+
+/// Spark `HiveHash`, whose seed is always 0, so unlike `murmur3_hash` there is no seed argument.
+fn spark_hive_hash(args: &[ColumnarValue]) -> Result<ColumnarValue, DataFusionError> {
+    // iterate over the arguments to find out the length of the array
+    let num_rows = args
+        .iter()
+        .find_map(|arg| match arg {
+            ColumnarValue::Array(array) => Some(array.len()),
+            ColumnarValue::Scalar(_) => None,
+        })
+        .unwrap_or(1);
+    let mut hashes: Vec<u32> = vec![0_u32; num_rows];
+    let arrays = args
+        .iter()
+        .map(|arg| arg.clone().into_array(num_rows))
+        .collect::<Result<Vec<ArrayRef>, _>>()?;
+    create_hive_hashes(&arrays, &mut hashes)?;
+    if num_rows == 1 {
+        Ok(ColumnarValue::Scalar(ScalarValue::Int32(Some(
+            hashes[0] as i32,
+        ))))
+    } else {
+        let hashes: Vec<i32> = hashes.into_iter().map(|x| x as i32).collect();
+        Ok(ColumnarValue::Array(Arc::new(Int32Array::from(hashes))))
+    }
+}
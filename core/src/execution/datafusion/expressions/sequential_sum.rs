@@ -0,0 +1,181 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use std::{any::Any, sync::Arc};
+
+use arrow_array::{cast::AsArray, types::Float64Type, Array, ArrayRef, Float64Array};
+use arrow_schema::{DataType, Field};
+use datafusion::logical_expr::{Accumulator, GroupsAccumulator};
+use datafusion_common::{Result, ScalarValue};
+use datafusion_physical_expr::{expressions::Sum, AggregateExpr, PhysicalExpr};
+
+use crate::execution::datafusion::expressions::utils::down_cast_any_ref;
+
+/// SUM of double values which adds the values one by one in the order of the input rows, like
+/// Spark, instead of with the vectorized `sum` kernel which reorders the additions into lanes.
+/// The floating-point result is then the same for the same input order, e.g., across retries,
+/// and the same as Spark's if the rows are aggregated in the same order.
+///
+/// The grouped aggregation of [`Sum`] already adds the values of each group in order, so it is
+/// only the aggregation without grouping keys which differs from [`Sum`].
+#[derive(Debug)]
+pub struct SequentialSum {
+    sum: Sum,
+}
+
+impl SequentialSum {
+    pub fn new(expr: Arc<dyn PhysicalExpr>, name: impl Into<String>) -> Self {
+        Self {
+            sum: Sum::new(expr, name, DataType::Float64),
+        }
+    }
+}
+
+impl AggregateExpr for SequentialSum {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn field(&self) -> Result<Field> {
+        self.sum.field()
+    }
+
+    fn create_accumulator(&self) -> Result<Box<dyn Accumulator>> {
+        Ok(Box::<SequentialSumAccumulator>::default())
+    }
+
+    fn state_fields(&self) -> Result<Vec<Field>> {
+        self.sum.state_fields()
+    }
+
+    fn expressions(&self) -> Vec<Arc<dyn PhysicalExpr>> {
+        self.sum.expressions()
+    }
+
+    fn name(&self) -> &str {
+        self.sum.name()
+    }
+
+    fn groups_accumulator_supported(&self) -> bool {
+        self.sum.groups_accumulator_supported()
+    }
+
+    fn create_groups_accumulator(&self) -> Result<Box<dyn GroupsAccumulator>> {
+        self.sum.create_groups_accumulator()
+    }
+}
+
+impl PartialEq<dyn Any> for SequentialSum {
+    fn eq(&self, other: &dyn Any) -> bool {
+        down_cast_any_ref(other)
+            .downcast_ref::<Self>()
+            .map(|x| self.sum.eq(x.sum.as_any()))
+            .unwrap_or(false)
+    }
+}
+
+/// Adds the non-null values to `sum` one by one in order. The sum stays `None` if all the values
+/// are null.
+pub(crate) fn sequential_sum(sum: Option<f64>, values: &Float64Array) -> Option<f64> {
+    if values.null_count() == values.len() {
+        sum
+    } else if values.null_count() == 0 {
+        Some(
+            values
+                .values()
+                .iter()
+                .fold(sum.unwrap_or_default(), |sum, v| sum + v),
+        )
+    } else {
+        Some(
+            values
+                .iter()
+                .flatten()
+                .fold(sum.unwrap_or_default(), |sum, v| sum + v),
+        )
+    }
+}
+
+#[derive(Debug, Default)]
+struct SequentialSumAccumulator {
+    sum: Option<f64>,
+}
+
+impl Accumulator for SequentialSumAccumulator {
+    fn update_batch(&mut self, values: &[ArrayRef]) -> Result<()> {
+        self.sum = sequential_sum(self.sum, values[0].as_primitive::<Float64Type>());
+        Ok(())
+    }
+
+    fn merge_batch(&mut self, states: &[ArrayRef]) -> Result<()> {
+        self.sum = sequential_sum(self.sum, states[0].as_primitive::<Float64Type>());
+        Ok(())
+    }
+
+    fn state(&mut self) -> Result<Vec<ScalarValue>> {
+        Ok(vec![ScalarValue::Float64(self.sum)])
+    }
+
+    fn evaluate(&mut self) -> Result<ScalarValue> {
+        Ok(ScalarValue::Float64(self.sum))
+    }
+
+    fn size(&self) -> usize {
+        std::mem::size_of_val(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use arrow_array::{ArrayRef, Float64Array};
+    use datafusion::logical_expr::Accumulator;
+    use datafusion_common::ScalarValue;
+
+    use super::SequentialSumAccumulator;
+
+    #[test]
+    fn test_sequential_sum() {
+        // 1e16 + 1 + 1 + ... is 1e16 when the ones are added one by one, but not when they are
+        // added up first
+        let mut values = vec![Some(1e16)];
+        values.extend(std::iter::repeat(Some(1.0)).take(63));
+        values.push(None);
+        let values: ArrayRef = Arc::new(Float64Array::from(values));
+
+        let mut accumulator = SequentialSumAccumulator::default();
+        accumulator.update_batch(&[values.clone()]).unwrap();
+        assert_eq!(
+            accumulator.evaluate().unwrap(),
+            ScalarValue::Float64(Some(1e16))
+        );
+
+        // The partial sums are added to the sum one by one too
+        let state: ArrayRef = Arc::new(Float64Array::from(vec![None, Some(-1e16), Some(0.5)]));
+        accumulator.merge_batch(&[state]).unwrap();
+        assert_eq!(
+            accumulator.evaluate().unwrap(),
+            ScalarValue::Float64(Some(0.5))
+        );
+
+        let mut accumulator = SequentialSumAccumulator::default();
+        let nulls: ArrayRef = Arc::new(Float64Array::from(vec![None, None]));
+        accumulator.update_batch(&[nulls]).unwrap();
+        assert_eq!(accumulator.evaluate().unwrap(), ScalarValue::Float64(None));
+    }
+}
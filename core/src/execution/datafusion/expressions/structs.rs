@@ -0,0 +1,327 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use std::{
+    any::Any,
+    fmt::{Display, Formatter},
+    hash::{Hash, Hasher},
+    sync::Arc,
+};
+
+use arrow::{
+    array::{make_array, Array, ArrayRef, AsArray, StructArray},
+    buffer::NullBuffer,
+    compute::cast,
+    datatypes::{DataType, Field, Fields, Schema},
+    record_batch::RecordBatch,
+};
+use datafusion::logical_expr::ColumnarValue;
+use datafusion_common::{DataFusionError, Result};
+use datafusion_physical_expr::PhysicalExpr;
+
+use crate::execution::datafusion::expressions::utils::down_cast_any_ref;
+
+/// Spark's `CreateNamedStruct`, which creates a non-null struct of the values of its children,
+/// e.g., the `window` and `session_window` columns of time-bucketed aggregations. All the fields
+/// are nullable, and the dictionary encoded values are decoded, so that the type of the struct
+/// only depends on the types of the children.
+#[derive(Debug, Hash)]
+pub struct CreateNamedStruct {
+    values: Vec<Arc<dyn PhysicalExpr>>,
+    names: Vec<String>,
+}
+
+impl CreateNamedStruct {
+    pub fn new(values: Vec<Arc<dyn PhysicalExpr>>, names: Vec<String>) -> Self {
+        Self { values, names }
+    }
+
+    fn fields(&self, input_schema: &Schema) -> Result<Fields> {
+        self.values
+            .iter()
+            .zip(&self.names)
+            .map(|(value, name)| {
+                let data_type = match value.data_type(input_schema)? {
+                    DataType::Dictionary(_, value_type) => *value_type,
+                    data_type => data_type,
+                };
+                Ok(Field::new(name, data_type, true))
+            })
+            .collect()
+    }
+}
+
+impl Display for CreateNamedStruct {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "CreateNamedStruct [names: {:?}, values: {:?}]",
+            self.names, self.values
+        )
+    }
+}
+
+impl PartialEq<dyn Any> for CreateNamedStruct {
+    fn eq(&self, other: &dyn Any) -> bool {
+        down_cast_any_ref(other)
+            .downcast_ref::<Self>()
+            .map(|x| {
+                self.names.eq(&x.names)
+                    && self.values.len() == x.values.len()
+                    && self.values.iter().zip(&x.values).all(|(a, b)| a.eq(b))
+            })
+            .unwrap_or(false)
+    }
+}
+
+impl PhysicalExpr for CreateNamedStruct {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn data_type(&self, input_schema: &Schema) -> Result<DataType> {
+        Ok(DataType::Struct(self.fields(input_schema)?))
+    }
+
+    fn nullable(&self, _: &Schema) -> Result<bool> {
+        Ok(false)
+    }
+
+    fn evaluate(&self, batch: &RecordBatch) -> Result<ColumnarValue> {
+        let fields = self.fields(&batch.schema())?;
+        let arrays = self
+            .values
+            .iter()
+            .zip(fields.iter())
+            .map(|(value, field)| {
+                let array = value.evaluate(batch)?.into_array(batch.num_rows())?;
+                if array.data_type() == field.data_type() {
+                    Ok(array)
+                } else {
+                    Ok(cast(&array, field.data_type())?)
+                }
+            })
+            .collect::<Result<Vec<ArrayRef>>>()?;
+        let array = StructArray::try_new(fields, arrays, None)?;
+        Ok(ColumnarValue::Array(Arc::new(array)))
+    }
+
+    fn children(&self) -> Vec<Arc<dyn PhysicalExpr>> {
+        self.values.clone()
+    }
+
+    fn with_new_children(
+        self: Arc<Self>,
+        children: Vec<Arc<dyn PhysicalExpr>>,
+    ) -> Result<Arc<dyn PhysicalExpr>> {
+        Ok(Arc::new(CreateNamedStruct::new(
+            children,
+            self.names.clone(),
+        )))
+    }
+
+    fn dyn_hash(&self, state: &mut dyn Hasher) {
+        let mut s = state;
+        self.values.hash(&mut s);
+        self.names.hash(&mut s);
+        self.hash(&mut s);
+    }
+}
+
+/// Spark's `GetStructField`, which extracts a field of a struct. The field is null if the struct
+/// is null.
+#[derive(Debug, Hash)]
+pub struct GetStructField {
+    child: Arc<dyn PhysicalExpr>,
+    ordinal: usize,
+}
+
+impl GetStructField {
+    pub fn new(child: Arc<dyn PhysicalExpr>, ordinal: usize) -> Self {
+        Self { child, ordinal }
+    }
+}
+
+impl Display for GetStructField {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "GetStructField [ordinal: {}, child: {}]",
+            self.ordinal, self.child
+        )
+    }
+}
+
+impl PartialEq<dyn Any> for GetStructField {
+    fn eq(&self, other: &dyn Any) -> bool {
+        down_cast_any_ref(other)
+            .downcast_ref::<Self>()
+            .map(|x| self.child.eq(&x.child) && self.ordinal == x.ordinal)
+            .unwrap_or(false)
+    }
+}
+
+impl PhysicalExpr for GetStructField {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn data_type(&self, input_schema: &Schema) -> Result<DataType> {
+        match self.child.data_type(input_schema)? {
+            DataType::Struct(fields) if self.ordinal < fields.len() => {
+                Ok(fields[self.ordinal].data_type().clone())
+            }
+            data_type => Err(DataFusionError::Internal(format!(
+                "Cannot get the field {} of {}",
+                self.ordinal, data_type
+            ))),
+        }
+    }
+
+    fn nullable(&self, _: &Schema) -> Result<bool> {
+        Ok(true)
+    }
+
+    fn evaluate(&self, batch: &RecordBatch) -> Result<ColumnarValue> {
+        let array = self.child.evaluate(batch)?.into_array(batch.num_rows())?;
+        let struct_array = array.as_struct();
+        let column = struct_array.column(self.ordinal);
+        let result = match struct_array.nulls() {
+            Some(nulls) => {
+                let nulls = NullBuffer::union(Some(nulls), column.nulls());
+                make_array(column.to_data().into_builder().nulls(nulls).build()?)
+            }
+            None => column.clone(),
+        };
+        Ok(ColumnarValue::Array(result))
+    }
+
+    fn children(&self) -> Vec<Arc<dyn PhysicalExpr>> {
+        vec![self.child.clone()]
+    }
+
+    fn with_new_children(
+        self: Arc<Self>,
+        children: Vec<Arc<dyn PhysicalExpr>>,
+    ) -> Result<Arc<dyn PhysicalExpr>> {
+        Ok(Arc::new(GetStructField::new(
+            children[0].clone(),
+            self.ordinal,
+        )))
+    }
+
+    fn dyn_hash(&self, state: &mut dyn Hasher) {
+        let mut s = state;
+        self.child.hash(&mut s);
+        self.ordinal.hash(&mut s);
+        self.hash(&mut s);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow::{
+        array::{DictionaryArray, Int32Array, Int64Array, StringArray},
+        datatypes::{Int32Type, Int64Type},
+    };
+    use datafusion_physical_expr::expressions::Column;
+
+    use super::*;
+
+    #[test]
+    fn test_create_and_get_struct_fields() -> Result<()> {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("a", DataType::Int64, true),
+            Field::new(
+                "b",
+                DataType::Dictionary(Box::new(DataType::Int32), Box::new(DataType::Utf8)),
+                true,
+            ),
+        ]));
+        let b = DictionaryArray::<Int32Type>::new(
+            Int32Array::from(vec![Some(0), None, Some(1)]),
+            Arc::new(StringArray::from(vec!["x", "y"])),
+        );
+        let batch = RecordBatch::try_new(
+            schema.clone(),
+            vec![
+                Arc::new(Int64Array::from(vec![Some(1), Some(2), None])),
+                Arc::new(b),
+            ],
+        )?;
+
+        let create = Arc::new(CreateNamedStruct::new(
+            vec![Arc::new(Column::new("a", 0)), Arc::new(Column::new("b", 1))],
+            vec!["start".to_string(), "end".to_string()],
+        ));
+        let struct_type = create.data_type(&schema)?;
+        assert_eq!(
+            struct_type,
+            DataType::Struct(Fields::from(vec![
+                Field::new("start", DataType::Int64, true),
+                Field::new("end", DataType::Utf8, true),
+            ]))
+        );
+        let result = create.evaluate(&batch)?.into_array(batch.num_rows())?;
+        assert_eq!(result.data_type(), &struct_type);
+        assert_eq!(result.null_count(), 0);
+        let end = result.as_struct().column(1).as_string::<i32>();
+        assert_eq!(
+            end.iter().collect::<Vec<_>>(),
+            vec![Some("x"), None, Some("y")]
+        );
+
+        let get = GetStructField::new(create, 0);
+        assert_eq!(get.data_type(&schema)?, DataType::Int64);
+        let result = get.evaluate(&batch)?.into_array(batch.num_rows())?;
+        assert_eq!(
+            result
+                .as_primitive::<Int64Type>()
+                .iter()
+                .collect::<Vec<_>>(),
+            vec![Some(1), Some(2), None]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_field_of_null_structs() -> Result<()> {
+        let fields = Fields::from(vec![Field::new("f", DataType::Int32, true)]);
+        let structs = StructArray::try_new(
+            fields.clone(),
+            vec![Arc::new(Int32Array::from(vec![Some(1), None, Some(3)]))],
+            Some(NullBuffer::from(vec![true, true, false])),
+        )?;
+        let schema = Arc::new(Schema::new(vec![Field::new(
+            "s",
+            DataType::Struct(fields),
+            true,
+        )]));
+        let batch = RecordBatch::try_new(schema, vec![Arc::new(structs)])?;
+
+        let get = GetStructField::new(Arc::new(Column::new("s", 0)), 0);
+        let result = get.evaluate(&batch)?.into_array(batch.num_rows())?;
+        assert_eq!(
+            result
+                .as_primitive::<Int32Type>()
+                .iter()
+                .collect::<Vec<_>>(),
+            vec![Some(1), None, None]
+        );
+        Ok(())
+    }
+}
@@ -31,8 +31,16 @@ use std::{
     sync::Arc,
 };
 
+use prost::Message;
+
 use crate::{
-    execution::{datafusion::expressions::utils::down_cast_any_ref, utils::bytes_to_i128},
+    errors::CometError,
+    execution::{
+        datafusion::expressions::utils::down_cast_any_ref,
+        operators::ExecutionError,
+        spark_expression::{self, InSubqueryValues},
+        utils::bytes_to_i128,
+    },
     jvm_bridge::{jni_static_call, BinaryWrapper, JVMClasses, StringWrapper},
 };
 
@@ -55,41 +63,9 @@ impl Subquery {
             data_type,
         }
     }
-}
-
-impl Display for Subquery {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "Subquery [id: {}]", self.id)
-    }
-}
 
-impl PartialEq<dyn Any> for Subquery {
-    fn eq(&self, other: &dyn Any) -> bool {
-        down_cast_any_ref(other)
-            .downcast_ref::<Self>()
-            .map(|x| {
-                self.id.eq(&x.id)
-                    && self.data_type.eq(&x.data_type)
-                    && self.exec_context_id.eq(&x.exec_context_id)
-            })
-            .unwrap_or(false)
-    }
-}
-
-impl PhysicalExpr for Subquery {
-    fn as_any(&self) -> &dyn Any {
-        self
-    }
-
-    fn data_type(&self, _: &Schema) -> datafusion_common::Result<DataType> {
-        Ok(self.data_type.clone())
-    }
-
-    fn nullable(&self, _: &Schema) -> datafusion_common::Result<bool> {
-        Ok(true)
-    }
-
-    fn evaluate(&self, _: &RecordBatch) -> datafusion_common::Result<ColumnarValue> {
+    /// Retrieves the result of the subquery from JVM.
+    pub fn value(&self) -> datafusion_common::Result<ScalarValue> {
         let mut env = JVMClasses::get_env();
 
         unsafe {
@@ -98,9 +74,7 @@ impl PhysicalExpr for Subquery {
             )?;
 
             if is_null > 0 {
-                return Ok(ColumnarValue::Scalar(ScalarValue::try_from(
-                    &self.data_type,
-                )?));
+                return ScalarValue::try_from(&self.data_type);
             }
 
             match &self.data_type {
@@ -108,44 +82,44 @@ impl PhysicalExpr for Subquery {
                     let r = jni_static_call!(&mut env,
                         comet_exec.get_bool(self.exec_context_id, self.id) -> jboolean
                     )?;
-                    Ok(ColumnarValue::Scalar(ScalarValue::Boolean(Some(r > 0))))
+                    Ok(ScalarValue::Boolean(Some(r > 0)))
                 }
                 DataType::Int8 => {
                     let r = jni_static_call!(&mut env,
                         comet_exec.get_byte(self.exec_context_id, self.id) -> jbyte
                     )?;
-                    Ok(ColumnarValue::Scalar(ScalarValue::Int8(Some(r))))
+                    Ok(ScalarValue::Int8(Some(r)))
                 }
                 DataType::Int16 => {
                     let r = jni_static_call!(&mut env,
                         comet_exec.get_short(self.exec_context_id, self.id) -> jshort
                     )?;
-                    Ok(ColumnarValue::Scalar(ScalarValue::Int16(Some(r))))
+                    Ok(ScalarValue::Int16(Some(r)))
                 }
                 DataType::Int32 => {
                     let r = jni_static_call!(&mut env,
                         comet_exec.get_int(self.exec_context_id, self.id) -> jint
                     )?;
-                    Ok(ColumnarValue::Scalar(ScalarValue::Int32(Some(r))))
+                    Ok(ScalarValue::Int32(Some(r)))
                 }
                 DataType::Int64 => {
                     let r = jni_static_call!(&mut env,
                         comet_exec.get_long(self.exec_context_id, self.id) -> jlong
                     )?;
-                    Ok(ColumnarValue::Scalar(ScalarValue::Int64(Some(r))))
+                    Ok(ScalarValue::Int64(Some(r)))
                 }
                 DataType::Float32 => {
                     let r = jni_static_call!(&mut env,
                         comet_exec.get_float(self.exec_context_id, self.id) -> f32
                     )?;
-                    Ok(ColumnarValue::Scalar(ScalarValue::Float32(Some(r))))
+                    Ok(ScalarValue::Float32(Some(r)))
                 }
                 DataType::Float64 => {
                     let r = jni_static_call!(&mut env,
                         comet_exec.get_double(self.exec_context_id, self.id) -> f64
                     )?;
 
-                    Ok(ColumnarValue::Scalar(ScalarValue::Float64(Some(r))))
+                    Ok(ScalarValue::Float64(Some(r)))
                 }
                 DataType::Decimal128(p, s) => {
                     let bytes = jni_static_call!(&mut env,
@@ -154,28 +128,21 @@ impl PhysicalExpr for Subquery {
                     let bytes: &JByteArray = bytes.get().into();
                     let slice = env.convert_byte_array(bytes).unwrap();
 
-                    Ok(ColumnarValue::Scalar(ScalarValue::Decimal128(
-                        Some(bytes_to_i128(&slice)),
-                        *p,
-                        *s,
-                    )))
+                    Ok(ScalarValue::Decimal128(Some(bytes_to_i128(&slice)), *p, *s))
                 }
                 DataType::Date32 => {
                     let r = jni_static_call!(&mut env,
                         comet_exec.get_int(self.exec_context_id, self.id) -> jint
                     )?;
 
-                    Ok(ColumnarValue::Scalar(ScalarValue::Date32(Some(r))))
+                    Ok(ScalarValue::Date32(Some(r)))
                 }
                 DataType::Timestamp(TimeUnit::Microsecond, timezone) => {
                     let r = jni_static_call!(&mut env,
                         comet_exec.get_long(self.exec_context_id, self.id) -> jlong
                     )?;
 
-                    Ok(ColumnarValue::Scalar(ScalarValue::TimestampMicrosecond(
-                        Some(r),
-                        timezone.clone(),
-                    )))
+                    Ok(ScalarValue::TimestampMicrosecond(Some(r), timezone.clone()))
                 }
                 DataType::Utf8 => {
                     let string = jni_static_call!(&mut env,
@@ -183,7 +150,7 @@ impl PhysicalExpr for Subquery {
                     )?;
 
                     let string = env.get_string(string.get()).unwrap().into();
-                    Ok(ColumnarValue::Scalar(ScalarValue::Utf8(Some(string))))
+                    Ok(ScalarValue::Utf8(Some(string)))
                 }
                 DataType::Binary => {
                     let bytes = jni_static_call!(&mut env,
@@ -192,12 +159,69 @@ impl PhysicalExpr for Subquery {
                     let bytes: &JByteArray = bytes.get().into();
                     let slice = env.convert_byte_array(bytes).unwrap();
 
-                    Ok(ColumnarValue::Scalar(ScalarValue::Binary(Some(slice))))
+                    Ok(ScalarValue::Binary(Some(slice)))
                 }
                 _ => internal_err!("Unsupported scalar subquery data type {:?}", self.data_type),
             }
         }
     }
+}
+
+/// Retrieves the distinct non-null values of the IN subquery with the given ID from JVM, e.g., to
+/// evaluate it as an in-list. Like Spark `InSubqueryExec`, the in-list is false rather than null
+/// for a value not in the subquery result.
+pub fn in_subquery_values(
+    exec_context_id: i64,
+    id: i64,
+) -> Result<Vec<spark_expression::Literal>, ExecutionError> {
+    let mut env = JVMClasses::get_env();
+
+    let bytes = unsafe {
+        jni_static_call!(&mut env,
+            comet_exec.get_in_values(exec_context_id, id) -> BinaryWrapper
+        )?
+    };
+    let bytes: &JByteArray = bytes.get().into();
+    let bytes = env.convert_byte_array(bytes).map_err(CometError::from)?;
+
+    Ok(InSubqueryValues::decode(bytes.as_slice())?.values)
+}
+
+impl Display for Subquery {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Subquery [id: {}]", self.id)
+    }
+}
+
+impl PartialEq<dyn Any> for Subquery {
+    fn eq(&self, other: &dyn Any) -> bool {
+        down_cast_any_ref(other)
+            .downcast_ref::<Self>()
+            .map(|x| {
+                self.id.eq(&x.id)
+                    && self.data_type.eq(&x.data_type)
+                    && self.exec_context_id.eq(&x.exec_context_id)
+            })
+            .unwrap_or(false)
+    }
+}
+
+impl PhysicalExpr for Subquery {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn data_type(&self, _: &Schema) -> datafusion_common::Result<DataType> {
+        Ok(self.data_type.clone())
+    }
+
+    fn nullable(&self, _: &Schema) -> datafusion_common::Result<bool> {
+        Ok(true)
+    }
+
+    fn evaluate(&self, _: &RecordBatch) -> datafusion_common::Result<ColumnarValue> {
+        Ok(ColumnarValue::Scalar(self.value()?))
+    }
 
     fn children(&self) -> Vec<Arc<dyn PhysicalExpr>> {
         vec![]
@@ -15,6 +15,7 @@
 // specific language governing permissions and limitations
 // under the License.
 
+use crate::execution::datafusion::expressions::utils::filter_nulls;
 use arrow::{
     array::BooleanBufferBuilder,
     buffer::{BooleanBuffer, NullBuffer},
@@ -308,7 +309,7 @@ impl SumDecimalGroupsAccumulator {
     }
 }
 
-fn ensure_bit_capacity(builder: &mut BooleanBufferBuilder, capacity: usize) {
+pub(crate) fn ensure_bit_capacity(builder: &mut BooleanBufferBuilder, capacity: usize) {
     if builder.len() < capacity {
         let additional = capacity - builder.len();
         builder.append_n(additional, true);
@@ -317,7 +318,10 @@ fn ensure_bit_capacity(builder: &mut BooleanBufferBuilder, capacity: usize) {
 
 /// Build a boolean buffer from the state and reset the state, based on the emit_to
 /// strategy.
-fn build_bool_state(state: &mut BooleanBufferBuilder, emit_to: &EmitTo) -> BooleanBuffer {
+pub(crate) fn build_bool_state(
+    state: &mut BooleanBufferBuilder,
+    emit_to: &EmitTo,
+) -> BooleanBuffer {
     let bool_state: BooleanBuffer = state.finish();
 
     match emit_to {
@@ -342,9 +346,9 @@ impl GroupsAccumulator for SumDecimalGroupsAccumulator {
         opt_filter: Option<&BooleanArray>,
         total_num_groups: usize,
     ) -> DFResult<()> {
-        assert!(opt_filter.is_none(), "opt_filter is not supported yet");
         assert_eq!(values.len(), 1);
-        let values = values[0].as_primitive::<Decimal128Type>();
+        let values = filter_nulls(&values[0], opt_filter)?;
+        let values = values.as_primitive::<Decimal128Type>();
         let data = values.values();
 
         // Update size for the accumulate states
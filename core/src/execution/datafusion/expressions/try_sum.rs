@@ -0,0 +1,346 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::execution::datafusion::expressions::{
+    sum_decimal::{build_bool_state, ensure_bit_capacity},
+    utils::filter_nulls,
+};
+use arrow::{array::BooleanBufferBuilder, buffer::NullBuffer};
+use arrow_array::{cast::AsArray, types::Int64Type, Array, ArrayRef, BooleanArray, Int64Array};
+use arrow_schema::{DataType, Field};
+use datafusion::logical_expr::{Accumulator, EmitTo, GroupsAccumulator};
+use datafusion_common::{Result as DFResult, ScalarValue};
+use datafusion_physical_expr::{aggregate::utils::down_cast_any_ref, AggregateExpr, PhysicalExpr};
+use std::{any::Any, ops::BitAnd, sync::Arc};
+
+use crate::unlikely;
+
+/// SUM of long values in TRY mode, i.e., Spark `try_sum`, which returns null if the sum
+/// overflows instead of wrapping around or failing.
+///
+/// Like [`super::sum_decimal::SumDecimal`], the state is the sum and whether no value has been
+/// added. The sum is null if it has overflowed, so an overflow before merging also makes the
+/// final result null.
+#[derive(Debug)]
+pub struct TrySum {
+    name: String,
+    expr: Arc<dyn PhysicalExpr>,
+}
+
+impl TrySum {
+    pub fn new(name: impl Into<String>, expr: Arc<dyn PhysicalExpr>) -> Self {
+        Self {
+            name: name.into(),
+            expr,
+        }
+    }
+}
+
+impl AggregateExpr for TrySum {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn field(&self) -> DFResult<Field> {
+        Ok(Field::new(&self.name, DataType::Int64, true))
+    }
+
+    fn create_accumulator(&self) -> DFResult<Box<dyn Accumulator>> {
+        Ok(Box::<TrySumAccumulator>::default())
+    }
+
+    fn state_fields(&self) -> DFResult<Vec<Field>> {
+        Ok(vec![
+            Field::new(&self.name, DataType::Int64, true),
+            Field::new("is_empty", DataType::Boolean, false),
+        ])
+    }
+
+    fn expressions(&self) -> Vec<Arc<dyn PhysicalExpr>> {
+        vec![self.expr.clone()]
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn groups_accumulator_supported(&self) -> bool {
+        true
+    }
+
+    fn create_groups_accumulator(&self) -> DFResult<Box<dyn GroupsAccumulator>> {
+        Ok(Box::new(TrySumGroupsAccumulator::new()))
+    }
+}
+
+impl PartialEq<dyn Any> for TrySum {
+    fn eq(&self, other: &dyn Any) -> bool {
+        down_cast_any_ref(other)
+            .downcast_ref::<Self>()
+            .map(|x| self.name == x.name && self.expr.eq(&x.expr))
+            .unwrap_or(false)
+    }
+}
+
+#[derive(Debug)]
+struct TrySumAccumulator {
+    /// The sum, or `None` if it has overflowed
+    sum: Option<i64>,
+    is_empty: bool,
+}
+
+impl Default for TrySumAccumulator {
+    fn default() -> Self {
+        Self {
+            sum: Some(0),
+            is_empty: true,
+        }
+    }
+}
+
+impl TrySumAccumulator {
+    fn add(&mut self, value: i64) {
+        self.sum = self.sum.and_then(|sum| sum.checked_add(value));
+    }
+}
+
+impl Accumulator for TrySumAccumulator {
+    fn update_batch(&mut self, values: &[ArrayRef]) -> DFResult<()> {
+        assert_eq!(
+            values.len(),
+            1,
+            "Expect only one element in 'values' but found {}",
+            values.len()
+        );
+        let values = values[0].as_primitive::<Int64Type>();
+
+        self.is_empty = self.is_empty && values.len() == values.null_count();
+        for value in values.iter().flatten() {
+            if unlikely(self.sum.is_none()) {
+                break;
+            }
+            self.add(value);
+        }
+        Ok(())
+    }
+
+    fn evaluate(&mut self) -> DFResult<ScalarValue> {
+        if self.is_empty {
+            Ok(ScalarValue::Int64(None))
+        } else {
+            Ok(ScalarValue::Int64(self.sum))
+        }
+    }
+
+    fn size(&self) -> usize {
+        std::mem::size_of_val(self)
+    }
+
+    fn state(&mut self) -> DFResult<Vec<ScalarValue>> {
+        Ok(vec![
+            ScalarValue::Int64(self.sum),
+            ScalarValue::from(self.is_empty),
+        ])
+    }
+
+    fn merge_batch(&mut self, states: &[ArrayRef]) -> DFResult<()> {
+        assert_eq!(
+            states.len(),
+            2,
+            "Expect two element in 'states' but found {}",
+            states.len()
+        );
+        let that_sum = states[0].as_primitive::<Int64Type>();
+        let that_is_empty = states[1].as_boolean();
+
+        for idx in 0..that_sum.len() {
+            self.is_empty = self.is_empty && that_is_empty.value(idx);
+            if that_sum.is_null(idx) {
+                // The other sum has overflowed
+                self.sum = None;
+            } else {
+                self.add(that_sum.value(idx));
+            }
+        }
+        Ok(())
+    }
+}
+
+struct TrySumGroupsAccumulator {
+    /// Whether the sum of a group has not overflowed
+    is_not_null: BooleanBufferBuilder,
+    is_empty: BooleanBufferBuilder,
+    sum: Vec<i64>,
+}
+
+impl TrySumGroupsAccumulator {
+    fn new() -> Self {
+        Self {
+            is_not_null: BooleanBufferBuilder::new(0),
+            is_empty: BooleanBufferBuilder::new(0),
+            sum: Vec::new(),
+        }
+    }
+
+    fn resize(&mut self, total_num_groups: usize) {
+        self.sum.resize(total_num_groups, 0);
+        ensure_bit_capacity(&mut self.is_empty, total_num_groups);
+        ensure_bit_capacity(&mut self.is_not_null, total_num_groups);
+    }
+
+    fn add(&mut self, group_index: usize, value: i64) {
+        if unlikely(!self.is_not_null.get_bit(group_index)) {
+            return;
+        }
+        match self.sum[group_index].checked_add(value) {
+            Some(sum) => self.sum[group_index] = sum,
+            None => self.is_not_null.set_bit(group_index, false),
+        }
+    }
+}
+
+impl GroupsAccumulator for TrySumGroupsAccumulator {
+    fn update_batch(
+        &mut self,
+        values: &[ArrayRef],
+        group_indices: &[usize],
+        opt_filter: Option<&BooleanArray>,
+        total_num_groups: usize,
+    ) -> DFResult<()> {
+        assert_eq!(values.len(), 1);
+        let values = filter_nulls(&values[0], opt_filter)?;
+        let values = values.as_primitive::<Int64Type>();
+        self.resize(total_num_groups);
+
+        for (idx, &group_index) in group_indices.iter().enumerate() {
+            if values.is_null(idx) {
+                continue;
+            }
+            self.is_empty.set_bit(group_index, false);
+            self.add(group_index, values.value(idx));
+        }
+        Ok(())
+    }
+
+    fn evaluate(&mut self, emit_to: EmitTo) -> DFResult<ArrayRef> {
+        // The result of a group is null if it has no value or its sum has overflowed
+        let is_not_null = build_bool_state(&mut self.is_not_null, &emit_to);
+        let is_empty = build_bool_state(&mut self.is_empty, &emit_to);
+        let nulls = NullBuffer::new((!&is_empty).bitand(&is_not_null));
+
+        let sum = emit_to.take_needed(&mut self.sum);
+        Ok(Arc::new(Int64Array::new(sum.into(), Some(nulls))))
+    }
+
+    fn state(&mut self, emit_to: EmitTo) -> DFResult<Vec<ArrayRef>> {
+        let is_not_null = build_bool_state(&mut self.is_not_null, &emit_to);
+        let sum = emit_to.take_needed(&mut self.sum);
+        let sum = Int64Array::new(sum.into(), Some(NullBuffer::new(is_not_null)));
+
+        let is_empty = build_bool_state(&mut self.is_empty, &emit_to);
+        let is_empty = BooleanArray::new(is_empty, None);
+
+        Ok(vec![Arc::new(sum), Arc::new(is_empty)])
+    }
+
+    fn merge_batch(
+        &mut self,
+        values: &[ArrayRef],
+        group_indices: &[usize],
+        opt_filter: Option<&BooleanArray>,
+        total_num_groups: usize,
+    ) -> DFResult<()> {
+        assert_eq!(
+            values.len(),
+            2,
+            "Expected two arrays: 'sum' and 'is_empty', but found {}",
+            values.len()
+        );
+        assert!(opt_filter.is_none(), "opt_filter is not supported yet");
+        self.resize(total_num_groups);
+
+        let that_sum = values[0].as_primitive::<Int64Type>();
+        let that_is_empty = values[1].as_boolean();
+
+        for (idx, &group_index) in group_indices.iter().enumerate() {
+            let is_empty = self.is_empty.get_bit(group_index) && that_is_empty.value(idx);
+            self.is_empty.set_bit(group_index, is_empty);
+            if that_sum.is_null(idx) {
+                // The other sum has overflowed
+                self.is_not_null.set_bit(group_index, false);
+            } else {
+                self.add(group_index, that_sum.value(idx));
+            }
+        }
+        Ok(())
+    }
+
+    fn size(&self) -> usize {
+        self.sum.capacity() * std::mem::size_of::<i64>()
+            + self.is_empty.capacity() / 8
+            + self.is_not_null.capacity() / 8
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use arrow_array::{Array, ArrayRef, BooleanArray, Int64Array};
+    use datafusion::logical_expr::{EmitTo, GroupsAccumulator};
+
+    use super::TrySumGroupsAccumulator;
+
+    #[test]
+    fn test_try_sum_overflow() {
+        let values: ArrayRef = Arc::new(Int64Array::from(vec![
+            Some(i64::MAX),
+            Some(1),
+            Some(2),
+            None,
+            Some(3),
+            Some(i64::MAX),
+        ]));
+        let filter = BooleanArray::from(vec![true, true, true, true, true, false]);
+
+        // Group 0 overflows, group 1 sums 2 and 3, group 2 has only null and filtered values
+        let mut partial = TrySumGroupsAccumulator::new();
+        partial
+            .update_batch(&[values], &[0, 0, 1, 2, 1, 2], Some(&filter), 3)
+            .unwrap();
+        let state = partial.state(EmitTo::All).unwrap();
+        assert!(state[0].is_null(0));
+
+        // The overflow before merging makes the result null
+        let mut final_ = TrySumGroupsAccumulator::new();
+        final_.merge_batch(&state, &[0, 1, 2], None, 3).unwrap();
+        final_.merge_batch(&state, &[1, 0, 2], None, 3).unwrap();
+        let result = final_.evaluate(EmitTo::All).unwrap();
+        assert_eq!(
+            result.as_any().downcast_ref::<Int64Array>().unwrap(),
+            &Int64Array::from(vec![None, None, None])
+        );
+
+        let mut final_ = TrySumGroupsAccumulator::new();
+        final_.merge_batch(&state, &[2, 0, 1], None, 3).unwrap();
+        let result = final_.evaluate(EmitTo::All).unwrap();
+        assert_eq!(
+            result.as_any().downcast_ref::<Int64Array>().unwrap(),
+            &Int64Array::from(vec![Some(5), None, None])
+        );
+    }
+}
@@ -18,20 +18,17 @@
 use crate::execution::timezone::Tz;
 use arrow::{
     array::{
-        as_dictionary_array, as_primitive_array, Array, ArrayRef, GenericStringArray,
-        PrimitiveArray,
+        as_dictionary_array, as_primitive_array, make_array, Array, ArrayRef, BooleanArray,
+        GenericStringArray, PrimitiveArray,
     },
-    compute::unary,
-    datatypes::{Int32Type, Int64Type, TimestampMicrosecondType},
-    error::ArrowError,
+    buffer::NullBuffer,
+    datatypes::{Int32Type, TimestampMicrosecondType},
     temporal_conversions::as_datetime,
 };
-use arrow_array::{cast::AsArray, types::ArrowPrimitiveType};
 use arrow_schema::DataType;
 use chrono::{DateTime, Offset, TimeZone};
-use datafusion_common::cast::as_generic_string_array;
+use datafusion_common::{cast::as_generic_string_array, Result as DFResult};
 use datafusion_physical_expr::PhysicalExpr;
-use num::integer::div_floor;
 use std::{any::Any, sync::Arc};
 
 /// An utility function from DataFusion. It is not exposed by DataFusion.
@@ -49,6 +46,24 @@ pub fn down_cast_any_ref(any: &dyn Any) -> &dyn Any {
     }
 }
 
+/// Nulls out the values of `array` which are not selected by `opt_filter`, the aggregate filter
+/// passed to `GroupsAccumulator::update_batch`, so accumulators can skip them like null values.
+pub(crate) fn filter_nulls(
+    array: &ArrayRef,
+    opt_filter: Option<&BooleanArray>,
+) -> DFResult<ArrayRef> {
+    let Some(filter) = opt_filter else {
+        return Ok(array.clone());
+    };
+    let selected = match filter.nulls() {
+        Some(nulls) => filter.values() & nulls.inner(),
+        None => filter.values().clone(),
+    };
+    let nulls = NullBuffer::union(array.nulls(), Some(&NullBuffer::new(selected)));
+    let data = array.to_data().into_builder().nulls(nulls).build()?;
+    Ok(make_array(data))
+}
+
 /// Preprocesses input arrays to add timezone information from Spark to Arrow array datatype or
 /// to apply timezone offset.
 //
@@ -58,8 +73,8 @@ pub fn down_cast_any_ref(any: &dyn Any) -> &dyn Any {
 //  | Conversion            | Input array  | Timezone          | Output array                     |
 //  | --------------------- | ------------ | ----------------- | -------------------------------- |
 //  | Timestamp ->          | Array in UTC | Timezone of input | A timestamp with the timezone    |
-//  |  Utf8 or Date32       |              |                   | offset applied and timezone      |
-//  |                       |              |                   | removed                          |
+//  |  Utf8, Date32 or      |              |                   | offset applied and timezone      |
+//  |  Timestamp_ntz        |              |                   | removed                          |
 //  | --------------------- | ------------ | ----------------- | -------------------------------- |
 //  | Timestamp ->          | Array in UTC | Timezone of input | Same as input array              |
 //  |  Timestamp  w/Timezone|              |                   |                                  |
@@ -106,9 +121,9 @@ pub fn array_with_timezone(
             let array_with_timezone = array.clone().with_timezone(timezone.clone());
             let array = Arc::new(array_with_timezone) as ArrayRef;
             match to_type {
-                Some(DataType::Utf8) | Some(DataType::Date32) => {
-                    pre_timestamp_cast(array, timezone)
-                }
+                Some(DataType::Utf8)
+                | Some(DataType::Date32)
+                | Some(DataType::Timestamp(_, None)) => pre_timestamp_cast(array, timezone),
                 _ => array,
             }
         }
@@ -160,7 +175,7 @@ fn timestamp_ntz_to_timestamp(array: ArrayRef, tz: &str, to_timezone: Option<&st
     }
 }
 
-const MICROS_PER_SECOND: i64 = 1000000;
+pub(crate) const MICROS_PER_SECOND: i64 = 1000000;
 
 /// This takes for special pre-casting cases of Spark. E.g., Timestamp to String.
 fn pre_timestamp_cast(array: ArrayRef, timezone: String) -> ArrayRef {
@@ -190,23 +205,13 @@ fn pre_timestamp_cast(array: ArrayRef, timezone: String) -> ArrayRef {
     }
 }
 
-/// This takes for special casting cases of Spark. E.g., Timestamp to Long.
+/// This takes for special casting cases of Spark. E.g., Timestamp to String.
 /// This function runs as a post process of the DataFusion cast(). By the time it arrives here,
 /// Dictionary arrays are already unpacked by the DataFusion cast() since Spark cannot specify
 /// Dictionary as to_type. The from_type is taken before the DataFusion cast() runs in
 /// expressions/cast.rs, so it can be still Dictionary.
 pub(crate) fn spark_cast(array: ArrayRef, from_type: &DataType, to_type: &DataType) -> ArrayRef {
     match (from_type, to_type) {
-        (DataType::Timestamp(_, _), DataType::Int64) => {
-            // See Spark's `Cast` expression
-            unary_dyn::<_, Int64Type>(&array, |v| div_floor(v, MICROS_PER_SECOND)).unwrap()
-        }
-        (DataType::Dictionary(_, value_type), DataType::Int64)
-            if matches!(value_type.as_ref(), &DataType::Timestamp(_, _)) =>
-        {
-            // See Spark's `Cast` expression
-            unary_dyn::<_, Int64Type>(&array, |v| div_floor(v, MICROS_PER_SECOND)).unwrap()
-        }
         (DataType::Timestamp(_, _), DataType::Utf8) => remove_trailing_zeroes(array),
         (DataType::Dictionary(_, value_type), DataType::Utf8)
             if matches!(value_type.as_ref(), &DataType::Timestamp(_, _)) =>
@@ -217,32 +222,6 @@ pub(crate) fn spark_cast(array: ArrayRef, from_type: &DataType, to_type: &DataTy
     }
 }
 
-/// A fork & modified version of Arrow's `unary_dyn` which is being deprecated
-fn unary_dyn<F, T>(array: &ArrayRef, op: F) -> Result<ArrayRef, ArrowError>
-where
-    T: ArrowPrimitiveType,
-    F: Fn(T::Native) -> T::Native,
-{
-    if let Some(d) = array.as_any_dictionary_opt() {
-        let new_values = unary_dyn::<F, T>(d.values(), op)?;
-        return Ok(Arc::new(d.with_values(Arc::new(new_values))));
-    }
-
-    match array.as_primitive_opt::<T>() {
-        Some(a) if PrimitiveArray::<T>::is_compatible(a.data_type()) => {
-            Ok(Arc::new(unary::<T, F, T>(
-                array.as_any().downcast_ref::<PrimitiveArray<T>>().unwrap(),
-                op,
-            )))
-        }
-        _ => Err(ArrowError::NotYetImplemented(format!(
-            "Cannot perform unary operation of type {} on array of type {}",
-            T::DATA_TYPE,
-            array.data_type()
-        ))),
-    }
-}
-
 /// Remove any trailing zeroes in the string if they occur after in the fractional seconds,
 /// to match Spark behavior
 /// example:
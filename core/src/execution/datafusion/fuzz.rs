@@ -0,0 +1,531 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Differential fuzzing of native expressions.
+//!
+//! There are two kinds of tests here:
+//!
+//! - `test_golden_fixtures` evaluates expressions against golden results produced by Spark. The
+//!   fixtures are generated by `CometExpressionFuzzSuite` on the JVM side and stored in
+//!   `testdata/fuzz`. Each fixture consists of `<name>.expr`, the serialized `Expr` protobuf, and
+//!   `<name>.arrow`, an Arrow IPC stream whose last column `expected` is the result computed by
+//!   Spark for the input in the other columns. Besides the random `case_*` fixtures, the suite
+//!   generates the fixtures named after their expressions, which cover edge cases such as integral
+//!   overflow, NaN and multi-byte characters. See `testdata/fuzz/README.md` to regenerate them.
+//! - `test_random_expressions` evaluates random expression trees on random batches. There is no
+//!   golden result for them, but evaluation must not panic and must return valid arrays of the
+//!   declared data type.
+
+use std::{
+    fs::{self, File},
+    panic::{catch_unwind, AssertUnwindSafe},
+    path::PathBuf,
+    sync::Arc,
+};
+
+use arrow::{
+    compute::concat_batches, ipc::reader::StreamReader, util::display::array_value_to_string,
+};
+use arrow_array::{
+    cast::AsArray,
+    types::{Float32Type, Float64Type},
+    Array, ArrayRef, BooleanArray, Float64Array, Int32Array, Int64Array, RecordBatch, StringArray,
+};
+use arrow_schema::{DataType, Field, Schema};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::execution::{
+    datafusion::planner::PhysicalPlanner,
+    serde::deserialize_expr,
+    spark_expression::{self, expr::ExprStruct, literal::Value, Expr},
+};
+
+const FIXTURE_DIR: &str = "testdata/fuzz";
+
+const BOOL: i32 = 0;
+const INT32: i32 = 3;
+const INT64: i32 = 4;
+const DOUBLE: i32 = 6;
+const STRING: i32 = 7;
+const TYPES: [i32; 5] = [BOOL, INT32, INT64, DOUBLE, STRING];
+
+const NUM_ROWS: usize = 256;
+const NUM_RANDOM_EXPRS: usize = 500;
+const MAX_DEPTH: usize = 4;
+
+struct Fixture {
+    name: String,
+    expr: Expr,
+    input: RecordBatch,
+    expected: ArrayRef,
+}
+
+fn load_fixtures() -> Vec<Fixture> {
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join(FIXTURE_DIR);
+    let entries = fs::read_dir(&dir)
+        .unwrap_or_else(|e| panic!("Cannot read fixtures in {}: {}", dir.display(), e));
+
+    let mut fixtures = entries
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().map_or(false, |ext| ext == "expr"))
+        .map(|expr_path| {
+            let name = expr_path.file_stem().unwrap().to_string_lossy().to_string();
+            let expr = deserialize_expr(&fs::read(&expr_path).unwrap()).unwrap();
+
+            let file = File::open(expr_path.with_extension("arrow")).unwrap();
+            let reader = StreamReader::try_new(file, None).unwrap();
+            let schema = reader.schema();
+            let batches = reader.collect::<Result<Vec<_>, _>>().unwrap();
+            let batch = concat_batches(&schema, &batches).unwrap();
+
+            let num_inputs = batch.num_columns() - 1;
+            let expected = batch.column(num_inputs).clone();
+            let input = batch.project(&(0..num_inputs).collect::<Vec<_>>()).unwrap();
+
+            Fixture {
+                name,
+                expr,
+                input,
+                expected,
+            }
+        })
+        .collect::<Vec<_>>();
+    fixtures.sort_by(|a, b| a.name.cmp(&b.name));
+    fixtures
+}
+
+/// Returns the first row where `actual` differs from `expected`. Floating point NaNs are
+/// considered equal to each other, as in Spark.
+fn first_mismatch(expected: &dyn Array, actual: &dyn Array) -> Option<usize> {
+    if expected.len() != actual.len() {
+        return Some(expected.len().min(actual.len()));
+    }
+
+    (0..expected.len()).find(|&i| {
+        if expected.is_null(i) || actual.is_null(i) {
+            return expected.is_null(i) != actual.is_null(i);
+        }
+        match expected.data_type() {
+            DataType::Float64 => {
+                let (e, a) = (
+                    expected.as_primitive::<Float64Type>().value(i),
+                    actual.as_primitive::<Float64Type>().value(i),
+                );
+                !(e == a || (e.is_nan() && a.is_nan()))
+            }
+            DataType::Float32 => {
+                let (e, a) = (
+                    expected.as_primitive::<Float32Type>().value(i),
+                    actual.as_primitive::<Float32Type>().value(i),
+                );
+                !(e == a || (e.is_nan() && a.is_nan()))
+            }
+            _ => expected.slice(i, 1).to_data() != actual.slice(i, 1).to_data(),
+        }
+    })
+}
+
+#[test]
+fn test_golden_fixtures() {
+    let planner = PhysicalPlanner::default();
+    let mut divergences = vec![];
+
+    let fixtures = load_fixtures();
+    assert!(!fixtures.is_empty(), "No fixtures found in {}", FIXTURE_DIR);
+    for fixture in fixtures {
+        let result = planner
+            .create_expr(&fixture.expr, fixture.input.schema())
+            .map_err(|e| e.to_string())
+            .and_then(|expr| expr.evaluate(&fixture.input).map_err(|e| e.to_string()))
+            .and_then(|value| {
+                value
+                    .into_array(fixture.input.num_rows())
+                    .map_err(|e| e.to_string())
+            });
+
+        let actual = match result {
+            Ok(actual) => actual,
+            Err(e) => {
+                divergences.push(format!("{}: evaluation failed: {}", fixture.name, e));
+                continue;
+            }
+        };
+
+        if actual.data_type() != fixture.expected.data_type() {
+            divergences.push(format!(
+                "{}: expected data type {} but got {}",
+                fixture.name,
+                fixture.expected.data_type(),
+                actual.data_type()
+            ));
+        } else if let Some(row) = first_mismatch(&fixture.expected, &actual) {
+            let display = |array: &ArrayRef| {
+                if row < array.len() {
+                    array_value_to_string(array, row).unwrap()
+                } else {
+                    "<missing>".to_string()
+                }
+            };
+            divergences.push(format!(
+                "{}: row {} expected {} but got {}",
+                fixture.name,
+                row,
+                display(&fixture.expected),
+                display(&actual)
+            ));
+        }
+    }
+
+    assert!(
+        divergences.is_empty(),
+        "Found {} divergences from Spark:\n{}",
+        divergences.len(),
+        divergences.join("\n")
+    );
+}
+
+/// Generates random expression trees over the columns of a batch created by `create_batch`.
+struct ExprGenerator {
+    rng: StdRng,
+}
+
+impl ExprGenerator {
+    fn new(seed: u64) -> Self {
+        Self {
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    fn data_type(type_id: i32) -> Option<spark_expression::DataType> {
+        Some(spark_expression::DataType {
+            type_id,
+            type_info: None,
+        })
+    }
+
+    fn expr(expr_struct: ExprStruct) -> Expr {
+        Expr {
+            expr_struct: Some(expr_struct),
+        }
+    }
+
+    fn random_type(&mut self) -> i32 {
+        TYPES[self.rng.gen_range(0..TYPES.len())]
+    }
+
+    fn random_numeric_type(&mut self) -> i32 {
+        [INT32, INT64, DOUBLE][self.rng.gen_range(0..3)]
+    }
+
+    /// Column `i` of the batch has type `TYPES[i]`.
+    fn column(&mut self, type_id: i32) -> Expr {
+        let index = TYPES.iter().position(|t| *t == type_id).unwrap() as i32;
+        Self::expr(ExprStruct::Bound(spark_expression::BoundReference {
+            index,
+            datatype: Self::data_type(type_id),
+        }))
+    }
+
+    fn literal(&mut self, type_id: i32) -> Expr {
+        let is_null = self.rng.gen_bool(0.1);
+        let value = if is_null {
+            None
+        } else {
+            Some(match type_id {
+                BOOL => Value::BoolVal(self.rng.gen()),
+                INT32 => Value::IntVal(self.rng.gen_range(-100..100)),
+                INT64 => Value::LongVal(self.rng.gen_range(-100..100)),
+                DOUBLE => Value::DoubleVal(self.rng.gen_range(-100.0..100.0)),
+                _ => Value::StringVal(random_string(&mut self.rng)),
+            })
+        };
+        Self::expr(ExprStruct::Literal(spark_expression::Literal {
+            value,
+            datatype: Self::data_type(type_id),
+            is_null,
+        }))
+    }
+
+    fn leaf(&mut self, type_id: i32) -> Expr {
+        if self.rng.gen_bool(0.7) {
+            self.column(type_id)
+        } else {
+            self.literal(type_id)
+        }
+    }
+
+    fn boxed(&mut self, type_id: i32, depth: usize) -> Option<Box<Expr>> {
+        Some(Box::new(self.generate(type_id, depth)))
+    }
+
+    /// Generates a random expression returning `type_id`.
+    fn generate(&mut self, type_id: i32, depth: usize) -> Expr {
+        if depth == 0 || self.rng.gen_bool(0.2) {
+            return self.leaf(type_id);
+        }
+        let depth = depth - 1;
+
+        // Expressions valid for all types
+        match self.rng.gen_range(0..10) {
+            0 => {
+                return Self::expr(ExprStruct::If(Box::new(spark_expression::IfExpr {
+                    if_expr: self.boxed(BOOL, depth),
+                    true_expr: self.boxed(type_id, depth),
+                    false_expr: self.boxed(type_id, depth),
+                })))
+            }
+            1 => {
+                let num_branches = self.rng.gen_range(1..4);
+                return Self::expr(ExprStruct::CaseWhen(Box::new(spark_expression::CaseWhen {
+                    expr: None,
+                    when: (0..num_branches)
+                        .map(|_| self.generate(BOOL, depth))
+                        .collect(),
+                    then: (0..num_branches)
+                        .map(|_| self.generate(type_id, depth))
+                        .collect(),
+                    else_expr: self.boxed(type_id, depth),
+                })));
+            }
+            _ => {}
+        }
+
+        match type_id {
+            BOOL => {
+                let operand_type = self.random_type();
+                let left = self.boxed(operand_type, depth);
+                let right = self.boxed(operand_type, depth);
+                match self.rng.gen_range(0..9) {
+                    0 => Self::expr(ExprStruct::Eq(Box::new(spark_expression::Equal {
+                        left,
+                        right,
+                    }))),
+                    1 => Self::expr(ExprStruct::Lt(Box::new(spark_expression::LessThan {
+                        left,
+                        right,
+                    }))),
+                    2 => Self::expr(ExprStruct::GtEq(Box::new(
+                        spark_expression::GreaterThanEqual { left, right },
+                    ))),
+                    3 => Self::expr(ExprStruct::EqNullSafe(Box::new(
+                        spark_expression::EqualNullSafe { left, right },
+                    ))),
+                    4 => Self::expr(ExprStruct::And(Box::new(spark_expression::And {
+                        left: self.boxed(BOOL, depth),
+                        right: self.boxed(BOOL, depth),
+                    }))),
+                    5 => Self::expr(ExprStruct::Or(Box::new(spark_expression::Or {
+                        left: self.boxed(BOOL, depth),
+                        right: self.boxed(BOOL, depth),
+                    }))),
+                    6 => Self::expr(ExprStruct::Not(Box::new(spark_expression::Not {
+                        child: self.boxed(BOOL, depth),
+                    }))),
+                    7 => Self::expr(ExprStruct::IsNull(Box::new(spark_expression::IsNull {
+                        child: left,
+                    }))),
+                    _ => Self::expr(ExprStruct::IsNotNull(Box::new(
+                        spark_expression::IsNotNull { child: left },
+                    ))),
+                }
+            }
+            INT32 | INT64 | DOUBLE => {
+                let left = self.boxed(type_id, depth);
+                let right = self.boxed(type_id, depth);
+                let return_type = Self::data_type(type_id);
+                match self.rng.gen_range(0..6) {
+                    0 => Self::expr(ExprStruct::Add(Box::new(spark_expression::Add {
+                        left,
+                        right,
+                        fail_on_error: false,
+                        return_type,
+                        allow_precision_loss: true,
+                    }))),
+                    1 => Self::expr(ExprStruct::Subtract(Box::new(spark_expression::Subtract {
+                        left,
+                        right,
+                        fail_on_error: false,
+                        return_type,
+                        allow_precision_loss: true,
+                    }))),
+                    2 => Self::expr(ExprStruct::Multiply(Box::new(spark_expression::Multiply {
+                        left,
+                        right,
+                        fail_on_error: false,
+                        return_type,
+                        allow_precision_loss: true,
+                    }))),
+                    3 => Self::expr(ExprStruct::Negative(Box::new(spark_expression::Negative {
+                        child: left,
+                    }))),
+                    4 => Self::expr(ExprStruct::Abs(Box::new(spark_expression::Abs {
+                        child: left,
+                    }))),
+                    _ => {
+                        let from_type = self.random_numeric_type();
+                        self.cast(from_type, type_id, depth)
+                    }
+                }
+            }
+            _ => match self.rng.gen_range(0..2) {
+                0 => Self::expr(ExprStruct::Substring(Box::new(
+                    spark_expression::Substring {
+                        child: self.boxed(STRING, depth),
+                        start: self.rng.gen_range(-3..4),
+                        len: self.rng.gen_range(0..5),
+                    },
+                ))),
+                _ => {
+                    let from_type = self.random_type();
+                    self.cast(from_type, STRING, depth)
+                }
+            },
+        }
+    }
+
+    fn cast(&mut self, from_type: i32, to_type: i32, depth: usize) -> Expr {
+        Self::expr(ExprStruct::Cast(Box::new(spark_expression::Cast {
+            child: self.boxed(from_type, depth),
+            datatype: Self::data_type(to_type),
+            timezone: "UTC".to_string(),
+            eval_mode: "LEGACY".to_string(),
+        })))
+    }
+}
+
+fn random_string(rng: &mut StdRng) -> String {
+    let len = rng.gen_range(0..8);
+    (0..len)
+        .map(|_| ['a', 'b', 'Z', '0', ' ', 'é', '中'][rng.gen_range(0..7)])
+        .collect()
+}
+
+/// Creates a batch with a column for each of `TYPES`, including nulls and edge values.
+fn create_batch(rng: &mut StdRng) -> RecordBatch {
+    let maybe_null = |rng: &mut StdRng| rng.gen_bool(0.1);
+
+    let bools: BooleanArray = (0..NUM_ROWS)
+        .map(|_| (!maybe_null(rng)).then(|| rng.gen::<bool>()))
+        .collect();
+    let ints: Int32Array = (0..NUM_ROWS)
+        .map(|_| {
+            (!maybe_null(rng)).then(|| match rng.gen_range(0..10) {
+                0 => i32::MAX,
+                1 => i32::MIN,
+                _ => rng.gen_range(-1000..1000),
+            })
+        })
+        .collect();
+    let longs: Int64Array = (0..NUM_ROWS)
+        .map(|_| {
+            (!maybe_null(rng)).then(|| match rng.gen_range(0..10) {
+                0 => i64::MAX,
+                1 => i64::MIN,
+                _ => rng.gen_range(-1000..1000),
+            })
+        })
+        .collect();
+    let doubles: Float64Array = (0..NUM_ROWS)
+        .map(|_| {
+            (!maybe_null(rng)).then(|| match rng.gen_range(0..10) {
+                0 => f64::NAN,
+                1 => -0.0,
+                2 => f64::INFINITY,
+                _ => rng.gen_range(-1000.0..1000.0),
+            })
+        })
+        .collect();
+    let strings: StringArray = (0..NUM_ROWS)
+        .map(|_| (!maybe_null(rng)).then(|| random_string(rng)))
+        .collect();
+
+    let schema = Schema::new(vec![
+        Field::new("c0", DataType::Boolean, true),
+        Field::new("c1", DataType::Int32, true),
+        Field::new("c2", DataType::Int64, true),
+        Field::new("c3", DataType::Float64, true),
+        Field::new("c4", DataType::Utf8, true),
+    ]);
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(bools),
+        Arc::new(ints),
+        Arc::new(longs),
+        Arc::new(doubles),
+        Arc::new(strings),
+    ];
+    RecordBatch::try_new(Arc::new(schema), columns).unwrap()
+}
+
+#[test]
+fn test_random_expressions() {
+    let planner = PhysicalPlanner::default();
+    let mut generator = ExprGenerator::new(42);
+    let batch = create_batch(&mut StdRng::seed_from_u64(42));
+    let mut failures = vec![];
+
+    for _ in 0..NUM_RANDOM_EXPRS {
+        let type_id = generator.random_type();
+        let spark_expr = generator.generate(type_id, MAX_DEPTH);
+
+        // Not all the generated expressions are supported, e.g., some casts
+        let expr = match planner.create_expr(&spark_expr, batch.schema()) {
+            Ok(expr) => expr,
+            Err(_) => continue,
+        };
+        let data_type = match expr.data_type(&batch.schema()) {
+            Ok(data_type) => data_type,
+            Err(_) => continue,
+        };
+
+        let result = catch_unwind(AssertUnwindSafe(|| {
+            expr.evaluate(&batch)
+                .and_then(|value| value.into_array(batch.num_rows()))
+        }));
+
+        match result {
+            Err(_) => failures.push(format!("panicked: {:?}", spark_expr)),
+            // Errors are legitimate results, e.g., for invalid casts
+            Ok(Err(_)) => {}
+            Ok(Ok(array)) => {
+                if array.len() != batch.num_rows() {
+                    failures.push(format!(
+                        "returned {} rows instead of {}: {:?}",
+                        array.len(),
+                        batch.num_rows(),
+                        spark_expr
+                    ));
+                } else if array.data_type() != &data_type {
+                    failures.push(format!(
+                        "returned {} instead of declared {}: {:?}",
+                        array.data_type(),
+                        data_type,
+                        spark_expr
+                    ));
+                } else if let Err(e) = array.to_data().validate_full() {
+                    failures.push(format!("returned invalid array ({}): {:?}", e, spark_expr));
+                }
+            }
+        }
+    }
+
+    assert!(
+        failures.is_empty(),
+        "Found {} failures:\n{}",
+        failures.len(),
+        failures.join("\n")
+    );
+}
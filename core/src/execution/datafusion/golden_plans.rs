@@ -0,0 +1,111 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Golden-file tests of native plan deserialization.
+//!
+//! `core/testdata/plans` contains `<name>.pb`, a serialized `Operator` protobuf produced by the
+//! JVM side for a known query shape (see `CometPlanSerdeSuite`), and `<name>.txt`, the native
+//! operator tree the plan is expected to be planned into. Together with the JVM suite, which
+//! checks that the plugin still serializes the same bytes for each query shape, this catches
+//! silent incompatibilities between the plugin and the native library.
+//!
+//! To regenerate the `.txt` files after an intended change, run the tests with
+//! `SPARK_GENERATE_GOLDEN_FILES=1`.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use datafusion::physical_plan::displayable;
+
+use crate::execution::{datafusion::planner::PhysicalPlanner, serde::deserialize_op};
+
+const GOLDEN_PLAN_DIR: &str = "testdata/plans";
+
+fn regenerate_golden_files() -> bool {
+    std::env::var("SPARK_GENERATE_GOLDEN_FILES").map_or(false, |v| v == "1")
+}
+
+fn golden_plan_files(dir: &Path) -> Vec<PathBuf> {
+    let mut files = fs::read_dir(dir)
+        .unwrap_or_else(|e| panic!("Cannot read golden plans in {}: {}", dir.display(), e))
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().map_or(false, |ext| ext == "pb"))
+        .collect::<Vec<_>>();
+    files.sort();
+    files
+}
+
+/// Deserializes and plans the given serialized plan, and returns the native operator tree.
+fn native_plan_tree(bytes: &[u8]) -> Result<String, String> {
+    let op = deserialize_op(bytes).map_err(|e| format!("deserialization failed: {}", e))?;
+    let (_, plan) = PhysicalPlanner::default()
+        .create_plan(&op, &mut vec![])
+        .map_err(|e| format!("planning failed: {}", e))?;
+    Ok(displayable(plan.as_ref()).indent(true).to_string())
+}
+
+#[test]
+fn test_golden_plans() {
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join(GOLDEN_PLAN_DIR);
+    let plan_files = golden_plan_files(&dir);
+    assert!(
+        !plan_files.is_empty(),
+        "No golden plans found in {}",
+        dir.display()
+    );
+
+    let mut mismatches = vec![];
+    for plan_path in plan_files {
+        let name = plan_path.file_stem().unwrap().to_string_lossy().to_string();
+        let tree_path = plan_path.with_extension("txt");
+
+        let actual = match native_plan_tree(&fs::read(&plan_path).unwrap()) {
+            Ok(actual) => actual,
+            Err(e) => {
+                mismatches.push(format!("{}: {}", name, e));
+                continue;
+            }
+        };
+
+        if regenerate_golden_files() {
+            fs::write(&tree_path, &actual).unwrap();
+            continue;
+        }
+
+        match fs::read_to_string(&tree_path) {
+            Ok(expected) if expected == actual => {}
+            Ok(expected) => mismatches.push(format!(
+                "{}: expected native plan\n{}\nbut got\n{}",
+                name, expected, actual
+            )),
+            Err(_) => mismatches.push(format!(
+                "{}: missing {}, run with SPARK_GENERATE_GOLDEN_FILES=1 to create it",
+                name,
+                tree_path.display()
+            )),
+        }
+    }
+
+    assert!(
+        mismatches.is_empty(),
+        "Found {} golden plan mismatches:\n{}",
+        mismatches.len(),
+        mismatches.join("\n")
+    );
+}
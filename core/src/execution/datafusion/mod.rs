@@ -17,9 +17,15 @@
 
 //! Native execution through DataFusion
 
-mod expressions;
-mod operators;
+mod decimal_precision;
+pub mod expressions; // for benchmarking
+#[cfg(test)]
+mod fuzz;
+#[cfg(test)]
+mod golden_plans;
+pub(crate) mod operators;
 pub mod planner;
-pub(crate) mod shuffle_writer;
-mod spark_hash;
+pub mod shuffle_writer; // for benchmarking
+mod simplify;
+pub mod spark_hash; // for benchmarking
 mod util;
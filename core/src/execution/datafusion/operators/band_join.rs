@@ -0,0 +1,804 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use std::{
+    any::Any,
+    cmp::{Ordering, Reverse},
+    collections::BinaryHeap,
+    sync::Arc,
+};
+
+use arrow::{
+    compute::{concat_batches, filter, take, SortOptions},
+    row::{OwnedRow, RowConverter, Rows, SortField},
+};
+use arrow_array::{
+    cast::AsArray, new_null_array, types::UInt32Type, Array, ArrayRef, RecordBatch,
+    RecordBatchOptions, UInt32Array,
+};
+use arrow_schema::SchemaRef;
+use datafusion::{
+    common::{JoinSide, JoinType},
+    execution::TaskContext,
+    physical_plan::{
+        joins::utils::{build_join_schema, JoinFilter},
+        metrics::{BaselineMetrics, ExecutionPlanMetricsSet, MetricsSet},
+        stream::RecordBatchStreamAdapter,
+        DisplayAs, DisplayFormatType, ExecutionMode, ExecutionPlan, Partitioning, PlanProperties,
+        SendableRecordBatchStream,
+    },
+};
+use datafusion_common::{cast::as_boolean_array, DataFusionError, Result as DataFusionResult};
+use datafusion_physical_expr::{EquivalenceProperties, PhysicalExpr};
+use futures::{stream, StreamExt, TryStreamExt};
+
+/// The band condition of a [`SortMergeBandJoinExec`], i.e., `lower <= value <= upper`, where the
+/// value is evaluated on the left side and the bounds on the right side. Either bound may be
+/// missing, and whether they are inclusive is left to the join filter.
+#[derive(Debug, Clone)]
+pub struct BandCondition {
+    pub value: Arc<dyn PhysicalExpr>,
+    pub lower: Option<Arc<dyn PhysicalExpr>>,
+    pub upper: Option<Arc<dyn PhysicalExpr>>,
+}
+
+/// The sort merge join of Spark with a band condition in its join condition, e.g.,
+/// `a.ts BETWEEN b.start AND b.end`. Both sides are sorted by the join keys, and the rows with
+/// the same keys are joined by a sliding window over the bounds: the left rows are probed by
+/// value, against the right rows whose lower bound is not greater than the value and whose upper
+/// bound is not less than it. The join filter, which includes the band condition, is evaluated
+/// on the probed pairs only.
+///
+/// The inner, left outer, left semi and left anti joins are supported.
+#[derive(Debug)]
+pub struct SortMergeBandJoinExec {
+    left: Arc<dyn ExecutionPlan>,
+    right: Arc<dyn ExecutionPlan>,
+    on: Vec<(Arc<dyn PhysicalExpr>, Arc<dyn PhysicalExpr>)>,
+    band: BandCondition,
+    filter: JoinFilter,
+    join_type: JoinType,
+    /// The sort options of the join keys, in which both sides are sorted
+    sort_options: Vec<SortOptions>,
+    schema: SchemaRef,
+    metrics: ExecutionPlanMetricsSet,
+    cache: PlanProperties,
+}
+
+impl SortMergeBandJoinExec {
+    pub fn try_new(
+        left: Arc<dyn ExecutionPlan>,
+        right: Arc<dyn ExecutionPlan>,
+        on: Vec<(Arc<dyn PhysicalExpr>, Arc<dyn PhysicalExpr>)>,
+        band: BandCondition,
+        filter: JoinFilter,
+        join_type: JoinType,
+        sort_options: Vec<SortOptions>,
+    ) -> DataFusionResult<Self> {
+        if !matches!(
+            join_type,
+            JoinType::Inner | JoinType::Left | JoinType::LeftSemi | JoinType::LeftAnti
+        ) {
+            return Err(DataFusionError::NotImplemented(format!(
+                "Sort merge band join doesn't support join type {}",
+                join_type
+            )));
+        }
+        if on.is_empty() || on.len() != sort_options.len() {
+            return Err(DataFusionError::Internal(format!(
+                "Sort merge band join requires a sort option for each of its join keys, but got \
+                 {} join keys and {} sort options",
+                on.len(),
+                sort_options.len()
+            )));
+        }
+
+        let schema = Arc::new(build_join_schema(&left.schema(), &right.schema(), &join_type).0);
+        let cache = PlanProperties::new(
+            EquivalenceProperties::new(schema.clone()),
+            Partitioning::UnknownPartitioning(1),
+            ExecutionMode::Bounded,
+        );
+        Ok(Self {
+            left,
+            right,
+            on,
+            band,
+            filter,
+            join_type,
+            sort_options,
+            schema,
+            metrics: ExecutionPlanMetricsSet::default(),
+            cache,
+        })
+    }
+}
+
+impl DisplayAs for SortMergeBandJoinExec {
+    fn fmt_as(&self, t: DisplayFormatType, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match t {
+            DisplayFormatType::Default | DisplayFormatType::Verbose => {
+                let on = self
+                    .on
+                    .iter()
+                    .map(|(l, r)| format!("({}, {})", l, r))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                let bound = |bound: &Option<Arc<dyn PhysicalExpr>>| {
+                    bound.as_ref().map_or("-".to_string(), |b| b.to_string())
+                };
+                write!(
+                    f,
+                    "SortMergeBandJoinExec: join_type={:?}, on=[{}], band={} in [{}, {}]",
+                    self.join_type,
+                    on,
+                    self.band.value,
+                    bound(&self.band.lower),
+                    bound(&self.band.upper)
+                )
+            }
+        }
+    }
+}
+
+impl ExecutionPlan for SortMergeBandJoinExec {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+
+    fn children(&self) -> Vec<Arc<dyn ExecutionPlan>> {
+        vec![self.left.clone(), self.right.clone()]
+    }
+
+    fn with_new_children(
+        self: Arc<Self>,
+        children: Vec<Arc<dyn ExecutionPlan>>,
+    ) -> DataFusionResult<Arc<dyn ExecutionPlan>> {
+        Ok(Arc::new(SortMergeBandJoinExec::try_new(
+            children[0].clone(),
+            children[1].clone(),
+            self.on.clone(),
+            self.band.clone(),
+            self.filter.clone(),
+            self.join_type,
+            self.sort_options.clone(),
+        )?))
+    }
+
+    fn execute(
+        &self,
+        partition: usize,
+        context: Arc<TaskContext>,
+    ) -> DataFusionResult<SendableRecordBatchStream> {
+        let key_fields = self
+            .on
+            .iter()
+            .zip(&self.sort_options)
+            .map(|((key, _), options)| {
+                Ok(SortField::new_with_options(
+                    key.data_type(&self.left.schema())?,
+                    *options,
+                ))
+            })
+            .collect::<DataFusionResult<Vec<_>>>()?;
+        let left = SortedSide {
+            input: self.left.execute(partition, context.clone())?,
+            keys: self.on.iter().map(|(key, _)| key.clone()).collect(),
+            converter: RowConverter::new(key_fields.clone())?,
+            batch: None,
+            done: false,
+        };
+        let right = SortedSide {
+            input: self.right.execute(partition, context.clone())?,
+            keys: self.on.iter().map(|(_, key)| key.clone()).collect(),
+            converter: RowConverter::new(key_fields)?,
+            batch: None,
+            done: false,
+        };
+        let state = BandJoinState {
+            left,
+            right,
+            left_group: None,
+            right_group: None,
+            joiner: BandJoiner {
+                schema: self.schema.clone(),
+                band: self.band.clone(),
+                filter: self.filter.clone(),
+                join_type: self.join_type,
+            },
+            output: vec![],
+            output_rows: 0,
+            batch_size: context.session_config().batch_size(),
+            done: false,
+        };
+
+        let baseline_metrics = BaselineMetrics::new(&self.metrics, partition);
+        let output = stream::try_unfold(state, |mut state| async move {
+            let batch = state.next_batch().await?;
+            Ok::<_, DataFusionError>(batch.map(|batch| (batch, state)))
+        })
+        .inspect_ok(move |batch| baseline_metrics.record_output(batch.num_rows()));
+
+        Ok(Box::pin(RecordBatchStreamAdapter::new(
+            self.schema(),
+            output,
+        )))
+    }
+
+    fn metrics(&self) -> Option<MetricsSet> {
+        Some(self.metrics.clone_inner())
+    }
+
+    fn properties(&self) -> &PlanProperties {
+        &self.cache
+    }
+}
+
+/// A side of the join, which is read by groups of rows with the same join keys.
+struct SortedSide {
+    input: SendableRecordBatchStream,
+    keys: Vec<Arc<dyn PhysicalExpr>>,
+    converter: RowConverter,
+    /// The current batch, its join keys, and the first of its rows not grouped yet
+    batch: Option<(RecordBatch, Vec<ArrayRef>, Rows, usize)>,
+    done: bool,
+}
+
+/// The rows of a side with the same join keys.
+struct Group {
+    key: OwnedRow,
+    /// Whether a join key is null, in which case the rows don't match any row
+    null_key: bool,
+    batch: RecordBatch,
+}
+
+impl SortedSide {
+    /// Reads the next batch if all the rows of the current one are grouped, and returns whether
+    /// there are rows left.
+    async fn fill(&mut self) -> DataFusionResult<bool> {
+        while !self.done {
+            if let Some((batch, _, _, offset)) = &self.batch {
+                if *offset < batch.num_rows() {
+                    return Ok(true);
+                }
+            }
+            match self.input.next().await {
+                Some(batch) => {
+                    let batch = batch?;
+                    let keys = self
+                        .keys
+                        .iter()
+                        .map(|key| key.evaluate(&batch)?.into_array(batch.num_rows()))
+                        .collect::<DataFusionResult<Vec<_>>>()?;
+                    let rows = self.converter.convert_columns(&keys)?;
+                    self.batch = Some((batch, keys, rows, 0));
+                }
+                None => {
+                    self.batch = None;
+                    self.done = true;
+                }
+            }
+        }
+        Ok(false)
+    }
+
+    async fn next_group(&mut self) -> DataFusionResult<Option<Group>> {
+        if !self.fill().await? {
+            return Ok(None);
+        }
+        let (batch, keys, rows, offset) = self.batch.as_ref().unwrap();
+        let key = rows.row(*offset).owned();
+        let null_key = keys.iter().any(|key| key.is_null(*offset));
+        let schema = batch.schema();
+
+        // The rows of the group may span several batches
+        let mut slices = vec![];
+        loop {
+            let (batch, _, rows, offset) = self.batch.as_mut().unwrap();
+            let start = *offset;
+            let mut end = start;
+            while end < batch.num_rows() && rows.row(end) == key.row() {
+                end += 1;
+            }
+            if end > start {
+                slices.push(batch.slice(start, end - start));
+            }
+            *offset = end;
+            let exhausted = end == batch.num_rows();
+            if !exhausted || !self.fill().await? {
+                break;
+            }
+        }
+
+        let batch = concat_batches(&schema, &slices)?;
+        Ok(Some(Group {
+            key,
+            null_key,
+            batch,
+        }))
+    }
+}
+
+struct BandJoinState {
+    left: SortedSide,
+    right: SortedSide,
+    /// The next groups of the sides, which are not joined yet
+    left_group: Option<Group>,
+    right_group: Option<Group>,
+    joiner: BandJoiner,
+    /// The joined batches not output yet
+    output: Vec<RecordBatch>,
+    output_rows: usize,
+    batch_size: usize,
+    done: bool,
+}
+
+impl BandJoinState {
+    /// Merges the groups of both sides until a batch of joined rows is ready.
+    async fn next_batch(&mut self) -> DataFusionResult<Option<RecordBatch>> {
+        while !self.done && self.output_rows < self.batch_size {
+            if self.left_group.is_none() {
+                self.left_group = self.left.next_group().await?;
+                if self.left_group.is_none() {
+                    self.done = true;
+                    break;
+                }
+            }
+            if self.right_group.is_none() {
+                self.right_group = self.right.next_group().await?;
+            }
+
+            let left = self.left_group.as_ref().unwrap();
+            let ordering = match &self.right_group {
+                Some(right) if !left.null_key => left.key.cmp(&right.key),
+                _ => Ordering::Less,
+            };
+            let joined = match ordering {
+                Ordering::Less => self.joiner.join(&left.batch, None)?,
+                // The right group is kept for the next left group, which has greater keys
+                Ordering::Greater => {
+                    self.right_group = None;
+                    continue;
+                }
+                Ordering::Equal => self
+                    .joiner
+                    .join(&left.batch, self.right_group.as_ref().map(|g| &g.batch))?,
+            };
+            self.left_group = None;
+            if let Some(batch) = joined {
+                self.output_rows += batch.num_rows();
+                self.output.push(batch);
+            }
+        }
+
+        if self.output.is_empty() {
+            return Ok(None);
+        }
+        let batch = concat_batches(&self.joiner.schema, &self.output)?;
+        self.output.clear();
+        self.output_rows = 0;
+        Ok(Some(batch))
+    }
+}
+
+/// Joins the groups of rows with the same join keys.
+struct BandJoiner {
+    schema: SchemaRef,
+    band: BandCondition,
+    filter: JoinFilter,
+    join_type: JoinType,
+}
+
+impl BandJoiner {
+    /// Joins the rows of a left group with the right group of the same keys, if any.
+    fn join(
+        &self,
+        left: &RecordBatch,
+        right: Option<&RecordBatch>,
+    ) -> DataFusionResult<Option<RecordBatch>> {
+        let (left_indices, right_indices) = match right {
+            Some(right) => {
+                let (left_indices, right_indices) = self.probe(left, right)?;
+                self.apply_filter(left, right, left_indices, right_indices)?
+            }
+            None => (
+                UInt32Array::from(Vec::<u32>::new()),
+                UInt32Array::from(Vec::<u32>::new()),
+            ),
+        };
+
+        let mut matched = vec![false; left.num_rows()];
+        left_indices
+            .values()
+            .iter()
+            .for_each(|i| matched[*i as usize] = true);
+        let rows_where = |value: bool| {
+            (0..left.num_rows() as u32)
+                .filter(|i| matched[*i as usize] == value)
+                .collect::<UInt32Array>()
+        };
+
+        let mut batches = vec![];
+        match self.join_type {
+            JoinType::LeftSemi => batches.push(take_rows(&self.schema, left, &rows_where(true))?),
+            JoinType::LeftAnti => batches.push(take_rows(&self.schema, left, &rows_where(false))?),
+            _ => {
+                if let Some(right) = right.filter(|_| !left_indices.is_empty()) {
+                    let columns = take_columns(left, &left_indices)?
+                        .into_iter()
+                        .chain(take_columns(right, &right_indices)?)
+                        .collect();
+                    batches.push(RecordBatch::try_new(self.schema.clone(), columns)?);
+                }
+                if self.join_type == JoinType::Left {
+                    // The unmatched left rows are joined with a row of nulls
+                    let unmatched = rows_where(false);
+                    let nulls = self.schema.fields()[left.num_columns()..]
+                        .iter()
+                        .map(|field| new_null_array(field.data_type(), unmatched.len()));
+                    let columns = take_columns(left, &unmatched)?
+                        .into_iter()
+                        .chain(nulls)
+                        .collect();
+                    batches.push(RecordBatch::try_new(self.schema.clone(), columns)?);
+                }
+            }
+        }
+
+        batches.retain(|batch| batch.num_rows() > 0);
+        match batches.len() {
+            0 => Ok(None),
+            1 => Ok(batches.pop()),
+            _ => Ok(Some(concat_batches(&self.schema, &batches)?)),
+        }
+    }
+
+    /// Returns the pairs of left and right rows within the band, by sliding a window over the
+    /// right rows sorted by lower bound while the left rows are probed by value. The rows of the
+    /// window whose upper bound is less than the value are dropped from a min-heap.
+    fn probe(
+        &self,
+        left: &RecordBatch,
+        right: &RecordBatch,
+    ) -> DataFusionResult<(UInt32Array, UInt32Array)> {
+        let values = self
+            .band
+            .value
+            .evaluate(left)?
+            .into_array(left.num_rows())?;
+        let bound = |bound: &Option<Arc<dyn PhysicalExpr>>| {
+            bound
+                .as_ref()
+                .map(|b| b.evaluate(right)?.into_array(right.num_rows()))
+                .transpose()
+        };
+        let lower = bound(&self.band.lower)?;
+        let upper = bound(&self.band.upper)?;
+
+        let converter = RowConverter::new(vec![SortField::new(values.data_type().clone())])?;
+        let value_rows = converter.convert_columns(&[values.clone()])?;
+        let lower_rows = lower
+            .as_ref()
+            .map(|lower| converter.convert_columns(&[lower.clone()]))
+            .transpose()?;
+        let upper_rows = upper
+            .as_ref()
+            .map(|upper| converter.convert_columns(&[upper.clone()]))
+            .transpose()?;
+
+        // The rows with a null value or bound are not within the band
+        let mut left_order = (0..left.num_rows())
+            .filter(|i| values.is_valid(*i))
+            .collect::<Vec<_>>();
+        left_order.sort_unstable_by(|a, b| value_rows.row(*a).cmp(&value_rows.row(*b)));
+        let mut right_order = (0..right.num_rows())
+            .filter(|j| lower.as_ref().map_or(true, |lower| lower.is_valid(*j)))
+            .filter(|j| upper.as_ref().map_or(true, |upper| upper.is_valid(*j)))
+            .collect::<Vec<_>>();
+        if let Some(lower_rows) = &lower_rows {
+            right_order.sort_unstable_by(|a, b| lower_rows.row(*a).cmp(&lower_rows.row(*b)));
+        }
+
+        let mut left_indices = vec![];
+        let mut right_indices = vec![];
+        // The right rows whose lower bound is not greater than the current value, by upper bound
+        let mut window = BinaryHeap::new();
+        let mut next = 0;
+        for i in left_order {
+            let value = value_rows.row(i);
+            while next < right_order.len()
+                && lower_rows
+                    .as_ref()
+                    .map_or(true, |lower| lower.row(right_order[next]) <= value)
+            {
+                let j = right_order[next];
+                window.push(Reverse((upper_rows.as_ref().map(|upper| upper.row(j)), j)));
+                next += 1;
+            }
+            while let Some(Reverse((Some(upper), _))) = window.peek() {
+                if *upper >= value {
+                    break;
+                }
+                window.pop();
+            }
+            for Reverse((_, j)) in window.iter() {
+                left_indices.push(i as u32);
+                right_indices.push(*j as u32);
+            }
+        }
+        Ok((
+            UInt32Array::from(left_indices),
+            UInt32Array::from(right_indices),
+        ))
+    }
+
+    /// Keeps the pairs of rows for which the join filter is true.
+    fn apply_filter(
+        &self,
+        left: &RecordBatch,
+        right: &RecordBatch,
+        left_indices: UInt32Array,
+        right_indices: UInt32Array,
+    ) -> DataFusionResult<(UInt32Array, UInt32Array)> {
+        if left_indices.is_empty() {
+            return Ok((left_indices, right_indices));
+        }
+        let columns = self
+            .filter
+            .column_indices()
+            .iter()
+            .map(|column| match column.side {
+                JoinSide::Left => take(left.column(column.index), &left_indices, None),
+                JoinSide::Right => take(right.column(column.index), &right_indices, None),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let batch = RecordBatch::try_new_with_options(
+            Arc::new(self.filter.schema().clone()),
+            columns,
+            &RecordBatchOptions::new().with_row_count(Some(left_indices.len())),
+        )?;
+        let mask = self
+            .filter
+            .expression()
+            .evaluate(&batch)?
+            .into_array(batch.num_rows())?;
+        let mask = as_boolean_array(mask.as_ref())?;
+        Ok((
+            filter(&left_indices, mask)?
+                .as_primitive::<UInt32Type>()
+                .clone(),
+            filter(&right_indices, mask)?
+                .as_primitive::<UInt32Type>()
+                .clone(),
+        ))
+    }
+}
+
+fn take_columns(batch: &RecordBatch, indices: &UInt32Array) -> DataFusionResult<Vec<ArrayRef>> {
+    Ok(batch
+        .columns()
+        .iter()
+        .map(|column| take(column, indices, None))
+        .collect::<Result<Vec<_>, _>>()?)
+}
+
+fn take_rows(
+    schema: &SchemaRef,
+    batch: &RecordBatch,
+    indices: &UInt32Array,
+) -> DataFusionResult<RecordBatch> {
+    Ok(RecordBatch::try_new(
+        schema.clone(),
+        take_columns(batch, indices)?,
+    )?)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use arrow::compute::SortOptions;
+    use arrow_array::{cast::AsArray, types::Int32Type, Int32Array, RecordBatch};
+    use arrow_schema::{DataType, Field, Schema};
+    use datafusion::{
+        common::{JoinSide, JoinType},
+        execution::TaskContext,
+        logical_expr::Operator,
+        physical_expr::expressions::{BinaryExpr, Column},
+        physical_plan::{
+            common::collect,
+            joins::utils::{ColumnIndex, JoinFilter},
+            memory::MemoryExec,
+            ExecutionPlan,
+        },
+    };
+    use datafusion_physical_expr::PhysicalExpr;
+    use futures::executor::block_on;
+
+    use super::{BandCondition, SortMergeBandJoinExec};
+
+    fn memory_exec(
+        names: [&str; 3],
+        batches: Vec<Vec<[Option<i32>; 3]>>,
+    ) -> Arc<dyn ExecutionPlan> {
+        let schema = Arc::new(Schema::new(
+            names
+                .iter()
+                .map(|name| Field::new(*name, DataType::Int32, true))
+                .collect::<Vec<_>>(),
+        ));
+        let batches = batches
+            .into_iter()
+            .map(|rows| {
+                let columns = (0..3)
+                    .map(|c| Arc::new(rows.iter().map(|row| row[c]).collect::<Int32Array>()) as _)
+                    .collect();
+                RecordBatch::try_new(schema.clone(), columns).unwrap()
+            })
+            .collect::<Vec<_>>();
+        Arc::new(MemoryExec::try_new(&[batches], schema, None).unwrap())
+    }
+
+    fn column(name: &str, index: usize) -> Arc<dyn PhysicalExpr> {
+        Arc::new(Column::new(name, index))
+    }
+
+    /// Joins `l.k = r.k AND l.v >= r.lo AND l.v <= r.hi`, returning the (`l.v`, `r.lo`) pairs
+    /// or the `l.v` values of the joined rows.
+    fn band_join(
+        left: Vec<Vec<[Option<i32>; 3]>>,
+        right: Vec<Vec<[Option<i32>; 3]>>,
+        join_type: JoinType,
+    ) -> Vec<(Option<i32>, Option<i32>)> {
+        // The filter is bound to (l.v, r.lo, r.hi)
+        let filter = JoinFilter::new(
+            Arc::new(BinaryExpr::new(
+                Arc::new(BinaryExpr::new(
+                    column("v", 0),
+                    Operator::GtEq,
+                    column("lo", 1),
+                )),
+                Operator::And,
+                Arc::new(BinaryExpr::new(
+                    column("v", 0),
+                    Operator::LtEq,
+                    column("hi", 2),
+                )),
+            )),
+            vec![
+                ColumnIndex {
+                    index: 1,
+                    side: JoinSide::Left,
+                },
+                ColumnIndex {
+                    index: 1,
+                    side: JoinSide::Right,
+                },
+                ColumnIndex {
+                    index: 2,
+                    side: JoinSide::Right,
+                },
+            ],
+            Schema::new(vec![
+                Field::new("v", DataType::Int32, true),
+                Field::new("lo", DataType::Int32, true),
+                Field::new("hi", DataType::Int32, true),
+            ]),
+        );
+        let join = SortMergeBandJoinExec::try_new(
+            memory_exec(["k", "v", "x"], left),
+            memory_exec(["k", "lo", "hi"], right),
+            vec![(column("k", 0), column("k", 0))],
+            BandCondition {
+                value: column("v", 1),
+                lower: Some(column("lo", 1)),
+                upper: Some(column("hi", 2)),
+            },
+            filter,
+            join_type,
+            vec![SortOptions::default()],
+        )
+        .unwrap();
+        let output = block_on(collect(
+            join.execute(0, Arc::new(TaskContext::default())).unwrap(),
+        ))
+        .unwrap();
+
+        let mut rows = output
+            .iter()
+            .flat_map(|batch| {
+                let values = batch.column(1).as_primitive::<Int32Type>().clone();
+                let bounds = (batch.num_columns() > 3)
+                    .then(|| batch.column(4).as_primitive::<Int32Type>().clone());
+                (0..batch.num_rows())
+                    .map(|i| {
+                        let bound = bounds
+                            .as_ref()
+                            .and_then(|b| b.is_valid(i).then(|| b.value(i)));
+                        (values.is_valid(i).then(|| values.value(i)), bound)
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        rows.sort();
+        rows
+    }
+
+    fn row(k: Option<i32>, a: i32, b: i32) -> [Option<i32>; 3] {
+        [k, Some(a), Some(b)]
+    }
+
+    #[test]
+    fn test_band_join() {
+        // The groups of the keys span several batches
+        let left = vec![
+            vec![row(None, 5, 0), row(Some(1), 1, 0), row(Some(1), 5, 0)],
+            vec![row(Some(1), 12, 0), row(Some(2), 3, 0), row(Some(4), 7, 0)],
+        ];
+        let right = vec![
+            vec![row(None, 0, 10), row(Some(1), 0, 5)],
+            vec![
+                row(Some(1), 4, 20),
+                row(Some(1), 10, 12),
+                row(Some(3), 0, 10),
+            ],
+            vec![row(Some(4), 7, 7), [Some(4), None, Some(10)]],
+        ];
+
+        assert_eq!(
+            band_join(left.clone(), right.clone(), JoinType::Inner),
+            vec![
+                (Some(1), Some(0)),
+                (Some(5), Some(0)),
+                (Some(5), Some(4)),
+                (Some(7), Some(7)),
+                (Some(12), Some(4)),
+                (Some(12), Some(10)),
+            ]
+        );
+        assert_eq!(
+            band_join(left.clone(), right.clone(), JoinType::Left),
+            vec![
+                (Some(1), Some(0)),
+                (Some(3), None),
+                (Some(5), None),
+                (Some(5), Some(0)),
+                (Some(5), Some(4)),
+                (Some(7), Some(7)),
+                (Some(12), Some(4)),
+                (Some(12), Some(10)),
+            ]
+        );
+        assert_eq!(
+            band_join(left.clone(), right.clone(), JoinType::LeftSemi),
+            vec![
+                (Some(1), None),
+                (Some(5), None),
+                (Some(7), None),
+                (Some(12), None)
+            ]
+        );
+        assert_eq!(
+            band_join(left, right, JoinType::LeftAnti),
+            vec![(Some(3), None), (Some(5), None)]
+        );
+    }
+}
@@ -0,0 +1,296 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use std::{any::Any, collections::HashSet, sync::Arc};
+
+use arrow::{
+    array::{Array, ArrayRef, AsArray, GenericByteArray},
+    compute::cast,
+    datatypes::{BinaryType, ByteArrayType, LargeBinaryType, LargeUtf8Type, Utf8Type},
+};
+use arrow_array::{RecordBatch, RecordBatchOptions};
+use arrow_schema::{DataType, Field, Schema, SchemaRef};
+use datafusion::{
+    execution::TaskContext,
+    physical_plan::{
+        stream::RecordBatchStreamAdapter, DisplayAs, DisplayFormatType, ExecutionMode,
+        ExecutionPlan, Partitioning, PlanProperties, SendableRecordBatchStream, Statistics,
+    },
+};
+use datafusion_common::{arrow_datafusion_err, DataFusionError, Result as DataFusionResult};
+use datafusion_physical_expr::EquivalenceProperties;
+use futures::StreamExt;
+use itertools::Itertools;
+
+/// When to encode string columns as dictionaries.
+#[derive(Debug, Clone, Copy)]
+pub struct DictionaryEncoding {
+    /// Number of rows sampled to estimate the cardinality of a column.
+    pub sample_rows: usize,
+    /// The maximum ratio of distinct values to sampled rows to encode a column.
+    pub max_ratio: f64,
+}
+
+impl DictionaryEncoding {
+    /// Returns the indices of the plain string or binary columns in `columns` whose first
+    /// `sample_rows` values have low cardinality, except the `excluded` ones.
+    pub fn low_cardinality_columns(
+        &self,
+        columns: &[ArrayRef],
+        excluded: &[usize],
+    ) -> DataFusionResult<Vec<usize>> {
+        let mut indices = vec![];
+        for (idx, column) in columns.iter().enumerate() {
+            if excluded.contains(&idx) || !is_encodable(column.data_type()) {
+                continue;
+            }
+            let num_rows = column.len().min(self.sample_rows);
+            if num_rows == 0 {
+                continue;
+            }
+            let distinct = distinct_count(&column.slice(0, num_rows))?;
+            if distinct as f64 <= self.max_ratio * num_rows as f64 {
+                indices.push(idx);
+            }
+        }
+        Ok(indices)
+    }
+}
+
+/// Whether a column of the given type can be encoded, i.e., it is a plain string or binary
+/// column, or a dictionary of them which is unpacked by its consumer.
+fn is_encodable(data_type: &DataType) -> bool {
+    match data_type {
+        DataType::Utf8 | DataType::LargeUtf8 | DataType::Binary | DataType::LargeBinary => true,
+        DataType::Dictionary(_, value_type) => is_encodable(value_type),
+        _ => false,
+    }
+}
+
+/// Returns the number of distinct non-null values of the given string or binary array.
+fn distinct_count(array: &ArrayRef) -> DataFusionResult<usize> {
+    let count = match array.data_type() {
+        DataType::Utf8 => distinct_bytes(array.as_bytes::<Utf8Type>()),
+        DataType::LargeUtf8 => distinct_bytes(array.as_bytes::<LargeUtf8Type>()),
+        DataType::Binary => distinct_bytes(array.as_bytes::<BinaryType>()),
+        DataType::LargeBinary => distinct_bytes(array.as_bytes::<LargeBinaryType>()),
+        DataType::Dictionary(_, value_type) => distinct_count(&cast(array, value_type)?)?,
+        dt => {
+            return Err(DataFusionError::Internal(format!(
+                "Unsupported data type to count distinct values: {dt}"
+            )))
+        }
+    };
+    Ok(count)
+}
+
+fn distinct_bytes<T: ByteArrayType>(array: &GenericByteArray<T>) -> usize {
+    array
+        .iter()
+        .flatten()
+        .map(<T::Native as AsRef<[u8]>>::as_ref)
+        .collect::<HashSet<_>>()
+        .len()
+}
+
+/// Encodes the given string or binary columns of the input as dictionary arrays.
+///
+/// A low-cardinality column takes much less memory and shuffle bytes as a dictionary of its
+/// distinct values, at the cost of hashing its values once. The columns to encode are chosen
+/// upfront by [`DictionaryEncoding::low_cardinality_columns`], usually from the first input
+/// batch, so that the schema of this operator is known at planning time.
+#[derive(Debug)]
+pub struct DictionaryEncodeExec {
+    input: Arc<dyn ExecutionPlan>,
+    /// The indices of the encoded columns
+    columns: Vec<usize>,
+    schema: SchemaRef,
+    cache: PlanProperties,
+}
+
+impl DictionaryEncodeExec {
+    pub fn new(input: Arc<dyn ExecutionPlan>, columns: Vec<usize>) -> Self {
+        let input_schema = input.schema();
+        let fields = input_schema
+            .fields()
+            .iter()
+            .enumerate()
+            .map(|(idx, field)| {
+                if !columns.contains(&idx) {
+                    return field.as_ref().clone();
+                }
+                let value_type = match field.data_type() {
+                    DataType::Dictionary(_, value_type) => value_type.as_ref().clone(),
+                    value_type => value_type.clone(),
+                };
+                Field::new(
+                    field.name(),
+                    DataType::Dictionary(Box::new(DataType::Int32), Box::new(value_type)),
+                    field.is_nullable(),
+                )
+            })
+            .collect_vec();
+        let schema = Arc::new(Schema::new_with_metadata(
+            fields,
+            input_schema.metadata().clone(),
+        ));
+        let cache = PlanProperties::new(
+            EquivalenceProperties::new(schema.clone()),
+            Partitioning::UnknownPartitioning(1),
+            ExecutionMode::Bounded,
+        );
+        Self {
+            input,
+            columns,
+            schema,
+            cache,
+        }
+    }
+}
+
+impl DisplayAs for DictionaryEncodeExec {
+    fn fmt_as(&self, t: DisplayFormatType, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match t {
+            DisplayFormatType::Default | DisplayFormatType::Verbose => {
+                write!(f, "DictionaryEncodeExec: columns={:?}", self.columns)
+            }
+        }
+    }
+}
+
+impl ExecutionPlan for DictionaryEncodeExec {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+
+    fn children(&self) -> Vec<Arc<dyn ExecutionPlan>> {
+        vec![self.input.clone()]
+    }
+
+    fn with_new_children(
+        self: Arc<Self>,
+        children: Vec<Arc<dyn ExecutionPlan>>,
+    ) -> DataFusionResult<Arc<dyn ExecutionPlan>> {
+        Ok(Arc::new(DictionaryEncodeExec::new(
+            children[0].clone(),
+            self.columns.clone(),
+        )))
+    }
+
+    fn execute(
+        &self,
+        partition: usize,
+        context: Arc<TaskContext>,
+    ) -> DataFusionResult<SendableRecordBatchStream> {
+        let input = self.input.execute(partition, context)?;
+        let schema = self.schema.clone();
+        let encoded = input.map(move |batch| {
+            let batch = batch?;
+            let columns = batch
+                .columns()
+                .iter()
+                .zip(schema.fields())
+                .map(|(column, field)| {
+                    if column.data_type() == field.data_type() {
+                        Ok(column.clone())
+                    } else {
+                        cast(column, field.data_type())
+                    }
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            let options = RecordBatchOptions::new().with_row_count(Some(batch.num_rows()));
+            RecordBatch::try_new_with_options(schema.clone(), columns, &options)
+                .map_err(|e| arrow_datafusion_err!(e))
+        });
+        Ok(Box::pin(RecordBatchStreamAdapter::new(
+            self.schema.clone(),
+            encoded,
+        )))
+    }
+
+    fn statistics(&self) -> DataFusionResult<Statistics> {
+        self.input.statistics()
+    }
+
+    fn properties(&self) -> &PlanProperties {
+        &self.cache
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow_array::{Int32Array, StringArray};
+    use datafusion::physical_plan::{common::collect, memory::MemoryExec};
+    use futures::executor::block_on;
+
+    use super::*;
+
+    #[test]
+    fn test_encode_low_cardinality_columns() {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("id", DataType::Int32, false),
+            Field::new("country", DataType::Utf8, true),
+            Field::new("name", DataType::Utf8, true),
+            Field::new("key", DataType::Utf8, true),
+        ]));
+        let countries = ["us", "fr", "jp"];
+        let batch = RecordBatch::try_new(
+            schema.clone(),
+            vec![
+                Arc::new(Int32Array::from_iter_values(0..100)),
+                Arc::new(StringArray::from_iter(
+                    (0..100).map(|i| (i % 10 != 0).then_some(countries[i % 3])),
+                )),
+                Arc::new(StringArray::from_iter_values(
+                    (0..100).map(|i| format!("name{i}")),
+                )),
+                Arc::new(StringArray::from_iter_values(
+                    (0..100).map(|i| countries[i % 3]),
+                )),
+            ],
+        )
+        .unwrap();
+
+        // Only the sampled rows count, and the excluded columns are not encoded
+        let encoding = DictionaryEncoding {
+            sample_rows: 50,
+            max_ratio: 0.1,
+        };
+        let columns = encoding
+            .low_cardinality_columns(batch.columns(), &[3])
+            .unwrap();
+        assert_eq!(columns, vec![1]);
+
+        let input =
+            Arc::new(MemoryExec::try_new(&[vec![batch.clone()]], schema.clone(), None).unwrap());
+        let exec = DictionaryEncodeExec::new(input, columns);
+        assert_eq!(
+            exec.schema().field(1).data_type(),
+            &DataType::Dictionary(Box::new(DataType::Int32), Box::new(DataType::Utf8))
+        );
+        let stream = exec.execute(0, Arc::new(TaskContext::default())).unwrap();
+        let batches = block_on(collect(stream)).unwrap();
+        let encoded = batches[0].column(1);
+        assert_eq!(encoded.as_any_dictionary().values().len(), 3);
+        assert_eq!(&cast(encoded, &DataType::Utf8).unwrap(), batch.column(1));
+        assert_eq!(batches[0].column(2), batch.column(2));
+    }
+}
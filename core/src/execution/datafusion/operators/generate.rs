@@ -0,0 +1,500 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use std::{any::Any, sync::Arc};
+
+use arrow::compute::take;
+use arrow_array::{
+    cast::AsArray, Array, ArrayRef, Int32Array, RecordBatch, RecordBatchOptions, UInt32Array,
+};
+use arrow_schema::{DataType, Field, Schema, SchemaRef};
+use datafusion::{
+    execution::TaskContext,
+    physical_plan::{
+        metrics::{BaselineMetrics, ExecutionPlanMetricsSet, MetricsSet},
+        stream::RecordBatchStreamAdapter,
+        DisplayAs, DisplayFormatType, ExecutionMode, ExecutionPlan, Partitioning, PlanProperties,
+        SendableRecordBatchStream,
+    },
+};
+use datafusion_common::{DataFusionError, Result as DataFusionResult};
+use datafusion_physical_expr::{EquivalenceProperties, PhysicalExpr};
+use futures::StreamExt;
+
+/// A Comet native operator that generates a row for each element of an array, or each entry of a
+/// map, of the input row. This behaves as same as Spark `GenerateExec` with `explode`,
+/// `posexplode` and their outer variants.
+///
+/// The output columns are the required input columns, followed by the position of the element if
+/// `position` is true, and the element of the array or the key and value of the map entry.
+#[derive(Debug)]
+pub struct CometGenerateExec {
+    /// The array or map to generate rows from
+    generator: Arc<dyn PhysicalExpr>,
+    /// The indices of the input columns kept in the output
+    required_child_output: Vec<usize>,
+    position: bool,
+    /// Whether to output a row of nulls for null or empty arrays and maps, instead of no rows
+    outer: bool,
+    child: Arc<dyn ExecutionPlan>,
+    schema: SchemaRef,
+    metrics: ExecutionPlanMetricsSet,
+    cache: PlanProperties,
+}
+
+impl CometGenerateExec {
+    pub fn try_new(
+        generator: Arc<dyn PhysicalExpr>,
+        required_child_output: Vec<usize>,
+        position: bool,
+        outer: bool,
+        child: Arc<dyn ExecutionPlan>,
+    ) -> DataFusionResult<Self> {
+        let child_schema = child.schema();
+        let mut types = required_child_output
+            .iter()
+            .map(|&idx| child_schema.field(idx).data_type().clone())
+            .collect::<Vec<_>>();
+        if position {
+            types.push(DataType::Int32);
+        }
+        match generator.data_type(&child_schema)? {
+            DataType::List(field) => types.push(field.data_type().clone()),
+            DataType::Map(field, _) => match field.data_type() {
+                DataType::Struct(fields) if fields.len() == 2 => {
+                    types.extend(fields.iter().map(|f| f.data_type().clone()))
+                }
+                dt => {
+                    return Err(DataFusionError::Internal(format!(
+                        "Invalid map entries type: {}",
+                        dt
+                    )))
+                }
+            },
+            dt => {
+                return Err(DataFusionError::NotImplemented(format!(
+                    "Cannot generate rows from {}",
+                    dt
+                )))
+            }
+        }
+        let schema = Arc::new(Schema::new(
+            types
+                .into_iter()
+                .enumerate()
+                .map(|(idx, dt)| Field::new(format!("col_{}", idx), dt, true))
+                .collect::<Vec<_>>(),
+        ));
+
+        let cache = PlanProperties::new(
+            EquivalenceProperties::new(schema.clone()),
+            Partitioning::UnknownPartitioning(1),
+            ExecutionMode::Bounded,
+        );
+        Ok(Self {
+            generator,
+            required_child_output,
+            position,
+            outer,
+            child,
+            schema,
+            metrics: ExecutionPlanMetricsSet::default(),
+            cache,
+        })
+    }
+}
+
+impl DisplayAs for CometGenerateExec {
+    fn fmt_as(&self, t: DisplayFormatType, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match t {
+            DisplayFormatType::Default | DisplayFormatType::Verbose => {
+                write!(
+                    f,
+                    "CometGenerateExec: generator={}, position={}, outer={}",
+                    self.generator, self.position, self.outer
+                )
+            }
+        }
+    }
+}
+
+impl ExecutionPlan for CometGenerateExec {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+
+    fn children(&self) -> Vec<Arc<dyn ExecutionPlan>> {
+        vec![self.child.clone()]
+    }
+
+    fn with_new_children(
+        self: Arc<Self>,
+        children: Vec<Arc<dyn ExecutionPlan>>,
+    ) -> DataFusionResult<Arc<dyn ExecutionPlan>> {
+        Ok(Arc::new(CometGenerateExec::try_new(
+            self.generator.clone(),
+            self.required_child_output.clone(),
+            self.position,
+            self.outer,
+            children[0].clone(),
+        )?))
+    }
+
+    fn execute(
+        &self,
+        partition: usize,
+        context: Arc<TaskContext>,
+    ) -> DataFusionResult<SendableRecordBatchStream> {
+        let batch_size = context.session_config().batch_size();
+        let input = self.child.execute(partition, context)?;
+        let generator = Generator {
+            expr: self.generator.clone(),
+            required_child_output: self.required_child_output.clone(),
+            position: self.position,
+            outer: self.outer,
+            schema: self.schema.clone(),
+        };
+        let baseline_metrics = BaselineMetrics::new(&self.metrics, partition);
+
+        // An input row can generate many rows, so the output rows of an input batch are taken in
+        // batches of at most the batch size, when they are polled, instead of all at once
+        let output = input.flat_map(move |batch| {
+            let generated = {
+                let _timer = baseline_metrics.elapsed_compute().timer();
+                batch.and_then(|batch| generator.generate(&batch, batch_size))
+            };
+            let batches: Box<dyn Iterator<Item = DataFusionResult<RecordBatch>> + Send> =
+                match generated {
+                    Ok(generated) => {
+                        let baseline_metrics = baseline_metrics.clone();
+                        Box::new(generated.map(move |output| {
+                            let _timer = baseline_metrics.elapsed_compute().timer();
+                            let output = output?;
+                            baseline_metrics.record_output(output.num_rows());
+                            Ok(output)
+                        }))
+                    }
+                    Err(e) => Box::new(std::iter::once(Err(e))),
+                };
+            futures::stream::iter(batches)
+        });
+        Ok(Box::pin(RecordBatchStreamAdapter::new(
+            self.schema.clone(),
+            output,
+        )))
+    }
+
+    fn metrics(&self) -> Option<MetricsSet> {
+        Some(self.metrics.clone_inner())
+    }
+
+    fn properties(&self) -> &PlanProperties {
+        &self.cache
+    }
+}
+
+struct Generator {
+    expr: Arc<dyn PhysicalExpr>,
+    required_child_output: Vec<usize>,
+    position: bool,
+    outer: bool,
+    schema: SchemaRef,
+}
+
+impl Generator {
+    /// Generates the rows of the given batch, which are returned in batches of at most
+    /// `batch_size` rows.
+    fn generate(
+        &self,
+        batch: &RecordBatch,
+        batch_size: usize,
+    ) -> DataFusionResult<GeneratedBatches> {
+        let array = self.expr.evaluate(batch)?.into_array(batch.num_rows())?;
+        let (offsets, elements): (&[i32], Vec<ArrayRef>) = match array.data_type() {
+            DataType::List(_) => {
+                let list = array.as_list::<i32>();
+                (list.value_offsets(), vec![list.values().clone()])
+            }
+            DataType::Map(_, _) => {
+                let map = array.as_map();
+                (
+                    map.value_offsets(),
+                    vec![map.keys().clone(), map.values().clone()],
+                )
+            }
+            dt => {
+                return Err(DataFusionError::Internal(format!(
+                    "Cannot generate rows from {}",
+                    dt
+                )))
+            }
+        };
+
+        // The input row, the element and its position of each output row. Outer rows of null or
+        // empty arrays and maps have null elements and positions.
+        let mut rows = vec![];
+        let mut indices = vec![];
+        let mut positions = vec![];
+        for row in 0..array.len() {
+            let (start, end) = (offsets[row] as u32, offsets[row + 1] as u32);
+            if array.is_valid(row) && start < end {
+                rows.extend(std::iter::repeat(row as u32).take((end - start) as usize));
+                indices.extend((start..end).map(Some));
+                positions.extend((0..(end - start) as i32).map(Some));
+            } else if self.outer {
+                rows.push(row as u32);
+                indices.push(None);
+                positions.push(None);
+            }
+        }
+        Ok(GeneratedBatches {
+            schema: self.schema.clone(),
+            columns: self
+                .required_child_output
+                .iter()
+                .map(|&idx| batch.column(idx).clone())
+                .collect(),
+            elements,
+            rows: UInt32Array::from(rows),
+            indices: UInt32Array::from(indices),
+            positions: self.position.then(|| Int32Array::from(positions)),
+            offset: 0,
+            batch_size,
+        })
+    }
+}
+
+/// The generated rows of an input batch, whose columns are taken batch by batch.
+struct GeneratedBatches {
+    schema: SchemaRef,
+    /// The input columns kept in the output
+    columns: Vec<ArrayRef>,
+    /// The elements of the array, or the keys and values of the map
+    elements: Vec<ArrayRef>,
+    /// The input row of each generated row
+    rows: UInt32Array,
+    /// The element of each generated row
+    indices: UInt32Array,
+    positions: Option<Int32Array>,
+    /// The first generated row not returned yet
+    offset: usize,
+    batch_size: usize,
+}
+
+impl GeneratedBatches {
+    fn take_rows(&self, offset: usize, length: usize) -> DataFusionResult<RecordBatch> {
+        let rows = self.rows.slice(offset, length);
+        let indices = self.indices.slice(offset, length);
+
+        let mut columns = self
+            .columns
+            .iter()
+            .map(|column| take(column, &rows, None))
+            .collect::<Result<Vec<_>, _>>()?;
+        if let Some(positions) = &self.positions {
+            columns.push(Arc::new(positions.slice(offset, length)));
+        }
+        for element in &self.elements {
+            columns.push(take(element, &indices, None)?);
+        }
+
+        let options = RecordBatchOptions::new().with_row_count(Some(length));
+        Ok(RecordBatch::try_new_with_options(
+            self.schema.clone(),
+            columns,
+            &options,
+        )?)
+    }
+}
+
+impl Iterator for GeneratedBatches {
+    type Item = DataFusionResult<RecordBatch>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.offset >= self.rows.len() {
+            return None;
+        }
+        let length = self.batch_size.min(self.rows.len() - self.offset);
+        let batch = self.take_rows(self.offset, length);
+        self.offset += length;
+        Some(batch)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use arrow_array::{
+        builder::{Int32Builder, ListBuilder, MapBuilder, StringBuilder},
+        cast::AsArray,
+        types::Int32Type,
+        ArrayRef, Int32Array, RecordBatch,
+    };
+    use datafusion::{
+        execution::TaskContext,
+        physical_expr::expressions::Column,
+        physical_plan::{common::collect, memory::MemoryExec, ExecutionPlan},
+        prelude::SessionConfig,
+    };
+    use futures::executor::block_on;
+
+    use super::CometGenerateExec;
+
+    fn generate(
+        column: ArrayRef,
+        position: bool,
+        outer: bool,
+    ) -> (Vec<Option<i32>>, Vec<Option<i32>>, RecordBatch) {
+        let ids = Arc::new(Int32Array::from(vec![1, 2, 3, 4]));
+        let batch =
+            RecordBatch::try_from_iter(vec![("id", ids as ArrayRef), ("c", column)]).unwrap();
+        let input =
+            Arc::new(MemoryExec::try_new(&[vec![batch.clone()]], batch.schema(), None).unwrap());
+        let exec = CometGenerateExec::try_new(
+            Arc::new(Column::new("c", 1)),
+            vec![0],
+            position,
+            outer,
+            input,
+        )
+        .unwrap();
+        let output = block_on(collect(
+            exec.execute(0, Arc::new(TaskContext::default())).unwrap(),
+        ))
+        .unwrap();
+        let output = arrow::compute::concat_batches(&exec.schema(), &output).unwrap();
+        let ids = output
+            .column(0)
+            .as_primitive::<Int32Type>()
+            .iter()
+            .collect();
+        let positions = if position {
+            output
+                .column(1)
+                .as_primitive::<Int32Type>()
+                .iter()
+                .collect()
+        } else {
+            vec![]
+        };
+        (ids, positions, output)
+    }
+
+    #[test]
+    fn test_explode_array() {
+        // [10, 20], null, [], [null]
+        let mut builder = ListBuilder::new(Int32Builder::new());
+        builder.append_value([Some(10), Some(20)]);
+        builder.append_null();
+        builder.append(true);
+        builder.append_value([None]);
+        let list: ArrayRef = Arc::new(builder.finish());
+
+        let (ids, positions, output) = generate(list.clone(), true, false);
+        assert_eq!(ids, vec![Some(1), Some(1), Some(4)]);
+        assert_eq!(positions, vec![Some(0), Some(1), Some(0)]);
+        let elements: Vec<_> = output
+            .column(2)
+            .as_primitive::<Int32Type>()
+            .iter()
+            .collect();
+        assert_eq!(elements, vec![Some(10), Some(20), None]);
+
+        // Outer generation outputs a row of nulls for null and empty arrays
+        let (ids, positions, output) = generate(list.slice(1, 3), true, true);
+        assert_eq!(ids, vec![Some(1), Some(2), Some(3)]);
+        assert_eq!(positions, vec![None, None, Some(0)]);
+        assert_eq!(output.column(2).null_count(), 3);
+
+        let (ids, _, output) = generate(list, false, true);
+        assert_eq!(ids, vec![Some(1), Some(1), Some(2), Some(3), Some(4)]);
+        assert_eq!(output.num_columns(), 2);
+    }
+
+    #[test]
+    fn test_explode_map() {
+        // {a: 1}, {}, null, {b: 2, c: null}
+        let mut builder = MapBuilder::new(None, StringBuilder::new(), Int32Builder::new());
+        builder.keys().append_value("a");
+        builder.values().append_value(1);
+        builder.append(true).unwrap();
+        builder.append(true).unwrap();
+        builder.append(false).unwrap();
+        builder.keys().append_value("b");
+        builder.values().append_value(2);
+        builder.keys().append_value("c");
+        builder.values().append_null();
+        builder.append(true).unwrap();
+        let map: ArrayRef = Arc::new(builder.finish());
+
+        let (ids, _, output) = generate(map.clone(), false, false);
+        assert_eq!(ids, vec![Some(1), Some(4), Some(4)]);
+        let keys: Vec<_> = output.column(1).as_string::<i32>().iter().collect();
+        assert_eq!(keys, vec![Some("a"), Some("b"), Some("c")]);
+        let values: Vec<_> = output
+            .column(2)
+            .as_primitive::<Int32Type>()
+            .iter()
+            .collect();
+        assert_eq!(values, vec![Some(1), Some(2), None]);
+
+        let (ids, positions, output) = generate(map, true, true);
+        assert_eq!(ids, vec![Some(1), Some(2), Some(3), Some(4), Some(4)]);
+        assert_eq!(positions, vec![Some(0), None, None, Some(0), Some(1)]);
+        assert_eq!(output.column(2).null_count(), 2);
+    }
+
+    #[test]
+    fn test_generate_in_batches() {
+        // An array of 10 elements in each of 3 rows
+        let mut builder = ListBuilder::new(Int32Builder::new());
+        for i in 0..3 {
+            builder.append_value((0..10).map(|j| Some(i * 10 + j)));
+        }
+        let list: ArrayRef = Arc::new(builder.finish());
+        let batch = RecordBatch::try_from_iter(vec![("c", list)]).unwrap();
+        let input =
+            Arc::new(MemoryExec::try_new(&[vec![batch.clone()]], batch.schema(), None).unwrap());
+        let exec =
+            CometGenerateExec::try_new(Arc::new(Column::new("c", 0)), vec![], true, false, input)
+                .unwrap();
+
+        let task_ctx =
+            TaskContext::default().with_session_config(SessionConfig::new().with_batch_size(8));
+        let output = block_on(collect(exec.execute(0, Arc::new(task_ctx)).unwrap())).unwrap();
+        assert_eq!(
+            output.iter().map(|b| b.num_rows()).collect::<Vec<_>>(),
+            vec![8, 8, 8, 6]
+        );
+        let elements: Vec<_> = output
+            .iter()
+            .flat_map(|b| b.column(1).as_primitive::<Int32Type>().values().to_vec())
+            .collect();
+        assert_eq!(elements, (0..30).collect::<Vec<_>>());
+        let positions: Vec<_> = output
+            .iter()
+            .flat_map(|b| b.column(0).as_primitive::<Int32Type>().values().to_vec())
+            .collect();
+        assert_eq!(positions, (0..30).map(|i| i % 10).collect::<Vec<_>>());
+        assert_eq!(exec.metrics().unwrap().output_rows(), Some(30));
+    }
+}
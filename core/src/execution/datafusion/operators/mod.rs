@@ -15,4 +15,10 @@
 // specific language governing permissions and limitations
 // under the License.
 
+pub mod band_join;
+pub mod dictionary_encode;
 pub mod expand;
+pub mod generate;
+pub mod null_aware_join;
+pub mod partial_agg;
+pub mod shared_join;
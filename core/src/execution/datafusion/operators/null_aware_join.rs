@@ -0,0 +1,284 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use std::{any::Any, sync::Arc};
+
+use arrow_array::{Array, RecordBatch};
+use arrow_schema::SchemaRef;
+use datafusion::{
+    common::JoinType,
+    execution::{
+        memory_pool::{MemoryConsumer, MemoryReservation},
+        TaskContext,
+    },
+    physical_expr::expressions::IsNotNullExpr,
+    physical_plan::{
+        filter::FilterExec,
+        joins::{HashJoinExec, PartitionMode},
+        memory::MemoryExec,
+        metrics::{BaselineMetrics, ExecutionPlanMetricsSet, MetricsSet},
+        stream::RecordBatchStreamAdapter,
+        DisplayAs, DisplayFormatType, EmptyRecordBatchStream, ExecutionMode, ExecutionPlan,
+        Partitioning, PlanProperties, SendableRecordBatchStream,
+    },
+};
+use datafusion_common::Result as DataFusionResult;
+use datafusion_physical_expr::{EquivalenceProperties, PhysicalExpr};
+use futures::{stream, StreamExt, TryStreamExt};
+
+/// The null-aware anti join of Spark, which evaluates `probe_key NOT IN (SELECT build_key ...)`
+/// with a single nullable key. Unlike a regular anti join, `NOT IN` is not true if the key or any
+/// value of the subquery is null:
+///   - if the build side is empty, every probe row is returned, including the ones with a null key,
+///   - if any build key is null, no row is returned,
+///   - otherwise, the probe rows with a non-null key not found in the build side are returned.
+///
+/// The build side is collected first to find out the case, and the last one is a regular hash
+/// anti join of the collected batches.
+#[derive(Debug)]
+pub struct NullAwareAntiJoinExec {
+    /// The streamed side, which is the left side of the Spark join
+    probe: Arc<dyn ExecutionPlan>,
+    /// The broadcast side, which is the right side of the Spark join
+    build: Arc<dyn ExecutionPlan>,
+    probe_key: Arc<dyn PhysicalExpr>,
+    build_key: Arc<dyn PhysicalExpr>,
+    metrics: ExecutionPlanMetricsSet,
+    cache: PlanProperties,
+}
+
+impl NullAwareAntiJoinExec {
+    pub fn new(
+        probe: Arc<dyn ExecutionPlan>,
+        build: Arc<dyn ExecutionPlan>,
+        probe_key: Arc<dyn PhysicalExpr>,
+        build_key: Arc<dyn PhysicalExpr>,
+    ) -> Self {
+        let cache = PlanProperties::new(
+            EquivalenceProperties::new(probe.schema()),
+            Partitioning::UnknownPartitioning(1),
+            ExecutionMode::Bounded,
+        );
+        Self {
+            probe,
+            build,
+            probe_key,
+            build_key,
+            metrics: ExecutionPlanMetricsSet::default(),
+            cache,
+        }
+    }
+}
+
+impl DisplayAs for NullAwareAntiJoinExec {
+    fn fmt_as(&self, t: DisplayFormatType, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match t {
+            DisplayFormatType::Default | DisplayFormatType::Verbose => {
+                write!(
+                    f,
+                    "NullAwareAntiJoinExec: on=({}, {})",
+                    self.probe_key, self.build_key
+                )
+            }
+        }
+    }
+}
+
+impl ExecutionPlan for NullAwareAntiJoinExec {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn schema(&self) -> SchemaRef {
+        self.probe.schema()
+    }
+
+    /// The probe and build sides, in the order of the Spark join.
+    fn children(&self) -> Vec<Arc<dyn ExecutionPlan>> {
+        vec![self.probe.clone(), self.build.clone()]
+    }
+
+    fn with_new_children(
+        self: Arc<Self>,
+        children: Vec<Arc<dyn ExecutionPlan>>,
+    ) -> DataFusionResult<Arc<dyn ExecutionPlan>> {
+        Ok(Arc::new(NullAwareAntiJoinExec::new(
+            children[0].clone(),
+            children[1].clone(),
+            self.probe_key.clone(),
+            self.build_key.clone(),
+        )))
+    }
+
+    fn execute(
+        &self,
+        partition: usize,
+        context: Arc<TaskContext>,
+    ) -> DataFusionResult<SendableRecordBatchStream> {
+        let build = self.build.execute(partition, context.clone())?;
+        let reservation =
+            MemoryConsumer::new("NullAwareAntiJoinExec").register(context.memory_pool());
+        let probe = self.probe.clone();
+        let build_schema = self.build.schema();
+        let probe_key = self.probe_key.clone();
+        let build_key = self.build_key.clone();
+
+        let output = stream::once(async move {
+            match collect_build_side(build, &build_key, reservation).await? {
+                BuildSide::Empty => probe.execute(partition, context),
+                BuildSide::HasNullKey => Ok(Box::pin(EmptyRecordBatchStream::new(probe.schema()))
+                    as SendableRecordBatchStream),
+                BuildSide::Batches(batches) => {
+                    let build = Arc::new(MemoryExec::try_new(&[batches], build_schema, None)?);
+                    let probe = Arc::new(FilterExec::try_new(
+                        Arc::new(IsNotNullExpr::new(probe_key.clone())),
+                        probe,
+                    )?);
+                    // The build side is the left side of `HashJoinExec`
+                    HashJoinExec::try_new(
+                        build,
+                        probe,
+                        vec![(build_key, probe_key)],
+                        None,
+                        &JoinType::RightAnti,
+                        None,
+                        PartitionMode::CollectLeft,
+                        false,
+                    )?
+                    .execute(partition, context)
+                }
+            }
+        })
+        .try_flatten();
+
+        let baseline_metrics = BaselineMetrics::new(&self.metrics, partition);
+        Ok(Box::pin(RecordBatchStreamAdapter::new(
+            self.schema(),
+            output.map(move |batch| {
+                if let Ok(batch) = &batch {
+                    baseline_metrics.record_output(batch.num_rows());
+                }
+                batch
+            }),
+        )))
+    }
+
+    fn metrics(&self) -> Option<MetricsSet> {
+        Some(self.metrics.clone_inner())
+    }
+
+    fn properties(&self) -> &PlanProperties {
+        &self.cache
+    }
+}
+
+enum BuildSide {
+    Empty,
+    HasNullKey,
+    Batches(Vec<RecordBatch>),
+}
+
+/// Collects the build side, stopping as soon as a null key is found. The reservation only
+/// accounts the collected batches until the hash join built from them accounts its own memory.
+async fn collect_build_side(
+    mut build: SendableRecordBatchStream,
+    build_key: &Arc<dyn PhysicalExpr>,
+    mut reservation: MemoryReservation,
+) -> DataFusionResult<BuildSide> {
+    let mut batches = vec![];
+    while let Some(batch) = build.next().await {
+        let batch = batch?;
+        if batch.num_rows() == 0 {
+            continue;
+        }
+        let keys = build_key.evaluate(&batch)?.into_array(batch.num_rows())?;
+        if keys.null_count() > 0 {
+            return Ok(BuildSide::HasNullKey);
+        }
+        reservation.try_grow(batch.get_array_memory_size())?;
+        batches.push(batch);
+    }
+    reservation.free();
+
+    if batches.is_empty() {
+        Ok(BuildSide::Empty)
+    } else {
+        Ok(BuildSide::Batches(batches))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use arrow_array::{Int32Array, RecordBatch};
+    use arrow_schema::{DataType, Field, Schema};
+    use datafusion::{
+        execution::TaskContext,
+        physical_expr::expressions::Column,
+        physical_plan::{common::collect, memory::MemoryExec, ExecutionPlan},
+    };
+    use futures::executor::block_on;
+
+    use super::NullAwareAntiJoinExec;
+
+    fn memory_exec(name: &str, values: Vec<Option<i32>>) -> Arc<dyn ExecutionPlan> {
+        let schema = Arc::new(Schema::new(vec![Field::new(name, DataType::Int32, true)]));
+        let batch =
+            RecordBatch::try_new(schema.clone(), vec![Arc::new(Int32Array::from(values))]).unwrap();
+        Arc::new(MemoryExec::try_new(&[vec![batch]], schema, None).unwrap())
+    }
+
+    fn not_in(probe: Vec<Option<i32>>, build: Vec<Option<i32>>) -> Vec<Option<i32>> {
+        let join = NullAwareAntiJoinExec::new(
+            memory_exec("a", probe),
+            memory_exec("b", build),
+            Arc::new(Column::new("a", 0)),
+            Arc::new(Column::new("b", 0)),
+        );
+        let output = block_on(collect(
+            join.execute(0, Arc::new(TaskContext::default())).unwrap(),
+        ))
+        .unwrap();
+        output
+            .iter()
+            .flat_map(|batch| {
+                let values = batch
+                    .column(0)
+                    .as_any()
+                    .downcast_ref::<Int32Array>()
+                    .unwrap();
+                values.iter().collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_null_aware_anti_join() {
+        let probe = vec![Some(1), Some(2), None, Some(3)];
+
+        // Probe rows with a null key are excluded if the build side isn't empty
+        assert_eq!(
+            not_in(probe.clone(), vec![Some(2), Some(4)]),
+            vec![Some(1), Some(3)]
+        );
+        // No row is returned if the build side has a null key
+        assert!(not_in(probe.clone(), vec![Some(4), None]).is_empty());
+        // Every row is returned if the build side is empty
+        assert_eq!(not_in(probe.clone(), vec![]), probe);
+    }
+}
@@ -0,0 +1,411 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use std::{any::Any, collections::HashSet, sync::Arc};
+
+use arrow::row::{OwnedRow, RowConverter, SortField};
+use arrow_array::RecordBatch;
+use arrow_schema::SchemaRef;
+use datafusion::{
+    execution::TaskContext,
+    physical_plan::{
+        aggregates::AggregateExec,
+        metrics::{BaselineMetrics, ExecutionPlanMetricsSet, MetricBuilder, MetricsSet},
+        stream::RecordBatchStreamAdapter,
+        DisplayAs, DisplayFormatType, ExecutionMode, ExecutionPlan, Partitioning, PlanProperties,
+        SendableRecordBatchStream,
+    },
+};
+use datafusion_common::{DataFusionError, Result as DataFusionResult};
+use datafusion_physical_expr::EquivalenceProperties;
+use futures::{stream, StreamExt, TryStreamExt};
+use parking_lot::Mutex;
+
+/// When to skip partial aggregation for high-cardinality grouping keys.
+#[derive(Debug, Clone, Copy)]
+pub struct PartialAggSkip {
+    /// Number of input rows to probe before deciding whether to skip partial aggregation.
+    pub probe_rows: usize,
+    /// The minimum ratio of distinct grouping keys to probed rows to skip partial aggregation.
+    pub ratio_threshold: f64,
+}
+
+/// A partial hash aggregation which stops aggregating across batches when it is not effective.
+///
+/// The first `probe_rows` input rows are buffered to count their distinct grouping keys. If the
+/// ratio of distinct keys to rows is at least `ratio_threshold`, i.e., the grouping keys have
+/// high cardinality, a full partial aggregation would spend most time and memory building a hash
+/// table which doesn't reduce the data before the shuffle. In that case, each input batch is
+/// aggregated on its own and emitted right away, like Spark skips partial aggregation. Otherwise,
+/// the input is aggregated by the wrapped `AggregateExec` as usual.
+///
+/// The wrapped aggregation must be a partial aggregation with grouping keys, which is fine to
+/// emit multiple rows per group as a final aggregation merges them later.
+#[derive(Debug)]
+pub struct AdaptivePartialAggExec {
+    /// The partial aggregation. Its child is the input of this operator.
+    aggregate: Arc<AggregateExec>,
+    input: Arc<dyn ExecutionPlan>,
+    probe_rows: usize,
+    ratio_threshold: f64,
+    metrics: ExecutionPlanMetricsSet,
+    cache: PlanProperties,
+}
+
+impl AdaptivePartialAggExec {
+    pub fn new(aggregate: Arc<AggregateExec>, probe_rows: usize, ratio_threshold: f64) -> Self {
+        let input = aggregate.input().clone();
+        let cache = PlanProperties::new(
+            EquivalenceProperties::new(aggregate.schema()),
+            Partitioning::UnknownPartitioning(1),
+            ExecutionMode::Bounded,
+        );
+        Self {
+            aggregate,
+            input,
+            probe_rows,
+            ratio_threshold,
+            metrics: ExecutionPlanMetricsSet::new(),
+            cache,
+        }
+    }
+}
+
+impl DisplayAs for AdaptivePartialAggExec {
+    fn fmt_as(&self, t: DisplayFormatType, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match t {
+            DisplayFormatType::Default | DisplayFormatType::Verbose => {
+                write!(
+                    f,
+                    "AdaptivePartialAggExec: probe_rows={}, ratio_threshold={}, ",
+                    self.probe_rows, self.ratio_threshold
+                )?;
+                self.aggregate.fmt_as(t, f)
+            }
+        }
+    }
+}
+
+impl ExecutionPlan for AdaptivePartialAggExec {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn schema(&self) -> SchemaRef {
+        self.aggregate.schema()
+    }
+
+    fn children(&self) -> Vec<Arc<dyn ExecutionPlan>> {
+        vec![self.input.clone()]
+    }
+
+    fn with_new_children(
+        self: Arc<Self>,
+        children: Vec<Arc<dyn ExecutionPlan>>,
+    ) -> DataFusionResult<Arc<dyn ExecutionPlan>> {
+        let aggregate = with_input(&self.aggregate, children[0].clone())?;
+        Ok(Arc::new(AdaptivePartialAggExec::new(
+            aggregate,
+            self.probe_rows,
+            self.ratio_threshold,
+        )))
+    }
+
+    fn execute(
+        &self,
+        partition: usize,
+        context: Arc<TaskContext>,
+    ) -> DataFusionResult<SendableRecordBatchStream> {
+        let mut input = self.input.execute(partition, context.clone())?;
+        let input_schema = input.schema();
+        let baseline_metrics = BaselineMetrics::new(&self.metrics, partition);
+        let skipped = MetricBuilder::new(&self.metrics).counter("partial_agg_skipped", partition);
+
+        let aggregate = self.aggregate.clone();
+        let probe_rows = self.probe_rows;
+        let ratio_threshold = self.ratio_threshold;
+
+        let output = stream::once(async move {
+            // Buffers the probed batches, which are aggregated after the decision
+            let mut probed = vec![];
+            let mut num_probed_rows = 0;
+            while num_probed_rows < probe_rows {
+                match input.next().await {
+                    Some(batch) => {
+                        let batch = batch?;
+                        num_probed_rows += batch.num_rows();
+                        probed.push(batch);
+                    }
+                    None => break,
+                }
+            }
+
+            let skip = num_probed_rows >= probe_rows && {
+                let _timer = baseline_metrics.elapsed_compute().timer();
+                let num_groups = count_groups(&aggregate, &probed)?;
+                num_groups as f64 >= num_probed_rows as f64 * ratio_threshold
+            };
+            let input = stream::iter(probed.into_iter().map(Ok)).chain(input);
+
+            if skip {
+                skipped.add(1);
+                Ok(aggregate_each_batch(
+                    aggregate,
+                    input_schema,
+                    Box::pin(input),
+                    partition,
+                    context,
+                ))
+            } else {
+                let source = Arc::new(OneShotExec::new(
+                    input_schema.clone(),
+                    Box::pin(RecordBatchStreamAdapter::new(input_schema, input)),
+                ));
+                with_input(&aggregate, source)?.execute(partition, context)
+            }
+        })
+        .try_flatten()
+        .inspect_ok(move |batch| baseline_metrics.record_output(batch.num_rows()));
+
+        Ok(Box::pin(RecordBatchStreamAdapter::new(
+            self.schema(),
+            output,
+        )))
+    }
+
+    fn metrics(&self) -> Option<MetricsSet> {
+        Some(self.metrics.clone_inner())
+    }
+
+    fn properties(&self) -> &PlanProperties {
+        &self.cache
+    }
+}
+
+/// Returns a copy of `aggregate` with a new input.
+fn with_input(
+    aggregate: &Arc<AggregateExec>,
+    input: Arc<dyn ExecutionPlan>,
+) -> DataFusionResult<Arc<AggregateExec>> {
+    let plan = aggregate.clone().with_new_children(vec![input])?;
+    plan.as_any()
+        .downcast_ref::<AggregateExec>()
+        .map(|aggregate| Arc::new(aggregate.clone()))
+        .ok_or_else(|| DataFusionError::Internal("Expected AggregateExec".to_string()))
+}
+
+/// Counts the distinct grouping keys of the given batches.
+fn count_groups(aggregate: &AggregateExec, batches: &[RecordBatch]) -> DataFusionResult<usize> {
+    let group_exprs = aggregate.group_expr().expr();
+    let mut converter: Option<RowConverter> = None;
+    let mut groups: HashSet<OwnedRow> = HashSet::new();
+
+    for batch in batches {
+        let keys = group_exprs
+            .iter()
+            .map(|(expr, _)| expr.evaluate(batch)?.into_array(batch.num_rows()))
+            .collect::<DataFusionResult<Vec<_>>>()?;
+        if converter.is_none() {
+            let fields = keys
+                .iter()
+                .map(|key| SortField::new(key.data_type().clone()))
+                .collect();
+            converter = Some(RowConverter::new(fields)?);
+        }
+        let rows = converter.as_ref().unwrap().convert_columns(&keys)?;
+        groups.extend(rows.iter().map(|row| row.owned()));
+    }
+    Ok(groups.len())
+}
+
+/// Aggregates each input batch on its own.
+fn aggregate_each_batch(
+    aggregate: Arc<AggregateExec>,
+    input_schema: SchemaRef,
+    input: stream::BoxStream<'static, DataFusionResult<RecordBatch>>,
+    partition: usize,
+    context: Arc<TaskContext>,
+) -> SendableRecordBatchStream {
+    let output_schema = aggregate.schema();
+    let output = input
+        .and_then(move |batch| {
+            let source = Arc::new(OneShotExec::new(
+                input_schema.clone(),
+                Box::pin(RecordBatchStreamAdapter::new(
+                    input_schema.clone(),
+                    stream::iter(vec![Ok(batch)]),
+                )),
+            ));
+            let result = with_input(&aggregate, source)
+                .and_then(|aggregate| aggregate.execute(partition, context.clone()));
+            futures::future::ready(result)
+        })
+        .try_flatten();
+    Box::pin(RecordBatchStreamAdapter::new(output_schema, output))
+}
+
+/// A source operator returning the given stream. It can only be executed once.
+struct OneShotExec {
+    stream: Mutex<Option<SendableRecordBatchStream>>,
+    cache: PlanProperties,
+}
+
+impl OneShotExec {
+    fn new(schema: SchemaRef, stream: SendableRecordBatchStream) -> Self {
+        let cache = PlanProperties::new(
+            EquivalenceProperties::new(schema),
+            Partitioning::UnknownPartitioning(1),
+            ExecutionMode::Bounded,
+        );
+        Self {
+            stream: Mutex::new(Some(stream)),
+            cache,
+        }
+    }
+}
+
+impl std::fmt::Debug for OneShotExec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OneShotExec").finish()
+    }
+}
+
+impl DisplayAs for OneShotExec {
+    fn fmt_as(&self, _: DisplayFormatType, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "OneShotExec")
+    }
+}
+
+impl ExecutionPlan for OneShotExec {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn schema(&self) -> SchemaRef {
+        self.cache.eq_properties.schema().clone()
+    }
+
+    fn children(&self) -> Vec<Arc<dyn ExecutionPlan>> {
+        vec![]
+    }
+
+    fn with_new_children(
+        self: Arc<Self>,
+        _: Vec<Arc<dyn ExecutionPlan>>,
+    ) -> DataFusionResult<Arc<dyn ExecutionPlan>> {
+        Ok(self)
+    }
+
+    fn execute(
+        &self,
+        _partition: usize,
+        _context: Arc<TaskContext>,
+    ) -> DataFusionResult<SendableRecordBatchStream> {
+        self.stream
+            .lock()
+            .take()
+            .ok_or_else(|| DataFusionError::Internal("OneShotExec is executed twice".to_string()))
+    }
+
+    fn properties(&self) -> &PlanProperties {
+        &self.cache
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use arrow_array::{Int32Array, RecordBatch};
+    use arrow_schema::{DataType, Field, Schema};
+    use datafusion::{
+        execution::TaskContext,
+        physical_expr::expressions::{col, Count},
+        physical_plan::{
+            aggregates::{AggregateExec, AggregateMode, PhysicalGroupBy},
+            common::collect,
+            memory::MemoryExec,
+            ExecutionPlan,
+        },
+    };
+    use futures::executor::block_on;
+
+    use super::AdaptivePartialAggExec;
+
+    /// Runs a partial `COUNT(v) GROUP BY k` over two batches of 4 rows with the given keys.
+    /// Returns the number of output rows and whether partial aggregation is skipped.
+    fn run(keys: [i32; 8]) -> (usize, usize) {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("k", DataType::Int32, false),
+            Field::new("v", DataType::Int32, false),
+        ]));
+        let batches = keys
+            .chunks(4)
+            .map(|keys| {
+                RecordBatch::try_new(
+                    schema.clone(),
+                    vec![
+                        Arc::new(Int32Array::from(keys.to_vec())),
+                        Arc::new(Int32Array::from(vec![1; 4])),
+                    ],
+                )
+                .unwrap()
+            })
+            .collect::<Vec<_>>();
+        let input = Arc::new(MemoryExec::try_new(&[batches], schema.clone(), None).unwrap());
+
+        let group_by = PhysicalGroupBy::new_single(vec![(col("k", &schema).unwrap(), "k".into())]);
+        let count = Arc::new(Count::new(
+            col("v", &schema).unwrap(),
+            "cnt",
+            DataType::Int64,
+        ));
+        let aggregate = AggregateExec::try_new(
+            AggregateMode::Partial,
+            group_by,
+            vec![count],
+            vec![None],
+            input,
+            schema,
+        )
+        .unwrap();
+
+        let exec = AdaptivePartialAggExec::new(Arc::new(aggregate), 4, 0.8);
+        let stream = exec.execute(0, Arc::new(TaskContext::default())).unwrap();
+        let num_rows = block_on(collect(stream))
+            .unwrap()
+            .iter()
+            .map(|batch| batch.num_rows())
+            .sum();
+        let skipped = exec
+            .metrics()
+            .unwrap()
+            .sum_by_name("partial_agg_skipped")
+            .map_or(0, |v| v.as_usize());
+        (num_rows, skipped)
+    }
+
+    #[test]
+    fn test_skip_partial_agg() {
+        // All the probed keys are distinct, so each batch is aggregated on its own. The key 1
+        // appears in both batches and is emitted twice.
+        assert_eq!(run([0, 1, 2, 3, 1, 5, 6, 6]), (7, 1));
+
+        // Low cardinality keys are aggregated over all the input
+        assert_eq!(run([0, 1, 0, 1, 1, 0, 2, 2]), (3, 0));
+    }
+}
@@ -0,0 +1,353 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use std::{
+    any::Any,
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+use arrow_schema::SchemaRef;
+use datafusion::{
+    common::JoinType,
+    execution::TaskContext,
+    physical_plan::{
+        joins::HashJoinExec,
+        metrics::{BaselineMetrics, ExecutionPlanMetricsSet, MetricsSet},
+        stream::RecordBatchStreamAdapter,
+        DisplayAs, DisplayFormatType, ExecutionMode, ExecutionPlan, Partitioning, PlanProperties,
+        SendableRecordBatchStream,
+    },
+};
+use datafusion_common::{DataFusionError, Result as DataFusionResult};
+use datafusion_physical_expr::EquivalenceProperties;
+use futures::StreamExt;
+use parking_lot::Mutex;
+
+/// A broadcast hash join shared by the tasks of an executor probing the same build side.
+///
+/// The wrapped `HashJoinExec` collects its left (build) side only once, when it is executed for
+/// the first time, and every later execution probes the same hash table. So each task executes
+/// it with its own probe stream, registered in the right child as a new partition. The build side
+/// must not depend on any task, e.g., it reads a memory-mapped broadcast relation.
+///
+/// Only join types which don't emit unmatched build rows are supported, as the probe streams of
+/// different tasks don't know each other.
+#[derive(Debug)]
+pub struct SharedHashJoin {
+    join: Arc<HashJoinExec>,
+    probe: Arc<ProbeExec>,
+    /// The context to build the hash table with. The hash table outlives the task which happens
+    /// to build it, so it cannot be accounted in the memory pool of the task.
+    context: Arc<TaskContext>,
+}
+
+impl SharedHashJoin {
+    /// Creates a shared join by `create_join` with a right child which is the probe side of the
+    /// tasks, whose schema is `probe_schema`.
+    pub fn try_new(
+        probe_schema: SchemaRef,
+        context: Arc<TaskContext>,
+        create_join: impl FnOnce(Arc<dyn ExecutionPlan>) -> DataFusionResult<HashJoinExec>,
+    ) -> DataFusionResult<Self> {
+        let probe = Arc::new(ProbeExec::new(probe_schema));
+        let join = create_join(probe.clone())?;
+        if !Self::supports(join.join_type()) {
+            return Err(DataFusionError::NotImplemented(format!(
+                "Shared hash join doesn't support join type {}",
+                join.join_type()
+            )));
+        }
+        Ok(Self {
+            join: Arc::new(join),
+            probe,
+            context,
+        })
+    }
+
+    /// Whether the join type can be shared, i.e., it doesn't emit unmatched build rows.
+    pub fn supports(join_type: &JoinType) -> bool {
+        matches!(join_type, JoinType::Inner | JoinType::Right)
+    }
+}
+
+/// The operator of a task which probes a [`SharedHashJoin`] with its input.
+#[derive(Debug)]
+pub struct SharedHashJoinExec {
+    shared: Arc<SharedHashJoin>,
+    /// The probe side of this task
+    input: Arc<dyn ExecutionPlan>,
+    metrics: ExecutionPlanMetricsSet,
+    cache: PlanProperties,
+}
+
+impl SharedHashJoinExec {
+    pub fn new(shared: Arc<SharedHashJoin>, input: Arc<dyn ExecutionPlan>) -> Self {
+        let cache = PlanProperties::new(
+            EquivalenceProperties::new(shared.join.schema()),
+            Partitioning::UnknownPartitioning(1),
+            ExecutionMode::Bounded,
+        );
+        Self {
+            shared,
+            input,
+            metrics: ExecutionPlanMetricsSet::default(),
+            cache,
+        }
+    }
+}
+
+impl DisplayAs for SharedHashJoinExec {
+    fn fmt_as(&self, t: DisplayFormatType, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match t {
+            DisplayFormatType::Default | DisplayFormatType::Verbose => {
+                write!(
+                    f,
+                    "SharedHashJoinExec: join_type={:?}",
+                    self.shared.join.join_type()
+                )
+            }
+        }
+    }
+}
+
+impl ExecutionPlan for SharedHashJoinExec {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn schema(&self) -> SchemaRef {
+        self.shared.join.schema()
+    }
+
+    /// The shared build side and the probe side of this task, like a hash join.
+    fn children(&self) -> Vec<Arc<dyn ExecutionPlan>> {
+        vec![self.shared.join.left().clone(), self.input.clone()]
+    }
+
+    fn with_new_children(
+        self: Arc<Self>,
+        children: Vec<Arc<dyn ExecutionPlan>>,
+    ) -> DataFusionResult<Arc<dyn ExecutionPlan>> {
+        Ok(Arc::new(SharedHashJoinExec::new(
+            self.shared.clone(),
+            children[1].clone(),
+        )))
+    }
+
+    fn execute(
+        &self,
+        partition: usize,
+        context: Arc<TaskContext>,
+    ) -> DataFusionResult<SendableRecordBatchStream> {
+        let input = self.input.execute(partition, context)?;
+        let probe_partition = self.shared.probe.register(input);
+        let stream = self
+            .shared
+            .join
+            .execute(probe_partition, self.shared.context.clone())?;
+
+        let baseline_metrics = BaselineMetrics::new(&self.metrics, partition);
+        Ok(Box::pin(RecordBatchStreamAdapter::new(
+            self.schema(),
+            stream.map(move |batch| {
+                if let Ok(batch) = &batch {
+                    baseline_metrics.record_output(batch.num_rows());
+                }
+                batch
+            }),
+        )))
+    }
+
+    fn metrics(&self) -> Option<MetricsSet> {
+        Some(self.metrics.clone_inner())
+    }
+
+    fn properties(&self) -> &PlanProperties {
+        &self.cache
+    }
+}
+
+/// The probe side of a [`SharedHashJoin`], whose partitions are the probe streams registered by
+/// the tasks.
+struct ProbeExec {
+    streams: Mutex<HashMap<usize, SendableRecordBatchStream>>,
+    next_partition: AtomicUsize,
+    cache: PlanProperties,
+}
+
+impl ProbeExec {
+    fn new(schema: SchemaRef) -> Self {
+        let cache = PlanProperties::new(
+            EquivalenceProperties::new(schema),
+            Partitioning::UnknownPartitioning(1),
+            ExecutionMode::Bounded,
+        );
+        Self {
+            streams: Mutex::new(HashMap::new()),
+            next_partition: AtomicUsize::new(0),
+            cache,
+        }
+    }
+
+    /// Registers the probe stream of a task, and returns its partition to execute.
+    fn register(&self, stream: SendableRecordBatchStream) -> usize {
+        let partition = self.next_partition.fetch_add(1, Ordering::Relaxed);
+        self.streams.lock().insert(partition, stream);
+        partition
+    }
+}
+
+impl std::fmt::Debug for ProbeExec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ProbeExec").finish()
+    }
+}
+
+impl DisplayAs for ProbeExec {
+    fn fmt_as(&self, _: DisplayFormatType, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "ProbeExec")
+    }
+}
+
+impl ExecutionPlan for ProbeExec {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn schema(&self) -> SchemaRef {
+        self.cache.eq_properties.schema().clone()
+    }
+
+    fn children(&self) -> Vec<Arc<dyn ExecutionPlan>> {
+        vec![]
+    }
+
+    fn with_new_children(
+        self: Arc<Self>,
+        _: Vec<Arc<dyn ExecutionPlan>>,
+    ) -> DataFusionResult<Arc<dyn ExecutionPlan>> {
+        Ok(self)
+    }
+
+    fn execute(
+        &self,
+        partition: usize,
+        _context: Arc<TaskContext>,
+    ) -> DataFusionResult<SendableRecordBatchStream> {
+        self.streams.lock().remove(&partition).ok_or_else(|| {
+            DataFusionError::Internal(format!("No probe stream for partition {}", partition))
+        })
+    }
+
+    fn properties(&self) -> &PlanProperties {
+        &self.cache
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use arrow_array::{Int32Array, RecordBatch};
+    use arrow_schema::{DataType, Field, Schema};
+    use datafusion::{
+        common::JoinType,
+        execution::TaskContext,
+        physical_expr::expressions::Column,
+        physical_plan::{
+            common::collect,
+            joins::{HashJoinExec, PartitionMode},
+            memory::MemoryExec,
+            ExecutionPlan,
+        },
+    };
+    use futures::executor::block_on;
+
+    use super::{SharedHashJoin, SharedHashJoinExec};
+
+    fn batch(name: &str, values: Vec<i32>) -> RecordBatch {
+        let schema = Arc::new(Schema::new(vec![Field::new(name, DataType::Int32, false)]));
+        RecordBatch::try_new(schema, vec![Arc::new(Int32Array::from(values))]).unwrap()
+    }
+
+    #[test]
+    fn test_shared_hash_join() {
+        let build = batch("a", vec![1, 2, 3]);
+        let build_exec =
+            Arc::new(MemoryExec::try_new(&[vec![build.clone()]], build.schema(), None).unwrap());
+        let probe_schema = batch("b", vec![]).schema();
+
+        let shared = Arc::new(
+            SharedHashJoin::try_new(
+                probe_schema.clone(),
+                Arc::new(TaskContext::default()),
+                |probe| {
+                    HashJoinExec::try_new(
+                        build_exec,
+                        probe,
+                        vec![(Arc::new(Column::new("a", 0)), Arc::new(Column::new("b", 0)))],
+                        None,
+                        &JoinType::Inner,
+                        None,
+                        PartitionMode::CollectLeft,
+                        false,
+                    )
+                },
+            )
+            .unwrap(),
+        );
+
+        // Tasks probe the same build side with their own inputs
+        for (probe, expected) in [(vec![1, 1, 4], 2), (vec![2, 3, 3, 5], 3), (vec![], 0)] {
+            let probe = batch("b", probe);
+            let input =
+                Arc::new(MemoryExec::try_new(&[vec![probe]], probe_schema.clone(), None).unwrap());
+            let exec = Arc::new(SharedHashJoinExec::new(shared.clone(), input));
+            let output = block_on(collect(
+                exec.execute(0, Arc::new(TaskContext::default())).unwrap(),
+            ))
+            .unwrap();
+            assert_eq!(output.iter().map(|b| b.num_rows()).sum::<usize>(), expected);
+            assert!(output.iter().all(|b| b.num_columns() == 2));
+        }
+
+        // Joins emitting unmatched build rows cannot be shared
+        let build_exec =
+            Arc::new(MemoryExec::try_new(&[vec![build.clone()]], build.schema(), None).unwrap());
+        assert!(SharedHashJoin::try_new(
+            probe_schema.clone(),
+            Arc::new(TaskContext::default()),
+            |probe| {
+                HashJoinExec::try_new(
+                    build_exec,
+                    probe,
+                    vec![(Arc::new(Column::new("a", 0)), Arc::new(Column::new("b", 0)))],
+                    None,
+                    &JoinType::Left,
+                    None,
+                    PartitionMode::CollectLeft,
+                    false,
+                )
+            },
+        )
+        .is_err());
+    }
+}
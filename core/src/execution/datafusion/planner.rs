@@ -17,13 +17,20 @@
 
 //! Converts Spark physical plan to DataFusion physical plan
 
-use std::{collections::HashMap, str::FromStr, sync::Arc};
+use std::{
+    collections::HashMap,
+    io::Cursor,
+    str::FromStr,
+    sync::{Arc, Mutex},
+};
 
+use arrow::ipc::reader::StreamReader;
+use arrow_array::RecordBatch;
 use arrow_schema::{DataType, Field, Schema, TimeUnit};
 use datafusion::{
     arrow::{compute::SortOptions, datatypes::SchemaRef},
     common::DataFusionError,
-    execution::FunctionRegistry,
+    execution::{FunctionRegistry, TaskContext},
     functions::math,
     logical_expr::{
         BuiltinScalarFunction, Operator as DataFusionOperator, ScalarFunctionDefinition,
@@ -31,10 +38,11 @@ use datafusion::{
     physical_expr::{
         execution_props::ExecutionProps,
         expressions::{
-            in_list, BinaryExpr, BitAnd, BitOr, BitXor, CaseExpr, CastExpr, Column, Count,
-            FirstValue, InListExpr, IsNotNullExpr, IsNullExpr, LastValue,
-            Literal as DataFusionLiteral, Max, Min, NegativeExpr, NotExpr, Sum, UnKnownColumn,
+            in_list, BinaryExpr, BitAnd, BitOr, BitXor, CastExpr, Column, Count, FirstValue,
+            InListExpr, IsNotNullExpr, IsNullExpr, LastValue, Literal as DataFusionLiteral, Max,
+            Min, NegativeExpr, NotExpr, Sum, UnKnownColumn,
         },
+        utils::collect_columns,
         AggregateExpr, PhysicalExpr, PhysicalSortExpr, ScalarFunctionExpr,
     },
     physical_plan::{
@@ -42,6 +50,7 @@ use datafusion::{
         filter::FilterExec,
         joins::{utils::JoinFilter, HashJoinExec, PartitionMode, SortMergeJoinExec},
         limit::LocalLimitExec,
+        memory::MemoryExec,
         projection::ProjectionExec,
         sorts::sort::SortExec,
         ExecutionPlan, Partitioning,
@@ -50,7 +59,7 @@ use datafusion::{
 };
 use datafusion_common::{
     tree_node::{Transformed, TransformedResult, TreeNode, TreeNodeRecursion, TreeNodeRewriter},
-    JoinType as DFJoinType, ScalarValue,
+    JoinSide, JoinType as DFJoinType, ScalarValue,
 };
 use itertools::Itertools;
 use jni::objects::GlobalRef;
@@ -59,36 +68,67 @@ use num::{BigInt, ToPrimitive};
 use crate::{
     errors::ExpressionError,
     execution::{
+        broadcast::MappedBroadcast,
+        config::CompressionCodec,
         datafusion::{
+            decimal_precision::{decimal_result_type, widen_decimal_operands, DecimalOp},
             expressions::{
+                approx_percentile::ApproxPercentile,
                 avg::Avg,
                 avg_decimal::AvgDecimal,
                 bitwise_not::BitwiseNotExpr,
+                bloom_filter_agg::BloomFilterAgg,
                 bloom_filter_might_contain::BloomFilterMightContain,
+                case_when::CaseWhenExpr,
                 cast::{Cast, EvalMode},
                 checkoverflow::CheckOverflow,
+                collect::Collect,
                 covariance::Covariance,
+                div_rem::{DivRemExpr, DivRemOp},
                 if_expr::IfExpr,
+                in_set::InSetExpr,
+                nondeterministic::{MonotonicallyIncreasingId, RandExpr},
+                percentile::Percentile,
                 scalar_funcs::create_comet_physical_fun,
+                sequential_sum::SequentialSum,
                 stats::StatsType,
                 strings::{Contains, EndsWith, Like, StartsWith, StringSpaceExec, SubstringExec},
-                subquery::Subquery,
+                structs::{CreateNamedStruct, GetStructField},
+                subquery::{in_subquery_values, Subquery},
                 sum_decimal::SumDecimal,
                 temporal::{DateTruncExec, HourExec, MinuteExec, SecondExec, TimestampTruncExec},
+                try_sum::TrySum,
                 variance::Variance,
                 NormalizeNaNAndZero,
             },
-            operators::expand::CometExpandExec,
+            operators::{
+                band_join::{BandCondition, SortMergeBandJoinExec},
+                dictionary_encode::{DictionaryEncodeExec, DictionaryEncoding},
+                expand::CometExpandExec,
+                generate::CometGenerateExec,
+                null_aware_join::NullAwareAntiJoinExec,
+                partial_agg::{AdaptivePartialAggExec, PartialAggSkip},
+                shared_join::{SharedHashJoin, SharedHashJoinExec},
+            },
             shuffle_writer::ShuffleWriterExec,
+            simplify::simplify_expr,
+        },
+        datasource::{file_format, FileScanExec, FileSplit},
+        offload::Offload,
+        operators::{
+            operator_name, unwrap_debug_operators, CopyExec, DebugTap, DebugTapExec,
+            ExecutionError, InputBatch, ScanExec, SharedScanExec, SharedScanSource, ValidationExec,
         },
-        operators::{CopyExec, ExecutionError, ScanExec},
         serde::to_arrow_datatype,
+        shuffle::{combiner::ShuffleCombine, crypto::EncryptionKey},
         spark_expression,
         spark_expression::{
             agg_expr::ExprStruct as AggExprStruct, expr::ExprStruct, literal::Value, AggExpr, Expr,
             ScalarFunc,
         },
-        spark_operator::{operator::OpStruct, JoinType, Operator},
+        spark_operator::{
+            operator::OpStruct, CompressionCodec as SparkCompressionCodec, JoinType, Operator,
+        },
         spark_partitioning::{partitioning::PartitioningStruct, Partitioning as SparkPartitioning},
     },
 };
@@ -115,6 +155,25 @@ pub struct PhysicalPlanner {
     exec_context_id: i64,
     execution_props: ExecutionProps,
     session_ctx: Arc<SessionContext>,
+    // Whether to insert `ValidationExec` on top of every native operator.
+    validate_batches: bool,
+    // Native operators whose output batches are mirrored by `DebugTapExec`.
+    debug_tap: Option<DebugTap>,
+    // When to skip partial aggregation for high-cardinality grouping keys, if enabled.
+    partial_agg_skip: Option<PartialAggSkip>,
+    // When to encode low-cardinality string columns as dictionaries before shuffles and join
+    // builds, if enabled.
+    dictionary_encoding: Option<DictionaryEncoding>,
+    // The memory-mapped broadcast relations of the input sources, if any.
+    mapped_broadcasts: Vec<Option<Arc<MappedBroadcast>>>,
+    // The index of the RDD partition computed by the plan, which seeds nondeterministic
+    // expressions.
+    partition_index: i32,
+    // The provider to offload native operators to, if any.
+    offload: Option<Offload>,
+    // The input sources read by several scans of the plan, e.g., reused exchanges, by the ids of
+    // the sources.
+    shared_scans: Mutex<HashMap<i64, Arc<SharedScanSource>>>,
 }
 
 impl Default for PhysicalPlanner {
@@ -125,6 +184,14 @@ impl Default for PhysicalPlanner {
             exec_context_id: TEST_EXEC_CONTEXT_ID,
             execution_props,
             session_ctx,
+            validate_batches: false,
+            debug_tap: None,
+            partial_agg_skip: None,
+            dictionary_encoding: None,
+            mapped_broadcasts: vec![],
+            partition_index: 0,
+            offload: None,
+            shared_scans: Mutex::default(),
         }
     }
 }
@@ -136,6 +203,14 @@ impl PhysicalPlanner {
             exec_context_id: TEST_EXEC_CONTEXT_ID,
             execution_props,
             session_ctx,
+            validate_batches: false,
+            debug_tap: None,
+            partial_agg_skip: None,
+            dictionary_encoding: None,
+            mapped_broadcasts: vec![],
+            partition_index: 0,
+            offload: None,
+            shared_scans: Mutex::default(),
         }
     }
 
@@ -144,11 +219,122 @@ impl PhysicalPlanner {
             exec_context_id,
             execution_props: self.execution_props,
             session_ctx: self.session_ctx.clone(),
+            validate_batches: self.validate_batches,
+            debug_tap: self.debug_tap,
+            partial_agg_skip: self.partial_agg_skip,
+            dictionary_encoding: self.dictionary_encoding,
+            mapped_broadcasts: self.mapped_broadcasts,
+            partition_index: self.partition_index,
+            offload: self.offload,
+            shared_scans: self.shared_scans,
+        }
+    }
+
+    /// Enables validating Arrow invariants of the output batches of every native operator.
+    /// This is for debugging only as it adds a full validation pass per batch and operator.
+    pub fn with_batch_validation(self, validate_batches: bool) -> Self {
+        Self {
+            validate_batches,
+            ..self
+        }
+    }
+
+    /// Mirrors the output batches of the native operators named by the given tap to Arrow IPC
+    /// files. This is for debugging only.
+    pub fn with_debug_tap(self, debug_tap: Option<DebugTap>) -> Self {
+        Self { debug_tap, ..self }
+    }
+
+    /// Skips partial aggregation adaptively when the grouping keys turn out to have high
+    /// cardinality, as the partial aggregation doesn't reduce the data before the shuffle.
+    pub fn with_partial_agg_skip(self, partial_agg_skip: Option<PartialAggSkip>) -> Self {
+        Self {
+            partial_agg_skip,
+            ..self
+        }
+    }
+
+    /// Encodes the low-cardinality string columns of the inputs of shuffles and join builds as
+    /// dictionaries, which are hashed and written once per distinct value.
+    pub fn with_dictionary_encoding(self, dictionary_encoding: Option<DictionaryEncoding>) -> Self {
+        Self {
+            dictionary_encoding,
+            ..self
+        }
+    }
+
+    /// Reads the input sources which are memory-mapped broadcast relations directly, and shares
+    /// the hash tables built from them with the other tasks of the executor. `mapped_broadcasts`
+    /// has an entry for each input source.
+    pub fn with_mapped_broadcasts(
+        self,
+        mapped_broadcasts: Vec<Option<Arc<MappedBroadcast>>>,
+    ) -> Self {
+        Self {
+            mapped_broadcasts,
+            ..self
+        }
+    }
+
+    /// Sets the index of the RDD partition computed by the plan. Like Spark, nondeterministic
+    /// expressions derive their values from it, so that task retries produce the same output.
+    pub fn with_partition_index(self, partition_index: i32) -> Self {
+        Self {
+            partition_index,
+            ..self
+        }
+    }
+
+    /// Offers the native operators to the given provider, which may replace them with alternative
+    /// implementations, e.g., backed by GPU kernels.
+    pub fn with_offload(self, offload: Option<Offload>) -> Self {
+        Self { offload, ..self }
+    }
+
+    /// Returns the mapped broadcast relation of the next input source to consume, if any.
+    fn next_mapped_broadcast(&self, inputs: &[Arc<GlobalRef>]) -> Option<Arc<MappedBroadcast>> {
+        let index = self.mapped_broadcasts.len().checked_sub(inputs.len())?;
+        self.mapped_broadcasts.get(index)?.clone()
+    }
+
+    /// Wraps `plan` with `DictionaryEncodeExec` if dictionary encoding is enabled, and some string
+    /// columns except the `excluded` ones have low cardinality in the first batch of the scan
+    /// read by `plan`. The plans not reading a scan directly are returned as they are, as their
+    /// output isn't known at planning time.
+    fn encode_dictionaries(
+        &self,
+        plan: Arc<dyn ExecutionPlan>,
+        excluded: &[usize],
+    ) -> Result<Arc<dyn ExecutionPlan>, ExecutionError> {
+        let (Some(encoding), Some(scan)) = (&self.dictionary_encoding, scan_input(&plan)) else {
+            return Ok(plan);
+        };
+        let columns = match &*scan.batch.lock().unwrap() {
+            Some(InputBatch::Batch(columns, _)) => {
+                encoding.low_cardinality_columns(columns, excluded)?
+            }
+            _ => vec![],
+        };
+        if columns.is_empty() {
+            Ok(plan)
+        } else {
+            Ok(Arc::new(DictionaryEncodeExec::new(plan, columns)))
         }
     }
 
     /// Create a DataFusion physical expression from Spark physical expression
-    fn create_expr(
+    pub(crate) fn create_expr(
+        &self,
+        spark_expr: &Expr,
+        input_schema: SchemaRef,
+    ) -> Result<Arc<dyn PhysicalExpr>, ExecutionError> {
+        let expr = self.create_unsimplified_expr(spark_expr, input_schema.clone())?;
+        Ok(simplify_expr(expr, &input_schema)?)
+    }
+
+    /// Create a DataFusion physical expression from Spark physical expression, without
+    /// simplifying it. Its children are created by `create_expr`, so they are simplified.
+    fn create_unsimplified_expr(
         &self,
         spark_expr: &Expr,
         input_schema: SchemaRef,
@@ -159,6 +345,7 @@ impl PhysicalPlanner {
                 expr.right.as_ref().unwrap(),
                 expr.return_type.as_ref(),
                 DataFusionOperator::Plus,
+                expr.allow_precision_loss,
                 input_schema,
             ),
             ExprStruct::Subtract(expr) => self.create_binary_expr(
@@ -166,6 +353,7 @@ impl PhysicalPlanner {
                 expr.right.as_ref().unwrap(),
                 expr.return_type.as_ref(),
                 DataFusionOperator::Minus,
+                expr.allow_precision_loss,
                 input_schema,
             ),
             ExprStruct::Multiply(expr) => self.create_binary_expr(
@@ -173,20 +361,34 @@ impl PhysicalPlanner {
                 expr.right.as_ref().unwrap(),
                 expr.return_type.as_ref(),
                 DataFusionOperator::Multiply,
+                expr.allow_precision_loss,
                 input_schema,
             ),
-            ExprStruct::Divide(expr) => self.create_binary_expr(
+            ExprStruct::Divide(expr) => self.create_div_rem_expr(
                 expr.left.as_ref().unwrap(),
                 expr.right.as_ref().unwrap(),
                 expr.return_type.as_ref(),
-                DataFusionOperator::Divide,
+                DivRemOp::Divide,
+                expr.fail_on_error,
+                expr.allow_precision_loss,
                 input_schema,
             ),
-            ExprStruct::Remainder(expr) => self.create_binary_expr(
+            ExprStruct::IntegralDivide(expr) => self.create_div_rem_expr(
                 expr.left.as_ref().unwrap(),
                 expr.right.as_ref().unwrap(),
                 expr.return_type.as_ref(),
-                DataFusionOperator::Modulo,
+                DivRemOp::IntegralDivide,
+                expr.fail_on_error,
+                true,
+                input_schema,
+            ),
+            ExprStruct::Remainder(expr) => self.create_div_rem_expr(
+                expr.left.as_ref().unwrap(),
+                expr.right.as_ref().unwrap(),
+                expr.return_type.as_ref(),
+                DivRemOp::Remainder,
+                expr.fail_on_error,
+                expr.allow_precision_loss,
                 input_schema,
             ),
             ExprStruct::Eq(expr) => {
@@ -522,11 +724,7 @@ impl PhysicalPlanner {
                         Some(self.create_expr(case_when.else_expr.as_ref().unwrap(), input_schema)?)
                     }
                 };
-                Ok(Arc::new(CaseExpr::try_new(
-                    None,
-                    when_then_pairs,
-                    else_phy_expr,
-                )?))
+                Ok(Arc::new(CaseWhenExpr::new(when_then_pairs, else_phy_expr)))
             }
             ExprStruct::In(expr) => {
                 let value =
@@ -536,19 +734,7 @@ impl PhysicalPlanner {
                     .iter()
                     .map(|x| self.create_expr(x, input_schema.clone()))
                     .collect::<Result<Vec<_>, _>>()?;
-
-                // if schema contains any dictionary type, we should use InListExpr instead of
-                // in_list as it doesn't handle value being dictionary type correctly
-                let contains_dict_type = input_schema
-                    .fields()
-                    .iter()
-                    .any(|f| matches!(f.data_type(), DataType::Dictionary(_, _)));
-                if contains_dict_type {
-                    // TODO: remove the fallback when https://github.com/apache/arrow-datafusion/issues/9530 is fixed
-                    Ok(Arc::new(InListExpr::new(value, list, expr.negated, None)))
-                } else {
-                    in_list(value, list, &expr.negated, input_schema.as_ref()).map_err(|e| e.into())
-                }
+                Self::create_in_list(value, list, expr.negated, expr.in_set, input_schema)
             }
             ExprStruct::If(expr) => {
                 let if_expr =
@@ -575,7 +761,31 @@ impl PhysicalPlanner {
             ExprStruct::Subquery(expr) => {
                 let id = expr.id;
                 let data_type = to_arrow_datatype(expr.datatype.as_ref().unwrap());
-                Ok(Arc::new(Subquery::new(self.exec_context_id, id, data_type)))
+                // The subquery has been executed before the native plan is created, so its result
+                // is retrieved only once as a literal
+                let subquery = Subquery::new(self.exec_context_id, id, data_type);
+                Ok(Arc::new(DataFusionLiteral::new(subquery.value()?)))
+            }
+            ExprStruct::InSubquery(expr) => {
+                let value = self.create_expr(expr.child.as_ref().unwrap(), input_schema.clone())?;
+                let list = in_subquery_values(self.exec_context_id, expr.id)?
+                    .into_iter()
+                    .map(|literal| {
+                        let literal = Expr {
+                            expr_struct: Some(ExprStruct::Literal(literal)),
+                        };
+                        self.create_expr(&literal, input_schema.clone())
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                if list.is_empty() {
+                    // Null if the value is null, otherwise false
+                    return Ok(Arc::new(IfExpr::new(
+                        Arc::new(IsNullExpr::new(value)),
+                        Arc::new(DataFusionLiteral::new(ScalarValue::Boolean(None))),
+                        Arc::new(DataFusionLiteral::new(ScalarValue::Boolean(Some(false)))),
+                    )));
+                }
+                Self::create_in_list(value, list, false, false, input_schema)
             }
             ExprStruct::BloomFilterMightContain(expr) => {
                 let bloom_filter_expr =
@@ -586,6 +796,32 @@ impl PhysicalPlanner {
                     value_expr,
                 )?))
             }
+            ExprStruct::Rand(expr) => Ok(Arc::new(RandExpr::new(expr.seed, self.partition_index))),
+            ExprStruct::MonotonicallyIncreasingId(_) => Ok(Arc::new(
+                MonotonicallyIncreasingId::new(self.partition_index),
+            )),
+            ExprStruct::SparkPartitionId(_) => Ok(Arc::new(DataFusionLiteral::new(
+                ScalarValue::Int32(Some(self.partition_index)),
+            ))),
+            ExprStruct::CreateNamedStruct(expr) => {
+                let values = expr
+                    .values
+                    .iter()
+                    .map(|value| self.create_expr(value, input_schema.clone()))
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(Arc::new(CreateNamedStruct::new(values, expr.names.clone())))
+            }
+            ExprStruct::GetStructField(expr) => {
+                let child = self.create_expr(expr.child.as_ref().unwrap(), input_schema)?;
+                Ok(Arc::new(GetStructField::new(child, expr.ordinal as usize)))
+            }
+            ExprStruct::PreciseTimestampConversion(expr) => {
+                // Unlike Spark's cast, Arrow's cast between timestamps and longs keeps their
+                // microseconds
+                let child = self.create_expr(expr.child.as_ref().unwrap(), input_schema)?;
+                let datatype = to_arrow_datatype(expr.datatype.as_ref().unwrap());
+                Ok(Arc::new(CastExpr::new(child, datatype, None)))
+            }
             expr => Err(ExecutionError::GeneralError(format!(
                 "Not implemented: {:?}",
                 expr
@@ -622,30 +858,111 @@ impl PhysicalPlanner {
         }
     }
 
+    fn create_in_list(
+        value: Arc<dyn PhysicalExpr>,
+        list: Vec<Arc<dyn PhysicalExpr>>,
+        negated: bool,
+        in_set: bool,
+        input_schema: SchemaRef,
+    ) -> Result<Arc<dyn PhysicalExpr>, ExecutionError> {
+        // Large lists of literals and the sets of `InSet` are looked up in a hash set
+        if let Some(in_set_expr) = InSetExpr::try_new(
+            Arc::clone(&value),
+            &list,
+            negated,
+            in_set,
+            input_schema.as_ref(),
+        )? {
+            return Ok(Arc::new(in_set_expr));
+        }
+
+        // if schema contains any dictionary type, we should use InListExpr instead of
+        // in_list as it doesn't handle value being dictionary type correctly
+        let contains_dict_type = input_schema
+            .fields()
+            .iter()
+            .any(|f| matches!(f.data_type(), DataType::Dictionary(_, _)));
+        if contains_dict_type {
+            // TODO: remove the fallback when https://github.com/apache/arrow-datafusion/issues/9530 is fixed
+            Ok(Arc::new(InListExpr::new(value, list, negated, None)))
+        } else {
+            in_list(value, list, &negated, input_schema.as_ref()).map_err(|e| e.into())
+        }
+    }
+
+    /// Creates the DataFusion sort expressions of a Spark `SortOrder`, which may need more than
+    /// one sort expression to order the rows like Spark.
+    ///
+    /// Spark orders null fields of structs first in ascending order, and last in descending
+    /// order, regardless of the null ordering of the struct itself. Arrow applies the null
+    /// ordering of a struct to its fields too. So for `ASC NULLS LAST` and `DESC NULLS FIRST`,
+    /// null structs are ordered by an additional `IS NULL` sort expression, and the struct is
+    /// ordered with the null ordering of its fields.
+    fn create_sort_exprs(
+        &self,
+        spark_expr: &Expr,
+        input_schema: SchemaRef,
+    ) -> Result<Vec<PhysicalSortExpr>, ExecutionError> {
+        let sort_expr = self.create_sort_expr(spark_expr, input_schema.clone())?;
+        let SortOptions {
+            descending,
+            nulls_first,
+        } = sort_expr.options;
+
+        match sort_expr.expr.data_type(&input_schema)? {
+            DataType::Struct(_) if nulls_first == descending => Ok(vec![
+                PhysicalSortExpr {
+                    expr: Arc::new(IsNullExpr::new(sort_expr.expr.clone())),
+                    options: SortOptions {
+                        descending: nulls_first,
+                        nulls_first: false,
+                    },
+                },
+                PhysicalSortExpr {
+                    expr: sort_expr.expr,
+                    options: SortOptions {
+                        descending,
+                        nulls_first: !descending,
+                    },
+                },
+            ]),
+            _ => Ok(vec![sort_expr]),
+        }
+    }
+
     fn create_binary_expr(
         &self,
         left: &Expr,
         right: &Expr,
         return_type: Option<&spark_expression::DataType>,
         op: DataFusionOperator,
+        allow_precision_loss: bool,
         input_schema: SchemaRef,
     ) -> Result<Arc<dyn PhysicalExpr>, ExecutionError> {
         let left = self.create_expr(left, input_schema.clone())?;
         let right = self.create_expr(right, input_schema.clone())?;
+        let (left, right) = widen_decimal_operands(left, right, &input_schema)?;
         match (
             op,
             left.data_type(&input_schema),
             right.data_type(&input_schema),
         ) {
             (
-                DataFusionOperator::Plus
-                | DataFusionOperator::Minus
-                | DataFusionOperator::Multiply
-                | DataFusionOperator::Modulo,
+                DataFusionOperator::Plus | DataFusionOperator::Minus | DataFusionOperator::Multiply,
                 Ok(DataType::Decimal128(p1, s1)),
                 Ok(DataType::Decimal128(p2, s2)),
             ) => {
-                let data_type = return_type.map(to_arrow_datatype).unwrap();
+                let data_type = match return_type {
+                    Some(data_type) => to_arrow_datatype(data_type),
+                    None => {
+                        let op = match op {
+                            DataFusionOperator::Plus => DecimalOp::Add,
+                            DataFusionOperator::Minus => DecimalOp::Subtract,
+                            _ => DecimalOp::Multiply,
+                        };
+                        decimal_result_type(op, (p1, s1), (p2, s2), allow_precision_loss)
+                    }
+                };
                 // For some Decimal128 operations, we need wider internal digits.
                 // Cast left and right to Decimal256 and cast the result back to Decimal128
                 let left = Arc::new(Cast::new_without_timezone(
@@ -665,30 +982,55 @@ impl PhysicalPlanner {
                     EvalMode::Legacy,
                 )))
             }
-            (
-                DataFusionOperator::Divide,
-                Ok(DataType::Decimal128(_p1, _s1)),
-                Ok(DataType::Decimal128(_p2, _s2)),
-            ) => {
-                let data_type = return_type.map(to_arrow_datatype).unwrap();
-                let fun_expr = create_comet_physical_fun(
-                    "decimal_div",
-                    data_type.clone(),
-                    &self.session_ctx.state(),
-                )?;
-                Ok(Arc::new(ScalarFunctionExpr::new(
-                    "decimal_div",
-                    fun_expr,
-                    vec![left, right],
-                    data_type,
-                    None,
-                    false,
-                )))
-            }
             _ => Ok(Arc::new(BinaryExpr::new(left, op, right))),
         }
     }
 
+    /// Creates Spark `/`, `div` or `%`, which differ from the DataFusion operators in division by
+    /// zero and in the precision and scale of decimal results.
+    #[allow(clippy::too_many_arguments)]
+    fn create_div_rem_expr(
+        &self,
+        left: &Expr,
+        right: &Expr,
+        return_type: Option<&spark_expression::DataType>,
+        op: DivRemOp,
+        fail_on_error: bool,
+        allow_precision_loss: bool,
+        input_schema: SchemaRef,
+    ) -> Result<Arc<dyn PhysicalExpr>, ExecutionError> {
+        let left = self.create_expr(left, input_schema.clone())?;
+        let right = self.create_expr(right, input_schema.clone())?;
+        let (left, right) = widen_decimal_operands(left, right, &input_schema)?;
+        let data_type = match (
+            return_type,
+            left.data_type(&input_schema)?,
+            right.data_type(&input_schema)?,
+        ) {
+            (Some(data_type), _, _) => to_arrow_datatype(data_type),
+            (None, DataType::Decimal128(p1, s1), DataType::Decimal128(p2, s2)) => match op {
+                DivRemOp::Divide => {
+                    decimal_result_type(DecimalOp::Divide, (p1, s1), (p2, s2), allow_precision_loss)
+                }
+                DivRemOp::Remainder => decimal_result_type(
+                    DecimalOp::Remainder,
+                    (p1, s1),
+                    (p2, s2),
+                    allow_precision_loss,
+                ),
+                DivRemOp::IntegralDivide => DataType::Int64,
+            },
+            (None, left_type, _) => left_type,
+        };
+        Ok(Arc::new(DivRemExpr::new(
+            left,
+            right,
+            op,
+            data_type,
+            fail_on_error,
+        )))
+    }
+
     /// Create a DataFusion physical plan from Spark physical plan.
     ///
     /// `inputs` is a vector of input source IDs. It is used to create `ScanExec`s. Each `ScanExec`
@@ -707,6 +1049,44 @@ impl PhysicalPlanner {
         &'a self,
         spark_plan: &'a Operator,
         inputs: &mut Vec<Arc<GlobalRef>>,
+    ) -> Result<(Vec<ScanExec>, Arc<dyn ExecutionPlan>), ExecutionError> {
+        let (scans, plan) = self.create_native_plan(spark_plan, inputs)?;
+
+        let plan = match &self.offload {
+            Some(offload) => offload.offload(plan)?,
+            None => plan,
+        };
+
+        let plan: Arc<dyn ExecutionPlan> = match &self.debug_tap {
+            Some(tap) if operator_name(plan.as_ref()) == tap.operator => {
+                Arc::new(DebugTapExec::new(
+                    plan,
+                    self.exec_context_id,
+                    tap.operator.clone(),
+                    tap.dir.clone(),
+                ))
+            }
+            _ => plan,
+        };
+
+        #[cfg(feature = "alloc_tracking")]
+        let plan: Arc<dyn ExecutionPlan> = Arc::new(
+            crate::execution::alloc_tracker::AllocTrackingExec::new(plan, self.exec_context_id),
+        );
+
+        if self.validate_batches {
+            Ok((scans, Arc::new(ValidationExec::new(plan))))
+        } else {
+            Ok((scans, plan))
+        }
+    }
+
+    /// Creates the DataFusion operator for the given Spark operator. Child operators are
+    /// created through `create_plan`.
+    fn create_native_plan<'a>(
+        &'a self,
+        spark_plan: &'a Operator,
+        inputs: &mut Vec<Arc<GlobalRef>>,
     ) -> Result<(Vec<ScanExec>, Arc<dyn ExecutionPlan>), ExecutionError> {
         let children = &spark_plan.children;
         match spark_plan.op_struct.as_ref().unwrap() {
@@ -760,14 +1140,37 @@ impl PhysicalPlanner {
                     .map(|expr| self.create_agg_expr(expr, schema.clone()))
                     .collect();
 
-                let num_agg = agg.agg_exprs.len();
+                // Rows are filtered per aggregate expression, e.g., `COUNT(*) FILTER (WHERE ...)`
+                let filter_exprs = agg
+                    .agg_exprs
+                    .iter()
+                    .map(|expr| {
+                        expr.filter
+                            .as_ref()
+                            .map(|filter| self.create_expr(filter, schema.clone()))
+                            .transpose()
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                let partial_agg_skip = self
+                    .partial_agg_skip
+                    .filter(|_| mode == DFAggregateMode::Partial && !group_by.expr().is_empty());
+                // The adaptive partial aggregation buffers the probed input batches, so they are
+                // copied if the child reuses its arrays across batches
+                let agg_input: Arc<dyn ExecutionPlan> =
+                    if partial_agg_skip.is_some() && can_reuse_input_batch(&child) {
+                        Arc::new(CopyExec::keeping_dictionaries(child.clone()))
+                    } else {
+                        child.clone()
+                    };
+
                 let aggregate = Arc::new(
                     datafusion::physical_plan::aggregates::AggregateExec::try_new(
                         mode,
                         group_by,
                         agg_exprs?,
-                        vec![None; num_agg], // no filter expressions
-                        child.clone(),
+                        filter_exprs,
+                        agg_input,
                         schema.clone(),
                     )?,
                 );
@@ -781,6 +1184,15 @@ impl PhysicalPlanner {
                     })
                     .collect();
 
+                let aggregate: Arc<dyn ExecutionPlan> = match partial_agg_skip {
+                    Some(skip) => Arc::new(AdaptivePartialAggExec::new(
+                        aggregate,
+                        skip.probe_rows,
+                        skip.ratio_threshold,
+                    )),
+                    None => aggregate,
+                };
+
                 let exec: Arc<dyn ExecutionPlan> = if agg.result_exprs.is_empty() {
                     aggregate
                 } else {
@@ -807,11 +1219,12 @@ impl PhysicalPlanner {
                 assert!(children.len() == 1);
                 let (scans, child) = self.create_plan(&children[0], inputs)?;
 
-                let exprs: Result<Vec<PhysicalSortExpr>, ExecutionError> = sort
+                let exprs: Vec<PhysicalSortExpr> = sort
                     .sort_orders
                     .iter()
-                    .map(|expr| self.create_sort_expr(expr, child.schema()))
-                    .collect();
+                    .map(|expr| self.create_sort_exprs(expr, child.schema()))
+                    .flatten_ok()
+                    .collect::<Result<_, _>>()?;
 
                 let fetch = sort.fetch.map(|num| num as usize);
 
@@ -819,12 +1232,20 @@ impl PhysicalPlanner {
 
                 Ok((
                     scans,
-                    Arc::new(SortExec::new(exprs?, copy_exec).with_fetch(fetch)),
+                    Arc::new(SortExec::new(exprs, copy_exec).with_fetch(fetch)),
                 ))
             }
             OpStruct::Scan(scan) => {
                 let fields = scan.fields.iter().map(to_arrow_datatype).collect_vec();
 
+                // The input source of a scan reading the same source as a previous one is not
+                // passed again, but read from the previous scan
+                if scan.source_id != 0 {
+                    if let Some(source) = self.shared_scans.lock().unwrap().get(&scan.source_id) {
+                        return Ok((vec![], Arc::new(SharedScanExec::new(source.clone()))));
+                    }
+                }
+
                 // If it is not test execution context for unit test, we should have at least one
                 // input source
                 if self.exec_context_id != TEST_EXEC_CONTEXT_ID && inputs.is_empty() {
@@ -833,6 +1254,8 @@ impl PhysicalPlanner {
                     ));
                 }
 
+                let mapped_broadcast = self.next_mapped_broadcast(inputs);
+
                 // Consumes the first input source for the scan
                 let input_source = if self.exec_context_id == TEST_EXEC_CONTEXT_ID
                     && inputs.is_empty()
@@ -843,25 +1266,179 @@ impl PhysicalPlanner {
                     Some(inputs.remove(0))
                 };
 
+                // Mapped broadcast relations are read without the JVM iterator
+                if let Some(relation) = mapped_broadcast {
+                    return Ok((vec![], Self::create_mapped_scan(&relation, &fields)?));
+                }
+
                 // The `ScanExec` operator will take actual arrays from Spark during execution
+                let source_id = scan.source_id;
                 let scan = ScanExec::new(self.exec_context_id, input_source, fields)?;
+                if source_id != 0 {
+                    // The batches of the shared source are kept until all its scans read them,
+                    // so they are copied from the arrays reused by JVM
+                    let source =
+                        SharedScanSource::new(Arc::new(CopyExec::new(Arc::new(scan.clone()))));
+                    self.shared_scans
+                        .lock()
+                        .unwrap()
+                        .insert(source_id, source.clone());
+                    return Ok((vec![scan], Arc::new(SharedScanExec::new(source))));
+                }
                 Ok((vec![scan.clone()], Arc::new(scan)))
             }
+            OpStruct::LocalTableScan(scan) => {
+                // The rows are in the plan, so no input source is consumed
+                let fields = scan.fields.iter().map(to_arrow_datatype).collect_vec();
+                Ok((vec![], Self::create_local_scan(&scan.data, &fields)?))
+            }
+            OpStruct::FileScan(scan) => {
+                // The files are read natively, so no input source is consumed
+                let format = file_format(&scan.format).ok_or_else(|| {
+                    ExecutionError::GeneralError(format!(
+                        "Unsupported native file format: {}",
+                        scan.format
+                    ))
+                })?;
+                let file_schema = Arc::new(Schema::new(
+                    scan.names
+                        .iter()
+                        .zip(scan.fields.iter())
+                        .map(|(name, field)| Field::new(name, to_arrow_datatype(field), true))
+                        .collect_vec(),
+                ));
+                let projection = scan.projection.iter().map(|&i| i as usize).collect_vec();
+                let schema = Arc::new(file_schema.project(&projection)?);
+                let filters = scan
+                    .filters
+                    .iter()
+                    .map(|expr| self.create_expr(expr, schema.clone()))
+                    .collect::<Result<Vec<_>, _>>()?;
+                // Each task reads the splits of its partition
+                let splits = scan
+                    .partitions
+                    .get(self.partition_index as usize)
+                    .map(|partition| {
+                        partition
+                            .files
+                            .iter()
+                            .map(|file| FileSplit {
+                                path: file.path.clone().into(),
+                                start: file.start as u64,
+                                length: file.length as u64,
+                            })
+                            .collect_vec()
+                    })
+                    .unwrap_or_default();
+                let scan = FileScanExec::try_new(
+                    format,
+                    splits,
+                    file_schema,
+                    projection,
+                    filters,
+                    scan.options.clone(),
+                )?;
+                Ok((vec![], Arc::new(scan)))
+            }
             OpStruct::ShuffleWriter(writer) => {
                 assert!(children.len() == 1);
                 let (scans, child) = self.create_plan(&children[0], inputs)?;
+                // The rows sorted before round-robin partitioning are copied without dictionaries
+                let child = match &writer.partitioning.as_ref().unwrap().partitioning_struct {
+                    Some(PartitioningStruct::RoundRobinPartition(round_robin))
+                        if round_robin.sort_before_partitioning =>
+                    {
+                        child
+                    }
+                    _ => self.encode_dictionaries(child, &[])?,
+                };
 
                 let partitioning = self
                     .create_partitioning(writer.partitioning.as_ref().unwrap(), child.schema())?;
 
+                let mut round_robin_start = 0;
+                let mut child = child;
+                // The sort-based writer holds the input batches until they are spilled, so they
+                // are copied unless they are sorted first, like `SortExec` does below
+                let mut copy_input = writer.sort_based;
+                if let Some(PartitioningStruct::RoundRobinPartition(round_robin)) =
+                    &writer.partitioning.as_ref().unwrap().partitioning_struct
+                {
+                    round_robin_start = round_robin.start_position;
+                    if round_robin.sort_before_partitioning {
+                        // Like Spark, sorts the rows by all the columns first, so that the rows
+                        // going to each partition don't depend on the input order, which may
+                        // change when the map task is retried
+                        let exprs = child
+                            .schema()
+                            .fields()
+                            .iter()
+                            .enumerate()
+                            .map(|(idx, field)| PhysicalSortExpr {
+                                expr: Arc::new(Column::new(field.name(), idx)),
+                                options: SortOptions::default(),
+                            })
+                            .collect_vec();
+                        child = Arc::new(SortExec::new(exprs, Arc::new(CopyExec::new(child))));
+                        copy_input = false;
+                    }
+                }
+
+                if copy_input {
+                    // The dictionary arrays are written as they are, like the hash-based writer
+                    child = Arc::new(CopyExec::keeping_dictionaries(child));
+                }
+
+                let codec = match writer.codec() {
+                    SparkCompressionCodec::Lz4 => CompressionCodec::Lz4,
+                    SparkCompressionCodec::Snappy => CompressionCodec::Snappy,
+                    SparkCompressionCodec::Zstd => CompressionCodec::Zstd(writer.compression_level),
+                };
+
+                let checksums = (!writer.output_checksum_file.is_empty()).then(|| {
+                    (
+                        writer.output_checksum_file.clone(),
+                        writer.checksum_algorithm,
+                    )
+                });
+
+                let encryption_key = (!writer.encryption_key.is_empty())
+                    .then(|| EncryptionKey(writer.encryption_key.clone()));
+
+                // The states of the aggregate expressions are merged like a final aggregation
+                let combine = writer
+                    .combiner
+                    .as_ref()
+                    .map(|combiner| {
+                        let aggr_exprs = combiner
+                            .agg_exprs
+                            .iter()
+                            .map(|expr| self.create_agg_expr(expr, child.schema()))
+                            .collect::<Result<Vec<_>, _>>()?;
+                        Ok::<_, ExecutionError>(ShuffleCombine {
+                            num_grouping_keys: combiner.num_grouping_keys as usize,
+                            aggr_exprs,
+                            max_groups: combiner.max_groups as usize,
+                        })
+                    })
+                    .transpose()?;
+
                 Ok((
                     scans,
-                    Arc::new(ShuffleWriterExec::try_new(
-                        child,
-                        partitioning,
-                        writer.output_data_file.clone(),
-                        writer.output_index_file.clone(),
-                    )?),
+                    Arc::new(
+                        ShuffleWriterExec::try_new(
+                            child,
+                            partitioning,
+                            writer.output_data_file.clone(),
+                            writer.output_index_file.clone(),
+                        )?
+                        .with_round_robin_start(round_robin_start)
+                        .with_codec(codec)
+                        .with_sort_based(writer.sort_based)
+                        .with_checksums(checksums)
+                        .with_encryption_key(encryption_key)
+                        .with_combine(combine),
+                    ),
                 ))
             }
             OpStruct::Expand(expand) => {
@@ -905,7 +1482,11 @@ impl PhysicalPlanner {
                 // the data corruption. Note that we only need to copy the input batch
                 // if the child operator is `ScanExec`, because other operators after `ScanExec`
                 // will create new arrays for the output batch.
-                let child = if child.as_any().downcast_ref::<ScanExec>().is_some() {
+                let child = if unwrap_debug_operators(&child)
+                    .as_any()
+                    .downcast_ref::<ScanExec>()
+                    .is_some()
+                {
                     Arc::new(CopyExec::new(child))
                 } else {
                     child
@@ -916,6 +1497,29 @@ impl PhysicalPlanner {
                     Arc::new(CometExpandExec::new(projections, child, schema)),
                 ))
             }
+            OpStruct::Generate(generate) => {
+                assert!(children.len() == 1);
+                let (scans, child) = self.create_plan(&children[0], inputs)?;
+
+                let generator =
+                    self.create_expr(generate.child.as_ref().unwrap(), child.schema())?;
+                let required_child_output = generate
+                    .required_child_output
+                    .iter()
+                    .map(|&idx| idx as usize)
+                    .collect();
+
+                Ok((
+                    scans,
+                    Arc::new(CometGenerateExec::try_new(
+                        generator,
+                        required_child_output,
+                        generate.position,
+                        generate.outer,
+                        child,
+                    )?),
+                ))
+            }
             OpStruct::SortMergeJoin(join) => {
                 let (join_params, scans) = self.parse_join_parameters(
                     inputs,
@@ -923,7 +1527,7 @@ impl PhysicalPlanner {
                     &join.left_join_keys,
                     &join.right_join_keys,
                     join.join_type,
-                    &None,
+                    &join.condition,
                 )?;
 
                 let sort_options = join
@@ -940,6 +1544,33 @@ impl PhysicalPlanner {
                     })
                     .collect();
 
+                // The rows with the same join keys are joined by a sliding window over the band,
+                // and the full condition is evaluated on the rows within it
+                if let (Some(band), Some(filter)) = (&join.band, join_params.join_filter.clone()) {
+                    let bound = |bound: &Option<Expr>| {
+                        bound
+                            .as_ref()
+                            .map(|b| self.create_expr(b, join_params.right.schema()))
+                            .transpose()
+                    };
+                    let band = BandCondition {
+                        value: self
+                            .create_expr(band.value.as_ref().unwrap(), join_params.left.schema())?,
+                        lower: bound(&band.lower)?,
+                        upper: bound(&band.upper)?,
+                    };
+                    let join = Arc::new(SortMergeBandJoinExec::try_new(
+                        join_params.left,
+                        join_params.right,
+                        join_params.join_on,
+                        band,
+                        filter,
+                        join_params.join_type,
+                        sort_options,
+                    )?);
+                    return Ok((scans, join));
+                }
+
                 let join = Arc::new(SortMergeJoinExec::try_new(
                     join_params.left,
                     join_params.right,
@@ -955,6 +1586,11 @@ impl PhysicalPlanner {
                 Ok((scans, join))
             }
             OpStruct::HashJoin(join) => {
+                // The build side is the left child, which may read a mapped broadcast relation
+                let build_broadcast = match children.first().and_then(|c| c.op_struct.as_ref()) {
+                    Some(OpStruct::Scan(_)) => self.next_mapped_broadcast(inputs),
+                    _ => None,
+                };
                 let (join_params, scans) = self.parse_join_parameters(
                     inputs,
                     children,
@@ -963,23 +1599,147 @@ impl PhysicalPlanner {
                     join.join_type,
                     &join.condition,
                 )?;
+
+                if join.null_aware_anti_join {
+                    if join_params.join_type != DFJoinType::LeftAnti
+                        || join_params.join_on.len() != 1
+                        || join_params.join_filter.is_some()
+                    {
+                        return Err(ExecutionError::GeneralError(format!(
+                            "Null-aware anti join requires a single join key and no condition, \
+                             but got: {:?}",
+                            join
+                        )));
+                    }
+                    // The build side is the right child, and the left child is streamed
+                    let (probe_key, build_key) = join_params.join_on.into_iter().next().unwrap();
+                    let join = Arc::new(NullAwareAntiJoinExec::new(
+                        join_params.left,
+                        join_params.right,
+                        probe_key,
+                        build_key,
+                    ));
+                    return Ok((scans, join));
+                }
+
+                // Probes the hash table shared by the tasks joining the same relation, instead of
+                // building it in every task
+                if let Some(relation) =
+                    build_broadcast.filter(|_| SharedHashJoin::supports(&join_params.join_type))
+                {
+                    let probe = join_params.right;
+                    let null_equals_null = join.null_equals_null;
+                    let key = format!("{:?}:{:?}", join, probe.schema());
+                    let shared = relation.shared_state(key, || {
+                        let context = TaskContext::default()
+                            .with_session_config(self.session_ctx.copied_config());
+                        SharedHashJoin::try_new(probe.schema(), Arc::new(context), |probe| {
+                            HashJoinExec::try_new(
+                                join_params.left,
+                                probe,
+                                join_params.join_on,
+                                join_params.join_filter,
+                                &join_params.join_type,
+                                None,
+                                PartitionMode::CollectLeft,
+                                null_equals_null,
+                            )
+                        })
+                        .map(Arc::new)
+                    })?;
+                    return Ok((scans, Arc::new(SharedHashJoinExec::new(shared, probe))));
+                }
+
+                // The join keys and the columns of the join filter are evaluated per row, and
+                // stay as they are
+                let mut excluded = join_params
+                    .join_on
+                    .iter()
+                    .flat_map(|(left_key, _)| collect_columns(left_key))
+                    .map(|column| column.index())
+                    .collect_vec();
+                if let Some(filter) = &join_params.join_filter {
+                    excluded.extend(
+                        filter
+                            .column_indices()
+                            .iter()
+                            .filter(|column| column.side == JoinSide::Left)
+                            .map(|column| column.index),
+                    );
+                }
+                let build = self.encode_dictionaries(join_params.left, &excluded)?;
+
                 let join = Arc::new(HashJoinExec::try_new(
-                    join_params.left,
+                    build,
                     join_params.right,
                     join_params.join_on,
                     join_params.join_filter,
                     &join_params.join_type,
                     None,
                     PartitionMode::Partitioned,
-                    // null doesn't equal to null in Spark join key. If the join key is
-                    // `EqualNullSafe`, Spark will rewrite it during planning.
-                    false,
+                    // null doesn't equal to null in Spark join key, unless the join keys are
+                    // all `EqualNullSafe`, which Spark rewrites and Comet restores
+                    join.null_equals_null,
                 )?);
                 Ok((scans, join))
             }
         }
     }
 
+    /// Creates the operator reading the batches of a mapped broadcast relation, whose columns are
+    /// named like the ones of `ScanExec`.
+    fn create_mapped_scan(
+        relation: &MappedBroadcast,
+        fields: &[DataType],
+    ) -> Result<Arc<dyn ExecutionPlan>, ExecutionError> {
+        Self::create_memory_scan(relation.batches(), fields)
+    }
+
+    /// Creates the operator reading the rows of a local relation, which are encoded as an Arrow
+    /// IPC stream in the plan.
+    fn create_local_scan(
+        data: &[u8],
+        fields: &[DataType],
+    ) -> Result<Arc<dyn ExecutionPlan>, ExecutionError> {
+        let batches = if data.is_empty() {
+            vec![]
+        } else {
+            StreamReader::try_new(Cursor::new(data), None)?.collect::<Result<Vec<_>, _>>()?
+        };
+        Self::create_memory_scan(&batches, fields)
+    }
+
+    /// Creates the operator reading the given in-memory batches, whose columns are named like the
+    /// ones of `ScanExec`.
+    fn create_memory_scan(
+        batches: &[RecordBatch],
+        fields: &[DataType],
+    ) -> Result<Arc<dyn ExecutionPlan>, ExecutionError> {
+        let schema: SchemaRef = match batches.first() {
+            Some(batch) => Arc::new(Schema::new(
+                batch
+                    .schema()
+                    .fields()
+                    .iter()
+                    .enumerate()
+                    .map(|(idx, field)| field.as_ref().clone().with_name(format!("col_{}", idx)))
+                    .collect_vec(),
+            )),
+            None => Arc::new(Schema::new(
+                fields
+                    .iter()
+                    .enumerate()
+                    .map(|(idx, dt)| Field::new(format!("col_{}", idx), dt.clone(), true))
+                    .collect_vec(),
+            )),
+        };
+        let batches = batches
+            .iter()
+            .map(|batch| RecordBatch::try_new(schema.clone(), batch.columns().to_vec()))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Arc::new(MemoryExec::try_new(&[batches], schema, None)?))
+    }
+
     fn parse_join_parameters(
         &self,
         inputs: &mut Vec<Arc<GlobalRef>>,
@@ -1147,6 +1907,14 @@ impl PhysicalPlanner {
                     DataType::Decimal128(_, _) => {
                         Ok(Arc::new(SumDecimal::new("sum", child, datatype)))
                     }
+                    DataType::Int64 if expr.try_mode => {
+                        let child = Arc::new(CastExpr::new(child, datatype, None));
+                        Ok(Arc::new(TrySum::new("sum", child)))
+                    }
+                    DataType::Float64 if expr.sequential => {
+                        let child = Arc::new(CastExpr::new(child, datatype, None));
+                        Ok(Arc::new(SequentialSum::new(child, "sum")))
+                    }
                     _ => {
                         // cast to the result data type of SUM if necessary, we should not expect
                         // a cast failure since it should have already been checked at Spark side
@@ -1171,7 +1939,9 @@ impl PhysicalPlanner {
                         // from the input type, e.g. AVG(Int32). We should not expect a cast
                         // failure since it should have already been checked at Spark side.
                         let child = Arc::new(CastExpr::new(child, datatype.clone(), None));
-                        Ok(Arc::new(Avg::new(child, "avg", datatype)))
+                        Ok(Arc::new(
+                            Avg::new(child, "avg", datatype).with_sequential(expr.sequential),
+                        ))
                     }
                 }
             }
@@ -1260,6 +2030,55 @@ impl PhysicalPlanner {
                     ))),
                 }
             }
+            AggExprStruct::CollectList(expr) => {
+                let child = self.create_expr(expr.child.as_ref().unwrap(), schema)?;
+                let datatype = to_arrow_datatype(expr.datatype.as_ref().unwrap());
+                Ok(Arc::new(Collect::new(
+                    child,
+                    "collect_list",
+                    datatype,
+                    false,
+                )))
+            }
+            AggExprStruct::CollectSet(expr) => {
+                let child = self.create_expr(expr.child.as_ref().unwrap(), schema)?;
+                let datatype = to_arrow_datatype(expr.datatype.as_ref().unwrap());
+                Ok(Arc::new(Collect::new(child, "collect_set", datatype, true)))
+            }
+            AggExprStruct::ApproxPercentile(expr) => {
+                let child = self.create_expr(expr.child.as_ref().unwrap(), schema)?;
+                let datatype = to_arrow_datatype(expr.datatype.as_ref().unwrap());
+                Ok(Arc::new(ApproxPercentile::new(
+                    child,
+                    "approx_percentile",
+                    datatype,
+                    expr.percentages.clone(),
+                    expr.return_array,
+                    expr.relative_error,
+                )))
+            }
+            AggExprStruct::Percentile(expr) => {
+                let child = self.create_expr(expr.child.as_ref().unwrap(), schema.clone())?;
+                let frequency = self.create_expr(expr.frequency.as_ref().unwrap(), schema)?;
+                let datatype = to_arrow_datatype(expr.datatype.as_ref().unwrap());
+                Ok(Arc::new(Percentile::new(
+                    child,
+                    frequency,
+                    "percentile",
+                    datatype,
+                    expr.percentages.clone(),
+                    expr.return_array,
+                )))
+            }
+            AggExprStruct::BloomFilterAgg(expr) => {
+                let child = self.create_expr(expr.child.as_ref().unwrap(), schema)?;
+                Ok(Arc::new(BloomFilterAgg::new(
+                    child,
+                    "bloom_filter_agg",
+                    expr.expected_num_items,
+                    expr.num_bits,
+                )))
+            }
         }
     }
 
@@ -1282,6 +2101,9 @@ impl PhysicalPlanner {
                 ))
             }
             PartitioningStruct::SinglePartition(_) => Ok(Partitioning::UnknownPartitioning(1)),
+            PartitioningStruct::RoundRobinPartition(round_robin_partition) => Ok(
+                Partitioning::RoundRobinBatch(round_robin_partition.num_partitions as usize),
+            ),
         }
     }
 
@@ -1353,10 +2175,21 @@ impl From<ExpressionError> for DataFusionError {
     }
 }
 
+/// Returns the scan whose batches are the output of `op`, looking through the operators which
+/// only copy or inspect them.
+fn scan_input(op: &Arc<dyn ExecutionPlan>) -> Option<&ScanExec> {
+    let op = unwrap_debug_operators(op);
+    if let Some(copy) = op.as_any().downcast_ref::<CopyExec>() {
+        return scan_input(copy.input());
+    }
+    op.as_any().downcast_ref::<ScanExec>()
+}
+
 /// Returns true if given operator can return input array as output array without
 /// modification. This is used to determine if we need to copy the input batch to avoid
 /// data corruption from reusing the input batch.
 fn can_reuse_input_batch(op: &Arc<dyn ExecutionPlan>) -> bool {
+    let op = unwrap_debug_operators(op);
     op.as_any().downcast_ref::<ScanExec>().is_some()
         || op.as_any().downcast_ref::<LocalLimitExec>().is_some()
         || op.as_any().downcast_ref::<ProjectionExec>().is_some()
@@ -1497,14 +2330,21 @@ mod tests {
 
     use futures::{poll, StreamExt};
 
-    use arrow_array::{DictionaryArray, Int32Array, StringArray};
-    use arrow_schema::DataType;
+    use arrow::{buffer::NullBuffer, compute::lexsort_to_indices, ipc::writer::StreamWriter};
+    use arrow_array::{
+        ArrayRef, Decimal128Array, DictionaryArray, Int32Array, RecordBatch, StringArray,
+        StructArray,
+    };
+    use arrow_schema::{DataType, Field, Fields, Schema};
     use datafusion::{physical_plan::common::collect, prelude::SessionContext};
     use tokio::sync::mpsc;
 
     use crate::execution::{
-        datafusion::planner::PhysicalPlanner,
-        operators::InputBatch,
+        datafusion::{
+            operators::partial_agg::{AdaptivePartialAggExec, PartialAggSkip},
+            planner::PhysicalPlanner,
+        },
+        operators::{CopyExec, InputBatch, ScanExec},
         spark_expression::{self, literal},
         spark_operator,
     };
@@ -1698,6 +2538,260 @@ mod tests {
         assert!(output.is_empty());
     }
 
+    #[test]
+    fn test_struct_sort_order() {
+        let fields = Fields::from(vec![Field::new("a", DataType::Int32, true)]);
+        // null, {a: null}, {a: 1}
+        let input: ArrayRef = Arc::new(StructArray::new(
+            fields.clone(),
+            vec![Arc::new(Int32Array::from(vec![Some(1), None, Some(1)]))],
+            Some(NullBuffer::from(vec![false, true, true])),
+        ));
+        let schema = Arc::new(Schema::new(vec![Field::new(
+            "s",
+            DataType::Struct(fields),
+            true,
+        )]));
+        let batch = RecordBatch::try_new(schema.clone(), vec![input]).unwrap();
+
+        // Returns the row indices sorted by the sort expressions of the given Spark `SortOrder`
+        let sort = |direction: i32, null_ordering: i32| {
+            let mut child = spark_expression::Expr::default();
+            child.expr_struct = Some(Bound(spark_expression::BoundReference {
+                index: 0,
+                datatype: None,
+            }));
+            let mut sort_order = spark_expression::Expr::default();
+            sort_order.expr_struct = Some(SortOrder(Box::new(spark_expression::SortOrder {
+                child: Some(Box::new(child)),
+                direction,
+                null_ordering,
+            })));
+
+            let exprs = PhysicalPlanner::default()
+                .create_sort_exprs(&sort_order, schema.clone())
+                .unwrap();
+            let columns = exprs
+                .iter()
+                .map(|e| e.evaluate_to_sort_column(&batch).unwrap())
+                .collect::<Vec<_>>();
+            let indices = lexsort_to_indices(&columns, None).unwrap();
+            (exprs.len(), indices.values().to_vec())
+        };
+
+        // (direction, null ordering): 0 is ascending and nulls first
+        assert_eq!(sort(0, 0), (1, vec![0, 1, 2]));
+        assert_eq!(sort(0, 1), (2, vec![1, 2, 0]));
+        assert_eq!(sort(1, 0), (2, vec![0, 2, 1]));
+        assert_eq!(sort(1, 1), (1, vec![2, 1, 0]));
+    }
+
+    #[test]
+    fn test_decimal_arithmetic_without_return_type() {
+        let schema = Arc::new(Schema::new(vec![Field::new(
+            "d",
+            DataType::Decimal128(10, 2),
+            true,
+        )]));
+        let input: ArrayRef = Arc::new(
+            Decimal128Array::from(vec![Some(123), None, Some(-9999999999)])
+                .with_data_type(DataType::Decimal128(10, 2)),
+        );
+        let batch = RecordBatch::try_new(schema.clone(), vec![input]).unwrap();
+
+        let mut column = spark_expression::Expr::default();
+        column.expr_struct = Some(Bound(spark_expression::BoundReference {
+            index: 0,
+            datatype: None,
+        }));
+        let mut five = spark_expression::Expr::default();
+        five.expr_struct = Some(Literal(spark_expression::Literal {
+            value: Some(literal::Value::IntVal(5)),
+            datatype: Some(spark_expression::DataType {
+                type_id: 3,
+                type_info: None,
+            }),
+            is_null: false,
+        }));
+        let mut add = spark_expression::Expr::default();
+        add.expr_struct = Some(Add(Box::new(spark_expression::Add {
+            left: Some(Box::new(column)),
+            right: Some(Box::new(five)),
+            fail_on_error: false,
+            return_type: None,
+            allow_precision_loss: true,
+        })));
+
+        // Like Spark, the literal is widened to Decimal(1, 0), so the sum is Decimal(11, 2)
+        let expr = PhysicalPlanner::default()
+            .create_expr(&add, schema.clone())
+            .unwrap();
+        assert_eq!(
+            expr.data_type(&schema).unwrap(),
+            DataType::Decimal128(11, 2)
+        );
+        let result = expr.evaluate(&batch).unwrap().into_array(3).unwrap();
+        let expected: ArrayRef = Arc::new(
+            Decimal128Array::from(vec![Some(623), None, Some(-9999999499)])
+                .with_data_type(DataType::Decimal128(11, 2)),
+        );
+        assert_eq!(&result, &expected);
+    }
+
+    #[test]
+    fn test_copy_input_of_adaptive_partial_agg() {
+        let op_scan = Operator {
+            children: vec![],
+            op_struct: Some(OpStruct::Scan(spark_operator::Scan {
+                fields: vec![spark_expression::DataType {
+                    type_id: 3, // Int32
+                    type_info: None,
+                }],
+            })),
+        };
+        let mut key = spark_expression::Expr::default();
+        key.expr_struct = Some(Bound(spark_expression::BoundReference {
+            index: 0,
+            datatype: Some(spark_expression::DataType {
+                type_id: 3,
+                type_info: None,
+            }),
+        }));
+        let op = Operator {
+            children: vec![op_scan],
+            op_struct: Some(OpStruct::HashAgg(spark_operator::HashAggregate {
+                grouping_exprs: vec![key],
+                agg_exprs: vec![],
+                result_exprs: vec![],
+                mode: 0, // Partial
+            })),
+        };
+
+        let planner = PhysicalPlanner::default().with_partial_agg_skip(Some(PartialAggSkip {
+            probe_rows: 100,
+            ratio_threshold: 0.5,
+        }));
+        let (_, datafusion_plan) = planner.create_plan(&op, &mut vec![]).unwrap();
+
+        // The probed batches are buffered, so the arrays reused by the scan are copied first
+        assert!(datafusion_plan
+            .as_any()
+            .downcast_ref::<AdaptivePartialAggExec>()
+            .is_some());
+        let input = datafusion_plan.children()[0].clone();
+        let copy = input.as_any().downcast_ref::<CopyExec>().unwrap();
+        assert!(copy.input().as_any().downcast_ref::<ScanExec>().is_some());
+    }
+
+    #[test]
+    fn test_local_table_scan() {
+        let schema = Arc::new(Schema::new(vec![Field::new("a", DataType::Int32, true)]));
+        let batch = RecordBatch::try_new(
+            schema.clone(),
+            vec![Arc::new(Int32Array::from(vec![
+                Some(1),
+                Some(3),
+                None,
+                Some(3),
+            ]))],
+        )
+        .unwrap();
+        let mut writer = StreamWriter::try_new(vec![], &schema).unwrap();
+        writer.write(&batch).unwrap();
+        writer.write(&batch).unwrap();
+        let data = writer.into_inner().unwrap();
+
+        let op_scan = Operator {
+            children: vec![],
+            op_struct: Some(OpStruct::LocalTableScan(spark_operator::LocalTableScan {
+                fields: vec![spark_expression::DataType {
+                    type_id: 3, // Int32
+                    type_info: None,
+                }],
+                data,
+            })),
+        };
+        let op = create_filter(op_scan, 3);
+
+        // The local scan consumes no input source
+        let (scans, datafusion_plan) = PhysicalPlanner::default()
+            .create_plan(&op, &mut vec![])
+            .unwrap();
+        assert!(scans.is_empty());
+
+        let task_ctx = SessionContext::new().task_ctx();
+        let stream = datafusion_plan.execute(0, task_ctx).unwrap();
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let batches = runtime.block_on(collect(stream)).unwrap();
+        assert_eq!(batches.iter().map(|b| b.num_rows()).sum::<usize>(), 4);
+        assert_eq!(batches[0].schema().field(0).name(), "col_0");
+    }
+
+    #[test]
+    fn test_file_scan() {
+        use std::io::Write;
+
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(b"x,1\ny,3\nz,\nw,3\n").unwrap();
+        let split = |start, length| spark_operator::FileSplit {
+            path: file.path().to_str().unwrap().to_string(),
+            start,
+            length,
+        };
+        let op_scan = Operator {
+            children: vec![],
+            op_struct: Some(OpStruct::FileScan(spark_operator::FileScan {
+                format: "csv".to_string(),
+                names: vec!["s".to_string(), "i".to_string()],
+                fields: vec![
+                    spark_expression::DataType {
+                        type_id: 7, // String
+                        type_info: None,
+                    },
+                    spark_expression::DataType {
+                        type_id: 3, // Int32
+                        type_info: None,
+                    },
+                ],
+                projection: vec![1],
+                // The second partition reads the last two lines
+                partitions: vec![
+                    spark_operator::FilePartition {
+                        files: vec![split(0, 6)],
+                    },
+                    spark_operator::FilePartition {
+                        files: vec![split(6, 10)],
+                    },
+                ],
+                filters: vec![],
+                options: Default::default(),
+            })),
+        };
+        let op = create_filter(op_scan, 3);
+
+        // The file scan consumes no input source
+        let (scans, datafusion_plan) = PhysicalPlanner::default()
+            .with_partition_index(1)
+            .create_plan(&op, &mut vec![])
+            .unwrap();
+        assert!(scans.is_empty());
+
+        let task_ctx = SessionContext::new().task_ctx();
+        let stream = datafusion_plan.execute(0, task_ctx).unwrap();
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let batches = runtime.block_on(collect(stream)).unwrap();
+        assert_eq!(batches.iter().map(|b| b.num_rows()).sum::<usize>(), 1);
+
+        // Unknown formats are rejected
+        let mut op = op;
+        if let Some(OpStruct::FileScan(scan)) = &mut op.children[0].op_struct {
+            scan.format = "unknown".to_string();
+        }
+        assert!(PhysicalPlanner::default()
+            .create_plan(&op, &mut vec![])
+            .is_err());
+    }
+
     // Creates a filter operator which takes an `Int32Array` and selects rows that are equal to
     // `value`.
     fn create_filter(child_op: spark_operator::Operator, value: i32) -> spark_operator::Operator {
@@ -26,9 +26,14 @@ use std::{
     path::Path,
     sync::Arc,
     task::{Context, Poll},
+    time::{Duration, Instant},
 };
 
-use arrow::{datatypes::*, ipc::writer::StreamWriter};
+use arrow::{
+    compute::{concat_batches, interleave},
+    datatypes::*,
+    ipc::writer::StreamWriter,
+};
 use async_trait::async_trait;
 use bytes::Buf;
 use crc32fast::Hasher;
@@ -42,12 +47,12 @@ use datafusion::{
     error::{DataFusionError, Result},
     execution::{
         context::TaskContext,
-        disk_manager::RefCountedTempFile,
-        memory_pool::{MemoryConsumer, MemoryReservation},
-        runtime_env::RuntimeEnv,
+        memory_pool::{MemoryConsumer, MemoryPool, MemoryReservation},
     },
     physical_plan::{
-        metrics::{BaselineMetrics, Count, ExecutionPlanMetricsSet, MetricBuilder, MetricsSet},
+        metrics::{
+            BaselineMetrics, Count, ExecutionPlanMetricsSet, MetricBuilder, MetricsSet, Time,
+        },
         stream::RecordBatchStreamAdapter,
         DisplayAs, DisplayFormatType, ExecutionMode, ExecutionPlan, Partitioning, PlanProperties,
         RecordBatchStream, SendableRecordBatchStream, Statistics,
@@ -57,14 +62,72 @@ use datafusion_physical_expr::EquivalenceProperties;
 use futures::{lock::Mutex, Stream, StreamExt, TryFutureExt, TryStreamExt};
 use itertools::Itertools;
 use simd_adler32::Adler32;
-use tokio::task;
 
 use crate::{
     common::bit::ceil,
     errors::{CometError, CometResult},
-    execution::datafusion::spark_hash::{create_hashes, pmod},
+    execution::{
+        config::{CompressionCodec, DEFAULT_ZSTD_LEVEL},
+        datafusion::spark_hash::{
+            compute_partitioning_hashes, hashes_to_partition_ids_with_counts, pmod,
+        },
+        runtime::{spawn_blocking_io, ResourceLimits},
+        shuffle::{
+            buffer_pool::BufferPool,
+            codec::CompressionWriter,
+            combiner::{combine_stream, ShuffleCombine, ShuffleCombiner},
+            crypto::{EncryptedBlockSink, EncryptionKey},
+            dictionary::consolidate_dictionaries,
+        },
+        spill::{SpillFile, SpillManager},
+    },
 };
 
+/// The maximum size of frozen buffers pooled by a shuffle writer to be reused after spilling.
+const MAX_POOLED_BYTES: usize = 64 * 1024 * 1024;
+
+/// The name of the column of partitioning hashes attached to the batches written by a hash
+/// partitioning shuffle writer. See [`ShuffleWriterExec::with_partitioning_hashes`].
+pub const PARTITIONING_HASH_COLUMN: &str = "_comet_partitioning_hash";
+
+/// The metadata key of the partitioning hash column, whose value is the partitioning keys which
+/// the hashes are computed from.
+const PARTITIONING_KEYS_METADATA_KEY: &str = "comet.partitioning.keys";
+
+/// The memory pool of native shuffle writers, which is set as a session config extension to
+/// account the memory of shuffle writers separately from the other operators, e.g., against
+/// Spark task memory manager. Shuffle writers use the memory pool of the runtime if it is not set.
+pub struct ShuffleMemoryPool(pub Arc<dyn MemoryPool>);
+
+/// The destination of the output partitions of a shuffle writer. By default, they are written
+/// into the data and index files of the map output by [`LocalFileSink`]. Another sink can be set
+/// by [`ShuffleBlockSinkFactory`], e.g., to push the partitions to a remote shuffle service.
+///
+/// The bytes of a partition, which are concatenated compressed Arrow IPC streams, are written
+/// after [`ShuffleBlockSink::start_partition`] is called with its id. The partitions are started
+/// in ascending order of their ids, and the partitions without rows may not be started at all.
+///
+/// A sink is written from the thread of the Spark task running the native plan, unless
+/// [`ShuffleBlockSink::is_local_io`] returns true, in which case the output partitions may be
+/// written from a thread of the IO runtime.
+pub trait ShuffleBlockSink: Write + Send {
+    /// Starts the block of the given partition, which ends the block of the previous one.
+    fn start_partition(&mut self, partition_id: usize) -> Result<()>;
+
+    /// Ends the block of the last partition and completes the output.
+    fn finish(&mut self) -> Result<()>;
+
+    /// Whether the sink only does local disk IO, so that it can be written from the IO runtime.
+    /// Sinks calling back into the JVM are not, as they expect the thread of the Spark task.
+    fn is_local_io(&self) -> bool {
+        false
+    }
+}
+
+/// Creates the sink of each native shuffle writer, which is set as a session config extension to
+/// write the output partitions somewhere other than the local files of the map output.
+pub struct ShuffleBlockSinkFactory(pub Arc<dyn Fn() -> Box<dyn ShuffleBlockSink> + Send + Sync>);
+
 /// The shuffle writer operator maps each input partition to M output partitions based on a
 /// partitioning scheme. No guarantees are made about the order of the resulting partitions.
 #[derive(Debug)]
@@ -77,6 +140,20 @@ pub struct ShuffleWriterExec {
     output_data_file: String,
     /// Output index file path
     output_index_file: String,
+    /// Whether to attach the partitioning hashes to the written batches
+    attach_partitioning_hashes: bool,
+    /// The position of round-robin partitioning before the first row
+    round_robin_start: i32,
+    /// The codec to compress the written batches
+    codec: CompressionCodec,
+    /// Whether to buffer the input rows and sort them by partition id when spilling
+    sort_based: bool,
+    /// Output checksum file path and algorithm, if the checksums of the partitions are computed
+    checksums: Option<(String, i32)>,
+    /// The key to encrypt the output partitions with, if IO encryption is enabled
+    encryption_key: Option<EncryptionKey>,
+    /// How to combine the input rows of a partial aggregation, if they are combined
+    combine: Option<ShuffleCombine>,
     /// Metrics
     metrics: ExecutionPlanMetricsSet,
     cache: PlanProperties,
@@ -113,12 +190,21 @@ impl ExecutionPlan for ShuffleWriterExec {
         children: Vec<Arc<dyn ExecutionPlan>>,
     ) -> Result<Arc<dyn ExecutionPlan>> {
         match children.len() {
-            1 => Ok(Arc::new(ShuffleWriterExec::try_new(
-                children[0].clone(),
-                self.partitioning.clone(),
-                self.output_data_file.clone(),
-                self.output_index_file.clone(),
-            )?)),
+            1 => Ok(Arc::new(
+                ShuffleWriterExec::try_new(
+                    children[0].clone(),
+                    self.partitioning.clone(),
+                    self.output_data_file.clone(),
+                    self.output_index_file.clone(),
+                )?
+                .with_partitioning_hashes(self.attach_partitioning_hashes)
+                .with_round_robin_start(self.round_robin_start)
+                .with_codec(self.codec)
+                .with_sort_based(self.sort_based)
+                .with_checksums(self.checksums.clone())
+                .with_encryption_key(self.encryption_key.clone())
+                .with_combine(self.combine.clone()),
+            )),
             _ => panic!("ShuffleWriterExec wrong number of children"),
         }
     }
@@ -140,6 +226,13 @@ impl ExecutionPlan for ShuffleWriterExec {
                     self.output_data_file.clone(),
                     self.output_index_file.clone(),
                     self.partitioning.clone(),
+                    self.attach_partitioning_hashes,
+                    self.round_robin_start,
+                    self.codec,
+                    self.sort_based,
+                    self.checksums.clone(),
+                    self.encryption_key.clone(),
+                    self.combine.clone(),
                     metrics,
                     context,
                 )
@@ -182,9 +275,115 @@ impl ShuffleWriterExec {
             metrics: ExecutionPlanMetricsSet::new(),
             output_data_file,
             output_index_file,
+            attach_partitioning_hashes: false,
+            round_robin_start: 0,
+            codec: CompressionCodec::Zstd(DEFAULT_ZSTD_LEVEL),
+            sort_based: false,
+            checksums: None,
+            encryption_key: None,
+            combine: None,
             cache,
         })
     }
+
+    /// Attaches the Spark murmur3 hashes of the partitioning keys, which are computed anyway to
+    /// partition the rows, as the last column [`PARTITIONING_HASH_COLUMN`] of the written batches.
+    /// It is only effective for hash partitioning.
+    ///
+    /// An operator reading the shuffled batches can then take the hashes of the same keys with
+    /// [`attached_partitioning_hashes`] instead of hashing the keys again, e.g., a shuffle writer
+    /// repartitioning the batches by the same keys reuses them.
+    pub fn with_partitioning_hashes(mut self, attach: bool) -> Self {
+        self.attach_partitioning_hashes = attach;
+        self
+    }
+
+    /// Sets the position of round-robin partitioning before the first row, i.e., the first row
+    /// goes to the partition next to `start`. Like Spark, each map task should start from a
+    /// different position, so that the rows of small map tasks do not all go to partition 0.
+    /// It is only effective for round-robin partitioning.
+    pub fn with_round_robin_start(mut self, start: i32) -> Self {
+        self.round_robin_start = start;
+        self
+    }
+
+    /// Sets the codec to compress the written batches, which must be the one the reducers
+    /// decompress them with. Defaults to zstd.
+    pub fn with_codec(mut self, codec: CompressionCodec) -> Self {
+        self.codec = codec;
+        self
+    }
+
+    /// Sets whether to write the shuffle data like Spark's `UnsafeShuffleWriter`. Instead of
+    /// copying the rows into the buffers of their partitions, the input batches are buffered as
+    /// they are with the partition ids of their rows. When the memory pool cannot grant more
+    /// memory, the buffered rows are sorted by partition id and spilled as a sorted run, and the
+    /// runs are merged partition by partition into the output data file at the end.
+    ///
+    /// The input batches must not be reused by the input operator once they are returned, since
+    /// they are held until spilled. Defaults to false.
+    pub fn with_sort_based(mut self, sort_based: bool) -> Self {
+        self.sort_based = sort_based;
+        self
+    }
+
+    /// Sets the file to write the checksums of the output partitions to, and the algorithm of
+    /// the checksums, i.e., 0 for CRC32 and 1 for Adler32. Like Spark's shuffle writers, the
+    /// checksum of each partition is computed over its bytes in the data file, and the file has
+    /// a big-endian long for each partition, so that it can be committed as the checksum file
+    /// of the map output. Defaults to no checksums.
+    pub fn with_checksums(mut self, checksums: Option<(String, i32)>) -> Self {
+        self.checksums = checksums;
+        self
+    }
+
+    /// Sets the AES key to encrypt the output partitions with, like Spark's shuffle writers do
+    /// when `spark.io.encryption.enabled` is true. See [`EncryptedBlockSink`] for the format. The
+    /// checksums are computed over the encrypted bytes. Defaults to no encryption.
+    pub fn with_encryption_key(mut self, key: Option<EncryptionKey>) -> Self {
+        self.encryption_key = key;
+        self
+    }
+
+    /// Sets how to combine the rows of the same grouping keys before partitioning them, if the
+    /// input is the output of a partial aggregation. See [`ShuffleCombine`]. Defaults to writing
+    /// the input rows as they are.
+    pub fn with_combine(mut self, combine: Option<ShuffleCombine>) -> Self {
+        self.combine = combine;
+        self
+    }
+}
+
+/// The partitioning keys identifying the attached partitioning hashes.
+fn partitioning_keys(partitioning: &Partitioning) -> Option<String> {
+    match partitioning {
+        Partitioning::Hash(exprs, _) => Some(exprs.iter().map(|expr| expr.to_string()).join(", ")),
+        _ => None,
+    }
+}
+
+/// The field of the partitioning hash column attached for the partitioning `keys`.
+fn partitioning_hash_field(keys: &str) -> Field {
+    Field::new(PARTITIONING_HASH_COLUMN, DataType::Int32, false).with_metadata(
+        [(PARTITIONING_KEYS_METADATA_KEY.to_string(), keys.to_string())]
+            .into_iter()
+            .collect(),
+    )
+}
+
+/// Returns the partitioning hashes attached to `batch` by a shuffle writer, if they are the hashes
+/// of the same `partitioning`.
+pub fn attached_partitioning_hashes<'a>(
+    batch: &'a RecordBatch,
+    partitioning: &Partitioning,
+) -> Option<&'a Int32Array> {
+    let keys = partitioning_keys(partitioning)?;
+    let schema = batch.schema();
+    let (index, field) = schema.column_with_name(PARTITIONING_HASH_COLUMN)?;
+    if field.metadata().get(PARTITIONING_KEYS_METADATA_KEY) != Some(&keys) {
+        return None;
+    }
+    batch.column(index).as_any().downcast_ref::<Int32Array>()
 }
 
 struct PartitionBuffer {
@@ -192,6 +391,9 @@ struct PartitionBuffer {
     schema: SchemaRef,
     /// The "frozen" Arrow IPC bytes of active data. They are frozen when `flush` is called.
     frozen: Vec<u8>,
+    /// The size the frozen bytes reached before they were last taken, e.g., to be spilled, which
+    /// is the expected size of the next frozen bytes. 0 if they have never been taken.
+    expected_frozen_size: usize,
     /// Array builders for appending rows into buffering batches.
     active: Vec<Box<dyn ArrayBuilder>>,
     /// The estimation of memory size of active builders in bytes when they are filled.
@@ -201,17 +403,33 @@ struct PartitionBuffer {
     /// The maximum number of rows in a batch. Once `num_active_rows` reaches `batch_size`,
     /// the active array builders will be frozen and appended to frozen buffer `frozen`.
     batch_size: usize,
+    /// The pool shared by all partitions to reuse frozen buffers.
+    buffer_pool: Arc<parking_lot::Mutex<BufferPool>>,
+    /// The codec to compress the frozen batches
+    codec: CompressionCodec,
+    /// The metrics of the time spent in freezing batches
+    ipc_metrics: IpcWriteMetrics,
 }
 
 impl PartitionBuffer {
-    fn new(schema: SchemaRef, batch_size: usize) -> Self {
+    fn new(
+        schema: SchemaRef,
+        batch_size: usize,
+        buffer_pool: Arc<parking_lot::Mutex<BufferPool>>,
+        codec: CompressionCodec,
+        ipc_metrics: IpcWriteMetrics,
+    ) -> Self {
         Self {
             schema,
             frozen: vec![],
+            expected_frozen_size: 0,
             active: vec![],
             active_slots_mem_size: 0,
             num_active_rows: 0,
             batch_size,
+            buffer_pool,
+            codec,
+            ipc_metrics,
         }
     }
 
@@ -282,14 +500,26 @@ impl PartitionBuffer {
 
         let frozen_batch = make_batch(self.schema.clone(), active, num_rows)?;
 
+        // Reuse a pooled buffer of the size class of the expected frozen bytes instead of growing
+        // a new one from scratch, so that small partitions don't take the large buffers of other
+        // partitions. The pooled buffer is already accounted, so only its growth is counted below.
+        if self.frozen.capacity() == 0 && self.expected_frozen_size > 0 {
+            self.frozen = self.buffer_pool.lock().take(self.expected_frozen_size);
+        }
         let frozen_capacity_old = self.frozen.capacity();
         let mut cursor = Cursor::new(&mut self.frozen);
         cursor.seek(SeekFrom::End(0))?;
-        write_ipc_compressed(&frozen_batch, &mut cursor)?;
+        write_ipc_compressed_timed(&frozen_batch, &mut cursor, &self.codec, &self.ipc_metrics)?;
 
         mem_diff += (self.frozen.capacity() - frozen_capacity_old) as isize;
         Ok(mem_diff)
     }
+
+    /// Takes the frozen bytes, remembering their size as the expected size of the next ones.
+    fn take_frozen(&mut self) -> Vec<u8> {
+        self.expected_frozen_size = self.frozen.len();
+        std::mem::take(&mut self.frozen)
+    }
 }
 
 fn slot_size(len: usize, data_type: &DataType) -> usize {
@@ -554,13 +784,14 @@ fn append_columns(
 }
 
 struct SpillInfo {
-    file: RefCountedTempFile,
+    file: SpillFile,
     offsets: Vec<u64>,
 }
 
 struct ShuffleRepartitioner {
-    output_data_file: String,
-    output_index_file: String,
+    /// The sink of the output partitions, which is moved to the IO runtime to write them if it
+    /// only does local disk IO
+    sink: Option<Box<dyn ShuffleBlockSink>>,
     schema: SchemaRef,
     buffered_partitions: Mutex<Vec<PartitionBuffer>>,
     spills: Mutex<Vec<SpillInfo>>,
@@ -568,13 +799,29 @@ struct ShuffleRepartitioner {
     /// Partitioning scheme to use
     partitioning: Partitioning,
     num_output_partitions: usize,
-    runtime: Arc<RuntimeEnv>,
+    spill_manager: Arc<SpillManager>,
+    /// Caps the spills in flight of all the plans of the resource profile
+    resource_limits: Arc<ResourceLimits>,
     metrics: ShuffleRepartitionerMetrics,
     reservation: MemoryReservation,
+    /// Whether to attach the partitioning hashes as the last column of the written batches
+    attach_partitioning_hashes: bool,
+    /// The position of round-robin partitioning, i.e., the previous row went to the partition
+    /// of this position
+    round_robin_position: i32,
     /// Hashes for each row in the current batch
     hashes_buf: Vec<u32>,
     /// Partition ids for each row in the current batch
     partition_ids: Vec<u64>,
+    /// Frozen buffers of partitions reused after they are written out. The pooled buffers are
+    /// accounted in `reservation`.
+    buffer_pool: Arc<parking_lot::Mutex<BufferPool>>,
+    /// Whether to buffer the input batches in `buffered_batches` instead of `buffered_partitions`
+    sort_based: bool,
+    /// Input batches buffered by the sort-based writer, with the partition id of each row
+    buffered_batches: Mutex<Vec<(RecordBatch, Vec<u32>)>>,
+    batch_size: usize,
+    codec: CompressionCodec,
 }
 
 struct ShuffleRepartitionerMetrics {
@@ -586,6 +833,15 @@ struct ShuffleRepartitionerMetrics {
 
     /// total spilled bytes during the execution of the operator
     spilled_bytes: Count,
+
+    /// size of the shuffle data file written by the operator
+    data_size: Count,
+
+    /// time spent writing the shuffle data, index and spill files
+    write_time: Time,
+
+    /// time spent encoding and compressing batches as Arrow IPC bytes
+    ipc: IpcWriteMetrics,
 }
 
 impl ShuffleRepartitionerMetrics {
@@ -594,26 +850,47 @@ impl ShuffleRepartitionerMetrics {
             baseline: BaselineMetrics::new(metrics, partition),
             spill_count: MetricBuilder::new(metrics).spill_count(partition),
             spilled_bytes: MetricBuilder::new(metrics).spilled_bytes(partition),
+            data_size: MetricBuilder::new(metrics).counter("data_size", partition),
+            write_time: MetricBuilder::new(metrics).subset_time("write_time", partition),
+            ipc: IpcWriteMetrics {
+                encode_time: MetricBuilder::new(metrics).subset_time("encode_time", partition),
+                compress_time: MetricBuilder::new(metrics).subset_time("compress_time", partition),
+            },
         }
     }
 }
 
+/// The metrics of writing batches as compressed Arrow IPC bytes.
+#[derive(Clone)]
+struct IpcWriteMetrics {
+    /// time spent encoding batches as Arrow IPC bytes, excluding the compression
+    encode_time: Time,
+
+    /// time spent compressing the Arrow IPC bytes
+    compress_time: Time,
+}
+
 impl ShuffleRepartitioner {
     #[allow(clippy::too_many_arguments)]
     pub fn new(
         partition_id: usize,
-        output_data_file: String,
-        output_index_file: String,
+        sink: Box<dyn ShuffleBlockSink>,
         schema: SchemaRef,
         partitioning: Partitioning,
+        attach_partitioning_hashes: bool,
+        round_robin_start: i32,
+        codec: CompressionCodec,
+        sort_based: bool,
         metrics: ShuffleRepartitionerMetrics,
-        runtime: Arc<RuntimeEnv>,
+        memory_pool: &Arc<dyn MemoryPool>,
+        spill_manager: Arc<SpillManager>,
+        resource_limits: Arc<ResourceLimits>,
         batch_size: usize,
     ) -> Self {
         let num_output_partitions = partitioning.partition_count();
         let reservation = MemoryConsumer::new(format!("ShuffleRepartitioner[{}]", partition_id))
             .with_can_spill(true)
-            .register(&runtime.memory_pool);
+            .register(memory_pool);
 
         let mut hashes_buf = Vec::with_capacity(batch_size);
         let mut partition_ids = Vec::with_capacity(batch_size);
@@ -625,29 +902,46 @@ impl ShuffleRepartitioner {
             partition_ids.set_len(batch_size);
         }
 
+        let buffer_pool = Arc::new(parking_lot::Mutex::new(BufferPool::new(MAX_POOLED_BYTES)));
+
         Self {
-            output_data_file,
-            output_index_file,
+            sink: Some(sink),
             schema: schema.clone(),
             buffered_partitions: Mutex::new(
                 (0..num_output_partitions)
-                    .map(|_| PartitionBuffer::new(schema.clone(), batch_size))
+                    .map(|_| {
+                        PartitionBuffer::new(
+                            schema.clone(),
+                            batch_size,
+                            buffer_pool.clone(),
+                            codec,
+                            metrics.ipc.clone(),
+                        )
+                    })
                     .collect::<Vec<_>>(),
             ),
             spills: Mutex::new(vec![]),
             partitioning,
             num_output_partitions,
-            runtime,
+            spill_manager,
+            resource_limits,
             metrics,
             reservation,
+            attach_partitioning_hashes,
+            round_robin_position: round_robin_start,
             hashes_buf,
             partition_ids,
+            buffer_pool,
+            sort_based,
+            buffered_batches: Mutex::new(vec![]),
+            batch_size,
+            codec,
         }
     }
 
     /// Shuffles rows in input batch into corresponding partition buffer.
-    /// This function first calculates hashes for rows and then takes rows in same
-    /// partition as a record batch which is appended into partition buffer.
+    /// This function first calculates partition ids for rows, e.g., from their hashes, and then
+    /// takes rows in same partition as a record batch which is appended into partition buffer.
     async fn insert_batch(&mut self, input: RecordBatch) -> Result<()> {
         if input.num_rows() == 0 {
             // skip empty batch
@@ -662,85 +956,58 @@ impl ShuffleRepartitioner {
         let num_output_partitions = self.num_output_partitions;
         match &self.partitioning {
             Partitioning::Hash(exprs, _) => {
-                let arrays = exprs
-                    .iter()
-                    .map(|expr| expr.evaluate(&input)?.into_array(input.num_rows()))
-                    .collect::<Result<Vec<_>>>()?;
-
-                // use identical seed as spark hash partition
-                let hashes_buf = &mut self.hashes_buf[..arrays[0].len()];
-                hashes_buf.fill(42_u32);
-
-                // Hash arrays and compute buckets based on number of partitions
-                let partition_ids = &mut self.partition_ids[..arrays[0].len()];
-                create_hashes(&arrays, hashes_buf)?
-                    .iter()
-                    .enumerate()
-                    .for_each(|(idx, hash)| {
-                        partition_ids[idx] = pmod(*hash, num_output_partitions) as u64
-                    });
-
-                // count each partition size
-                let mut partition_counters = vec![0usize; num_output_partitions];
-                partition_ids
-                    .iter()
-                    .for_each(|partition_id| partition_counters[*partition_id as usize] += 1);
-
-                // accumulate partition counters into partition ends
-                // e.g. partition counter: [1, 3, 2, 1] => [1, 4, 6, 7]
-                let mut partition_ends = partition_counters;
-                let mut accum = 0;
-                partition_ends.iter_mut().for_each(|v| {
-                    *v += accum;
-                    accum = *v;
-                });
-
-                // calculate shuffled partition ids
-                // e.g. partition ids: [3, 1, 1, 1, 2, 2, 0] => [6, 1, 2, 3, 4, 5, 0] which is the
-                // row indices for rows ordered by their partition id. For example, first partition
-                // 0 has one row index [6], partition 1 has row indices [1, 2, 3], etc.
-                let mut shuffled_partition_ids = vec![0usize; input.num_rows()];
-                for (index, partition_id) in partition_ids.iter().enumerate().rev() {
-                    partition_ends[*partition_id as usize] -= 1;
-                    let end = partition_ends[*partition_id as usize];
-                    shuffled_partition_ids[end] = index;
+                let hashes_buf = &mut self.hashes_buf[..input.num_rows()];
+                // Reuses the hashes of the same keys attached by an upstream shuffle writer
+                if let Some(hashes) = attached_partitioning_hashes(&input, &self.partitioning) {
+                    hashes_buf
+                        .iter_mut()
+                        .zip(hashes.values().iter())
+                        .for_each(|(dst, hash)| *dst = *hash as u32);
+                } else {
+                    let arrays = exprs
+                        .iter()
+                        .map(|expr| expr.evaluate(&input)?.into_array(input.num_rows()))
+                        .collect::<Result<Vec<_>>>()?;
+                    compute_partitioning_hashes(&arrays, hashes_buf)?;
                 }
 
-                // after calculating, partition ends become partition starts
-                let mut partition_starts = partition_ends;
-                partition_starts.push(input.num_rows());
+                let mut columns = input.columns().to_vec();
+                if self.attach_partitioning_hashes {
+                    columns.push(Arc::new(Int32Array::from_iter_values(
+                        hashes_buf.iter().map(|hash| *hash as i32),
+                    )));
+                }
 
-                let mut mem_diff = 0;
-                // For each interval of row indices of partition, taking rows from input batch and
-                // appending into output buffer.
-                for (partition_id, (&start, &end)) in partition_starts
+                // Compute buckets based on number of partitions, counting each partition size in
+                // the same pass
+                let partition_ids = &mut self.partition_ids[..input.num_rows()];
+                let mut partition_counters = vec![0usize; num_output_partitions];
+                hashes_to_partition_ids_with_counts(
+                    hashes_buf,
+                    num_output_partitions,
+                    &mut partition_counters,
+                )?;
+                hashes_buf
                     .iter()
-                    .tuple_windows()
-                    .enumerate()
-                    .filter(|(_, (start, end))| start < end)
-                {
-                    let mut buffered_partitions = self.buffered_partitions.lock().await;
-                    let output = &mut buffered_partitions[partition_id];
-
-                    // If the range of indices is not big enough, just appending the rows into
-                    // active array builders instead of directly adding them as a record batch.
-                    mem_diff +=
-                        output.append_rows(input.columns(), &shuffled_partition_ids[start..end])?;
-                }
+                    .zip(partition_ids.iter_mut())
+                    .for_each(|(partition_id, dst)| *dst = *partition_id as u64);
 
-                if mem_diff > 0 {
-                    let mem_increase = mem_diff as usize;
-                    if self.reservation.try_grow(mem_increase).is_err() {
-                        self.spill().await?;
-                        self.reservation.free();
-                        self.reservation.try_grow(mem_increase)?;
-                    }
-                }
-                if mem_diff < 0 {
-                    let mem_used = self.reservation.size();
-                    let mem_decrease = mem_used.min(-mem_diff as usize);
-                    self.reservation.shrink(mem_decrease);
+                self.append_partitioned_rows(&columns, partition_counters)
+                    .await?;
+            }
+            Partitioning::RoundRobinBatch(_) => {
+                // Like Spark, each row goes to the partition next to the previous row's, starting
+                // from the partition next to the start position of this map task
+                let partition_ids = &mut self.partition_ids[..input.num_rows()];
+                let mut partition_counters = vec![0usize; num_output_partitions];
+                for partition_id in partition_ids.iter_mut() {
+                    self.round_robin_position = self.round_robin_position.wrapping_add(1);
+                    *partition_id =
+                        pmod(self.round_robin_position as u32, num_output_partitions) as u64;
+                    partition_counters[*partition_id as usize] += 1;
                 }
+                self.append_partitioned_rows(input.columns(), partition_counters)
+                    .await?;
             }
             Partitioning::UnknownPartitioning(n) if *n == 1 => {
                 let mut buffered_partitions = self.buffered_partitions.lock().await;
@@ -766,6 +1033,110 @@ impl ShuffleRepartitioner {
         Ok(())
     }
 
+    /// Appends the rows of `columns` into the buffers of their partitions in `partition_ids`,
+    /// where `partition_counters` is the number of rows of each partition.
+    async fn append_partitioned_rows(
+        &mut self,
+        columns: &[ArrayRef],
+        partition_counters: Vec<usize>,
+    ) -> Result<()> {
+        if self.sort_based {
+            return self.buffer_partitioned_rows(columns).await;
+        }
+
+        let num_rows = columns[0].len();
+        let partition_ids = &self.partition_ids[..num_rows];
+
+        // accumulate partition counters into partition ends
+        // e.g. partition counter: [1, 3, 2, 1] => [1, 4, 6, 7]
+        let mut partition_ends = partition_counters;
+        let mut accum = 0;
+        partition_ends.iter_mut().for_each(|v| {
+            *v += accum;
+            accum = *v;
+        });
+
+        // calculate shuffled partition ids
+        // e.g. partition ids: [3, 1, 1, 1, 2, 2, 0] => [6, 1, 2, 3, 4, 5, 0] which is the
+        // row indices for rows ordered by their partition id. For example, first partition
+        // 0 has one row index [6], partition 1 has row indices [1, 2, 3], etc.
+        let mut shuffled_partition_ids = vec![0usize; num_rows];
+        for (index, partition_id) in partition_ids.iter().enumerate().rev() {
+            partition_ends[*partition_id as usize] -= 1;
+            let end = partition_ends[*partition_id as usize];
+            shuffled_partition_ids[end] = index;
+        }
+
+        // after calculating, partition ends become partition starts
+        let mut partition_starts = partition_ends;
+        partition_starts.push(num_rows);
+
+        let mut mem_diff = 0;
+        // For each interval of row indices of partition, taking rows from input batch and
+        // appending into output buffer.
+        for (partition_id, (&start, &end)) in partition_starts
+            .iter()
+            .tuple_windows()
+            .enumerate()
+            .filter(|(_, (start, end))| start < end)
+        {
+            let mut buffered_partitions = self.buffered_partitions.lock().await;
+            let output = &mut buffered_partitions[partition_id];
+
+            // If the range of indices is not big enough, just appending the rows into
+            // active array builders instead of directly adding them as a record batch.
+            mem_diff += output.append_rows(columns, &shuffled_partition_ids[start..end])?;
+        }
+
+        if mem_diff > 0 {
+            let mem_increase = mem_diff as usize;
+            if self.reservation.try_grow(mem_increase).is_err() {
+                self.spill().await?;
+                self.reservation.free();
+                // Keep the buffers returned to the pool by spilling if there is enough
+                // memory for them
+                let pooled_bytes = self.buffer_pool.lock().pooled_bytes();
+                if self
+                    .reservation
+                    .try_grow(mem_increase + pooled_bytes)
+                    .is_err()
+                {
+                    self.buffer_pool.lock().clear();
+                    self.reservation.try_grow(mem_increase)?;
+                }
+            }
+        }
+        if mem_diff < 0 {
+            let mem_used = self.reservation.size();
+            let mem_decrease = mem_used.min(-mem_diff as usize);
+            self.reservation.shrink(mem_decrease);
+        }
+        Ok(())
+    }
+
+    /// Buffers the rows of `columns` with their partition ids in `partition_ids`. If the memory
+    /// pool cannot grant the memory of them, the rows buffered so far are spilled first.
+    async fn buffer_partitioned_rows(&mut self, columns: &[ArrayRef]) -> Result<()> {
+        let num_rows = columns[0].len();
+        let batch = RecordBatch::try_new(self.schema.clone(), columns.to_vec())?;
+        let partition_ids = self.partition_ids[..num_rows]
+            .iter()
+            .map(|partition_id| *partition_id as u32)
+            .collect::<Vec<_>>();
+
+        let mem_size = batch.get_array_memory_size() + partition_ids.len() * 4;
+        if self.reservation.try_grow(mem_size).is_err() {
+            self.spill().await?;
+            self.reservation.free();
+            self.reservation.try_grow(mem_size)?;
+        }
+        self.buffered_batches
+            .lock()
+            .await
+            .push((batch, partition_ids));
+        Ok(())
+    }
+
     /// Writes buffered shuffled record batches into Arrow IPC bytes.
     async fn shuffle_write(&mut self) -> Result<SendableRecordBatchStream> {
         let _timer = self.metrics.baseline.elapsed_compute().timer();
@@ -775,59 +1146,87 @@ impl ShuffleRepartitioner {
 
         for i in 0..num_output_partitions {
             buffered_partitions[i].flush()?;
-            output_batches[i] = std::mem::take(&mut buffered_partitions[i].frozen);
+            output_batches[i] = buffered_partitions[i].take_frozen();
         }
 
+        // The rows buffered by the sort-based writer are sorted in memory as the last run
+        let sorted_run = if self.sort_based {
+            let buffered_batches = std::mem::take(&mut *self.buffered_batches.lock().await);
+            let mut bytes = Cursor::new(vec![]);
+            let offsets = write_sorted_partitions(
+                &buffered_batches,
+                &self.schema,
+                num_output_partitions,
+                self.batch_size,
+                &self.codec,
+                &self.metrics.ipc,
+                &mut bytes,
+            )?;
+            Some((bytes.into_inner(), offsets))
+        } else {
+            None
+        };
+
         let mut spills = self.spills.lock().await;
         let output_spills = spills.drain(..).collect::<Vec<_>>();
 
-        let data_file = self.output_data_file.clone();
-        let index_file = self.output_index_file.clone();
-
-        let mut offsets = vec![0; num_output_partitions + 1];
-        let mut output_data = OpenOptions::new()
-            .write(true)
-            .create(true)
-            .truncate(true)
-            .open(data_file)
-            .map_err(|e| DataFusionError::Execution(format!("shuffle write error: {:?}", e)))?;
-
-        for i in 0..num_output_partitions {
-            offsets[i] = output_data.stream_position()?;
-            output_data.write_all(&output_batches[i])?;
-            output_batches[i].clear();
+        let mut output_data = self.sink.take().ok_or_else(|| {
+            DataFusionError::Execution("shuffle output is already written".to_string())
+        })?;
+        let write_time = self.metrics.write_time.clone();
+
+        // The partitions are copied from the memory and the spill files to the output on the IO
+        // runtime like the spills, if the output is local files. Other sinks are written from the
+        // thread of the task, see `ShuffleBlockSink::is_local_io`
+        let local_io = output_data.is_local_io();
+        let write_output = move || -> Result<usize> {
+            let _timer = write_time.timer();
+            let mut data_size = 0;
+
+            for i in 0..num_output_partitions {
+                output_data.start_partition(i)?;
+                output_data.write_all(&output_batches[i])?;
+                data_size += output_batches[i].len();
+                output_batches[i].clear();
+                if let Some((bytes, run_offsets)) = &sorted_run {
+                    let run = &bytes[run_offsets[i] as usize..run_offsets[i + 1] as usize];
+                    output_data.write_all(run)?;
+                    data_size += run.len();
+                }
 
-            // append partition in each spills
-            for spill in &output_spills {
-                let length = spill.offsets[i + 1] - spill.offsets[i];
-                if length > 0 {
-                    let mut spill_file =
-                        BufReader::new(File::open(spill.file.path()).map_err(|e| {
-                            DataFusionError::Execution(format!("shuffle write error: {:?}", e))
-                        })?);
-                    spill_file.seek(SeekFrom::Start(spill.offsets[i]))?;
-                    std::io::copy(&mut spill_file.take(length), &mut output_data).map_err(|e| {
-                        DataFusionError::Execution(format!("shuffle write error: {:?}", e))
-                    })?;
+                // append partition in each spills
+                for spill in &output_spills {
+                    let length = spill.offsets[i + 1] - spill.offsets[i];
+                    if length > 0 {
+                        let mut spill_file =
+                            BufReader::new(File::open(spill.file.path()).map_err(|e| {
+                                DataFusionError::Execution(format!("shuffle write error: {:?}", e))
+                            })?);
+                        spill_file.seek(SeekFrom::Start(spill.offsets[i]))?;
+                        std::io::copy(&mut spill_file.take(length), &mut output_data).map_err(
+                            |e| DataFusionError::Execution(format!("shuffle write error: {:?}", e)),
+                        )?;
+                        data_size += length as usize;
+                    }
                 }
             }
-        }
-        output_data.flush()?;
-
-        // add one extra offset at last to ease partition length computation
-        offsets[num_output_partitions] = output_data
-            .stream_position()
-            .map_err(|e| DataFusionError::Execution(format!("shuffle write error: {:?}", e)))?;
-        let mut output_index =
-            BufWriter::new(File::create(index_file).map_err(|e| {
+            output_data.finish()?;
+            Ok(data_size)
+        };
+        let data_size = if local_io {
+            let _io_request = self.resource_limits.acquire_io().await;
+            spawn_blocking_io(write_output).await.map_err(|e| {
                 DataFusionError::Execution(format!("shuffle write error: {:?}", e))
-            })?);
-        for offset in offsets {
-            output_index
-                .write_all(&(offset as i64).to_le_bytes()[..])
-                .map_err(|e| DataFusionError::Execution(format!("shuffle write error: {:?}", e)))?;
-        }
-        output_index.flush()?;
+            })??
+        } else {
+            write_output()?
+        };
+
+        self.metrics.data_size.add(data_size);
+        #[cfg(feature = "prometheus")]
+        crate::execution::metrics::exporter::EXECUTOR_METRICS
+            .shuffle_written_bytes
+            .add(data_size);
 
         let used = self.reservation.size();
         self.reservation.shrink(used);
@@ -855,27 +1254,71 @@ impl ShuffleRepartitioner {
             self.spill_count()
         );
 
-        let mut buffered_partitions = self.buffered_partitions.lock().await;
-        // we could always get a chance to free some memory as long as we are holding some
-        if buffered_partitions.len() == 0 {
-            return Ok(0);
-        }
+        let _io_request = self.resource_limits.acquire_io().await;
+        let (spillfile, offsets) = if self.sort_based {
+            let buffered_batches = std::mem::take(&mut *self.buffered_batches.lock().await);
+            if buffered_batches.is_empty() {
+                return Ok(0);
+            }
 
-        let spillfile = self
-            .runtime
-            .disk_manager
-            .create_tmp_file("shuffle writer spill")?;
-        let offsets = spill_into(
-            &mut buffered_partitions,
-            spillfile.path(),
-            self.num_output_partitions,
-        )
-        .await?;
+            let mut spillfile = self
+                .spill_manager
+                .create_spill_file("shuffle writer spill")?;
+            // The disk space is reserved before writing with the memory size of the buffered
+            // data, which the spill file is usually smaller than as it's compressed
+            spillfile.record_size(self.used())?;
+            let write_timer = self.metrics.write_time.timer();
+            let offsets = spill_sorted_into(
+                buffered_batches,
+                self.schema.clone(),
+                spillfile.path(),
+                self.num_output_partitions,
+                self.batch_size,
+                self.codec,
+                self.metrics.ipc.clone(),
+            )
+            .await?;
+            write_timer.done();
+            spillfile.record_size(offsets[self.num_output_partitions] as usize)?;
+            (spillfile, offsets)
+        } else {
+            let mut buffered_partitions = self.buffered_partitions.lock().await;
+            // we could always get a chance to free some memory as long as we are holding some
+            if buffered_partitions.len() == 0 {
+                return Ok(0);
+            }
+
+            let mut spillfile = self
+                .spill_manager
+                .create_spill_file("shuffle writer spill")?;
+            spillfile.record_size(self.used())?;
+            let write_timer = self.metrics.write_time.timer();
+            let (offsets, buffers) = spill_into(
+                &mut buffered_partitions,
+                spillfile.path(),
+                self.num_output_partitions,
+            )
+            .await?;
+            write_timer.done();
+            spillfile.record_size(offsets[self.num_output_partitions] as usize)?;
+            let mut buffer_pool = self.buffer_pool.lock();
+            buffers
+                .into_iter()
+                .for_each(|buffer| buffer_pool.give(buffer));
+            drop(buffer_pool);
+            (spillfile, offsets)
+        };
 
         let mut spills = self.spills.lock().await;
         let used = self.reservation.size();
         self.metrics.spill_count.add(1);
         self.metrics.spilled_bytes.add(used);
+        #[cfg(feature = "prometheus")]
+        {
+            use crate::execution::metrics::exporter::EXECUTOR_METRICS;
+            EXECUTOR_METRICS.spill_count.add(1);
+            EXECUTOR_METRICS.spilled_bytes.add(used);
+        }
         spills.push(SpillInfo {
             file: spillfile,
             offsets,
@@ -884,21 +1327,23 @@ impl ShuffleRepartitioner {
     }
 }
 
-/// consume the `buffered_partitions` and do spill into a single temp shuffle output file
+/// consume the `buffered_partitions` and do spill into a single temp shuffle output file.
+/// Returns the offsets of partitions in the file, and the cleared buffers of the partitions to
+/// be reused.
 async fn spill_into(
     buffered_partitions: &mut [PartitionBuffer],
     path: &Path,
     num_output_partitions: usize,
-) -> Result<Vec<u64>> {
+) -> Result<(Vec<u64>, Vec<Vec<u8>>)> {
     let mut output_batches: Vec<Vec<u8>> = vec![vec![]; num_output_partitions];
 
     for i in 0..num_output_partitions {
         buffered_partitions[i].flush()?;
-        output_batches[i] = std::mem::take(&mut buffered_partitions[i].frozen);
+        output_batches[i] = buffered_partitions[i].take_frozen();
     }
     let path = path.to_owned();
 
-    task::spawn_blocking(move || {
+    spawn_blocking_io(move || {
         let mut offsets = vec![0; num_output_partitions + 1];
         let mut spill_data = OpenOptions::new()
             .write(true)
@@ -913,12 +1358,109 @@ async fn spill_into(
         }
         // add one extra offset at last to ease partition length computation
         offsets[num_output_partitions] = spill_data.stream_position()?;
+        Ok((offsets, output_batches))
+    })
+    .await
+    .map_err(|e| DataFusionError::Execution(format!("Error occurred while spilling {}", e)))?
+}
+
+/// Sorts the rows of `buffered_batches` by partition id and spills them into a single temp
+/// shuffle output file. Returns the offsets of partitions in the file.
+async fn spill_sorted_into(
+    buffered_batches: Vec<(RecordBatch, Vec<u32>)>,
+    schema: SchemaRef,
+    path: &Path,
+    num_output_partitions: usize,
+    batch_size: usize,
+    codec: CompressionCodec,
+    ipc_metrics: IpcWriteMetrics,
+) -> Result<Vec<u64>> {
+    let path = path.to_owned();
+
+    spawn_blocking_io(move || {
+        let mut spill_data = BufWriter::new(
+            OpenOptions::new()
+                .write(true)
+                .create(true)
+                .truncate(true)
+                .open(path)?,
+        );
+        let offsets = write_sorted_partitions(
+            &buffered_batches,
+            &schema,
+            num_output_partitions,
+            batch_size,
+            &codec,
+            &ipc_metrics,
+            &mut spill_data,
+        )?;
+        spill_data.flush()?;
         Ok(offsets)
     })
     .await
     .map_err(|e| DataFusionError::Execution(format!("Error occurred while spilling {}", e)))?
 }
 
+/// Writes the rows of `batches` ordered by their partition ids into `output`, in batches of at
+/// most `batch_size` rows. Returns the offsets of partitions in `output`, with one extra offset
+/// at last to ease partition length computation.
+fn write_sorted_partitions<W: Write + Seek>(
+    batches: &[(RecordBatch, Vec<u32>)],
+    schema: &SchemaRef,
+    num_output_partitions: usize,
+    batch_size: usize,
+    codec: &CompressionCodec,
+    ipc_metrics: &IpcWriteMetrics,
+    output: &mut W,
+) -> Result<Vec<u64>> {
+    // Like the radix sort of Spark's `ShuffleInMemorySorter`, the (batch index, row index) pairs
+    // are sorted by counting the rows of each partition, which keeps the input order of the
+    // rows in the same partition
+    let mut partition_starts = vec![0usize; num_output_partitions + 1];
+    for (_, partition_ids) in batches {
+        for partition_id in partition_ids {
+            partition_starts[*partition_id as usize + 1] += 1;
+        }
+    }
+    for i in 0..num_output_partitions {
+        partition_starts[i + 1] += partition_starts[i];
+    }
+    let mut positions = partition_starts.clone();
+    let mut sorted_indices = vec![(0, 0); partition_starts[num_output_partitions]];
+    for (batch_index, (_, partition_ids)) in batches.iter().enumerate() {
+        for (row_index, partition_id) in partition_ids.iter().enumerate() {
+            let position = &mut positions[*partition_id as usize];
+            sorted_indices[*position] = (batch_index, row_index);
+            *position += 1;
+        }
+    }
+
+    let columns = (0..schema.fields().len())
+        .map(|i| {
+            batches
+                .iter()
+                .map(|(batch, _)| batch.column(i).as_ref())
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    let mut offsets = vec![0; num_output_partitions + 1];
+    for (partition_id, (&start, &end)) in partition_starts.iter().tuple_windows().enumerate() {
+        offsets[partition_id] = output.stream_position()?;
+        for indices in sorted_indices[start..end].chunks(batch_size) {
+            let arrays = columns
+                .iter()
+                .map(|arrays| interleave(arrays, indices))
+                .collect::<ArrowResult<Vec<_>>>()?;
+            // The interleaved dictionaries keep the values of all the buffered batches
+            let batch = consolidate_dictionaries(RecordBatch::try_new(schema.clone(), arrays)?)?;
+            write_ipc_compressed_timed(&batch, output, codec, ipc_metrics)?;
+        }
+    }
+    offsets[num_output_partitions] = output.stream_position()?;
+    Ok(offsets)
+}
+
 impl Debug for ShuffleRepartitioner {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         f.debug_struct("ShuffleRepartitioner")
@@ -929,24 +1471,206 @@ impl Debug for ShuffleRepartitioner {
     }
 }
 
+/// The shuffle writer of a single output partition. Unlike [`ShuffleRepartitioner`], it doesn't
+/// compute partition ids or copy rows into partition buffers, but writes the input batches into
+/// the output data file as they come. Small batches are coalesced before being written, so that
+/// the reader doesn't get too many tiny batches.
+struct SinglePartitionShuffleWriter {
+    sink: Box<dyn ShuffleBlockSink>,
+    schema: SchemaRef,
+    /// Batches not written yet, which have less than `batch_size` rows in total
+    buffered_batches: Vec<RecordBatch>,
+    num_buffered_rows: usize,
+    batch_size: usize,
+    /// Reused buffer of the Arrow IPC bytes of a batch
+    ipc_buffer: Vec<u8>,
+    codec: CompressionCodec,
+    metrics: ShuffleRepartitionerMetrics,
+}
+
+impl SinglePartitionShuffleWriter {
+    fn try_new(
+        mut sink: Box<dyn ShuffleBlockSink>,
+        schema: SchemaRef,
+        codec: CompressionCodec,
+        metrics: ShuffleRepartitionerMetrics,
+        batch_size: usize,
+    ) -> Result<Self> {
+        sink.start_partition(0)?;
+
+        Ok(Self {
+            sink,
+            schema,
+            buffered_batches: vec![],
+            num_buffered_rows: 0,
+            batch_size,
+            ipc_buffer: vec![],
+            codec,
+            metrics,
+        })
+    }
+
+    fn insert_batch(&mut self, batch: RecordBatch) -> Result<()> {
+        if batch.num_rows() == 0 {
+            return Ok(());
+        }
+        let _timer = self.metrics.baseline.elapsed_compute().timer();
+        self.metrics.baseline.record_output(batch.num_rows());
+
+        if self.buffered_batches.is_empty() && batch.num_rows() >= self.batch_size {
+            return self.write_batch(&batch);
+        }
+        self.num_buffered_rows += batch.num_rows();
+        self.buffered_batches.push(batch);
+        if self.num_buffered_rows >= self.batch_size {
+            self.write_buffered_batches()?;
+        }
+        Ok(())
+    }
+
+    fn write_buffered_batches(&mut self) -> Result<()> {
+        if self.buffered_batches.is_empty() {
+            return Ok(());
+        }
+        let batch =
+            consolidate_dictionaries(concat_batches(&self.schema, &self.buffered_batches)?)?;
+        self.buffered_batches.clear();
+        self.num_buffered_rows = 0;
+        self.write_batch(&batch)
+    }
+
+    fn write_batch(&mut self, batch: &RecordBatch) -> Result<()> {
+        self.ipc_buffer.clear();
+        write_ipc_compressed_timed(
+            batch,
+            &mut Cursor::new(&mut self.ipc_buffer),
+            &self.codec,
+            &self.metrics.ipc,
+        )?;
+
+        let _write_timer = self.metrics.write_time.timer();
+        self.sink
+            .write_all(&self.ipc_buffer)
+            .map_err(|e| DataFusionError::Execution(format!("shuffle write error: {:?}", e)))?;
+        self.metrics.data_size.add(self.ipc_buffer.len());
+        #[cfg(feature = "prometheus")]
+        crate::execution::metrics::exporter::EXECUTOR_METRICS
+            .shuffle_written_bytes
+            .add(self.ipc_buffer.len());
+        Ok(())
+    }
+
+    /// Writes the remaining batches and completes the output.
+    fn shuffle_write(&mut self) -> Result<SendableRecordBatchStream> {
+        {
+            let _timer = self.metrics.baseline.elapsed_compute().timer();
+            self.write_buffered_batches()?;
+        }
+
+        let write_timer = self.metrics.write_time.timer();
+        self.sink.finish()?;
+        write_timer.done();
+
+        // shuffle writer always has empty output
+        Ok(Box::pin(EmptyStream::try_new(self.schema.clone())?))
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 async fn external_shuffle(
     mut input: SendableRecordBatchStream,
     partition_id: usize,
     output_data_file: String,
     output_index_file: String,
     partitioning: Partitioning,
+    attach_partitioning_hashes: bool,
+    round_robin_start: i32,
+    codec: CompressionCodec,
+    sort_based: bool,
+    checksums: Option<(String, i32)>,
+    encryption_key: Option<EncryptionKey>,
+    combine: Option<ShuffleCombine>,
     metrics: ShuffleRepartitionerMetrics,
     context: Arc<TaskContext>,
 ) -> Result<SendableRecordBatchStream> {
+    if let Some(combine) = combine {
+        let combiner = ShuffleCombiner::try_new(input.schema(), combine)?;
+        input = combine_stream(input, combiner);
+    }
+
     let schema = input.schema();
+    // The hashes are not attached again if the input already has the column of an upstream
+    // shuffle writer, which passes through like other columns
+    let (schema, attach_partitioning_hashes) = match partitioning_keys(&partitioning) {
+        Some(keys)
+            if attach_partitioning_hashes
+                && schema.column_with_name(PARTITIONING_HASH_COLUMN).is_none() =>
+        {
+            let mut fields = schema.fields().to_vec();
+            fields.push(Arc::new(partitioning_hash_field(&keys)));
+            (
+                Arc::new(Schema::new_with_metadata(fields, schema.metadata().clone())),
+                true,
+            )
+        }
+        _ => (schema, false),
+    };
+
+    let sink: Box<dyn ShuffleBlockSink> = match context
+        .session_config()
+        .get_extension::<ShuffleBlockSinkFactory>()
+    {
+        Some(factory) => (factory.0)(),
+        None => Box::new(LocalFileSink::try_new(
+            output_data_file,
+            output_index_file,
+            checksums,
+            partitioning.partition_count(),
+        )?),
+    };
+    let sink: Box<dyn ShuffleBlockSink> = match encryption_key {
+        Some(key) => Box::new(EncryptedBlockSink::try_new(sink, key)?),
+        None => sink,
+    };
+
+    if partitioning.partition_count() == 1 && !attach_partitioning_hashes {
+        let mut writer = SinglePartitionShuffleWriter::try_new(
+            sink,
+            schema,
+            codec,
+            metrics,
+            context.session_config().batch_size(),
+        )?;
+        while let Some(batch) = input.next().await {
+            writer.insert_batch(batch?)?;
+        }
+        return writer.shuffle_write();
+    }
+
+    let memory_pool = context
+        .session_config()
+        .get_extension::<ShuffleMemoryPool>()
+        .map(|pool| pool.0.clone())
+        .unwrap_or_else(|| context.runtime_env().memory_pool.clone());
     let mut repartitioner = ShuffleRepartitioner::new(
         partition_id,
-        output_data_file,
-        output_index_file,
+        sink,
         schema.clone(),
         partitioning,
+        attach_partitioning_hashes,
+        round_robin_start,
+        codec,
+        sort_based,
         metrics,
-        context.runtime_env(),
+        &memory_pool,
+        context
+            .session_config()
+            .get_extension::<SpillManager>()
+            .unwrap_or_default(),
+        context
+            .session_config()
+            .get_extension::<ResourceLimits>()
+            .unwrap_or_default(),
         context.session_config().batch_size(),
     );
 
@@ -1301,17 +2025,15 @@ impl Checksum {
     }
 
     pub(crate) fn update(&mut self, cursor: &mut Cursor<&mut Vec<u8>>) -> CometResult<()> {
+        std::io::Seek::seek(cursor, SeekFrom::Start(0))?;
+        self.update_bytes(cursor.chunk());
+        Ok(())
+    }
+
+    fn update_bytes(&mut self, bytes: &[u8]) {
         match self {
-            Checksum::CRC32(hasher) => {
-                std::io::Seek::seek(cursor, SeekFrom::Start(0))?;
-                hasher.update(cursor.chunk());
-                Ok(())
-            }
-            Checksum::Adler32(hasher) => {
-                std::io::Seek::seek(cursor, SeekFrom::Start(0))?;
-                hasher.write(cursor.chunk());
-                Ok(())
-            }
+            Checksum::CRC32(hasher) => hasher.update(bytes),
+            Checksum::Adler32(hasher) => hasher.write(bytes),
         }
     }
 
@@ -1323,14 +2045,204 @@ impl Checksum {
     }
 }
 
-/// Writes given record batch as Arrow IPC bytes into given writer.
+/// A writer computing the checksum of the bytes written for each output partition, like the
+/// `MutableCheckedOutputStream` of Spark's shuffle writers.
+struct PartitionChecksumWriter<W: Write> {
+    inner: W,
+    /// The checksum of no bytes, which the checksum of each partition starts from
+    initial: Option<Checksum>,
+    current: Option<Checksum>,
+    /// The checksums of the partitions ended so far
+    checksums: Vec<u32>,
+}
+
+impl<W: Write> PartitionChecksumWriter<W> {
+    /// Creates a writer computing the checksums with the given algorithm, or no checksums if it
+    /// is `None`.
+    fn try_new(inner: W, algorithm: Option<i32>) -> CometResult<Self> {
+        let initial = algorithm
+            .map(|algorithm| Checksum::try_new(algorithm, None))
+            .transpose()?;
+        Ok(Self {
+            inner,
+            current: initial.clone(),
+            initial,
+            checksums: vec![],
+        })
+    }
+
+    fn get_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+
+    /// Records the checksum of the bytes written since the previous partition ended.
+    fn end_partition(&mut self) {
+        if let Some(checksum) = std::mem::replace(&mut self.current, self.initial.clone()) {
+            self.checksums.push(checksum.finalize());
+        }
+    }
+}
+
+impl<W: Write> Write for PartitionChecksumWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        if let Some(checksum) = &mut self.current {
+            checksum.update_bytes(&buf[..written]);
+        }
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Writes the checksums of the partitions like the checksum file of Spark's shuffle writers.
+fn write_checksum_file(path: &str, checksums: &[u32]) -> Result<()> {
+    let mut output = BufWriter::new(
+        File::create(path)
+            .map_err(|e| DataFusionError::Execution(format!("shuffle write error: {:?}", e)))?,
+    );
+    for checksum in checksums {
+        output.write_all(&(*checksum as i64).to_be_bytes())?;
+    }
+    output.flush()?;
+    Ok(())
+}
+
+/// The default [`ShuffleBlockSink`], which writes the output partitions into the data file of the
+/// map output, their offsets into the index file, and their checksums into the checksum file if
+/// enabled.
+///
+/// The files are laid out like the ones of Spark's `IndexShuffleBlockResolver`, i.e., the index
+/// file has `num_partitions + 1` big-endian offsets starting at 0 and ending at the length of the
+/// data file, so that they can be committed by the resolver and served by the external shuffle
+/// service as they are. The paths are the temporary files of the map output, which the JVM side
+/// renames on commit.
+pub struct LocalFileSink {
+    output_data: PartitionChecksumWriter<File>,
+    output_index_file: String,
+    output_checksum_file: Option<String>,
+    num_partitions: usize,
+    /// The offsets of the partitions started so far
+    offsets: Vec<u64>,
+}
+
+impl LocalFileSink {
+    /// Creates the sink of `num_partitions` partitions. See [`ShuffleWriterExec::with_checksums`]
+    /// for `checksums`.
+    pub fn try_new(
+        output_data_file: String,
+        output_index_file: String,
+        checksums: Option<(String, i32)>,
+        num_partitions: usize,
+    ) -> Result<Self> {
+        let output_file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(output_data_file)
+            .map_err(|e| DataFusionError::Execution(format!("shuffle write error: {:?}", e)))?;
+        let (output_checksum_file, checksum_algorithm) = checksums.unzip();
+        Ok(Self {
+            output_data: PartitionChecksumWriter::try_new(output_file, checksum_algorithm)?,
+            output_index_file,
+            output_checksum_file,
+            num_partitions,
+            offsets: Vec::with_capacity(num_partitions + 1),
+        })
+    }
+}
+
+impl Write for LocalFileSink {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.output_data.write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.output_data.flush()
+    }
+}
+
+impl ShuffleBlockSink for LocalFileSink {
+    fn start_partition(&mut self, partition_id: usize) -> Result<()> {
+        let position = self
+            .output_data
+            .get_mut()
+            .stream_position()
+            .map_err(|e| DataFusionError::Execution(format!("shuffle write error: {:?}", e)))?;
+        // The partitions skipped are empty
+        while self.offsets.len() <= partition_id {
+            if !self.offsets.is_empty() {
+                self.output_data.end_partition();
+            }
+            self.offsets.push(position);
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        self.output_data.flush()?;
+        // add one extra offset at last to ease partition length computation
+        self.start_partition(self.num_partitions)?;
+        debug_assert_eq!(self.offsets.len(), self.num_partitions + 1);
+
+        let mut output_index =
+            BufWriter::new(File::create(&self.output_index_file).map_err(|e| {
+                DataFusionError::Execution(format!("shuffle write error: {:?}", e))
+            })?);
+        for offset in &self.offsets {
+            output_index
+                .write_all(&(*offset as i64).to_be_bytes()[..])
+                .map_err(|e| DataFusionError::Execution(format!("shuffle write error: {:?}", e)))?;
+        }
+        output_index.flush()?;
+        if let Some(checksum_file) = &self.output_checksum_file {
+            write_checksum_file(checksum_file, &self.output_data.checksums)?;
+        }
+        Ok(())
+    }
+
+    fn is_local_io(&self) -> bool {
+        true
+    }
+}
+
+/// Writes given record batch as Arrow IPC bytes compressed with given codec into given writer.
 /// Returns number of bytes written.
 pub(crate) fn write_ipc_compressed<W: Write + Seek>(
     batch: &RecordBatch,
     output: &mut W,
+    codec: &CompressionCodec,
 ) -> Result<usize> {
+    write_ipc_compressed_into(batch, output, codec).map(|(size, _)| size)
+}
+
+/// Like [`write_ipc_compressed`], but also adds the time spent compressing to `compress_time` of
+/// given metrics, and the rest of the time to `encode_time`.
+fn write_ipc_compressed_timed<W: Write + Seek>(
+    batch: &RecordBatch,
+    output: &mut W,
+    codec: &CompressionCodec,
+    metrics: &IpcWriteMetrics,
+) -> Result<usize> {
+    let start = Instant::now();
+    let (size, compress_time) = write_ipc_compressed_into(batch, output, codec)?;
+    metrics.compress_time.add_duration(compress_time);
+    metrics
+        .encode_time
+        .add_duration(start.elapsed().saturating_sub(compress_time));
+    Ok(size)
+}
+
+/// Implements [`write_ipc_compressed`], also returning the time spent compressing.
+fn write_ipc_compressed_into<W: Write + Seek>(
+    batch: &RecordBatch,
+    output: &mut W,
+    codec: &CompressionCodec,
+) -> Result<(usize, Duration)> {
     if batch.num_rows() == 0 {
-        return Ok(0);
+        return Ok((0, Duration::ZERO));
     }
     let start_pos = output.stream_position()?;
 
@@ -1338,13 +2250,15 @@ pub(crate) fn write_ipc_compressed<W: Write + Seek>(
     output.write_all(&[0u8; 8])?;
 
     // write ipc data
-    // TODO: make compression level configurable
-    let mut arrow_writer = StreamWriter::try_new(zstd::Encoder::new(output, 1)?, &batch.schema())?;
+    let mut arrow_writer = StreamWriter::try_new(
+        TimedWriter::new(CompressionWriter::try_new(codec, output)?),
+        &batch.schema(),
+    )?;
     arrow_writer.write(batch)?;
     arrow_writer.finish()?;
 
-    let zwriter = arrow_writer.into_inner()?;
-    let output = zwriter.finish()?;
+    let writer = arrow_writer.into_inner()?;
+    let (output, compress_time) = writer.finish()?;
     let end_pos = output.stream_position()?;
     let ipc_length = end_pos - start_pos - 8;
 
@@ -1353,7 +2267,45 @@ pub(crate) fn write_ipc_compressed<W: Write + Seek>(
     output.write_all(&ipc_length.to_le_bytes()[..])?;
 
     output.seek(SeekFrom::Start(end_pos))?;
-    Ok((end_pos - start_pos) as usize)
+    Ok(((end_pos - start_pos) as usize, compress_time))
+}
+
+/// A writer which measures the time spent in writing into the compression writer it wraps.
+struct TimedWriter<W: Write> {
+    inner: CompressionWriter<W>,
+    elapsed: Duration,
+}
+
+impl<W: Write> TimedWriter<W> {
+    fn new(inner: CompressionWriter<W>) -> Self {
+        Self {
+            inner,
+            elapsed: Duration::ZERO,
+        }
+    }
+
+    /// Finishes the compression, and returns the inner output with the total time spent.
+    fn finish(self) -> std::io::Result<(W, Duration)> {
+        let start = Instant::now();
+        let output = self.inner.finish()?;
+        Ok((output, self.elapsed + start.elapsed()))
+    }
+}
+
+impl<W: Write> Write for TimedWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let start = Instant::now();
+        let result = self.inner.write(buf);
+        self.elapsed += start.elapsed();
+        result
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        let start = Instant::now();
+        let result = self.inner.flush();
+        self.elapsed += start.elapsed();
+        result
+    }
 }
 
 /// A stream that yields no record batches which represent end of output.
@@ -1387,6 +2339,577 @@ impl RecordBatchStream for EmptyStream {
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::execution::broadcast::{deserialize_batches, deserialize_batches_with_codec};
+    use arrow::compute::cast;
+    use arrow_array::{cast::AsArray, types::Int32Type};
+    use datafusion::{
+        execution::{context::SessionConfig, runtime_env::RuntimeConfig},
+        physical_plan::{common::collect, memory::MemoryExec},
+    };
+    use datafusion_physical_expr::expressions::Column;
+    use futures::executor::block_on;
+
+    #[test]
+    fn test_attached_partitioning_hashes() {
+        let partitioning = Partitioning::Hash(vec![Arc::new(Column::new("a", 0))], 16);
+        let keys = partitioning_keys(&partitioning).unwrap();
+        let a: ArrayRef = Arc::new(Int32Array::from(vec![1, 2, 3]));
+        let mut hashes = vec![0_u32; 3];
+        compute_partitioning_hashes(&[a.clone()], &mut hashes).unwrap();
+        // Same as `hash(1)` in Spark
+        assert_eq!(hashes[0] as i32, -559580957);
+
+        let schema = Schema::new(vec![
+            Field::new("a", DataType::Int32, true),
+            partitioning_hash_field(&keys),
+        ]);
+        let batch = RecordBatch::try_new(
+            Arc::new(schema),
+            vec![
+                a,
+                Arc::new(Int32Array::from_iter_values(
+                    hashes.iter().map(|hash| *hash as i32),
+                )),
+            ],
+        )
+        .unwrap();
+        let attached = attached_partitioning_hashes(&batch, &partitioning).unwrap();
+        assert_eq!(
+            attached
+                .values()
+                .iter()
+                .map(|hash| *hash as u32)
+                .collect_vec(),
+            hashes
+        );
+
+        // The hashes are only reused for the same partitioning keys
+        let other = Partitioning::Hash(vec![Arc::new(Column::new("b", 1))], 16);
+        assert!(attached_partitioning_hashes(&batch, &other).is_none());
+        assert!(
+            attached_partitioning_hashes(&batch, &Partitioning::UnknownPartitioning(1)).is_none()
+        );
+    }
+
+    #[test]
+    fn test_single_partition_shuffle() {
+        let schema = Arc::new(Schema::new(vec![Field::new("a", DataType::Int32, false)]));
+        let batches = (0..3)
+            .map(|i| {
+                RecordBatch::try_new(
+                    schema.clone(),
+                    vec![Arc::new(Int32Array::from(vec![
+                        i * 3,
+                        i * 3 + 1,
+                        i * 3 + 2,
+                    ]))],
+                )
+                .unwrap()
+            })
+            .collect::<Vec<_>>();
+        let input = Arc::new(MemoryExec::try_new(&[batches], schema, None).unwrap());
+
+        let dir = tempfile::tempdir().unwrap();
+        let data_file = dir.path().join("shuffle.data");
+        let index_file = dir.path().join("shuffle.index");
+        let checksum_file = dir.path().join("shuffle.checksum");
+        let writer = ShuffleWriterExec::try_new(
+            input,
+            Partitioning::UnknownPartitioning(1),
+            data_file.to_str().unwrap().to_string(),
+            index_file.to_str().unwrap().to_string(),
+        )
+        .unwrap()
+        .with_checksums(Some((checksum_file.to_str().unwrap().to_string(), 1)));
+        let context =
+            TaskContext::default().with_session_config(SessionConfig::new().with_batch_size(4));
+        let stream = writer.execute(0, Arc::new(context)).unwrap();
+        assert!(block_on(collect(stream)).unwrap().is_empty());
+
+        // The batches of 3 rows are coalesced up to the batch size
+        let data = std::fs::read(data_file).unwrap();
+        let output = deserialize_batches(&data).unwrap();
+        assert_eq!(
+            output.iter().map(|batch| batch.num_rows()).collect_vec(),
+            vec![6, 3]
+        );
+        let values = output
+            .iter()
+            .flat_map(|batch| {
+                batch
+                    .column(0)
+                    .as_primitive::<Int32Type>()
+                    .values()
+                    .to_vec()
+            })
+            .collect_vec();
+        assert_eq!(values, (0..9).collect_vec());
+
+        let index = std::fs::read(index_file).unwrap();
+        let offsets = index
+            .chunks(8)
+            .map(|offset| i64::from_be_bytes(offset.try_into().unwrap()))
+            .collect_vec();
+        assert_eq!(offsets, vec![0, data.len() as i64]);
+
+        let mut adler32 = Adler32::new();
+        adler32.write(&data);
+        let checksums = std::fs::read(checksum_file).unwrap();
+        assert_eq!(checksums, (adler32.finish() as i64).to_be_bytes());
+    }
+
+    #[test]
+    fn test_shuffle_codecs() {
+        let schema = Arc::new(Schema::new(vec![Field::new("a", DataType::Int32, false)]));
+        let batch = RecordBatch::try_new(
+            schema.clone(),
+            vec![Arc::new(Int32Array::from_iter_values(0..10000))],
+        )
+        .unwrap();
+
+        for codec in [
+            CompressionCodec::Lz4,
+            CompressionCodec::Snappy,
+            CompressionCodec::Zstd(3),
+        ] {
+            let input = Arc::new(
+                MemoryExec::try_new(&[vec![batch.clone()]], schema.clone(), None).unwrap(),
+            );
+            let dir = tempfile::tempdir().unwrap();
+            let data_file = dir.path().join("shuffle.data");
+            let index_file = dir.path().join("shuffle.index");
+            let checksum_file = dir.path().join("shuffle.checksum");
+            let writer = ShuffleWriterExec::try_new(
+                input,
+                Partitioning::Hash(vec![Arc::new(Column::new("a", 0))], 2),
+                data_file.to_str().unwrap().to_string(),
+                index_file.to_str().unwrap().to_string(),
+            )
+            .unwrap()
+            .with_codec(codec)
+            .with_checksums(Some((checksum_file.to_str().unwrap().to_string(), 0)));
+            let stream = writer.execute(0, Arc::new(TaskContext::default())).unwrap();
+            assert!(block_on(collect(stream)).unwrap().is_empty());
+
+            let data = std::fs::read(data_file).unwrap();
+            let index = std::fs::read(index_file).unwrap();
+            let offsets = index
+                .chunks(8)
+                .map(|offset| i64::from_be_bytes(offset.try_into().unwrap()) as usize)
+                .collect_vec();
+            let mut values = offsets
+                .windows(2)
+                .flat_map(|range| {
+                    deserialize_batches_with_codec(&data[range[0]..range[1]], &codec).unwrap()
+                })
+                .flat_map(|batch| {
+                    batch
+                        .column(0)
+                        .as_primitive::<Int32Type>()
+                        .values()
+                        .to_vec()
+                })
+                .collect_vec();
+            values.sort();
+            assert_eq!(values, (0..10000).collect_vec(), "{}", codec);
+
+            // The CRC32 checksum of each partition in the data file
+            let checksums = std::fs::read(checksum_file).unwrap();
+            let expected = offsets
+                .windows(2)
+                .flat_map(|range| (crc32fast::hash(&data[range[0]..range[1]]) as i64).to_be_bytes())
+                .collect_vec();
+            assert_eq!(checksums, expected, "{}", codec);
+        }
+    }
+
+    #[test]
+    fn test_merged_shuffle_blocks() {
+        let schema = Arc::new(Schema::new(vec![Field::new("a", DataType::Int32, false)]));
+        let dir = tempfile::tempdir().unwrap();
+        let codec = CompressionCodec::Zstd(1);
+
+        // The output of each map task, i.e., its data file and partition offsets
+        let outputs = (0..3)
+            .map(|map| {
+                let batch = RecordBatch::try_new(
+                    schema.clone(),
+                    vec![Arc::new(Int32Array::from_iter_values(
+                        map * 1000..(map + 1) * 1000,
+                    ))],
+                )
+                .unwrap();
+                let input =
+                    Arc::new(MemoryExec::try_new(&[vec![batch]], schema.clone(), None).unwrap());
+                let data_file = dir.path().join(format!("shuffle_{}.data", map));
+                let index_file = dir.path().join(format!("shuffle_{}.index", map));
+                let writer = ShuffleWriterExec::try_new(
+                    input,
+                    Partitioning::Hash(vec![Arc::new(Column::new("a", 0))], 4),
+                    data_file.to_str().unwrap().to_string(),
+                    index_file.to_str().unwrap().to_string(),
+                )
+                .unwrap()
+                .with_codec(codec);
+                let stream = writer.execute(0, Arc::new(TaskContext::default())).unwrap();
+                assert!(block_on(collect(stream)).unwrap().is_empty());
+
+                let offsets = std::fs::read(index_file)
+                    .unwrap()
+                    .chunks(8)
+                    .map(|offset| i64::from_be_bytes(offset.try_into().unwrap()) as usize)
+                    .collect_vec();
+                (std::fs::read(data_file).unwrap(), offsets)
+            })
+            .collect_vec();
+
+        // Push-based shuffle merges the blocks of a partition by concatenating them, which are
+        // decoded like the block of a single map
+        let mut values = (0..4)
+            .flat_map(|partition| {
+                let merged = outputs
+                    .iter()
+                    .flat_map(|(data, offsets)| {
+                        data[offsets[partition]..offsets[partition + 1]].to_vec()
+                    })
+                    .collect_vec();
+                deserialize_batches_with_codec(&merged, &codec).unwrap()
+            })
+            .flat_map(|batch| {
+                batch
+                    .column(0)
+                    .as_primitive::<Int32Type>()
+                    .values()
+                    .to_vec()
+            })
+            .collect_vec();
+        values.sort();
+        assert_eq!(values, (0..3000).collect_vec());
+    }
+
+    #[test]
+    fn test_sort_based_shuffle_spills() {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("a", DataType::Int32, false),
+            Field::new("b", DataType::Utf8, true),
+        ]));
+        let batches = (0..20)
+            .map(|i| {
+                RecordBatch::try_new(
+                    schema.clone(),
+                    vec![
+                        Arc::new(Int32Array::from_iter_values(i * 500..(i + 1) * 500)),
+                        Arc::new(StringArray::from_iter(
+                            (i * 500..(i + 1) * 500).map(|v| (v % 3 != 0).then(|| v.to_string())),
+                        )),
+                    ],
+                )
+                .unwrap()
+            })
+            .collect::<Vec<_>>();
+        let input = Arc::new(MemoryExec::try_new(&[batches], schema, None).unwrap());
+
+        let dir = tempfile::tempdir().unwrap();
+        let data_file = dir.path().join("shuffle.data");
+        let index_file = dir.path().join("shuffle.index");
+        let writer = ShuffleWriterExec::try_new(
+            input,
+            Partitioning::Hash(vec![Arc::new(Column::new("a", 0))], 7),
+            data_file.to_str().unwrap().to_string(),
+            index_file.to_str().unwrap().to_string(),
+        )
+        .unwrap()
+        .with_sort_based(true);
+        // Only a few input batches fit into the memory pool
+        let runtime = RuntimeEnv::new(RuntimeConfig::new().with_memory_limit(64 * 1024, 1.0));
+        let context = TaskContext::default()
+            .with_session_config(SessionConfig::new().with_batch_size(1024))
+            .with_runtime(Arc::new(runtime.unwrap()));
+        let stream = writer.execute(0, Arc::new(context)).unwrap();
+        assert!(block_on(collect(stream)).unwrap().is_empty());
+        assert!(writer.metrics().unwrap().spill_count().unwrap() > 1);
+
+        let data = std::fs::read(data_file).unwrap();
+        let index = std::fs::read(index_file).unwrap();
+        let offsets = index
+            .chunks(8)
+            .map(|offset| i64::from_be_bytes(offset.try_into().unwrap()) as usize)
+            .collect_vec();
+        assert_eq!(offsets.len(), 8);
+        let mut values = vec![];
+        for (partition_id, range) in offsets.windows(2).enumerate() {
+            for batch in deserialize_batches(&data[range[0]..range[1]]).unwrap() {
+                assert!(batch.num_rows() <= 1024);
+                let a = batch.column(0).as_primitive::<Int32Type>();
+                let b = batch.column(1).as_string::<i32>();
+                let mut hashes = vec![0_u32; batch.num_rows()];
+                compute_partitioning_hashes(&[batch.column(0).clone()], &mut hashes).unwrap();
+                for (i, hash) in hashes.iter().enumerate() {
+                    // Every row is in the partition of its hash, with its own string
+                    assert_eq!(pmod(*hash, 7), partition_id);
+                    let v = a.value(i);
+                    assert_eq!(b.is_valid(i), v % 3 != 0);
+                    if b.is_valid(i) {
+                        assert_eq!(b.value(i), v.to_string());
+                    }
+                    values.push(v);
+                }
+            }
+        }
+        values.sort();
+        assert_eq!(values, (0..10000).collect_vec());
+    }
+
+    #[test]
+    fn test_shuffle_spill_disk_limit() {
+        let schema = Arc::new(Schema::new(vec![Field::new("a", DataType::Int32, false)]));
+        let batches = (0..20)
+            .map(|i| {
+                RecordBatch::try_new(
+                    schema.clone(),
+                    vec![Arc::new(Int32Array::from_iter_values(
+                        i * 1000..(i + 1) * 1000,
+                    ))],
+                )
+                .unwrap()
+            })
+            .collect::<Vec<_>>();
+        let input = Arc::new(MemoryExec::try_new(&[batches], schema, None).unwrap());
+
+        let dir = tempfile::tempdir().unwrap();
+        let spill_dir = tempfile::tempdir().unwrap();
+        let spill_manager = Arc::new(SpillManager::new(
+            vec![spill_dir.path().to_path_buf()],
+            Some(1024),
+        ));
+        let writer = ShuffleWriterExec::try_new(
+            input,
+            Partitioning::Hash(vec![Arc::new(Column::new("a", 0))], 7),
+            dir.path()
+                .join("shuffle.data")
+                .to_str()
+                .unwrap()
+                .to_string(),
+            dir.path()
+                .join("shuffle.index")
+                .to_str()
+                .unwrap()
+                .to_string(),
+        )
+        .unwrap();
+        let runtime = RuntimeEnv::new(RuntimeConfig::new().with_memory_limit(64 * 1024, 1.0));
+        let context = TaskContext::default()
+            .with_session_config(SessionConfig::new().with_extension(spill_manager.clone()))
+            .with_runtime(Arc::new(runtime.unwrap()));
+        let stream = writer.execute(0, Arc::new(context)).unwrap();
+        let err = block_on(collect(stream)).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("exceeding the limit of 1024 bytes"));
+
+        // The failed spill is neither accounted nor left on disk
+        assert_eq!(spill_manager.peak(), 0);
+        assert_eq!(spill_manager.used(), 0);
+        for temp_dir in std::fs::read_dir(spill_dir.path()).unwrap() {
+            let temp_dir = temp_dir.unwrap().path();
+            assert_eq!(std::fs::read_dir(temp_dir).unwrap().count(), 0);
+        }
+    }
+
+    #[test]
+    fn test_sort_based_shuffle_keeps_dictionaries() {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("a", DataType::Int32, false),
+            Field::new(
+                "b",
+                DataType::Dictionary(Box::new(DataType::Int32), Box::new(DataType::Utf8)),
+                true,
+            ),
+        ]));
+        let batches = (0..10)
+            .map(|i| {
+                let b = (i * 500..(i + 1) * 500)
+                    .map(|v| format!("v{}", v % 5))
+                    .collect::<DictionaryArray<Int32Type>>();
+                RecordBatch::try_new(
+                    schema.clone(),
+                    vec![
+                        Arc::new(Int32Array::from_iter_values(i * 500..(i + 1) * 500)),
+                        Arc::new(b),
+                    ],
+                )
+                .unwrap()
+            })
+            .collect::<Vec<_>>();
+        let input = Arc::new(MemoryExec::try_new(&[batches], schema, None).unwrap());
+
+        let dir = tempfile::tempdir().unwrap();
+        let data_file = dir.path().join("shuffle.data");
+        let index_file = dir.path().join("shuffle.index");
+        let writer = ShuffleWriterExec::try_new(
+            input,
+            Partitioning::Hash(vec![Arc::new(Column::new("a", 0))], 3),
+            data_file.to_str().unwrap().to_string(),
+            index_file.to_str().unwrap().to_string(),
+        )
+        .unwrap()
+        .with_sort_based(true);
+        let context =
+            TaskContext::default().with_session_config(SessionConfig::new().with_batch_size(1024));
+        let stream = writer.execute(0, Arc::new(context)).unwrap();
+        assert!(block_on(collect(stream)).unwrap().is_empty());
+
+        let data = std::fs::read(data_file).unwrap();
+        let mut num_rows = 0;
+        for batch in deserialize_batches(&data).unwrap() {
+            // Every batch has a single dictionary of the values of its rows
+            let b = batch.column(1).as_dictionary::<Int32Type>();
+            assert!(b.values().len() <= 5);
+            let a = batch.column(0).as_primitive::<Int32Type>();
+            let b = cast(b, &DataType::Utf8).unwrap();
+            for (a, b) in a.iter().zip(b.as_string::<i32>().iter()) {
+                assert_eq!(b.unwrap(), format!("v{}", a.unwrap() % 5));
+            }
+            num_rows += batch.num_rows();
+        }
+        assert_eq!(num_rows, 5000);
+    }
+
+    /// Collects the blocks of the started partitions in memory.
+    struct MemorySink {
+        blocks: Arc<parking_lot::Mutex<Vec<(usize, Vec<u8>)>>>,
+        /// The thread expected to write the sink, i.e., the one running the plan
+        thread: std::thread::ThreadId,
+    }
+
+    impl Write for MemorySink {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            assert_eq!(std::thread::current().id(), self.thread);
+            let mut blocks = self.blocks.lock();
+            blocks.last_mut().unwrap().1.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl ShuffleBlockSink for MemorySink {
+        fn start_partition(&mut self, partition_id: usize) -> Result<()> {
+            self.blocks.lock().push((partition_id, vec![]));
+            Ok(())
+        }
+
+        fn finish(&mut self) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_shuffle_block_sink() {
+        let schema = Arc::new(Schema::new(vec![Field::new("a", DataType::Int32, false)]));
+        let batch = RecordBatch::try_new(
+            schema.clone(),
+            vec![Arc::new(Int32Array::from_iter_values(0..1000))],
+        )
+        .unwrap();
+        let input = Arc::new(MemoryExec::try_new(&[vec![batch]], schema, None).unwrap());
+
+        let dir = tempfile::tempdir().unwrap();
+        let data_file = dir.path().join("shuffle.data");
+        let writer = ShuffleWriterExec::try_new(
+            input,
+            Partitioning::Hash(vec![Arc::new(Column::new("a", 0))], 3),
+            data_file.to_str().unwrap().to_string(),
+            dir.path()
+                .join("shuffle.index")
+                .to_str()
+                .unwrap()
+                .to_string(),
+        )
+        .unwrap();
+        let blocks = Arc::new(parking_lot::Mutex::new(vec![]));
+        let sink_blocks = blocks.clone();
+        let thread = std::thread::current().id();
+        let factory = ShuffleBlockSinkFactory(Arc::new(move || -> Box<dyn ShuffleBlockSink> {
+            Box::new(MemorySink {
+                blocks: sink_blocks.clone(),
+                thread,
+            })
+        }));
+        let context = TaskContext::default()
+            .with_session_config(SessionConfig::new().with_extension(Arc::new(factory)));
+        let stream = writer.execute(0, Arc::new(context)).unwrap();
+        assert!(block_on(collect(stream)).unwrap().is_empty());
+
+        // The partitions are pushed to the sink instead of the local files, from the thread running
+        // the plan rather than the IO runtime
+        assert!(!data_file.exists());
+        let blocks = blocks.lock();
+        assert_eq!(
+            blocks.iter().map(|(id, _)| *id).collect_vec(),
+            vec![0, 1, 2]
+        );
+        let mut values = vec![];
+        for (partition_id, block) in blocks.iter() {
+            for batch in deserialize_batches(block).unwrap() {
+                let a = batch.column(0).as_primitive::<Int32Type>();
+                let mut hashes = vec![0_u32; batch.num_rows()];
+                compute_partitioning_hashes(&[batch.column(0).clone()], &mut hashes).unwrap();
+                assert!(hashes.iter().all(|hash| pmod(*hash, 3) == *partition_id));
+                values.extend(a.values().iter());
+            }
+        }
+        values.sort();
+        assert_eq!(values, (0..1000).collect_vec());
+    }
+
+    #[test]
+    fn test_partition_buffer_reuses_pooled_buffers_by_size() {
+        let schema = Arc::new(Schema::new(vec![Field::new("a", DataType::Int32, false)]));
+        let batch = RecordBatch::try_new(
+            schema.clone(),
+            vec![Arc::new(Int32Array::from_iter_values(0..100))],
+        )
+        .unwrap();
+        let buffer_pool = Arc::new(parking_lot::Mutex::new(BufferPool::new(MAX_POOLED_BYTES)));
+        buffer_pool.lock().give(Vec::with_capacity(1 << 20));
+        buffer_pool.lock().give(Vec::with_capacity(1 << 16));
+        let new_partition_buffer = || {
+            PartitionBuffer::new(
+                schema.clone(),
+                100,
+                buffer_pool.clone(),
+                CompressionCodec::Zstd(1),
+                IpcWriteMetrics {
+                    encode_time: Time::new(),
+                    compress_time: Time::new(),
+                },
+            )
+        };
+
+        // No buffer is taken from the pool before the size of the frozen bytes is known
+        let mut partition = new_partition_buffer();
+        partition.append_batch(&batch).unwrap();
+        partition.flush().unwrap();
+        let frozen = partition.take_frozen();
+        assert!(frozen.capacity() < 1 << 16);
+        assert_eq!(buffer_pool.lock().pooled_bytes(), (1 << 20) + (1 << 16));
+
+        // A small partition takes a buffer of the smallest size class, not the largest
+        partition.append_batch(&batch).unwrap();
+        partition.flush().unwrap();
+        assert_eq!(partition.take_frozen().capacity(), 1 << 16);
+
+        // A large partition takes a buffer of its size class
+        let mut large = new_partition_buffer();
+        large.expected_frozen_size = 600_000;
+        large.append_batch(&batch).unwrap();
+        large.flush().unwrap();
+        assert_eq!(large.take_frozen().capacity(), 1 << 20);
+        assert_eq!(buffer_pool.lock().pooled_bytes(), 0);
+    }
 
     #[test]
     fn test_slot_size() {
@@ -0,0 +1,304 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Simplification of the physical expressions created by the native planner.
+//!
+//! The planner creates expressions bottom-up and simplifies each of them once its children are
+//! created, so a rule only needs to look at an expression and its already simplified children.
+
+use std::sync::Arc;
+
+use arrow_array::{RecordBatch, RecordBatchOptions};
+use arrow_schema::{DataType, Schema};
+use datafusion::{
+    logical_expr::Operator,
+    physical_expr::{
+        expressions::{BinaryExpr, Literal, NotExpr},
+        PhysicalExpr, ScalarFunctionExpr,
+    },
+    physical_plan::ColumnarValue,
+};
+use datafusion_common::{Result, ScalarValue};
+
+use crate::execution::datafusion::expressions::cast::Cast;
+
+/// Functions which may return different results for the same arguments, so that calling them
+/// with literal arguments cannot be folded.
+const VOLATILE_FUNCTIONS: [&str; 4] = ["random", "uuid", "now", "current_time"];
+
+/// Simplifies `expr`, whose children are already simplified:
+///   - expressions of literals only are evaluated into a literal,
+///   - casts to the type of their input are removed, and a widening integer cast followed by
+///     another widening cast is collapsed into a single cast,
+///   - double negations are removed, and `x AND true` and `x OR false` are replaced by `x`,
+///   - comparisons between a literal and another expression are rewritten to have the literal on
+///     the right side.
+///
+/// The simplified expression returns the same result for every input. An expression that fails
+/// to be evaluated is kept as it is, so that errors are still raised only for actual input rows.
+pub(crate) fn simplify_expr(
+    expr: Arc<dyn PhysicalExpr>,
+    input_schema: &Schema,
+) -> Result<Arc<dyn PhysicalExpr>> {
+    if let Some(literal) = fold_constant(&expr, input_schema)? {
+        return Ok(literal);
+    }
+
+    if let Some(cast) = expr.as_any().downcast_ref::<Cast>() {
+        return simplify_cast(cast, input_schema).map(|e| e.unwrap_or(expr));
+    }
+
+    if let Some(not) = expr.as_any().downcast_ref::<NotExpr>() {
+        if let Some(inner) = not.arg().as_any().downcast_ref::<NotExpr>() {
+            return Ok(Arc::clone(inner.arg()));
+        }
+        return Ok(expr);
+    }
+
+    if let Some(binary) = expr.as_any().downcast_ref::<BinaryExpr>() {
+        return Ok(simplify_binary(binary).unwrap_or(expr));
+    }
+
+    Ok(expr)
+}
+
+/// Evaluates an expression whose children are all literals into a literal.
+fn fold_constant(
+    expr: &Arc<dyn PhysicalExpr>,
+    input_schema: &Schema,
+) -> Result<Option<Arc<dyn PhysicalExpr>>> {
+    let children = expr.children();
+    if children.is_empty() || !children.iter().all(|child| is_literal(child)) {
+        return Ok(None);
+    }
+    if let Some(func) = expr.as_any().downcast_ref::<ScalarFunctionExpr>() {
+        if VOLATILE_FUNCTIONS.contains(&func.name()) {
+            return Ok(None);
+        }
+    }
+
+    // The children don't reference any column, so a batch of a single row without columns is
+    // enough to evaluate the expression
+    let batch = RecordBatch::try_new_with_options(
+        Arc::new(Schema::empty()),
+        vec![],
+        &RecordBatchOptions::new().with_row_count(Some(1)),
+    )?;
+    let value = match expr.evaluate(&batch) {
+        Ok(ColumnarValue::Scalar(value)) => value,
+        Ok(ColumnarValue::Array(array)) if array.len() == 1 => {
+            ScalarValue::try_from_array(&array, 0)?
+        }
+        _ => return Ok(None),
+    };
+
+    if value.data_type() != expr.data_type(input_schema)? {
+        return Ok(None);
+    }
+    Ok(Some(Arc::new(Literal::new(value))))
+}
+
+fn simplify_cast(cast: &Cast, input_schema: &Schema) -> Result<Option<Arc<dyn PhysicalExpr>>> {
+    let child_type = cast.child.data_type(input_schema)?;
+    // String arrays may be dictionary-encoded at runtime, which the cast unpacks
+    if child_type == cast.data_type && !is_string_or_binary(&child_type) {
+        return Ok(Some(Arc::clone(&cast.child)));
+    }
+
+    if let Some(inner) = cast.child.as_any().downcast_ref::<Cast>() {
+        let input_type = inner.child.data_type(input_schema)?;
+        if is_widening_integer_cast(&input_type, &inner.data_type)
+            && is_widening_cast(&inner.data_type, &cast.data_type)
+        {
+            return Ok(Some(Arc::new(Cast::new(
+                Arc::clone(&inner.child),
+                cast.data_type.clone(),
+                cast.eval_mode,
+                cast.timezone.clone(),
+            ))));
+        }
+    }
+    Ok(None)
+}
+
+fn simplify_binary(binary: &BinaryExpr) -> Option<Arc<dyn PhysicalExpr>> {
+    let (left, op, right) = (binary.left(), *binary.op(), binary.right());
+
+    match (op, literal_value(left), literal_value(right)) {
+        (Operator::And, _, Some(ScalarValue::Boolean(Some(true))))
+        | (Operator::Or, _, Some(ScalarValue::Boolean(Some(false)))) => Some(Arc::clone(left)),
+        (Operator::And, Some(ScalarValue::Boolean(Some(true))), _)
+        | (Operator::Or, Some(ScalarValue::Boolean(Some(false))), _) => Some(Arc::clone(right)),
+        (
+            Operator::Eq
+            | Operator::NotEq
+            | Operator::Lt
+            | Operator::LtEq
+            | Operator::Gt
+            | Operator::GtEq,
+            Some(_),
+            None,
+        ) => op.swap().map(|swapped| {
+            Arc::new(BinaryExpr::new(
+                Arc::clone(right),
+                swapped,
+                Arc::clone(left),
+            )) as Arc<dyn PhysicalExpr>
+        }),
+        _ => None,
+    }
+}
+
+fn is_literal(expr: &Arc<dyn PhysicalExpr>) -> bool {
+    expr.as_any().is::<Literal>()
+}
+
+fn literal_value(expr: &Arc<dyn PhysicalExpr>) -> Option<&ScalarValue> {
+    expr.as_any()
+        .downcast_ref::<Literal>()
+        .map(|literal| literal.value())
+}
+
+fn is_string_or_binary(data_type: &DataType) -> bool {
+    matches!(
+        data_type,
+        DataType::Utf8 | DataType::LargeUtf8 | DataType::Binary | DataType::LargeBinary
+    )
+}
+
+fn integer_width(data_type: &DataType) -> Option<usize> {
+    match data_type {
+        DataType::Int8 => Some(1),
+        DataType::Int16 => Some(2),
+        DataType::Int32 => Some(4),
+        DataType::Int64 => Some(8),
+        _ => None,
+    }
+}
+
+/// Whether casting an integer of `from` type to `to` type keeps its value.
+fn is_widening_integer_cast(from: &DataType, to: &DataType) -> bool {
+    matches!((integer_width(from), integer_width(to)), (Some(from), Some(to)) if from <= to)
+}
+
+/// Whether casting an integer of `from` type to `to` type never overflows, so that it returns
+/// the same result as casting the original narrower integer directly.
+fn is_widening_cast(from: &DataType, to: &DataType) -> bool {
+    is_widening_integer_cast(from, to) || matches!(to, DataType::Float32 | DataType::Float64)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use arrow_schema::{DataType, Field, Schema};
+    use datafusion::{
+        logical_expr::Operator,
+        physical_expr::{
+            expressions::{BinaryExpr, Column, Literal, NotExpr},
+            PhysicalExpr,
+        },
+    };
+    use datafusion_common::ScalarValue;
+
+    use super::simplify_expr;
+    use crate::execution::datafusion::expressions::cast::{Cast, EvalMode};
+
+    fn literal(value: ScalarValue) -> Arc<dyn PhysicalExpr> {
+        Arc::new(Literal::new(value))
+    }
+
+    fn cast(child: Arc<dyn PhysicalExpr>, data_type: DataType) -> Arc<dyn PhysicalExpr> {
+        Arc::new(Cast::new_without_timezone(
+            child,
+            data_type,
+            EvalMode::Legacy,
+        ))
+    }
+
+    #[test]
+    fn test_simplify_expr() {
+        let schema = Schema::new(vec![Field::new("a", DataType::Int32, true)]);
+        let column: Arc<dyn PhysicalExpr> = Arc::new(Column::new("a", 0));
+
+        // 1 + 2 is folded into 3, and then `3 < a` is rewritten to `a > 3`
+        let sum = simplify_expr(
+            Arc::new(BinaryExpr::new(
+                literal(ScalarValue::Int32(Some(1))),
+                Operator::Plus,
+                literal(ScalarValue::Int32(Some(2))),
+            )),
+            &schema,
+        )
+        .unwrap();
+        assert_eq!(sum.to_string(), "3");
+        let comparison = simplify_expr(
+            Arc::new(BinaryExpr::new(sum, Operator::Lt, Arc::clone(&column))),
+            &schema,
+        )
+        .unwrap();
+        assert_eq!(comparison.to_string(), "a@0 > 3");
+
+        // Folding an expression failing to evaluate is skipped
+        let divide_by_zero = Arc::new(BinaryExpr::new(
+            literal(ScalarValue::Int32(Some(1))),
+            Operator::Divide,
+            literal(ScalarValue::Int32(Some(0))),
+        ));
+        let simplified = simplify_expr(divide_by_zero, &schema).unwrap();
+        assert!(simplified.as_any().is::<BinaryExpr>());
+
+        // Chained widening casts are collapsed, and a cast to the input type is removed
+        let chained = cast(
+            cast(Arc::clone(&column), DataType::Int64),
+            DataType::Float64,
+        );
+        let simplified = simplify_expr(chained, &schema).unwrap();
+        let simplified = simplified.as_any().downcast_ref::<Cast>().unwrap();
+        assert!(simplified.child.as_any().is::<Column>());
+        assert_eq!(simplified.data_type, DataType::Float64);
+        let identity = simplify_expr(cast(Arc::clone(&column), DataType::Int32), &schema).unwrap();
+        assert!(identity.as_any().is::<Column>());
+
+        // A narrowing cast is kept
+        let narrowing = cast(cast(Arc::clone(&column), DataType::Int64), DataType::Int16);
+        let simplified = simplify_expr(narrowing, &schema).unwrap();
+        let simplified = simplified.as_any().downcast_ref::<Cast>().unwrap();
+        assert!(simplified.child.as_any().is::<Cast>());
+
+        // Double negations and identities of boolean operators are removed
+        let predicate: Arc<dyn PhysicalExpr> = Arc::new(BinaryExpr::new(
+            Arc::clone(&column),
+            Operator::Gt,
+            literal(ScalarValue::Int32(Some(0))),
+        ));
+        let not_not = Arc::new(NotExpr::new(Arc::new(NotExpr::new(Arc::clone(&predicate)))));
+        assert_eq!(
+            simplify_expr(not_not, &schema).unwrap().to_string(),
+            predicate.to_string()
+        );
+        let and_true = Arc::new(BinaryExpr::new(
+            literal(ScalarValue::Boolean(Some(true))),
+            Operator::And,
+            Arc::clone(&predicate),
+        ));
+        assert_eq!(
+            simplify_expr(and_true, &schema).unwrap().to_string(),
+            predicate.to_string()
+        );
+    }
+}
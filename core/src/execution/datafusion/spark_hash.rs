@@ -23,9 +23,13 @@ use std::sync::Arc;
 use datafusion::{
     arrow::{
         array::*,
+        buffer::{NullBuffer, OffsetBuffer},
+        compute::{cast, take},
         datatypes::{
-            ArrowDictionaryKeyType, ArrowNativeType, DataType, Int16Type, Int32Type, Int64Type,
-            Int8Type, TimeUnit,
+            ArrowDictionaryKeyType, ArrowNativeType, DataType, Date32Type, Decimal128Type,
+            Float32Type, Float64Type, Int16Type, Int32Type, Int64Type, Int8Type, TimeUnit,
+            TimestampMicrosecondType, TimestampMillisecondType, TimestampNanosecondType,
+            TimestampSecondType,
         },
     },
     error::{DataFusionError, Result},
@@ -106,17 +110,311 @@ fn test_murmur3() {
     ];
 }
 
+/// The number of values the batched murmur3 hashing of fixed-width values hashes at a time. The
+/// lanes are independent, so the compiler vectorizes them with SIMD instructions.
+const MURMUR3_LANES: usize = 8;
+
+#[inline(always)]
+fn murmur3_mix_k1(k1: u32) -> u32 {
+    k1.wrapping_mul(0xcc9e2d51)
+        .rotate_left(15)
+        .wrapping_mul(0x1b873593)
+}
+
+#[inline(always)]
+fn murmur3_mix_h1(h1: u32, k1: u32) -> u32 {
+    (h1 ^ k1)
+        .rotate_left(13)
+        .wrapping_mul(5)
+        .wrapping_add(0xe6546b64)
+}
+
+#[inline(always)]
+fn murmur3_fmix(mut h1: u32, len: u32) -> u32 {
+    h1 ^= len;
+    h1 ^= h1 >> 16;
+    h1 = h1.wrapping_mul(0x85ebca6b);
+    h1 ^= h1 >> 13;
+    h1 = h1.wrapping_mul(0xc2b2ae35);
+    h1 ^ (h1 >> 16)
+}
+
+/// [`spark_compatible_murmur3_hash`] of the little-endian bytes of an int.
+#[inline(always)]
+fn murmur3_hash_int(value: u32, seed: u32) -> u32 {
+    murmur3_fmix(murmur3_mix_h1(seed, murmur3_mix_k1(value)), 4)
+}
+
+/// [`spark_compatible_murmur3_hash`] of the little-endian bytes of a long.
+#[inline(always)]
+fn murmur3_hash_long(value: u64, seed: u32) -> u32 {
+    let h1 = murmur3_mix_h1(seed, murmur3_mix_k1(value as u32));
+    murmur3_fmix(murmur3_mix_h1(h1, murmur3_mix_k1((value >> 32) as u32)), 8)
+}
+
+/// The 64-bit hash of a long a Spark `BloomFilterImpl` derives its hash functions from, i.e.,
+/// the murmur3 hash `h1` of the long with seed 0 in the lower 32 bits, and the murmur3 hash of
+/// the long with seed `h1` in the upper 32 bits.
+#[inline(always)]
+pub(crate) fn spark_bloom_filter_hash_long(value: i64) -> u64 {
+    let h1 = murmur3_hash_long(value as u64, 0);
+    let h2 = murmur3_hash_long(value as u64, h1);
+    ((h2 as u64) << 32) | h1 as u64
+}
+
+/// Computes the [`spark_bloom_filter_hash_long`] hashes of `values` into `hashes`,
+/// [`MURMUR3_LANES`] values at a time.
+pub(crate) fn create_bloom_filter_hashes(values: &[i64], hashes: &mut [u64]) {
+    let mut value_chunks = values.chunks_exact(MURMUR3_LANES);
+    let mut hash_chunks = hashes.chunks_exact_mut(MURMUR3_LANES);
+    for (values, hashes) in (&mut value_chunks).zip(&mut hash_chunks) {
+        for i in 0..MURMUR3_LANES {
+            hashes[i] = spark_bloom_filter_hash_long(values[i]);
+        }
+    }
+    for (value, h) in value_chunks
+        .remainder()
+        .iter()
+        .zip(hash_chunks.into_remainder())
+    {
+        *h = spark_bloom_filter_hash_long(*value);
+    }
+}
+
+/// The bits of `value` hashed by Spark, i.e., `Float.floatToIntBits` with 0 for -0.0. All the
+/// NaNs have the bits of the canonical NaN, so that they are hashed the same, like the NaNs
+/// normalized by `NormalizeNaNAndZero`.
+#[inline]
+fn float_to_int_bits(value: f32) -> u32 {
+    if value == 0.0 {
+        0
+    } else if value.is_nan() {
+        f32::NAN.to_bits()
+    } else {
+        value.to_bits()
+    }
+}
+
+/// The bits of `value` hashed by Spark, i.e., `Double.doubleToLongBits` with 0 for -0.0.
+#[inline]
+fn double_to_long_bits(value: f64) -> u64 {
+    if value == 0.0 {
+        0
+    } else if value.is_nan() {
+        f64::NAN.to_bits()
+    } else {
+        value.to_bits()
+    }
+}
+
+/// Hashes fixed-width values into `hashes` with `hash`, [`MURMUR3_LANES`] values at a time. The
+/// values under nulls are hashed too, but like Spark, the hashes of the null rows are kept.
+#[inline]
+fn murmur3_hash_fixed_width<T: Copy>(
+    values: &[T],
+    nulls: Option<&NullBuffer>,
+    hashes: &mut [u32],
+    hash: impl Fn(T, u32) -> u32,
+) {
+    let mut value_chunks = values.chunks_exact(MURMUR3_LANES);
+    let mut hash_chunks = hashes.chunks_exact_mut(MURMUR3_LANES);
+    for (chunk, (values, hashes)) in (&mut value_chunks).zip(&mut hash_chunks).enumerate() {
+        let mut lanes = [0_u32; MURMUR3_LANES];
+        for i in 0..MURMUR3_LANES {
+            lanes[i] = hash(values[i], hashes[i]);
+        }
+        match nulls {
+            None => hashes.copy_from_slice(&lanes),
+            Some(nulls) => {
+                let offset = chunk * MURMUR3_LANES;
+                for i in 0..MURMUR3_LANES {
+                    if nulls.is_valid(offset + i) {
+                        hashes[i] = lanes[i];
+                    }
+                }
+            }
+        }
+    }
+
+    let offset = values.len() - value_chunks.remainder().len();
+    for (i, (value, h)) in value_chunks
+        .remainder()
+        .iter()
+        .zip(hash_chunks.into_remainder())
+        .enumerate()
+    {
+        if nulls.map_or(true, |nulls| nulls.is_valid(offset + i)) {
+            *h = hash(*value, *h);
+        }
+    }
+}
+
+/// Hashes a fixed-width primitive column with [`murmur3_hash_fixed_width`], which computes the
+/// same hashes as `create_hashes_internal!`. Returns false if the column isn't one of them.
+fn create_fixed_width_hashes(col: &ArrayRef, hashes: &mut [u32]) -> bool {
+    macro_rules! hash_primitive {
+        ($arrow_type:ty, $hash:ident, $ty:ty) => {{
+            let array = col.as_primitive::<$arrow_type>();
+            murmur3_hash_fixed_width(array.values(), array.nulls(), hashes, |v, seed| {
+                $hash(v as $ty, seed)
+            });
+            true
+        }};
+    }
+
+    match col.data_type() {
+        DataType::Int8 => hash_primitive!(Int8Type, murmur3_hash_int, u32),
+        DataType::Int16 => hash_primitive!(Int16Type, murmur3_hash_int, u32),
+        DataType::Int32 => hash_primitive!(Int32Type, murmur3_hash_int, u32),
+        DataType::Date32 => hash_primitive!(Date32Type, murmur3_hash_int, u32),
+        DataType::Int64 => hash_primitive!(Int64Type, murmur3_hash_long, u64),
+        DataType::Timestamp(TimeUnit::Second, _) => {
+            hash_primitive!(TimestampSecondType, murmur3_hash_long, u64)
+        }
+        DataType::Timestamp(TimeUnit::Millisecond, _) => {
+            hash_primitive!(TimestampMillisecondType, murmur3_hash_long, u64)
+        }
+        DataType::Timestamp(TimeUnit::Microsecond, _) => {
+            hash_primitive!(TimestampMicrosecondType, murmur3_hash_long, u64)
+        }
+        DataType::Timestamp(TimeUnit::Nanosecond, _) => {
+            hash_primitive!(TimestampNanosecondType, murmur3_hash_long, u64)
+        }
+        // Spark uses 0 as hash for -0.0, see `Murmur3Hash` expression.
+        DataType::Float32 => {
+            let array = col.as_primitive::<Float32Type>();
+            murmur3_hash_fixed_width(array.values(), array.nulls(), hashes, |v, seed| {
+                murmur3_hash_int(float_to_int_bits(v), seed)
+            });
+            true
+        }
+        DataType::Float64 => {
+            let array = col.as_primitive::<Float64Type>();
+            murmur3_hash_fixed_width(array.values(), array.nulls(), hashes, |v, seed| {
+                murmur3_hash_long(double_to_long_bits(v), seed)
+            });
+            true
+        }
+        _ => false,
+    }
+}
+
+const PRIME64_1: u64 = 0x9E3779B185EBCA87;
+const PRIME64_2: u64 = 0xC2B2AE3D27D4EB4F;
+const PRIME64_3: u64 = 0x165667B19E3779F9;
+const PRIME64_4: u64 = 0x85EBCA77C2B2AE63;
+const PRIME64_5: u64 = 0x27D4EB2F165667C5;
+
+/// XXH64 of `data`, like `XXH64.hashUnsafeBytes` in Spark. Spark hashes an int or a long as its 4
+/// or 8 little-endian bytes, which is the same as XXH64 of these bytes.
+#[inline]
+pub(crate) fn spark_compatible_xxhash64<T: AsRef<[u8]>>(data: T, seed: u64) -> u64 {
+    #[inline]
+    fn round(acc: u64, input: u64) -> u64 {
+        acc.wrapping_add(input.wrapping_mul(PRIME64_2))
+            .rotate_left(31)
+            .wrapping_mul(PRIME64_1)
+    }
+
+    #[inline]
+    fn merge_round(acc: u64, val: u64) -> u64 {
+        (acc ^ round(0, val))
+            .wrapping_mul(PRIME64_1)
+            .wrapping_add(PRIME64_4)
+    }
+
+    #[inline]
+    fn read_u64(data: &[u8], offset: usize) -> u64 {
+        u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap())
+    }
+
+    #[inline]
+    fn read_u32(data: &[u8], offset: usize) -> u32 {
+        u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap())
+    }
+
+    let data = data.as_ref();
+    let length = data.len();
+    let mut offset = 0;
+
+    let mut hash = if length >= 32 {
+        let mut acc1 = seed.wrapping_add(PRIME64_1).wrapping_add(PRIME64_2);
+        let mut acc2 = seed.wrapping_add(PRIME64_2);
+        let mut acc3 = seed;
+        let mut acc4 = seed.wrapping_sub(PRIME64_1);
+        while offset + 32 <= length {
+            acc1 = round(acc1, read_u64(data, offset));
+            acc2 = round(acc2, read_u64(data, offset + 8));
+            acc3 = round(acc3, read_u64(data, offset + 16));
+            acc4 = round(acc4, read_u64(data, offset + 24));
+            offset += 32;
+        }
+        let mut hash = acc1
+            .rotate_left(1)
+            .wrapping_add(acc2.rotate_left(7))
+            .wrapping_add(acc3.rotate_left(12))
+            .wrapping_add(acc4.rotate_left(18));
+        hash = merge_round(hash, acc1);
+        hash = merge_round(hash, acc2);
+        hash = merge_round(hash, acc3);
+        merge_round(hash, acc4)
+    } else {
+        seed.wrapping_add(PRIME64_5)
+    };
+    hash = hash.wrapping_add(length as u64);
+
+    while offset + 8 <= length {
+        hash ^= round(0, read_u64(data, offset));
+        hash = hash
+            .rotate_left(27)
+            .wrapping_mul(PRIME64_1)
+            .wrapping_add(PRIME64_4);
+        offset += 8;
+    }
+    if offset + 4 <= length {
+        hash ^= (read_u32(data, offset) as u64).wrapping_mul(PRIME64_1);
+        hash = hash
+            .rotate_left(23)
+            .wrapping_mul(PRIME64_2)
+            .wrapping_add(PRIME64_3);
+        offset += 4;
+    }
+    while offset < length {
+        hash ^= (data[offset] as u64).wrapping_mul(PRIME64_5);
+        hash = hash.rotate_left(11).wrapping_mul(PRIME64_1);
+        offset += 1;
+    }
+
+    hash ^= hash >> 33;
+    hash = hash.wrapping_mul(PRIME64_2);
+    hash ^= hash >> 29;
+    hash = hash.wrapping_mul(PRIME64_3);
+    hash ^= hash >> 32;
+    hash
+}
+
+#[test]
+fn test_xxhash64() {
+    assert_eq!(spark_compatible_xxhash64(b"", 0), 0xef46db3751d8e999);
+    assert_eq!(spark_compatible_xxhash64(b"a", 0), 0xd24ec4f1a98c6e5b);
+    // longer than a stripe of 32 bytes
+    assert_eq!(
+        spark_compatible_xxhash64([b'x'; 100], 42),
+        0x0513559782e486c7
+    );
+}
+
 macro_rules! hash_array {
-    ($array_type:ident, $column: ident, $hashes: ident) => {
+    ($array_type:ident, $column: ident, $hashes: ident, $hash_method: ident) => {
         let array = $column.as_any().downcast_ref::<$array_type>().unwrap();
         if array.null_count() == 0 {
             for (i, hash) in $hashes.iter_mut().enumerate() {
-                *hash = spark_compatible_murmur3_hash(&array.value(i), *hash);
+                *hash = $hash_method(&array.value(i), *hash);
             }
         } else {
             for (i, hash) in $hashes.iter_mut().enumerate() {
                 if !array.is_null(i) {
-                    *hash = spark_compatible_murmur3_hash(&array.value(i), *hash);
+                    *hash = $hash_method(&array.value(i), *hash);
                 }
             }
         }
@@ -124,18 +422,18 @@ macro_rules! hash_array {
 }
 
 macro_rules! hash_array_primitive {
-    ($array_type:ident, $column: ident, $ty: ident, $hashes: ident) => {
+    ($array_type:ident, $column: ident, $ty: ident, $hashes: ident, $hash_method: ident) => {
         let array = $column.as_any().downcast_ref::<$array_type>().unwrap();
         let values = array.values();
 
         if array.null_count() == 0 {
             for (hash, value) in $hashes.iter_mut().zip(values.iter()) {
-                *hash = spark_compatible_murmur3_hash((*value as $ty).to_le_bytes(), *hash);
+                *hash = $hash_method((*value as $ty).to_le_bytes(), *hash);
             }
         } else {
             for (i, (hash, value)) in $hashes.iter_mut().zip(values.iter()).enumerate() {
                 if !array.is_null(i) {
-                    *hash = spark_compatible_murmur3_hash((*value as $ty).to_le_bytes(), *hash);
+                    *hash = $hash_method((*value as $ty).to_le_bytes(), *hash);
                 }
             }
         }
@@ -143,46 +441,68 @@ macro_rules! hash_array_primitive {
 }
 
 macro_rules! hash_array_primitive_float {
-    ($array_type:ident, $column: ident, $ty: ident, $ty2: ident, $hashes: ident) => {
+    (
+        $array_type:ident,
+        $column: ident,
+        $to_bits: ident,
+        $hashes: ident,
+        $hash_method: ident
+    ) => {
         let array = $column.as_any().downcast_ref::<$array_type>().unwrap();
         let values = array.values();
 
+        // Spark uses 0 as hash for -0.0 and hashes all the NaNs the same, see `HashExpression`.
         if array.null_count() == 0 {
             for (hash, value) in $hashes.iter_mut().zip(values.iter()) {
-                // Spark uses 0 as hash for -0.0, see `Murmur3Hash` expression.
-                if *value == 0.0 && value.is_sign_negative() {
-                    *hash = spark_compatible_murmur3_hash((0 as $ty2).to_le_bytes(), *hash);
-                } else {
-                    *hash = spark_compatible_murmur3_hash((*value as $ty).to_le_bytes(), *hash);
-                }
+                *hash = $hash_method($to_bits(*value).to_le_bytes(), *hash);
             }
         } else {
             for (i, (hash, value)) in $hashes.iter_mut().zip(values.iter()).enumerate() {
                 if !array.is_null(i) {
-                    // Spark uses 0 as hash for -0.0, see `Murmur3Hash` expression.
-                    if *value == 0.0 && value.is_sign_negative() {
-                        *hash = spark_compatible_murmur3_hash((0 as $ty2).to_le_bytes(), *hash);
-                    } else {
-                        *hash = spark_compatible_murmur3_hash((*value as $ty).to_le_bytes(), *hash);
-                    }
+                    *hash = $hash_method($to_bits(*value).to_le_bytes(), *hash);
                 }
             }
         }
     };
 }
 
+/// The maximum precision of a decimal whose unscaled value fits in a long, i.e.,
+/// `Decimal.MAX_LONG_DIGITS` in Spark.
+const MAX_LONG_DIGITS: u8 = 18;
+
+/// The minimal two's-complement big-endian bytes of `value`, like `BigInteger.toByteArray` in
+/// Java. Returns the bytes of `value` and the index of the first one of them.
+#[inline]
+fn big_integer_bytes(value: i128) -> ([u8; 16], usize) {
+    let bytes = value.to_be_bytes();
+    let mut start = 0;
+    // A leading byte can be dropped if it only repeats the sign bit of the next one
+    while start < 15
+        && ((bytes[start] == 0 && bytes[start + 1] & 0x80 == 0)
+            || (bytes[start] == 0xff && bytes[start + 1] & 0x80 != 0))
+    {
+        start += 1;
+    }
+    (bytes, start)
+}
+
+/// Like Spark, a decimal whose precision is at most `MAX_LONG_DIGITS` is hashed as the long of
+/// its unscaled value, and a wider one as the bytes of its unscaled `BigInteger`.
 macro_rules! hash_array_decimal {
-    ($array_type:ident, $column: ident, $hashes: ident) => {
+    ($array_type:ident, $column: ident, $hashes: ident, $hash_method: ident) => {
         let array = $column.as_any().downcast_ref::<$array_type>().unwrap();
 
-        if array.null_count() == 0 {
+        if array.precision() <= MAX_LONG_DIGITS {
             for (i, hash) in $hashes.iter_mut().enumerate() {
-                *hash = spark_compatible_murmur3_hash(array.value(i).to_le_bytes(), *hash);
+                if !array.is_null(i) {
+                    *hash = $hash_method((array.value(i) as i64).to_le_bytes(), *hash);
+                }
             }
         } else {
             for (i, hash) in $hashes.iter_mut().enumerate() {
                 if !array.is_null(i) {
-                    *hash = spark_compatible_murmur3_hash(array.value(i).to_le_bytes(), *hash);
+                    let (bytes, start) = big_integer_bytes(array.value(i));
+                    *hash = $hash_method(&bytes[start..], *hash);
                 }
             }
         }
@@ -218,150 +538,710 @@ fn create_hashes_dictionary<K: ArrowDictionaryKeyType>(
     Ok(())
 }
 
-/// Creates hash values for every row, based on the values in the
-/// columns.
-///
-/// The number of rows to hash is determined by `hashes_buffer.len()`.
-/// `hashes_buffer` should be pre-sized appropriately
-pub fn create_hashes<'a>(
-    arrays: &[ArrayRef],
-    hashes_buffer: &'a mut [u32],
-) -> Result<&'a mut [u32]> {
-    for col in arrays {
-        match col.data_type() {
-            DataType::Boolean => {
-                let array = col.as_any().downcast_ref::<BooleanArray>().unwrap();
-                if array.null_count() == 0 {
-                    for (i, hash) in hashes_buffer.iter_mut().enumerate() {
-                        *hash = spark_compatible_murmur3_hash(
-                            i32::from(array.value(i)).to_le_bytes(),
-                            *hash,
-                        );
-                    }
-                } else {
-                    for (i, hash) in hashes_buffer.iter_mut().enumerate() {
-                        if !array.is_null(i) {
-                            *hash = spark_compatible_murmur3_hash(
-                                i32::from(array.value(i)).to_le_bytes(),
-                                *hash,
-                            );
+/// Hash the values in a dictionary array with xxhash64. Each row is hashed with its own seed,
+/// so the dictionary is unpacked instead of hashing each dictionary value once.
+fn create_xxhash64_hashes_dictionary<K: ArrowDictionaryKeyType>(
+    array: &ArrayRef,
+    hashes_buffer: &mut [u64],
+) -> Result<()> {
+    let dict_array = array.as_any().downcast_ref::<DictionaryArray<K>>().unwrap();
+    let unpacked = take(dict_array.values().as_ref(), dict_array.keys(), None)?;
+    create_xxhash64_hashes(&[unpacked], hashes_buffer)?;
+    Ok(())
+}
+
+/// Hash the values in a list array like Spark, which hashes the elements of a list in order,
+/// each with the hash of the previous elements as seed. So a null list, an empty list and null
+/// elements don't update the hash, and nested lists are hashed like their flattened elements.
+fn create_list_hashes<O: OffsetSizeTrait, H: Copy>(
+    array: &ArrayRef,
+    hashes_buffer: &mut [H],
+    create_hashes: impl Fn(&[ArrayRef], &mut [H]) -> Result<()>,
+) -> Result<()> {
+    let list_array = array
+        .as_any()
+        .downcast_ref::<GenericListArray<O>>()
+        .unwrap();
+    hash_list_elements(
+        list_array.value_offsets(),
+        list_array.nulls(),
+        list_array.values(),
+        hashes_buffer,
+        create_hashes,
+    )
+}
+
+/// Hash the values in a map array like Spark, which hashes the key and then the value of each
+/// entry of a map in order, each with the hash of the previous ones as seed. This is the same as
+/// hashing a list of the entries, which are structs of the key and the value.
+fn create_map_hashes<H: Copy>(
+    array: &ArrayRef,
+    hashes_buffer: &mut [H],
+    create_hashes: impl Fn(&[ArrayRef], &mut [H]) -> Result<()>,
+) -> Result<()> {
+    let map_array = array.as_any().downcast_ref::<MapArray>().unwrap();
+    let entries = Arc::new(map_array.entries().clone()) as ArrayRef;
+    hash_list_elements(
+        map_array.value_offsets(),
+        map_array.nulls(),
+        &entries,
+        hashes_buffer,
+        create_hashes,
+    )
+}
+
+/// Hashes the elements of the lists given by `offsets` into `values` in order, into the hashes of
+/// their rows. The elements at the same position of the lists are hashed together by
+/// `create_hashes`, from the first position to the last one of the longest list.
+fn hash_list_elements<O: OffsetSizeTrait, H: Copy>(
+    offsets: &[O],
+    nulls: Option<&NullBuffer>,
+    values: &ArrayRef,
+    hashes_buffer: &mut [H],
+    create_hashes: impl Fn(&[ArrayRef], &mut [H]) -> Result<()>,
+) -> Result<()> {
+    let is_valid = |i: usize| nulls.map_or(true, |nulls| nulls.is_valid(i));
+
+    // The rows whose lists have an element at the current position
+    let mut rows = (0..hashes_buffer.len())
+        .filter(|&i| is_valid(i) && offsets[i + 1] > offsets[i])
+        .collect::<Vec<_>>();
+    let mut position = 0;
+    while !rows.is_empty() {
+        let indices = rows
+            .iter()
+            .map(|&i| (offsets[i].as_usize() + position) as u64)
+            .collect::<UInt64Array>();
+        let elements = unpack_dictionary(take(values.as_ref(), &indices, None)?)?;
+        let mut element_hashes = rows.iter().map(|&i| hashes_buffer[i]).collect::<Vec<_>>();
+        create_hashes(&[elements], &mut element_hashes)?;
+        for (&i, hash) in rows.iter().zip(element_hashes) {
+            hashes_buffer[i] = hash;
+        }
+
+        position += 1;
+        rows.retain(|&i| offsets[i].as_usize() + position < offsets[i + 1].as_usize());
+    }
+    Ok(())
+}
+
+/// Unpacks a dictionary array nested in a list or a struct, as the hash of a dictionary value
+/// doesn't depend on the hash of the previous elements or fields.
+fn unpack_dictionary(array: ArrayRef) -> Result<ArrayRef> {
+    match array.data_type() {
+        DataType::Dictionary(_, value_type) => Ok(cast(&array, value_type)?),
+        _ => Ok(array),
+    }
+}
+
+/// Hash the values in a struct array like Spark, which hashes the fields of a struct in order,
+/// each with the hash of the previous fields as seed. So a null struct and null fields don't
+/// update the hash.
+fn create_struct_hashes<H: Copy>(
+    array: &ArrayRef,
+    hashes_buffer: &mut [H],
+    create_hashes: impl Fn(&[ArrayRef], &mut [H]) -> Result<()>,
+) -> Result<()> {
+    let struct_array = array.as_any().downcast_ref::<StructArray>().unwrap();
+    if struct_array.null_count() == 0 {
+        let fields = struct_array
+            .columns()
+            .iter()
+            .map(|field| unpack_dictionary(Arc::clone(field)))
+            .collect::<Result<Vec<_>>>()?;
+        return create_hashes(&fields, hashes_buffer);
+    }
+
+    // The fields of a null struct may have any values, so only the rows of non-null structs are
+    // hashed
+    let rows = (0..hashes_buffer.len())
+        .filter(|&i| struct_array.is_valid(i))
+        .collect::<Vec<_>>();
+    let indices = rows.iter().map(|&i| i as u32).collect::<UInt32Array>();
+    let fields = struct_array
+        .columns()
+        .iter()
+        .map(|field| unpack_dictionary(take(field.as_ref(), &indices, None)?))
+        .collect::<Result<Vec<_>>>()?;
+    let mut field_hashes = rows.iter().map(|&i| hashes_buffer[i]).collect::<Vec<_>>();
+    create_hashes(&fields, &mut field_hashes)?;
+    for (&i, hash) in rows.iter().zip(field_hashes) {
+        hashes_buffer[i] = hash;
+    }
+    Ok(())
+}
+
+macro_rules! create_hashes_internal {
+    (
+        $arrays: ident,
+        $hashes_buffer: ident,
+        $hash_method: ident,
+        $create_dictionary_hashes: ident,
+        $create_hashes: ident
+    ) => {
+        for col in $arrays {
+            match col.data_type() {
+                DataType::Boolean => {
+                    let array = col.as_any().downcast_ref::<BooleanArray>().unwrap();
+                    if array.null_count() == 0 {
+                        for (i, hash) in $hashes_buffer.iter_mut().enumerate() {
+                            *hash = $hash_method(i32::from(array.value(i)).to_le_bytes(), *hash);
+                        }
+                    } else {
+                        for (i, hash) in $hashes_buffer.iter_mut().enumerate() {
+                            if !array.is_null(i) {
+                                *hash =
+                                    $hash_method(i32::from(array.value(i)).to_le_bytes(), *hash);
+                            }
                         }
                     }
                 }
-            }
-            DataType::Int8 => {
-                hash_array_primitive!(Int8Array, col, i32, hashes_buffer);
-            }
-            DataType::Int16 => {
-                hash_array_primitive!(Int16Array, col, i32, hashes_buffer);
-            }
-            DataType::Int32 => {
-                hash_array_primitive!(Int32Array, col, i32, hashes_buffer);
-            }
-            DataType::Int64 => {
-                hash_array_primitive!(Int64Array, col, i64, hashes_buffer);
-            }
-            DataType::Float32 => {
-                hash_array_primitive_float!(Float32Array, col, f32, i32, hashes_buffer);
-            }
-            DataType::Float64 => {
-                hash_array_primitive_float!(Float64Array, col, f64, i64, hashes_buffer);
-            }
-            DataType::Timestamp(TimeUnit::Second, _) => {
-                hash_array_primitive!(TimestampSecondArray, col, i64, hashes_buffer);
-            }
-            DataType::Timestamp(TimeUnit::Millisecond, _) => {
-                hash_array_primitive!(TimestampMillisecondArray, col, i64, hashes_buffer);
-            }
-            DataType::Timestamp(TimeUnit::Microsecond, _) => {
-                hash_array_primitive!(TimestampMicrosecondArray, col, i64, hashes_buffer);
-            }
-            DataType::Timestamp(TimeUnit::Nanosecond, _) => {
-                hash_array_primitive!(TimestampNanosecondArray, col, i64, hashes_buffer);
-            }
-            DataType::Date32 => {
-                hash_array_primitive!(Date32Array, col, i32, hashes_buffer);
-            }
-            DataType::Date64 => {
-                hash_array_primitive!(Date64Array, col, i64, hashes_buffer);
-            }
-            DataType::Utf8 => {
-                hash_array!(StringArray, col, hashes_buffer);
-            }
-            DataType::LargeUtf8 => {
-                hash_array!(LargeStringArray, col, hashes_buffer);
-            }
-            DataType::Binary => {
-                hash_array!(BinaryArray, col, hashes_buffer);
-            }
-            DataType::LargeBinary => {
-                hash_array!(LargeBinaryArray, col, hashes_buffer);
-            }
-            DataType::FixedSizeBinary(_) => {
-                hash_array!(FixedSizeBinaryArray, col, hashes_buffer);
-            }
-            DataType::Decimal128(_, _) => {
-                hash_array_decimal!(Decimal128Array, col, hashes_buffer);
-            }
-            DataType::Dictionary(index_type, _) => match **index_type {
                 DataType::Int8 => {
-                    create_hashes_dictionary::<Int8Type>(col, hashes_buffer)?;
+                    hash_array_primitive!(Int8Array, col, i32, $hashes_buffer, $hash_method);
                 }
                 DataType::Int16 => {
-                    create_hashes_dictionary::<Int16Type>(col, hashes_buffer)?;
+                    hash_array_primitive!(Int16Array, col, i32, $hashes_buffer, $hash_method);
                 }
                 DataType::Int32 => {
-                    create_hashes_dictionary::<Int32Type>(col, hashes_buffer)?;
+                    hash_array_primitive!(Int32Array, col, i32, $hashes_buffer, $hash_method);
                 }
                 DataType::Int64 => {
-                    create_hashes_dictionary::<Int64Type>(col, hashes_buffer)?;
+                    hash_array_primitive!(Int64Array, col, i64, $hashes_buffer, $hash_method);
+                }
+                DataType::Float32 => {
+                    hash_array_primitive_float!(
+                        Float32Array,
+                        col,
+                        float_to_int_bits,
+                        $hashes_buffer,
+                        $hash_method
+                    );
+                }
+                DataType::Float64 => {
+                    hash_array_primitive_float!(
+                        Float64Array,
+                        col,
+                        double_to_long_bits,
+                        $hashes_buffer,
+                        $hash_method
+                    );
+                }
+                DataType::Timestamp(TimeUnit::Second, _) => {
+                    hash_array_primitive!(
+                        TimestampSecondArray,
+                        col,
+                        i64,
+                        $hashes_buffer,
+                        $hash_method
+                    );
+                }
+                DataType::Timestamp(TimeUnit::Millisecond, _) => {
+                    hash_array_primitive!(
+                        TimestampMillisecondArray,
+                        col,
+                        i64,
+                        $hashes_buffer,
+                        $hash_method
+                    );
+                }
+                DataType::Timestamp(TimeUnit::Microsecond, _) => {
+                    hash_array_primitive!(
+                        TimestampMicrosecondArray,
+                        col,
+                        i64,
+                        $hashes_buffer,
+                        $hash_method
+                    );
+                }
+                DataType::Timestamp(TimeUnit::Nanosecond, _) => {
+                    hash_array_primitive!(
+                        TimestampNanosecondArray,
+                        col,
+                        i64,
+                        $hashes_buffer,
+                        $hash_method
+                    );
+                }
+                DataType::Date32 => {
+                    hash_array_primitive!(Date32Array, col, i32, $hashes_buffer, $hash_method);
+                }
+                DataType::Date64 => {
+                    hash_array_primitive!(Date64Array, col, i64, $hashes_buffer, $hash_method);
                 }
-                DataType::UInt8 => {
-                    create_hashes_dictionary::<UInt8Type>(col, hashes_buffer)?;
+                DataType::Utf8 => {
+                    hash_array!(StringArray, col, $hashes_buffer, $hash_method);
                 }
-                DataType::UInt16 => {
-                    create_hashes_dictionary::<UInt16Type>(col, hashes_buffer)?;
+                DataType::LargeUtf8 => {
+                    hash_array!(LargeStringArray, col, $hashes_buffer, $hash_method);
                 }
-                DataType::UInt32 => {
-                    create_hashes_dictionary::<UInt32Type>(col, hashes_buffer)?;
+                DataType::Binary => {
+                    hash_array!(BinaryArray, col, $hashes_buffer, $hash_method);
                 }
-                DataType::UInt64 => {
-                    create_hashes_dictionary::<UInt64Type>(col, hashes_buffer)?;
+                DataType::LargeBinary => {
+                    hash_array!(LargeBinaryArray, col, $hashes_buffer, $hash_method);
                 }
+                DataType::FixedSizeBinary(_) => {
+                    hash_array!(FixedSizeBinaryArray, col, $hashes_buffer, $hash_method);
+                }
+                DataType::Decimal128(_, _) => {
+                    hash_array_decimal!(Decimal128Array, col, $hashes_buffer, $hash_method);
+                }
+                DataType::List(_) => {
+                    create_list_hashes::<i32, _>(col, $hashes_buffer, |arrays, hashes| {
+                        $create_hashes(arrays, hashes).map(|_| ())
+                    })?;
+                }
+                DataType::LargeList(_) => {
+                    create_list_hashes::<i64, _>(col, $hashes_buffer, |arrays, hashes| {
+                        $create_hashes(arrays, hashes).map(|_| ())
+                    })?;
+                }
+                DataType::Map(_, _) => {
+                    create_map_hashes(col, $hashes_buffer, |arrays, hashes| {
+                        $create_hashes(arrays, hashes).map(|_| ())
+                    })?;
+                }
+                DataType::Struct(_) => {
+                    create_struct_hashes(col, $hashes_buffer, |arrays, hashes| {
+                        $create_hashes(arrays, hashes).map(|_| ())
+                    })?;
+                }
+                DataType::Dictionary(index_type, _) => match **index_type {
+                    DataType::Int8 => {
+                        $create_dictionary_hashes::<Int8Type>(col, $hashes_buffer)?;
+                    }
+                    DataType::Int16 => {
+                        $create_dictionary_hashes::<Int16Type>(col, $hashes_buffer)?;
+                    }
+                    DataType::Int32 => {
+                        $create_dictionary_hashes::<Int32Type>(col, $hashes_buffer)?;
+                    }
+                    DataType::Int64 => {
+                        $create_dictionary_hashes::<Int64Type>(col, $hashes_buffer)?;
+                    }
+                    DataType::UInt8 => {
+                        $create_dictionary_hashes::<UInt8Type>(col, $hashes_buffer)?;
+                    }
+                    DataType::UInt16 => {
+                        $create_dictionary_hashes::<UInt16Type>(col, $hashes_buffer)?;
+                    }
+                    DataType::UInt32 => {
+                        $create_dictionary_hashes::<UInt32Type>(col, $hashes_buffer)?;
+                    }
+                    DataType::UInt64 => {
+                        $create_dictionary_hashes::<UInt64Type>(col, $hashes_buffer)?;
+                    }
+                    _ => {
+                        return Err(DataFusionError::Internal(format!(
+                            "Unsupported dictionary type in hasher hashing: {}",
+                            col.data_type(),
+                        )))
+                    }
+                },
                 _ => {
+                    // This is internal because we should have caught this before.
                     return Err(DataFusionError::Internal(format!(
-                        "Unsupported dictionary type in hasher hashing: {}",
-                        col.data_type(),
-                    )))
+                        "Unsupported data type in hasher: {}",
+                        col.data_type()
+                    )));
                 }
-            },
-            _ => {
-                // This is internal because we should have caught this before.
-                return Err(DataFusionError::Internal(format!(
-                    "Unsupported data type in hasher: {}",
-                    col.data_type()
-                )));
             }
         }
+    };
+}
+
+/// Creates hash values for every row, based on the values in the
+/// columns.
+///
+/// The number of rows to hash is determined by `hashes_buffer.len()`.
+/// `hashes_buffer` should be pre-sized appropriately
+pub fn create_hashes<'a>(
+    arrays: &[ArrayRef],
+    hashes_buffer: &'a mut [u32],
+) -> Result<&'a mut [u32]> {
+    for col in arrays {
+        // Fixed-width primitive columns are hashed in batches of lanes
+        if !create_fixed_width_hashes(col, hashes_buffer) {
+            let column = std::slice::from_ref(col);
+            create_hashes_internal!(
+                column,
+                hashes_buffer,
+                spark_compatible_murmur3_hash,
+                create_hashes_dictionary,
+                create_hashes
+            );
+        }
+    }
+    Ok(hashes_buffer)
+}
+
+/// Creates Spark-compatible xxhash64 values for every row, based on the values in the columns,
+/// like [`create_hashes`] does for murmur3 hashes.
+///
+/// The number of rows to hash is determined by `hashes_buffer.len()`.
+/// `hashes_buffer` should be pre-sized appropriately
+pub fn create_xxhash64_hashes<'a>(
+    arrays: &[ArrayRef],
+    hashes_buffer: &'a mut [u64],
+) -> Result<&'a mut [u64]> {
+    create_hashes_internal!(
+        arrays,
+        hashes_buffer,
+        spark_compatible_xxhash64,
+        create_xxhash64_hashes_dictionary,
+        create_xxhash64_hashes
+    );
+    Ok(hashes_buffer)
+}
+
+/// Creates Spark-compatible Hive hash values for every row, based on the values in the columns,
+/// i.e., `HiveHash` in Spark, which Hive bucketed tables are bucketed by instead of murmur3. Unlike
+/// [`create_hashes`], the hash of a row is `31 * hash + column_hash` for each column, and
+/// `hashes_buffer` should be filled with 0, the seed of `HiveHash`. The hashes are `int`s in
+/// Spark, so [`pmod_in_place`] computes the bucket ids of the rows from them.
+///
+/// The number of rows to hash is determined by `hashes_buffer.len()`.
+/// `hashes_buffer` should be pre-sized appropriately
+pub fn create_hive_hashes<'a>(
+    arrays: &[ArrayRef],
+    hashes_buffer: &'a mut [u32],
+) -> Result<&'a mut [u32]> {
+    for col in arrays {
+        let col_hashes = hive_hash_array(col)?;
+        for (hash, col_hash) in hashes_buffer.iter_mut().zip(col_hashes) {
+            *hash = hash.wrapping_mul(31).wrapping_add(col_hash);
+        }
     }
     Ok(hashes_buffer)
 }
 
-pub(crate) fn pmod(hash: u32, n: usize) -> usize {
-    let hash = hash as i32;
+/// The Hive hash of a long, i.e., `(int) (value ^ (value >>> 32))` in Java.
+#[inline]
+fn hive_hash_long(value: i64) -> u32 {
+    (value ^ ((value as u64) >> 32) as i64) as u32
+}
+
+#[inline]
+fn hive_hash_bytes(bytes: &[u8]) -> u32 {
+    // Bytes are signed in Java
+    bytes.iter().fold(0_u32, |hash, byte| {
+        hash.wrapping_mul(31).wrapping_add(*byte as i8 as u32)
+    })
+}
+
+/// The Hive hash of a timestamp in microseconds, whose seconds and nanoseconds are packed into
+/// a long like the `TimestampWritable` of Hive.
+#[inline]
+fn hive_hash_timestamp(micros: i64) -> u32 {
+    let seconds = micros / 1_000_000;
+    let nanos = (micros % 1_000_000) * 1_000;
+    hive_hash_long((seconds << 30) | nanos)
+}
+
+/// The Hive hash of a decimal, i.e., the `hashCode` of its `java.math.BigDecimal` normalized like
+/// a `HiveDecimal`, without trailing zeros and with a non-negative scale.
+#[inline]
+fn hive_hash_decimal(unscaled: i128, scale: i8) -> u32 {
+    if unscaled == 0 {
+        return 0;
+    }
+    let (mut unscaled, mut scale) = (unscaled, scale as i32);
+    while unscaled % 10 == 0 && scale > 0 {
+        unscaled /= 10;
+        scale -= 1;
+    }
+    while scale < 0 {
+        unscaled *= 10;
+        scale += 1;
+    }
+    // `BigInteger.hashCode`, which combines the big-endian ints of the magnitude
+    let magnitude = unscaled.unsigned_abs();
+    let hash = (0..4)
+        .rev()
+        .map(|i| (magnitude >> (i * 32)) as u32)
+        .skip_while(|word| *word == 0)
+        .fold(0_u32, |hash, word| hash.wrapping_mul(31).wrapping_add(word));
+    let hash = if unscaled < 0 {
+        hash.wrapping_neg()
+    } else {
+        hash
+    };
+    hash.wrapping_mul(31).wrapping_add(scale as u32)
+}
+
+#[inline]
+fn hive_hash_values<T>(
+    values: impl Iterator<Item = Option<T>>,
+    hash: impl Fn(T) -> u32,
+) -> Vec<u32> {
+    values.map(|value| value.map_or(0, &hash)).collect()
+}
+
+/// The Hive hash of each value of `array`, i.e., `HiveHashFunction.hash` in Spark. A null value
+/// is hashed as 0.
+fn hive_hash_array(array: &ArrayRef) -> Result<Vec<u32>> {
+    let hashes = match array.data_type() {
+        DataType::Null => vec![0; array.len()],
+        DataType::Boolean => hive_hash_values(array.as_boolean().iter(), |v| v as u32),
+        DataType::Int8 => hive_hash_values(array.as_primitive::<Int8Type>().iter(), |v| v as u32),
+        DataType::Int16 => hive_hash_values(array.as_primitive::<Int16Type>().iter(), |v| v as u32),
+        DataType::Int32 => hive_hash_values(array.as_primitive::<Int32Type>().iter(), |v| v as u32),
+        DataType::Date32 => {
+            hive_hash_values(array.as_primitive::<Date32Type>().iter(), |v| v as u32)
+        }
+        DataType::Int64 => {
+            hive_hash_values(array.as_primitive::<Int64Type>().iter(), hive_hash_long)
+        }
+        // Like `Float.floatToIntBits`, the zeros are hashed the same and NaNs are canonical
+        DataType::Float32 => hive_hash_values(
+            array.as_primitive::<Float32Type>().iter(),
+            float_to_int_bits,
+        ),
+        DataType::Float64 => hive_hash_values(array.as_primitive::<Float64Type>().iter(), |v| {
+            hive_hash_long(double_to_long_bits(v) as i64)
+        }),
+        DataType::Utf8 => hive_hash_values(array.as_string::<i32>().iter(), |v| {
+            hive_hash_bytes(v.as_bytes())
+        }),
+        DataType::LargeUtf8 => hive_hash_values(array.as_string::<i64>().iter(), |v| {
+            hive_hash_bytes(v.as_bytes())
+        }),
+        DataType::Binary => hive_hash_values(array.as_binary::<i32>().iter(), hive_hash_bytes),
+        DataType::LargeBinary => hive_hash_values(array.as_binary::<i64>().iter(), hive_hash_bytes),
+        DataType::Decimal128(_, scale) => {
+            hive_hash_values(array.as_primitive::<Decimal128Type>().iter(), |v| {
+                hive_hash_decimal(v, *scale)
+            })
+        }
+        DataType::Timestamp(TimeUnit::Microsecond, _) => hive_hash_values(
+            array.as_primitive::<TimestampMicrosecondType>().iter(),
+            hive_hash_timestamp,
+        ),
+        DataType::List(_) => {
+            let list = array.as_list::<i32>();
+            hive_hash_list(list.offsets(), list.nulls(), list.values())?
+        }
+        DataType::LargeList(_) => {
+            let list = array.as_list::<i64>();
+            hive_hash_list(list.offsets(), list.nulls(), list.values())?
+        }
+        DataType::Map(_, _) => {
+            // The hash of a map is the sum of `hash(key) ^ hash(value)` of its entries, so it
+            // doesn't depend on their order
+            let map = array.as_map();
+            let key_hashes = hive_hash_array(map.keys())?;
+            let value_hashes = hive_hash_array(map.values())?;
+            map.offsets()
+                .windows(2)
+                .enumerate()
+                .map(|(i, offsets)| {
+                    if map.is_null(i) {
+                        return 0;
+                    }
+                    (offsets[0] as usize..offsets[1] as usize).fold(0_u32, |hash, j| {
+                        hash.wrapping_add(key_hashes[j] ^ value_hashes[j])
+                    })
+                })
+                .collect()
+        }
+        DataType::Struct(_) => {
+            let struct_array = array.as_struct();
+            let mut hashes = vec![0_u32; array.len()];
+            for column in struct_array.columns() {
+                for (hash, field_hash) in hashes.iter_mut().zip(hive_hash_array(column)?) {
+                    *hash = hash.wrapping_mul(31).wrapping_add(field_hash);
+                }
+            }
+            if let Some(nulls) = struct_array.nulls() {
+                for (hash, valid) in hashes.iter_mut().zip(nulls.iter()) {
+                    if !valid {
+                        *hash = 0;
+                    }
+                }
+            }
+            hashes
+        }
+        DataType::Dictionary(_, _) => {
+            // Hashes each dictionary value only once
+            let dict = array.as_any_dictionary();
+            let value_hashes = hive_hash_array(dict.values())?;
+            dict.normalized_keys()
+                .into_iter()
+                .enumerate()
+                .map(|(i, key)| {
+                    if dict.keys().is_null(i) {
+                        0
+                    } else {
+                        value_hashes[key]
+                    }
+                })
+                .collect()
+        }
+        _ => {
+            return Err(DataFusionError::Internal(format!(
+                "Unsupported data type in Hive hasher: {}",
+                array.data_type()
+            )));
+        }
+    };
+    Ok(hashes)
+}
+
+/// The Hive hashes of lists, which fold the hashes of their elements like the columns of a row.
+fn hive_hash_list<O: OffsetSizeTrait>(
+    offsets: &OffsetBuffer<O>,
+    nulls: Option<&NullBuffer>,
+    values: &ArrayRef,
+) -> Result<Vec<u32>> {
+    let value_hashes = hive_hash_array(values)?;
+    Ok(offsets
+        .windows(2)
+        .enumerate()
+        .map(|(i, offsets)| {
+            if nulls.is_some_and(|nulls| nulls.is_null(i)) {
+                return 0;
+            }
+            (offsets[0].as_usize()..offsets[1].as_usize()).fold(0_u32, |hash, j| {
+                hash.wrapping_mul(31).wrapping_add(value_hashes[j])
+            })
+        })
+        .collect())
+}
+
+/// Computes the Spark hash partition ids of the rows, i.e., `pmod(murmur3_hash(row, 42), n)`
+/// like `HashPartitioning.partitionIdExpression` in Spark, and returns them in `hashes_buffer`.
+///
+/// The number of rows is determined by `hashes_buffer.len()`.
+pub fn compute_partition_ids<'a>(
+    arrays: &[ArrayRef],
+    num_partitions: usize,
+    hashes_buffer: &'a mut [u32],
+) -> Result<&'a mut [u32]> {
+    let hashes = hash_for_partitioning(arrays, num_partitions, hashes_buffer)?;
+    pmod_in_place(hashes, num_partitions);
+    Ok(hashes)
+}
+
+/// Same as [`compute_partition_ids`], and also counts the rows of each partition into
+/// `partition_counts` in the same pass, e.g., to pre-size the buffers of each partition.
+pub fn compute_partition_ids_with_counts<'a>(
+    arrays: &[ArrayRef],
+    num_partitions: usize,
+    hashes_buffer: &'a mut [u32],
+    partition_counts: &mut [usize],
+) -> Result<&'a mut [u32]> {
+    let hashes = hash_for_partitioning(arrays, num_partitions, hashes_buffer)?;
+    hashes_to_partition_ids_with_counts(hashes, num_partitions, partition_counts)?;
+    Ok(hashes)
+}
+
+/// Computes the Spark murmur3 hashes used by hash partitioning, i.e., `murmur3_hash(row, 42)`,
+/// into `hashes_buffer`, without taking their modulo by the number of partitions.
+pub fn compute_partitioning_hashes<'a>(
+    arrays: &[ArrayRef],
+    hashes_buffer: &'a mut [u32],
+) -> Result<&'a mut [u32]> {
+    // use identical seed as spark hash partition
+    hashes_buffer.fill(42_u32);
+    create_hashes(arrays, hashes_buffer)
+}
+
+/// Replaces the hashes from [`compute_partitioning_hashes`] with the partition ids in place, and
+/// counts the rows of each partition into `partition_counts`.
+pub fn hashes_to_partition_ids_with_counts(
+    hashes: &mut [u32],
+    num_partitions: usize,
+    partition_counts: &mut [usize],
+) -> Result<()> {
+    check_num_partitions(num_partitions)?;
+    if partition_counts.len() != num_partitions {
+        return Err(DataFusionError::Internal(format!(
+            "Expected {} partition counts but got {}",
+            num_partitions,
+            partition_counts.len()
+        )));
+    }
+    partition_counts.fill(0);
+
+    if num_partitions.is_power_of_two() {
+        let mask = num_partitions as u32 - 1;
+        for hash in hashes.iter_mut() {
+            *hash &= mask;
+            partition_counts[*hash as usize] += 1;
+        }
+    } else {
+        for hash in hashes.iter_mut() {
+            *hash = pmod(*hash, num_partitions) as u32;
+            partition_counts[*hash as usize] += 1;
+        }
+    }
+    Ok(())
+}
+
+fn hash_for_partitioning<'a>(
+    arrays: &[ArrayRef],
+    num_partitions: usize,
+    hashes_buffer: &'a mut [u32],
+) -> Result<&'a mut [u32]> {
+    check_num_partitions(num_partitions)?;
+    compute_partitioning_hashes(arrays, hashes_buffer)
+}
+
+fn check_num_partitions(num_partitions: usize) -> Result<()> {
+    if num_partitions == 0 || num_partitions > i32::MAX as usize {
+        return Err(DataFusionError::Internal(format!(
+            "Invalid number of partitions: {}",
+            num_partitions
+        )));
+    }
+    Ok(())
+}
+
+/// Replaces the hashes with their [`pmod`] by `n` in place.
+///
+/// When `n` is a power of two, the non-negative modulo is a bit mask of the hash, as the low bits
+/// of a hash are the same as its non-negative modulo as a signed integer. Otherwise the loop is
+/// still branch-free, so the compiler can vectorize it.
+pub fn pmod_in_place(hashes: &mut [u32], n: usize) {
+    if n.is_power_of_two() {
+        let mask = n as u32 - 1;
+        hashes.iter_mut().for_each(|hash| *hash &= mask);
+    } else {
+        hashes
+            .iter_mut()
+            .for_each(|hash| *hash = pmod(*hash, n) as u32);
+    }
+}
+
+/// The non-negative modulo of `hash` as a signed integer, like `Pmod` in Spark.
+#[inline]
+pub fn pmod(hash: u32, n: usize) -> usize {
     let n = n as i32;
-    let r = hash % n;
-    let result = if r < 0 { (r + n) % n } else { r };
-    result as usize
+    let r = hash as i32 % n;
+    // Adds `n` to negative remainders without a branch
+    (r + ((r >> 31) & n)) as usize
 }
 
 #[cfg(test)]
 mod tests {
     use arrow::array::{Float32Array, Float64Array};
+    use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
     use std::sync::Arc;
 
-    use crate::execution::datafusion::spark_hash::{create_hashes, pmod};
-    use datafusion::arrow::array::{ArrayRef, Int32Array, Int64Array, Int8Array, StringArray};
+    use crate::execution::datafusion::spark_hash::{
+        compute_partition_ids, compute_partition_ids_with_counts, create_bloom_filter_hashes,
+        create_hashes, create_hive_hashes, create_xxhash64_hashes, pmod,
+        spark_compatible_murmur3_hash,
+    };
+    use datafusion::arrow::{
+        array::{
+            Array, ArrayRef, Date32Array, Decimal128Array, DictionaryArray, Int16Array, Int32Array,
+            Int32Builder, Int64Array, Int8Array, ListArray, MapBuilder, StringArray, StringBuilder,
+            StructArray, TimestampMicrosecondArray,
+        },
+        buffer::{NullBuffer, OffsetBuffer},
+        datatypes::{DataType, Field, Int32Type},
+    };
 
     macro_rules! test_hashes {
         ($ty:ty, $values:expr, $expected:expr) => {
@@ -372,6 +1252,15 @@ mod tests {
         };
     }
 
+    macro_rules! test_xxhash64_hashes {
+        ($ty:ty, $values:expr, $expected:expr) => {
+            let i = Arc::new(<$ty>::from($values)) as ArrayRef;
+            let mut hashes = vec![42; $values.len()];
+            create_xxhash64_hashes(&[i], &mut hashes).unwrap();
+            assert_eq!(hashes, $expected);
+        };
+    }
+
     #[test]
     fn test_i8() {
         test_hashes!(
@@ -431,6 +1320,42 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_decimal() {
+        let values = vec![Some(1), Some(0), Some(-1), None, Some(12345), Some(-12345)];
+
+        // Hashed as the long of the unscaled value
+        let array = Arc::new(
+            Decimal128Array::from(values.clone())
+                .with_precision_and_scale(10, 2)
+                .unwrap(),
+        ) as ArrayRef;
+        let mut hashes = vec![42; values.len()];
+        create_hashes(&[array], &mut hashes).unwrap();
+        assert_eq!(
+            hashes,
+            vec![0x99f0149d, 0x9c67b85d, 0xc8008529, 42, 0x5467c2e0, 0x8b3434e6]
+        );
+
+        // Hashed as the bytes of the unscaled `BigInteger`
+        let mut values = values;
+        values.extend([Some(10_i128.pow(20)), Some(-(10_i128.pow(20)))]);
+        let array = Arc::new(
+            Decimal128Array::from(values.clone())
+                .with_precision_and_scale(38, 2)
+                .unwrap(),
+        ) as ArrayRef;
+        let mut hashes = vec![42; values.len()];
+        create_hashes(&[array], &mut hashes).unwrap();
+        assert_eq!(
+            hashes,
+            vec![
+                0xe8f30d16, 0xd1497b27, 0x535b391c, 42, 0x2325cc32, 0x0fcca404, 0x2a285eda,
+                0x364339a0
+            ]
+        );
+    }
+
     #[test]
     fn test_f32() {
         test_hashes!(
@@ -491,6 +1416,36 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_nan() {
+        // All the NaNs are hashed like the canonical NaN, whatever their sign and payload
+        let f32_nans: ArrayRef = Arc::new(Float32Array::from(vec![
+            f32::NAN,
+            -f32::NAN,
+            f32::from_bits(0x7fc00001),
+            f32::from_bits(0xffffffff),
+        ]));
+        let f64_nans: ArrayRef = Arc::new(Float64Array::from(vec![
+            f64::NAN,
+            -f64::NAN,
+            f64::from_bits(0x7ff8000000000001),
+            f64::from_bits(0xffffffffffffffff),
+        ]));
+        for nans in [f32_nans, f64_nans] {
+            let mut hashes = vec![42; nans.len()];
+            create_hashes(&[nans.clone()], &mut hashes).unwrap();
+            assert!(hashes.iter().all(|h| *h == hashes[0]));
+
+            let mut hashes = vec![42; nans.len()];
+            create_xxhash64_hashes(&[nans.clone()], &mut hashes).unwrap();
+            assert!(hashes.iter().all(|h| *h == hashes[0]));
+
+            let mut hashes = vec![0; nans.len()];
+            create_hive_hashes(&[nans], &mut hashes).unwrap();
+            assert!(hashes.iter().all(|h| *h == hashes[0]));
+        }
+    }
+
     #[test]
     fn test_str() {
         test_hashes!(
@@ -513,6 +1468,140 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_list() {
+        let list = Arc::new(ListArray::from_iter_primitive::<Int32Type, _, _>(vec![
+            Some(vec![Some(1), Some(2)]),
+            None,
+            Some(vec![]),
+            Some(vec![Some(3), None]),
+            Some(vec![Some(1), Some(2), Some(3)]),
+        ])) as ArrayRef;
+        let mut hashes = vec![42; list.len()];
+        create_hashes(&[list], &mut hashes).unwrap();
+        assert_eq!(hashes, vec![0xf2b63325, 42, 42, 0x9355fa23, 0xc995f9af]);
+
+        // Nested lists are hashed like their flattened elements
+        let inner = ListArray::from_iter_primitive::<Int32Type, _, _>(vec![
+            Some(vec![Some(1)]),
+            Some(vec![Some(2), Some(3)]),
+            Some(vec![Some(3), None]),
+        ]);
+        let nested = Arc::new(ListArray::new(
+            Arc::new(Field::new("item", inner.data_type().clone(), true)),
+            OffsetBuffer::new(vec![0, 2, 3].into()),
+            Arc::new(inner),
+            None,
+        )) as ArrayRef;
+        let mut hashes = vec![42; nested.len()];
+        create_hashes(&[nested], &mut hashes).unwrap();
+        assert_eq!(hashes, vec![0xc995f9af, 0x9355fa23]);
+    }
+
+    #[test]
+    fn test_map() {
+        let mut builder = MapBuilder::new(None, StringBuilder::new(), Int32Builder::new());
+        for (key, value) in [("a", Some(1)), ("b", Some(2))] {
+            builder.keys().append_value(key);
+            builder.values().append_option(value);
+        }
+        builder.append(true).unwrap();
+        builder.append(false).unwrap();
+        builder.append(true).unwrap();
+        builder.keys().append_value("c");
+        builder.values().append_null();
+        builder.append(true).unwrap();
+        let map = Arc::new(builder.finish()) as ArrayRef;
+
+        // Keys and values are hashed in entry order, and null values are skipped
+        let mut hashes = vec![42; map.len()];
+        create_hashes(&[map], &mut hashes).unwrap();
+        assert_eq!(hashes, vec![0xd0af9c77, 42, 42, 0x8160701a]);
+    }
+
+    #[test]
+    fn test_struct() {
+        let a = Arc::new(Int32Array::from(vec![Some(1), Some(5), Some(3), None])) as ArrayRef;
+        let b = Arc::new(Int64Array::from(vec![Some(2), Some(6), None, Some(4)])) as ArrayRef;
+        let struct_array = Arc::new(StructArray::new(
+            vec![
+                Field::new("a", DataType::Int32, true),
+                Field::new("b", DataType::Int64, true),
+            ]
+            .into(),
+            vec![a, b],
+            Some(NullBuffer::from(vec![true, false, true, true])),
+        )) as ArrayRef;
+        let mut hashes = vec![42; struct_array.len()];
+        create_hashes(&[struct_array], &mut hashes).unwrap();
+        assert_eq!(hashes, vec![0x398c0b07, 42, 0x9355fa23, 0x50209a54]);
+    }
+
+    #[test]
+    fn test_xxhash64_hashes() {
+        test_xxhash64_hashes!(
+            Int32Array,
+            vec![
+                Some(1),
+                Some(0),
+                None,
+                Some(-1),
+                Some(i32::MAX),
+                Some(i32::MIN)
+            ],
+            vec![
+                0xa309b38455455929,
+                0x3229fbc4681e48f3,
+                42,
+                0x1bfdda8861c06e45,
+                0x14f0ac009c21721c,
+                0x1cc7cb8d034769cd
+            ]
+        );
+        test_xxhash64_hashes!(
+            Int64Array,
+            vec![Some(1), Some(0), Some(-1), Some(i64::MAX), Some(i64::MIN)],
+            vec![
+                0x9ed50fd59358d232,
+                0xb71b47ebda15746c,
+                0x358ae035bfb46fd2,
+                0xd2f1c616ae7eb306,
+                0x88608019c494c1f4
+            ]
+        );
+        test_xxhash64_hashes!(
+            StringArray,
+            vec![
+                Some("hello"),
+                Some("bar"),
+                Some(""),
+                Some("😁"),
+                Some("天地"),
+                None
+            ],
+            vec![
+                0xc3629e6318d53932,
+                0xe7097b6a54378d8a,
+                0x98b1582b0977e704,
+                0xa80d9d5a6a523bd5,
+                0xfcba5f61ac666c61,
+                42
+            ]
+        );
+
+        // Dictionary-encoded values are hashed like plain values, with the seed of each row
+        let values = vec![Some("hello"), None, Some("bar"), Some("hello")];
+        let dictionary =
+            Arc::new(DictionaryArray::<Int32Type>::from_iter(values.clone())) as ArrayRef;
+        let plain = Arc::new(StringArray::from(values)) as ArrayRef;
+        let seeds = vec![42, 1, 2, 3];
+        let mut expected = seeds.clone();
+        create_xxhash64_hashes(&[plain], &mut expected).unwrap();
+        let mut hashes = seeds;
+        create_xxhash64_hashes(&[dictionary], &mut hashes).unwrap();
+        assert_eq!(hashes, expected);
+    }
+
     #[test]
     fn test_pmod() {
         let i: Vec<u32> = vec![0x99f0149d, 0x9c67b85d, 0xc8008529, 0xa05b5d7b, 0xcd1e64fb];
@@ -522,4 +1611,188 @@ mod tests {
         let expected = vec![69, 5, 193, 171, 115];
         assert_eq!(result, expected);
     }
+
+    macro_rules! test_fixed_width_hashes {
+        ($rng:ident, $array_type:ty, $gen:expr, $bytes:expr) => {
+            for _ in 0..20 {
+                let len = $rng.gen_range(0..100);
+                let values = (0..len)
+                    .map(|_| if $rng.gen_bool(0.8) { Some($gen) } else { None })
+                    .collect::<Vec<_>>();
+                // The array is sliced, so that the lanes aren't aligned with its buffers
+                let offset = $rng.gen_range(0..=len.min(7));
+                let array =
+                    Arc::new(<$array_type>::from(values.clone()).slice(offset, len - offset))
+                        as ArrayRef;
+                let seeds = (offset..len).map(|_| $rng.gen::<u32>()).collect::<Vec<_>>();
+
+                let mut hashes = seeds.clone();
+                create_hashes(&[array], &mut hashes).unwrap();
+                let expected = values[offset..]
+                    .iter()
+                    .zip(seeds)
+                    .map(|(value, seed)| match value {
+                        Some(value) => spark_compatible_murmur3_hash($bytes(*value), seed),
+                        None => seed,
+                    })
+                    .collect::<Vec<_>>();
+                assert_eq!(hashes, expected);
+            }
+        };
+    }
+
+    #[test]
+    fn test_fixed_width_hashes_fuzz() {
+        // The fixed-width columns hashed in lanes against the hashes of the bytes of each value
+        let mut rng = StdRng::seed_from_u64(42);
+        test_fixed_width_hashes!(rng, Int8Array, rng.gen::<i8>(), |v: i8| {
+            (v as i32).to_le_bytes()
+        });
+        test_fixed_width_hashes!(rng, Int16Array, rng.gen::<i16>(), |v: i16| {
+            (v as i32).to_le_bytes()
+        });
+        test_fixed_width_hashes!(rng, Int32Array, rng.gen::<i32>(), |v: i32| v.to_le_bytes());
+        test_fixed_width_hashes!(rng, Date32Array, rng.gen::<i32>(), |v: i32| v.to_le_bytes());
+        test_fixed_width_hashes!(rng, Int64Array, rng.gen::<i64>(), |v: i64| v.to_le_bytes());
+        test_fixed_width_hashes!(
+            rng,
+            TimestampMicrosecondArray,
+            rng.gen::<i64>(),
+            |v: i64| v.to_le_bytes()
+        );
+
+        let special_f32 = [0.0, -0.0, f32::NAN, f32::INFINITY, f32::MIN];
+        test_fixed_width_hashes!(
+            rng,
+            Float32Array,
+            if rng.gen_bool(0.3) {
+                *special_f32.choose(&mut rng).unwrap()
+            } else {
+                rng.gen::<f32>()
+            },
+            |v: f32| if v == 0.0 && v.is_sign_negative() {
+                0_i32.to_le_bytes()
+            } else {
+                v.to_le_bytes()
+            }
+        );
+        let special_f64 = [0.0, -0.0, f64::NAN, f64::NEG_INFINITY, f64::MAX];
+        test_fixed_width_hashes!(
+            rng,
+            Float64Array,
+            if rng.gen_bool(0.3) {
+                *special_f64.choose(&mut rng).unwrap()
+            } else {
+                rng.gen::<f64>()
+            },
+            |v: f64| if v == 0.0 && v.is_sign_negative() {
+                0_i64.to_le_bytes()
+            } else {
+                v.to_le_bytes()
+            }
+        );
+    }
+
+    #[test]
+    fn test_bloom_filter_hashes() {
+        let values = [0, 1, -1, 1 << 40, i64::MIN, i64::MAX, 42, -42, 1234567890];
+        let mut hashes = vec![0; values.len()];
+        create_bloom_filter_hashes(&values, &mut hashes);
+        assert_eq!(
+            &hashes[..4],
+            &[
+                0xcb919d2c63852afc,
+                0xb8ddba5d53075d44,
+                0xd083e8e2627564e8,
+                0xff267e05a9f62f22
+            ]
+        );
+        for (value, hash) in values.iter().zip(hashes) {
+            let h1 = spark_compatible_murmur3_hash(value.to_le_bytes(), 0);
+            let h2 = spark_compatible_murmur3_hash(value.to_le_bytes(), h1);
+            assert_eq!(hash, ((h2 as u64) << 32) | h1 as u64);
+        }
+    }
+
+    #[test]
+    fn test_hive_hash() {
+        let hive_hashes = |arrays: Vec<ArrayRef>| {
+            let mut hashes = vec![0; arrays[0].len()];
+            create_hive_hashes(&arrays, &mut hashes).unwrap();
+            hashes
+        };
+
+        let ints = Arc::new(Int32Array::from(vec![Some(1), Some(0), Some(-1), None])) as ArrayRef;
+        assert_eq!(hive_hashes(vec![ints]), vec![1, 0, 0xffffffff, 0]);
+        let longs = Arc::new(Int64Array::from(vec![1, -1, 1 << 32])) as ArrayRef;
+        assert_eq!(hive_hashes(vec![longs]), vec![1, 0, 1]);
+        let strings = Arc::new(StringArray::from(vec![
+            Some("hello"),
+            Some(""),
+            Some("天"),
+            None,
+        ])) as ArrayRef;
+        assert_eq!(hive_hashes(vec![strings]), vec![99162322, 0, 0xffff8f2a, 0]);
+
+        // Decimals are hashed without trailing zeros, e.g., 1.50 as 1.5
+        let decimals = Arc::new(
+            Decimal128Array::from(vec![150, -150, 0, 12345])
+                .with_precision_and_scale(10, 2)
+                .unwrap(),
+        ) as ArrayRef;
+        assert_eq!(
+            hive_hashes(vec![decimals]),
+            vec![466, 0xfffffe30, 0, 382697]
+        );
+
+        let timestamps =
+            Arc::new(TimestampMicrosecondArray::from(vec![1_500_000, -1_500_000])) as ArrayRef;
+        assert_eq!(hive_hashes(vec![timestamps]), vec![1573741824, 0x1dcd64ff]);
+
+        let lists = Arc::new(ListArray::from_iter_primitive::<Int32Type, _, _>(vec![
+            Some(vec![Some(1), Some(2)]),
+            None,
+            Some(vec![]),
+        ])) as ArrayRef;
+        assert_eq!(hive_hashes(vec![lists]), vec![33, 0, 0]);
+
+        // The hashes of the columns are folded like the elements of a list
+        let ints = Arc::new(Int32Array::from(vec![1, 2])) as ArrayRef;
+        let strings = Arc::new(StringArray::from(vec!["a", "b"])) as ArrayRef;
+        assert_eq!(hive_hashes(vec![ints, strings]), vec![128, 160]);
+    }
+
+    #[test]
+    fn test_compute_partition_ids() {
+        let values = (-1000..1000).collect::<Vec<i32>>();
+        let array = Arc::new(Int32Array::from(values.clone())) as ArrayRef;
+
+        for num_partitions in [1, 7, 200, 256, 1 << 20] {
+            let mut hashes = vec![42; values.len()];
+            create_hashes(&[array.clone()], &mut hashes).unwrap();
+            let expected = hashes
+                .iter()
+                .map(|hash| pmod(*hash, num_partitions) as u32)
+                .collect::<Vec<_>>();
+
+            let mut partition_ids = vec![0; values.len()];
+            compute_partition_ids(&[array.clone()], num_partitions, &mut partition_ids).unwrap();
+            assert_eq!(partition_ids, expected);
+
+            let mut partition_counts = vec![0; num_partitions];
+            let partition_ids = compute_partition_ids_with_counts(
+                &[array.clone()],
+                num_partitions,
+                &mut partition_ids,
+                &mut partition_counts,
+            )
+            .unwrap();
+            assert_eq!(partition_ids, expected);
+            let mut expected_counts = vec![0; num_partitions];
+            expected
+                .iter()
+                .for_each(|id| expected_counts[*id as usize] += 1);
+            assert_eq!(partition_counts, expected_counts);
+        }
+    }
 }
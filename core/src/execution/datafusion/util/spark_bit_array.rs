@@ -61,6 +61,25 @@ impl SparkBitArray {
     pub fn cardinality(&self) -> usize {
         self.bit_count
     }
+
+    pub fn data(&self) -> &[u64] {
+        &self.data
+    }
+
+    /// Combines the two bit arrays with a bitwise OR, like `BitArray.putAll` of Spark.
+    pub fn merge(&mut self, other: &SparkBitArray) {
+        assert_eq!(
+            self.data.len(),
+            other.data.len(),
+            "BitArrays must be of equal length when merging"
+        );
+        let mut bit_count = 0;
+        for (word, other_word) in self.data.iter_mut().zip(other.data.iter()) {
+            *word |= other_word;
+            bit_count += word.count_ones() as usize;
+        }
+        self.bit_count = bit_count;
+    }
 }
 
 #[cfg(test)]
@@ -128,4 +147,12 @@ mod test {
         // check cardinality
         assert_eq!(array.cardinality(), 6);
     }
+
+    #[test]
+    fn test_spark_bit_array_merge() {
+        let mut array = SparkBitArray::new(vec![0b0011u64, 0]);
+        array.merge(&SparkBitArray::new(vec![0b0110u64, 1]));
+        assert_eq!(array.data(), &[0b0111u64, 1]);
+        assert_eq!(array.cardinality(), 4);
+    }
 }
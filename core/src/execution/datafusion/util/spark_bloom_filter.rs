@@ -16,15 +16,17 @@
 // under the License.
 
 use crate::execution::datafusion::{
-    spark_hash::spark_compatible_murmur3_hash, util::spark_bit_array::SparkBitArray,
+    spark_hash::{create_bloom_filter_hashes, spark_bloom_filter_hash_long},
+    util::spark_bit_array::SparkBitArray,
 };
-use arrow_array::{ArrowNativeTypeOp, BooleanArray, Int64Array};
+use arrow_array::{Array, ArrowNativeTypeOp, BooleanArray, Int64Array};
 
 const SPARK_BLOOM_FILTER_VERSION_1: i32 = 1;
 
 /// A Bloom filter implementation that simulates the behavior of Spark's BloomFilter.
 /// It's not a complete implementation of Spark's BloomFilter, but just add the minimum
-/// methods to support mightContainsLong in the native side.
+/// methods to evaluate `BloomFilterMightContain` and to build the filters of
+/// `BloomFilterAggregate` in the native side, which only put and test longs.
 #[derive(Debug, Hash)]
 pub struct SparkBloomFilter {
     bits: SparkBitArray,
@@ -56,15 +58,96 @@ impl SparkBloomFilter {
         }
     }
 
+    /// Creates an empty Bloom filter like Spark's `BloomFilter.create(expectedNumItems, numBits)`.
+    pub fn new_empty(expected_num_items: i64, num_bits: i64) -> Self {
+        assert!(
+            expected_num_items > 0 && num_bits > 0,
+            "Expected insertions and number of bits must be positive"
+        );
+        let num_words = ((num_bits as u64 + 63) / 64) as usize;
+        Self {
+            bits: SparkBitArray::new(vec![0u64; num_words]),
+            num_hash_functions: optimal_num_of_hash_functions(expected_num_items, num_bits),
+        }
+    }
+
+    /// Serializes the Bloom filter in the same format as Spark's `BloomFilter.writeTo`, which
+    /// is read by [`SparkBloomFilter::new`].
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let words = self.bits.data();
+        let mut buf = Vec::with_capacity(12 + words.len() * 8);
+        buf.extend_from_slice(&SPARK_BLOOM_FILTER_VERSION_1.to_be_bytes());
+        buf.extend_from_slice(&(self.num_hash_functions as i32).to_be_bytes());
+        buf.extend_from_slice(&(words.len() as i32).to_be_bytes());
+        for word in words {
+            buf.extend_from_slice(&word.to_be_bytes());
+        }
+        buf
+    }
+
+    /// Combines the Bloom filter with another one with the same size and hash functions, like
+    /// Spark's `BloomFilter.mergeInPlace`.
+    pub fn merge(&mut self, other: &SparkBloomFilter) {
+        assert_eq!(
+            self.num_hash_functions, other.num_hash_functions,
+            "Cannot merge bloom filters with different number of hash functions"
+        );
+        self.bits.merge(&other.bits);
+    }
+
+    /// The number of set bits, which is 0 if nothing has been put into the Bloom filter.
+    pub fn cardinality(&self) -> usize {
+        self.bits.cardinality()
+    }
+
+    /// The size of the bits in memory, in bytes.
+    pub fn size(&self) -> usize {
+        self.bits.data().len() * std::mem::size_of::<u64>()
+    }
+
     pub fn put_long(&mut self, item: i64) -> bool {
-        // Here we first hash the input long element into 2 int hash values, h1 and h2, then produce
-        // n hash values by `h1 + i * h2` with 1 <= i <= num_hash_functions.
-        let h1 = spark_compatible_murmur3_hash(item.to_le_bytes(), 0);
-        let h2 = spark_compatible_murmur3_hash(item.to_le_bytes(), h1);
+        self.put_hash(spark_bloom_filter_hash_long(item))
+    }
+
+    /// Puts the non-null longs of `items` into the Bloom filter.
+    pub fn put_longs(&mut self, items: &Int64Array) {
+        let mut hashes = vec![0u64; items.len()];
+        create_bloom_filter_hashes(items.values(), &mut hashes);
+        match items.nulls() {
+            None => hashes.into_iter().for_each(|hash| {
+                self.put_hash(hash);
+            }),
+            Some(nulls) => nulls.valid_indices().for_each(|i| {
+                self.put_hash(hashes[i]);
+            }),
+        }
+    }
+
+    pub fn might_contain_long(&self, item: i64) -> bool {
+        self.might_contain_hash(spark_bloom_filter_hash_long(item))
+    }
+
+    pub fn might_contain_longs(&self, items: &Int64Array) -> BooleanArray {
+        let mut hashes = vec![0u64; items.len()];
+        create_bloom_filter_hashes(items.values(), &mut hashes);
+        BooleanArray::new(
+            hashes
+                .into_iter()
+                .map(|hash| self.might_contain_hash(hash))
+                .collect(),
+            items.nulls().cloned(),
+        )
+    }
+
+    /// Sets the bits of a 64-bit hash of [`spark_bloom_filter_hash_long`]. Here the 2 int hash
+    /// values in the hash, h1 and h2, produce n hash values by `h1 + i * h2` with
+    /// 1 <= i <= num_hash_functions.
+    fn put_hash(&mut self, hash: u64) -> bool {
+        let (h1, h2) = (hash as i32, (hash >> 32) as i32);
         let bit_size = self.bits.bit_size() as i32;
         let mut bit_changed = false;
         for i in 1..=self.num_hash_functions {
-            let mut combined_hash = (h1 as i32).add_wrapping((i as i32).mul_wrapping(h2 as i32));
+            let mut combined_hash = h1.add_wrapping((i as i32).mul_wrapping(h2));
             if combined_hash < 0 {
                 combined_hash = !combined_hash;
             }
@@ -73,12 +156,11 @@ impl SparkBloomFilter {
         bit_changed
     }
 
-    pub fn might_contain_long(&self, item: i64) -> bool {
-        let h1 = spark_compatible_murmur3_hash(item.to_le_bytes(), 0);
-        let h2 = spark_compatible_murmur3_hash(item.to_le_bytes(), h1);
+    fn might_contain_hash(&self, hash: u64) -> bool {
+        let (h1, h2) = (hash as i32, (hash >> 32) as i32);
         let bit_size = self.bits.bit_size() as i32;
         for i in 1..=self.num_hash_functions {
-            let mut combined_hash = (h1 as i32).add_wrapping((i as i32).mul_wrapping(h2 as i32));
+            let mut combined_hash = h1.add_wrapping((i as i32).mul_wrapping(h2));
             if combined_hash < 0 {
                 combined_hash = !combined_hash;
             }
@@ -88,11 +170,47 @@ impl SparkBloomFilter {
         }
         true
     }
+}
 
-    pub fn might_contain_longs(&self, items: &Int64Array) -> BooleanArray {
-        items
-            .iter()
-            .map(|v| v.map(|x| self.might_contain_long(x)))
-            .collect()
+/// The number of hash functions of Spark's `BloomFilter.optimalNumOfHashFunctions`.
+fn optimal_num_of_hash_functions(expected_num_items: i64, num_bits: i64) -> u32 {
+    1.max((num_bits as f64 / expected_num_items as f64 * std::f64::consts::LN_2).round() as i32)
+        as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow_array::{Array, Int64Array};
+
+    use super::SparkBloomFilter;
+
+    #[test]
+    fn test_spark_bloom_filter() {
+        // A filter serialized by Spark's `BloomFilter.writeTo`, which contains 1
+        let spark_bytes = vec![
+            0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x05, 0x00, 0x00, 0x00, 0x03, 0x43, 0xa2,
+            0xec, 0x6e, 0xa8, 0xc1, 0x17, 0xe2, 0xd3, 0xcd, 0xb7, 0x67, 0x29, 0x6b, 0x14, 0x4f,
+            0xc5, 0xbf, 0xbc, 0xed, 0x97, 0x37, 0xf2, 0x67,
+        ];
+        let filter = SparkBloomFilter::new(&spark_bytes);
+        assert_eq!(filter.to_bytes(), spark_bytes);
+        assert!(filter.might_contain_long(1));
+        assert!(!filter.might_contain_long(201));
+        let result = filter.might_contain_longs(&Int64Array::from(vec![Some(1), None, Some(201)]));
+        assert!(result.value(0) && result.is_null(1) && !result.value(2));
+
+        let mut filter = SparkBloomFilter::new_empty(3, 160);
+        assert_eq!(filter.num_hash_functions, 37);
+        assert_eq!(filter.bits.data().len(), 3);
+        filter.put_longs(&Int64Array::from(vec![Some(1), None, Some(2), Some(3)]));
+        assert!((1..=3).all(|v| filter.might_contain_long(v)));
+        assert!(!filter.might_contain_long(100));
+
+        let mut other = SparkBloomFilter::new_empty(3, 160);
+        other.put_long(100);
+        filter.merge(&other);
+        assert!((1..=3).chain([100]).all(|v| filter.might_contain_long(v)));
+        let filter = SparkBloomFilter::new(&filter.to_bytes());
+        assert!((1..=3).chain([100]).all(|v| filter.might_contain_long(v)));
     }
 }
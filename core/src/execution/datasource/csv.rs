@@ -0,0 +1,200 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use arrow::csv::ReaderBuilder;
+use arrow_schema::SchemaRef;
+use datafusion_common::DataFusionError;
+
+use super::{
+    select_and_filter, BatchIterator, FileSplit, LineSplitReader, NativeFile, NativeFileFormat,
+    ReadOptions,
+};
+
+/// Whether the first line of a CSV file is the header. False by default.
+pub const CSV_HEADER: &str = "header";
+/// The single-byte delimiter of the values of CSV files. `,` by default.
+pub const CSV_DELIMITER: &str = "delimiter";
+
+/// CSV files, whose columns are matched by position. Like Spark, values may be quoted by `"`,
+/// which is escaped by `\` in quoted values, and empty values are null.
+#[derive(Debug)]
+pub struct CsvFormat;
+
+impl NativeFileFormat for CsvFormat {
+    fn name(&self) -> &str {
+        "csv"
+    }
+
+    fn open(
+        &self,
+        split: &FileSplit,
+        schema: &SchemaRef,
+    ) -> Result<Box<dyn NativeFile>, DataFusionError> {
+        Ok(Box::new(CsvFile {
+            reader: LineSplitReader::try_new(split)?,
+            first_split: split.start == 0,
+            schema: schema.clone(),
+        }))
+    }
+}
+
+struct CsvFile {
+    reader: LineSplitReader,
+    /// Whether the split is at the beginning of the file, which is the only one with the header
+    first_split: bool,
+    schema: SchemaRef,
+}
+
+impl NativeFile for CsvFile {
+    fn read_batches(
+        self: Box<Self>,
+        options: &ReadOptions,
+    ) -> Result<BatchIterator, DataFusionError> {
+        let delimiter = match options.options.get(CSV_DELIMITER).map(String::as_bytes) {
+            Some([delimiter]) => *delimiter,
+            Some(_) => {
+                return Err(DataFusionError::Configuration(format!(
+                    "CSV delimiter must be a single byte, but got '{}'",
+                    options.options[CSV_DELIMITER]
+                )))
+            }
+            None => b',',
+        };
+        let has_header = self.first_split && options.bool_option(CSV_HEADER, false)?;
+
+        let mut builder = ReaderBuilder::new(self.schema.clone())
+            .has_header(has_header)
+            .with_delimiter(delimiter)
+            .with_escape(b'\\')
+            .with_batch_size(options.batch_size);
+        if let Some(projection) = &options.projection {
+            builder = builder.with_projection(projection.clone());
+        }
+        let reader = builder.build(self.reader)?;
+        Ok(select_and_filter(
+            Box::new(reader.map(|batch| batch.map_err(DataFusionError::from))),
+            options,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::execution::datasource::file_format;
+    use arrow_array::{cast::AsArray, types::Int32Type, RecordBatch};
+    use arrow_schema::{DataType, Field, Schema};
+    use datafusion::logical_expr::Operator;
+    use datafusion_physical_expr::expressions::{binary, lit, Column};
+    use std::{collections::HashMap, io::Write, sync::Arc};
+
+    fn read(content: &str, splits: &[(u64, u64)], options: ReadOptions) -> Vec<RecordBatch> {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(content.as_bytes()).unwrap();
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("a", DataType::Int32, true),
+            Field::new("b", DataType::Utf8, true),
+        ]));
+        let format = file_format("csv").unwrap();
+        splits
+            .iter()
+            .flat_map(|&(start, length)| {
+                let split = FileSplit {
+                    path: file.path().to_path_buf(),
+                    start,
+                    length,
+                };
+                format
+                    .open(&split, &schema)
+                    .unwrap()
+                    .read_batches(&options)
+                    .unwrap()
+                    .map(Result::unwrap)
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    fn options(entries: &[(&str, &str)]) -> ReadOptions {
+        ReadOptions {
+            projection: None,
+            filters: vec![],
+            row_selection: None,
+            batch_size: 2,
+            options: entries
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect::<HashMap<_, _>>(),
+        }
+    }
+
+    fn column_a(batches: &[RecordBatch]) -> Vec<Option<i32>> {
+        batches
+            .iter()
+            .flat_map(|b| b.column(0).as_primitive::<Int32Type>().iter())
+            .collect()
+    }
+
+    #[test]
+    fn test_read_csv() {
+        let content = "a|b\n1|x\n2|\n|\"z|\"\n4|w\n";
+        let batches = read(
+            content,
+            &[(0, content.len() as u64)],
+            options(&[(CSV_HEADER, "true"), (CSV_DELIMITER, "|")]),
+        );
+        assert_eq!(batches.len(), 2);
+        assert_eq!(column_a(&batches), vec![Some(1), Some(2), None, Some(4)]);
+        let b = batches
+            .iter()
+            .flat_map(|b| b.column(1).as_string::<i32>().iter())
+            .collect::<Vec<_>>();
+        assert_eq!(b, vec![Some("x"), None, Some("z|"), Some("w")]);
+
+        // Only the first split has the header
+        let batches = read(
+            content,
+            &[(0, 10), (10, content.len() as u64 - 10)],
+            options(&[(CSV_HEADER, "true"), (CSV_DELIMITER, "|")]),
+        );
+        assert_eq!(column_a(&batches), vec![Some(1), Some(2), None, Some(4)]);
+    }
+
+    #[test]
+    fn test_read_csv_projection_and_filters() {
+        let content = "1,x\n2,y\n3,z\n4,w\n";
+        let mut options = options(&[]);
+        options.projection = Some(vec![0]);
+        let schema = Arc::new(Schema::new(vec![Field::new("a", DataType::Int32, true)]));
+        options.filters = vec![binary(
+            Arc::new(Column::new("a", 0)),
+            Operator::NotEq,
+            lit(2),
+            &schema,
+        )
+        .unwrap()];
+        options.row_selection = Some(vec![1..3]);
+
+        let batches = read(content, &[(0, content.len() as u64)], options);
+        assert_eq!(batches[0].num_columns(), 1);
+        assert_eq!(column_a(&batches), vec![Some(3)]);
+        assert_eq!(
+            batches.iter().map(|b| b.num_rows()).collect::<Vec<_>>(),
+            vec![0, 1]
+        );
+    }
+}
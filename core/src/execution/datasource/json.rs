@@ -0,0 +1,130 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use std::io::BufReader;
+
+use arrow::json::ReaderBuilder;
+use arrow_schema::SchemaRef;
+use datafusion_common::DataFusionError;
+
+use super::{
+    select_and_filter, BatchIterator, FileSplit, LineSplitReader, NativeFile, NativeFileFormat,
+    ReadOptions,
+};
+
+/// JSON lines files, i.e., a JSON object per line, whose columns are matched by field name. The
+/// fields of the objects which are not read are ignored, and the missing ones are null.
+#[derive(Debug)]
+pub struct JsonFormat;
+
+impl NativeFileFormat for JsonFormat {
+    fn name(&self) -> &str {
+        "json"
+    }
+
+    fn open(
+        &self,
+        split: &FileSplit,
+        schema: &SchemaRef,
+    ) -> Result<Box<dyn NativeFile>, DataFusionError> {
+        Ok(Box::new(JsonFile {
+            reader: LineSplitReader::try_new(split)?,
+            schema: schema.clone(),
+        }))
+    }
+}
+
+struct JsonFile {
+    reader: LineSplitReader,
+    schema: SchemaRef,
+}
+
+impl NativeFile for JsonFile {
+    fn read_batches(
+        self: Box<Self>,
+        options: &ReadOptions,
+    ) -> Result<BatchIterator, DataFusionError> {
+        // The fields are matched by name, so only the projected ones are decoded
+        let reader = ReaderBuilder::new(options.projected_schema(&self.schema)?)
+            .with_batch_size(options.batch_size)
+            .build(BufReader::new(self.reader))?;
+        Ok(select_and_filter(
+            Box::new(reader.map(|batch| batch.map_err(DataFusionError::from))),
+            options,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::execution::datasource::file_format;
+    use arrow_array::{cast::AsArray, types::Int64Type};
+    use arrow_schema::{DataType, Field, Schema};
+    use std::{io::Write, sync::Arc};
+
+    #[test]
+    fn test_read_json() {
+        let content = "{\"a\": 1, \"b\": \"x\"}\n{\"b\": \"y\", \"c\": true}\n\n{\"a\": 3}\n";
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(content.as_bytes()).unwrap();
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("a", DataType::Int64, true),
+            Field::new("b", DataType::Utf8, true),
+        ]));
+        let options = ReadOptions {
+            projection: Some(vec![1, 0]),
+            filters: vec![],
+            row_selection: None,
+            batch_size: 8192,
+            options: Default::default(),
+        };
+
+        let format = file_format("json").unwrap();
+        // Split in the middle of the second line, which is read by the first split
+        let batches = [(0, 25), (25, content.len() as u64 - 25)]
+            .into_iter()
+            .flat_map(|(start, length)| {
+                let split = FileSplit {
+                    path: file.path().to_path_buf(),
+                    start,
+                    length,
+                };
+                format
+                    .open(&split, &schema)
+                    .unwrap()
+                    .read_batches(&options)
+                    .unwrap()
+                    .map(Result::unwrap)
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(batches.len(), 2);
+        assert_eq!(batches[0].schema().field(0).name(), "b");
+        let a = batches
+            .iter()
+            .flat_map(|b| b.column(1).as_primitive::<Int64Type>().iter())
+            .collect::<Vec<_>>();
+        assert_eq!(a, vec![Some(1), None, Some(3)]);
+        let b = batches
+            .iter()
+            .flat_map(|b| b.column(0).as_string::<i32>().iter())
+            .collect::<Vec<_>>();
+        assert_eq!(b, vec![Some("x"), Some("y"), None]);
+    }
+}
@@ -0,0 +1,322 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Native readers of data files.
+//!
+//! Every format implements [`NativeFileFormat`], and is read by [`FileScanExec`], which opens the
+//! files of a partition in order, prunes them, reads their batches ahead on the IO runtime and
+//! reports the same metrics for all the formats. CSV, JSON lines and Parquet files are built in.
+//! Other formats, e.g., ORC or Avro, can be registered by a crate linking Comet with
+//! [`register_file_format`].
+
+mod csv;
+mod json;
+mod parquet;
+mod scan;
+
+pub use csv::*;
+pub use json::*;
+pub use parquet::*;
+pub use scan::*;
+
+use std::{
+    collections::HashMap,
+    fmt::Debug,
+    fs::File,
+    io::{BufRead, BufReader, Read, Seek, SeekFrom},
+    ops::Range,
+    path::PathBuf,
+    sync::{Arc, RwLock},
+};
+
+use arrow::compute::{and, filter_record_batch};
+use arrow_array::{BooleanArray, RecordBatch};
+use arrow_schema::SchemaRef;
+use datafusion_common::{cast::as_boolean_array, DataFusionError};
+use datafusion_physical_expr::PhysicalExpr;
+use once_cell::sync::Lazy;
+
+/// The batches read from a file
+pub type BatchIterator = Box<dyn Iterator<Item = Result<RecordBatch, DataFusionError>> + Send>;
+
+/// A byte range of a data file, which is read by a single task like Spark `PartitionedFile`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileSplit {
+    pub path: PathBuf,
+    pub start: u64,
+    pub length: u64,
+}
+
+impl FileSplit {
+    pub fn end(&self) -> u64 {
+        self.start + self.length
+    }
+}
+
+/// How to read the batches of a file.
+#[derive(Debug, Clone)]
+pub struct ReadOptions {
+    /// The indices of the columns of the file schema to read. All the columns are read if this is
+    /// `None`.
+    pub projection: Option<Vec<usize>>,
+    /// Predicates on the read columns. Only the rows satisfying all of them are read.
+    pub filters: Vec<Arc<dyn PhysicalExpr>>,
+    /// The ranges of the indices of the rows of the split to read, which are sorted and disjoint.
+    /// All the rows are read if this is `None`.
+    pub row_selection: Option<Vec<Range<usize>>>,
+    /// The maximum number of rows of the read batches
+    pub batch_size: usize,
+    /// The options of the format, e.g., the delimiter of CSV files
+    pub options: HashMap<String, String>,
+}
+
+impl ReadOptions {
+    /// Returns the schema of the batches read from the files of the given schema.
+    pub fn projected_schema(&self, schema: &SchemaRef) -> Result<SchemaRef, DataFusionError> {
+        match &self.projection {
+            Some(projection) => Ok(Arc::new(schema.project(projection)?)),
+            None => Ok(schema.clone()),
+        }
+    }
+
+    /// Returns the value of the given boolean option, or `default` if it is not set.
+    pub fn bool_option(&self, key: &str, default: bool) -> Result<bool, DataFusionError> {
+        match self.options.get(key) {
+            Some(value) => value.parse().map_err(|_| {
+                DataFusionError::Configuration(format!(
+                    "Invalid value '{}' of option '{}'",
+                    value, key
+                ))
+            }),
+            None => Ok(default),
+        }
+    }
+}
+
+/// A format of data files which can be read natively.
+pub trait NativeFileFormat: Debug + Send + Sync {
+    /// The name of the format, e.g., `csv`, which native scans select it by.
+    fn name(&self) -> &str;
+
+    /// Opens the given split of a file, whose columns are `schema`, reading its metadata if any.
+    fn open(
+        &self,
+        split: &FileSplit,
+        schema: &SchemaRef,
+    ) -> Result<Box<dyn NativeFile>, DataFusionError>;
+}
+
+/// A split of a data file opened by a [`NativeFileFormat`].
+pub trait NativeFile: Send {
+    /// Skips the parts of the split, e.g., Parquet row groups, whose metadata shows that none of
+    /// their rows satisfy the filters of `options`. Returns the number of the skipped parts.
+    fn prune(&mut self, _options: &ReadOptions) -> Result<usize, DataFusionError> {
+        Ok(0)
+    }
+
+    /// Reads the batches of the split.
+    fn read_batches(
+        self: Box<Self>,
+        options: &ReadOptions,
+    ) -> Result<BatchIterator, DataFusionError>;
+}
+
+/// The file formats which can be read natively, by name
+static FILE_FORMATS: Lazy<RwLock<HashMap<String, Arc<dyn NativeFileFormat>>>> = Lazy::new(|| {
+    let formats: [Arc<dyn NativeFileFormat>; 3] = [
+        Arc::new(CsvFormat),
+        Arc::new(JsonFormat),
+        Arc::new(ParquetFormat),
+    ];
+    RwLock::new(
+        formats
+            .into_iter()
+            .map(|format| (format.name().to_string(), format))
+            .collect(),
+    )
+});
+
+/// Registers the given file format, replacing the one of the same name if any.
+pub fn register_file_format(format: Arc<dyn NativeFileFormat>) {
+    FILE_FORMATS
+        .write()
+        .unwrap()
+        .insert(format.name().to_string(), format);
+}
+
+/// Returns the file format of the given name, if it is built in or registered.
+pub fn file_format(name: &str) -> Option<Arc<dyn NativeFileFormat>> {
+    FILE_FORMATS.read().unwrap().get(name).cloned()
+}
+
+/// Applies the row selection and the filters of `options` to the given batches, for formats
+/// which can't skip the rows while reading them.
+pub(crate) fn select_and_filter(batches: BatchIterator, options: &ReadOptions) -> BatchIterator {
+    let batches = match options.row_selection.clone() {
+        Some(ranges) => select_rows(batches, ranges),
+        None => batches,
+    };
+    if options.filters.is_empty() {
+        return batches;
+    }
+    let filters = options.filters.clone();
+    Box::new(batches.map(move |batch| filter_batch(batch?, &filters)))
+}
+
+/// Keeps the rows of the given batches whose indices are in the given ranges, which are sorted
+/// and disjoint.
+fn select_rows(batches: BatchIterator, ranges: Vec<Range<usize>>) -> BatchIterator {
+    let mut offset = 0;
+    Box::new(batches.map(move |batch| {
+        let batch = batch?;
+        let start = offset;
+        offset += batch.num_rows();
+        let selected = (start..offset)
+            .map(|row| {
+                let index = ranges.partition_point(|range| range.end <= row);
+                Some(index < ranges.len() && ranges[index].contains(&row))
+            })
+            .collect::<BooleanArray>();
+        Ok(filter_record_batch(&batch, &selected)?)
+    }))
+}
+
+/// Keeps the rows of the given batch satisfying all the given filters.
+pub(crate) fn filter_batch(
+    batch: RecordBatch,
+    filters: &[Arc<dyn PhysicalExpr>],
+) -> Result<RecordBatch, DataFusionError> {
+    let mut selected: Option<BooleanArray> = None;
+    for filter in filters {
+        let array = filter.evaluate(&batch)?.into_array(batch.num_rows())?;
+        let array = as_boolean_array(&array)?;
+        selected = Some(match selected {
+            Some(selected) => and(&selected, array)?,
+            None => array.clone(),
+        });
+    }
+    match selected {
+        Some(selected) => Ok(filter_record_batch(&batch, &selected)?),
+        None => Ok(batch),
+    }
+}
+
+/// Reads the lines of a split of a text file like Hadoop `LineRecordReader`, i.e., the lines
+/// starting in the byte range of the split, except the first one of the split if it doesn't
+/// start at the beginning of the file, which is read by the previous split.
+pub(crate) struct LineSplitReader {
+    reader: BufReader<File>,
+    /// The position of the next line in the file
+    pos: u64,
+    end: u64,
+    /// The line being read, and the position in it
+    line: Vec<u8>,
+    line_pos: usize,
+}
+
+impl LineSplitReader {
+    pub(crate) fn try_new(split: &FileSplit) -> std::io::Result<Self> {
+        let mut file = File::open(&split.path)?;
+        file.seek(SeekFrom::Start(split.start))?;
+        let mut reader = BufReader::new(file);
+        let mut pos = split.start;
+        let mut line = vec![];
+        if split.start != 0 {
+            pos += reader.read_until(b'\n', &mut line)? as u64;
+            line.clear();
+        }
+        Ok(Self {
+            reader,
+            pos,
+            end: split.end(),
+            line,
+            line_pos: 0,
+        })
+    }
+}
+
+impl Read for LineSplitReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.line_pos == self.line.len() {
+            // The lines starting after the end of the split are read by the next split
+            if self.pos > self.end {
+                return Ok(0);
+            }
+            self.line.clear();
+            self.line_pos = 0;
+            let len = self.reader.read_until(b'\n', &mut self.line)?;
+            if len == 0 {
+                return Ok(0);
+            }
+            self.pos += len as u64;
+        }
+        let len = buf.len().min(self.line.len() - self.line_pos);
+        buf[..len].copy_from_slice(&self.line[self.line_pos..self.line_pos + len]);
+        self.line_pos += len;
+        Ok(len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    /// Returns the lines read by each split of the given lengths.
+    fn read_splits(content: &str, lengths: &[u64]) -> Vec<String> {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(content.as_bytes()).unwrap();
+        let mut start = 0;
+        lengths
+            .iter()
+            .map(|&length| {
+                let split = FileSplit {
+                    path: file.path().to_path_buf(),
+                    start,
+                    length,
+                };
+                start += length;
+                let mut lines = String::new();
+                LineSplitReader::try_new(&split)
+                    .unwrap()
+                    .read_to_string(&mut lines)
+                    .unwrap();
+                lines
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_line_splits() {
+        let content = "aaa\nbbb\nccc\nddd";
+        assert_eq!(read_splits(content, &[15]), vec![content]);
+        // Every line is read by the split it starts in
+        assert_eq!(
+            read_splits(content, &[2, 4, 2, 7]),
+            vec!["aaa\n", "bbb\n", "ccc\n", "ddd"]
+        );
+        assert_eq!(
+            read_splits(content, &[2, 1, 1, 11]),
+            vec!["aaa\n", "", "bbb\n", "ccc\nddd"]
+        );
+        // A line starting at the end of a split is read by the split
+        assert_eq!(
+            read_splits(content, &[4, 4, 7]),
+            vec!["aaa\nbbb\n", "ccc\n", "ddd"]
+        );
+    }
+}
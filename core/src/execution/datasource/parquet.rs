@@ -0,0 +1,402 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use std::{cmp::Ordering, fs::File, sync::Arc};
+
+use arrow::compute::cast;
+use arrow_array::{new_null_array, RecordBatch, RecordBatchOptions};
+use arrow_schema::{DataType, SchemaRef};
+use datafusion::logical_expr::Operator;
+use datafusion_common::{DataFusionError, ScalarValue};
+use datafusion_physical_expr::{
+    expressions::{BinaryExpr, Column, Literal},
+    PhysicalExpr,
+};
+use parquet::{
+    arrow::{
+        arrow_reader::{ParquetRecordBatchReaderBuilder, RowSelection},
+        ProjectionMask,
+    },
+    errors::ParquetError,
+    file::{metadata::RowGroupMetaData, statistics::Statistics},
+};
+
+use super::{filter_batch, BatchIterator, FileSplit, NativeFile, NativeFileFormat, ReadOptions};
+
+/// Parquet files, whose columns are matched by name. The columns missing in a file are null,
+/// and the others are cast to the read types. The row groups are pruned by the min/max
+/// statistics of their columns.
+#[derive(Debug)]
+pub struct ParquetFormat;
+
+impl NativeFileFormat for ParquetFormat {
+    fn name(&self) -> &str {
+        "parquet"
+    }
+
+    fn open(
+        &self,
+        split: &FileSplit,
+        schema: &SchemaRef,
+    ) -> Result<Box<dyn NativeFile>, DataFusionError> {
+        let builder =
+            ParquetRecordBatchReaderBuilder::try_new(File::open(&split.path)?).map_err(external)?;
+        // Like parquet-mr, a split reads the row groups whose midpoints are in its byte range
+        let row_groups = builder
+            .metadata()
+            .row_groups()
+            .iter()
+            .enumerate()
+            .filter(|(_, row_group)| {
+                let midpoint = row_group_midpoint(row_group);
+                midpoint >= split.start as i64 && midpoint < split.end() as i64
+            })
+            .map(|(index, _)| index)
+            .collect();
+        Ok(Box::new(ParquetFile {
+            builder,
+            schema: schema.clone(),
+            row_groups,
+        }))
+    }
+}
+
+struct ParquetFile {
+    builder: ParquetRecordBatchReaderBuilder<File>,
+    schema: SchemaRef,
+    /// The indices of the row groups of the split to read
+    row_groups: Vec<usize>,
+}
+
+impl NativeFile for ParquetFile {
+    fn prune(&mut self, options: &ReadOptions) -> Result<usize, DataFusionError> {
+        // The row selection refers to the rows of all the row groups of the split
+        if options.filters.is_empty() || options.row_selection.is_some() {
+            return Ok(0);
+        }
+        let schema = options.projected_schema(&self.schema)?;
+        let metadata = self.builder.metadata().clone();
+        let leaves = schema
+            .fields()
+            .iter()
+            .map(|field| {
+                metadata
+                    .file_metadata()
+                    .schema_descr()
+                    .columns()
+                    .iter()
+                    .position(|column| column.path().string() == *field.name())
+            })
+            .collect::<Vec<_>>();
+
+        let num_row_groups = self.row_groups.len();
+        self.row_groups.retain(|&index| {
+            let row_group = metadata.row_group(index);
+            let min_max = |column: usize| {
+                let statistics = row_group.column(leaves[column]?).statistics()?;
+                let data_type = schema.field(column).data_type();
+                Some((
+                    statistic(statistics, data_type, true)?,
+                    statistic(statistics, data_type, false)?,
+                ))
+            };
+            options
+                .filters
+                .iter()
+                .all(|filter| may_satisfy(filter, &min_max))
+        });
+        Ok(num_row_groups - self.row_groups.len())
+    }
+
+    fn read_batches(
+        self: Box<Self>,
+        options: &ReadOptions,
+    ) -> Result<BatchIterator, DataFusionError> {
+        let ParquetFile {
+            builder,
+            schema,
+            row_groups,
+        } = *self;
+        let schema = options.projected_schema(&schema)?;
+        let file_schema = builder.schema().clone();
+        let roots = schema
+            .fields()
+            .iter()
+            .filter_map(|field| file_schema.index_of(field.name()).ok())
+            .collect::<Vec<_>>();
+        let mask = ProjectionMask::roots(builder.parquet_schema(), roots);
+        let num_rows = row_groups
+            .iter()
+            .map(|&index| builder.metadata().row_group(index).num_rows() as usize)
+            .sum();
+
+        let mut builder = builder
+            .with_projection(mask)
+            .with_row_groups(row_groups)
+            .with_batch_size(options.batch_size);
+        if let Some(ranges) = &options.row_selection {
+            builder = builder.with_row_selection(RowSelection::from_consecutive_ranges(
+                ranges.iter().cloned(),
+                num_rows,
+            ));
+        }
+        let reader = builder.build().map_err(external)?;
+
+        let filters = options.filters.clone();
+        Ok(Box::new(reader.map(move |batch| {
+            filter_batch(adapt_batch(batch?, &schema)?, &filters)
+        })))
+    }
+}
+
+/// Returns the batch of the given schema from the given batch read from a file, casting the
+/// columns to the types of the schema and filling the missing ones with nulls.
+fn adapt_batch(batch: RecordBatch, schema: &SchemaRef) -> Result<RecordBatch, DataFusionError> {
+    let columns = schema
+        .fields()
+        .iter()
+        .map(|field| match batch.column_by_name(field.name()) {
+            Some(column) if column.data_type() == field.data_type() => Ok(column.clone()),
+            Some(column) => cast(column, field.data_type()),
+            None => Ok(new_null_array(field.data_type(), batch.num_rows())),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(RecordBatch::try_new_with_options(
+        schema.clone(),
+        columns,
+        &RecordBatchOptions::new().with_row_count(Some(batch.num_rows())),
+    )?)
+}
+
+/// Returns the position of the middle of the given row group in its file.
+fn row_group_midpoint(row_group: &RowGroupMetaData) -> i64 {
+    let column = row_group.column(0);
+    let start = match column.dictionary_page_offset() {
+        Some(offset) => offset.min(column.data_page_offset()),
+        None => column.data_page_offset(),
+    };
+    start + row_group.compressed_size() / 2
+}
+
+/// Returns the min or max value of a column chunk, of the given type, if it is known. Floating
+/// point statistics are not used as they may not account for NaN.
+fn statistic(statistics: &Statistics, data_type: &DataType, min: bool) -> Option<ScalarValue> {
+    if !statistics.has_min_max_set() {
+        return None;
+    }
+    match (statistics, data_type) {
+        (Statistics::Boolean(s), DataType::Boolean) => {
+            let value = *if min { s.min() } else { s.max() };
+            Some(ScalarValue::Boolean(Some(value)))
+        }
+        (Statistics::Int32(s), _) => {
+            let value = *if min { s.min() } else { s.max() };
+            match data_type {
+                DataType::Int8 => Some(ScalarValue::Int8(Some(value as i8))),
+                DataType::Int16 => Some(ScalarValue::Int16(Some(value as i16))),
+                DataType::Int32 => Some(ScalarValue::Int32(Some(value))),
+                DataType::Int64 => Some(ScalarValue::Int64(Some(value as i64))),
+                DataType::Date32 => Some(ScalarValue::Date32(Some(value))),
+                _ => None,
+            }
+        }
+        (Statistics::Int64(s), DataType::Int64) => {
+            let value = *if min { s.min() } else { s.max() };
+            Some(ScalarValue::Int64(Some(value)))
+        }
+        // Old writers ordered binary values as signed bytes in the deprecated statistics
+        (Statistics::ByteArray(s), DataType::Utf8) if !statistics.is_min_max_deprecated() => {
+            let value = if min { s.min() } else { s.max() };
+            Some(ScalarValue::Utf8(Some(value.as_utf8().ok()?.to_string())))
+        }
+        _ => None,
+    }
+}
+
+/// Returns whether some rows may satisfy the given filter, from the min/max values of the
+/// columns returned by `min_max`. Only the comparisons of columns with literals, and their
+/// conjunctions and disjunctions, are evaluated. The other filters may be satisfied.
+fn may_satisfy(
+    filter: &Arc<dyn PhysicalExpr>,
+    min_max: &dyn Fn(usize) -> Option<(ScalarValue, ScalarValue)>,
+) -> bool {
+    let Some(binary) = filter.as_any().downcast_ref::<BinaryExpr>() else {
+        return true;
+    };
+    let (left, right) = (binary.left(), binary.right());
+    match binary.op() {
+        Operator::And => may_satisfy(left, min_max) && may_satisfy(right, min_max),
+        Operator::Or => may_satisfy(left, min_max) || may_satisfy(right, min_max),
+        op => {
+            let as_column = |expr: &Arc<dyn PhysicalExpr>| {
+                expr.as_any().downcast_ref::<Column>().map(Column::index)
+            };
+            let as_literal = |expr: &Arc<dyn PhysicalExpr>| {
+                expr.as_any()
+                    .downcast_ref::<Literal>()
+                    .map(|literal| literal.value().clone())
+            };
+            let (column, literal, op) = match (as_column(left), as_literal(right)) {
+                (Some(column), Some(literal)) => (column, literal, *op),
+                _ => match (as_literal(left), as_column(right), op.swap()) {
+                    (Some(literal), Some(column), Some(op)) => (column, literal, op),
+                    _ => return true,
+                },
+            };
+            let Some((min, max)) = min_max(column) else {
+                return true;
+            };
+            let (Some(min), Some(max)) = (min.partial_cmp(&literal), max.partial_cmp(&literal))
+            else {
+                return true;
+            };
+            match op {
+                Operator::Eq => min != Ordering::Greater && max != Ordering::Less,
+                Operator::NotEq => min != Ordering::Equal || max != Ordering::Equal,
+                Operator::Lt => min == Ordering::Less,
+                Operator::LtEq => min != Ordering::Greater,
+                Operator::Gt => max == Ordering::Greater,
+                Operator::GtEq => max != Ordering::Less,
+                _ => true,
+            }
+        }
+    }
+}
+
+fn external(e: ParquetError) -> DataFusionError {
+    DataFusionError::External(Box::new(e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::execution::datasource::file_format;
+    use arrow_array::{cast::AsArray, types::Int64Type, Int32Array, StringArray};
+    use arrow_schema::{Field, Schema};
+    use datafusion_physical_expr::expressions::{binary, lit};
+    use parquet::{arrow::ArrowWriter, file::properties::WriterProperties};
+
+    #[test]
+    fn test_read_parquet() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        let batch = RecordBatch::try_from_iter(vec![
+            ("a", Arc::new(Int32Array::from_iter_values(1..=6)) as _),
+            (
+                "b",
+                Arc::new(StringArray::from(vec!["u", "v", "w", "x", "y", "z"])) as _,
+            ),
+        ])
+        .unwrap();
+        let properties = WriterProperties::builder()
+            .set_max_row_group_size(2)
+            .build();
+        let mut writer = ArrowWriter::try_new(
+            file.as_file().try_clone().unwrap(),
+            batch.schema(),
+            Some(properties),
+        )
+        .unwrap();
+        writer.write(&batch).unwrap();
+        writer.close().unwrap();
+
+        // The column `c` is missing in the file, and `a` is read as longs
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("c", DataType::Utf8, true),
+            Field::new("a", DataType::Int64, true),
+            Field::new("b", DataType::Utf8, true),
+        ]));
+        let projected = Arc::new(schema.project(&[1, 0]).unwrap());
+        let options = ReadOptions {
+            projection: Some(vec![1, 0]),
+            filters: vec![binary(
+                Arc::new(Column::new("a", 0)),
+                Operator::Gt,
+                lit(3i64),
+                &projected,
+            )
+            .unwrap()],
+            row_selection: None,
+            batch_size: 8192,
+            options: Default::default(),
+        };
+        let split = FileSplit {
+            path: file.path().to_path_buf(),
+            start: 0,
+            length: std::fs::metadata(file.path()).unwrap().len(),
+        };
+
+        let mut parquet_file = file_format("parquet")
+            .unwrap()
+            .open(&split, &schema)
+            .unwrap();
+        // The first row group has no value greater than 3
+        assert_eq!(parquet_file.prune(&options).unwrap(), 1);
+        let batches = parquet_file
+            .read_batches(&options)
+            .unwrap()
+            .map(Result::unwrap)
+            .collect::<Vec<_>>();
+
+        let a = batches
+            .iter()
+            .flat_map(|b| b.column(0).as_primitive::<Int64Type>().iter())
+            .collect::<Vec<_>>();
+        assert_eq!(a, vec![Some(4), Some(5), Some(6)]);
+        assert!(batches
+            .iter()
+            .all(|b| b.column(1).null_count() == b.num_rows()));
+    }
+
+    #[test]
+    fn test_may_satisfy() {
+        let schema = Schema::new(vec![Field::new("a", DataType::Int32, true)]);
+        let min_max = |_: usize| Some((ScalarValue::Int32(Some(3)), ScalarValue::Int32(Some(5))));
+        let filter = |op: Operator, value: i32| {
+            binary(Arc::new(Column::new("a", 0)), op, lit(value), &schema).unwrap()
+        };
+
+        assert!(may_satisfy(&filter(Operator::Eq, 4), &min_max));
+        assert!(!may_satisfy(&filter(Operator::Eq, 6), &min_max));
+        assert!(!may_satisfy(&filter(Operator::Lt, 3), &min_max));
+        assert!(may_satisfy(&filter(Operator::LtEq, 3), &min_max));
+        assert!(!may_satisfy(&filter(Operator::Gt, 5), &min_max));
+        assert!(may_satisfy(&filter(Operator::NotEq, 5), &min_max));
+        // The literal on the left
+        let reversed =
+            binary(lit(2), Operator::Gt, Arc::new(Column::new("a", 0)), &schema).unwrap();
+        assert!(!may_satisfy(&reversed, &min_max));
+
+        let and = binary(
+            filter(Operator::Gt, 5),
+            Operator::And,
+            filter(Operator::Lt, 4),
+            &schema,
+        )
+        .unwrap();
+        assert!(!may_satisfy(&and, &min_max));
+        let or = binary(
+            filter(Operator::Gt, 5),
+            Operator::Or,
+            filter(Operator::Lt, 4),
+            &schema,
+        )
+        .unwrap();
+        assert!(may_satisfy(&or, &min_max));
+        // Unknown statistics
+        assert!(may_satisfy(&filter(Operator::Eq, 6), &|_| None));
+    }
+}
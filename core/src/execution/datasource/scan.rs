@@ -0,0 +1,347 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use std::{any::Any, collections::HashMap, sync::Arc};
+
+use arrow_array::RecordBatch;
+use arrow_schema::SchemaRef;
+use datafusion::{
+    execution::TaskContext,
+    physical_plan::{
+        metrics::{Count, ExecutionPlanMetricsSet, MetricBuilder, MetricsSet, Time},
+        stream::RecordBatchStreamAdapter,
+        DisplayAs, DisplayFormatType, ExecutionMode, ExecutionPlan, Partitioning, PlanProperties,
+        SendableRecordBatchStream,
+    },
+};
+use datafusion_common::{DataFusionError, Result as DataFusionResult};
+use datafusion_physical_expr::{EquivalenceProperties, PhysicalExpr};
+use itertools::Itertools;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+
+use crate::execution::runtime::{io_handle, spawn_blocking_io, ResourceLimits};
+
+use super::{BatchIterator, FileSplit, NativeFileFormat, ReadOptions};
+
+/// The number of batches read ahead of the consumer of a file scan
+const PREFETCH_BATCHES: usize = 2;
+
+/// Reads the splits of data files of a partition with a [`NativeFileFormat`]. The splits are
+/// opened and read in order on the IO runtime, a few batches ahead of the consumer, so that
+/// reading the files overlaps with the downstream operators. Every read, including opening a
+/// file, is an IO request of the resource profile of the plan.
+#[derive(Debug)]
+pub struct FileScanExec {
+    format: Arc<dyn NativeFileFormat>,
+    splits: Vec<FileSplit>,
+    /// The columns of the files
+    file_schema: SchemaRef,
+    /// The indices of the read columns in `file_schema`
+    projection: Vec<usize>,
+    /// Predicates on the read columns, which rows are filtered by
+    filters: Vec<Arc<dyn PhysicalExpr>>,
+    /// The options of the format
+    options: HashMap<String, String>,
+    schema: SchemaRef,
+    metrics: ExecutionPlanMetricsSet,
+    cache: PlanProperties,
+}
+
+impl FileScanExec {
+    pub fn try_new(
+        format: Arc<dyn NativeFileFormat>,
+        splits: Vec<FileSplit>,
+        file_schema: SchemaRef,
+        projection: Vec<usize>,
+        filters: Vec<Arc<dyn PhysicalExpr>>,
+        options: HashMap<String, String>,
+    ) -> DataFusionResult<Self> {
+        let schema = Arc::new(file_schema.project(&projection)?);
+        let cache = PlanProperties::new(
+            EquivalenceProperties::new(schema.clone()),
+            Partitioning::UnknownPartitioning(1),
+            ExecutionMode::Bounded,
+        );
+        Ok(Self {
+            format,
+            splits,
+            file_schema,
+            projection,
+            filters,
+            options,
+            schema,
+            metrics: ExecutionPlanMetricsSet::default(),
+            cache,
+        })
+    }
+}
+
+impl DisplayAs for FileScanExec {
+    fn fmt_as(&self, t: DisplayFormatType, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match t {
+            DisplayFormatType::Default | DisplayFormatType::Verbose => {
+                write!(
+                    f,
+                    "FileScanExec: format={}, files={}, projection=[{}], filters=[{}]",
+                    self.format.name(),
+                    self.splits.len(),
+                    self.schema.fields().iter().map(|f| f.name()).join(", "),
+                    self.filters.iter().join(", ")
+                )
+            }
+        }
+    }
+}
+
+impl ExecutionPlan for FileScanExec {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+
+    fn children(&self) -> Vec<Arc<dyn ExecutionPlan>> {
+        vec![]
+    }
+
+    fn with_new_children(
+        self: Arc<Self>,
+        _: Vec<Arc<dyn ExecutionPlan>>,
+    ) -> DataFusionResult<Arc<dyn ExecutionPlan>> {
+        Ok(self)
+    }
+
+    fn execute(
+        &self,
+        partition: usize,
+        context: Arc<TaskContext>,
+    ) -> DataFusionResult<SendableRecordBatchStream> {
+        let reader = SplitReader {
+            format: self.format.clone(),
+            file_schema: self.file_schema.clone(),
+            options: ReadOptions {
+                projection: Some(self.projection.clone()),
+                filters: self.filters.clone(),
+                row_selection: None,
+                batch_size: context.session_config().batch_size(),
+                options: self.options.clone(),
+            },
+            resource_limits: context
+                .session_config()
+                .get_extension::<ResourceLimits>()
+                .unwrap_or_default(),
+            metrics: FileScanMetrics::new(&self.metrics, partition),
+        };
+        let (sender, receiver) = mpsc::channel(PREFETCH_BATCHES);
+        io_handle().spawn(reader.read(self.splits.clone(), sender));
+
+        Ok(Box::pin(RecordBatchStreamAdapter::new(
+            self.schema.clone(),
+            ReceiverStream::new(receiver),
+        )))
+    }
+
+    fn metrics(&self) -> Option<MetricsSet> {
+        Some(self.metrics.clone_inner())
+    }
+
+    fn properties(&self) -> &PlanProperties {
+        &self.cache
+    }
+}
+
+/// The metrics of a file scan, which are the same for all the formats.
+struct FileScanMetrics {
+    output_rows: Count,
+    files_opened: Count,
+    /// The parts of the files skipped by their metadata, e.g., Parquet row groups
+    parts_pruned: Count,
+    open_time: Time,
+    read_time: Time,
+}
+
+impl FileScanMetrics {
+    fn new(metrics: &ExecutionPlanMetricsSet, partition: usize) -> Self {
+        Self {
+            output_rows: MetricBuilder::new(metrics).output_rows(partition),
+            files_opened: MetricBuilder::new(metrics).counter("files_opened", partition),
+            parts_pruned: MetricBuilder::new(metrics).counter("parts_pruned", partition),
+            open_time: MetricBuilder::new(metrics).subset_time("open_time", partition),
+            read_time: MetricBuilder::new(metrics).subset_time("read_time", partition),
+        }
+    }
+}
+
+struct SplitReader {
+    format: Arc<dyn NativeFileFormat>,
+    file_schema: SchemaRef,
+    options: ReadOptions,
+    resource_limits: Arc<ResourceLimits>,
+    metrics: FileScanMetrics,
+}
+
+impl SplitReader {
+    /// Reads the given splits in order, and sends their batches until the receiver is dropped.
+    async fn read(
+        self,
+        splits: Vec<FileSplit>,
+        sender: mpsc::Sender<DataFusionResult<RecordBatch>>,
+    ) {
+        let reader = Arc::new(self);
+        for split in splits {
+            let batches = match reader.clone().open(split).await {
+                Ok(batches) => batches,
+                Err(e) => {
+                    let _ = sender.send(Err(e)).await;
+                    return;
+                }
+            };
+            let mut batches = Some(batches);
+            while let Some(remaining) = batches.take() {
+                let (batch, remaining) = match reader.clone().next_batch(remaining).await {
+                    Ok(next) => next,
+                    Err(e) => {
+                        let _ = sender.send(Err(e)).await;
+                        return;
+                    }
+                };
+                let Some(batch) = batch else {
+                    break;
+                };
+                let is_err = batch.is_err();
+                if let Ok(batch) = &batch {
+                    reader.metrics.output_rows.add(batch.num_rows());
+                }
+                if sender.send(batch).await.is_err() || is_err {
+                    return;
+                }
+                batches = Some(remaining);
+            }
+        }
+    }
+
+    /// Opens and prunes the given split, and returns its batches.
+    async fn open(self: Arc<Self>, split: FileSplit) -> DataFusionResult<BatchIterator> {
+        let _io_request = self.resource_limits.acquire_io().await;
+        spawn_blocking_io(move || {
+            let _timer = self.metrics.open_time.timer();
+            let mut file = self.format.open(&split, &self.file_schema)?;
+            self.metrics.files_opened.add(1);
+            self.metrics.parts_pruned.add(file.prune(&self.options)?);
+            file.read_batches(&self.options)
+        })
+        .await
+        .map_err(|e| DataFusionError::Execution(format!("Failed to open a file: {}", e)))?
+    }
+
+    /// Reads the next batch of the given batches, which are returned to read the rest.
+    async fn next_batch(
+        self: Arc<Self>,
+        mut batches: BatchIterator,
+    ) -> DataFusionResult<(Option<DataFusionResult<RecordBatch>>, BatchIterator)> {
+        let _io_request = self.resource_limits.acquire_io().await;
+        spawn_blocking_io(move || {
+            let _timer = self.metrics.read_time.timer();
+            (batches.next(), batches)
+        })
+        .await
+        .map_err(|e| DataFusionError::Execution(format!("Failed to read a file: {}", e)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::execution::datasource::file_format;
+    use arrow_schema::{DataType, Field, Schema};
+    use datafusion::{physical_plan::common::collect, prelude::SessionContext};
+    use std::io::Write;
+
+    #[tokio::test]
+    async fn test_file_scan() {
+        let mut splits = vec![];
+        let mut files = vec![];
+        for i in 0..3 {
+            let mut file = tempfile::NamedTempFile::new().unwrap();
+            for j in 0..10 {
+                writeln!(file, "{},{}", i * 10 + j, j).unwrap();
+            }
+            splits.push(FileSplit {
+                path: file.path().to_path_buf(),
+                start: 0,
+                length: file.as_file().metadata().unwrap().len(),
+            });
+            files.push(file);
+        }
+        let file_schema = Arc::new(Schema::new(vec![
+            Field::new("a", DataType::Int32, true),
+            Field::new("b", DataType::Int32, true),
+        ]));
+        let scan = FileScanExec::try_new(
+            file_format("csv").unwrap(),
+            splits,
+            file_schema,
+            vec![0],
+            vec![],
+            HashMap::new(),
+        )
+        .unwrap();
+
+        let task_ctx = SessionContext::new().task_ctx();
+        let batches = collect(scan.execute(0, task_ctx).unwrap()).await.unwrap();
+        // The files are read in order
+        let values = batches
+            .iter()
+            .flat_map(|b| {
+                b.column(0)
+                    .as_any()
+                    .downcast_ref::<arrow_array::Int32Array>()
+                    .unwrap()
+                    .values()
+                    .to_vec()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(values, (0..30).collect::<Vec<_>>());
+
+        let metrics = scan.metrics().unwrap();
+        assert_eq!(metrics.output_rows(), Some(30));
+        assert_eq!(
+            metrics.sum_by_name("files_opened").map(|m| m.as_usize()),
+            Some(3)
+        );
+
+        // A missing file fails the scan
+        let scan = FileScanExec::try_new(
+            file_format("csv").unwrap(),
+            vec![FileSplit {
+                path: "/nonexistent".into(),
+                start: 0,
+                length: 1,
+            }],
+            scan.file_schema.clone(),
+            vec![0, 1],
+            vec![],
+            HashMap::new(),
+        )
+        .unwrap();
+        let task_ctx = SessionContext::new().task_ctx();
+        assert!(collect(scan.execute(0, task_ctx).unwrap()).await.is_err());
+    }
+}
@@ -21,7 +21,9 @@ use arrow::{
     datatypes::DataType as ArrowDataType,
     ffi::{FFI_ArrowArray, FFI_ArrowSchema},
 };
-use arrow_array::RecordBatch;
+use arrow_array::{make_array, ArrayRef, RecordBatch, RecordBatchOptions};
+use arrow_data::ArrayData;
+use arrow_schema::{Field, Schema};
 use datafusion::{
     execution::{
         disk_manager::DiskManagerConfig,
@@ -34,33 +36,50 @@ use futures::poll;
 use jni::{
     errors::Result as JNIResult,
     objects::{
-        JByteArray, JClass, JIntArray, JLongArray, JMap, JObject, JObjectArray, JPrimitiveArray,
-        JString, ReleaseMode,
+        JByteArray, JByteBuffer, JClass, JIntArray, JLongArray, JMap, JObject, JObjectArray,
+        JPrimitiveArray, JString, ReleaseMode,
     },
     sys::{jbyteArray, jint, jlong, jlongArray},
     JNIEnv,
 };
-use std::{collections::HashMap, sync::Arc, task::Poll};
+use std::{collections::HashMap, path::PathBuf, sync::Arc, task::Poll, time::Instant};
 
 use super::{serde, utils::SparkArrowConvert, CometMemoryPool};
 
 use crate::{
     errors::{try_unwrap_or_throw, CometError, CometResult},
     execution::{
-        datafusion::planner::PhysicalPlanner, metrics::utils::update_comet_metric,
-        serde::to_arrow_datatype, shuffle::row::process_sorted_row_partition, sort::RdxSort,
+        broadcast::{decode_block, deserialize_batches, serialize_batch, MappedBroadcast},
+        config::{CompressionCodec, NativeConfig},
+        datafusion::{
+            operators::{dictionary_encode::DictionaryEncoding, partial_agg::PartialAggSkip},
+            planner::PhysicalPlanner,
+            shuffle_writer::{ShuffleBlockSink, ShuffleBlockSinkFactory, ShuffleMemoryPool},
+        },
+        metrics::utils::{
+            update_comet_metric, update_jni_metrics, update_spill_metrics, JniMetrics,
+        },
+        offload::Offload,
+        runtime::{compute_runtime, io_runtime, ResourceLimits},
+        serde::to_arrow_datatype,
+        shuffle::{
+            block_sink::JvmShuffleBlockSink, coalescer::ShuffleBatchCoalescer,
+            row::process_sorted_row_partition,
+        },
+        sort::RdxSort,
         spark_operator::Operator,
+        spill::SpillManager,
     },
     jvm_bridge::{jni_new_global_ref, JVMClasses},
 };
 use futures::stream::StreamExt;
 use jni::{
     objects::GlobalRef,
-    sys::{jboolean, jdouble, jintArray, jobjectArray, jstring},
+    sys::{jboolean, jdouble, jintArray, jobject, jobjectArray, jstring},
 };
 use tokio::runtime::Runtime;
 
-use crate::execution::operators::ScanExec;
+use crate::execution::operators::{BatchSplitStream, DebugTap, ScanExec};
 use log::info;
 
 /// Comet native execution context. Kept alive across JNI calls.
@@ -75,20 +94,26 @@ struct ExecutionContext {
     pub scans: Vec<ScanExec>,
     /// The global reference of input sources for the DataFusion plan
     pub input_sources: Vec<Arc<GlobalRef>>,
+    /// The memory-mapped broadcast relations of the input sources, if any
+    pub mapped_broadcasts: Vec<Option<Arc<MappedBroadcast>>>,
     /// The record batch stream to pull results from
     pub stream: Option<SendableRecordBatchStream>,
     /// The FFI arrays. We need to keep them alive here.
     pub ffi_arrays: Vec<(Arc<FFI_ArrowArray>, Arc<FFI_ArrowSchema>)>,
     /// Configurations for DF execution
-    pub conf: HashMap<String, String>,
-    /// The Tokio runtime used for async.
-    pub runtime: Runtime,
+    pub conf: NativeConfig,
+    /// The Tokio runtime used for async, shared by all the native plans of the executor.
+    pub runtime: &'static Runtime,
     /// Native metrics
     pub metrics: Arc<GlobalRef>,
+    /// Metrics of the data crossing the JVM/native boundary
+    pub jni_metrics: JniMetrics,
+    /// The manager of the spill files of the plan
+    pub spill_manager: Arc<SpillManager>,
+    /// The limits of the resource profile of the plan, shared with the other plans of the profile
+    pub resource_limits: Arc<ResourceLimits>,
     /// DataFusion SessionContext
     pub session_ctx: Arc<SessionContext>,
-    /// Whether to enable additional debugging checks & messages
-    pub debug_native: bool,
 }
 
 /// Accept serialized query plan and return the address of the native query plan.
@@ -101,9 +126,11 @@ pub unsafe extern "system" fn Java_org_apache_comet_Native_createPlan(
     id: jlong,
     config_object: JObject,
     iterators: jobjectArray,
+    mapped_broadcasts: jlongArray,
     serialized_query: jbyteArray,
     metrics_node: JObject,
     comet_task_memory_manager_obj: JObject,
+    shuffle_block_sink: JObject,
 ) -> jlong {
     try_unwrap_or_throw(&e, |mut env| {
         // Init JVM classes
@@ -125,17 +152,22 @@ pub unsafe extern "system" fn Java_org_apache_comet_Native_createPlan(
             let value: String = env.get_string(&JString::from(value)).unwrap().into();
             configs.insert(key, value);
         }
+        let conf = NativeConfig::try_new(&configs)?;
 
-        // Whether we've enabled additional debugging on the native side
-        let debug_native = configs
-            .get("debug_native")
-            .and_then(|x| x.parse::<bool>().ok())
-            .unwrap_or(false);
+        #[cfg(feature = "prometheus")]
+        {
+            use crate::execution::metrics::exporter;
+            if let Some(port) = conf.metrics_exporter_port {
+                exporter::start_exporter(&conf.metrics_exporter_host, port);
+            }
+            exporter::EXECUTOR_METRICS.plans_created.add(1);
+        }
 
-        // Use multi-threaded tokio runtime to prevent blocking spawned tasks if any
-        let runtime = tokio::runtime::Builder::new_multi_thread()
-            .enable_all()
-            .build()?;
+        // Use the executor-wide multi-threaded tokio runtime to prevent blocking spawned tasks
+        // if any, without creating a thread pool per plan
+        let runtime = compute_runtime(&conf)?;
+        // Disk IO of operators, e.g., shuffle spilling, runs on a separate runtime
+        io_runtime(&conf)?;
 
         let metrics = Arc::new(jni_new_global_ref!(env, metrics_node)?);
 
@@ -148,13 +180,46 @@ pub unsafe extern "system" fn Java_org_apache_comet_Native_createPlan(
             let input_source = Arc::new(jni_new_global_ref!(env, input_source)?);
             input_sources.push(input_source);
         }
+
+        // The handles of mapped broadcast relations read by the input sources, or 0 for others
+        let handle_array = JLongArray::from_raw(mapped_broadcasts);
+        let mut handles = vec![0; env.get_array_length(&handle_array)? as usize];
+        env.get_long_array_region(&handle_array, 0, &mut handles)?;
+        let mapped_broadcasts = handles
+            .into_iter()
+            .map(|handle| (handle != 0).then(|| get_mapped_broadcast(handle).clone()))
+            .collect();
+
         let task_memory_manager =
             Arc::new(jni_new_global_ref!(env, comet_task_memory_manager_obj)?);
 
+        // The sink of the output partitions of native shuffle writers, if they are not written
+        // into the local shuffle files
+        let shuffle_block_sink = if shuffle_block_sink.is_null() {
+            None
+        } else {
+            Some(Arc::new(jni_new_global_ref!(env, shuffle_block_sink)?))
+        };
+
         // We need to keep the session context alive. Some session state like temporary
         // dictionaries are stored in session context. If it is dropped, the temporary
         // dictionaries will be dropped as well.
-        let session = prepare_datafusion_session_context(&configs, task_memory_manager)?;
+        let spill_manager = Arc::new(SpillManager::new(
+            conf.spill_dirs.iter().map(PathBuf::from).collect(),
+            conf.spill_disk_limit,
+        ));
+        let resource_limits = conf
+            .resource_profile
+            .as_ref()
+            .map(ResourceLimits::of_profile)
+            .unwrap_or_default();
+        let session = prepare_datafusion_session_context(
+            &conf,
+            task_memory_manager,
+            Arc::clone(&spill_manager),
+            Arc::clone(&resource_limits),
+            shuffle_block_sink,
+        )?;
 
         let exec_context = Box::new(ExecutionContext {
             id,
@@ -162,71 +227,89 @@ pub unsafe extern "system" fn Java_org_apache_comet_Native_createPlan(
             root_op: None,
             scans: vec![],
             input_sources,
+            mapped_broadcasts,
             stream: None,
             ffi_arrays: vec![],
-            conf: configs,
+            conf,
             runtime,
             metrics,
+            jni_metrics: JniMetrics::default(),
+            spill_manager,
+            resource_limits,
             session_ctx: Arc::new(session),
-            debug_native,
         });
 
         Ok(Box::into_raw(exec_context) as i64)
     })
 }
 
-/// Parse Comet configs and configure DataFusion session context.
+/// Configure DataFusion session context from Comet configs.
+///
+/// The spill files of Comet operators are created by `spill_manager`, which is passed to them
+/// as a session config extension. DataFusion operators, e.g., sorts and aggregations, spill into
+/// the same local directories through the DataFusion disk manager, which the disk limit of
+/// `spill_manager` doesn't apply to.
+///
+/// The IO requests of Comet operators are capped by `resource_limits`, which is passed to them as
+/// a session config extension too.
+///
+/// If `shuffle_block_sink` is set, native shuffle writers push their output partitions to it
+/// instead of writing the local shuffle files.
 fn prepare_datafusion_session_context(
-    conf: &HashMap<String, String>,
+    conf: &NativeConfig,
     comet_task_memory_manager: Arc<GlobalRef>,
+    spill_manager: Arc<SpillManager>,
+    resource_limits: Arc<ResourceLimits>,
+    shuffle_block_sink: Option<Arc<GlobalRef>>,
 ) -> CometResult<SessionContext> {
-    // Get the batch size from Comet JVM side
-    let batch_size = conf
-        .get("batch_size")
-        .ok_or(CometError::Internal(
-            "Config 'batch_size' is not specified from Comet JVM side".to_string(),
-        ))?
-        .parse::<usize>()?;
-
-    let mut rt_config = RuntimeConfig::new().with_disk_manager(DiskManagerConfig::NewOs);
-
-    // Check if we are using unified memory manager integrated with Spark. Default to false if not
-    // set.
-    let use_unified_memory_manager = conf
-        .get("use_unified_memory_manager")
-        .map(String::as_str)
-        .unwrap_or("false")
-        .parse::<bool>()?;
-
-    if use_unified_memory_manager {
+    let disk_manager = if conf.spill_dirs.is_empty() {
+        DiskManagerConfig::NewOs
+    } else {
+        DiskManagerConfig::NewSpecified(conf.spill_dirs.iter().map(PathBuf::from).collect())
+    };
+    let mut rt_config = RuntimeConfig::new().with_disk_manager(disk_manager);
+
+    // Check if we are using unified memory manager integrated with Spark.
+    if conf.use_unified_memory_manager {
         // Set Comet memory pool for native
-        let memory_pool = CometMemoryPool::new(comet_task_memory_manager);
+        let memory_pool = CometMemoryPool::new(comet_task_memory_manager.clone());
         rt_config = rt_config.with_memory_pool(Arc::new(memory_pool));
-    } else {
+    } else if let Some(memory_limit) = conf.memory_limit {
         // Use the memory pool from DF
-        if conf.contains_key("memory_limit") {
-            let memory_limit = conf.get("memory_limit").unwrap().parse::<usize>()?;
-            let memory_fraction = conf
-                .get("memory_fraction")
-                .ok_or(CometError::Internal(
-                    "Config 'memory_fraction' is not specified from Comet JVM side".to_string(),
-                ))?
-                .parse::<f64>()?;
-            rt_config = rt_config.with_memory_limit(memory_limit, memory_fraction)
-        }
+        rt_config = rt_config.with_memory_limit(memory_limit, conf.effective_memory_fraction())
     }
 
     // Get Datafusion configuration from Spark Execution context
     // can be configured in Comet Spark JVM using Spark --conf parameters
     // e.g: spark-shell --conf spark.datafusion.sql_parser.parse_float_as_decimal=true
-    let mut session_config = SessionConfig::new().with_batch_size(batch_size);
+    let mut session_config = SessionConfig::new()
+        .with_batch_size(conf.batch_size)
+        .with_extension(spill_manager)
+        .with_extension(resource_limits);
+
+    // Shuffle writers acquire memory from Spark separately, so that Spark can ask them to spill
+    if conf.use_unified_shuffle_memory {
+        let memory_pool = CometMemoryPool::new_for_shuffle(comet_task_memory_manager.clone());
+        session_config =
+            session_config.with_extension(Arc::new(ShuffleMemoryPool(Arc::new(memory_pool))));
+    }
 
-    for (key, value) in conf.iter().filter(|(k, _)| k.starts_with("datafusion.")) {
+    if let Some(sink) = shuffle_block_sink {
+        let factory = ShuffleBlockSinkFactory(Arc::new(move || -> Box<dyn ShuffleBlockSink> {
+            Box::new(JvmShuffleBlockSink::new(Arc::clone(&sink)))
+        }));
+        session_config = session_config.with_extension(Arc::new(factory));
+    }
+
+    for (key, value) in conf.datafusion_configs.iter() {
         session_config = session_config.set_str(key, value);
     }
 
     let runtime = RuntimeEnv::new(rt_config).unwrap();
 
+    #[cfg(feature = "prometheus")]
+    crate::execution::metrics::exporter::register_memory_pool(&runtime.memory_pool);
+
     Ok(SessionContext::new_with_config_rt(
         session_config,
         Arc::new(runtime),
@@ -239,10 +322,11 @@ fn prepare_output(
     output_batch: RecordBatch,
     exec_context: &mut ExecutionContext,
 ) -> CometResult<jlongArray> {
+    let start = Instant::now();
     let results = output_batch.columns();
     let num_rows = output_batch.num_rows();
 
-    if exec_context.debug_native {
+    if exec_context.conf.debug_native {
         // Validate the output arrays.
         for array in results.iter() {
             let array_data = array.to_data();
@@ -274,6 +358,11 @@ fn prepare_output(
         i += 1;
     }
 
+    let jni_metrics = &mut exec_context.jni_metrics;
+    jni_metrics.export_batches += 1;
+    jni_metrics.export_bytes += output_batch.get_array_memory_size();
+    jni_metrics.time += start.elapsed();
+
     // Update metrics
     update_metrics(env, exec_context)?;
 
@@ -291,8 +380,9 @@ fn prepare_output(
 /// operators before polling the stream,
 #[inline]
 fn pull_input_batches(exec_context: &mut ExecutionContext) -> Result<(), CometError> {
+    let jni_metrics = &mut exec_context.jni_metrics;
     exec_context.scans.iter_mut().try_for_each(|scan| {
-        scan.get_next_batch()?;
+        scan.get_next_batch(jni_metrics)?;
         Ok::<(), CometError>(())
     })
 }
@@ -313,12 +403,22 @@ pub unsafe extern "system" fn Java_org_apache_comet_Native_executePlan(
 
         let exec_context_id = exec_context.id;
 
+        // Waits until the resource profile of the plan allows another thread to execute it
+        let _decode_thread = exec_context.resource_limits.acquire_decode_thread();
+
         // Initialize the execution stream.
         // Because we don't know if input arrays are dictionary-encoded when we create
         // query plan, we need to defer stream initialization to first time execution.
         if exec_context.root_op.is_none() {
             let planner = PhysicalPlanner::new(exec_context.session_ctx.clone())
-                .with_exec_id(exec_context_id);
+                .with_exec_id(exec_context_id)
+                .with_batch_validation(exec_context.conf.debug_validate_batches)
+                .with_partition_index(exec_context.conf.partition_index)
+                .with_debug_tap(debug_tap(&exec_context.conf))
+                .with_partial_agg_skip(partial_agg_skip(&exec_context.conf))
+                .with_dictionary_encoding(dictionary_encoding(&exec_context.conf))
+                .with_mapped_broadcasts(exec_context.mapped_broadcasts.clone())
+                .with_offload(Offload::of_config(&exec_context.conf));
             let (scans, root_op) = planner.create_plan(
                 &exec_context.spark_plan,
                 &mut exec_context.input_sources.clone(),
@@ -326,8 +426,12 @@ pub unsafe extern "system" fn Java_org_apache_comet_Native_executePlan(
 
             exec_context.root_op = Some(root_op.clone());
             exec_context.scans = scans;
+            exec_context
+                .scans
+                .iter()
+                .for_each(|scan| scan.record_first_batch(&mut exec_context.jni_metrics));
 
-            if exec_context.debug_native {
+            if exec_context.conf.debug_native {
                 let formatted_plan_str =
                     DisplayableExecutionPlan::new(root_op.as_ref()).indent(true);
                 info!("Comet native query plan:\n {formatted_plan_str:}");
@@ -339,7 +443,12 @@ pub unsafe extern "system" fn Java_org_apache_comet_Native_executePlan(
                 .as_ref()
                 .unwrap()
                 .execute(0, task_ctx)?;
-            exec_context.stream = Some(stream);
+            // Some operators, e.g., joins, may produce batches larger than the batch size.
+            // Slice them so JVM side only imports batches within the configured size.
+            exec_context.stream = Some(Box::pin(BatchSplitStream::new(
+                stream,
+                exec_context.conf.batch_size,
+            )));
         } else {
             // Pull input batches
             pull_input_batches(exec_context)?;
@@ -348,7 +457,14 @@ pub unsafe extern "system" fn Java_org_apache_comet_Native_executePlan(
         loop {
             // Polling the stream.
             let next_item = exec_context.stream.as_mut().unwrap().next();
-            let poll_output = exec_context.runtime.block_on(async { poll!(next_item) });
+            let poll_output = if exec_context.scans.is_empty() {
+                // Without inputs from JVM, a pending stream waits for native sources only, e.g.,
+                // file scans on the IO runtime. Wait for the next batch instead of polling in a
+                // busy loop, which returns it as soon as it is produced.
+                Poll::Ready(exec_context.runtime.block_on(next_item))
+            } else {
+                exec_context.runtime.block_on(async { poll!(next_item) })
+            };
 
             match poll_output {
                 Poll::Ready(Some(output)) => {
@@ -397,16 +513,78 @@ pub extern "system" fn Java_org_apache_comet_Native_releasePlan(
 ) {
     try_unwrap_or_throw(&e, |_| unsafe {
         let execution_context = get_execution_context(exec_context);
+        let _exec_context_id = execution_context.id;
         let _: Box<ExecutionContext> = Box::from_raw(execution_context);
+
+        #[cfg(feature = "alloc_tracking")]
+        crate::execution::alloc_tracker::report_leaks(_exec_context_id);
+
+        #[cfg(feature = "prometheus")]
+        crate::execution::metrics::exporter::EXECUTOR_METRICS
+            .plans_released
+            .add(1);
+
         Ok(())
     })
 }
 
+#[no_mangle]
+/// Returns the effective value of the given config of a native plan, or null if the config is
+/// neither set nor has a default value.
+/// # Safety
+/// This function is inheritly unsafe since it deals with raw pointers passed from JNI.
+pub unsafe extern "system" fn Java_org_apache_comet_Native_getConfig(
+    e: JNIEnv,
+    _class: JClass,
+    exec_context: jlong,
+    key: jstring,
+) -> jstring {
+    try_unwrap_or_throw(&e, |mut env| {
+        let exec_context = get_execution_context(exec_context);
+        let key: String = env.get_string(&JString::from_raw(key))?.into();
+        match exec_context.conf.get(&key) {
+            Some(value) => Ok(env.new_string(value)?.into_raw()),
+            None => Ok(std::ptr::null_mut()),
+        }
+    })
+}
+
+/// Returns the debug tap configured for native plans, if any.
+fn debug_tap(conf: &NativeConfig) -> Option<DebugTap> {
+    conf.debug_tap_operator.as_ref().map(|operator| DebugTap {
+        operator: operator.clone(),
+        dir: conf
+            .debug_tap_dir
+            .as_ref()
+            .map(PathBuf::from)
+            .unwrap_or_else(std::env::temp_dir),
+    })
+}
+
+/// Returns when to skip partial aggregation, if it is enabled.
+fn partial_agg_skip(conf: &NativeConfig) -> Option<PartialAggSkip> {
+    conf.partial_agg_skip_enabled.then_some(PartialAggSkip {
+        probe_rows: conf.partial_agg_skip_probe_rows,
+        ratio_threshold: conf.partial_agg_skip_ratio_threshold,
+    })
+}
+
+/// Returns when to encode string columns as dictionaries, if it is enabled.
+fn dictionary_encoding(conf: &NativeConfig) -> Option<DictionaryEncoding> {
+    conf.dictionary_encoding_enabled
+        .then_some(DictionaryEncoding {
+            sample_rows: conf.dictionary_encoding_sample_rows,
+            max_ratio: conf.dictionary_encoding_max_ratio,
+        })
+}
+
 /// Updates the metrics of the query plan.
 fn update_metrics(env: &mut JNIEnv, exec_context: &ExecutionContext) -> CometResult<()> {
     let native_query = exec_context.root_op.as_ref().unwrap();
     let metrics = exec_context.metrics.as_obj();
-    update_comet_metric(env, metrics, native_query)
+    update_comet_metric(env, metrics, native_query)?;
+    update_jni_metrics(env, metrics, &exec_context.jni_metrics)?;
+    update_spill_metrics(env, metrics, &exec_context.spill_manager)
 }
 
 fn convert_datatype_arrays(
@@ -452,6 +630,8 @@ pub unsafe extern "system" fn Java_org_apache_comet_Native_writeSortedFileNative
     file_path: jstring,
     prefer_dictionary_ratio: jdouble,
     batch_size: jlong,
+    codec: jstring,
+    zstd_level: jint,
     checksum_enabled: jboolean,
     checksum_algo: jint,
     current_checksum: jlong,
@@ -474,6 +654,9 @@ pub unsafe extern "system" fn Java_org_apache_comet_Native_writeSortedFileNative
             .unwrap()
             .into();
 
+        let codec: String = env.get_string(&JString::from_raw(codec))?.into();
+        let codec = CompressionCodec::try_new(&codec, zstd_level)?;
+
         let checksum_enabled = checksum_enabled == 1;
         let current_checksum = if current_checksum == i64::MIN {
             // Initial checksum is not available.
@@ -490,6 +673,7 @@ pub unsafe extern "system" fn Java_org_apache_comet_Native_writeSortedFileNative
             &data_types,
             output_path,
             prefer_dictionary_ratio,
+            &codec,
             checksum_enabled,
             checksum_algo,
             current_checksum,
@@ -526,3 +710,321 @@ pub extern "system" fn Java_org_apache_comet_Native_sortRowPartitionsNative(
         Ok(())
     })
 }
+
+/// Used by Comet broadcast exchange to serialize a batch exported by `NativeUtil.exportBatch`
+/// into compressed Arrow IPC bytes.
+/// # Safety
+/// This function is inheritly unsafe since it deals with raw pointers passed from JNI.
+#[no_mangle]
+pub unsafe extern "system" fn Java_org_apache_comet_Native_serializeBatch(
+    e: JNIEnv,
+    _class: JClass,
+    addresses: jlongArray,
+) -> jbyteArray {
+    try_unwrap_or_throw(&e, |mut env| unsafe {
+        let address_array = JLongArray::from_raw(addresses);
+        let num_addresses = env.get_array_length(&address_array)? as usize;
+        let mut addresses = vec![0; num_addresses];
+        env.get_long_array_region(&address_array, 0, &mut addresses)?;
+
+        // The number of rows, followed by (address of Arrow array, address of Arrow schema) of
+        // each column
+        let num_rows = addresses[0] as usize;
+        let columns = addresses[1..]
+            .chunks(2)
+            .map(|pair| Ok(make_array(ArrayData::from_spark((pair[0], pair[1]))?)))
+            .collect::<Result<Vec<ArrayRef>, CometError>>()?;
+        let fields = columns
+            .iter()
+            .enumerate()
+            .map(|(i, column)| Field::new(format!("c{}", i), column.data_type().clone(), true))
+            .collect::<Vec<_>>();
+        let options = RecordBatchOptions::new().with_row_count(Some(num_rows));
+        let batch =
+            RecordBatch::try_new_with_options(Arc::new(Schema::new(fields)), columns, &options)?;
+
+        let bytes = serialize_batch(&batch)?;
+        Ok(env.byte_array_from_slice(&bytes)?.into_raw())
+    })
+}
+
+/// Used by Comet broadcast exchange to deserialize a batch serialized by `serializeBatch`, and
+/// move its columns into the given Arrow arrays and schemas allocated by JVM.
+/// Returns the number of rows of the batch.
+/// # Safety
+/// This function is inheritly unsafe since it deals with raw pointers passed from JNI.
+#[no_mangle]
+pub unsafe extern "system" fn Java_org_apache_comet_Native_deserializeBatch(
+    e: JNIEnv,
+    _class: JClass,
+    bytes: jbyteArray,
+    array_addresses: jlongArray,
+    schema_addresses: jlongArray,
+) -> jlong {
+    try_unwrap_or_throw(&e, |mut env| unsafe {
+        let bytes = env.convert_byte_array(JByteArray::from_raw(bytes))?;
+        let batches = deserialize_batches(&bytes)?;
+        if batches.len() != 1 {
+            return Err(CometError::Internal(format!(
+                "Expected 1 serialized batch but got {}",
+                batches.len()
+            )));
+        }
+        move_batch_to_spark(&mut env, &batches[0], array_addresses, schema_addresses)
+    })
+}
+
+/// Used by the native shuffle reader to decode a block of Comet native shuffle, i.e., the Arrow
+/// IPC stream of a batch compressed with `codec`, from the first `length` bytes of the given
+/// direct byte buffer, and move its columns into the given Arrow arrays and schemas allocated by
+/// JVM. The block is decompressed and decoded without being copied into the JVM heap.
+/// Returns the number of rows of the batch.
+/// # Safety
+/// This function is inheritly unsafe since it deals with raw pointers passed from JNI.
+#[no_mangle]
+pub unsafe extern "system" fn Java_org_apache_comet_Native_decodeShuffleBlock(
+    e: JNIEnv,
+    _class: JClass,
+    byte_buffer: jobject,
+    length: jint,
+    codec: jstring,
+    array_addresses: jlongArray,
+    schema_addresses: jlongArray,
+) -> jlong {
+    try_unwrap_or_throw(&e, |mut env| unsafe {
+        let codec: String = env.get_string(&JString::from_raw(codec))?.into();
+        let codec = codec
+            .parse::<CompressionCodec>()
+            .map_err(CometError::Config)?;
+        let address = env.get_direct_buffer_address(&JByteBuffer::from_raw(byte_buffer))?;
+        let block = std::slice::from_raw_parts(address, length as usize);
+        let batches = decode_block(block, &codec)?;
+        if batches.len() != 1 {
+            return Err(CometError::Internal(format!(
+                "Expected 1 batch in the shuffle block but got {}",
+                batches.len()
+            )));
+        }
+        move_batch_to_spark(&mut env, &batches[0], array_addresses, schema_addresses)
+    })
+}
+
+/// Creates the coalescer of the batches decoded from the fetched blocks of a native shuffle
+/// compressed with `codec`, which coalesces them until they have `target_rows` rows or
+/// `target_bytes` bytes.
+/// Returns the handle of the coalescer, which must be released by `releaseShuffleCoalescer`.
+/// # Safety
+/// This function is inheritly unsafe since it deals with raw pointers passed from JNI.
+#[no_mangle]
+pub unsafe extern "system" fn Java_org_apache_comet_Native_createShuffleCoalescer(
+    e: JNIEnv,
+    _class: JClass,
+    codec: jstring,
+    target_rows: jint,
+    target_bytes: jlong,
+) -> jlong {
+    try_unwrap_or_throw(&e, |mut env| unsafe {
+        let codec: String = env.get_string(&JString::from_raw(codec))?.into();
+        let codec = codec
+            .parse::<CompressionCodec>()
+            .map_err(CometError::Config)?;
+        let coalescer =
+            ShuffleBatchCoalescer::new(codec, target_rows as usize, target_bytes as usize);
+        Ok(Box::into_raw(Box::new(coalescer)) as jlong)
+    })
+}
+
+/// Decodes the block of a native shuffle from the first `length` bytes of the given direct byte
+/// buffer into the given coalescer. The block is the compressed Arrow IPC stream without its
+/// length. If `length` is negative, there is no more block and the coalescer coalesces the
+/// batches it buffers.
+/// Returns the number of the coalesced batches ready to be taken by `nextCoalescedBatch`.
+/// # Safety
+/// This function is inheritly unsafe since it deals with raw pointers passed from JNI.
+#[no_mangle]
+pub unsafe extern "system" fn Java_org_apache_comet_Native_coalesceShuffleBlock(
+    e: JNIEnv,
+    _class: JClass,
+    handle: jlong,
+    byte_buffer: jobject,
+    length: jint,
+) -> jint {
+    try_unwrap_or_throw(&e, |env| unsafe {
+        let coalescer = get_shuffle_coalescer(handle);
+        let ready = if length < 0 {
+            coalescer.finish()?
+        } else {
+            let address = env.get_direct_buffer_address(&JByteBuffer::from_raw(byte_buffer))?;
+            coalescer.push_block(std::slice::from_raw_parts(address, length as usize))?
+        };
+        Ok(ready as jint)
+    })
+}
+
+/// Moves the columns of the next coalesced batch of the given coalescer into the given Arrow
+/// arrays and schemas allocated by JVM.
+/// Returns the number of rows of the batch.
+/// # Safety
+/// This function is inheritly unsafe since it deals with raw pointers passed from JNI.
+#[no_mangle]
+pub unsafe extern "system" fn Java_org_apache_comet_Native_nextCoalescedBatch(
+    e: JNIEnv,
+    _class: JClass,
+    handle: jlong,
+    array_addresses: jlongArray,
+    schema_addresses: jlongArray,
+) -> jlong {
+    try_unwrap_or_throw(&e, |mut env| unsafe {
+        let batch = get_shuffle_coalescer(handle).next_batch().ok_or_else(|| {
+            CometError::Internal("No coalesced shuffle batch is ready".to_string())
+        })?;
+        move_batch_to_spark(&mut env, &batch, array_addresses, schema_addresses)
+    })
+}
+
+/// Releases the shuffle coalescer returned by `createShuffleCoalescer`.
+#[no_mangle]
+pub extern "system" fn Java_org_apache_comet_Native_releaseShuffleCoalescer(
+    e: JNIEnv,
+    _class: JClass,
+    handle: jlong,
+) {
+    try_unwrap_or_throw(&e, |_| unsafe {
+        let _: Box<ShuffleBatchCoalescer> = Box::from_raw(handle as *mut ShuffleBatchCoalescer);
+        Ok(())
+    })
+}
+
+fn get_shuffle_coalescer<'a>(handle: i64) -> &'a mut ShuffleBatchCoalescer {
+    unsafe {
+        (handle as *mut ShuffleBatchCoalescer)
+            .as_mut()
+            .expect("Comet shuffle coalescer shouldn't be null!")
+    }
+}
+
+/// Moves the columns of the batch into the given Arrow arrays and schemas allocated by JVM.
+/// Returns the number of rows of the batch.
+unsafe fn move_batch_to_spark(
+    env: &mut JNIEnv,
+    batch: &RecordBatch,
+    array_addresses: jlongArray,
+    schema_addresses: jlongArray,
+) -> CometResult<jlong> {
+    let array_addresses = JLongArray::from_raw(array_addresses);
+    let schema_addresses = JLongArray::from_raw(schema_addresses);
+    let num_columns = env.get_array_length(&array_addresses)? as usize;
+    if num_columns != batch.num_columns()
+        || env.get_array_length(&schema_addresses)? as usize != num_columns
+    {
+        return Err(CometError::Internal(format!(
+            "Expected {} columns but got {}",
+            num_columns,
+            batch.num_columns()
+        )));
+    }
+    let mut arrays = vec![0; num_columns];
+    env.get_long_array_region(&array_addresses, 0, &mut arrays)?;
+    let mut schemas = vec![0; num_columns];
+    env.get_long_array_region(&schema_addresses, 0, &mut schemas)?;
+
+    for (column, addresses) in batch.columns().iter().zip(arrays.into_iter().zip(schemas)) {
+        column.to_data().move_to_spark(addresses)?;
+    }
+
+    Ok(batch.num_rows() as jlong)
+}
+
+/// Used by Comet broadcast exchange to map a broadcast relation serialized by `serializeBatch`
+/// into memory, which is shared by the tasks of the executor. If `chunks` is null, only returns
+/// the relation already mapped by other tasks, or 0 if there is none.
+/// Returns the handle of the mapped relation, which must be released by
+/// `releaseMappedBroadcast`.
+/// # Safety
+/// This function is inheritly unsafe since it deals with raw pointers passed from JNI.
+#[no_mangle]
+pub unsafe extern "system" fn Java_org_apache_comet_Native_mapBroadcast(
+    e: JNIEnv,
+    _class: JClass,
+    broadcast_id: jlong,
+    chunks: jobjectArray,
+    dir: jstring,
+) -> jlong {
+    try_unwrap_or_throw(&e, |mut env| unsafe {
+        let relation = if chunks.is_null() {
+            match MappedBroadcast::get(broadcast_id) {
+                Some(relation) => relation,
+                None => return Ok(0),
+            }
+        } else {
+            let chunk_array = JObjectArray::from_raw(chunks);
+            let num_chunks = env.get_array_length(&chunk_array)?;
+            let mut chunks = Vec::with_capacity(num_chunks as usize);
+            for i in 0..num_chunks {
+                let chunk: JByteArray = env.get_object_array_element(&chunk_array, i)?.into();
+                chunks.push(env.convert_byte_array(chunk)?);
+            }
+            let dir: String = env.get_string(&JString::from_raw(dir))?.into();
+            MappedBroadcast::get_or_map(broadcast_id, &chunks, &PathBuf::from(dir))?
+        };
+        Ok(Box::into_raw(Box::new(relation)) as jlong)
+    })
+}
+
+/// Returns the number of batches of the mapped broadcast relation.
+#[no_mangle]
+pub extern "system" fn Java_org_apache_comet_Native_numMappedBatches(
+    e: JNIEnv,
+    _class: JClass,
+    handle: jlong,
+) -> jint {
+    try_unwrap_or_throw(&e, |_| {
+        let relation = get_mapped_broadcast(handle);
+        Ok(relation.batches().len() as jint)
+    })
+}
+
+/// Moves the columns of the given batch of the mapped broadcast relation into the given Arrow
+/// arrays and schemas allocated by JVM, without copying the mapped data.
+/// Returns the number of rows of the batch.
+/// # Safety
+/// This function is inheritly unsafe since it deals with raw pointers passed from JNI.
+#[no_mangle]
+pub unsafe extern "system" fn Java_org_apache_comet_Native_getMappedBatch(
+    e: JNIEnv,
+    _class: JClass,
+    handle: jlong,
+    index: jint,
+    array_addresses: jlongArray,
+    schema_addresses: jlongArray,
+) -> jlong {
+    try_unwrap_or_throw(&e, |mut env| unsafe {
+        let relation = get_mapped_broadcast(handle);
+        let batch = relation
+            .batches()
+            .get(index as usize)
+            .ok_or(CometError::IndexOutOfBounds(index as usize))?;
+        move_batch_to_spark(&mut env, batch, array_addresses, schema_addresses)
+    })
+}
+
+/// Releases the handle of the mapped broadcast relation returned by `mapBroadcast`.
+#[no_mangle]
+pub extern "system" fn Java_org_apache_comet_Native_releaseMappedBroadcast(
+    e: JNIEnv,
+    _class: JClass,
+    handle: jlong,
+) {
+    try_unwrap_or_throw(&e, |_| unsafe {
+        let _: Box<Arc<MappedBroadcast>> = Box::from_raw(handle as *mut Arc<MappedBroadcast>);
+        Ok(())
+    })
+}
+
+fn get_mapped_broadcast<'a>(handle: i64) -> &'a Arc<MappedBroadcast> {
+    unsafe {
+        (handle as *const Arc<MappedBroadcast>)
+            .as_ref()
+            .expect("Comet mapped broadcast shouldn't be null!")
+    }
+}
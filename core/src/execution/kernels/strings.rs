@@ -28,11 +28,8 @@ use arrow::{
 
 use crate::errors::ExpressionError;
 
-/// Returns an ArrayRef with a string consisting of `length` spaces.
-///
-/// # Preconditions
-///
-/// - elements in `length` must not be negative
+/// Returns an ArrayRef with a string consisting of `length` spaces. Like Spark, the string is
+/// empty if `length` is negative.
 pub fn string_space(length: &dyn Array) -> Result<ArrayRef, ExpressionError> {
     match length.data_type() {
         DataType::Int32 => {
@@ -130,12 +127,22 @@ fn generic_string_space<OffsetSize: OffsetSizeTrait>(length: &Int32Array) -> Arr
     let mut length_so_far = OffsetSize::zero();
 
     // compute null bitmap (copy)
-    let null_bit_buffer = length.to_data().nulls().map(|b| b.buffer().clone());
-
-    // Gets slice of length array to access it directly for performance.
-    let length_data = length.to_data();
-    let lengths = length_data.buffers()[0].typed_data::<i32>();
-    let total = lengths.iter().map(|l| *l as usize).sum::<usize>();
+    let null_bit_buffer = length.nulls().map(|b| b.inner().sliced());
+
+    // The lengths of the strings, where a negative or null length is 0
+    let lengths = length
+        .values()
+        .iter()
+        .enumerate()
+        .map(|(i, l)| {
+            if length.is_null(i) {
+                0
+            } else {
+                (*l).max(0) as usize
+            }
+        })
+        .collect::<Vec<_>>();
+    let total = lengths.iter().sum::<usize>();
     let mut values = MutableBuffer::new(total);
 
     offsets.push(length_so_far);
@@ -143,10 +150,8 @@ fn generic_string_space<OffsetSize: OffsetSizeTrait>(length: &Int32Array) -> Arr
     let blank = " ".as_bytes()[0];
     values.resize(total, blank);
 
-    (0..array_len).for_each(|i| {
-        let current_len = lengths[i] as usize;
-
-        length_so_far += OffsetSize::from_usize(current_len).unwrap();
+    lengths.iter().for_each(|current_len| {
+        length_so_far += OffsetSize::from_usize(*current_len).unwrap();
         offsets.push(length_so_far);
     });
 
@@ -39,12 +39,16 @@ use crate::{
 /// implemented via delegating calls to [`crate::jvm_bridge::CometTaskMemoryManager`].
 pub struct CometMemoryPool {
     task_memory_manager_handle: Arc<GlobalRef>,
+    /// Whether the memory is acquired for native shuffle writers, which are accounted as a
+    /// separate memory consumer of the task
+    shuffle: bool,
     used: AtomicUsize,
 }
 
 impl Debug for CometMemoryPool {
     fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
         f.debug_struct("CometMemoryPool")
+            .field("shuffle", &self.shuffle)
             .field("used", &self.used.load(Relaxed))
             .finish()
     }
@@ -54,6 +58,18 @@ impl CometMemoryPool {
     pub fn new(task_memory_manager_handle: Arc<GlobalRef>) -> CometMemoryPool {
         Self {
             task_memory_manager_handle,
+            shuffle: false,
+            used: AtomicUsize::new(0),
+        }
+    }
+
+    /// Creates the memory pool of native shuffle writers. Spark can ask them to spill to free
+    /// memory for other consumers of the task, in which case the next acquisition fails, so that
+    /// they spill their buffers before acquiring the memory again.
+    pub fn new_for_shuffle(task_memory_manager_handle: Arc<GlobalRef>) -> CometMemoryPool {
+        Self {
+            task_memory_manager_handle,
+            shuffle: true,
             used: AtomicUsize::new(0),
         }
     }
@@ -62,8 +78,13 @@ impl CometMemoryPool {
         let mut env = JVMClasses::get_env();
         let handle = self.task_memory_manager_handle.as_obj();
         unsafe {
-            jni_call!(&mut env,
-              comet_task_memory_manager(handle).acquire_memory(additional as i64) -> i64)
+            if self.shuffle {
+                jni_call!(&mut env,
+                  comet_task_memory_manager(handle).acquire_shuffle_memory(additional as i64) -> i64)
+            } else {
+                jni_call!(&mut env,
+                  comet_task_memory_manager(handle).acquire_memory(additional as i64) -> i64)
+            }
         }
     }
 
@@ -71,7 +92,13 @@ impl CometMemoryPool {
         let mut env = JVMClasses::get_env();
         let handle = self.task_memory_manager_handle.as_obj();
         unsafe {
-            jni_call!(&mut env, comet_task_memory_manager(handle).release_memory(size as i64) -> ())
+            if self.shuffle {
+                jni_call!(&mut env,
+                  comet_task_memory_manager(handle).release_shuffle_memory(size as i64) -> ())
+            } else {
+                jni_call!(&mut env,
+                  comet_task_memory_manager(handle).release_memory(size as i64) -> ())
+            }
         }
     }
 }
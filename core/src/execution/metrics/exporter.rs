@@ -0,0 +1,221 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Executor-level native metrics exported in the Prometheus text format, enabled by the
+//! `prometheus` feature.
+//!
+//! Unlike the per-operator metrics which are reported to Spark SQL metrics, these metrics are
+//! aggregated over all the native plans of an executor, and are served on `/metrics` of a local
+//! HTTP port by a background thread, independent of the Spark UI.
+
+use std::{
+    fmt::Write as _,
+    io::{BufRead, BufReader, Read, Write},
+    net::{TcpListener, TcpStream},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Weak,
+    },
+    thread,
+    time::Duration,
+};
+
+use datafusion::execution::memory_pool::MemoryPool;
+use log::{info, warn};
+use once_cell::sync::{Lazy, OnceCell};
+use parking_lot::Mutex;
+
+/// A monotonically increasing metric.
+#[derive(Default)]
+pub struct Counter(AtomicU64);
+
+impl Counter {
+    pub fn add(&self, value: usize) {
+        self.0.fetch_add(value as u64, Ordering::Relaxed);
+    }
+
+    fn value(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Native metrics aggregated over all the native plans of the executor.
+#[derive(Default)]
+pub struct ExecutorMetrics {
+    /// Number of native plans created
+    pub plans_created: Counter,
+    /// Number of native plans released
+    pub plans_released: Counter,
+    /// Number of rows imported from JVM by scans
+    pub scan_input_rows: Counter,
+    /// Number of batches imported from JVM by scans
+    pub scan_input_batches: Counter,
+    /// Number of bytes spilled to disk
+    pub spilled_bytes: Counter,
+    /// Number of spills
+    pub spill_count: Counter,
+    /// Number of bytes written to shuffle data files
+    pub shuffle_written_bytes: Counter,
+}
+
+pub static EXECUTOR_METRICS: Lazy<ExecutorMetrics> = Lazy::new(ExecutorMetrics::default);
+
+/// Memory pools of the native plans, used to report the memory reserved by native plans.
+static MEMORY_POOLS: Lazy<Mutex<Vec<Weak<dyn MemoryPool>>>> = Lazy::new(|| Mutex::new(vec![]));
+
+static EXPORTER: OnceCell<()> = OnceCell::new();
+
+/// The timeout to read a request or write a response, so that a client that doesn't send its
+/// request doesn't block the exporter, which serves one connection at a time.
+const IO_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The maximum length of a request line. The rest of the request is ignored.
+const MAX_REQUEST_LINE: u64 = 8192;
+
+/// Registers the memory pool of a native plan. It is unregistered once the plan is dropped.
+pub fn register_memory_pool(pool: &Arc<dyn MemoryPool>) {
+    let mut pools = MEMORY_POOLS.lock();
+    pools.retain(|pool| pool.strong_count() > 0);
+    pools.push(Arc::downgrade(pool));
+}
+
+/// Starts the exporter serving the metrics on the given address and port, if it is not started
+/// yet.
+pub fn start_exporter(host: &str, port: u16) {
+    EXPORTER.get_or_init(|| match TcpListener::bind((host, port)) {
+        Ok(listener) => {
+            info!("Serving Comet native metrics on {}:{}", host, port);
+            let spawned = thread::Builder::new()
+                .name("comet-metrics-exporter".to_string())
+                .spawn(move || {
+                    for stream in listener.incoming().flatten() {
+                        if let Err(e) = handle_request(stream) {
+                            warn!("Failed to serve Comet native metrics: {}", e);
+                        }
+                    }
+                });
+            if let Err(e) = spawned {
+                warn!("Failed to start Comet native metrics exporter: {}", e);
+            }
+        }
+        // e.g., another executor on the same host already uses the port
+        Err(e) => warn!(
+            "Failed to bind Comet native metrics exporter to {}:{}: {}",
+            host, port, e
+        ),
+    });
+}
+
+fn handle_request(mut stream: TcpStream) -> std::io::Result<()> {
+    stream.set_read_timeout(Some(IO_TIMEOUT))?;
+    stream.set_write_timeout(Some(IO_TIMEOUT))?;
+
+    let mut request_line = String::new();
+    BufReader::new((&stream).take(MAX_REQUEST_LINE)).read_line(&mut request_line)?;
+
+    let path = request_line.split_whitespace().nth(1).unwrap_or_default();
+    let (status, body) = if path == "/metrics" {
+        ("200 OK", render())
+    } else {
+        ("404 Not Found", String::new())
+    };
+
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\n\
+         Connection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    )?;
+    stream.flush()
+}
+
+/// Renders the metrics in the Prometheus text exposition format.
+fn render() -> String {
+    let metrics = &*EXECUTOR_METRICS;
+    let memory_reserved: usize = {
+        let mut pools = MEMORY_POOLS.lock();
+        pools.retain(|pool| pool.strong_count() > 0);
+        pools
+            .iter()
+            .filter_map(|pool| pool.upgrade())
+            .map(|pool| pool.reserved())
+            .sum()
+    };
+    let plans_active = metrics
+        .plans_created
+        .value()
+        .saturating_sub(metrics.plans_released.value());
+
+    let mut out = String::new();
+    let mut metric = |name: &str, kind: &str, help: &str, value: u64| {
+        let _ = write!(
+            out,
+            "# HELP {name} {help}\n# TYPE {name} {kind}\n{name} {value}\n"
+        );
+    };
+    metric(
+        "comet_native_plans_created_total",
+        "counter",
+        "Number of native plans created.",
+        metrics.plans_created.value(),
+    );
+    metric(
+        "comet_native_plans_active",
+        "gauge",
+        "Number of native plans not released yet.",
+        plans_active,
+    );
+    metric(
+        "comet_native_memory_reserved_bytes",
+        "gauge",
+        "Memory reserved from the memory pools of native plans.",
+        memory_reserved as u64,
+    );
+    metric(
+        "comet_native_scan_input_rows_total",
+        "counter",
+        "Number of rows imported from JVM by native scans.",
+        metrics.scan_input_rows.value(),
+    );
+    metric(
+        "comet_native_scan_input_batches_total",
+        "counter",
+        "Number of batches imported from JVM by native scans.",
+        metrics.scan_input_batches.value(),
+    );
+    metric(
+        "comet_native_spilled_bytes_total",
+        "counter",
+        "Number of bytes spilled to disk by native operators.",
+        metrics.spilled_bytes.value(),
+    );
+    metric(
+        "comet_native_spill_count_total",
+        "counter",
+        "Number of spills of native operators.",
+        metrics.spill_count.value(),
+    );
+    metric(
+        "comet_native_shuffle_written_bytes_total",
+        "counter",
+        "Number of bytes written to shuffle data files by native shuffle.",
+        metrics.shuffle_written_bytes.value(),
+    );
+    out
+}
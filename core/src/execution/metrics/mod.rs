@@ -15,4 +15,6 @@
 // specific language governing permissions and limitations
 // under the License.
 
+#[cfg(feature = "prometheus")]
+pub mod exporter;
 pub mod utils;
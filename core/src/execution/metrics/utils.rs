@@ -17,11 +17,39 @@
 
 use crate::{
     errors::CometError,
+    execution::{operators::unwrap_debug_operators, spill::SpillManager},
     jvm_bridge::{jni_call, jni_new_string},
 };
 use datafusion::physical_plan::ExecutionPlan;
 use jni::{objects::JObject, JNIEnv};
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
+
+/// Metrics of the data crossing the JVM/native boundary of a native plan, i.e., the batches
+/// imported from JVM input sources, the batches exported to JVM, the calls from native into JVM
+/// to pull input batches, and the time spent on them. They are reported on the root metric node
+/// of the plan.
+#[derive(Debug, Default)]
+pub struct JniMetrics {
+    pub import_batches: usize,
+    pub import_bytes: usize,
+    pub export_batches: usize,
+    pub export_bytes: usize,
+    pub callbacks: usize,
+    pub time: Duration,
+}
+
+impl JniMetrics {
+    fn values(&self) -> [(&'static str, i64); 6] {
+        [
+            ("jni_import_batches", self.import_batches as i64),
+            ("jni_import_bytes", self.import_bytes as i64),
+            ("jni_export_batches", self.export_batches as i64),
+            ("jni_export_bytes", self.export_bytes as i64),
+            ("jni_callbacks", self.callbacks as i64),
+            ("jni_time", self.time.as_nanos() as i64),
+        ]
+    }
+}
 
 /// Updates the metrics of a CometMetricNode. This function is called recursively to
 /// update the metrics of all the children nodes. The metrics are pulled from the
@@ -31,6 +59,9 @@ pub fn update_comet_metric(
     metric_node: &JObject,
     execution_plan: &Arc<dyn ExecutionPlan>,
 ) -> Result<(), CometError> {
+    // Debugging operators have no Spark counterpart, so the metric node belongs to their input.
+    let execution_plan = unwrap_debug_operators(execution_plan);
+
     update_metrics(
         env,
         metric_node,
@@ -57,6 +88,28 @@ pub fn update_comet_metric(
     Ok(())
 }
 
+/// Updates the JVM/native boundary metrics of the plan on its root CometMetricNode.
+pub fn update_jni_metrics(
+    env: &mut JNIEnv,
+    metric_node: &JObject,
+    jni_metrics: &JniMetrics,
+) -> Result<(), CometError> {
+    update_metrics(env, metric_node, &jni_metrics.values())
+}
+
+/// Updates the disk usage of the spill files of the plan on its root CometMetricNode.
+pub fn update_spill_metrics(
+    env: &mut JNIEnv,
+    metric_node: &JObject,
+    spill_manager: &SpillManager,
+) -> Result<(), CometError> {
+    update_metrics(
+        env,
+        metric_node,
+        &[("spill_disk_used", spill_manager.peak() as i64)],
+    )
+}
+
 #[inline]
 fn update_metrics(
     env: &mut JNIEnv,
@@ -16,16 +16,24 @@
 // under the License.
 
 //! PoC of vectorization execution through JNI to Rust.
+#[cfg(feature = "alloc_tracking")]
+pub mod alloc_tracker;
+pub mod broadcast;
+pub mod config;
 pub mod datafusion;
+pub mod datasource;
 pub mod jni_api;
 
 pub mod kernels; // for benchmarking
 
 mod metrics;
+pub mod offload;
 pub mod operators;
+pub mod runtime;
 pub mod serde;
 pub mod shuffle;
 pub(crate) mod sort;
+pub mod spill;
 mod timezone;
 pub(crate) mod utils;
 
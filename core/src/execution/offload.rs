@@ -0,0 +1,208 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Hooks for offloading native operators to alternative implementations, e.g., backed by GPU
+//! kernels.
+//!
+//! Comet itself only runs on CPU. An external crate linking Comet registers an
+//! [`OperatorProvider`] with [`register_operator_provider`] when it is loaded, and a query
+//! selects it by name with `spark.comet.exec.offload.provider`. The planner then asks the
+//! provider for an alternative of each native operator it creates, optionally only of the
+//! operators listed in `spark.comet.exec.offload.operators`, and keeps the CPU operator if the
+//! provider has none.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+};
+
+use datafusion::physical_plan::ExecutionPlan;
+use datafusion_common::DataFusionError;
+use log::{info, warn};
+use once_cell::sync::Lazy;
+
+use crate::execution::{config::NativeConfig, operators::operator_name};
+
+/// The operator providers registered in this process, by name
+static OPERATOR_PROVIDERS: Lazy<RwLock<HashMap<String, Arc<dyn OperatorProvider>>>> =
+    Lazy::new(|| RwLock::new(HashMap::new()));
+
+/// Provides alternative implementations of native operators.
+pub trait OperatorProvider: Send + Sync {
+    /// The name of the provider, which queries select it by.
+    fn name(&self) -> &str;
+
+    /// Returns the alternative of the given native operator, whose children are already
+    /// planned, or `None` to keep the operator. The alternative must produce the same output as
+    /// the operator, with the same schema.
+    fn offload(
+        &self,
+        plan: &Arc<dyn ExecutionPlan>,
+    ) -> Result<Option<Arc<dyn ExecutionPlan>>, DataFusionError>;
+}
+
+/// Registers the given operator provider, replacing the one of the same name if any.
+pub fn register_operator_provider(provider: Arc<dyn OperatorProvider>) {
+    info!("Registered Comet operator provider '{}'", provider.name());
+    OPERATOR_PROVIDERS
+        .write()
+        .unwrap()
+        .insert(provider.name().to_string(), provider);
+}
+
+/// The operator provider selected by a native plan, and the operators to offload to it.
+#[derive(Clone)]
+pub struct Offload {
+    provider: Arc<dyn OperatorProvider>,
+    /// The names of the operators to offload, e.g., `ProjectionExec`. All the operators are
+    /// offered to the provider if this is empty.
+    operators: Vec<String>,
+}
+
+impl Offload {
+    pub fn new(provider: Arc<dyn OperatorProvider>, operators: Vec<String>) -> Self {
+        Self {
+            provider,
+            operators,
+        }
+    }
+
+    /// Returns the offload selected by the given configs of a native plan, if any. If the
+    /// selected provider is not registered, the operators are not offloaded.
+    pub fn of_config(conf: &NativeConfig) -> Option<Self> {
+        let name = conf.offload_provider.as_ref()?;
+        match OPERATOR_PROVIDERS.read().unwrap().get(name) {
+            Some(provider) => Some(Self::new(
+                Arc::clone(provider),
+                conf.offload_operators.clone(),
+            )),
+            None => {
+                warn!(
+                    "Comet operator provider '{}' is not registered, native operators are not \
+                    offloaded",
+                    name
+                );
+                None
+            }
+        }
+    }
+
+    /// Returns the alternative of the given native operator from the provider, or the operator
+    /// itself if it is not to be offloaded.
+    pub fn offload(
+        &self,
+        plan: Arc<dyn ExecutionPlan>,
+    ) -> Result<Arc<dyn ExecutionPlan>, DataFusionError> {
+        if !self.operators.is_empty() && !self.operators.contains(&operator_name(plan.as_ref())) {
+            return Ok(plan);
+        }
+        match self.provider.offload(&plan)? {
+            Some(offloaded) if offloaded.schema() != plan.schema() => {
+                Err(DataFusionError::Internal(format!(
+                    "Operator provider '{}' changed the schema of {} from {:?} to {:?}",
+                    self.provider.name(),
+                    operator_name(plan.as_ref()),
+                    plan.schema(),
+                    offloaded.schema()
+                )))
+            }
+            Some(offloaded) => Ok(offloaded),
+            None => Ok(plan),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow_array::{Int32Array, RecordBatch};
+    use arrow_schema::{DataType, Field, Schema};
+    use datafusion::{
+        logical_expr::Operator,
+        physical_plan::{
+            coalesce_batches::CoalesceBatchesExec, filter::FilterExec, memory::MemoryExec,
+        },
+    };
+    use datafusion_physical_expr::expressions::{binary, lit, Column};
+
+    /// Wraps the filters in `CoalesceBatchesExec`, to tell them apart.
+    struct TestProvider;
+
+    impl OperatorProvider for TestProvider {
+        fn name(&self) -> &str {
+            "test"
+        }
+
+        fn offload(
+            &self,
+            plan: &Arc<dyn ExecutionPlan>,
+        ) -> Result<Option<Arc<dyn ExecutionPlan>>, DataFusionError> {
+            Ok(plan
+                .as_any()
+                .is::<FilterExec>()
+                .then(|| Arc::new(CoalesceBatchesExec::new(plan.clone(), 1)) as _))
+        }
+    }
+
+    #[test]
+    fn test_offload() {
+        register_operator_provider(Arc::new(TestProvider));
+        let mut conf = NativeConfig {
+            offload_provider: Some("test".to_string()),
+            ..NativeConfig::default()
+        };
+
+        let schema = Arc::new(Schema::new(vec![Field::new("a", DataType::Int32, false)]));
+        let batch = RecordBatch::try_new(
+            schema.clone(),
+            vec![Arc::new(Int32Array::from(vec![1, 2, 3]))],
+        )
+        .unwrap();
+        let scan: Arc<dyn ExecutionPlan> =
+            Arc::new(MemoryExec::try_new(&[vec![batch]], schema, None).unwrap());
+        let filter: Arc<dyn ExecutionPlan> = Arc::new(
+            FilterExec::try_new(
+                binary(
+                    Arc::new(Column::new("a", 0)),
+                    Operator::Gt,
+                    lit(1),
+                    &scan.schema(),
+                )
+                .unwrap(),
+                scan.clone(),
+            )
+            .unwrap(),
+        );
+
+        let offload = Offload::of_config(&conf).unwrap();
+        assert!(offload
+            .offload(filter.clone())
+            .unwrap()
+            .as_any()
+            .is::<CoalesceBatchesExec>());
+        // The provider keeps the operators it doesn't offload
+        assert!(offload.offload(scan).unwrap().as_any().is::<MemoryExec>());
+
+        // Only the listed operators are offloaded
+        conf.offload_operators = vec!["ProjectionExec".to_string()];
+        let offload = Offload::of_config(&conf).unwrap();
+        assert!(offload.offload(filter).unwrap().as_any().is::<FilterExec>());
+
+        conf.offload_provider = Some("unknown".to_string());
+        assert!(Offload::of_config(&conf).is_none());
+    }
+}
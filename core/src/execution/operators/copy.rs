@@ -30,7 +30,7 @@ use arrow_schema::{DataType, Field, FieldRef, Schema, SchemaRef};
 use datafusion::{execution::TaskContext, physical_expr::*, physical_plan::*};
 use datafusion_common::{arrow_datafusion_err, DataFusionError, Result as DataFusionResult};
 
-use super::copy_or_cast_array;
+use super::{copy_array, copy_or_cast_array};
 
 /// An utility execution node which makes deep copies of input batches.
 ///
@@ -41,17 +41,30 @@ use super::copy_or_cast_array;
 pub struct CopyExec {
     input: Arc<dyn ExecutionPlan>,
     schema: SchemaRef,
+    /// Whether the dictionary arrays are unpacked into arrays of their values
+    unpack_dictionaries: bool,
     cache: PlanProperties,
 }
 
 impl CopyExec {
+    /// Creates the copy of the given input, whose dictionary arrays are unpacked.
     pub fn new(input: Arc<dyn ExecutionPlan>) -> Self {
+        Self::create(input, true)
+    }
+
+    /// Creates the copy of the given input which keeps its dictionary arrays, for consumers which
+    /// don't sort them, e.g., the sort-based shuffle writer.
+    pub fn keeping_dictionaries(input: Arc<dyn ExecutionPlan>) -> Self {
+        Self::create(input, false)
+    }
+
+    fn create(input: Arc<dyn ExecutionPlan>, unpack_dictionaries: bool) -> Self {
         let fields: Vec<Field> = input
             .schema()
             .fields
             .iter()
             .map(|f: &FieldRef| match f.data_type() {
-                DataType::Dictionary(_, value_type) => {
+                DataType::Dictionary(_, value_type) if unpack_dictionaries => {
                     Field::new(f.name(), value_type.as_ref().clone(), f.is_nullable())
                 }
                 _ => f.as_ref().clone(),
@@ -69,9 +82,15 @@ impl CopyExec {
         Self {
             input,
             schema,
+            unpack_dictionaries,
             cache,
         }
     }
+
+    /// The operator whose output is copied by this node.
+    pub fn input(&self) -> &Arc<dyn ExecutionPlan> {
+        &self.input
+    }
 }
 
 impl DisplayAs for CopyExec {
@@ -106,6 +125,7 @@ impl ExecutionPlan for CopyExec {
         Ok(Arc::new(CopyExec {
             input: new_input,
             schema: self.schema.clone(),
+            unpack_dictionaries: self.unpack_dictionaries,
             cache: self.cache.clone(),
         }))
     }
@@ -116,7 +136,11 @@ impl ExecutionPlan for CopyExec {
         context: Arc<TaskContext>,
     ) -> DataFusionResult<SendableRecordBatchStream> {
         let child_stream = self.input.execute(partition, context)?;
-        Ok(Box::pin(CopyStream::new(self.schema(), child_stream)))
+        Ok(Box::pin(CopyStream::new(
+            self.schema(),
+            child_stream,
+            self.unpack_dictionaries,
+        )))
     }
 
     fn statistics(&self) -> DataFusionResult<Statistics> {
@@ -131,13 +155,19 @@ impl ExecutionPlan for CopyExec {
 struct CopyStream {
     schema: SchemaRef,
     child_stream: SendableRecordBatchStream,
+    unpack_dictionaries: bool,
 }
 
 impl CopyStream {
-    fn new(schema: SchemaRef, child_stream: SendableRecordBatchStream) -> Self {
+    fn new(
+        schema: SchemaRef,
+        child_stream: SendableRecordBatchStream,
+        unpack_dictionaries: bool,
+    ) -> Self {
         Self {
             schema,
             child_stream,
+            unpack_dictionaries,
         }
     }
 
@@ -147,7 +177,13 @@ impl CopyStream {
         let vectors = batch
             .columns()
             .iter()
-            .map(|v| copy_or_cast_array(v))
+            .map(|v| {
+                if self.unpack_dictionaries {
+                    copy_or_cast_array(v)
+                } else {
+                    Ok(copy_array(v))
+                }
+            })
             .collect::<Result<Vec<ArrayRef>, _>>()?;
 
         let options = RecordBatchOptions::new().with_row_count(Some(batch.num_rows()));
@@ -0,0 +1,215 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use std::{
+    any::Any,
+    fs::File,
+    io::{BufWriter, Write},
+    path::PathBuf,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+};
+
+use futures::{Stream, StreamExt};
+
+use arrow::ipc::writer::StreamWriter;
+use arrow_array::RecordBatch;
+use arrow_schema::SchemaRef;
+
+use datafusion::{execution::TaskContext, physical_plan::*};
+use datafusion_common::{DataFusionError, Result as DataFusionResult};
+
+/// Sequence number of the taps created in this process, to give each tap a distinct file.
+static NEXT_TAP_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// Where to mirror the output batches of native operators to.
+#[derive(Debug, Clone)]
+pub struct DebugTap {
+    /// Name of the native operators to tap, e.g., `SortExec`.
+    pub operator: String,
+    /// The directory to write the Arrow IPC files to.
+    pub dir: PathBuf,
+}
+
+/// A debugging execution node which mirrors the output batches of its input operator to an
+/// Arrow IPC stream file, so intermediate results of a native plan can be inspected without
+/// modifying the query.
+///
+/// This node doesn't modify input batches. The file is named
+/// `plan_<plan id>_<operator>_<tap id>_<partition>.arrow` and is flushed after every batch, so
+/// it is readable even if the plan is not executed to the end.
+#[derive(Debug)]
+pub struct DebugTapExec {
+    input: Arc<dyn ExecutionPlan>,
+    exec_context_id: i64,
+    operator: String,
+    dir: PathBuf,
+    tap_id: usize,
+}
+
+impl DebugTapExec {
+    pub fn new(
+        input: Arc<dyn ExecutionPlan>,
+        exec_context_id: i64,
+        operator: String,
+        dir: PathBuf,
+    ) -> Self {
+        Self {
+            input,
+            exec_context_id,
+            operator,
+            dir,
+            tap_id: NEXT_TAP_ID.fetch_add(1, Ordering::Relaxed),
+        }
+    }
+
+    /// The operator whose output is mirrored by this node.
+    pub fn input(&self) -> &Arc<dyn ExecutionPlan> {
+        &self.input
+    }
+
+    fn file_path(&self, partition: usize) -> PathBuf {
+        self.dir.join(format!(
+            "plan_{}_{}_{}_{}.arrow",
+            self.exec_context_id, self.operator, self.tap_id, partition
+        ))
+    }
+}
+
+/// Returns the name of the given native operator, e.g., `SortExec`.
+pub(crate) fn operator_name(plan: &dyn ExecutionPlan) -> String {
+    let description = displayable(plan).one_line().to_string();
+    description
+        .split(|c: char| c == ':' || c.is_whitespace())
+        .next()
+        .unwrap_or_default()
+        .to_string()
+}
+
+impl DisplayAs for DebugTapExec {
+    fn fmt_as(&self, t: DisplayFormatType, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match t {
+            DisplayFormatType::Default | DisplayFormatType::Verbose => {
+                write!(f, "DebugTapExec: dir={}", self.dir.display())
+            }
+        }
+    }
+}
+
+impl ExecutionPlan for DebugTapExec {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn schema(&self) -> SchemaRef {
+        self.input.schema()
+    }
+
+    fn children(&self) -> Vec<Arc<dyn ExecutionPlan>> {
+        vec![self.input.clone()]
+    }
+
+    fn with_new_children(
+        self: Arc<Self>,
+        children: Vec<Arc<dyn ExecutionPlan>>,
+    ) -> DataFusionResult<Arc<dyn ExecutionPlan>> {
+        Ok(Arc::new(DebugTapExec::new(
+            children[0].clone(),
+            self.exec_context_id,
+            self.operator.clone(),
+            self.dir.clone(),
+        )))
+    }
+
+    fn execute(
+        &self,
+        partition: usize,
+        context: Arc<TaskContext>,
+    ) -> DataFusionResult<SendableRecordBatchStream> {
+        let child_stream = self.input.execute(partition, context)?;
+
+        let path = self.file_path(partition);
+        let file = File::create(&path).map_err(|e| {
+            DataFusionError::Execution(format!(
+                "Cannot create debug tap file {}: {}",
+                path.display(),
+                e
+            ))
+        })?;
+        let writer = StreamWriter::try_new(BufWriter::new(file), &self.schema())?;
+
+        Ok(Box::pin(DebugTapStream {
+            schema: self.schema(),
+            child_stream,
+            writer: Some(writer),
+        }))
+    }
+
+    fn statistics(&self) -> DataFusionResult<Statistics> {
+        self.input.statistics()
+    }
+
+    fn properties(&self) -> &PlanProperties {
+        self.input.properties()
+    }
+}
+
+struct DebugTapStream {
+    schema: SchemaRef,
+    child_stream: SendableRecordBatchStream,
+    /// The writer of the tap file. It is taken when the file is finished.
+    writer: Option<StreamWriter<BufWriter<File>>>,
+}
+
+impl DebugTapStream {
+    fn tap(&mut self, batch: &RecordBatch) -> DataFusionResult<()> {
+        if let Some(writer) = self.writer.as_mut() {
+            writer.write(batch)?;
+            writer.get_mut().flush()?;
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self) -> DataFusionResult<()> {
+        if let Some(mut writer) = self.writer.take() {
+            writer.finish()?;
+        }
+        Ok(())
+    }
+}
+
+impl Stream for DebugTapStream {
+    type Item = DataFusionResult<RecordBatch>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.child_stream.poll_next_unpin(cx).map(|x| match x {
+            Some(Ok(batch)) => Some(self.tap(&batch).map(|_| batch)),
+            None => self.finish().err().map(Err),
+            other => other,
+        })
+    }
+}
+
+impl RecordBatchStream for DebugTapStream {
+    fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+}
@@ -25,6 +25,7 @@ use arrow::{
 
 use arrow::compute::{cast_with_options, CastOptions};
 use arrow_schema::ArrowError;
+use datafusion::physical_plan::ExecutionPlan;
 use std::{fmt::Debug, sync::Arc};
 
 mod scan;
@@ -33,6 +34,18 @@ pub use scan::*;
 mod copy;
 pub use copy::*;
 
+mod validation;
+pub use validation::*;
+
+mod debug_tap;
+pub use debug_tap::*;
+
+mod split;
+pub use split::*;
+
+mod shared_scan;
+pub use shared_scan::*;
+
 /// Error returned during executing operators.
 #[derive(thiserror::Error, Debug)]
 pub enum ExecutionError {
@@ -54,6 +67,30 @@ pub enum ExecutionError {
     DataFusionError(String),
 }
 
+/// Returns the operator wrapped by debugging operators, e.g., `ValidationExec`, which are
+/// inserted on top of native operators and have no Spark counterpart.
+pub(crate) fn unwrap_debug_operators(mut op: &Arc<dyn ExecutionPlan>) -> &Arc<dyn ExecutionPlan> {
+    loop {
+        if let Some(validation) = op.as_any().downcast_ref::<ValidationExec>() {
+            op = validation.input();
+            continue;
+        }
+        if let Some(tap) = op.as_any().downcast_ref::<DebugTapExec>() {
+            op = tap.input();
+            continue;
+        }
+        #[cfg(feature = "alloc_tracking")]
+        if let Some(tracking) = op
+            .as_any()
+            .downcast_ref::<crate::execution::alloc_tracker::AllocTrackingExec>()
+        {
+            op = tracking.input();
+            continue;
+        }
+        return op;
+    }
+}
+
 /// Copy an Arrow Array
 pub fn copy_array(array: &dyn Array) -> ArrayRef {
     let capacity = array.len();
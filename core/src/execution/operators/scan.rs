@@ -20,6 +20,7 @@ use std::{
     pin::Pin,
     sync::{Arc, Mutex},
     task::{Context, Poll},
+    time::Instant,
 };
 
 use futures::Stream;
@@ -33,8 +34,8 @@ use arrow_schema::{DataType, Field, Schema, SchemaRef};
 use crate::{
     errors::CometError,
     execution::{
-        datafusion::planner::TEST_EXEC_CONTEXT_ID, operators::ExecutionError,
-        utils::SparkArrowConvert,
+        datafusion::planner::TEST_EXEC_CONTEXT_ID, metrics::utils::JniMetrics,
+        operators::ExecutionError, utils::SparkArrowConvert,
     },
     jvm_bridge::{jni_call, JVMClasses},
 };
@@ -119,8 +120,9 @@ impl ScanExec {
         *self.batch.try_lock().unwrap() = Some(input);
     }
 
-    /// Pull next input batch from JVM.
-    pub fn get_next_batch(&mut self) -> Result<(), CometError> {
+    /// Pull next input batch from JVM. The JNI call and the imported batch are recorded in
+    /// `jni_metrics`.
+    pub fn get_next_batch(&mut self, jni_metrics: &mut JniMetrics) -> Result<(), CometError> {
         let mut current_batch = self.batch.try_lock().unwrap();
 
         if self.input_source.is_none() {
@@ -129,16 +131,36 @@ impl ScanExec {
         }
 
         if current_batch.is_none() {
+            let start = Instant::now();
             let next_batch = ScanExec::get_next(
                 self.exec_context_id,
                 self.input_source.as_ref().unwrap().as_obj(),
             )?;
+            jni_metrics.time += start.elapsed();
+            record_import(&next_batch, jni_metrics);
+
+            #[cfg(feature = "prometheus")]
+            if let InputBatch::Batch(_, num_rows) = &next_batch {
+                use crate::execution::metrics::exporter::EXECUTOR_METRICS;
+                EXECUTOR_METRICS.scan_input_batches.add(1);
+                EXECUTOR_METRICS.scan_input_rows.add(*num_rows);
+            }
             *current_batch = Some(next_batch);
         }
 
         Ok(())
     }
 
+    /// Records the JNI call and the input batch pulled from JVM when this scan was created.
+    pub fn record_first_batch(&self, jni_metrics: &mut JniMetrics) {
+        if self.input_source.is_none() {
+            return;
+        }
+        if let Some(batch) = self.batch.try_lock().unwrap().as_ref() {
+            record_import(batch, jni_metrics);
+        }
+    }
+
     /// Invokes JNI call to get next batch.
     fn get_next(exec_context_id: i64, iter: &JObject) -> Result<InputBatch, CometError> {
         if exec_context_id == TEST_EXEC_CONTEXT_ID {
@@ -207,6 +229,17 @@ impl ScanExec {
     }
 }
 
+fn record_import(input_batch: &InputBatch, jni_metrics: &mut JniMetrics) {
+    jni_metrics.callbacks += 1;
+    if let InputBatch::Batch(columns, _) = input_batch {
+        jni_metrics.import_batches += 1;
+        jni_metrics.import_bytes += columns
+            .iter()
+            .map(|c| c.get_array_memory_size())
+            .sum::<usize>();
+    }
+}
+
 fn scan_schema(input_batch: &InputBatch, data_types: &[DataType]) -> SchemaRef {
     let fields = match input_batch {
         // Note that if `columns` is empty, we'll get an empty schema
@@ -0,0 +1,303 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use std::{
+    any::Any,
+    collections::VecDeque,
+    fmt::Debug,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+};
+
+use futures::{Stream, StreamExt};
+
+use arrow_array::RecordBatch;
+use arrow_schema::SchemaRef;
+use datafusion::{
+    execution::TaskContext,
+    physical_expr::EquivalenceProperties,
+    physical_plan::{
+        DisplayAs, DisplayFormatType, ExecutionMode, ExecutionPlan, Partitioning, PlanProperties,
+        RecordBatchStream, SendableRecordBatchStream,
+    },
+};
+use datafusion_common::{DataFusionError, Result as DataFusionResult};
+
+/// The batches of an input source read by several scans of a plan, e.g., the shuffle of an
+/// exchange reused by both sides of a self-join. The input is only read once, and each of its
+/// batches is kept until all the scans have read it.
+pub struct SharedScanSource {
+    /// The input, whose batches must not be reused by it, e.g., the copy of a `ScanExec`
+    input: Arc<dyn ExecutionPlan>,
+    state: Mutex<SharedScanState>,
+}
+
+struct SharedScanState {
+    /// The stream of the input, which is created by the first scan polling it
+    stream: Option<SendableRecordBatchStream>,
+    /// The batches not read by all the scans yet, the first of which is the batch at `offset`
+    buffered: VecDeque<RecordBatch>,
+    offset: usize,
+    /// The position of the next batch of each scan
+    positions: Vec<usize>,
+    done: bool,
+}
+
+impl SharedScanSource {
+    pub fn new(input: Arc<dyn ExecutionPlan>) -> Arc<Self> {
+        Arc::new(Self {
+            input,
+            state: Mutex::new(SharedScanState {
+                stream: None,
+                buffered: VecDeque::new(),
+                offset: 0,
+                positions: vec![],
+                done: false,
+            }),
+        })
+    }
+
+    pub fn schema(&self) -> SchemaRef {
+        self.input.schema()
+    }
+
+    /// Registers a scan of this source, which reads its batches from the first one.
+    fn add_scan(&self) -> usize {
+        let mut state = self.state.lock().unwrap();
+        state.positions.push(0);
+        state.positions.len() - 1
+    }
+
+    fn poll_next(
+        &self,
+        scan: usize,
+        cx: &mut Context<'_>,
+        context: &Arc<TaskContext>,
+    ) -> Poll<Option<DataFusionResult<RecordBatch>>> {
+        let mut state = self.state.lock().unwrap();
+        let state = &mut *state;
+
+        let position = state.positions[scan];
+        if position < state.offset + state.buffered.len() {
+            let batch = state.buffered[position - state.offset].clone();
+            state.advance(scan);
+            return Poll::Ready(Some(Ok(batch)));
+        }
+        if state.done {
+            return Poll::Ready(None);
+        }
+
+        if state.stream.is_none() {
+            match self.input.execute(0, context.clone()) {
+                Ok(stream) => state.stream = Some(stream),
+                Err(e) => return Poll::Ready(Some(Err(e))),
+            }
+        }
+        match state.stream.as_mut().unwrap().poll_next_unpin(cx) {
+            Poll::Ready(Some(Ok(batch))) => {
+                state.buffered.push_back(batch.clone());
+                state.advance(scan);
+                Poll::Ready(Some(Ok(batch)))
+            }
+            Poll::Ready(None) => {
+                state.done = true;
+                state.stream = None;
+                Poll::Ready(None)
+            }
+            other => other,
+        }
+    }
+}
+
+impl SharedScanState {
+    /// Moves the given scan to its next batch, and drops the batches read by all the scans.
+    fn advance(&mut self, scan: usize) {
+        self.positions[scan] += 1;
+        let min_position = self.positions.iter().min().copied().unwrap_or_default();
+        while self.offset < min_position && self.buffered.pop_front().is_some() {
+            self.offset += 1;
+        }
+    }
+}
+
+/// A scan of a [`SharedScanSource`], which reads all the batches of the source whatever the
+/// other scans of the source read.
+pub struct SharedScanExec {
+    source: Arc<SharedScanSource>,
+    /// The index of this scan in the source
+    scan: usize,
+    cache: PlanProperties,
+}
+
+impl SharedScanExec {
+    pub fn new(source: Arc<SharedScanSource>) -> Self {
+        let scan = source.add_scan();
+        let cache = PlanProperties::new(
+            EquivalenceProperties::new(source.schema()),
+            Partitioning::UnknownPartitioning(1),
+            ExecutionMode::Bounded,
+        );
+        Self {
+            source,
+            scan,
+            cache,
+        }
+    }
+}
+
+impl Debug for SharedScanExec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SharedScanExec")
+            .field("scan", &self.scan)
+            .finish()
+    }
+}
+
+impl DisplayAs for SharedScanExec {
+    fn fmt_as(&self, t: DisplayFormatType, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match t {
+            DisplayFormatType::Default | DisplayFormatType::Verbose => {
+                write!(f, "SharedScanExec: scan={}", self.scan)
+            }
+        }
+    }
+}
+
+impl ExecutionPlan for SharedScanExec {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn schema(&self) -> SchemaRef {
+        self.source.schema()
+    }
+
+    fn children(&self) -> Vec<Arc<dyn ExecutionPlan>> {
+        vec![]
+    }
+
+    fn with_new_children(
+        self: Arc<Self>,
+        _: Vec<Arc<dyn ExecutionPlan>>,
+    ) -> DataFusionResult<Arc<dyn ExecutionPlan>> {
+        Ok(self)
+    }
+
+    fn execute(
+        &self,
+        partition: usize,
+        context: Arc<TaskContext>,
+    ) -> DataFusionResult<SendableRecordBatchStream> {
+        if partition != 0 {
+            return Err(DataFusionError::Internal(format!(
+                "SharedScanExec has a single partition, but got {}",
+                partition
+            )));
+        }
+        Ok(Box::pin(SharedScanStream {
+            source: self.source.clone(),
+            scan: self.scan,
+            context,
+        }))
+    }
+
+    fn properties(&self) -> &PlanProperties {
+        &self.cache
+    }
+}
+
+struct SharedScanStream {
+    source: Arc<SharedScanSource>,
+    scan: usize,
+    context: Arc<TaskContext>,
+}
+
+impl Stream for SharedScanStream {
+    type Item = DataFusionResult<RecordBatch>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.source.poll_next(self.scan, cx, &self.context)
+    }
+}
+
+impl RecordBatchStream for SharedScanStream {
+    fn schema(&self) -> SchemaRef {
+        self.source.schema()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow_array::{cast::AsArray, types::Int32Type, Int32Array};
+    use arrow_schema::{DataType, Field, Schema};
+    use datafusion::physical_plan::memory::MemoryExec;
+    use futures::{executor::block_on, StreamExt};
+
+    fn values(batch: &RecordBatch) -> Vec<i32> {
+        batch
+            .column(0)
+            .as_primitive::<Int32Type>()
+            .values()
+            .to_vec()
+    }
+
+    #[test]
+    fn test_shared_scans() {
+        let schema = Arc::new(Schema::new(vec![Field::new("a", DataType::Int32, false)]));
+        let batches = (0..3)
+            .map(|i| {
+                RecordBatch::try_new(
+                    schema.clone(),
+                    vec![Arc::new(Int32Array::from(vec![i * 2, i * 2 + 1]))],
+                )
+                .unwrap()
+            })
+            .collect::<Vec<_>>();
+        let input = Arc::new(MemoryExec::try_new(&[batches], schema, None).unwrap());
+        let source = SharedScanSource::new(input);
+        let context = Arc::new(TaskContext::default());
+        let mut left = SharedScanExec::new(source.clone())
+            .execute(0, context.clone())
+            .unwrap();
+        let mut right = SharedScanExec::new(source.clone())
+            .execute(0, context)
+            .unwrap();
+
+        // The scans read all the batches at their own pace
+        let first = block_on(left.next()).unwrap().unwrap();
+        assert_eq!(values(&first), vec![0, 1]);
+        let second = block_on(left.next()).unwrap().unwrap();
+        assert_eq!(values(&second), vec![2, 3]);
+        assert_eq!(source.state.lock().unwrap().buffered.len(), 2);
+
+        let right_batches = block_on(right.by_ref().collect::<Vec<_>>());
+        let right_values = right_batches
+            .iter()
+            .flat_map(|b| values(b.as_ref().unwrap()))
+            .collect::<Vec<_>>();
+        assert_eq!(right_values, (0..6).collect::<Vec<_>>());
+        // The batches read by both scans are dropped
+        assert_eq!(source.state.lock().unwrap().buffered.len(), 1);
+
+        let third = block_on(left.next()).unwrap().unwrap();
+        assert_eq!(values(&third), vec![4, 5]);
+        assert!(block_on(left.next()).is_none());
+        assert!(source.state.lock().unwrap().buffered.is_empty());
+    }
+}
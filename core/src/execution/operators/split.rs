@@ -0,0 +1,189 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use std::{
+    collections::VecDeque,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures::{Stream, StreamExt};
+
+use arrow_array::RecordBatch;
+use arrow_schema::SchemaRef;
+
+use datafusion::physical_plan::{RecordBatchStream, SendableRecordBatchStream};
+use datafusion_common::Result as DataFusionResult;
+
+use super::copy_array;
+
+/// A stream which slices the batches of its input stream with more than `max_batch_size` rows
+/// into batches of at most `max_batch_size` rows.
+///
+/// Some operators, e.g., joins, can produce batches much larger than the configured batch size.
+/// Exporting such batches to JVM at once may cause OOM there. The slices are compacted, i.e.,
+/// copied into arrays without offsets, as JVM imports the exported arrays from their start.
+/// Batches within the limit are passed through with no overhead.
+pub struct BatchSplitStream {
+    input: SendableRecordBatchStream,
+    max_batch_size: usize,
+    /// Slices of the last input batch which are not returned yet
+    pending: VecDeque<RecordBatch>,
+}
+
+impl BatchSplitStream {
+    pub fn new(input: SendableRecordBatchStream, max_batch_size: usize) -> Self {
+        assert!(max_batch_size > 0, "max_batch_size must be positive");
+        Self {
+            input,
+            max_batch_size,
+            pending: VecDeque::new(),
+        }
+    }
+
+    fn split(&mut self, batch: RecordBatch) -> DataFusionResult<RecordBatch> {
+        let num_rows = batch.num_rows();
+        if num_rows <= self.max_batch_size {
+            return Ok(batch);
+        }
+
+        let mut offset = self.max_batch_size;
+        while offset < num_rows {
+            let length = self.max_batch_size.min(num_rows - offset);
+            self.pending
+                .push_back(compact(&batch.slice(offset, length))?);
+            offset += length;
+        }
+        compact(&batch.slice(0, self.max_batch_size))
+    }
+}
+
+impl Stream for BatchSplitStream {
+    type Item = DataFusionResult<RecordBatch>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        if let Some(batch) = self.pending.pop_front() {
+            return Poll::Ready(Some(Ok(batch)));
+        }
+
+        self.input.poll_next_unpin(cx).map(|x| match x {
+            Some(Ok(batch)) => Some(self.split(batch)),
+            other => other,
+        })
+    }
+}
+
+impl RecordBatchStream for BatchSplitStream {
+    fn schema(&self) -> SchemaRef {
+        self.input.schema()
+    }
+}
+
+/// Copies the arrays of the given slice of a batch into arrays starting at offset 0.
+fn compact(batch: &RecordBatch) -> DataFusionResult<RecordBatch> {
+    let columns = batch
+        .columns()
+        .iter()
+        .map(|array| copy_array(array.as_ref()))
+        .collect();
+    Ok(RecordBatch::try_new(batch.schema(), columns)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use arrow_array::{Array, Decimal128Array, Int32Array, RecordBatch, StringArray};
+    use arrow_schema::{DataType, Field, Schema};
+    use datafusion::physical_plan::stream::RecordBatchStreamAdapter;
+    use futures::{executor::block_on, StreamExt};
+
+    use super::BatchSplitStream;
+
+    #[test]
+    fn test_split_oversized_batches() {
+        let schema = Arc::new(Schema::new(vec![Field::new("a", DataType::Int32, false)]));
+        let batch = |n: i32| {
+            RecordBatch::try_new(
+                schema.clone(),
+                vec![Arc::new(Int32Array::from_iter_values(0..n))],
+            )
+            .unwrap()
+        };
+
+        let input = futures::stream::iter(vec![Ok(batch(10)), Ok(batch(3)), Ok(batch(4))]);
+        let input = Box::pin(RecordBatchStreamAdapter::new(schema.clone(), input));
+        let stream = BatchSplitStream::new(input, 4);
+        let batches = block_on(stream.collect::<Vec<_>>())
+            .into_iter()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+
+        let sizes = batches.iter().map(|b| b.num_rows()).collect::<Vec<_>>();
+        assert_eq!(sizes, vec![4, 4, 2, 3, 4]);
+
+        // The rows are in the original order
+        let values = batches[..3]
+            .iter()
+            .flat_map(|b| {
+                let array = b.column(0).as_any().downcast_ref::<Int32Array>().unwrap();
+                array.values().to_vec()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(values, (0..10).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_compact_split_batches() {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("s", DataType::Utf8, true),
+            Field::new("d", DataType::Decimal128(20, 2), true),
+        ]));
+        let strings = StringArray::from_iter((0..10).map(|i| (i % 3 != 0).then(|| "x".repeat(i))));
+        let decimals = Decimal128Array::from_iter((0..10).map(|i| (i % 4 != 0).then_some(i * 100)))
+            .with_precision_and_scale(20, 2)
+            .unwrap();
+        let batch = RecordBatch::try_new(
+            schema.clone(),
+            vec![Arc::new(strings.clone()), Arc::new(decimals.clone())],
+        )
+        .unwrap();
+
+        let input = futures::stream::iter(vec![Ok(batch)]);
+        let input = Box::pin(RecordBatchStreamAdapter::new(schema, input));
+        let batches = block_on(BatchSplitStream::new(input, 4).collect::<Vec<_>>())
+            .into_iter()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(batches.len(), 3);
+
+        let mut offset = 0;
+        for batch in batches {
+            // The slices are exported from the start of their buffers
+            for column in batch.columns() {
+                assert_eq!(column.nulls().map_or(0, |nulls| nulls.offset()), 0);
+            }
+            let s = batch.column(0).as_any().downcast_ref::<StringArray>();
+            assert_eq!(s.unwrap().value_offsets()[0], 0);
+            let length = batch.num_rows();
+            assert_eq!(batch.column(0).as_ref(), &strings.slice(offset, length));
+            assert_eq!(batch.column(1).as_ref(), &decimals.slice(offset, length));
+            offset += length;
+        }
+        assert_eq!(offset, 10);
+    }
+}
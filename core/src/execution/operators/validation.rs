@@ -0,0 +1,280 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use std::{
+    any::Any,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use futures::{Stream, StreamExt};
+
+use arrow_array::{Array, RecordBatch};
+use arrow_schema::SchemaRef;
+
+use datafusion::{execution::TaskContext, physical_plan::*};
+use datafusion_common::{DataFusionError, Result as DataFusionResult};
+
+/// A debugging execution node which validates Arrow invariants of every batch produced by its
+/// input operator, e.g., offsets monotonicity, null count consistency, UTF-8 validity and schema
+/// match.
+///
+/// This node doesn't modify input batches. When a violation is found, it fails the query with
+/// an error naming the operator which produced the invalid batch. It is only inserted into the
+/// native plan when batch validation is enabled in the configs.
+#[derive(Debug)]
+pub struct ValidationExec {
+    input: Arc<dyn ExecutionPlan>,
+    /// One-line description of the input operator, used in error messages.
+    producer: String,
+}
+
+impl ValidationExec {
+    pub fn new(input: Arc<dyn ExecutionPlan>) -> Self {
+        let producer = displayable(input.as_ref()).one_line().to_string();
+        Self { input, producer }
+    }
+
+    /// The operator whose output is validated by this node.
+    pub fn input(&self) -> &Arc<dyn ExecutionPlan> {
+        &self.input
+    }
+}
+
+impl DisplayAs for ValidationExec {
+    fn fmt_as(&self, t: DisplayFormatType, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match t {
+            DisplayFormatType::Default | DisplayFormatType::Verbose => {
+                write!(f, "ValidationExec")
+            }
+        }
+    }
+}
+
+impl ExecutionPlan for ValidationExec {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn schema(&self) -> SchemaRef {
+        self.input.schema()
+    }
+
+    fn children(&self) -> Vec<Arc<dyn ExecutionPlan>> {
+        vec![self.input.clone()]
+    }
+
+    fn with_new_children(
+        self: Arc<Self>,
+        children: Vec<Arc<dyn ExecutionPlan>>,
+    ) -> DataFusionResult<Arc<dyn ExecutionPlan>> {
+        Ok(Arc::new(ValidationExec::new(children[0].clone())))
+    }
+
+    fn execute(
+        &self,
+        partition: usize,
+        context: Arc<TaskContext>,
+    ) -> DataFusionResult<SendableRecordBatchStream> {
+        let child_stream = self.input.execute(partition, context)?;
+        Ok(Box::pin(ValidationStream {
+            schema: self.schema(),
+            producer: self.producer.clone(),
+            child_stream,
+            batch_index: 0,
+        }))
+    }
+
+    fn statistics(&self) -> DataFusionResult<Statistics> {
+        self.input.statistics()
+    }
+
+    fn properties(&self) -> &PlanProperties {
+        self.input.properties()
+    }
+}
+
+struct ValidationStream {
+    schema: SchemaRef,
+    producer: String,
+    child_stream: SendableRecordBatchStream,
+    /// Number of batches seen so far, reported on violations.
+    batch_index: usize,
+}
+
+impl ValidationStream {
+    fn validate(&mut self, batch: RecordBatch) -> DataFusionResult<RecordBatch> {
+        let batch_index = self.batch_index;
+        self.batch_index += 1;
+
+        validate_batch(&batch, &self.schema).map_err(|reason| {
+            DataFusionError::Execution(format!(
+                "Invalid batch #{} produced by operator '{}': {}",
+                batch_index, self.producer, reason
+            ))
+        })?;
+        Ok(batch)
+    }
+}
+
+impl Stream for ValidationStream {
+    type Item = DataFusionResult<RecordBatch>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.child_stream.poll_next_unpin(cx).map(|x| match x {
+            Some(Ok(batch)) => Some(self.validate(batch)),
+            other => other,
+        })
+    }
+}
+
+impl RecordBatchStream for ValidationStream {
+    fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+}
+
+/// Checks the given batch against the expected schema and the Arrow format invariants.
+/// Returns the reason of the first violation found.
+pub(crate) fn validate_batch(batch: &RecordBatch, schema: &SchemaRef) -> Result<(), String> {
+    if batch.num_columns() != schema.fields().len() {
+        return Err(format!(
+            "expected {} columns but got {}",
+            schema.fields().len(),
+            batch.num_columns()
+        ));
+    }
+
+    for (i, (column, field)) in batch.columns().iter().zip(schema.fields()).enumerate() {
+        if column.data_type() != field.data_type() {
+            return Err(format!(
+                "column {} ('{}') has data type {} but schema expects {}",
+                i,
+                field.name(),
+                column.data_type(),
+                field.data_type()
+            ));
+        }
+
+        if column.len() != batch.num_rows() {
+            return Err(format!(
+                "column {} ('{}') has {} rows but batch has {} rows",
+                i,
+                field.name(),
+                column.len(),
+                batch.num_rows()
+            ));
+        }
+
+        // `null_count` is cached when the null buffer is created, so recount it from the
+        // validity bits to catch inconsistent arrays built from raw buffers.
+        let actual_null_count = column
+            .nulls()
+            .map(|nulls| nulls.len() - nulls.inner().count_set_bits())
+            .unwrap_or(0);
+        if column.null_count() != actual_null_count {
+            return Err(format!(
+                "column {} ('{}') reports {} nulls but validity buffer has {}",
+                i,
+                field.name(),
+                column.null_count(),
+                actual_null_count
+            ));
+        }
+
+        // Validates buffer sizes, offsets monotonicity, UTF-8 validity and dictionary keys,
+        // recursively for nested types.
+        column
+            .to_data()
+            .validate_full()
+            .map_err(|e| format!("column {} ('{}'): {}", i, field.name(), e))?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use arrow::buffer::{Buffer, NullBuffer};
+    use arrow_array::{ArrayRef, Int32Array, RecordBatch, StringArray};
+    use arrow_data::ArrayData;
+    use arrow_schema::{DataType, Field, Schema};
+
+    use super::validate_batch;
+
+    #[test]
+    fn test_validate_batch() {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("a", DataType::Int32, true),
+            Field::new("b", DataType::Utf8, true),
+        ]));
+        let a: ArrayRef = Arc::new(Int32Array::from(vec![Some(1), None, Some(3)]));
+        let b: ArrayRef = Arc::new(StringArray::from(vec!["x", "y", "z"]));
+        let batch = RecordBatch::try_new(schema.clone(), vec![a.clone(), b]).unwrap();
+        assert!(validate_batch(&batch, &schema).is_ok());
+
+        // Schema mismatch
+        let other_schema = Arc::new(Schema::new(vec![
+            Field::new("a", DataType::Int64, true),
+            Field::new("b", DataType::Utf8, true),
+        ]));
+        assert!(validate_batch(&batch, &other_schema).is_err());
+
+        // Non-monotonic offsets
+        let data = unsafe {
+            ArrayData::builder(DataType::Utf8)
+                .len(3)
+                .add_buffer(Buffer::from_slice_ref([0i32, 2, 1, 3]))
+                .add_buffer(Buffer::from_slice_ref(b"abc"))
+                .build_unchecked()
+        };
+        let b: ArrayRef = Arc::new(StringArray::from(data));
+        let batch = RecordBatch::try_new(schema.clone(), vec![a.clone(), b]).unwrap();
+        assert!(validate_batch(&batch, &schema).is_err());
+
+        // Invalid UTF-8
+        let data = unsafe {
+            ArrayData::builder(DataType::Utf8)
+                .len(3)
+                .add_buffer(Buffer::from_slice_ref([0i32, 1, 2, 3]))
+                .add_buffer(Buffer::from_slice_ref([b'a', 0xff, b'c']))
+                .build_unchecked()
+        };
+        let b: ArrayRef = Arc::new(StringArray::from(data));
+        let batch = RecordBatch::try_new(schema.clone(), vec![a, b]).unwrap();
+        assert!(validate_batch(&batch, &schema).is_err());
+
+        // Inconsistent null count
+        let data = unsafe {
+            ArrayData::builder(DataType::Int32)
+                .len(3)
+                .add_buffer(Buffer::from_slice_ref([1i32, 2, 3]))
+                .nulls(Some(NullBuffer::new_unchecked(
+                    NullBuffer::from(vec![true, false, true]).into_inner(),
+                    0,
+                )))
+                .build_unchecked()
+        };
+        let a: ArrayRef = Arc::new(Int32Array::from(data));
+        let b: ArrayRef = Arc::new(StringArray::from(vec!["x", "y", "z"]));
+        let batch = RecordBatch::try_new(schema.clone(), vec![a, b]).unwrap();
+        assert!(validate_batch(&batch, &schema).is_err());
+    }
+}
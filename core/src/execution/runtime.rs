@@ -0,0 +1,215 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! The executor-wide Tokio runtimes used by native plans.
+//!
+//! All the native plans of an executor share the same compute thread pool, so that many
+//! concurrent tasks don't oversubscribe the CPU cores with per-plan thread pools. The disk IO of
+//! native operators, i.e., the spills and local map output files of shuffle writers and the
+//! reads of native file scans, runs on a separate IO runtime with independent sizing, so that
+//! long decode loops don't starve IO and vice versa. Shuffle block sinks calling back into the
+//! JVM are written from the thread of the Spark task instead.
+//!
+//! The runtimes are created by the first native plan of the executor, using its configs. As the
+//! configs are executor-level Spark configs, they are the same for all the native plans of an
+//! executor.
+//!
+//! The native plans of a query with a resource profile share its [`ResourceLimits`], which cap
+//! the IO requests and threads of all the plans of the profile within the shared runtimes.
+
+use std::{
+    cell::Cell,
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use log::{info, warn};
+use once_cell::sync::{Lazy, OnceCell};
+use tokio::{
+    runtime::{Handle, Runtime},
+    sync::{OwnedSemaphorePermit, Semaphore},
+    task::JoinHandle,
+};
+
+use crate::{
+    errors::CometResult,
+    execution::config::{NativeConfig, ResourceProfile},
+};
+
+static COMPUTE_RUNTIME: OnceCell<Runtime> = OnceCell::new();
+
+static IO_RUNTIME: OnceCell<Runtime> = OnceCell::new();
+
+/// Returns the executor-wide compute runtime, creating it with the given configs if this is the
+/// first native plan of the executor.
+pub fn compute_runtime(conf: &NativeConfig) -> CometResult<&'static Runtime> {
+    COMPUTE_RUNTIME
+        .get_or_try_init(|| create_compute_runtime(conf.compute_threads, conf.pin_compute_threads))
+}
+
+fn create_compute_runtime(num_threads: Option<usize>, pin_threads: bool) -> CometResult<Runtime> {
+    let mut builder = tokio::runtime::Builder::new_multi_thread();
+    builder.thread_name("comet-compute");
+    if let Some(num_threads) = num_threads {
+        builder.worker_threads(num_threads);
+    }
+
+    if pin_threads {
+        match core_affinity::get_core_ids().filter(|core_ids| !core_ids.is_empty()) {
+            Some(core_ids) => {
+                // Pins the worker threads to cores in a round-robin way. Blocking threads are
+                // pinned too, but they are only used by blocking IO of operators.
+                let next_core = AtomicUsize::new(0);
+                builder.on_thread_start(move || {
+                    let core_id =
+                        core_ids[next_core.fetch_add(1, Ordering::Relaxed) % core_ids.len()];
+                    if !core_affinity::set_for_current(core_id) {
+                        warn!("Failed to pin Comet compute thread to core {}", core_id.id);
+                    }
+                });
+            }
+            None => warn!("Cannot get CPU core ids, Comet compute threads are not pinned"),
+        }
+    }
+
+    let runtime = builder.enable_all().build()?;
+    info!(
+        "Created Comet compute runtime with {} worker threads{}",
+        num_threads.map_or("default number of".to_string(), |n| n.to_string()),
+        if pin_threads { " pinned to cores" } else { "" }
+    );
+    Ok(runtime)
+}
+
+/// Returns the executor-wide IO runtime, creating it with the given configs if this is the first
+/// native plan of the executor.
+pub fn io_runtime(conf: &NativeConfig) -> CometResult<&'static Runtime> {
+    IO_RUNTIME.get_or_try_init(|| {
+        let mut builder = tokio::runtime::Builder::new_multi_thread();
+        builder.thread_name("comet-io");
+        if let Some(io_parallelism) = conf.io_parallelism {
+            // Async IO runs on the worker threads, and blocking disk IO on the blocking threads
+            builder
+                .worker_threads(io_parallelism)
+                .max_blocking_threads(io_parallelism);
+        }
+        let runtime = builder.enable_all().build()?;
+        info!(
+            "Created Comet IO runtime with {} threads",
+            conf.io_parallelism
+                .map_or("default number of".to_string(), |n| n.to_string())
+        );
+        Ok(runtime)
+    })
+}
+
+/// Returns a handle to the IO runtime. If it is not created, e.g., when native operators are
+/// executed outside of Spark, returns the handle to the current runtime.
+///
+/// # Panics
+///
+/// Panics if the IO runtime is not created and this is not called in a Tokio runtime.
+pub fn io_handle() -> Handle {
+    IO_RUNTIME
+        .get()
+        .map(|runtime| runtime.handle().clone())
+        .unwrap_or_else(Handle::current)
+}
+
+/// Runs the blocking IO function `f` on the IO runtime.
+pub fn spawn_blocking_io<F, R>(f: F) -> JoinHandle<R>
+where
+    F: FnOnce() -> R + Send + 'static,
+    R: Send + 'static,
+{
+    io_handle().spawn_blocking(f)
+}
+
+/// The limits of the resource profiles used by the native plans of the executor, by name
+static RESOURCE_LIMITS: Lazy<Mutex<HashMap<String, Arc<ResourceLimits>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+thread_local! {
+    /// The number of native plans executing on the current thread, which holds a decode thread
+    /// permit if it is positive. Native plans may execute the native plans of their inputs on
+    /// the same thread, which don't acquire another permit.
+    static EXECUTING_PLANS: Cell<usize> = const { Cell::new(0) };
+}
+
+/// The caps on the resources used at the same time by all the native plans of a resource
+/// profile on the executor. The default limits don't cap anything.
+#[derive(Debug, Default)]
+pub struct ResourceLimits {
+    io_requests: Option<Arc<Semaphore>>,
+    decode_threads: Option<Arc<Semaphore>>,
+}
+
+impl ResourceLimits {
+    /// Returns the limits of the given profile, creating them with its caps if this is the first
+    /// native plan of the profile on the executor. Later plans of the profile share the limits
+    /// even if they set different caps.
+    pub fn of_profile(profile: &ResourceProfile) -> Arc<Self> {
+        let mut limits = RESOURCE_LIMITS.lock().unwrap();
+        Arc::clone(limits.entry(profile.name.clone()).or_insert_with(|| {
+            info!(
+                "Created Comet resource profile '{}' with max IO requests {:?} and decode \
+                threads {:?}",
+                profile.name, profile.max_io_requests, profile.decode_threads
+            );
+            Arc::new(Self {
+                io_requests: profile.max_io_requests.map(|n| Arc::new(Semaphore::new(n))),
+                decode_threads: profile.decode_threads.map(|n| Arc::new(Semaphore::new(n))),
+            })
+        }))
+    }
+
+    /// Waits until an IO request can be issued. The request is in flight until the returned
+    /// permit is dropped.
+    pub async fn acquire_io(&self) -> Option<OwnedSemaphorePermit> {
+        match &self.io_requests {
+            Some(semaphore) => Arc::clone(semaphore).acquire_owned().await.ok(),
+            None => None,
+        }
+    }
+
+    /// Blocks the current thread until it can execute a native plan, which it does until the
+    /// returned guard is dropped. A thread already executing a native plan doesn't wait.
+    pub fn acquire_decode_thread(&self) -> DecodeThreadGuard {
+        let permit = match &self.decode_threads {
+            Some(semaphore) if EXECUTING_PLANS.with(Cell::get) == 0 => {
+                futures::executor::block_on(Arc::clone(semaphore).acquire_owned()).ok()
+            }
+            _ => None,
+        };
+        EXECUTING_PLANS.with(|n| n.set(n.get() + 1));
+        DecodeThreadGuard { _permit: permit }
+    }
+}
+
+/// Returned by [`ResourceLimits::acquire_decode_thread`] while the thread executes a native plan.
+pub struct DecodeThreadGuard {
+    _permit: Option<OwnedSemaphorePermit>,
+}
+
+impl Drop for DecodeThreadGuard {
+    fn drop(&mut self) {
+        EXECUTING_PLANS.with(|n| n.set(n.get() - 1));
+    }
+}
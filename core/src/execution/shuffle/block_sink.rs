@@ -0,0 +1,104 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! The shuffle block sink pushing the output partitions of native shuffle writers back to the
+//! JVM, e.g., to the client of a remote shuffle service such as Celeborn or Uniffle, which
+//! forwards them to the service instead of writing local shuffle files.
+
+use std::{io, sync::Arc};
+
+use datafusion::error::Result;
+use jni::objects::{GlobalRef, JObject};
+
+use crate::{
+    errors::CometResult,
+    execution::datafusion::shuffle_writer::ShuffleBlockSink,
+    jvm_bridge::{jni_call, JVMClasses},
+};
+
+/// The maximum size of a block pushed to the JVM. The bytes of a larger partition are pushed in
+/// several blocks.
+const MAX_BLOCK_SIZE: usize = 4 * 1024 * 1024;
+
+/// A [`ShuffleBlockSink`] delegating to the JVM side `CometShuffleBlockSink`. The bytes of the
+/// current partition are buffered and pushed as a block when the partition ends, or when the
+/// buffer reaches [`MAX_BLOCK_SIZE`]. The blocks of a partition must be concatenated in order to
+/// be read, as they may split an Arrow IPC stream.
+pub struct JvmShuffleBlockSink {
+    handle: Arc<GlobalRef>,
+    partition_id: Option<usize>,
+    buffer: Vec<u8>,
+}
+
+impl JvmShuffleBlockSink {
+    pub fn new(handle: Arc<GlobalRef>) -> Self {
+        Self {
+            handle,
+            partition_id: None,
+            buffer: vec![],
+        }
+    }
+
+    /// Pushes the buffered bytes of the current partition to the JVM.
+    fn push_block(&mut self) -> CometResult<()> {
+        let partition_id = match self.partition_id {
+            Some(partition_id) if !self.buffer.is_empty() => partition_id,
+            _ => return Ok(()),
+        };
+        let mut env = JVMClasses::get_env();
+        let block = env.byte_array_from_slice(&self.buffer)?;
+        let handle = self.handle.as_obj();
+        let result = unsafe {
+            jni_call!(&mut env,
+              comet_shuffle_block_sink(handle).write_block(partition_id as i32, &block) -> ())
+        };
+        // The thread may stay attached to the JVM, so the local reference is deleted here
+        env.delete_local_ref(JObject::from(block))?;
+        result?;
+        self.buffer.clear();
+        Ok(())
+    }
+}
+
+impl io::Write for JvmShuffleBlockSink {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buffer.extend_from_slice(buf);
+        if self.buffer.len() >= MAX_BLOCK_SIZE {
+            self.push_block()
+                .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl ShuffleBlockSink for JvmShuffleBlockSink {
+    fn start_partition(&mut self, partition_id: usize) -> Result<()> {
+        self.push_block()?;
+        self.partition_id = Some(partition_id);
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        self.push_block()?;
+        self.partition_id = None;
+        Ok(())
+    }
+}
@@ -0,0 +1,135 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+/// The smallest size class of pooled buffers, i.e., 64 KiB. Smaller buffers are not pooled.
+const MIN_SIZE_CLASS: u32 = 16;
+
+/// The largest size class of pooled buffers, i.e., 1 GiB. Larger buffers are pooled as this class.
+const MAX_SIZE_CLASS: u32 = 30;
+
+/// A pool of byte buffers reused across batches and partitions by the shuffle writer, instead of
+/// allocating and dropping large encoding buffers for each of them.
+///
+/// Buffers are pooled by size classes, where class `i` holds the buffers with capacity in
+/// `[2^i, 2^(i+1))`. The pool holds at most `max_pooled_bytes` bytes, and the caller is
+/// responsible for accounting `pooled_bytes` in the memory pool.
+pub(crate) struct BufferPool {
+    classes: Vec<Vec<Vec<u8>>>,
+    pooled_bytes: usize,
+    max_pooled_bytes: usize,
+}
+
+impl BufferPool {
+    pub(crate) fn new(max_pooled_bytes: usize) -> Self {
+        Self {
+            classes: vec![vec![]; (MAX_SIZE_CLASS - MIN_SIZE_CLASS + 1) as usize],
+            pooled_bytes: 0,
+            max_pooled_bytes,
+        }
+    }
+
+    /// Takes an empty buffer with at least `min_capacity` bytes of capacity, which is reused from
+    /// the pool if possible. Otherwise, a buffer of exactly `min_capacity` bytes is allocated, so
+    /// taking a buffer of 0 bytes doesn't allocate.
+    pub(crate) fn take(&mut self, min_capacity: usize) -> Vec<u8> {
+        // The smallest class whose buffers all have enough capacity
+        let class = min_capacity
+            .max(1)
+            .next_power_of_two()
+            .trailing_zeros()
+            .clamp(MIN_SIZE_CLASS, MAX_SIZE_CLASS);
+
+        for index in (class - MIN_SIZE_CLASS) as usize..self.classes.len() {
+            if let Some(mut buffer) = self.classes[index].pop() {
+                self.pooled_bytes -= buffer.capacity();
+                // Buffers of the largest class may still be smaller than `min_capacity`
+                buffer.reserve(min_capacity);
+                return buffer;
+            }
+        }
+        Vec::with_capacity(min_capacity)
+    }
+
+    /// Gives the buffer back to the pool. The buffer is dropped if it is too small to be pooled,
+    /// or the pool is full.
+    pub(crate) fn give(&mut self, mut buffer: Vec<u8>) {
+        let capacity = buffer.capacity();
+        if capacity < 1 << MIN_SIZE_CLASS || self.pooled_bytes + capacity > self.max_pooled_bytes {
+            return;
+        }
+
+        buffer.clear();
+        let class = (usize::BITS - 1 - capacity.leading_zeros()).min(MAX_SIZE_CLASS);
+        self.classes[(class - MIN_SIZE_CLASS) as usize].push(buffer);
+        self.pooled_bytes += capacity;
+    }
+
+    /// Drops all the pooled buffers.
+    pub(crate) fn clear(&mut self) {
+        self.classes.iter_mut().for_each(Vec::clear);
+        self.pooled_bytes = 0;
+    }
+
+    /// The total capacity of the pooled buffers in bytes.
+    pub(crate) fn pooled_bytes(&self) -> usize {
+        self.pooled_bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BufferPool;
+
+    #[test]
+    fn test_buffer_pool() {
+        let mut pool = BufferPool::new(1 << 20);
+
+        // Buffers of the requested capacity are allocated if none is pooled
+        assert_eq!(pool.take(0).capacity(), 0);
+        let buffer = pool.take(100);
+        assert_eq!(buffer.capacity(), 100);
+        let mut large = pool.take(100_000);
+        assert_eq!(large.capacity(), 100_000);
+
+        // Small buffers are not pooled
+        large.extend_from_slice(&[1, 2, 3]);
+        pool.give(buffer);
+        pool.give(large);
+        assert_eq!(pool.pooled_bytes(), 100_000);
+
+        // Buffers are reused by size classes, i.e., a buffer of the class [2^16, 2^17) is only
+        // reused for at most 2^16 bytes
+        let buffer = pool.take(70_000);
+        assert_eq!(buffer.capacity(), 70_000);
+        assert_eq!(pool.pooled_bytes(), 100_000);
+        let buffer = pool.take(50_000);
+        assert_eq!(buffer.capacity(), 100_000);
+        assert!(buffer.is_empty());
+        assert_eq!(pool.pooled_bytes(), 0);
+
+        pool.give(buffer);
+        assert_eq!(pool.take(0).capacity(), 100_000);
+
+        // The pool is bounded
+        pool.give(Vec::with_capacity(1 << 19));
+        pool.give(Vec::with_capacity(1 << 20));
+        assert_eq!(pool.pooled_bytes(), 1 << 19);
+
+        pool.clear();
+        assert_eq!(pool.pooled_bytes(), 0);
+    }
+}
@@ -0,0 +1,223 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Coalesces the small batches decoded from the fetched blocks of native shuffle.
+
+use std::collections::VecDeque;
+
+use arrow::compute::concat_batches;
+use arrow_array::RecordBatch;
+
+use crate::{
+    errors::CometError,
+    execution::{
+        broadcast::decode_block, config::CompressionCodec,
+        shuffle::dictionary::consolidate_dictionaries,
+    },
+};
+
+/// Decodes the fetched blocks of a native shuffle, and coalesces their batches until they have
+/// `target_rows` rows or `target_bytes` bytes. A reducer usually fetches a small block from each
+/// map, whose batches would be too small for the operators consuming them otherwise.
+pub struct ShuffleBatchCoalescer {
+    codec: CompressionCodec,
+    target_rows: usize,
+    target_bytes: usize,
+    /// The batches to coalesce, which have the same schema
+    buffered: Vec<RecordBatch>,
+    buffered_rows: usize,
+    buffered_bytes: usize,
+    /// The coalesced batches which are not taken yet
+    coalesced: VecDeque<RecordBatch>,
+}
+
+impl ShuffleBatchCoalescer {
+    pub fn new(codec: CompressionCodec, target_rows: usize, target_bytes: usize) -> Self {
+        Self {
+            codec,
+            target_rows,
+            target_bytes,
+            buffered: vec![],
+            buffered_rows: 0,
+            buffered_bytes: 0,
+            coalesced: VecDeque::new(),
+        }
+    }
+
+    /// Decodes the given block, i.e., the compressed Arrow IPC stream without its length, and
+    /// buffers its batches. Returns the number of the coalesced batches ready to be taken.
+    pub fn push_block(&mut self, block: &[u8]) -> Result<usize, CometError> {
+        for batch in decode_block(block, &self.codec)? {
+            self.push_batch(batch)?;
+        }
+        Ok(self.coalesced.len())
+    }
+
+    /// Coalesces the buffered batches, as no more block is pushed. Returns the number of the
+    /// coalesced batches ready to be taken.
+    pub fn finish(&mut self) -> Result<usize, CometError> {
+        self.coalesce()?;
+        Ok(self.coalesced.len())
+    }
+
+    /// Takes the next coalesced batch, if any.
+    pub fn next_batch(&mut self) -> Option<RecordBatch> {
+        self.coalesced.pop_front()
+    }
+
+    fn push_batch(&mut self, batch: RecordBatch) -> Result<(), CometError> {
+        if batch.num_rows() == 0 {
+            return Ok(());
+        }
+        // The blocks of different maps may differ in schema, e.g., dictionary encoded or not,
+        // whose batches can't be concatenated.
+        if self
+            .buffered
+            .first()
+            .is_some_and(|buffered| buffered.schema() != batch.schema())
+        {
+            self.coalesce()?;
+        }
+
+        self.buffered_rows += batch.num_rows();
+        self.buffered_bytes += batch.get_array_memory_size();
+        self.buffered.push(batch);
+        if self.buffered_rows >= self.target_rows || self.buffered_bytes >= self.target_bytes {
+            self.coalesce()?;
+        }
+        Ok(())
+    }
+
+    fn coalesce(&mut self) -> Result<(), CometError> {
+        let batch = match self.buffered.len() {
+            0 => return Ok(()),
+            1 => self.buffered.pop().unwrap(),
+            _ => {
+                let batch = concat_batches(&self.buffered[0].schema(), &self.buffered)?;
+                // The blocks of different maps have their own dictionaries
+                let batch = consolidate_dictionaries(batch)?;
+                self.buffered.clear();
+                batch
+            }
+        };
+        self.buffered_rows = 0;
+        self.buffered_bytes = 0;
+        self.coalesced.push_back(batch);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::execution::datafusion::shuffle_writer::write_ipc_compressed;
+    use arrow_array::{
+        cast::AsArray, types::Int32Type, ArrayRef, DictionaryArray, Int32Array, StringArray,
+    };
+    use std::{io::Cursor, sync::Arc};
+
+    /// Encodes the given batch into a block without its length.
+    fn block(batch: &RecordBatch, codec: &CompressionCodec) -> Vec<u8> {
+        let mut cursor = Cursor::new(Vec::new());
+        write_ipc_compressed(batch, &mut cursor, codec).unwrap();
+        cursor.into_inner()[8..].to_vec()
+    }
+
+    fn batch(column: ArrayRef) -> RecordBatch {
+        RecordBatch::try_from_iter(vec![("a", column)]).unwrap()
+    }
+
+    fn num_rows(coalescer: &mut ShuffleBatchCoalescer) -> Vec<usize> {
+        std::iter::from_fn(|| coalescer.next_batch())
+            .map(|batch| batch.num_rows())
+            .collect()
+    }
+
+    #[test]
+    fn test_coalesce_rows() {
+        let codec = CompressionCodec::Lz4;
+        let mut coalescer = ShuffleBatchCoalescer::new(codec, 25, usize::MAX);
+
+        // The batches are coalesced until they have the target rows
+        let mut ready = 0;
+        for i in 0..5 {
+            let values = Int32Array::from_iter_values(i * 10..(i + 1) * 10);
+            ready = coalescer
+                .push_block(&block(&batch(Arc::new(values)), &codec))
+                .unwrap();
+        }
+        assert_eq!(ready, 1);
+        assert_eq!(coalescer.finish().unwrap(), 2);
+
+        let batches = std::iter::from_fn(|| coalescer.next_batch()).collect::<Vec<_>>();
+        assert_eq!(
+            batches.iter().map(|b| b.num_rows()).collect::<Vec<_>>(),
+            vec![30, 20]
+        );
+        let values = batches
+            .iter()
+            .flat_map(|b| b.column(0).as_primitive::<Int32Type>().values().to_vec())
+            .collect::<Vec<_>>();
+        assert_eq!(values, (0..50).collect::<Vec<_>>());
+        assert!(coalescer.next_batch().is_none());
+    }
+
+    #[test]
+    fn test_coalesce_bytes() {
+        let codec = CompressionCodec::Zstd(1);
+        let values = block(
+            &batch(Arc::new(Int32Array::from_iter_values(0..1000))),
+            &codec,
+        );
+        let decoded_bytes = decode_block(&values, &codec).unwrap()[0].get_array_memory_size();
+        let mut coalescer = ShuffleBatchCoalescer::new(codec, usize::MAX, decoded_bytes + 1);
+
+        // Every other batch reaches the target bytes
+        for _ in 0..4 {
+            coalescer.push_block(&values).unwrap();
+        }
+        assert_eq!(coalescer.finish().unwrap(), 2);
+        assert_eq!(num_rows(&mut coalescer), vec![2000, 2000]);
+    }
+
+    #[test]
+    fn test_coalesce_different_schemas() {
+        let codec = CompressionCodec::Snappy;
+        let mut coalescer = ShuffleBatchCoalescer::new(codec, 100, usize::MAX);
+
+        // The blocks of some maps are dictionary encoded, which can't be concatenated with the
+        // others
+        let plain = batch(Arc::new(StringArray::from(vec!["a", "b"])));
+        let dictionary = batch(Arc::new(
+            vec!["a", "a", "b"]
+                .into_iter()
+                .collect::<DictionaryArray<Int32Type>>(),
+        ));
+        for batch in [&plain, &plain, &dictionary, &dictionary, &plain] {
+            coalescer.push_block(&block(batch, &codec)).unwrap();
+        }
+        assert_eq!(coalescer.finish().unwrap(), 3);
+        let batches = std::iter::from_fn(|| coalescer.next_batch()).collect::<Vec<_>>();
+        assert_eq!(
+            batches.iter().map(|b| b.num_rows()).collect::<Vec<_>>(),
+            vec![4, 6, 2]
+        );
+        // The coalesced dictionary batch stays dictionary encoded, with a single dictionary
+        let coalesced = batches[1].column(0).as_dictionary::<Int32Type>();
+        assert_eq!(coalesced.values().len(), 2);
+    }
+}
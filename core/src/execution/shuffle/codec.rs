@@ -0,0 +1,521 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Compression of shuffle blocks, framed like the streams of Spark `CompressionCodec`s, so that
+//! the blocks written natively can be read by JVM reducers and vice versa.
+//!
+//! - LZ4 uses the block format of `LZ4BlockOutputStream` from lz4-java.
+//! - Snappy uses the chunk format of `SnappyOutputStream` from snappy-java.
+//! - Zstandard uses standard Zstandard frames, as `ZstdOutputStream` from zstd-jni.
+
+use std::{
+    hash::Hasher,
+    io::{self, Read, Write},
+};
+
+use twox_hash::XxHash32;
+
+use crate::execution::config::CompressionCodec;
+
+/// Spark's default of `spark.io.compression.lz4.blockSize` and
+/// `spark.io.compression.snappy.blockSize`
+const BLOCK_SIZE: usize = 32 * 1024;
+
+const LZ4_MAGIC: &[u8; 8] = b"LZ4Block";
+/// Magic, token, compressed length, original length and checksum
+const LZ4_HEADER_LENGTH: usize = LZ4_MAGIC.len() + 1 + 4 + 4 + 4;
+const LZ4_METHOD_RAW: u8 = 0x10;
+const LZ4_METHOD_LZ4: u8 = 0x20;
+const LZ4_COMPRESSION_LEVEL_BASE: u32 = 10;
+/// The seed of the XXHash32 checksums of lz4-java
+const LZ4_CHECKSUM_SEED: u32 = 0x9747b28c;
+
+const SNAPPY_MAGIC: &[u8; 8] = b"\x82SNAPPY\x00";
+/// Magic, version and minimum compatible version
+const SNAPPY_HEADER_LENGTH: usize = SNAPPY_MAGIC.len() + 4 + 4;
+const SNAPPY_VERSION: i32 = 1;
+
+/// A writer compressing the written bytes with a codec. [`CompressionWriter::finish`] must be
+/// called to complete the stream.
+pub(crate) enum CompressionWriter<W: Write> {
+    Lz4(Lz4BlockWriter<W>),
+    Snappy(SnappyWriter<W>),
+    Zstd(zstd::Encoder<'static, W>),
+}
+
+impl<W: Write> CompressionWriter<W> {
+    pub(crate) fn try_new(codec: &CompressionCodec, output: W) -> io::Result<Self> {
+        Ok(match codec {
+            CompressionCodec::Lz4 => CompressionWriter::Lz4(Lz4BlockWriter::new(output)),
+            CompressionCodec::Snappy => CompressionWriter::Snappy(SnappyWriter::try_new(output)?),
+            CompressionCodec::Zstd(level) => {
+                CompressionWriter::Zstd(zstd::Encoder::new(output, *level)?)
+            }
+        })
+    }
+
+    /// Completes the compressed stream and returns the underlying writer.
+    pub(crate) fn finish(self) -> io::Result<W> {
+        match self {
+            CompressionWriter::Lz4(writer) => writer.finish(),
+            CompressionWriter::Snappy(writer) => writer.finish(),
+            CompressionWriter::Zstd(encoder) => encoder.finish(),
+        }
+    }
+}
+
+impl<W: Write> Write for CompressionWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            CompressionWriter::Lz4(writer) => writer.write(buf),
+            CompressionWriter::Snappy(writer) => writer.write(buf),
+            CompressionWriter::Zstd(encoder) => encoder.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            CompressionWriter::Lz4(writer) => writer.flush(),
+            CompressionWriter::Snappy(writer) => writer.flush(),
+            CompressionWriter::Zstd(encoder) => encoder.flush(),
+        }
+    }
+}
+
+/// Returns a reader decompressing the stream written with the given codec, either natively or by
+/// the JVM.
+pub(crate) fn decompression_reader<'a, R: Read + 'a>(
+    codec: &CompressionCodec,
+    input: R,
+) -> io::Result<Box<dyn Read + 'a>> {
+    Ok(match codec {
+        CompressionCodec::Lz4 => Box::new(Lz4BlockReader::new(input)),
+        CompressionCodec::Snappy => Box::new(SnappyReader::new(input)),
+        CompressionCodec::Zstd(_) => Box::new(zstd::Decoder::new(input)?),
+    })
+}
+
+/// Buffers the written bytes into blocks, and writes each of them compressed with the block
+/// header of lz4-java.
+pub(crate) struct Lz4BlockWriter<W: Write> {
+    output: W,
+    buffer: Vec<u8>,
+    compressed: Vec<u8>,
+}
+
+impl<W: Write> Lz4BlockWriter<W> {
+    fn new(output: W) -> Self {
+        Self {
+            output,
+            buffer: Vec::with_capacity(BLOCK_SIZE),
+            compressed: vec![],
+        }
+    }
+
+    fn write_block(&mut self) -> io::Result<()> {
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+        let checksum = lz4_checksum(&self.buffer);
+
+        let bound = lz4::block::compress_bound(self.buffer.len())?;
+        self.compressed.resize(bound, 0);
+        let compressed_length =
+            lz4::block::compress_to_buffer(&self.buffer, None, false, &mut self.compressed)?;
+
+        // Like lz4-java, stores the block as it is if it is not compressible
+        if compressed_length >= self.buffer.len() {
+            write_lz4_header(
+                &mut self.output,
+                LZ4_METHOD_RAW,
+                self.buffer.len(),
+                self.buffer.len(),
+                checksum,
+            )?;
+            self.output.write_all(&self.buffer)?;
+        } else {
+            write_lz4_header(
+                &mut self.output,
+                LZ4_METHOD_LZ4,
+                compressed_length,
+                self.buffer.len(),
+                checksum,
+            )?;
+            self.output
+                .write_all(&self.compressed[..compressed_length])?;
+        }
+        self.buffer.clear();
+        Ok(())
+    }
+
+    fn finish(mut self) -> io::Result<W> {
+        self.write_block()?;
+        // An empty block marks the end of the stream
+        write_lz4_header(&mut self.output, LZ4_METHOD_RAW, 0, 0, 0)?;
+        Ok(self.output)
+    }
+}
+
+impl<W: Write> Write for Lz4BlockWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let length = buf.len().min(BLOCK_SIZE - self.buffer.len());
+        self.buffer.extend_from_slice(&buf[..length]);
+        if self.buffer.len() == BLOCK_SIZE {
+            self.write_block()?;
+        }
+        Ok(length)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.write_block()?;
+        self.output.flush()
+    }
+}
+
+fn lz4_checksum(data: &[u8]) -> u32 {
+    let mut hasher = XxHash32::with_seed(LZ4_CHECKSUM_SEED);
+    hasher.write(data);
+    // lz4-java only keeps the lower 28 bits of the hash as the checksum
+    hasher.finish() as u32 & 0x0FFFFFFF
+}
+
+fn write_lz4_header<W: Write>(
+    output: &mut W,
+    method: u8,
+    compressed_length: usize,
+    original_length: usize,
+    checksum: u32,
+) -> io::Result<()> {
+    // The compression level in the token is the log2 of the block size, as lz4-java validates
+    // the original length of the blocks with it
+    let level = (u32::BITS - (BLOCK_SIZE as u32 - 1).leading_zeros())
+        .saturating_sub(LZ4_COMPRESSION_LEVEL_BASE) as u8;
+    let mut header = [0u8; LZ4_HEADER_LENGTH];
+    header[..8].copy_from_slice(LZ4_MAGIC);
+    header[8] = method | level;
+    header[9..13].copy_from_slice(&(compressed_length as u32).to_le_bytes());
+    header[13..17].copy_from_slice(&(original_length as u32).to_le_bytes());
+    header[17..21].copy_from_slice(&checksum.to_le_bytes());
+    output.write_all(&header)
+}
+
+/// Reads the blocks written by [`Lz4BlockWriter`] or lz4-java, including the concatenated
+/// streams.
+struct Lz4BlockReader<R: Read> {
+    input: R,
+    buffer: Vec<u8>,
+    position: usize,
+    compressed: Vec<u8>,
+}
+
+impl<R: Read> Lz4BlockReader<R> {
+    fn new(input: R) -> Self {
+        Self {
+            input,
+            buffer: vec![],
+            position: 0,
+            compressed: vec![],
+        }
+    }
+
+    /// Reads the next non-empty block into the buffer. Returns false at the end of the input.
+    fn read_block(&mut self) -> io::Result<bool> {
+        loop {
+            let mut header = [0u8; LZ4_HEADER_LENGTH];
+            if !read_exact_or_eof(&mut self.input, &mut header)? {
+                return Ok(false);
+            }
+            if &header[..8] != LZ4_MAGIC {
+                return Err(corrupted("invalid LZ4 block magic"));
+            }
+            let method = header[8] & 0xF0;
+            let max_length = 1usize << (LZ4_COMPRESSION_LEVEL_BASE + (header[8] & 0x0F) as u32);
+            let compressed_length = u32::from_le_bytes(header[9..13].try_into().unwrap()) as usize;
+            let original_length = u32::from_le_bytes(header[13..17].try_into().unwrap()) as usize;
+            let checksum = u32::from_le_bytes(header[17..21].try_into().unwrap());
+            if original_length > max_length
+                || (method == LZ4_METHOD_RAW && compressed_length != original_length)
+                || (method != LZ4_METHOD_RAW && method != LZ4_METHOD_LZ4)
+            {
+                return Err(corrupted("invalid LZ4 block header"));
+            }
+            if original_length == 0 {
+                // The end of a stream, which may be followed by another stream
+                continue;
+            }
+
+            self.buffer.resize(original_length, 0);
+            if method == LZ4_METHOD_RAW {
+                self.input.read_exact(&mut self.buffer)?;
+            } else {
+                self.compressed.resize(compressed_length, 0);
+                self.input.read_exact(&mut self.compressed)?;
+                let length = lz4::block::decompress_to_buffer(
+                    &self.compressed,
+                    Some(original_length as i32),
+                    &mut self.buffer,
+                )?;
+                if length != original_length {
+                    return Err(corrupted("unexpected length of LZ4 block"));
+                }
+            }
+            if lz4_checksum(&self.buffer) != checksum {
+                return Err(corrupted("checksum mismatch of LZ4 block"));
+            }
+            self.position = 0;
+            return Ok(true);
+        }
+    }
+}
+
+impl<R: Read> Read for Lz4BlockReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.position == self.buffer.len() && !self.read_block()? {
+            return Ok(0);
+        }
+        let length = buf.len().min(self.buffer.len() - self.position);
+        buf[..length].copy_from_slice(&self.buffer[self.position..self.position + length]);
+        self.position += length;
+        Ok(length)
+    }
+}
+
+/// Buffers the written bytes into chunks, and writes each of them compressed with the length
+/// prefix of snappy-java.
+pub(crate) struct SnappyWriter<W: Write> {
+    output: W,
+    buffer: Vec<u8>,
+    compressed: Vec<u8>,
+    encoder: snap::raw::Encoder,
+}
+
+impl<W: Write> SnappyWriter<W> {
+    fn try_new(mut output: W) -> io::Result<Self> {
+        let mut header = [0u8; SNAPPY_HEADER_LENGTH];
+        header[..8].copy_from_slice(SNAPPY_MAGIC);
+        header[8..12].copy_from_slice(&SNAPPY_VERSION.to_be_bytes());
+        header[12..16].copy_from_slice(&SNAPPY_VERSION.to_be_bytes());
+        output.write_all(&header)?;
+        Ok(Self {
+            output,
+            buffer: Vec::with_capacity(BLOCK_SIZE),
+            compressed: vec![],
+            encoder: snap::raw::Encoder::new(),
+        })
+    }
+
+    fn write_chunk(&mut self) -> io::Result<()> {
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+        self.compressed
+            .resize(snap::raw::max_compress_len(self.buffer.len()), 0);
+        let length = self.encoder.compress(&self.buffer, &mut self.compressed)?;
+        self.output.write_all(&(length as i32).to_be_bytes())?;
+        self.output.write_all(&self.compressed[..length])?;
+        self.buffer.clear();
+        Ok(())
+    }
+
+    fn finish(mut self) -> io::Result<W> {
+        self.write_chunk()?;
+        Ok(self.output)
+    }
+}
+
+impl<W: Write> Write for SnappyWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let length = buf.len().min(BLOCK_SIZE - self.buffer.len());
+        self.buffer.extend_from_slice(&buf[..length]);
+        if self.buffer.len() == BLOCK_SIZE {
+            self.write_chunk()?;
+        }
+        Ok(length)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.write_chunk()?;
+        self.output.flush()
+    }
+}
+
+/// Reads the chunks written by [`SnappyWriter`] or snappy-java, including the concatenated
+/// streams.
+struct SnappyReader<R: Read> {
+    input: R,
+    buffer: Vec<u8>,
+    position: usize,
+    compressed: Vec<u8>,
+    decoder: snap::raw::Decoder,
+    header_read: bool,
+}
+
+impl<R: Read> SnappyReader<R> {
+    fn new(input: R) -> Self {
+        Self {
+            input,
+            buffer: vec![],
+            position: 0,
+            compressed: vec![],
+            decoder: snap::raw::Decoder::new(),
+            header_read: false,
+        }
+    }
+
+    /// Reads the rest of the stream header, given its first 4 bytes.
+    fn read_header(&mut self, prefix: [u8; 4]) -> io::Result<()> {
+        let mut header = [0u8; SNAPPY_HEADER_LENGTH];
+        header[..4].copy_from_slice(&prefix);
+        self.input.read_exact(&mut header[4..])?;
+        if &header[..8] != SNAPPY_MAGIC {
+            return Err(corrupted("invalid Snappy stream header"));
+        }
+        self.header_read = true;
+        Ok(())
+    }
+
+    /// Reads the next non-empty chunk into the buffer. Returns false at the end of the input.
+    fn read_chunk(&mut self) -> io::Result<bool> {
+        loop {
+            let mut length = [0u8; 4];
+            if !read_exact_or_eof(&mut self.input, &mut length)? {
+                return Ok(false);
+            }
+            if !self.header_read || length == SNAPPY_MAGIC[..4] {
+                // The header of the first or a concatenated stream
+                self.read_header(length)?;
+                continue;
+            }
+            let length = i32::from_be_bytes(length);
+            if length < 0 {
+                return Err(corrupted("invalid Snappy chunk length"));
+            }
+
+            self.compressed.resize(length as usize, 0);
+            self.input.read_exact(&mut self.compressed)?;
+            let original_length = snap::raw::decompress_len(&self.compressed)?;
+            self.buffer.resize(original_length, 0);
+            self.decoder
+                .decompress(&self.compressed, &mut self.buffer)?;
+            if original_length > 0 {
+                self.position = 0;
+                return Ok(true);
+            }
+        }
+    }
+}
+
+impl<R: Read> Read for SnappyReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.position == self.buffer.len() && !self.read_chunk()? {
+            return Ok(0);
+        }
+        let length = buf.len().min(self.buffer.len() - self.position);
+        buf[..length].copy_from_slice(&self.buffer[self.position..self.position + length]);
+        self.position += length;
+        Ok(length)
+    }
+}
+
+/// Fills the buffer from the input. Returns false if the input is already at the end.
+fn read_exact_or_eof<R: Read>(input: &mut R, buf: &mut [u8]) -> io::Result<bool> {
+    let mut read = 0;
+    while read < buf.len() {
+        match input.read(&mut buf[read..]) {
+            Ok(0) if read == 0 => return Ok(false),
+            Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+            Ok(n) => read += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(true)
+}
+
+fn corrupted(message: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("Stream is corrupted: {}", message),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+
+    use super::{decompression_reader, CompressionWriter};
+    use crate::execution::config::CompressionCodec;
+
+    fn round_trip(codec: CompressionCodec, data: &[u8]) -> Vec<u8> {
+        let mut writer = CompressionWriter::try_new(&codec, vec![]).unwrap();
+        writer.write_all(data).unwrap();
+        let mut compressed = writer.finish().unwrap();
+
+        // Concatenated streams are read as one, like the JVM readers
+        let mut writer = CompressionWriter::try_new(&codec, vec![]).unwrap();
+        writer.write_all(data).unwrap();
+        compressed.extend(writer.finish().unwrap());
+
+        let mut output = vec![];
+        decompression_reader(&codec, compressed.as_slice())
+            .unwrap()
+            .read_to_end(&mut output)
+            .unwrap();
+        output
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let data = (0..100000).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+        let expected = [data.as_slice(), data.as_slice()].concat();
+        for codec in [
+            CompressionCodec::Lz4,
+            CompressionCodec::Snappy,
+            CompressionCodec::Zstd(3),
+        ] {
+            assert_eq!(round_trip(codec, &data), expected, "{}", codec);
+            assert!(round_trip(codec, &[]).is_empty(), "{}", codec);
+        }
+    }
+
+    #[test]
+    fn test_lz4_block_format() {
+        // Incompressible data is stored raw, followed by the empty block ending the stream
+        let mut writer = CompressionWriter::try_new(&CompressionCodec::Lz4, vec![]).unwrap();
+        writer.write_all(b"abc").unwrap();
+        let output = writer.finish().unwrap();
+
+        assert_eq!(output.len(), 21 + 3 + 21);
+        assert_eq!(&output[..8], b"LZ4Block");
+        assert_eq!(output[8], 0x15);
+        assert_eq!(&output[9..17], &[3, 0, 0, 0, 3, 0, 0, 0]);
+        assert_eq!(&output[21..24], b"abc");
+        assert_eq!(&output[24..32], b"LZ4Block");
+        assert_eq!(&output[33..45], &[0; 12]);
+    }
+
+    #[test]
+    fn test_corrupted_lz4_block() {
+        let mut writer = CompressionWriter::try_new(&CompressionCodec::Lz4, vec![]).unwrap();
+        writer.write_all(b"abc").unwrap();
+        let mut output = writer.finish().unwrap();
+        output[21] = b'x';
+
+        let mut reader = decompression_reader(&CompressionCodec::Lz4, output.as_slice()).unwrap();
+        assert!(reader.read_to_end(&mut vec![]).is_err());
+    }
+}
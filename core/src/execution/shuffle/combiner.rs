@@ -0,0 +1,313 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Combines the rows of the same grouping keys written by a partial aggregation into a shuffle,
+//! like the map-side combiner of a Spark shuffle with an aggregator.
+//!
+//! A partial aggregation emits a single row per group only if it aggregates all its input in one
+//! hash table. When it aggregates batch by batch instead, e.g., when it skips aggregating high
+//! cardinality keys or spills, the same keys are emitted again and again, and all their rows are
+//! shuffled. The shuffle writer can merge the partial states of these rows before partitioning
+//! them, as the final aggregation would do after the shuffle.
+
+use std::sync::Arc;
+
+use ahash::RandomState;
+use arrow::{
+    compute::cast,
+    row::{RowConverter, Rows, SortField},
+};
+use arrow_array::RecordBatch;
+use arrow_schema::SchemaRef;
+use datafusion::{
+    logical_expr::{EmitTo, GroupsAccumulator},
+    physical_plan::{stream::RecordBatchStreamAdapter, SendableRecordBatchStream},
+};
+use datafusion_common::{DataFusionError, Result};
+use datafusion_physical_expr::{AggregateExpr, GroupsAccumulatorAdapter};
+use futures::{stream, StreamExt};
+use hashbrown::raw::RawTable;
+
+/// How to combine the rows written by a partial aggregation, whose columns are the grouping keys
+/// followed by the partial states of the aggregate expressions.
+#[derive(Debug, Clone)]
+pub struct ShuffleCombine {
+    /// The number of leading grouping key columns
+    pub num_grouping_keys: usize,
+    /// The aggregate expressions whose states are merged, in the order of their state columns
+    pub aggr_exprs: Vec<Arc<dyn AggregateExpr>>,
+    /// The maximum number of groups held before the combined rows are emitted
+    pub max_groups: usize,
+}
+
+/// A bounded hash table of grouping keys to the merged partial states of their rows.
+pub(crate) struct ShuffleCombiner {
+    schema: SchemaRef,
+    num_keys: usize,
+    max_groups: usize,
+    /// The number of state columns of each aggregate expression
+    num_states: Vec<usize>,
+    accumulators: Vec<Box<dyn GroupsAccumulator>>,
+    converter: RowConverter,
+    random_state: RandomState,
+    /// The hashes and indices of the groups, whose keys are in `group_keys`
+    map: RawTable<(u64, usize)>,
+    group_keys: Rows,
+    /// The group indices of the rows of the current batch
+    group_indices: Vec<usize>,
+}
+
+impl ShuffleCombiner {
+    pub(crate) fn try_new(schema: SchemaRef, combine: ShuffleCombine) -> Result<Self> {
+        let num_states = combine
+            .aggr_exprs
+            .iter()
+            .map(|expr| expr.state_fields().map(|fields| fields.len()))
+            .collect::<Result<Vec<_>>>()?;
+        if combine.num_grouping_keys + num_states.iter().sum::<usize>() != schema.fields().len() {
+            return Err(DataFusionError::Internal(format!(
+                "The schema of a partial aggregation with {} grouping keys and {} states is \
+                 expected to combine, but got {}",
+                combine.num_grouping_keys,
+                num_states.iter().sum::<usize>(),
+                schema
+            )));
+        }
+
+        let accumulators = combine
+            .aggr_exprs
+            .iter()
+            .map(create_accumulator)
+            .collect::<Result<Vec<_>>>()?;
+        let converter = RowConverter::new(
+            schema.fields()[..combine.num_grouping_keys]
+                .iter()
+                .map(|field| SortField::new(field.data_type().clone()))
+                .collect(),
+        )?;
+        let group_keys = converter.empty_rows(0, 0);
+
+        Ok(Self {
+            schema,
+            num_keys: combine.num_grouping_keys,
+            max_groups: combine.max_groups,
+            num_states,
+            accumulators,
+            converter,
+            random_state: RandomState::new(),
+            map: RawTable::new(),
+            group_keys,
+            group_indices: vec![],
+        })
+    }
+
+    /// Merges the rows of `batch` into their groups. Returns the combined rows of all the groups
+    /// if there are `max_groups` of them after merging, in which case the combiner starts over.
+    pub(crate) fn insert_batch(&mut self, batch: &RecordBatch) -> Result<Option<RecordBatch>> {
+        let keys = self
+            .converter
+            .convert_columns(&batch.columns()[..self.num_keys])?;
+
+        self.group_indices.clear();
+        for row in keys.iter() {
+            let hash = self.random_state.hash_one(row.as_ref());
+            let group_keys = &self.group_keys;
+            let group = match self.map.get(hash, |(h, group)| {
+                *h == hash && group_keys.row(*group) == row
+            }) {
+                Some((_, group)) => *group,
+                None => {
+                    let group = self.group_keys.num_rows();
+                    self.group_keys.push(row);
+                    self.map.insert(hash, (hash, group), |(h, _)| *h);
+                    group
+                }
+            };
+            self.group_indices.push(group);
+        }
+
+        let num_groups = self.group_keys.num_rows();
+        let mut offset = self.num_keys;
+        for (accumulator, num_states) in self.accumulators.iter_mut().zip(&self.num_states) {
+            accumulator.merge_batch(
+                &batch.columns()[offset..offset + num_states],
+                &self.group_indices,
+                None,
+                num_groups,
+            )?;
+            offset += num_states;
+        }
+
+        if num_groups >= self.max_groups {
+            self.emit()
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Returns the combined rows of all the groups, if any, and starts over.
+    pub(crate) fn emit(&mut self) -> Result<Option<RecordBatch>> {
+        if self.group_keys.num_rows() == 0 {
+            return Ok(None);
+        }
+
+        let mut columns = self.converter.convert_rows(&self.group_keys)?;
+        for accumulator in self.accumulators.iter_mut() {
+            columns.extend(accumulator.state(EmitTo::All)?);
+        }
+        // The row format decodes dictionary keys into their values
+        let columns = columns
+            .iter()
+            .zip(self.schema.fields())
+            .map(|(column, field)| {
+                if column.data_type() == field.data_type() {
+                    Ok(column.clone())
+                } else {
+                    cast(column, field.data_type())
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        self.map.clear();
+        self.group_keys = self.converter.empty_rows(0, 0);
+        Ok(Some(RecordBatch::try_new(self.schema.clone(), columns)?))
+    }
+}
+
+fn create_accumulator(expr: &Arc<dyn AggregateExpr>) -> Result<Box<dyn GroupsAccumulator>> {
+    if expr.groups_accumulator_supported() {
+        expr.create_groups_accumulator()
+    } else {
+        let expr = expr.clone();
+        Ok(Box::new(GroupsAccumulatorAdapter::new(move || {
+            expr.create_accumulator()
+        })))
+    }
+}
+
+/// Returns the rows of `input` combined by `combiner`. The combined rows are emitted whenever
+/// the combiner is full, and once the input ends.
+pub(crate) fn combine_stream(
+    input: SendableRecordBatchStream,
+    combiner: ShuffleCombiner,
+) -> SendableRecordBatchStream {
+    let schema = input.schema();
+    let combined = stream::unfold(
+        (input, Some(combiner)),
+        |(mut input, mut combiner)| async move {
+            let c = combiner.as_mut()?;
+            loop {
+                match input.next().await {
+                    Some(Ok(batch)) => match c.insert_batch(&batch) {
+                        Ok(Some(combined)) => return Some((Ok(combined), (input, combiner))),
+                        Ok(None) => continue,
+                        Err(e) => return Some((Err(e), (input, None))),
+                    },
+                    Some(Err(e)) => return Some((Err(e), (input, None))),
+                    None => return c.emit().transpose().map(|last| (last, (input, None))),
+                }
+            }
+        },
+    );
+    Box::pin(RecordBatchStreamAdapter::new(schema, combined))
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow_array::{cast::AsArray, types::Int64Type, Int64Array, StringArray};
+    use arrow_schema::{DataType, Field, Schema};
+    use datafusion::physical_expr::expressions::{Column, Count, Sum};
+
+    use super::*;
+
+    /// Combines the partial states of `SUM(v), COUNT(v) GROUP BY k` in the given batches of
+    /// `(k, sum, count)`, and returns the combined rows sorted by `k`.
+    fn combine(batches: Vec<Vec<(&str, i64, i64)>>, max_groups: usize) -> Vec<(String, i64, i64)> {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("k", DataType::Utf8, true),
+            Field::new("sum", DataType::Int64, true),
+            Field::new("count", DataType::Int64, true),
+        ]));
+        let v = Arc::new(Column::new("v", 1));
+        let combine = ShuffleCombine {
+            num_grouping_keys: 1,
+            aggr_exprs: vec![
+                Arc::new(Sum::new(v.clone(), "sum", DataType::Int64)),
+                Arc::new(Count::new(v, "count", DataType::Int64)),
+            ],
+            max_groups,
+        };
+        let mut combiner = ShuffleCombiner::try_new(schema.clone(), combine).unwrap();
+
+        let mut combined = vec![];
+        for rows in batches {
+            let batch = RecordBatch::try_new(
+                schema.clone(),
+                vec![
+                    Arc::new(StringArray::from_iter_values(rows.iter().map(|r| r.0))),
+                    Arc::new(Int64Array::from_iter_values(rows.iter().map(|r| r.1))),
+                    Arc::new(Int64Array::from_iter_values(rows.iter().map(|r| r.2))),
+                ],
+            )
+            .unwrap();
+            combined.extend(combiner.insert_batch(&batch).unwrap());
+        }
+        combined.extend(combiner.emit().unwrap());
+
+        let mut rows = combined
+            .iter()
+            .flat_map(|batch| {
+                let k = batch.column(0).as_string::<i32>();
+                let sum = batch.column(1).as_primitive::<Int64Type>();
+                let count = batch.column(2).as_primitive::<Int64Type>();
+                (0..batch.num_rows())
+                    .map(|i| (k.value(i).to_string(), sum.value(i), count.value(i)))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        rows.sort();
+        rows
+    }
+
+    #[test]
+    fn test_combine_partial_states() {
+        let batches = vec![
+            vec![("a", 1, 1), ("b", 2, 1), ("a", 3, 2)],
+            vec![("b", 4, 2), ("c", 5, 1)],
+        ];
+        assert_eq!(
+            combine(batches.clone(), 100),
+            vec![
+                ("a".to_string(), 4, 3),
+                ("b".to_string(), 6, 3),
+                ("c".to_string(), 5, 1)
+            ]
+        );
+
+        // The combined rows are emitted once the combiner holds 2 groups, after the first batch,
+        // so that the keys of the second batch are emitted again
+        assert_eq!(
+            combine(batches, 2),
+            vec![
+                ("a".to_string(), 4, 3),
+                ("b".to_string(), 2, 1),
+                ("b".to_string(), 4, 2),
+                ("c".to_string(), 5, 1)
+            ]
+        );
+    }
+}
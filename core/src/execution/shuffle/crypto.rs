@@ -0,0 +1,260 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Encrypts the output partitions of native shuffle writers when Spark IO encryption is enabled.
+//!
+//! Like Spark's `CryptoStreamUtils`, the bytes of each partition are a separate stream, which
+//! starts with a random 16-byte initialization vector, followed by the bytes encrypted with
+//! `AES/CTR/NoPadding`, whose 128-bit big-endian counter starts from the initialization vector.
+//! So the reducers decrypt the fetched blocks with `SerializerManager.wrapForEncryption`, as
+//! they do for the blocks of Spark's shuffle writers.
+
+use std::io;
+
+use aes::{Aes128, Aes192, Aes256};
+use ctr::{
+    cipher::{KeyIvInit, StreamCipher},
+    Ctr128BE,
+};
+use datafusion::error::{DataFusionError, Result};
+use rand::RngCore;
+
+use crate::execution::datafusion::shuffle_writer::ShuffleBlockSink;
+
+/// The length of the initialization vector written before the bytes of each partition
+const IV_LENGTH: usize = 16;
+
+/// AES in counter mode, with the key sizes supported by `spark.io.encryption.keySizeBits`.
+enum AesCtr {
+    Aes128(Ctr128BE<Aes128>),
+    Aes192(Ctr128BE<Aes192>),
+    Aes256(Ctr128BE<Aes256>),
+}
+
+impl AesCtr {
+    fn try_new(key: &[u8], iv: &[u8; IV_LENGTH]) -> Result<Self> {
+        let cipher = match key.len() {
+            16 => Ctr128BE::<Aes128>::new_from_slices(key, iv).map(AesCtr::Aes128),
+            24 => Ctr128BE::<Aes192>::new_from_slices(key, iv).map(AesCtr::Aes192),
+            32 => Ctr128BE::<Aes256>::new_from_slices(key, iv).map(AesCtr::Aes256),
+            len => {
+                return Err(DataFusionError::Execution(format!(
+                    "Invalid shuffle encryption key of {} bytes",
+                    len
+                )))
+            }
+        };
+        cipher.map_err(|e| DataFusionError::Execution(format!("Invalid shuffle cipher: {}", e)))
+    }
+
+    fn apply_keystream(&mut self, buf: &mut [u8]) {
+        match self {
+            AesCtr::Aes128(cipher) => cipher.apply_keystream(buf),
+            AesCtr::Aes192(cipher) => cipher.apply_keystream(buf),
+            AesCtr::Aes256(cipher) => cipher.apply_keystream(buf),
+        }
+    }
+}
+
+/// The AES key of shuffle encryption, which is not shown by `Debug`, e.g., in the plans logged.
+#[derive(Clone)]
+pub struct EncryptionKey(pub Vec<u8>);
+
+impl std::fmt::Debug for EncryptionKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "EncryptionKey({} bits)", self.0.len() * 8)
+    }
+}
+
+/// A [`ShuffleBlockSink`] encrypting the bytes of each partition before writing them into the
+/// sink it wraps. The initialization vector of a partition is only written with its first bytes,
+/// so the partitions without bytes stay empty, as Spark's shuffle writers leave them.
+pub struct EncryptedBlockSink {
+    inner: Box<dyn ShuffleBlockSink>,
+    key: EncryptionKey,
+    /// The cipher of the current partition, which is created on its first bytes
+    cipher: Option<AesCtr>,
+    /// Reused buffer of the encrypted bytes
+    buffer: Vec<u8>,
+}
+
+impl EncryptedBlockSink {
+    /// Creates the sink encrypting with the given AES key, i.e., the IO encryption key of Spark.
+    pub fn try_new(inner: Box<dyn ShuffleBlockSink>, key: EncryptionKey) -> Result<Self> {
+        // Validates the key before any partition is written
+        AesCtr::try_new(&key.0, &[0; IV_LENGTH])?;
+        Ok(Self {
+            inner,
+            key,
+            cipher: None,
+            buffer: vec![],
+        })
+    }
+
+    /// Starts the stream of the current partition with a new initialization vector.
+    fn start_stream(&mut self) -> Result<AesCtr> {
+        let mut iv = [0u8; IV_LENGTH];
+        rand::thread_rng().fill_bytes(&mut iv);
+        self.inner.write_all(&iv)?;
+        AesCtr::try_new(&self.key.0, &iv)
+    }
+}
+
+impl io::Write for EncryptedBlockSink {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let mut cipher = match self.cipher.take() {
+            Some(cipher) => cipher,
+            None => self
+                .start_stream()
+                .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?,
+        };
+        self.buffer.clear();
+        self.buffer.extend_from_slice(buf);
+        cipher.apply_keystream(&mut self.buffer);
+        self.cipher = Some(cipher);
+        // The keystream has advanced over all the bytes, so they must be written at once
+        self.inner.write_all(&self.buffer)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl ShuffleBlockSink for EncryptedBlockSink {
+    fn start_partition(&mut self, partition_id: usize) -> Result<()> {
+        self.cipher = None;
+        self.inner.start_partition(partition_id)
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        self.cipher = None;
+        self.inner.finish()
+    }
+
+    fn is_local_io(&self) -> bool {
+        self.inner.is_local_io()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        io::Write,
+        sync::{Arc, Mutex},
+    };
+
+    fn hex(s: &str) -> Vec<u8> {
+        (0..s.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    /// Collects the bytes written into each partition.
+    struct PartitionsSink(Arc<Mutex<Vec<Vec<u8>>>>);
+
+    impl io::Write for PartitionsSink {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0
+                .lock()
+                .unwrap()
+                .last_mut()
+                .unwrap()
+                .extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl ShuffleBlockSink for PartitionsSink {
+        fn start_partition(&mut self, _: usize) -> Result<()> {
+            self.0.lock().unwrap().push(vec![]);
+            Ok(())
+        }
+
+        fn finish(&mut self) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_aes_ctr() {
+        // F.5.1 of NIST SP 800-38A, which `AES/CTR/NoPadding` of Spark gives too
+        let key = hex("2b7e151628aed2a6abf7158809cf4f3c");
+        let iv: [u8; IV_LENGTH] = hex("f0f1f2f3f4f5f6f7f8f9fafbfcfdfeff").try_into().unwrap();
+        let mut bytes = hex("6bc1bee22e409f96e93d7e117393172aae2d8a571e03ac9c9eb76fac45af8e51");
+        AesCtr::try_new(&key, &iv)
+            .unwrap()
+            .apply_keystream(&mut bytes);
+        assert_eq!(
+            bytes,
+            hex("874d6191b620e3261bef6864990db6ce9806f66b7970fdff8617187bb9fffdff")
+        );
+
+        assert!(AesCtr::try_new(&key[..10], &iv).is_err());
+    }
+
+    #[test]
+    fn test_encrypted_block_sink() {
+        let key = hex("000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f");
+        let partitions = Arc::new(Mutex::new(vec![]));
+        let mut sink = EncryptedBlockSink::try_new(
+            Box::new(PartitionsSink(partitions.clone())),
+            EncryptionKey(key.clone()),
+        )
+        .unwrap();
+
+        sink.start_partition(0).unwrap();
+        sink.write_all(b"hello ").unwrap();
+        sink.write_all(b"shuffle").unwrap();
+        // An empty partition has no initialization vector
+        sink.start_partition(1).unwrap();
+        sink.start_partition(2).unwrap();
+        sink.write_all(b"hello shuffle").unwrap();
+        sink.finish().unwrap();
+
+        let partitions = partitions.lock().unwrap();
+        assert_eq!(partitions.len(), 3);
+        assert!(partitions[1].is_empty());
+        for partition in [&partitions[0], &partitions[2]] {
+            assert_eq!(partition.len(), IV_LENGTH + 13);
+            let (iv, encrypted) = partition.split_at(IV_LENGTH);
+            let mut decrypted = encrypted.to_vec();
+            AesCtr::try_new(&key, iv.try_into().unwrap())
+                .unwrap()
+                .apply_keystream(&mut decrypted);
+            assert_eq!(decrypted, b"hello shuffle");
+        }
+        // Each partition has its own initialization vector
+        assert_ne!(partitions[0][..IV_LENGTH], partitions[2][..IV_LENGTH]);
+
+        assert!(EncryptedBlockSink::try_new(
+            Box::new(PartitionsSink(Arc::new(Mutex::new(vec![])))),
+            EncryptionKey(vec![0; 7])
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,124 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Keeps the dictionary arrays of shuffled batches compact.
+//!
+//! Combining the dictionary arrays of several batches, e.g., by `interleave` or `concat`, keeps
+//! the values of all their dictionaries, including the duplicated and unused ones. The batches of
+//! native shuffle are written and read with a single dictionary per column instead, holding the
+//! distinct values of the rows of the batch only.
+
+use arrow::compute::cast;
+use arrow_array::{Array, ArrayRef, RecordBatch, RecordBatchOptions};
+use arrow_schema::{ArrowError, DataType};
+
+/// Returns the given batch whose dictionary arrays only have the distinct values used by their
+/// keys. The other columns are unchanged.
+pub(crate) fn consolidate_dictionaries(batch: RecordBatch) -> Result<RecordBatch, ArrowError> {
+    if !batch
+        .columns()
+        .iter()
+        .any(|column| matches!(column.data_type(), DataType::Dictionary(_, _)))
+    {
+        return Ok(batch);
+    }
+    let columns = batch
+        .columns()
+        .iter()
+        .map(consolidate_dictionary)
+        .collect::<Result<Vec<_>, _>>()?;
+    RecordBatch::try_new_with_options(
+        batch.schema(),
+        columns,
+        &RecordBatchOptions::new().with_row_count(Some(batch.num_rows())),
+    )
+}
+
+fn consolidate_dictionary(array: &ArrayRef) -> Result<ArrayRef, ArrowError> {
+    match array.data_type() {
+        // Casting the values back into a dictionary deduplicates them, and drops the ones not
+        // referenced by any key
+        DataType::Dictionary(_, value_type) => cast(&cast(array, value_type)?, array.data_type()),
+        _ => Ok(array.clone()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::compute::concat_batches;
+    use arrow_array::{cast::AsArray, types::Int32Type, DictionaryArray, Int32Array};
+    use arrow_schema::{Field, Schema};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_consolidate_dictionaries() {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new(
+                "a",
+                DataType::Dictionary(Box::new(DataType::Int32), Box::new(DataType::Utf8)),
+                true,
+            ),
+            Field::new("b", DataType::Int32, true),
+        ]));
+        let batch = |values: Vec<Option<&str>>| {
+            let num_rows = values.len() as i32;
+            RecordBatch::try_new(
+                schema.clone(),
+                vec![
+                    Arc::new(values.into_iter().collect::<DictionaryArray<Int32Type>>()),
+                    Arc::new(Int32Array::from_iter_values(0..num_rows)),
+                ],
+            )
+            .unwrap()
+        };
+        let batches = [
+            batch(vec![Some("x"), Some("y"), None]),
+            batch(vec![Some("y"), Some("x"), Some("y")]),
+            // The unused values of a sliced dictionary are dropped too
+            batch(vec![Some("z"), Some("x")]).slice(1, 1),
+        ];
+        let concatenated = concat_batches(&schema, &batches).unwrap();
+
+        let consolidated = consolidate_dictionaries(concatenated.clone()).unwrap();
+        assert_eq!(consolidated.schema(), schema);
+        assert_eq!(consolidated.column(1), concatenated.column(1));
+        let dictionary = consolidated.column(0).as_dictionary::<Int32Type>();
+        assert_eq!(dictionary.values().len(), 2);
+        let values = cast(dictionary, &DataType::Utf8).unwrap();
+        assert_eq!(
+            values.as_string::<i32>().iter().collect::<Vec<_>>(),
+            vec![
+                Some("x"),
+                Some("y"),
+                None,
+                Some("y"),
+                Some("x"),
+                Some("y"),
+                Some("x")
+            ]
+        );
+
+        // Batches without dictionaries are returned as they are
+        let plain = RecordBatch::try_new(
+            Arc::new(Schema::new(vec![Field::new("b", DataType::Int32, true)])),
+            vec![Arc::new(Int32Array::from(vec![1, 2]))],
+        )
+        .unwrap();
+        assert_eq!(consolidate_dictionaries(plain.clone()).unwrap(), plain);
+    }
+}
@@ -15,6 +15,13 @@
 // specific language governing permissions and limitations
 // under the License.
 
+pub(crate) mod block_sink;
+pub(crate) mod buffer_pool;
+pub(crate) mod coalescer;
+pub(crate) mod codec;
+pub mod combiner;
+pub mod crypto;
+pub(crate) mod dictionary;
 mod list;
 mod map;
 pub mod row;
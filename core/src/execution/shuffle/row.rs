@@ -20,6 +20,7 @@
 use crate::{
     errors::CometError,
     execution::{
+        config::CompressionCodec,
         datafusion::shuffle_writer::{write_ipc_compressed, Checksum},
         shuffle::{
             list::{append_list_element, SparkUnsafeArray},
@@ -3286,6 +3287,7 @@ pub fn process_sorted_row_partition(
     schema: &Vec<DataType>,
     output_path: String,
     prefer_dictionary_ratio: f64,
+    codec: &CompressionCodec,
     checksum_enabled: bool,
     checksum_algo: i32,
     // This is the checksum value passed in from Spark side, and is getting updated for
@@ -3352,7 +3354,7 @@ pub fn process_sorted_row_partition(
         let mut frozen: Vec<u8> = vec![];
         let mut cursor = Cursor::new(&mut frozen);
         cursor.seek(SeekFrom::End(0))?;
-        written += write_ipc_compressed(&batch, &mut cursor)?;
+        written += write_ipc_compressed(&batch, &mut cursor, codec)?;
 
         if let Some(checksum) = &mut current_checksum {
             checksum.update(&mut cursor)?;
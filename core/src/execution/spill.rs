@@ -0,0 +1,214 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Placement and accounting of the files spilled by native operators.
+
+use std::{
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use datafusion_common::{DataFusionError, Result};
+use tempfile::{NamedTempFile, TempDir};
+
+/// Creates the spill files of a native plan under the local directories of the Spark executor,
+/// choosing the directories in round-robin so that spills are spread over the disks. It tracks
+/// the disk space used by the live spill files of the plan, and fails the spills exceeding the
+/// optional disk limit.
+///
+/// A manager without local directories creates the spill files in the temporary directory of
+/// the OS.
+#[derive(Debug, Default)]
+pub struct SpillManager {
+    /// The local directories to create spill files in
+    local_dirs: Vec<PathBuf>,
+    /// The temporary directories of the plan under `local_dirs`, created on the first spill into
+    /// each of them. They are removed once the plan and all its spill files are dropped.
+    temp_dirs: Mutex<Vec<Option<Arc<TempDir>>>>,
+    /// The index of the local directory to create the next spill file in
+    next_dir: AtomicUsize,
+    /// The maximum disk space the spill files of the plan can use, in bytes
+    disk_limit: Option<usize>,
+    /// The disk space used by the live spill files, in bytes
+    used: AtomicUsize,
+    /// The peak of `used`
+    peak: AtomicUsize,
+}
+
+impl SpillManager {
+    pub fn new(local_dirs: Vec<PathBuf>, disk_limit: Option<usize>) -> Self {
+        let temp_dirs = Mutex::new(vec![None; local_dirs.len()]);
+        Self {
+            local_dirs,
+            temp_dirs,
+            next_dir: AtomicUsize::new(0),
+            disk_limit,
+            used: AtomicUsize::new(0),
+            peak: AtomicUsize::new(0),
+        }
+    }
+
+    /// Creates a new spill file. `description` is used in the file name for debugging.
+    pub fn create_spill_file(self: &Arc<Self>, description: &str) -> Result<SpillFile> {
+        let prefix = format!("comet-{}-", description.replace(' ', "-"));
+        let (file, temp_dir) = if self.local_dirs.is_empty() {
+            let file = tempfile::Builder::new().prefix(&prefix).tempfile()?;
+            (file, None)
+        } else {
+            let index = self.next_dir.fetch_add(1, Ordering::Relaxed) % self.local_dirs.len();
+            let temp_dir = self.temp_dir(index)?;
+            let file = tempfile::Builder::new()
+                .prefix(&prefix)
+                .tempfile_in(temp_dir.path())?;
+            (file, Some(temp_dir))
+        };
+
+        Ok(SpillFile {
+            file,
+            size: 0,
+            manager: Arc::clone(self),
+            _temp_dir: temp_dir,
+        })
+    }
+
+    /// The disk space used by the live spill files, in bytes.
+    pub fn used(&self) -> usize {
+        self.used.load(Ordering::Relaxed)
+    }
+
+    /// The peak disk space used by the spill files, in bytes.
+    pub fn peak(&self) -> usize {
+        self.peak.load(Ordering::Relaxed)
+    }
+
+    fn temp_dir(&self, index: usize) -> Result<Arc<TempDir>> {
+        let mut temp_dirs = self.temp_dirs.lock().unwrap();
+        if let Some(temp_dir) = &temp_dirs[index] {
+            return Ok(Arc::clone(temp_dir));
+        }
+        let temp_dir = tempfile::Builder::new()
+            .prefix("comet-spill-")
+            .tempdir_in(&self.local_dirs[index])
+            .map_err(|e| {
+                DataFusionError::Execution(format!(
+                    "Failed to create spill directory under '{}': {}",
+                    self.local_dirs[index].display(),
+                    e
+                ))
+            })?;
+        let temp_dir = Arc::new(temp_dir);
+        temp_dirs[index] = Some(Arc::clone(&temp_dir));
+        Ok(temp_dir)
+    }
+
+    fn grow(&self, size: usize) -> Result<()> {
+        let used = self.used.fetch_add(size, Ordering::Relaxed) + size;
+        if let Some(disk_limit) = self.disk_limit {
+            if used > disk_limit {
+                self.used.fetch_sub(size, Ordering::Relaxed);
+                return Err(DataFusionError::ResourcesExhausted(format!(
+                    "Failed to spill {} bytes: the spill files of the plan would use {} bytes \
+                     of disk space, exceeding the limit of {} bytes",
+                    size, used, disk_limit
+                )));
+            }
+        }
+        self.peak.fetch_max(used, Ordering::Relaxed);
+        Ok(())
+    }
+
+    fn shrink(&self, size: usize) {
+        self.used.fetch_sub(size, Ordering::Relaxed);
+    }
+}
+
+/// A spill file created by [`SpillManager`]. The file is deleted and its disk space is released
+/// from the manager when this is dropped.
+#[derive(Debug)]
+pub struct SpillFile {
+    file: NamedTempFile,
+    /// The size of the file recorded by `record_size`
+    size: usize,
+    manager: Arc<SpillManager>,
+    /// Keeps the parent directory alive until the file is dropped
+    _temp_dir: Option<Arc<TempDir>>,
+}
+
+impl SpillFile {
+    pub fn path(&self) -> &Path {
+        self.file.path()
+    }
+
+    /// Records the size of the file. It is called with the estimated size before writing to the
+    /// file, so that a spill exceeding the disk limit fails before using the disk space, and with
+    /// the actual size after writing to it. Fails if the spill files of the plan would exceed the
+    /// disk limit.
+    pub fn record_size(&mut self, size: usize) -> Result<()> {
+        if size > self.size {
+            self.manager.grow(size - self.size)?;
+        } else {
+            self.manager.shrink(self.size - size);
+        }
+        self.size = size;
+        Ok(())
+    }
+}
+
+impl Drop for SpillFile {
+    fn drop(&mut self) {
+        self.manager.shrink(self.size);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::SpillManager;
+
+    #[test]
+    fn test_spill_manager() {
+        let dirs = [tempfile::tempdir().unwrap(), tempfile::tempdir().unwrap()];
+        let manager = Arc::new(SpillManager::new(
+            dirs.iter().map(|dir| dir.path().to_path_buf()).collect(),
+            Some(100),
+        ));
+
+        // Spill files are created in the local directories in round-robin
+        let mut files = (0..3)
+            .map(|_| manager.create_spill_file("test spill").unwrap())
+            .collect::<Vec<_>>();
+        assert!(files[0].path().starts_with(dirs[0].path()));
+        assert!(files[1].path().starts_with(dirs[1].path()));
+        assert!(files[2].path().starts_with(dirs[0].path()));
+
+        files[0].record_size(60).unwrap();
+        files[1].record_size(30).unwrap();
+        assert!(files[2].record_size(20).is_err());
+        assert_eq!(manager.used(), 90);
+
+        let path = files[0].path().to_path_buf();
+        drop(files.remove(0));
+        assert!(!path.exists());
+        files[1].record_size(20).unwrap();
+        assert_eq!(manager.used(), 50);
+        assert_eq!(manager.peak(), 90);
+    }
+}
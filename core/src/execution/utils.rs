@@ -55,6 +55,10 @@ pub trait SparkArrowConvert {
     /// Convert Arrow Arrays to C data interface.
     /// It returns a tuple (ArrowArray address, ArrowSchema address).
     fn to_spark(&self) -> Result<(i64, i64), ExecutionError>;
+
+    /// Move Arrow Arrays into the C data interface structs allocated by Spark.
+    /// It accepts a tuple (ArrowArray address, ArrowSchema address).
+    fn move_to_spark(&self, addresses: (i64, i64)) -> Result<(), ExecutionError>;
 }
 
 impl SparkArrowConvert for ArrayData {
@@ -96,6 +100,28 @@ impl SparkArrowConvert for ArrayData {
 
         Ok((array as i64, schema as i64))
     }
+
+    fn move_to_spark(&self, addresses: (i64, i64)) -> Result<(), ExecutionError> {
+        let (array_ptr, schema_ptr) = addresses;
+
+        let array_ptr = array_ptr as *mut FFI_ArrowArray;
+        let schema_ptr = schema_ptr as *mut FFI_ArrowSchema;
+
+        if array_ptr.is_null() || schema_ptr.is_null() {
+            return Err(ExecutionError::ArrowError(
+                "At least one of passed pointers is null".to_string(),
+            ));
+        };
+
+        // The structs allocated by Spark are empty, so there is nothing to release before
+        // overwriting them. Spark takes the ownership of the moved data by importing them.
+        unsafe {
+            std::ptr::write(array_ptr, FFI_ArrowArray::new(self));
+            std::ptr::write(schema_ptr, FFI_ArrowSchema::try_from(self.data_type())?);
+        }
+
+        Ok(())
+    }
 }
 
 /// Converts a slice of bytes to i128. The bytes are serialized in big-endian order by
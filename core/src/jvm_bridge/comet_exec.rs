@@ -49,6 +49,8 @@ pub struct CometExec<'a> {
     pub method_get_binary_ret: ReturnType,
     pub method_is_null: JStaticMethodID,
     pub method_is_null_ret: ReturnType,
+    pub method_get_in_values: JStaticMethodID,
+    pub method_get_in_values_ret: ReturnType,
 }
 
 impl<'a> CometExec<'a> {
@@ -103,6 +105,10 @@ impl<'a> CometExec<'a> {
                 .get_static_method_id(Self::JVM_CLASS, "isNull", "(JJ)Z")
                 .unwrap(),
             method_is_null_ret: ReturnType::Primitive(Primitive::Boolean),
+            method_get_in_values: env
+                .get_static_method_id(Self::JVM_CLASS, "getInValues", "(JJ)[B")
+                .unwrap(),
+            method_get_in_values_ret: ReturnType::Array,
             class,
         })
     }
@@ -0,0 +1,47 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use jni::{
+    errors::Result as JniResult,
+    objects::{JClass, JMethodID},
+    signature::{Primitive, ReturnType},
+    JNIEnv,
+};
+
+use crate::jvm_bridge::get_global_jclass;
+
+/// A struct that holds the JNI methods of the JVM `CometShuffleBlockSink` interface.
+#[derive(Debug)]
+pub struct CometShuffleBlockSink<'a> {
+    pub class: JClass<'a>,
+    pub method_write_block: JMethodID,
+    pub method_write_block_ret: ReturnType,
+}
+
+impl<'a> CometShuffleBlockSink<'a> {
+    pub const JVM_CLASS: &'static str = "org/apache/comet/CometShuffleBlockSink";
+
+    pub fn new(env: &mut JNIEnv<'a>) -> JniResult<CometShuffleBlockSink<'a>> {
+        let class = get_global_jclass(env, Self::JVM_CLASS)?;
+
+        Ok(CometShuffleBlockSink {
+            class,
+            method_write_block: env.get_method_id(Self::JVM_CLASS, "writeBlock", "(I[B)V")?,
+            method_write_block_ret: ReturnType::Primitive(Primitive::Void),
+        })
+    }
+}
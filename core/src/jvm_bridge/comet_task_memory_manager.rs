@@ -31,9 +31,13 @@ pub struct CometTaskMemoryManager<'a> {
     pub class: JClass<'a>,
     pub method_acquire_memory: JMethodID,
     pub method_release_memory: JMethodID,
+    pub method_acquire_shuffle_memory: JMethodID,
+    pub method_release_shuffle_memory: JMethodID,
 
     pub method_acquire_memory_ret: ReturnType,
     pub method_release_memory_ret: ReturnType,
+    pub method_acquire_shuffle_memory_ret: ReturnType,
+    pub method_release_shuffle_memory_ret: ReturnType,
 }
 
 impl<'a> CometTaskMemoryManager<'a> {
@@ -54,8 +58,20 @@ impl<'a> CometTaskMemoryManager<'a> {
                 "releaseMemory",
                 "(J)V".to_string(),
             )?,
+            method_acquire_shuffle_memory: env.get_method_id(
+                Self::JVM_CLASS,
+                "acquireShuffleMemory",
+                "(J)J".to_string(),
+            )?,
+            method_release_shuffle_memory: env.get_method_id(
+                Self::JVM_CLASS,
+                "releaseShuffleMemory",
+                "(J)V".to_string(),
+            )?,
             method_acquire_memory_ret: ReturnType::Primitive(Primitive::Long),
             method_release_memory_ret: ReturnType::Primitive(Primitive::Void),
+            method_acquire_shuffle_memory_ret: ReturnType::Primitive(Primitive::Long),
+            method_release_shuffle_memory_ret: ReturnType::Primitive(Primitive::Void),
         };
         Ok(result)
     }
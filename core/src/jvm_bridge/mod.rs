@@ -194,11 +194,13 @@ mod comet_exec;
 pub use comet_exec::*;
 mod batch_iterator;
 mod comet_metric_node;
+mod comet_shuffle_block_sink;
 mod comet_task_memory_manager;
 
 use crate::{errors::CometError, JAVA_VM};
 use batch_iterator::CometBatchIterator;
 pub use comet_metric_node::*;
+pub use comet_shuffle_block_sink::*;
 pub use comet_task_memory_manager::*;
 
 /// The JVM classes that are used in the JNI calls.
@@ -221,6 +223,9 @@ pub struct JVMClasses<'a> {
     /// The CometTaskMemoryManager used for interacting with JVM side to
     /// acquire & release native memory.
     pub comet_task_memory_manager: CometTaskMemoryManager<'a>,
+    /// The CometShuffleBlockSink interface. Used for pushing the output partitions of native
+    /// shuffle writers to JVM.
+    pub comet_shuffle_block_sink: CometShuffleBlockSink<'a>,
 }
 
 unsafe impl<'a> Send for JVMClasses<'a> {}
@@ -267,6 +272,7 @@ impl JVMClasses<'_> {
                 comet_exec: CometExec::new(env).unwrap(),
                 comet_batch_iterator: CometBatchIterator::new(env).unwrap(),
                 comet_task_memory_manager: CometTaskMemoryManager::new(env).unwrap(),
+                comet_shuffle_block_sink: CometShuffleBlockSink::new(env).unwrap(),
             }
         });
     }
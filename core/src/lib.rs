@@ -28,7 +28,8 @@
 use core::intrinsics::{likely, unlikely};
 
 use jni::{
-    objects::{JClass, JString},
+    objects::{JClass, JObject, JString},
+    sys::{jint, jobjectArray},
     JNIEnv, JavaVM,
 };
 use log::{info, LevelFilter};
@@ -55,10 +56,20 @@ pub mod execution;
 mod jvm_bridge;
 pub mod parquet;
 
-#[cfg(feature = "mimalloc")]
+#[cfg(all(feature = "mimalloc", not(feature = "alloc_tracking")))]
 #[global_allocator]
 static GLOBAL: MiMalloc = MiMalloc;
 
+#[cfg(all(feature = "mimalloc", feature = "alloc_tracking"))]
+#[global_allocator]
+static GLOBAL: execution::alloc_tracker::TrackingAllocator<MiMalloc> =
+    execution::alloc_tracker::TrackingAllocator::new(MiMalloc);
+
+#[cfg(all(not(feature = "mimalloc"), feature = "alloc_tracking"))]
+#[global_allocator]
+static GLOBAL: execution::alloc_tracker::TrackingAllocator<std::alloc::System> =
+    execution::alloc_tracker::TrackingAllocator::new(std::alloc::System);
+
 static JAVA_VM: OnceCell<JavaVM> = OnceCell::new();
 
 #[no_mangle]
@@ -93,6 +104,61 @@ pub extern "system" fn Java_org_apache_comet_NativeBase_init(
     })
 }
 
+/// The version of the JNI interface of the native library, which `NativeBase` of the plugin jar
+/// checks before using the library. It is bumped when the signature or the semantics of an existing
+/// native method change. New native methods don't bump it, but are advertised in [`CAPABILITIES`],
+/// so that a jar only uses them if the library it loads has them.
+const ABI_VERSION: jint = 1;
+
+/// The optional features of the native library, which the plugin only enables if the library it
+/// loads advertises them.
+const CAPABILITIES: &[&str] = &[
+    // `Native.decodeShuffleBlock`
+    "native_shuffle_reader",
+    // `Native.createShuffleCoalescer` and the methods of shuffle coalescers
+    "shuffle_coalescer",
+    // `Native.mapBroadcast` and the methods of mapped broadcast relations
+    "mapped_broadcast",
+    // The `resource_profile*` configs of native plans
+    "resource_profile",
+    // The `offload_*` configs of native plans
+    "operator_offload",
+    // The `FileScan` operator of native plans
+    "native_file_scan",
+    // The `encryption_key` of native shuffle writers
+    "shuffle_encryption",
+    // The `source_id` of the `Scan` operator of native plans
+    "shared_scan",
+    // The `condition` and `band` of the `SortMergeJoin` operator of native plans
+    "band_join",
+    #[cfg(feature = "prometheus")]
+    "metrics_exporter",
+];
+
+#[no_mangle]
+pub extern "system" fn Java_org_apache_comet_NativeBase_abiVersion(_: JNIEnv, _: JClass) -> jint {
+    ABI_VERSION
+}
+
+#[no_mangle]
+pub extern "system" fn Java_org_apache_comet_NativeBase_capabilities(
+    e: JNIEnv,
+    _: JClass,
+) -> jobjectArray {
+    try_unwrap_or_throw(&e, |mut env| {
+        let capabilities = env.new_object_array(
+            CAPABILITIES.len() as i32,
+            "java/lang/String",
+            JObject::null(),
+        )?;
+        for (i, capability) in CAPABILITIES.iter().enumerate() {
+            let capability = env.new_string(capability)?;
+            env.set_object_array_element(&capabilities, i as i32, capability)?;
+        }
+        Ok(capabilities.into_raw())
+    })
+}
+
 const LOG_PATTERN: &str = "{d(%y/%m/%d %H:%M:%S)} {l} {f}: {m}{n}";
 
 // Creates a default log4rs config, which logs to console with `INFO` level.
@@ -40,9 +40,17 @@ use jni::{
 };
 
 use crate::execution::utils::SparkArrowConvert;
-use arrow::buffer::{Buffer, MutableBuffer};
-use jni::objects::{JBooleanArray, JLongArray, JPrimitiveArray, ReleaseMode};
-use read::ColumnReader;
+use arrow::{
+    array::ArrayData,
+    buffer::{Buffer, MutableBuffer},
+};
+use jni::objects::{
+    JBooleanArray, JIntArray, JLongArray, JObjectArray, JPrimitiveArray, JString, ReleaseMode,
+};
+use read::{
+    nested::{leaf_array_data, list_array_data, map_array_data, struct_array_data, Scope},
+    ColumnReader,
+};
 use util::jni::{convert_column_descriptor, convert_encoding};
 
 use self::util::jni::TypePromotionInfo;
@@ -77,6 +85,7 @@ pub extern "system" fn Java_org_apache_comet_parquet_Native_initColumnReader(
     batch_size: jint,
     use_decimal_128: jboolean,
     use_legacy_date_timestamp: jboolean,
+    validate_utf8: jboolean,
 ) -> jlong {
     try_unwrap_or_throw(&e, |mut env| {
         let desc = convert_column_descriptor(
@@ -102,6 +111,7 @@ pub extern "system" fn Java_org_apache_comet_parquet_Native_initColumnReader(
                 batch_size as usize,
                 use_decimal_128 != 0,
                 use_legacy_date_timestamp != 0,
+                validate_utf8 != 0,
             ),
             arrays: None,
             last_data_page: None,
@@ -582,6 +592,438 @@ pub extern "system" fn Java_org_apache_comet_parquet_Native_closeColumnReader(
     })
 }
 
+/// The kinds of readers of the fields of nested columns, as passed from the JVM side.
+const COLUMN_READER: jint = 0;
+const STRUCT_READER: jint = 1;
+const LIST_READER: jint = 2;
+const MAP_READER: jint = 3;
+
+/// A reader of a field of a Parquet struct, list or map column, which is owned by the JVM side.
+enum NestedFieldReader {
+    /// The handle of a column reader
+    Column(jlong),
+    /// The handle of a struct reader
+    Struct(jlong),
+    /// The handle of a list reader
+    List(jlong),
+    /// The handle of a map reader
+    Map(jlong),
+}
+
+impl NestedFieldReader {
+    fn try_new(handle: jlong, kind: jint) -> Result<Self, CometError> {
+        match kind {
+            COLUMN_READER => Ok(Self::Column(handle)),
+            STRUCT_READER => Ok(Self::Struct(handle)),
+            LIST_READER => Ok(Self::List(handle)),
+            MAP_READER => Ok(Self::Map(handle)),
+            _ => Err(CometError::Internal(format!(
+                "Unknown kind of nested field reader: {kind}"
+            ))),
+        }
+    }
+
+    /// Returns the values of the field in the current batch of `num_rows` rows, which are
+    /// selected by `scope`, and the handle of a column reader under it which has read the
+    /// definition levels of the batch, if any.
+    fn current_batch(
+        &self,
+        num_rows: usize,
+        scope: Scope,
+    ) -> Result<(ArrayData, Option<jlong>), CometError> {
+        match self {
+            NestedFieldReader::Column(handle) => {
+                let reader = get_reader(*handle)?;
+                let levels_reader = reader.def_levels().map(|_| *handle);
+                let data = reader.current_batch();
+                let data = leaf_array_data(data, reader.def_levels(), reader.rep_levels(), scope)?;
+                Ok((data, levels_reader))
+            }
+            NestedFieldReader::Struct(handle) => {
+                get_struct_context(*handle)?.current_batch(num_rows, scope)
+            }
+            NestedFieldReader::List(handle) => {
+                get_list_context(*handle)?.current_batch(num_rows, scope)
+            }
+            NestedFieldReader::Map(handle) => {
+                get_map_context(*handle)?.current_batch(num_rows, scope)
+            }
+        }
+    }
+}
+
+/// A Parquet struct column, whose fields are read by the column readers of its primitive fields,
+/// or by the readers of its nested groups.
+struct StructContext {
+    fields: Vec<(String, NestedFieldReader)>,
+    /// The number of optional and repeated fields in the path of the struct, including itself
+    def_level: i16,
+    arrays: Option<(Arc<FFI_ArrowArray>, Arc<FFI_ArrowSchema>)>,
+}
+
+impl StructContext {
+    /// Returns the structs in the current batch which are selected by `scope`, and the handle of
+    /// a column reader under the struct which has read the definition levels of the batch, if any.
+    fn current_batch(
+        &self,
+        num_rows: usize,
+        scope: Scope,
+    ) -> Result<(ArrayData, Option<jlong>), CometError> {
+        let mut fields = Vec::with_capacity(self.fields.len());
+        let mut levels_reader = None;
+        for (name, reader) in &self.fields {
+            let (data, field_levels_reader) = reader.current_batch(num_rows, scope)?;
+            levels_reader = levels_reader.or(field_levels_reader);
+            fields.push((name.clone(), data));
+        }
+        let def_levels = match levels_reader {
+            Some(handle) => {
+                let reader = get_reader(handle)?;
+                reader
+                    .def_levels()
+                    .map(|def_levels| scope.def_levels(def_levels, reader.rep_levels()))
+            }
+            None => None,
+        };
+        let num_values = def_levels.as_ref().map_or(num_rows, Vec::len);
+        let data = struct_array_data(fields, def_levels.as_deref(), self.def_level, num_values)?;
+        Ok((data, levels_reader))
+    }
+}
+
+/// A Parquet list column, whose elements are read by the reader of its element field.
+struct ListContext {
+    element: NestedFieldReader,
+    /// The number of optional and repeated fields in the path of the list, including itself
+    def_level: i16,
+    /// The number of repeated fields in the path of the list, including its repeated field
+    rep_level: i16,
+    arrays: Option<(Arc<FFI_ArrowArray>, Arc<FFI_ArrowSchema>)>,
+}
+
+impl ListContext {
+    /// Returns the lists in the current batch which are selected by `scope`, and the handle of
+    /// the column reader under the list which has read the levels of the batch.
+    fn current_batch(
+        &self,
+        num_rows: usize,
+        scope: Scope,
+    ) -> Result<(ArrayData, Option<jlong>), CometError> {
+        let element_scope = Scope {
+            rep_level: self.rep_level,
+            def_level: self.def_level + 1,
+        };
+        let (elements, levels_reader) = self.element.current_batch(num_rows, element_scope)?;
+        let (def_levels, rep_levels) = repeated_levels(levels_reader)?;
+        let data = list_array_data(
+            elements,
+            def_levels,
+            rep_levels,
+            scope,
+            self.def_level,
+            self.rep_level,
+        )?;
+        Ok((data, levels_reader))
+    }
+}
+
+/// A Parquet map column, whose keys and values are read by the readers of the key and value
+/// fields of its repeated key-value group.
+struct MapContext {
+    keys: NestedFieldReader,
+    values: NestedFieldReader,
+    /// The number of optional and repeated fields in the path of the map, including itself
+    def_level: i16,
+    /// The number of repeated fields in the path of the map, including its key-value group
+    rep_level: i16,
+    arrays: Option<(Arc<FFI_ArrowArray>, Arc<FFI_ArrowSchema>)>,
+}
+
+impl MapContext {
+    /// Returns the maps in the current batch which are selected by `scope`, and the handle of a
+    /// column reader under the map which has read the levels of the batch.
+    fn current_batch(
+        &self,
+        num_rows: usize,
+        scope: Scope,
+    ) -> Result<(ArrayData, Option<jlong>), CometError> {
+        let entry_scope = Scope {
+            rep_level: self.rep_level,
+            def_level: self.def_level + 1,
+        };
+        let (keys, keys_levels_reader) = self.keys.current_batch(num_rows, entry_scope)?;
+        let (values, values_levels_reader) = self.values.current_batch(num_rows, entry_scope)?;
+        let levels_reader = keys_levels_reader.or(values_levels_reader);
+        let (def_levels, rep_levels) = repeated_levels(levels_reader)?;
+        let data = map_array_data(
+            keys,
+            values,
+            def_levels,
+            rep_levels,
+            scope,
+            self.def_level,
+            self.rep_level,
+        )?;
+        Ok((data, levels_reader))
+    }
+}
+
+/// Returns the definition and repetition levels read by the given column reader under a list
+/// or a map column, which must be repeated.
+fn repeated_levels<'a>(levels_reader: Option<jlong>) -> Result<(&'a [i16], &'a [i16]), CometError> {
+    let handle = levels_reader.ok_or_else(|| {
+        CometError::Internal("A list or map column must have a column under it".to_string())
+    })?;
+    let reader = get_reader(handle)?;
+    match (reader.def_levels(), reader.rep_levels()) {
+        (Some(def_levels), Some(rep_levels)) => Ok((def_levels, rep_levels)),
+        _ => Err(CometError::Internal(
+            "The column under a list or map column must be repeated".to_string(),
+        )),
+    }
+}
+
+/// Reads the field readers of the given kinds and handles from JNI arrays.
+fn get_field_readers(
+    env: &mut JNIEnv,
+    handles: &JLongArray,
+    kinds: &JIntArray,
+) -> Result<Vec<NestedFieldReader>, CometError> {
+    let num_fields = env.get_array_length(handles)? as usize;
+    let mut handle_values = vec![0; num_fields];
+    env.get_long_array_region(handles, 0, &mut handle_values)?;
+    let mut kind_values = vec![0; num_fields];
+    env.get_int_array_region(kinds, 0, &mut kind_values)?;
+    handle_values
+        .into_iter()
+        .zip(kind_values)
+        .map(|(handle, kind)| NestedFieldReader::try_new(handle, kind))
+        .collect()
+}
+
+/// Exports the given array through the C data interface, which is kept alive by `arrays` until
+/// the next batch, and returns the addresses of the exported array and schema.
+fn export_nested_batch(
+    env: &mut JNIEnv,
+    data: ArrayData,
+    arrays: &mut Option<(Arc<FFI_ArrowArray>, Arc<FFI_ArrowSchema>)>,
+) -> Result<jlongArray, CometError> {
+    let (array, schema) = data.to_spark()?;
+
+    unsafe {
+        let arrow_array = Arc::from_raw(array as *const FFI_ArrowArray);
+        let arrow_schema = Arc::from_raw(schema as *const FFI_ArrowSchema);
+        *arrays = Some((arrow_array, arrow_schema));
+
+        let res = env.new_long_array(2)?;
+        let buf: [i64; 2] = [array, schema];
+        env.set_long_array_region(&res, 0, &buf)
+            .expect("set long array region failed");
+        Ok(res.into_raw())
+    }
+}
+
+/// Creates a struct reader of the fields with the given names, which are read by the readers of
+/// the given handles and kinds, i.e., column, struct or list readers.
+///
+/// # Safety
+/// This function is inheritly unsafe since it deals with raw pointers passed from JNI.
+#[no_mangle]
+pub unsafe extern "system" fn Java_org_apache_comet_parquet_Native_initStructReader(
+    e: JNIEnv,
+    _jclass: JClass,
+    names: jobjectArray,
+    handles: jlongArray,
+    kinds: jintArray,
+    def_level: jint,
+) -> jlong {
+    try_unwrap_or_throw(&e, |mut env| {
+        let names = unsafe { JObjectArray::from_raw(names) };
+        let handles = unsafe { JLongArray::from_raw(handles) };
+        let kinds = unsafe { JIntArray::from_raw(kinds) };
+        let readers = get_field_readers(&mut env, &handles, &kinds)?;
+
+        let mut fields = Vec::with_capacity(readers.len());
+        for (i, reader) in readers.into_iter().enumerate() {
+            let name: JString = env.get_object_array_element(&names, i as i32)?.into();
+            let name: String = env.get_string(&name)?.into();
+            fields.push((name, reader));
+        }
+        let ctx = StructContext {
+            fields,
+            def_level: def_level as i16,
+            arrays: None,
+        };
+        Ok(Box::into_raw(Box::new(ctx)) as i64)
+    })
+}
+
+/// Returns the current batch of `num_rows` structs, reconstructed from the current batches of
+/// the readers of the fields, which must have read them.
+#[no_mangle]
+pub extern "system" fn Java_org_apache_comet_parquet_Native_currentStructBatch(
+    e: JNIEnv,
+    _jclass: JClass,
+    handle: jlong,
+    num_rows: jint,
+) -> jlongArray {
+    try_unwrap_or_throw(&e, |mut env| {
+        let ctx = get_struct_context(handle)?;
+        let (data, _) = ctx.current_batch(num_rows as usize, Scope::ROWS)?;
+        export_nested_batch(&mut env, data, &mut ctx.arrays)
+    })
+}
+
+#[no_mangle]
+pub extern "system" fn Java_org_apache_comet_parquet_Native_closeStructReader(
+    env: JNIEnv,
+    _jclass: JClass,
+    handle: jlong,
+) {
+    try_unwrap_or_throw(&env, |_| {
+        unsafe {
+            let ctx = handle as *mut StructContext;
+            let _ = Box::from_raw(ctx);
+        };
+        Ok(())
+    })
+}
+
+#[inline]
+fn get_struct_context<'a>(handle: jlong) -> Result<&'a mut StructContext, CometError> {
+    unsafe {
+        (handle as *mut StructContext)
+            .as_mut()
+            .ok_or_else(|| CometError::NullPointer("null struct context handle".to_string()))
+    }
+}
+
+/// Creates a list reader whose elements are read by the reader of the given handle and kind.
+#[no_mangle]
+pub extern "system" fn Java_org_apache_comet_parquet_Native_initListReader(
+    e: JNIEnv,
+    _jclass: JClass,
+    element_handle: jlong,
+    element_kind: jint,
+    def_level: jint,
+    rep_level: jint,
+) -> jlong {
+    try_unwrap_or_throw(&e, |_| {
+        let ctx = ListContext {
+            element: NestedFieldReader::try_new(element_handle, element_kind)?,
+            def_level: def_level as i16,
+            rep_level: rep_level as i16,
+            arrays: None,
+        };
+        Ok(Box::into_raw(Box::new(ctx)) as i64)
+    })
+}
+
+/// Returns the current batch of `num_rows` lists, reconstructed from the current batch of the
+/// reader of the elements, which must have read them.
+#[no_mangle]
+pub extern "system" fn Java_org_apache_comet_parquet_Native_currentListBatch(
+    e: JNIEnv,
+    _jclass: JClass,
+    handle: jlong,
+    num_rows: jint,
+) -> jlongArray {
+    try_unwrap_or_throw(&e, |mut env| {
+        let ctx = get_list_context(handle)?;
+        let (data, _) = ctx.current_batch(num_rows as usize, Scope::ROWS)?;
+        export_nested_batch(&mut env, data, &mut ctx.arrays)
+    })
+}
+
+#[no_mangle]
+pub extern "system" fn Java_org_apache_comet_parquet_Native_closeListReader(
+    env: JNIEnv,
+    _jclass: JClass,
+    handle: jlong,
+) {
+    try_unwrap_or_throw(&env, |_| {
+        unsafe {
+            let ctx = handle as *mut ListContext;
+            let _ = Box::from_raw(ctx);
+        };
+        Ok(())
+    })
+}
+
+#[inline]
+fn get_list_context<'a>(handle: jlong) -> Result<&'a mut ListContext, CometError> {
+    unsafe {
+        (handle as *mut ListContext)
+            .as_mut()
+            .ok_or_else(|| CometError::NullPointer("null list context handle".to_string()))
+    }
+}
+
+/// Creates a map reader whose keys and values are read by the readers of the given handles and
+/// kinds.
+#[no_mangle]
+pub extern "system" fn Java_org_apache_comet_parquet_Native_initMapReader(
+    e: JNIEnv,
+    _jclass: JClass,
+    key_handle: jlong,
+    key_kind: jint,
+    value_handle: jlong,
+    value_kind: jint,
+    def_level: jint,
+    rep_level: jint,
+) -> jlong {
+    try_unwrap_or_throw(&e, |_| {
+        let ctx = MapContext {
+            keys: NestedFieldReader::try_new(key_handle, key_kind)?,
+            values: NestedFieldReader::try_new(value_handle, value_kind)?,
+            def_level: def_level as i16,
+            rep_level: rep_level as i16,
+            arrays: None,
+        };
+        Ok(Box::into_raw(Box::new(ctx)) as i64)
+    })
+}
+
+/// Returns the current batch of `num_rows` maps, reconstructed from the current batches of the
+/// readers of the keys and values, which must have read them.
+#[no_mangle]
+pub extern "system" fn Java_org_apache_comet_parquet_Native_currentMapBatch(
+    e: JNIEnv,
+    _jclass: JClass,
+    handle: jlong,
+    num_rows: jint,
+) -> jlongArray {
+    try_unwrap_or_throw(&e, |mut env| {
+        let ctx = get_map_context(handle)?;
+        let (data, _) = ctx.current_batch(num_rows as usize, Scope::ROWS)?;
+        export_nested_batch(&mut env, data, &mut ctx.arrays)
+    })
+}
+
+#[no_mangle]
+pub extern "system" fn Java_org_apache_comet_parquet_Native_closeMapReader(
+    env: JNIEnv,
+    _jclass: JClass,
+    handle: jlong,
+) {
+    try_unwrap_or_throw(&env, |_| {
+        unsafe {
+            let ctx = handle as *mut MapContext;
+            let _ = Box::from_raw(ctx);
+        };
+        Ok(())
+    })
+}
+
+#[inline]
+fn get_map_context<'a>(handle: jlong) -> Result<&'a mut MapContext, CometError> {
+    unsafe {
+        (handle as *mut MapContext)
+            .as_mut()
+            .ok_or_else(|| CometError::NullPointer("null map context handle".to_string()))
+    }
+}
+
 fn from_u8_slice(src: &mut [u8]) -> &mut [i8] {
     let raw_ptr = src.as_mut_ptr() as *mut i8;
     unsafe { std::slice::from_raw_parts_mut(raw_ptr, src.len()) }
@@ -142,6 +142,23 @@ impl ParquetMutableVector {
         }
     }
 
+    /// Grows this vector to hold at least `additional` more values, e.g., the values of a batch of
+    /// rows of a repeated column, which may be more than the rows.
+    pub fn reserve(&mut self, additional: usize) {
+        let capacity = self.num_values + additional;
+        if capacity <= self.capacity {
+            return;
+        }
+        self.validity_buffer.resize(bit::ceil(capacity, 8));
+        let mut value_capacity = capacity;
+        if Self::is_binary_type(&self.arrow_type) {
+            value_capacity += 1;
+        }
+        self.value_buffer
+            .resize(bit::ceil(value_capacity * self.bit_width, 8));
+        self.capacity = capacity;
+    }
+
     /// Appends a new null value to the end of this vector.
     #[inline]
     pub fn put_null(&mut self) {
@@ -86,15 +86,19 @@ impl ColumnReader {
     /// - `use_legacy_date_timestamp_or_ntz`: Whether to read dates/timestamps that were written
     ///   using the legacy Julian/Gregorian hybrid calendar as it is. If false, exceptions will be
     ///   thrown. If the spark type is TimestampNTZ, this should be true.
+    /// - `validate_utf8`: Whether to check that the values of string columns are valid UTF-8. If
+    ///   true, exceptions will be thrown on invalid values.
     pub fn get(
         desc: ColumnDescriptor,
         promotion_info: TypePromotionInfo,
         capacity: usize,
         use_decimal_128: bool,
         use_legacy_date_timestamp_or_ntz: bool,
+        validate_utf8: bool,
     ) -> Self {
         let read_options = ReadOptions {
             use_legacy_date_timestamp_or_ntz,
+            validate_utf8,
         };
         macro_rules! typed_reader {
             ($reader_ty:ident, $arrow_ty:ident) => {
@@ -434,6 +438,16 @@ impl ColumnReader {
         make_func_mut!(self, reset_batch)
     }
 
+    #[inline]
+    pub fn def_levels(&self) -> Option<&[i16]> {
+        make_func!(self, def_levels)
+    }
+
+    #[inline]
+    pub fn rep_levels(&self) -> Option<&[i16]> {
+        make_func!(self, rep_levels)
+    }
+
     #[inline]
     pub fn current_batch(&mut self) -> ArrayData {
         make_func_mut!(self, current_batch)
@@ -468,6 +482,15 @@ pub struct TypedColumnReader<T: DataType> {
     bit_width: usize,
     /// Whether this is a constant column reader (always return constant vector).
     is_const: bool,
+    /// The definition levels of the values in the current batch, only kept for a column nested
+    /// in structs or lists, whose nulls are found from them.
+    def_levels: Option<Vec<i16>>,
+    /// The repetition levels of the values in the current batch, only kept for a repeated column,
+    /// whose lists are found from them.
+    rep_levels: Option<Vec<i16>>,
+    /// The repetition levels of all the values in the current page of a repeated column, which
+    /// are decoded upfront to find how many values make a batch of rows.
+    page_rep_levels: Vec<i16>,
 
     // Options for reading Parquet
     read_options: ReadOptions,
@@ -485,6 +508,16 @@ impl<T: DataType> TypedColumnReader<T> {
     ) -> Self {
         let vector = ParquetMutableVector::new(capacity, &arrow_type);
         let bit_width = ParquetMutableVector::bit_width(&arrow_type);
+        let def_levels = if desc.path().parts().len() > 1 || desc.max_rep_level() > 0 {
+            Some(Vec::with_capacity(capacity))
+        } else {
+            None
+        };
+        let rep_levels = if desc.max_rep_level() > 0 {
+            Some(Vec::with_capacity(capacity))
+        } else {
+            None
+        };
         Self {
             desc: Arc::new(desc),
             arrow_type,
@@ -496,6 +529,9 @@ impl<T: DataType> TypedColumnReader<T> {
             capacity,
             bit_width,
             is_const: false,
+            def_levels,
+            rep_levels,
+            page_rep_levels: vec![],
             read_options,
             _phantom: PhantomData,
         }
@@ -510,7 +546,27 @@ impl<T: DataType> TypedColumnReader<T> {
     /// well as reset all of its internal states.
     #[inline]
     pub fn reset_batch(&mut self) {
-        self.vector.reset()
+        self.vector.reset();
+        if let Some(def_levels) = self.def_levels.as_mut() {
+            def_levels.clear();
+        }
+        if let Some(rep_levels) = self.rep_levels.as_mut() {
+            rep_levels.clear();
+        }
+    }
+
+    /// Returns the definition levels of the values in the current batch, if this column is nested
+    /// in structs or lists. A padded or skipped null has the definition level 0.
+    #[inline]
+    pub fn def_levels(&self) -> Option<&[i16]> {
+        self.def_levels.as_deref()
+    }
+
+    /// Returns the repetition levels of the values in the current batch, if this column is
+    /// repeated. A padded or skipped null has the repetition level 0.
+    #[inline]
+    pub fn rep_levels(&self) -> Option<&[i16]> {
+        self.rep_levels.as_deref()
     }
 
     /// Returns the current batch that's been constructed.
@@ -530,12 +586,19 @@ impl<T: DataType> TypedColumnReader<T> {
     ///
     /// If the return number of values is < `total`, it means the current page is drained and the
     /// caller should call `set_page_v1` or `set_page_v2` before calling next `read_batch`.
+    ///
+    /// For a repeated column, `total` and the returned number of values count rows instead, each
+    /// of which may have any number of values, and `null_pad_size` nulls are padded as rows.
     pub fn read_batch(&mut self, total: usize, null_pad_size: usize) -> (usize, usize) {
         debug_assert!(
             self.value_decoder.is_some() && self.def_level_decoder.is_some(),
             "set_page_v1/v2 should have been called"
         );
 
+        if self.rep_levels.is_some() {
+            return self.read_repeated_batch(total, null_pad_size);
+        }
+
         let n = ::std::cmp::min(self.num_values_in_page, total);
         self.num_values_in_page -= n;
         let value_decoder = self.value_decoder.as_mut().unwrap();
@@ -543,7 +606,15 @@ impl<T: DataType> TypedColumnReader<T> {
 
         let previous_num_nulls = self.vector.num_nulls;
         self.vector.put_nulls(null_pad_size);
-        dl_decoder.read_batch(n, &mut self.vector, value_decoder.as_mut());
+        if let Some(def_levels) = self.def_levels.as_mut() {
+            def_levels.resize(def_levels.len() + null_pad_size, 0);
+        }
+        dl_decoder.read_batch(
+            n,
+            &mut self.vector,
+            value_decoder.as_mut(),
+            self.def_levels.as_mut(),
+        );
 
         (n, self.vector.num_nulls - previous_num_nulls)
     }
@@ -553,22 +624,96 @@ impl<T: DataType> TypedColumnReader<T> {
     ///
     /// If the return value is < `total`, it means the current page is drained and the caller should
     /// call `set_page_v1` or `set_page_v2` before calling next `skip_batch`.
+    ///
+    /// For a repeated column, `total` and the returned number of values count rows instead.
     pub fn skip_batch(&mut self, total: usize, put_nulls: bool) -> usize {
         debug_assert!(
             self.value_decoder.is_some() && self.def_level_decoder.is_some(),
             "set_page_v1/v2 should have been called"
         );
 
+        if self.rep_levels.is_some() {
+            let (num_rows, n) = self.next_rows(total);
+            self.num_values_in_page -= n;
+            let value_decoder = self.value_decoder.as_mut().unwrap();
+            let dl_decoder = self.def_level_decoder.as_mut().unwrap();
+            dl_decoder.skip_batch(n, &mut self.vector, value_decoder.as_mut(), false);
+            if put_nulls {
+                self.put_null_rows(num_rows);
+            }
+            return num_rows;
+        }
+
         let n = ::std::cmp::min(self.num_values_in_page, total);
         self.num_values_in_page -= n;
         let value_decoder = self.value_decoder.as_mut().unwrap();
         let dl_decoder = self.def_level_decoder.as_mut().unwrap();
 
         dl_decoder.skip_batch(n, &mut self.vector, value_decoder.as_mut(), put_nulls);
+        if put_nulls {
+            if let Some(def_levels) = self.def_levels.as_mut() {
+                def_levels.resize(def_levels.len() + n, 0);
+            }
+        }
 
         n
     }
 
+    /// Reads a batch of at most `total` rows of a repeated column from the current page, like
+    /// `read_batch`, and keeps the definition and repetition levels of their values.
+    fn read_repeated_batch(&mut self, total: usize, null_pad_size: usize) -> (usize, usize) {
+        let (num_rows, n) = self.next_rows(total);
+        let start = self.page_rep_levels.len() - self.num_values_in_page;
+        self.num_values_in_page -= n;
+
+        let previous_num_nulls = self.vector.num_nulls;
+        self.vector.reserve(null_pad_size + n);
+        self.put_null_rows(null_pad_size);
+
+        let value_decoder = self.value_decoder.as_mut().unwrap();
+        let dl_decoder = self.def_level_decoder.as_mut().unwrap();
+        dl_decoder.read_batch(
+            n,
+            &mut self.vector,
+            value_decoder.as_mut(),
+            self.def_levels.as_mut(),
+        );
+        if let Some(rep_levels) = self.rep_levels.as_mut() {
+            rep_levels.extend_from_slice(&self.page_rep_levels[start..start + n]);
+        }
+
+        (num_rows, self.vector.num_nulls - previous_num_nulls)
+    }
+
+    /// Returns the number of rows, at most `total`, from the current position of the page of a
+    /// repeated column, and the number of their values. A row starts with a value of repetition
+    /// level 0, and doesn't span pages.
+    fn next_rows(&self, total: usize) -> (usize, usize) {
+        let start = self.page_rep_levels.len() - self.num_values_in_page;
+        let mut num_rows = 0;
+        for (i, level) in self.page_rep_levels[start..].iter().enumerate() {
+            if *level == 0 {
+                if num_rows == total {
+                    return (num_rows, i);
+                }
+                num_rows += 1;
+            }
+        }
+        (num_rows, self.num_values_in_page)
+    }
+
+    /// Appends `n` null rows, each of a single null value, to a repeated column.
+    fn put_null_rows(&mut self, n: usize) {
+        self.vector.reserve(n);
+        self.vector.put_nulls(n);
+        if let Some(def_levels) = self.def_levels.as_mut() {
+            def_levels.resize(def_levels.len() + n, 0);
+        }
+        if let Some(rep_levels) = self.rep_levels.as_mut() {
+            rep_levels.resize(rep_levels.len() + n, 0);
+        }
+    }
+
     /// Sets the dictionary page for this column reader and eagerly reads it.
     ///
     /// # Panics
@@ -637,6 +782,7 @@ impl<T: DataType> TypedColumnReader<T> {
         let bit_width = log2(self.desc.max_rep_level() as u64 + 1) as u8;
         let mut rl_decoder = LevelDecoder::new(self.desc.clone(), bit_width, true);
         let offset = rl_decoder.set_data(page_value_count, &page_buffer);
+        self.read_page_rep_levels(&mut rl_decoder, page_value_count);
         self.rep_level_decoder = Some(rl_decoder);
         page_buffer = page_buffer.slice(offset);
 
@@ -665,6 +811,7 @@ impl<T: DataType> TypedColumnReader<T> {
         let bit_width = log2(self.desc.max_rep_level() as u64 + 1) as u8;
         let mut rl_decoder = LevelDecoder::new(self.desc.clone(), bit_width, false);
         rl_decoder.set_data(page_value_count, &rep_level_data);
+        self.read_page_rep_levels(&mut rl_decoder, page_value_count);
         self.rep_level_decoder = Some(rl_decoder);
 
         let bit_width = log2(self.desc.max_def_level() as u64 + 1) as u8;
@@ -676,6 +823,14 @@ impl<T: DataType> TypedColumnReader<T> {
         self.value_decoder = Some(value_decoder);
     }
 
+    /// Decodes the repetition levels of all the values of a new page, if this column is repeated.
+    fn read_page_rep_levels(&mut self, rl_decoder: &mut LevelDecoder, page_value_count: usize) {
+        self.page_rep_levels.clear();
+        if self.rep_levels.is_some() {
+            rl_decoder.read_levels(page_value_count, &mut self.page_rep_levels);
+        }
+    }
+
     /// Sets all values in the vector of this column reader to be null.
     pub fn set_null(&mut self) {
         self.check_const("set_null");
@@ -800,10 +955,11 @@ impl<T: DataType> TypedColumnReader<T> {
     }
 
     fn check_dictionary(&mut self, encoding: &Encoding) {
-        // The column has a dictionary while the new page is of PLAIN encoding. In this case, we
-        // should eagerly decode all the dictionary indices and convert the underlying vector to a
-        // plain encoded vector.
-        if self.vector.dictionary.is_some() && *encoding == Encoding::PLAIN {
+        // The column has a dictionary while the new page is not dictionary encoded, e.g., of PLAIN
+        // or DELTA_BINARY_PACKED encoding after the writer fell back. In this case, we should
+        // eagerly decode all the dictionary indices and convert the underlying vector to a plain
+        // encoded vector.
+        if self.vector.dictionary.is_some() && *encoding != Encoding::RLE_DICTIONARY {
             let new_vector = ParquetMutableVector::new(self.capacity, &self.arrow_type);
             let old_vector = std::mem::replace(&mut self.vector, new_vector);
             T::decode_dict(old_vector, &mut self.vector, self.bit_width);
@@ -100,12 +100,14 @@ impl LevelDecoder {
     }
 
     /// Reads a batch of `total` values into `vector`. The value decoding is done by
-    /// `value_decoder`.
+    /// `value_decoder`. The definition levels of the values are appended to `def_levels` if it is
+    /// given, e.g., to find the null structs of a column nested in them.
     pub fn read_batch(
         &mut self,
         total: usize,
         vector: &mut ParquetMutableVector,
         value_decoder: &mut dyn Decoder,
+        mut def_levels: Option<&mut Vec<i16>>,
     ) {
         let mut left = total;
         while left > 0 {
@@ -127,8 +129,16 @@ impl LevelDecoder {
                     } else {
                         vector.put_nulls(n);
                     }
+                    if let Some(def_levels) = def_levels.as_mut() {
+                        let level = self.current_value as i16;
+                        def_levels.resize(def_levels.len() + n, level);
+                    }
                 }
                 Mode::BitPacked => {
+                    if let Some(def_levels) = def_levels.as_mut() {
+                        let levels = &self.current_buffer[self.current_buffer_idx..][..n];
+                        def_levels.extend(levels.iter().map(|level| *level as i16));
+                    }
                     for i in 0..n {
                         if self.current_buffer[self.current_buffer_idx + i] == max_def_level as i32
                         {
@@ -194,6 +204,31 @@ impl LevelDecoder {
         }
     }
 
+    /// Reads `total` levels into `levels` as they are, e.g., the repetition levels of a page.
+    pub fn read_levels(&mut self, total: usize, levels: &mut Vec<i16>) {
+        let mut left = total;
+        while left > 0 {
+            if unlikely(self.current_count == 0) {
+                self.read_next_group();
+            }
+
+            debug_assert!(self.current_count > 0);
+
+            let n = ::std::cmp::min(left, self.current_count);
+            match self.mode {
+                Mode::RLE => levels.resize(levels.len() + n, self.current_value as i16),
+                Mode::BitPacked => {
+                    let group = &self.current_buffer[self.current_buffer_idx..][..n];
+                    levels.extend(group.iter().map(|level| *level as i16));
+                    self.current_buffer_idx += n;
+                }
+            }
+
+            left -= n;
+            self.current_count -= n;
+        }
+    }
+
     /// Loads the next group from this RLE/BitPacked hybrid reader.
     fn read_next_group(&mut self) {
         let bit_reader = self.bit_reader.as_mut().expect("bit_reader should be set");
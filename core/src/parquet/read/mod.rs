@@ -17,6 +17,7 @@
 
 pub mod column;
 pub mod levels;
+pub mod nested;
 pub mod values;
 
 pub use column::ColumnReader;
@@ -34,6 +35,9 @@ pub(crate) const DECIMAL_BYTE_WIDTH: usize = 16;
 pub struct ReadOptions {
     // Whether to read legacy dates/timestamps as it is. If false, throw exceptions.
     pub(crate) use_legacy_date_timestamp_or_ntz: bool,
+    // Whether to check that the values of string columns are valid UTF-8. If true, throw
+    // exceptions on invalid values.
+    pub(crate) validate_utf8: bool,
 }
 
 /// Internal states for PLAIN decoder. Used in combination of `PlainDecoding`.
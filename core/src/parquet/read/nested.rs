@@ -0,0 +1,497 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use std::sync::Arc;
+
+use arrow::{
+    array::{
+        make_array, Array, ArrayData, ArrayRef, BooleanArray, ListArray, MapArray, StructArray,
+    },
+    buffer::{BooleanBuffer, NullBuffer, OffsetBuffer},
+    compute::{cast, filter},
+    datatypes::{DataType as ArrowDataType, Field},
+    error::ArrowError,
+};
+
+/// Where the values of a nested field are found in the values of a leaf column under it. The
+/// values of a field in a list, or in a struct in a list, etc., are the leaf values which start a
+/// new value of it in a defined element of the innermost list, i.e., whose repetition level is at
+/// most `rep_level`, and whose definition level is at least `def_level`. The other leaf values
+/// are repeated values of the field, or nulls and empty lists above it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Scope {
+    /// The maximum repetition level of the innermost list containing the field, if any
+    pub rep_level: i16,
+    /// The definition level of the element of the innermost list containing the field, if any
+    pub def_level: i16,
+}
+
+impl Scope {
+    /// The scope of a top-level column, whose values are all the rows.
+    pub const ROWS: Scope = Scope {
+        rep_level: 0,
+        def_level: 0,
+    };
+
+    /// Whether the leaf value of the given levels is a value of the field.
+    #[inline]
+    fn contains(&self, def_level: i16, rep_level: i16) -> bool {
+        rep_level <= self.rep_level && def_level >= self.def_level
+    }
+
+    /// Returns the definition levels of the values of the field, from the definition and
+    /// repetition levels of the values of a leaf column under it. All the values of a column
+    /// which isn't repeated are the ones of the field.
+    pub fn def_levels(&self, def_levels: &[i16], rep_levels: Option<&[i16]>) -> Vec<i16> {
+        match rep_levels {
+            Some(rep_levels) => def_levels
+                .iter()
+                .zip(rep_levels)
+                .filter(|(def, rep)| self.contains(**def, **rep))
+                .map(|(def, _)| *def)
+                .collect(),
+            None => def_levels.to_vec(),
+        }
+    }
+}
+
+/// Returns the values of a field which is the leaf column of the given array, whose values have
+/// the given definition and repetition levels. The dictionary encoded values are decoded if the
+/// column is repeated, so that they can be selected.
+pub fn leaf_array_data(
+    data: ArrayData,
+    def_levels: Option<&[i16]>,
+    rep_levels: Option<&[i16]>,
+    scope: Scope,
+) -> Result<ArrayData, ArrowError> {
+    let (Some(def_levels), Some(rep_levels)) = (def_levels, rep_levels) else {
+        return Ok(data);
+    };
+    let array = decode(make_array(data))?;
+    let predicate = BooleanArray::from(BooleanBuffer::collect_bool(array.len(), |i| {
+        scope.contains(def_levels[i], rep_levels[i])
+    }));
+    Ok(filter(&array, &predicate)?.into_data())
+}
+
+/// Reconstructs the Arrow list array of a Parquet list from the array of its elements, and the
+/// definition and repetition levels of a leaf column under it. The elements are the values of
+/// the element field, whose scope is the repeated field of the list.
+///
+/// A list is null in the values whose definition level is lower than `def_level`, the number of
+/// optional and repeated fields in the path of the list, including itself. The repeated field
+/// under it has the definition level `def_level + 1`, and the repetition level `rep_level`.
+/// A list in the 2-level layout, or a repeated field without a list, has no definition level
+/// of its own, so it is never null.
+pub fn list_array_data(
+    elements: ArrayData,
+    def_levels: &[i16],
+    rep_levels: &[i16],
+    scope: Scope,
+    def_level: i16,
+    rep_level: i16,
+) -> Result<ArrayData, ArrowError> {
+    let (offsets, nulls) = list_offsets(def_levels, rep_levels, scope, def_level, rep_level);
+    let elements = element_array(elements, &offsets, "list elements")?;
+    let field = Arc::new(Field::new("element", elements.data_type().clone(), true));
+    let array = ListArray::try_new(field, offsets, elements, Some(nulls))?;
+    Ok(array.into_data())
+}
+
+/// Reconstructs the Arrow map array of a Parquet map from the arrays of its keys and values,
+/// and the definition and repetition levels of a leaf column under it. The keys and values are
+/// the values of the fields of the repeated key-value group of the map, whose scope is the
+/// group, like the elements of a list. So the levels of the map are the ones of a list, see
+/// [`list_array_data`].
+///
+/// The keys must not be null, while the values can be.
+pub fn map_array_data(
+    keys: ArrayData,
+    values: ArrayData,
+    def_levels: &[i16],
+    rep_levels: &[i16],
+    scope: Scope,
+    def_level: i16,
+    rep_level: i16,
+) -> Result<ArrayData, ArrowError> {
+    let (offsets, nulls) = list_offsets(def_levels, rep_levels, scope, def_level, rep_level);
+    let keys = element_array(keys, &offsets, "map keys")?;
+    let values = element_array(values, &offsets, "map values")?;
+    if keys.null_count() > 0 {
+        return Err(ArrowError::InvalidArgumentError(
+            "Map keys must not be null".to_string(),
+        ));
+    }
+    let entry_fields = vec![
+        Arc::new(Field::new("key", keys.data_type().clone(), false)),
+        Arc::new(Field::new("value", values.data_type().clone(), true)),
+    ];
+    let entries = StructArray::try_new(entry_fields.into(), vec![keys, values], None)?;
+    let field = Arc::new(Field::new("entries", entries.data_type().clone(), false));
+    let array = MapArray::try_new(field, offsets, entries, Some(nulls), false)?;
+    Ok(array.into_data())
+}
+
+/// Returns the offsets and validity of the lists, or maps, selected by `scope`, from the
+/// definition and repetition levels of a leaf column under them.
+fn list_offsets(
+    def_levels: &[i16],
+    rep_levels: &[i16],
+    scope: Scope,
+    def_level: i16,
+    rep_level: i16,
+) -> (OffsetBuffer<i32>, NullBuffer) {
+    let element_scope = Scope {
+        rep_level,
+        def_level: def_level + 1,
+    };
+    let mut offsets = vec![];
+    let mut validity = vec![];
+    let mut num_elements = 0;
+    for (def, rep) in def_levels.iter().zip(rep_levels) {
+        if scope.contains(*def, *rep) {
+            offsets.push(num_elements);
+            validity.push(*def >= def_level);
+        }
+        if element_scope.contains(*def, *rep) {
+            num_elements += 1;
+        }
+    }
+    offsets.push(num_elements);
+    (
+        OffsetBuffer::new(offsets.into()),
+        NullBuffer::from(validity),
+    )
+}
+
+/// Returns the decoded array of the elements of lists with the given offsets, which must have
+/// as many values as the offsets point to.
+fn element_array(
+    data: ArrayData,
+    offsets: &OffsetBuffer<i32>,
+    name: &str,
+) -> Result<ArrayRef, ArrowError> {
+    let num_elements = *offsets.last().unwrap() as usize;
+    let array = decode(make_array(data))?;
+    if array.len() != num_elements {
+        return Err(ArrowError::InvalidArgumentError(format!(
+            "Expected {} {} from the levels, but got {}",
+            num_elements,
+            name,
+            array.len()
+        )));
+    }
+    Ok(array)
+}
+
+/// Decodes the given array if it is dictionary encoded.
+fn decode(array: ArrayRef) -> Result<ArrayRef, ArrowError> {
+    match array.data_type() {
+        ArrowDataType::Dictionary(_, value_type) => cast(&array, value_type),
+        _ => Ok(array),
+    }
+}
+
+/// Reconstructs the Arrow struct array of a Parquet group from the arrays of its fields, which
+/// are either read by the column readers of its primitive fields, or reconstructed from the
+/// fields of its nested groups.
+///
+/// A struct is null in the rows whose definition level is lower than `def_level`, the number of
+/// optional fields in the path of the group, including itself. Any leaf column under the group
+/// has the same definition levels up to it, so `def_levels` may be read by any of them. If
+/// there are none, e.g., all the fields of the group are missing in the file, all the structs
+/// are null.
+///
+/// The arrays of the fields have at least `num_rows` values, the others being ignored, and the
+/// dictionary encoded ones are decoded.
+pub fn struct_array_data(
+    fields: Vec<(String, ArrayData)>,
+    def_levels: Option<&[i16]>,
+    def_level: i16,
+    num_rows: usize,
+) -> Result<ArrayData, ArrowError> {
+    let mut struct_fields = Vec::with_capacity(fields.len());
+    let mut arrays = Vec::with_capacity(fields.len());
+    for (name, data) in fields {
+        let array = decode(make_array(data.slice(0, num_rows)))?;
+        struct_fields.push(Arc::new(Field::new(name, array.data_type().clone(), true)));
+        arrays.push(array);
+    }
+
+    let nulls = match def_levels {
+        Some(def_levels) => NullBuffer::new(BooleanBuffer::collect_bool(num_rows, |i| {
+            def_levels[i] >= def_level
+        })),
+        None => NullBuffer::new_null(num_rows),
+    };
+    let array = StructArray::try_new(struct_fields.into(), arrays, Some(nulls))?;
+    Ok(array.into_data())
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow::{
+        array::{AsArray, DictionaryArray, Int32Array, Int64Array, StringArray},
+        datatypes::{Int32Type, Int64Type},
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_nested_structs() {
+        // The rows of `a: optional group { b: optional group { c: optional int64 },
+        // d: optional string }`, whose definition levels are read by `a.b.c`:
+        //   {b: {c: 1}, d: 'x'}, {b: {c: null}, d: null}, {b: null, d: 'y'}, null
+        let c = Int64Array::from(vec![Some(1), None, None, None]);
+        let c_levels = vec![3_i16, 2, 1, 0];
+        let d = DictionaryArray::<Int32Type>::new(
+            Int32Array::from(vec![Some(0), None, Some(1), None]),
+            Arc::new(StringArray::from(vec!["x", "y"])),
+        );
+
+        let b = struct_array_data(
+            vec![("c".to_string(), c.into_data())],
+            Some(c_levels.as_slice()),
+            2,
+            4,
+        )
+        .unwrap();
+        let a = struct_array_data(
+            vec![("b".to_string(), b), ("d".to_string(), d.into_data())],
+            Some(c_levels.as_slice()),
+            1,
+            4,
+        )
+        .unwrap();
+
+        let a = StructArray::from(a);
+        assert_eq!(a.len(), 4);
+        assert_eq!(
+            (0..4).map(|i| a.is_valid(i)).collect::<Vec<_>>(),
+            vec![true, true, true, false]
+        );
+        let b = a.column(0).as_struct();
+        assert_eq!(
+            (0..4).map(|i| b.is_valid(i)).collect::<Vec<_>>(),
+            vec![true, true, false, false]
+        );
+        let c = b.column(0).as_primitive::<Int64Type>();
+        assert_eq!(
+            c.iter().collect::<Vec<_>>(),
+            vec![Some(1), None, None, None]
+        );
+        // The dictionary encoded values are decoded
+        let d = a.column(1).as_string::<i32>();
+        assert_eq!(
+            d.iter().collect::<Vec<_>>(),
+            vec![Some("x"), None, Some("y"), None]
+        );
+    }
+
+    #[test]
+    fn test_lists() {
+        // The rows of `a: optional group (LIST) { repeated group list { optional int32 element } }`
+        //   [1, null, 2], [], null, [3]
+        let values = Int32Array::from(vec![Some(1), None, Some(2), None, None, Some(3)]);
+        let def_levels = vec![3_i16, 2, 3, 1, 0, 3];
+        let rep_levels = vec![0_i16, 1, 1, 0, 0, 0];
+
+        let element_scope = Scope {
+            rep_level: 1,
+            def_level: 2,
+        };
+        let elements = leaf_array_data(
+            values.into_data(),
+            Some(def_levels.as_slice()),
+            Some(rep_levels.as_slice()),
+            element_scope,
+        )
+        .unwrap();
+        let a = list_array_data(elements, &def_levels, &rep_levels, Scope::ROWS, 1, 1).unwrap();
+
+        let a = ListArray::from(a);
+        assert_eq!(a.len(), 4);
+        assert_eq!(
+            (0..4).map(|i| a.is_valid(i)).collect::<Vec<_>>(),
+            vec![true, true, false, true]
+        );
+        assert_eq!(a.value_offsets(), &[0, 3, 3, 3, 4]);
+        assert_eq!(
+            a.values()
+                .as_primitive::<Int32Type>()
+                .iter()
+                .collect::<Vec<_>>(),
+            vec![Some(1), None, Some(2), Some(3)]
+        );
+    }
+
+    #[test]
+    fn test_nested_lists_of_structs() {
+        // The rows of `a: optional group (LIST) { repeated group list { optional group (LIST)
+        // element { repeated group list { optional group element { optional string b } } } } }`
+        //   [[{b: 'x'}, null], [], null], null, [[{b: null}]]
+        let b = DictionaryArray::<Int32Type>::new(
+            Int32Array::from(vec![Some(0), None, None, None, None, None]),
+            Arc::new(StringArray::from(vec!["x"])),
+        );
+        let def_levels = vec![6_i16, 4, 3, 2, 0, 5];
+        let rep_levels = vec![0_i16, 2, 1, 1, 0, 0];
+
+        // The structs are the elements of the inner lists
+        let struct_scope = Scope {
+            rep_level: 2,
+            def_level: 4,
+        };
+        let b_values = leaf_array_data(
+            b.into_data(),
+            Some(def_levels.as_slice()),
+            Some(rep_levels.as_slice()),
+            struct_scope,
+        )
+        .unwrap();
+        let structs = struct_array_data(
+            vec![("b".to_string(), b_values)],
+            Some(&struct_scope.def_levels(&def_levels, Some(&rep_levels))),
+            5,
+            3,
+        )
+        .unwrap();
+        let inner_scope = Scope {
+            rep_level: 1,
+            def_level: 2,
+        };
+        let inner = list_array_data(structs, &def_levels, &rep_levels, inner_scope, 3, 2).unwrap();
+        let a = list_array_data(inner, &def_levels, &rep_levels, Scope::ROWS, 1, 1).unwrap();
+
+        let a = ListArray::from(a);
+        assert_eq!(a.len(), 3);
+        assert_eq!(a.value_offsets(), &[0, 3, 3, 4]);
+        assert!(a.is_null(1));
+        let inner = a.values().as_list::<i32>();
+        assert_eq!(inner.value_offsets(), &[0, 2, 2, 2, 3]);
+        assert_eq!(
+            (0..4).map(|i| inner.is_valid(i)).collect::<Vec<_>>(),
+            vec![true, true, false, true]
+        );
+        let structs = inner.values().as_struct();
+        assert_eq!(
+            (0..3).map(|i| structs.is_valid(i)).collect::<Vec<_>>(),
+            vec![true, false, true]
+        );
+        assert_eq!(
+            structs
+                .column(0)
+                .as_string::<i32>()
+                .iter()
+                .collect::<Vec<_>>(),
+            vec![Some("x"), None, None]
+        );
+    }
+
+    #[test]
+    fn test_maps() {
+        // The rows of `a: optional group (MAP) { repeated group key_value { required binary key;
+        // optional group value { optional int32 b } } }`, whose levels are read by `value.b`:
+        //   {'x': {b: 1}, 'y': null}, {}, null, {'x': {b: null}}
+        let keys = DictionaryArray::<Int32Type>::new(
+            Int32Array::from(vec![Some(0), Some(1), None, None, Some(0)]),
+            Arc::new(StringArray::from(vec!["x", "y"])),
+        );
+        let b = Int32Array::from(vec![Some(1), None, None, None, None]);
+        let def_levels = vec![4_i16, 2, 1, 0, 3];
+        let rep_levels = vec![0_i16, 1, 0, 0, 0];
+
+        let entry_scope = Scope {
+            rep_level: 1,
+            def_level: 2,
+        };
+        let keys = leaf_array_data(
+            keys.into_data(),
+            Some(def_levels.as_slice()),
+            Some(rep_levels.as_slice()),
+            entry_scope,
+        )
+        .unwrap();
+        let b_values = leaf_array_data(
+            b.into_data(),
+            Some(def_levels.as_slice()),
+            Some(rep_levels.as_slice()),
+            entry_scope,
+        )
+        .unwrap();
+        let values = struct_array_data(
+            vec![("b".to_string(), b_values)],
+            Some(&entry_scope.def_levels(&def_levels, Some(&rep_levels))),
+            3,
+            3,
+        )
+        .unwrap();
+        let a = map_array_data(keys, values, &def_levels, &rep_levels, Scope::ROWS, 1, 1).unwrap();
+
+        let a = MapArray::from(a);
+        assert_eq!(a.len(), 4);
+        assert_eq!(
+            (0..4).map(|i| a.is_valid(i)).collect::<Vec<_>>(),
+            vec![true, true, false, true]
+        );
+        assert_eq!(a.value_offsets(), &[0, 2, 2, 2, 3]);
+        assert_eq!(
+            a.keys().as_string::<i32>().iter().collect::<Vec<_>>(),
+            vec![Some("x"), Some("y"), Some("x")]
+        );
+        let values = a.values().as_struct();
+        assert_eq!(
+            (0..3).map(|i| values.is_valid(i)).collect::<Vec<_>>(),
+            vec![true, false, true]
+        );
+        assert_eq!(
+            values
+                .column(0)
+                .as_primitive::<Int32Type>()
+                .iter()
+                .collect::<Vec<_>>(),
+            vec![Some(1), None, None]
+        );
+
+        // The keys must not be null
+        let keys = StringArray::from(vec![None::<&str>]);
+        let values = Int32Array::from(vec![Some(1)]);
+        assert!(map_array_data(
+            keys.into_data(),
+            values.into_data(),
+            &[2],
+            &[0],
+            Scope::ROWS,
+            1,
+            1
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_missing_fields() {
+        // Without any field read from the file, all the structs are null, and the values of
+        // the constant fields beyond the rows of the batch are ignored
+        let missing = Int32Array::from(vec![None; 8]);
+        let a =
+            struct_array_data(vec![("b".to_string(), missing.into_data())], None, 1, 3).unwrap();
+        let a = StructArray::from(a);
+        assert_eq!(a.len(), 3);
+        assert_eq!(a.null_count(), 3);
+        assert_eq!(a.column(0).len(), 3);
+    }
+}
@@ -15,12 +15,15 @@
 // specific language governing permissions and limitations
 // under the License.
 
-use std::{marker::PhantomData, mem, ptr::copy_nonoverlapping};
+use std::{cmp, marker::PhantomData, mem, ptr::copy_nonoverlapping};
 
 use arrow::buffer::Buffer;
 use bytes::Buf;
 use log::debug;
-use parquet::{basic::Encoding, schema::types::ColumnDescPtr};
+use parquet::{
+    basic::{Encoding, Type as PhysicalType},
+    schema::types::ColumnDescPtr,
+};
 
 use super::{PlainDecoderInner, PlainDecoding, PlainDictDecoding, ReadOptions};
 use crate::{
@@ -45,6 +48,14 @@ pub fn get_decoder<T: DataType>(
         )),
         // This is for dictionary indices
         Encoding::RLE_DICTIONARY => Box::new(DictDecoder::new(value_data, num_values)),
+        Encoding::DELTA_BINARY_PACKED => Box::new(DeltaBinaryPackedDecoder::<T>::new(
+            value_data,
+            desc,
+            read_options,
+        )),
+        Encoding::DELTA_LENGTH_BYTE_ARRAY | Encoding::DELTA_BYTE_ARRAY => Box::new(
+            DeltaByteArrayDecoder::<T>::new(value_data, encoding, desc, read_options),
+        ),
         _ => panic!("Unsupported encoding: {}", encoding),
     };
     decoder
@@ -96,6 +107,46 @@ const MICROS_PER_MILLIS: i64 = 1000;
 
 const MICROS_PER_DAY: i64 = 24_i64 * 60 * 60 * 1000 * 1000;
 
+/// Converts a `TIMESTAMP_MILLIS` value to micro-seconds like Spark's
+/// `DateTimeUtils.millisToMicros`, which fails instead of wrapping around on overflow.
+///
+/// # Panics
+///
+/// If the value overflows, like the other errors of the decoders.
+#[inline]
+fn millis_to_micros(millis: i64) -> i64 {
+    millis.checked_mul(MICROS_PER_MILLIS).unwrap_or_else(|| {
+        panic!(
+            "long overflow: timestamp value {} in milli-seconds is out of the range of \
+             micro-seconds",
+            millis
+        )
+    })
+}
+
+/// Converts an `INT96` value, i.e., the nano-seconds of a Julian day, to micro-seconds like
+/// Spark's `DateTimeUtils.fromJulianDay`. The timestamps of all the units are converted with
+/// overflow checks, so this fails like [`millis_to_micros`] if the value overflows, where
+/// `fromJulianDay` doesn't check it. Such a day is millions of years away from any timestamp
+/// Spark can write.
+///
+/// # Panics
+///
+/// If the value overflows, like the other errors of the decoders.
+#[inline]
+fn julian_day_to_micros(day: i32, nanos: i64) -> i64 {
+    (day as i64 - JULIAN_DAY_OF_EPOCH as i64)
+        .checked_mul(MICROS_PER_DAY)
+        .and_then(|micros| micros.checked_add(nanos / 1000))
+        .unwrap_or_else(|| {
+            panic!(
+                "long overflow: INT96 timestamp value of Julian day {} and {} nano-seconds is out \
+                 of the range of micro-seconds",
+                day, nanos
+            )
+        })
+}
+
 pub struct PlainDecoder<T: DataType> {
     /// Internal states for this decoder.
     inner: PlainDecoderInner,
@@ -316,7 +367,7 @@ impl PlainDecoding for Int64TimestampMillisType {
                 unsafe {
                     let v = &src_data[offset..offset + byte_width] as *const [u8] as *const u8
                         as *const i64;
-                    let v = v.read_unaligned() * MICROS_PER_MILLIS;
+                    let v = millis_to_micros(v.read_unaligned());
 
                     // TODO: optimize this further as checking value one by one is not very
                     // efficient
@@ -341,7 +392,7 @@ impl PlainDecoding for Int64TimestampMillisType {
             for _ in 0..num {
                 let v = &src_data[offset..offset + byte_width] as *const [u8] as *const u8
                     as *const i64;
-                let v = v.read_unaligned() * MICROS_PER_MILLIS;
+                let v = millis_to_micros(v.read_unaligned());
                 bit::memcpy_value(&v, byte_width, &mut dst.value_buffer[dst_offset..]);
                 offset += byte_width;
                 dst_offset += byte_width;
@@ -504,7 +555,7 @@ make_int_variant_impl!(UInt32Type, u32, 8);
 
 // Shared implementation for variants of Binary type
 macro_rules! make_plain_binary_impl {
-    ($($ty: ident), *) => {
+    ($($ty: ident; $is_string: expr), *) => {
         $(
             impl PlainDecoding for $ty {
                 fn decode(src: &mut PlainDecoderInner, dst: &mut ParquetMutableVector, num: usize) {
@@ -551,6 +602,10 @@ macro_rules! make_plain_binary_impl {
 
                     src.offset = src_offset;
                     child.num_values = value_offset;
+
+                    if $is_string && src.read_options.validate_utf8 {
+                        check_utf8(dst, num, &src.desc);
+                    }
                 }
 
                 fn skip(src: &mut PlainDecoderInner, num: usize) {
@@ -570,7 +625,30 @@ macro_rules! make_plain_binary_impl {
     };
 }
 
-make_plain_binary_impl! { ByteArrayType, StringType }
+make_plain_binary_impl! { ByteArrayType; false, StringType; true }
+
+/// Panics if any of the `num` string values just decoded into `dst` is not valid UTF-8.
+///
+/// Instead of validating the values one by one, the bytes of all the values are validated at once,
+/// which is vectorized, and then the values are valid if each of them starts at a character
+/// boundary of the validated bytes.
+fn check_utf8(dst: &ParquetMutableVector, num: usize, desc: &ColumnDescPtr) {
+    let offsets = &dst.value_buffer[dst.num_values * 4..(dst.num_values + num + 1) * 4];
+    let offset = |i: usize| read_num_bytes!(i32, 4, &offsets[i * 4..]) as usize;
+    let start = offset(0);
+    let bytes = &dst.children[0].value_buffer[start..offset(num)];
+
+    let valid = match std::str::from_utf8(bytes) {
+        Ok(s) => (1..num).all(|i| s.is_char_boundary(offset(i) - start)),
+        Err(_) => false,
+    };
+    if unlikely(!valid) {
+        panic!(
+            "Encountered invalid UTF-8 string when reading Parquet column {}",
+            desc.path()
+        );
+    }
+}
 
 macro_rules! make_plain_dict_binary_impl {
     ($($ty: ident), *) => {
@@ -770,9 +848,7 @@ impl PlainDecoding for Int96TimestampMicrosType {
 
                 // TODO: optimize this further as checking value one by one is not very efficient
                 unsafe {
-                    let micros = (day.read_unaligned() - JULIAN_DAY_OF_EPOCH) as i64
-                        * MICROS_PER_DAY
-                        + nanos.read_unaligned() / 1000;
+                    let micros = julian_day_to_micros(day.read_unaligned(), nanos.read_unaligned());
 
                     if unlikely(micros < JULIAN_GREGORIAN_SWITCH_OFF_TS) {
                         panic!(
@@ -797,8 +873,7 @@ impl PlainDecoding for Int96TimestampMicrosType {
                 let nanos = &v[..INT96_DST_BYTE_WIDTH] as *const [u8] as *const u8 as *const i64;
                 let day = &v[INT96_DST_BYTE_WIDTH..] as *const [u8] as *const u8 as *const i32;
 
-                let micros = (day.read_unaligned() - JULIAN_DAY_OF_EPOCH) as i64 * MICROS_PER_DAY
-                    + nanos.read_unaligned() / 1000;
+                let micros = julian_day_to_micros(day.read_unaligned(), nanos.read_unaligned());
 
                 bit::memcpy_value(
                     &micros,
@@ -864,6 +939,280 @@ impl<T: DataType> Decoder for PlainDecoder<T> {
     }
 }
 
+/// A decoder for `INT32` and `INT64` values encoded with DELTA_BINARY_PACKED encoding.
+///
+/// The page is decoded upfront, a miniblock of bit-packed deltas at a time, into PLAIN encoded
+/// values, which are then read by a [`PlainDecoder`] so that all the conversions from the Parquet
+/// physical types to Arrow, e.g., for dates, timestamps and decimals, apply as is.
+pub struct DeltaBinaryPackedDecoder<T: DataType> {
+    /// The decoder of the decoded PLAIN values
+    inner: PlainDecoder<T>,
+}
+
+impl<T: DataType> DeltaBinaryPackedDecoder<T> {
+    pub fn new(value_data: Buffer, desc: ColumnDescPtr, read_options: ReadOptions) -> Self {
+        let (values, _) = decode_delta_binary_packed(value_data);
+        let (data, num_values) = match desc.physical_type() {
+            PhysicalType::INT32 => {
+                let data: Vec<u8> = values
+                    .iter()
+                    .flat_map(|v| (*v as i32).to_le_bytes())
+                    .collect();
+                (data, values.len())
+            }
+            PhysicalType::INT64 => {
+                let data: Vec<u8> = values.iter().flat_map(|v| v.to_le_bytes()).collect();
+                (data, values.len())
+            }
+            physical_type => panic!(
+                "Unsupported physical type for DELTA_BINARY_PACKED encoding: {}",
+                physical_type
+            ),
+        };
+        Self {
+            inner: PlainDecoder::new(Buffer::from(data), num_values, desc, read_options),
+        }
+    }
+}
+
+/// Decodes all the values of a DELTA_BINARY_PACKED encoded page, which is made of a header and
+/// blocks of bit-packed deltas:
+///
+/// ```text
+/// header: <block size> <number of miniblocks in a block> <total value count> <first value>
+/// block:  <min delta> <bit widths of the miniblocks> <miniblocks>
+/// ```
+///
+/// The deltas are added with wrapping arithmetic, which yields the right `INT32` values once
+/// truncated, as the writer computes the deltas of `INT32` values as 32-bit integers.
+///
+/// Returns the values and the offset of the end of the encoded values in `data`, i.e., of the
+/// padded last miniblock, where the encodings embedding DELTA_BINARY_PACKED lengths continue.
+fn decode_delta_binary_packed(data: Buffer) -> (Vec<i64>, usize) {
+    let len = data.len();
+    let mut bit_reader = BitReader::new_all(data);
+    let mut read_header = || {
+        bit_reader
+            .get_vlq_int()
+            .expect("Not enough data to decode DELTA_BINARY_PACKED header")
+    };
+    let block_size = read_header() as usize;
+    let num_miniblocks = read_header() as usize;
+    let total_count = read_header() as usize;
+    let first_value = bit_reader
+        .get_zigzag_vlq_int()
+        .expect("Not enough data to decode DELTA_BINARY_PACKED header");
+    assert!(
+        num_miniblocks > 0 && block_size % (num_miniblocks * 32) == 0,
+        "Invalid DELTA_BINARY_PACKED block size {} with {} miniblocks",
+        block_size,
+        num_miniblocks
+    );
+    let miniblock_size = block_size / num_miniblocks;
+
+    let mut values = Vec::with_capacity(total_count);
+    let mut end = bit_reader.get_byte_offset();
+    if total_count == 0 {
+        return (values, end);
+    }
+    values.push(first_value);
+
+    let mut last_value = first_value;
+    let mut bit_widths = vec![0u8; num_miniblocks];
+    let mut packed_deltas = vec![0u32; miniblock_size];
+    while values.len() < total_count {
+        let min_delta = bit_reader
+            .get_zigzag_vlq_int()
+            .expect("Not enough data to decode DELTA_BINARY_PACKED block");
+        for bit_width in bit_widths.iter_mut() {
+            *bit_width = bit_reader
+                .get_aligned::<u8>(1)
+                .expect("Not enough data to decode DELTA_BINARY_PACKED block");
+        }
+
+        for bit_width in bit_widths.iter().map(|w| *w as usize) {
+            let remaining = total_count - values.len();
+            if remaining == 0 {
+                break;
+            }
+            // The last miniblock may be padded, in which case only its remaining values are read
+            let num = remaining.min(miniblock_size);
+            end = cmp::min(
+                len,
+                bit_reader.get_byte_offset() + miniblock_size * bit_width / 8,
+            );
+            if bit_width <= 32 {
+                let n = bit_reader.get_batch(&mut packed_deltas[..num], bit_width);
+                assert_eq!(
+                    n, num,
+                    "Not enough data to decode DELTA_BINARY_PACKED miniblock"
+                );
+                for delta in &packed_deltas[..num] {
+                    last_value = last_value
+                        .wrapping_add(min_delta)
+                        .wrapping_add(*delta as i64);
+                    values.push(last_value);
+                }
+            } else {
+                for _ in 0..num {
+                    let delta: u64 = bit_reader
+                        .get_value(bit_width)
+                        .expect("Not enough data to decode DELTA_BINARY_PACKED miniblock");
+                    last_value = last_value
+                        .wrapping_add(min_delta)
+                        .wrapping_add(delta as i64);
+                    values.push(last_value);
+                }
+            }
+        }
+    }
+    (values, end)
+}
+
+impl<T: DataType> Decoder for DeltaBinaryPackedDecoder<T> {
+    #[inline]
+    fn read(&mut self, dst: &mut ParquetMutableVector) {
+        self.inner.read(dst)
+    }
+
+    #[inline]
+    fn read_batch(&mut self, dst: &mut ParquetMutableVector, num: usize) {
+        self.inner.read_batch(dst, num)
+    }
+
+    #[inline]
+    fn skip_batch(&mut self, num: usize) {
+        self.inner.skip_batch(num)
+    }
+
+    #[inline]
+    fn encoding(&self) -> Encoding {
+        Encoding::DELTA_BINARY_PACKED
+    }
+}
+
+/// A decoder for `BYTE_ARRAY` and `FIXED_LEN_BYTE_ARRAY` values encoded with
+/// DELTA_LENGTH_BYTE_ARRAY or DELTA_BYTE_ARRAY encoding.
+///
+/// Like [`DeltaBinaryPackedDecoder`], the page is decoded upfront into PLAIN encoded values, so
+/// that the [`PlainDecoder`] reading them also validates the UTF-8 strings as usual.
+pub struct DeltaByteArrayDecoder<T: DataType> {
+    /// The decoder of the decoded PLAIN values
+    inner: PlainDecoder<T>,
+
+    /// Either DELTA_LENGTH_BYTE_ARRAY or DELTA_BYTE_ARRAY
+    encoding: Encoding,
+}
+
+impl<T: DataType> DeltaByteArrayDecoder<T> {
+    pub fn new(
+        value_data: Buffer,
+        encoding: Encoding,
+        desc: ColumnDescPtr,
+        read_options: ReadOptions,
+    ) -> Self {
+        // PLAIN `FIXED_LEN_BYTE_ARRAY` values are not prefixed with their length
+        let fixed_len = match desc.physical_type() {
+            PhysicalType::BYTE_ARRAY => None,
+            PhysicalType::FIXED_LEN_BYTE_ARRAY => Some(desc.type_length() as usize),
+            physical_type => panic!(
+                "Unsupported physical type for {} encoding: {}",
+                encoding, physical_type
+            ),
+        };
+        let mut data = Vec::with_capacity(value_data.len());
+        let mut num_values = 0;
+        decode_delta_byte_arrays(&value_data, encoding, |value| {
+            match fixed_len {
+                Some(len) => assert_eq!(
+                    value.len(),
+                    len,
+                    "Invalid length of FIXED_LEN_BYTE_ARRAY value"
+                ),
+                None => data.extend_from_slice(&(value.len() as u32).to_le_bytes()),
+            }
+            data.extend_from_slice(value);
+            num_values += 1;
+        });
+        Self {
+            inner: PlainDecoder::new(Buffer::from(data), num_values, desc, read_options),
+            encoding,
+        }
+    }
+}
+
+/// Decodes all the byte arrays of a DELTA_LENGTH_BYTE_ARRAY or DELTA_BYTE_ARRAY encoded page, and
+/// passes them to `f` in order.
+fn decode_delta_byte_arrays(data: &Buffer, encoding: Encoding, f: impl FnMut(&[u8])) {
+    match encoding {
+        Encoding::DELTA_LENGTH_BYTE_ARRAY => decode_delta_length_byte_array(data, f),
+        Encoding::DELTA_BYTE_ARRAY => decode_delta_byte_array(data, f),
+        _ => panic!("Unsupported encoding for byte arrays: {}", encoding),
+    }
+}
+
+/// Decodes a DELTA_LENGTH_BYTE_ARRAY encoded page, which is made of the lengths of the byte arrays
+/// encoded with DELTA_BINARY_PACKED, followed by the concatenated byte arrays.
+fn decode_delta_length_byte_array(data: &Buffer, mut f: impl FnMut(&[u8])) {
+    let (lengths, offset) = decode_delta_binary_packed(data.clone());
+    let mut bytes = &data.as_slice()[offset..];
+    for len in lengths {
+        assert!(
+            len >= 0 && len as usize <= bytes.len(),
+            "Not enough data to decode DELTA_LENGTH_BYTE_ARRAY value of length {}",
+            len
+        );
+        let (value, rest) = bytes.split_at(len as usize);
+        f(value);
+        bytes = rest;
+    }
+}
+
+/// Decodes a DELTA_BYTE_ARRAY encoded page, a.k.a. incremental encoding, which is made of the
+/// lengths of the prefixes shared with the previous byte arrays encoded with DELTA_BINARY_PACKED,
+/// followed by the remaining suffixes encoded with DELTA_LENGTH_BYTE_ARRAY.
+fn decode_delta_byte_array(data: &Buffer, mut f: impl FnMut(&[u8])) {
+    let (prefix_lengths, offset) = decode_delta_binary_packed(data.clone());
+    let mut prefix_lengths = prefix_lengths.into_iter();
+    let mut value = Vec::new();
+    decode_delta_length_byte_array(&data.slice(offset), |suffix| {
+        let prefix_len = prefix_lengths
+            .next()
+            .expect("Not enough prefix lengths to decode DELTA_BYTE_ARRAY values");
+        assert!(
+            prefix_len >= 0 && prefix_len as usize <= value.len(),
+            "Invalid DELTA_BYTE_ARRAY prefix length {} of a value after one of length {}",
+            prefix_len,
+            value.len()
+        );
+        value.truncate(prefix_len as usize);
+        value.extend_from_slice(suffix);
+        f(value.as_slice());
+    });
+}
+
+impl<T: DataType> Decoder for DeltaByteArrayDecoder<T> {
+    #[inline]
+    fn read(&mut self, dst: &mut ParquetMutableVector) {
+        self.inner.read(dst)
+    }
+
+    #[inline]
+    fn read_batch(&mut self, dst: &mut ParquetMutableVector, num: usize) {
+        self.inner.read_batch(dst, num)
+    }
+
+    #[inline]
+    fn skip_batch(&mut self, num: usize) {
+        self.inner.skip_batch(num)
+    }
+
+    #[inline]
+    fn encoding(&self) -> Encoding {
+        self.encoding
+    }
+}
+
 /// A decoder for Parquet dictionary indices, which is always of integer type, and encoded with
 /// RLE/BitPacked encoding.
 pub struct DictDecoder {
@@ -1006,3 +1355,129 @@ impl Decoder for DictDecoder {
         Encoding::RLE_DICTIONARY
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use parquet::{
+        data_type::{
+            ByteArray, ByteArrayType as ParquetByteArrayType, Int32Type as ParquetInt32Type,
+            Int64Type as ParquetInt64Type,
+        },
+        encodings::encoding::get_encoder,
+    };
+
+    use super::*;
+
+    fn encode<T: parquet::data_type::DataType>(encoding: Encoding, values: &[T::T]) -> Buffer {
+        let mut encoder = get_encoder::<T>(encoding).unwrap();
+        encoder.put(values).unwrap();
+        Buffer::from(encoder.flush_buffer().unwrap().to_vec())
+    }
+
+    /// Decodes DELTA_BINARY_PACKED values, checking that all the encoded bytes are consumed.
+    fn decode_delta(data: Buffer) -> Vec<i64> {
+        let len = data.len();
+        let (values, end) = decode_delta_binary_packed(data);
+        assert_eq!(end, len);
+        values
+    }
+
+    #[test]
+    fn test_timestamp_conversion() {
+        assert_eq!(millis_to_micros(-1), -1000);
+        assert_eq!(millis_to_micros(i64::MAX / 1000), i64::MAX / 1000 * 1000);
+
+        assert_eq!(
+            julian_day_to_micros(JULIAN_DAY_OF_EPOCH + 1, 1500),
+            MICROS_PER_DAY + 1
+        );
+        assert_eq!(
+            julian_day_to_micros(JULIAN_DAY_OF_EPOCH - 1, 0),
+            -MICROS_PER_DAY
+        );
+        let max_day = (i64::MAX / MICROS_PER_DAY) as i32 + JULIAN_DAY_OF_EPOCH;
+        assert_eq!(
+            julian_day_to_micros(max_day, 0),
+            i64::MAX / MICROS_PER_DAY * MICROS_PER_DAY
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "long overflow")]
+    fn test_millis_overflow() {
+        millis_to_micros(i64::MAX / 1000 + 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "long overflow")]
+    fn test_julian_day_overflow() {
+        julian_day_to_micros(i32::MIN, 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "long overflow")]
+    fn test_julian_day_nanos_overflow() {
+        // The day fits in micro-seconds, but not with the nano-seconds
+        let max_day = (i64::MAX / MICROS_PER_DAY) as i32 + JULIAN_DAY_OF_EPOCH;
+        julian_day_to_micros(max_day, i64::MAX);
+    }
+
+    #[test]
+    fn test_decode_delta_binary_packed() {
+        // Multiple blocks, with a padded last miniblock and deltas overflowing 32-bit integers
+        let mut ints: Vec<i32> = (0..300).map(|i| (i * 7919) % 1000 - 500).collect();
+        ints.extend([i32::MAX, i32::MIN, 0, i32::MIN, i32::MAX]);
+        let decoded = decode_delta(encode::<ParquetInt32Type>(
+            Encoding::DELTA_BINARY_PACKED,
+            &ints,
+        ));
+        assert_eq!(decoded.iter().map(|v| *v as i32).collect::<Vec<_>>(), ints);
+
+        // Deltas wider than 32 bits
+        let mut longs: Vec<i64> = (0..200).map(|i| i * (1 << 40) - 7).collect();
+        longs.extend([i64::MAX, i64::MIN, 1, i64::MIN]);
+        let decoded = decode_delta(encode::<ParquetInt64Type>(
+            Encoding::DELTA_BINARY_PACKED,
+            &longs,
+        ));
+        assert_eq!(decoded, longs);
+
+        assert_eq!(
+            decode_delta(encode::<ParquetInt64Type>(
+                Encoding::DELTA_BINARY_PACKED,
+                &[42]
+            )),
+            vec![42]
+        );
+        assert!(decode_delta(encode::<ParquetInt64Type>(
+            Encoding::DELTA_BINARY_PACKED,
+            &[]
+        ))
+        .is_empty());
+    }
+
+    #[test]
+    fn test_decode_delta_byte_arrays() {
+        // Shared prefixes of various lengths, empty values and values longer than the previous ones
+        let values: Vec<String> = (0..500)
+            .map(|i| match i % 7 {
+                0 => String::new(),
+                1 => format!("prefix/{}", i / 7),
+                _ => format!("prefix/{}/{}", i / 7, "x".repeat(i % 13)),
+            })
+            .collect();
+        let byte_arrays: Vec<ByteArray> = values.iter().map(|v| v.as_str().into()).collect();
+
+        for encoding in [
+            Encoding::DELTA_LENGTH_BYTE_ARRAY,
+            Encoding::DELTA_BYTE_ARRAY,
+        ] {
+            let data = encode::<ParquetByteArrayType>(encoding, &byte_arrays);
+            let mut decoded = vec![];
+            decode_delta_byte_arrays(&data, encoding, |value| {
+                decoded.push(String::from_utf8(value.to_vec()).unwrap())
+            });
+            assert_eq!(decoded, values, "{}", encoding);
+        }
+    }
+}
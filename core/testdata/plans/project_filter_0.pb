@@ -0,0 +1,13 @@
+
+8
+
+
+(
+&#
+
+Z
+ 
+Z&
+"
+ Z"(
+
\ No newline at end of file
@@ -0,0 +1,39 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one
+ * or more contributor license agreements.  See the NOTICE file
+ * distributed with this work for additional information
+ * regarding copyright ownership.  The ASF licenses this file
+ * to you under the Apache License, Version 2.0 (the
+ * "License"); you may not use this file except in compliance
+ * with the License.  You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+ * KIND, either express or implied.  See the License for the
+ * specific language governing permissions and limitations
+ * under the License.
+ */
+
+package org.apache.comet;
+
+import java.io.IOException;
+
+/**
+ * The destination of the output partitions of a native shuffle writer, instead of the local
+ * shuffle files, e.g., the client of a remote shuffle service such as Celeborn or Uniffle, which
+ * forwards the blocks to the service. This is called by native code through JNI, from the thread
+ * of the Spark task running the native shuffle writer, so that implementations can use {@code
+ * TaskContext.get()} and don't need to be thread-safe.
+ */
+public interface CometShuffleBlockSink {
+  /**
+   * Writes a block of the given partition, which is the Arrow IPC streams of the partition
+   * compressed with the shuffle codec. The partitions are written in ascending order of their ids,
+   * and a large partition is written in several blocks, which must be concatenated in order to be
+   * read, as they may split an Arrow IPC stream. The block is not reused by the caller.
+   */
+  void writeBlock(int partitionId, byte[] block) throws IOException;
+}
@@ -19,6 +19,10 @@
 
 package org.apache.parquet.filter2.predicate;
 
+import java.lang.reflect.InvocationTargetException;
+import java.lang.reflect.Method;
+import java.util.Set;
+
 import org.apache.parquet.filter2.predicate.Operators.*;
 import org.apache.parquet.hadoop.metadata.ColumnPath;
 
@@ -28,6 +32,17 @@ import org.apache.parquet.hadoop.metadata.ColumnPath;
  * <p>TODO: find a way to remove this duplication
  */
 public final class SparkFilterApi {
+  /** `FilterApi.in`, or null if the Parquet in the classpath is older than 1.13. */
+  private static final Method IN_METHOD = findInMethod();
+
+  private static Method findInMethod() {
+    try {
+      return FilterApi.class.getMethod("in", Column.class, Set.class);
+    } catch (NoSuchMethodException e) {
+      return null;
+    }
+  }
+
   public static IntColumn intColumn(String[] path) {
     return new IntColumn(ColumnPath.get(path));
   }
@@ -51,4 +66,24 @@ public final class SparkFilterApi {
   public static BinaryColumn binaryColumn(String[] path) {
     return new BinaryColumn(ColumnPath.get(path));
   }
+
+  /** Whether Parquet's `in` predicate is supported, i.e., from Parquet 1.13. */
+  public static boolean isInSupported() {
+    return IN_METHOD != null;
+  }
+
+  /**
+   * Calls `FilterApi.in`, which is called by reflection so that this compiles with Parquet 1.12
+   * of Spark 3.2 and 3.3. Callers must check {@link #isInSupported()} first.
+   */
+  public static <T extends Comparable<T>> FilterPredicate in(Column<T> column, Set<T> values) {
+    if (IN_METHOD == null) {
+      throw new UnsupportedOperationException("In predicate requires Parquet 1.13 or later");
+    }
+    try {
+      return (FilterPredicate) IN_METHOD.invoke(null, column, values);
+    } catch (IllegalAccessException | InvocationTargetException e) {
+      throw new IllegalStateException("Failed to create In predicate", e);
+    }
+  }
 }
@@ -35,11 +35,13 @@ public class CometTaskMemoryManager {
 
   private final TaskMemoryManager internal;
   private final NativeMemoryConsumer nativeMemoryConsumer;
+  private final ShuffleMemoryConsumer shuffleMemoryConsumer;
 
   public CometTaskMemoryManager(long id) {
     this.id = id;
     this.internal = TaskContext$.MODULE$.get().taskMemoryManager();
     this.nativeMemoryConsumer = new NativeMemoryConsumer();
+    this.shuffleMemoryConsumer = new ShuffleMemoryConsumer();
   }
 
   // Called by Comet native through JNI.
@@ -53,6 +55,22 @@ public class CometTaskMemoryManager {
     internal.releaseExecutionMemory(size, nativeMemoryConsumer);
   }
 
+  // Called by Comet native shuffle writers through JNI.
+  // Returns the actual amount of memory (in bytes) granted, which is 0 if Spark has asked the
+  // shuffle writers to spill since the last call.
+  public long acquireShuffleMemory(long size) {
+    if (shuffleMemoryConsumer.spillRequested) {
+      shuffleMemoryConsumer.spillRequested = false;
+      return 0;
+    }
+    return shuffleMemoryConsumer.acquireMemory(size);
+  }
+
+  // Called by Comet native shuffle writers through JNI
+  public void releaseShuffleMemory(long size) {
+    shuffleMemoryConsumer.freeMemory(size);
+  }
+
   /**
    * A dummy memory consumer that does nothing when spilling. At the moment, Comet native doesn't
    * share the same API as Spark and cannot trigger spill when acquire memory. Therefore, when
@@ -74,4 +92,39 @@ public class CometTaskMemoryManager {
       return String.format("NativeMemoryConsumer(id=%)", id);
     }
   }
+
+  /**
+   * The memory consumer of the buffers of Comet native shuffle writers, in the memory mode of the
+   * task so that the buffers are accounted against Spark's execution memory even if off-heap
+   * memory is not enabled.
+   *
+   * <p>The native shuffle writers cannot spill while Spark is acquiring memory for another
+   * consumer, e.g., a JVM operator of the same task, since they may be running the native plan
+   * which the other consumer is part of. Instead, a spill request fails their next memory
+   * acquisition, and they spill all their buffers before acquiring the memory again.
+   */
+  private class ShuffleMemoryConsumer extends MemoryConsumer {
+    private volatile boolean spillRequested = false;
+
+    protected ShuffleMemoryConsumer() {
+      super(
+          CometTaskMemoryManager.this.internal,
+          CometTaskMemoryManager.this.internal.pageSizeBytes(),
+          CometTaskMemoryManager.this.internal.getTungstenMemoryMode());
+    }
+
+    @Override
+    public long spill(long size, MemoryConsumer trigger) throws IOException {
+      // The shuffle writers spill by themselves if they cannot acquire memory
+      if (trigger != this && getUsed() > 0) {
+        spillRequested = true;
+      }
+      return 0;
+    }
+
+    @Override
+    public String toString() {
+      return String.format("ShuffleMemoryConsumer(id=%d)", id);
+    }
+  }
 }
@@ -21,24 +21,30 @@ package org.apache.spark.sql.comet;
 
 import java.util.HashMap;
 
-import org.apache.spark.sql.execution.ScalarSubquery;
+import org.apache.spark.sql.execution.ExecSubqueryExpression;
+import org.apache.spark.sql.execution.InSubqueryExec;
 import org.apache.spark.sql.types.Decimal;
 import org.apache.spark.unsafe.types.UTF8String;
 
 import org.apache.comet.CometRuntimeException;
+import org.apache.comet.serde.QueryPlanSerde;
 
-/** A helper class to execute scalar subqueries and retrieve subquery results from native code. */
+/**
+ * A helper class to execute scalar and IN subqueries and retrieve subquery results from native
+ * code.
+ */
 public class CometScalarSubquery {
   /**
-   * A map from (planId, subqueryId) to the corresponding ScalarSubquery. We cannot simply use
-   * `subqueryId` because same query plan may be executed multiple times in same executor (i.e., JVM
-   * instance). For such cases, if we delete the ScalarSubquery from the map after the first
-   * execution, the second execution will fail to find the ScalarSubquery if the native code is
+   * A map from (planId, subqueryId) to the corresponding ScalarSubquery or InSubqueryExec. We
+   * cannot simply use `subqueryId` because same query plan may be executed multiple times in same
+   * executor (i.e., JVM instance). For such cases, if we delete the subquery from the map after
+   * the first execution, the second execution will fail to find the subquery if the native code is
    * still running.
    */
-  private static final HashMap<Long, HashMap<Long, ScalarSubquery>> subqueryMap = new HashMap<>();
+  private static final HashMap<Long, HashMap<Long, ExecSubqueryExpression>> subqueryMap =
+      new HashMap<>();
 
-  public static synchronized void setSubquery(long planId, ScalarSubquery subquery) {
+  public static synchronized void setSubquery(long planId, ExecSubqueryExpression subquery) {
     if (!subqueryMap.containsKey(planId)) {
       subqueryMap.put(planId, new HashMap<>());
     }
@@ -46,7 +52,7 @@ public class CometScalarSubquery {
     subqueryMap.get(planId).put(subquery.exprId().id(), subquery);
   }
 
-  public static synchronized void removeSubquery(long planId, ScalarSubquery subquery) {
+  public static synchronized void removeSubquery(long planId, ExecSubqueryExpression subquery) {
     if (subqueryMap.containsKey(planId)) {
       subqueryMap.get(planId).remove(subquery.exprId().id());
 
@@ -56,13 +62,17 @@ public class CometScalarSubquery {
     }
   }
 
-  /** Retrieve the result of subquery. */
-  private static Object getSubquery(Long planId, Long id) {
+  private static ExecSubqueryExpression lookupSubquery(Long planId, Long id) {
     if (!subqueryMap.containsKey(planId)) {
       throw new CometRuntimeException("Subquery " + id + " not found for plan " + planId + ".");
     }
 
-    return subqueryMap.get(planId).get(id).eval(null);
+    return subqueryMap.get(planId).get(id);
+  }
+
+  /** Retrieve the result of subquery. */
+  private static Object getSubquery(Long planId, Long id) {
+    return lookupSubquery(planId, id).eval(null);
   }
 
   /** Check if the result of a subquery is null. Called from native code. */
@@ -119,4 +129,12 @@ public class CometScalarSubquery {
   public static byte[] getBinary(long planId, long id) {
     return (byte[]) getSubquery(planId, id);
   }
+
+  /**
+   * Get the distinct non-null values of an IN subquery, serialized as `InSubqueryValues`. Called
+   * from native code.
+   */
+  public static byte[] getInValues(long planId, long id) {
+    return QueryPlanSerde.serializeInSubqueryValues((InSubqueryExec) lookupSubquery(planId, id));
+  }
 }
@@ -188,6 +188,8 @@ public abstract class SpillWriter {
             file.getAbsolutePath(),
             preferDictionaryRatio,
             batchSize,
+            ShuffleUtils.shuffleCodecName(),
+            ShuffleUtils.shuffleZstdLevel(),
             checksumEnabled,
             checksumAlgo,
             currentChecksum);
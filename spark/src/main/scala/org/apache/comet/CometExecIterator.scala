@@ -20,10 +20,12 @@
 package org.apache.comet
 
 import org.apache.spark._
-import org.apache.spark.sql.comet.CometMetricNode
+import org.apache.spark.sql.comet.{CometMetricNode, MappedBatchIterator}
+import org.apache.spark.sql.comet.util.Utils
 import org.apache.spark.sql.vectorized._
 
-import org.apache.comet.CometConf.{COMET_BATCH_SIZE, COMET_DEBUG_ENABLED, COMET_EXEC_MEMORY_FRACTION}
+import org.apache.comet.CometConf.{COMET_BATCH_SIZE, COMET_DEBUG_ENABLED, COMET_DEBUG_TAP_DIR, COMET_DEBUG_TAP_OPERATOR, COMET_DEBUG_VALIDATE_BATCHES, COMET_EXEC_BROADCAST_SHARED_HASH_TABLE_ENABLED, COMET_EXEC_COMPUTE_PIN_THREADS, COMET_EXEC_COMPUTE_THREADS, COMET_EXEC_DICTIONARY_ENCODING_ENABLED, COMET_EXEC_DICTIONARY_ENCODING_MAX_RATIO, COMET_EXEC_DICTIONARY_ENCODING_SAMPLE_ROWS, COMET_EXEC_IO_PARALLELISM, COMET_EXEC_MEMORY_FRACTION, COMET_EXEC_OFFLOAD_OPERATORS, COMET_EXEC_OFFLOAD_PROVIDER, COMET_EXEC_PARTIAL_AGG_SKIP_ENABLED, COMET_EXEC_PARTIAL_AGG_SKIP_PROBE_ROWS, COMET_EXEC_PARTIAL_AGG_SKIP_RATIO_THRESHOLD, COMET_EXEC_RESOURCE_PROFILE_DECODE_THREADS, COMET_EXEC_RESOURCE_PROFILE_MAX_IO_REQUESTS, COMET_EXEC_RESOURCE_PROFILE_MEMORY_FRACTION, COMET_EXEC_RESOURCE_PROFILE_NAME, COMET_EXEC_SHUFFLE_CODEC, COMET_EXEC_SHUFFLE_UNIFIED_MEMORY_ENABLED, COMET_EXEC_SPILL_DISK_LIMIT, COMET_METRICS_PROMETHEUS_HOST, COMET_METRICS_PROMETHEUS_PORT}
+import org.apache.comet.shims.ShimSparkErrorConverter
 import org.apache.comet.vector.NativeUtil
 
 /**
@@ -39,28 +41,45 @@ import org.apache.comet.vector.NativeUtil
  *   The input iterators producing sequence of batches of Arrow Arrays.
  * @param protobufQueryPlan
  *   The serialized bytes of Spark execution plan.
+ * @param partitionIndex
+ *   The index of the RDD partition computed by the native plan, which nondeterministic
+ *   expressions are seeded with like Spark.
+ * @param shuffleBlockSink
+ *   The sink which the native shuffle writers of the plan push their output partitions to, if
+ *   they don't write the local shuffle files.
  */
 class CometExecIterator(
     val id: Long,
     inputs: Seq[Iterator[ColumnarBatch]],
     protobufQueryPlan: Array[Byte],
-    nativeMetrics: CometMetricNode)
-    extends Iterator[ColumnarBatch] {
+    nativeMetrics: CometMetricNode,
+    partitionIndex: Int,
+    shuffleBlockSink: Option[CometShuffleBlockSink] = None)
+    extends Iterator[ColumnarBatch]
+    with ShimSparkErrorConverter {
 
   private val nativeLib = new Native()
   private val nativeUtil = new NativeUtil
   private val cometBatchIterators = inputs.map { iterator =>
     new CometBatchIterator(iterator, nativeUtil)
   }.toArray
+  // The handles of the memory-mapped broadcast relations read by the inputs, or 0 for others
+  private val mappedBroadcasts = inputs.map {
+    case iter: MappedBatchIterator if COMET_EXEC_BROADCAST_SHARED_HASH_TABLE_ENABLED.get() =>
+      iter.handle
+    case _ => 0L
+  }.toArray
   private val plan = {
     val configs = createNativeConf
     nativeLib.createPlan(
       id,
       configs,
       cometBatchIterators,
+      mappedBroadcasts,
       protobufQueryPlan,
       nativeMetrics,
-      new CometTaskMemoryManager(id))
+      new CometTaskMemoryManager(id),
+      shuffleBlockSink.orNull)
   }
 
   private var nextBatch: Option[ColumnarBatch] = None
@@ -68,7 +87,12 @@ class CometExecIterator(
   private var closed: Boolean = false
 
   private def executeNative(): ExecutionState = {
-    val result = nativeLib.executePlan(plan)
+    val result =
+      try {
+        nativeLib.executePlan(plan)
+      } catch {
+        case e: CometSparkException => throw toSparkException(e)
+      }
 
     val flag = result(0)
     if (flag == -1) EOF
@@ -95,10 +119,65 @@ class CometExecIterator(
     result.put(
       "use_unified_memory_manager",
       String.valueOf(conf.get("spark.memory.offHeap.enabled", "false")))
+    result.put(
+      "use_unified_shuffle_memory",
+      String.valueOf(COMET_EXEC_SHUFFLE_UNIFIED_MEMORY_ENABLED.get()))
     result.put("memory_limit", String.valueOf(maxMemory))
     result.put("memory_fraction", String.valueOf(COMET_EXEC_MEMORY_FRACTION.get()))
     result.put("batch_size", String.valueOf(COMET_BATCH_SIZE.get()))
+    result.put("partition_index", String.valueOf(partitionIndex))
+    COMET_EXEC_SHUFFLE_CODEC.get().foreach(codec => result.put("shuffle_codec", codec))
+    COMET_EXEC_IO_PARALLELISM.get().foreach(n => result.put("io_parallelism", String.valueOf(n)))
+    COMET_EXEC_COMPUTE_THREADS
+      .get()
+      .foreach(n => result.put("compute_threads", String.valueOf(n)))
+    result.put("pin_compute_threads", String.valueOf(COMET_EXEC_COMPUTE_PIN_THREADS.get()))
+    result.put(
+      "partial_agg_skip_enabled",
+      String.valueOf(COMET_EXEC_PARTIAL_AGG_SKIP_ENABLED.get()))
+    result.put(
+      "partial_agg_skip_probe_rows",
+      String.valueOf(COMET_EXEC_PARTIAL_AGG_SKIP_PROBE_ROWS.get()))
+    result.put(
+      "partial_agg_skip_ratio_threshold",
+      String.valueOf(COMET_EXEC_PARTIAL_AGG_SKIP_RATIO_THRESHOLD.get()))
+    result.put(
+      "dictionary_encoding_enabled",
+      String.valueOf(COMET_EXEC_DICTIONARY_ENCODING_ENABLED.get()))
+    result.put(
+      "dictionary_encoding_sample_rows",
+      String.valueOf(COMET_EXEC_DICTIONARY_ENCODING_SAMPLE_ROWS.get()))
+    result.put(
+      "dictionary_encoding_max_ratio",
+      String.valueOf(COMET_EXEC_DICTIONARY_ENCODING_MAX_RATIO.get()))
+    result.put("metrics_exporter_host", COMET_METRICS_PROMETHEUS_HOST.get())
+    COMET_METRICS_PROMETHEUS_PORT
+      .get()
+      .foreach(port => result.put("metrics_exporter_port", String.valueOf(port)))
+    result.put("spill_dirs", Utils.getConfiguredLocalDirs(conf).mkString(","))
+    COMET_EXEC_SPILL_DISK_LIMIT
+      .get()
+      .foreach(limit => result.put("spill_disk_limit", String.valueOf(limit)))
+    if (NativeBase.hasCapability(NativeBase.CAPABILITY_RESOURCE_PROFILE)) {
+      COMET_EXEC_RESOURCE_PROFILE_NAME.get().foreach(result.put("resource_profile", _))
+      COMET_EXEC_RESOURCE_PROFILE_MAX_IO_REQUESTS
+        .get()
+        .foreach(n => result.put("resource_profile_max_io_requests", String.valueOf(n)))
+      COMET_EXEC_RESOURCE_PROFILE_DECODE_THREADS
+        .get()
+        .foreach(n => result.put("resource_profile_decode_threads", String.valueOf(n)))
+      COMET_EXEC_RESOURCE_PROFILE_MEMORY_FRACTION
+        .get()
+        .foreach(f => result.put("resource_profile_memory_fraction", String.valueOf(f)))
+    }
+    if (NativeBase.hasCapability(NativeBase.CAPABILITY_OPERATOR_OFFLOAD)) {
+      COMET_EXEC_OFFLOAD_PROVIDER.get().foreach(result.put("offload_provider", _))
+      COMET_EXEC_OFFLOAD_OPERATORS.get().foreach(result.put("offload_operators", _))
+    }
     result.put("debug_native", String.valueOf(COMET_DEBUG_ENABLED.get()))
+    result.put("debug_validate_batches", String.valueOf(COMET_DEBUG_VALIDATE_BATCHES.get()))
+    COMET_DEBUG_TAP_OPERATOR.get().foreach(result.put("debug_tap_operator", _))
+    COMET_DEBUG_TAP_DIR.get().foreach(result.put("debug_tap_dir", _))
 
     // Strip mandatory prefix spark. which is not required for DataFusion session params
     conf.getAll.foreach {
@@ -26,7 +26,7 @@ import org.apache.spark.internal.Logging
 import org.apache.spark.network.util.ByteUnit
 import org.apache.spark.sql.{SparkSession, SparkSessionExtensions}
 import org.apache.spark.sql.catalyst.expressions.AttributeReference
-import org.apache.spark.sql.catalyst.expressions.aggregate.{Final, Partial}
+import org.apache.spark.sql.catalyst.expressions.aggregate.{AggregateExpression, ApproximatePercentile, CollectList, CollectSet, Final, Partial, Percentile}
 import org.apache.spark.sql.catalyst.rules.Rule
 import org.apache.spark.sql.catalyst.trees.TreeNode
 import org.apache.spark.sql.comet._
@@ -35,7 +35,7 @@ import org.apache.spark.sql.comet.execution.shuffle.CometShuffleExchangeExec
 import org.apache.spark.sql.comet.util.Utils
 import org.apache.spark.sql.execution._
 import org.apache.spark.sql.execution.adaptive.{AQEShuffleReadExec, BroadcastQueryStageExec, ShuffleQueryStageExec}
-import org.apache.spark.sql.execution.aggregate.HashAggregateExec
+import org.apache.spark.sql.execution.aggregate.{BaseAggregateExec, HashAggregateExec, ObjectHashAggregateExec}
 import org.apache.spark.sql.execution.datasources._
 import org.apache.spark.sql.execution.datasources.parquet.ParquetFileFormat
 import org.apache.spark.sql.execution.datasources.v2.BatchScanExec
@@ -46,8 +46,8 @@ import org.apache.spark.sql.internal.SQLConf
 import org.apache.spark.sql.types._
 
 import org.apache.comet.CometConf._
-import org.apache.comet.CometSparkSessionExtensions.{createMessage, isANSIEnabled, isCometBroadCastForceEnabled, isCometColumnarShuffleEnabled, isCometEnabled, isCometExecEnabled, isCometOperatorEnabled, isCometScan, isCometScanEnabled, isCometShuffleEnabled, isSchemaSupported, shouldApplyRowToColumnar, withInfo}
-import org.apache.comet.parquet.{CometParquetScan, SupportsComet}
+import org.apache.comet.CometSparkSessionExtensions.{createMessage, isANSIEnabled, isCometBroadCastForceEnabled, isCometColumnarShuffleEnabled, isCometEnabled, isCometExecEnabled, isCometOperatorEnabled, isCometScan, isCometScanEnabled, isCometShuffleEnabled, isScanSchemaSupported, isSchemaSupported, shouldApplyRowToColumnar, withInfo}
+import org.apache.comet.parquet.{CometParquetAggregation, CometParquetScan, SupportsComet}
 import org.apache.comet.serde.OperatorOuterClass.Operator
 import org.apache.comet.serde.QueryPlanSerde
 import org.apache.comet.shims.ShimCometSparkSessionExtensions
@@ -91,15 +91,15 @@ class CometSparkSessionExtensions
         }
         plan
       } else {
-        plan.transform {
+        val newPlan = plan.transform {
           // data source V2
           case scanExec: BatchScanExec
               if scanExec.scan.isInstanceOf[ParquetScan] &&
-                isSchemaSupported(scanExec.scan.asInstanceOf[ParquetScan].readDataSchema) &&
+                isScanSchemaSupported(scanExec.scan.asInstanceOf[ParquetScan].readDataSchema) &&
                 isSchemaSupported(scanExec.scan.asInstanceOf[ParquetScan].readPartitionSchema) &&
-                // Comet does not support pushedAggregate
-                getPushedAggregate(scanExec.scan.asInstanceOf[ParquetScan]).isEmpty =>
-            val cometScan = CometParquetScan(scanExec.scan.asInstanceOf[ParquetScan])
+                isPushedAggregateSupported(scanExec.scan.asInstanceOf[ParquetScan]) =>
+            val parquetScan = scanExec.scan.asInstanceOf[ParquetScan]
+            val cometScan = CometParquetScan(parquetScan, cometPushedAggregation(parquetScan))
             logInfo("Comet extension enabled for Scan")
             CometBatchScanExec(
               scanExec.copy(scan = cometScan),
@@ -110,16 +110,16 @@ class CometSparkSessionExtensions
           case scanExec: BatchScanExec if scanExec.scan.isInstanceOf[ParquetScan] =>
             val requiredSchema = scanExec.scan.asInstanceOf[ParquetScan].readDataSchema
             val info1 = createMessage(
-              !isSchemaSupported(requiredSchema),
+              !isScanSchemaSupported(requiredSchema),
               s"Schema $requiredSchema is not supported")
             val readPartitionSchema = scanExec.scan.asInstanceOf[ParquetScan].readPartitionSchema
             val info2 = createMessage(
               !isSchemaSupported(readPartitionSchema),
               s"Partition schema $readPartitionSchema is not supported")
-            // Comet does not support pushedAggregate
             val info3 = createMessage(
-              getPushedAggregate(scanExec.scan.asInstanceOf[ParquetScan]).isDefined,
-              "Comet does not support pushed aggregate")
+              !isPushedAggregateSupported(scanExec.scan.asInstanceOf[ParquetScan]),
+              "Comet does not support pushed aggregate with group-by columns, or other " +
+                "aggregates than count, min and max of primitive columns")
             withInfo(scanExec, Seq(info1, info2, info3).flatten.mkString("\n"))
             scanExec
 
@@ -166,7 +166,8 @@ class CometSparkSessionExtensions
                 _,
                 _,
                 _,
-                _) if isSchemaSupported(requiredSchema) && isSchemaSupported(partitionSchema) =>
+                _)
+              if isScanSchemaSupported(requiredSchema) && isSchemaSupported(partitionSchema) =>
             logInfo("Comet extension enabled for v1 Scan")
             CometScanExec(scanExec, session)
 
@@ -182,7 +183,7 @@ class CometSparkSessionExtensions
                 _,
                 _) =>
             val info1 = createMessage(
-              !isSchemaSupported(requiredSchema),
+              !isScanSchemaSupported(requiredSchema),
               s"Schema $requiredSchema is not supported")
             val info2 = createMessage(
               !isSchemaSupported(partitionSchema),
@@ -190,15 +191,70 @@ class CometSparkSessionExtensions
             withInfo(scanExec, Seq(info1, info2).flatten.mkString(","))
             scanExec
         }
+        if (COMET_SCAN_LIMIT_PUSHDOWN_ENABLED.get(conf)) pushLimitsIntoScans(newPlan) else newPlan
       }
     }
+
+    /**
+     * Pushes the limits of partitions into the Comet scans below them, through the operators
+     * which keep the number of rows, so that the scans stop reading the files once they produce
+     * enough rows. The rows are still read in the order of the files, and the limits stay on top
+     * of the scans to take the exact number of rows.
+     */
+    private def pushLimitsIntoScans(plan: SparkPlan): SparkPlan = {
+      def pushLimit(limit: Int, op: SparkPlan): SparkPlan = op match {
+        case scan: CometScanExec =>
+          val newScan =
+            scan.copy(pushedLimit = Some(scan.pushedLimit.fold(limit)(math.min(_, limit))))
+          scan.logicalLink.foreach(newScan.setLogicalLink)
+          newScan
+        case project: ProjectExec =>
+          project.withNewChildren(Seq(pushLimit(limit, project.child)))
+        case _ => op
+      }
+
+      plan.transformDown {
+        case op: LocalLimitExec =>
+          op.withNewChildren(Seq(pushLimit(op.limit, op.child)))
+        // `CollectLimitExec` takes the first rows of each partition too, including the offset
+        case op: CollectLimitExec if op.limit >= 0 =>
+          op.withNewChildren(Seq(pushLimit(op.limit, op.child)))
+      }
+    }
+
+    /**
+     * The aggregation pushed down into `scan` which Comet answers from Parquet footers, if
+     * there is one.
+     */
+    private def cometPushedAggregation(scan: ParquetScan): Option[CometParquetAggregation] =
+      getPushedAggregate(scan).flatMap { aggregation =>
+        CometParquetAggregation(
+          aggregation,
+          scan.readDataSchema,
+          scan.dataSchema,
+          conf.caseSensitiveAnalysis)
+      }
+
+    private def isPushedAggregateSupported(scan: ParquetScan): Boolean =
+      getPushedAggregate(scan).isEmpty || cometPushedAggregation(scan).isDefined
   }
 
   case class CometExecRule(session: SparkSession) extends Rule[SparkPlan] {
+
+    /**
+     * Whether native shuffle can write the shuffle data, i.e., IO encryption is disabled or the
+     * native library encrypts the data like Spark's shuffle writers. Otherwise, the data would be
+     * written in plaintext.
+     */
+    private def isNativeShuffleEncryptionSupported: Boolean =
+      !session.sparkContext.conf.getBoolean("spark.io.encryption.enabled", false) ||
+        NativeBase.hasCapability(NativeBase.CAPABILITY_SHUFFLE_ENCRYPTION)
+
     private def applyCometShuffle(plan: SparkPlan): SparkPlan = {
       plan.transformUp {
         case s: ShuffleExchangeExec
             if isCometPlan(s.child) && !isCometColumnarShuffleEnabled(conf) &&
+              isNativeShuffleEncryptionSupported &&
               QueryPlanSerde.supportPartitioning(s.child.output, s.outputPartitioning)._1 =>
           logInfo("Comet extension enabled for Native Shuffle")
 
@@ -302,11 +358,34 @@ class CometSparkSessionExtensions
           val nativeOp = QueryPlanSerde.operator2Proto(op).get
           CometScanWrapper(nativeOp, op)
 
+        case op: FileSourceScanExec if COMET_SCAN_NATIVE_FORMATS.get(conf).nonEmpty =>
+          // The scans of the formats in `spark.comet.scan.nativeFormats` are read natively
+          QueryPlanSerde.operator2Proto(op) match {
+            case Some(nativeOp) =>
+              val numPartitions = nativeOp.getFileScan.getPartitionsCount
+              CometNativeFileScanExec(
+                nativeOp,
+                op,
+                op.output,
+                numPartitions,
+                SerializedPlan(None))
+            case None =>
+              op
+          }
+
         case op if shouldApplyRowToColumnar(conf, op) =>
           val cometOp = CometRowToColumnarExec(op)
           val nativeOp = QueryPlanSerde.operator2Proto(cometOp).get
           CometScanWrapper(nativeOp, cometOp)
 
+        case op: LocalTableScanExec =>
+          QueryPlanSerde.operator2Proto(op) match {
+            case Some(nativeOp) =>
+              CometLocalTableScanExec(nativeOp, op, op.rows, SerializedPlan(None))
+            case None =>
+              op
+          }
+
         case op: ProjectExec =>
           val newOp = transform1(op)
           newOp match {
@@ -381,7 +460,28 @@ class CometSparkSessionExtensions
               op
           }
 
-        case op @ HashAggregateExec(_, _, _, groupingExprs, aggExprs, _, _, _, child) =>
+        case op: GenerateExec =>
+          val newOp = transform1(op)
+          newOp match {
+            case Some(nativeOp) =>
+              CometGenerateExec(
+                nativeOp,
+                op,
+                op.generator,
+                op.requiredChildOutput,
+                op.outer,
+                op.generatorOutput,
+                op.child,
+                SerializedPlan(None))
+            case None =>
+              op
+          }
+
+        case op: BaseAggregateExec
+            if op.isInstanceOf[HashAggregateExec] || op.isInstanceOf[ObjectHashAggregateExec] =>
+          val groupingExprs = op.groupingExpressions
+          val aggExprs = op.aggregateExpressions
+          val child = op.child
           val modes = aggExprs.map(_.mode).distinct
 
           if (!modes.isEmpty && modes.size != 1) {
@@ -389,8 +489,11 @@ class CometSparkSessionExtensions
             // Fallback to Spark nevertheless here.
             op
           } else {
+            // Comet can finalize the partial aggregation of Spark only if the aggregation buffers
+            // are in the same format
             val sparkFinalMode = {
-              !modes.isEmpty && modes.head == Final && findPartialAgg(child).isEmpty
+              !modes.isEmpty && modes.head == Final && findPartialAgg(child).isEmpty &&
+              !hasSparkCompatibleBuffers(aggExprs)
             }
 
             if (sparkFinalMode) {
@@ -572,6 +675,20 @@ class CometSparkSessionExtensions
               s
           }
 
+        // For AQE broadcast stage on a reused Comet broadcast exchange, e.g., in a self-join. The
+        // broadcast of the original exchange is read by both stages.
+        case s @ BroadcastQueryStageExec(
+              _,
+              ReusedExchangeExec(_, _: CometBroadcastExchangeExec),
+              _) =>
+          val newOp = transform1(s)
+          newOp match {
+            case Some(nativeOp) =>
+              CometSinkPlaceHolder(nativeOp, s, s)
+            case None =>
+              s
+          }
+
         // `CometBroadcastExchangeExec`'s broadcast output is not compatible with Spark's broadcast
         // exchange. It is only used for Comet native execution. We only transform Spark broadcast
         // exchange to Comet broadcast exchange if its downstream is a Comet native plan or if the
@@ -637,10 +754,23 @@ class CometSparkSessionExtensions
               s
           }
 
+        // For AQE shuffle read on a Comet shuffle stage, whose partition specs may coalesce
+        // partitions, or split a skewed partition into ranges of map outputs for a skew join.
+        // The read partitions of the two sides of a skew join are zipped like any others.
+        case r @ AQEShuffleReadExec(CometSinkPlaceHolder(_, _: ShuffleQueryStageExec, _), _) =>
+          val newOp = transform1(r)
+          newOp match {
+            case Some(nativeOp) =>
+              CometSinkPlaceHolder(nativeOp, r, r)
+            case None =>
+              r
+          }
+
         // Native shuffle for Comet operators
         case s: ShuffleExchangeExec
             if isCometShuffleEnabled(conf) &&
               !isCometColumnarShuffleEnabled(conf) &&
+              isNativeShuffleEncryptionSupported &&
               QueryPlanSerde.supportPartitioning(s.child.output, s.outputPartitioning)._1 =>
           logInfo("Comet extension enabled for Native Shuffle")
 
@@ -734,6 +864,20 @@ class CometSparkSessionExtensions
       } else {
         var newPlan = transform(plan)
 
+        // Local relations are only read natively by Comet operators. Otherwise, Spark returns
+        // their rows directly.
+        newPlan = newPlan match {
+          case scan: CometLocalTableScanExec => scan.originalPlan
+          case _ =>
+            newPlan.transformUp {
+              case op if !isCometPlan(op) =>
+                op.mapChildren {
+                  case scan: CometLocalTableScanExec => scan.originalPlan
+                  case child => child
+                }
+            }
+        }
+
         // Remove placeholders
         newPlan = newPlan.transform {
           case CometSinkPlaceHolder(_, _, s) => s
@@ -792,19 +936,29 @@ class CometSparkSessionExtensions
     }
 
     /**
-     * Find the first Comet partial aggregate in the plan. If it reaches a Spark HashAggregate
-     * with partial mode, it will return None.
+     * Find the first Comet partial aggregate in the plan. If it reaches a Spark aggregate with
+     * partial mode, it will return None.
      */
     def findPartialAgg(plan: SparkPlan): Option[CometHashAggregateExec] = {
       plan.collectFirst {
         case agg: CometHashAggregateExec if agg.aggregateExpressions.forall(_.mode == Partial) =>
           Some(agg)
-        case agg: HashAggregateExec if agg.aggregateExpressions.forall(_.mode == Partial) => None
+        case agg: BaseAggregateExec if agg.aggregateExpressions.forall(_.mode == Partial) => None
         case a: AQEShuffleReadExec => findPartialAgg(a.child)
         case s: ShuffleQueryStageExec => findPartialAgg(s.plan)
       }.flatten
     }
 
+    /**
+     * Whether the aggregation buffers of the given aggregate expressions are serialized natively
+     * in the same format as Spark.
+     */
+    def hasSparkCompatibleBuffers(aggExprs: Seq[AggregateExpression]): Boolean =
+      aggExprs.forall(_.aggregateFunction match {
+        case _: CollectList | _: CollectSet | _: ApproximatePercentile | _: Percentile => true
+        case fn => QueryPlanSerde.isBloomFilterAggregate(fn)
+      })
+
     /**
      * Returns true if a given spark plan is Comet shuffle operator.
      */
@@ -941,6 +1095,20 @@ object CometSparkSessionExtensions extends Logging {
   private[comet] def isSchemaSupported(schema: StructType): Boolean =
     schema.map(_.dataType).forall(isTypeSupported)
 
+  /**
+   * Whether the native Parquet reader supports the given schema, which besides the types of
+   * `isTypeSupported` includes the structs, arrays and maps of them, nested or not.
+   */
+  private[comet] def isScanSchemaSupported(schema: StructType): Boolean =
+    schema.map(_.dataType).forall(isScanTypeSupported)
+
+  private[comet] def isScanTypeSupported(dt: DataType): Boolean = dt match {
+    case s: StructType => s.nonEmpty && s.fields.map(_.dataType).forall(isScanTypeSupported)
+    case a: ArrayType => isScanTypeSupported(a.elementType)
+    case m: MapType => isScanTypeSupported(m.keyType) && isScanTypeSupported(m.valueType)
+    case dt => isTypeSupported(dt)
+  }
+
   private[comet] def isTypeSupported(dt: DataType): Boolean = dt match {
     case BooleanType | ByteType | ShortType | IntegerType | LongType | FloatType | DoubleType |
         BinaryType | StringType | _: DecimalType | DateType | TimestampType =>
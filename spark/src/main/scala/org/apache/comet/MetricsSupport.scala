@@ -33,6 +33,15 @@ trait MetricsSupport {
       "ParquetRowGroups" -> SQLMetrics.createMetric(
         sparkContext,
         "num of Parquet row groups read"),
+      "ParquetRowGroupsSkipped" -> SQLMetrics.createMetric(
+        sparkContext,
+        "num of Parquet row groups skipped by pushed filters"),
+      "ParquetRowGroupsSkippedByBloomFilter" -> SQLMetrics.createMetric(
+        sparkContext,
+        "num of Parquet row groups skipped by Bloom filters"),
+      "ParquetRowsSkipped" -> SQLMetrics.createMetric(
+        sparkContext,
+        "num of rows in Parquet row groups skipped by pushed filters"),
       "ParquetNativeDecodeTime" -> SQLMetrics.createNanoTimingMetric(
         sparkContext,
         "time spent in Parquet native decoding"),
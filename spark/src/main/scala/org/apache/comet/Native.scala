@@ -19,6 +19,7 @@
 
 package org.apache.comet
 
+import java.nio.ByteBuffer
 import java.util.Map
 
 import org.apache.spark.CometTaskMemoryManager
@@ -35,6 +36,9 @@ class Native extends NativeBase {
    * @param iterators
    *   the input iterators to the native query plan. It should be the same number as the number of
    *   scan nodes in the SparkPlan.
+   * @param mappedBroadcasts
+   *   the handles of the memory-mapped broadcast relations read by the input iterators, or 0 for
+   *   the iterators not reading such relations.
    * @param plan
    *   the bytes of serialized SparkPlan.
    * @param metrics
@@ -42,6 +46,9 @@ class Native extends NativeBase {
    * @param taskMemoryManager
    *   the task-level memory manager that is responsible for tracking memory usage across JVM and
    *   native side.
+   * @param shuffleBlockSink
+   *   the sink which the native shuffle writers push their output partitions to instead of
+   *   writing the local shuffle files, or null to write the files.
    * @return
    *   the address to native query plan.
    */
@@ -49,9 +56,11 @@ class Native extends NativeBase {
       id: Long,
       configMap: Map[String, String],
       iterators: Array[CometBatchIterator],
+      mappedBroadcasts: Array[Long],
       plan: Array[Byte],
       metrics: CometMetricNode,
-      taskMemoryManager: CometTaskMemoryManager): Long
+      taskMemoryManager: CometTaskMemoryManager,
+      shuffleBlockSink: CometShuffleBlockSink): Long
 
   /**
    * Execute a native query plan based on given input Arrow arrays.
@@ -73,6 +82,20 @@ class Native extends NativeBase {
    */
   @native def releasePlan(plan: Long): Unit
 
+  /**
+   * Get the effective value of a config of a native query plan, after it is parsed and validated
+   * by the native side.
+   *
+   * @param plan
+   *   the address to native query plan.
+   * @param key
+   *   the key of the config, as passed to `createPlan`.
+   * @return
+   *   the effective value of the config, or null if the config is neither set nor has a default
+   *   value.
+   */
+  @native def getConfig(plan: Long, key: String): String
+
   /**
    * Used by Comet shuffle external sorter to write sorted records to disk.
    *
@@ -91,6 +114,10 @@ class Native extends NativeBase {
    * @param batchSize
    *   the batch size on the native side to buffer outputs during the row to columnar conversion
    *   before writing them out to disk.
+   * @param compressionCodec
+   *   the codec to compress the written data with, i.e., lz4, snappy or zstd.
+   * @param zstdLevel
+   *   the compression level of zstd.
    * @param checksumEnabled
    *   whether to compute checksum of written file.
    * @param checksumAlgo
@@ -108,6 +135,8 @@ class Native extends NativeBase {
       file: String,
       preferDictionaryRatio: Double,
       batchSize: Int,
+      compressionCodec: String,
+      zstdLevel: Int,
       checksumEnabled: Boolean,
       checksumAlgo: Int,
       currentChecksum: Long): Array[Long]
@@ -121,4 +150,169 @@ class Native extends NativeBase {
    *   the size of the array.
    */
   @native def sortRowPartitionsNative(addr: Long, size: Long): Unit
+
+  /**
+   * Serializes a batch into compressed Arrow IPC bytes. Used by Comet broadcast exchange.
+   *
+   * @param addresses
+   *   the number of rows of the batch, followed by the addresses of its Arrow arrays and schemas,
+   *   as exported by `NativeUtil.exportBatch`.
+   * @return
+   *   the serialized bytes, which are empty if the batch is empty.
+   */
+  @native def serializeBatch(addresses: Array[Long]): Array[Byte]
+
+  /**
+   * Deserializes a batch serialized by `serializeBatch`, and moves its columns into the given
+   * Arrow arrays and schemas.
+   *
+   * @param bytes
+   *   the serialized bytes of the batch.
+   * @param arrayAddrs
+   *   the addresses of the Arrow arrays to move the columns into.
+   * @param schemaAddrs
+   *   the addresses of the Arrow schemas to move the column types into.
+   * @return
+   *   the number of rows of the batch.
+   */
+  @native def deserializeBatch(
+      bytes: Array[Byte],
+      arrayAddrs: Array[Long],
+      schemaAddrs: Array[Long]): Long
+
+  /**
+   * Decodes a block of Comet native shuffle, i.e., the compressed Arrow IPC stream of a batch
+   * without its length, and moves its columns into the given Arrow arrays and schemas. Used by
+   * the native shuffle reader.
+   *
+   * @param block
+   *   the direct byte buffer of the block, which is read from its start.
+   * @param length
+   *   the length of the block in bytes.
+   * @param codec
+   *   the short name of the codec which the block is compressed with.
+   * @param arrayAddrs
+   *   the addresses of the Arrow arrays to move the columns into.
+   * @param schemaAddrs
+   *   the addresses of the Arrow schemas to move the column types into.
+   * @return
+   *   the number of rows of the batch.
+   */
+  @native def decodeShuffleBlock(
+      block: ByteBuffer,
+      length: Int,
+      codec: String,
+      arrayAddrs: Array[Long],
+      schemaAddrs: Array[Long]): Long
+
+  /**
+   * Creates the coalescer of the batches decoded from the blocks of Comet native shuffle, which
+   * coalesces them until they have `targetRows` rows or `targetBytes` bytes.
+   *
+   * @param codec
+   *   the short name of the codec which the blocks are compressed with.
+   * @param targetRows
+   *   the number of rows of the coalesced batches.
+   * @param targetBytes
+   *   the size in bytes of the coalesced batches.
+   * @return
+   *   the handle of the coalescer, which must be released by `releaseShuffleCoalescer`.
+   */
+  @native def createShuffleCoalescer(codec: String, targetRows: Int, targetBytes: Long): Long
+
+  /**
+   * Decodes a block of Comet native shuffle, i.e., the compressed Arrow IPC stream without its
+   * length, into the given coalescer.
+   *
+   * @param coalescer
+   *   the handle of the coalescer.
+   * @param block
+   *   the direct byte buffer of the block, which is read from its start.
+   * @param length
+   *   the length of the block in bytes, or -1 if there is no more block, in which case the
+   *   batches buffered by the coalescer are coalesced.
+   * @return
+   *   the number of the coalesced batches ready to be taken by `nextCoalescedBatch`.
+   */
+  @native def coalesceShuffleBlock(coalescer: Long, block: ByteBuffer, length: Int): Int
+
+  /**
+   * Moves the columns of the next coalesced batch of the coalescer into the given Arrow arrays
+   * and schemas.
+   *
+   * @param coalescer
+   *   the handle of the coalescer.
+   * @param arrayAddrs
+   *   the addresses of the Arrow arrays to move the columns into.
+   * @param schemaAddrs
+   *   the addresses of the Arrow schemas to move the column types into.
+   * @return
+   *   the number of rows of the batch.
+   */
+  @native def nextCoalescedBatch(
+      coalescer: Long,
+      arrayAddrs: Array[Long],
+      schemaAddrs: Array[Long]): Long
+
+  /**
+   * Releases the coalescer created by `createShuffleCoalescer`.
+   *
+   * @param coalescer
+   *   the handle of the coalescer.
+   */
+  @native def releaseShuffleCoalescer(coalescer: Long): Unit
+
+  /**
+   * Maps a broadcast relation serialized by `serializeBatch` into memory, which is shared by all
+   * the tasks of the executor using it at the same time.
+   *
+   * @param broadcastId
+   *   the id of the broadcast.
+   * @param chunks
+   *   the serialized batches of the relation. If null, only returns the relation already mapped
+   *   by other tasks.
+   * @param dir
+   *   the directory to write the file of the relation to be mapped.
+   * @return
+   *   the handle of the mapped relation, or 0 if `chunks` is null and the relation is not mapped
+   *   yet. The handle must be released by `releaseMappedBroadcast`.
+   */
+  @native def mapBroadcast(broadcastId: Long, chunks: Array[Array[Byte]], dir: String): Long
+
+  /**
+   * Returns the number of batches of a mapped broadcast relation.
+   *
+   * @param handle
+   *   the handle of the mapped relation.
+   */
+  @native def numMappedBatches(handle: Long): Int
+
+  /**
+   * Moves the columns of a batch of a mapped broadcast relation into the given Arrow arrays and
+   * schemas, without copying the mapped data.
+   *
+   * @param handle
+   *   the handle of the mapped relation.
+   * @param index
+   *   the index of the batch.
+   * @param arrayAddrs
+   *   the addresses of the Arrow arrays to move the columns into.
+   * @param schemaAddrs
+   *   the addresses of the Arrow schemas to move the column types into.
+   * @return
+   *   the number of rows of the batch.
+   */
+  @native def getMappedBatch(
+      handle: Long,
+      index: Int,
+      arrayAddrs: Array[Long],
+      schemaAddrs: Array[Long]): Long
+
+  /**
+   * Releases the handle of a mapped broadcast relation.
+   *
+   * @param handle
+   *   the handle of the mapped relation.
+   */
+  @native def releaseMappedBroadcast(handle: Long): Unit
 }
@@ -0,0 +1,233 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one
+ * or more contributor license agreements.  See the NOTICE file
+ * distributed with this work for additional information
+ * regarding copyright ownership.  The ASF licenses this file
+ * to you under the Apache License, Version 2.0 (the
+ * "License"); you may not use this file except in compliance
+ * with the License.  You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+ * KIND, either express or implied.  See the License for the
+ * specific language governing permissions and limitations
+ * under the License.
+ */
+
+package org.apache.comet.parquet
+
+import scala.collection.JavaConverters._
+
+import org.apache.parquet.hadoop.metadata.{BlockMetaData, ColumnChunkMetaData, ParquetMetadata}
+import org.apache.parquet.schema.LogicalTypeAnnotation.{DateLogicalTypeAnnotation, IntLogicalTypeAnnotation}
+import org.apache.parquet.schema.PrimitiveType.PrimitiveTypeName
+import org.apache.spark.sql.catalyst.InternalRow
+import org.apache.spark.sql.connector.expressions.{Expression, NamedReference}
+import org.apache.spark.sql.connector.expressions.aggregate.{Aggregation, Count, CountStar, Max, Min}
+import org.apache.spark.sql.types._
+
+/**
+ * An aggregation pushed down into a Parquet scan by Spark, i.e., `count`, `min` and `max`
+ * without group-by columns, which Comet answers from the row group statistics in the file
+ * footers instead of reading the rows. Like Spark, the scan returns a row of the partial
+ * aggregates of each file, which are merged by the final aggregation on top of the scan.
+ *
+ * The columns are resolved against the data schema on the driver, so that the aggregation can
+ * be sent to the executors without the Spark version specific aggregation API.
+ */
+case class CometParquetAggregation(functions: Seq[CometParquetAggregation.Function]) {
+  import CometParquetAggregation._
+
+  /** The data columns which the aggregate functions take */
+  def columns: Seq[StructField] = functions.flatMap {
+    case RowCount => None
+    case NonNullCount(field) => Some(field)
+    case MinValue(field) => Some(field)
+    case MaxValue(field) => Some(field)
+  }
+
+  /**
+   * Creates the row of the aggregates of the Parquet file `filePath` from its `footer`, where
+   * `rebaseDate` rebases the days of date statistics.
+   */
+  def createAggregateRow(
+      footer: ParquetMetadata,
+      filePath: String,
+      rebaseDate: Int => Int,
+      isCaseSensitive: Boolean): InternalRow = {
+    val blocks = footer.getBlocks.asScala.toSeq
+
+    def columnChunk(block: BlockMetaData, field: StructField): Option[ColumnChunkMetaData] =
+      block.getColumns.asScala.find { column =>
+        val path = column.getPath.toArray
+        path.length == 1 &&
+        (if (isCaseSensitive) path(0) == field.name else path(0).equalsIgnoreCase(field.name))
+      }
+
+    def minOrMax(field: StructField, isMax: Boolean): Any = {
+      val values = blocks.flatMap { block =>
+        columnChunk(block, field).flatMap { column =>
+          checkPhysicalType(column, field, filePath)
+          val statistics = column.getStatistics
+          if (statistics != null && statistics.hasNonNullValue) {
+            val value = if (isMax) statistics.genericGetMax else statistics.genericGetMin
+            Some(toSparkValue(value, field.dataType, rebaseDate))
+          } else if (statistics != null && statistics.isNumNullsSet &&
+            statistics.getNumNulls == block.getRowCount) {
+            // All the values in the row group are nulls
+            None
+          } else {
+            throw unsupported(s"No min/max found for Parquet file $filePath")
+          }
+        }
+      }
+      values.reduceOption { (left, right) =>
+        val compared = left.asInstanceOf[Comparable[Any]].compareTo(right)
+        if (isMax == (compared < 0)) right else left
+      }.orNull
+    }
+
+    val values = functions.map {
+      case RowCount =>
+        blocks.map(_.getRowCount).sum
+      case NonNullCount(field) =>
+        blocks.map { block =>
+          columnChunk(block, field) match {
+            case Some(column) =>
+              val statistics = column.getStatistics
+              if (statistics == null || !statistics.isNumNullsSet) {
+                throw unsupported(s"Number of nulls not set for Parquet file $filePath")
+              }
+              block.getRowCount - statistics.getNumNulls
+            // Missing columns are read as nulls
+            case None => 0L
+          }
+        }.sum
+      case MinValue(field) => minOrMax(field, isMax = false)
+      case MaxValue(field) => minOrMax(field, isMax = true)
+    }
+    InternalRow.fromSeq(values)
+  }
+}
+
+object CometParquetAggregation {
+
+  /** An aggregate function answered from Parquet footers */
+  sealed trait Function extends Serializable
+
+  /** `count(*)` */
+  case object RowCount extends Function
+
+  /** `count(column)` */
+  case class NonNullCount(field: StructField) extends Function
+
+  /** `min(column)` */
+  case class MinValue(field: StructField) extends Function
+
+  /** `max(column)` */
+  case class MaxValue(field: StructField) extends Function
+
+  /**
+   * Resolves the `aggregation` pushed down into a Parquet scan, whose output schema is
+   * `aggregateSchema`, against the `dataSchema` of the files. Returns `None` if Comet cannot
+   * answer it.
+   */
+  def apply(
+      aggregation: Aggregation,
+      aggregateSchema: StructType,
+      dataSchema: StructType,
+      isCaseSensitive: Boolean): Option[CometParquetAggregation] = {
+    val aggregates = aggregation.aggregateExpressions()
+    // The output schema starts with the group-by columns, which are not supported
+    if (aggregateSchema.length != aggregates.length) {
+      return None
+    }
+
+    def resolve(column: Expression): Option[StructField] = column match {
+      case ref: NamedReference if ref.fieldNames().length == 1 =>
+        val name = ref.fieldNames().head
+        dataSchema.find { field =>
+          if (isCaseSensitive) field.name == name else field.name.equalsIgnoreCase(name)
+        }
+      case _ => None
+    }
+
+    val functions = aggregates.toSeq.map {
+      case _: CountStar => Some(RowCount)
+      case count: Count if !count.isDistinct =>
+        resolve(count.column)
+          .filter(field => isCountTypeSupported(field.dataType))
+          .map(NonNullCount)
+      case min: Min =>
+        resolve(min.column).filter(field => isMinMaxTypeSupported(field.dataType)).map(MinValue)
+      case max: Max =>
+        resolve(max.column).filter(field => isMinMaxTypeSupported(field.dataType)).map(MaxValue)
+      case _ => None
+    }
+    if (functions.forall(_.isDefined)) {
+      Some(CometParquetAggregation(functions.flatten))
+    } else {
+      None
+    }
+  }
+
+  private def isCountTypeSupported(dataType: DataType): Boolean = dataType match {
+    case _: StructType | _: ArrayType | _: MapType => false
+    case _ => true
+  }
+
+  /**
+   * Only the types whose statistics are not truncated, and are ordered like Spark orders their
+   * values, are supported.
+   */
+  private def isMinMaxTypeSupported(dataType: DataType): Boolean = dataType match {
+    case BooleanType | ByteType | ShortType | IntegerType | LongType | FloatType | DoubleType |
+        DateType =>
+      true
+    case _ => false
+  }
+
+  /**
+   * Checks the column of the footer is stored as the physical type of its Spark type, e.g., not
+   * an unsigned integer, so that its statistics can be taken as they are.
+   */
+  private def checkPhysicalType(
+      column: ColumnChunkMetaData,
+      field: StructField,
+      filePath: String): Unit = {
+    val primitiveType = column.getPrimitiveType
+    val expected = field.dataType match {
+      case BooleanType => PrimitiveTypeName.BOOLEAN
+      case ByteType | ShortType | IntegerType | DateType => PrimitiveTypeName.INT32
+      case LongType => PrimitiveTypeName.INT64
+      case FloatType => PrimitiveTypeName.FLOAT
+      case DoubleType => PrimitiveTypeName.DOUBLE
+    }
+    val isAnnotationSupported = primitiveType.getLogicalTypeAnnotation match {
+      case null => field.dataType != DateType
+      case _: DateLogicalTypeAnnotation => field.dataType == DateType
+      case int: IntLogicalTypeAnnotation => int.isSigned && field.dataType != DateType
+      case _ => false
+    }
+    if (primitiveType.getPrimitiveTypeName != expected || !isAnnotationSupported) {
+      throw unsupported(
+        s"Cannot take the min/max of column ${field.name} of type ${field.dataType.sql} from " +
+          s"column $primitiveType of Parquet file $filePath")
+    }
+  }
+
+  private def toSparkValue(value: Any, dataType: DataType, rebaseDate: Int => Int): Any =
+    dataType match {
+      case ByteType => value.asInstanceOf[Integer].byteValue()
+      case ShortType => value.asInstanceOf[Integer].shortValue()
+      case DateType => rebaseDate(value.asInstanceOf[Integer])
+      case _ => value
+    }
+
+  private def unsupported(message: String): UnsupportedOperationException =
+    new UnsupportedOperationException(
+      s"$message. Set SQLConf spark.sql.parquet.aggregatePushdown to false and execute again")
+}
@@ -100,7 +100,9 @@ class CometParquetFileFormat extends ParquetFileFormat with MetricsSupport with
     val parquetFilterPushDown = sqlConf.parquetFilterPushDown
 
     // Comet specific configurations
-    val capacity = CometConf.COMET_BATCH_SIZE.get(sqlConf)
+    val capacity = CometParquetFileFormat.batchCapacity(
+      sqlConf,
+      optionsMap.get(CometParquetFileFormat.OPTION_PUSHED_LIMIT).map(_.toInt))
 
     (file: PartitionedFile) => {
       val sharedConf = broadcastedHadoopConf.value.value
@@ -163,6 +165,18 @@ class CometParquetFileFormat extends ParquetFileFormat with MetricsSupport with
 
 object CometParquetFileFormat extends Logging {
 
+  /** The option of the limit of the rows read by each partition of the scan */
+  val OPTION_PUSHED_LIMIT = "comet.pushed_limit"
+
+  /**
+   * The capacity of the batches read by Comet scans, which is not larger than the limit pushed
+   * into the scan, to avoid decoding the rows which are not taken.
+   */
+  def batchCapacity(sqlConf: SQLConf, pushedLimit: Option[Int]): Int = {
+    val batchSize = CometConf.COMET_BATCH_SIZE.get(sqlConf)
+    pushedLimit.fold(batchSize)(limit => math.max(math.min(batchSize, limit), 1))
+  }
+
   /**
    * Populates Parquet related configurations from the input `sqlConf` to the `hadoopConf`
    */
@@ -190,6 +204,12 @@ object CometParquetFileFormat extends Logging {
     hadoopConf.setBoolean(
       CometConf.COMET_EXCEPTION_ON_LEGACY_DATE_TIMESTAMP.key,
       CometConf.COMET_EXCEPTION_ON_LEGACY_DATE_TIMESTAMP.get())
+    hadoopConf.setBoolean(
+      CometConf.COMET_PARQUET_FOOTER_CACHE_ENABLED.key,
+      CometConf.COMET_PARQUET_FOOTER_CACHE_ENABLED.get())
+    hadoopConf.setInt(
+      CometConf.COMET_PARQUET_FOOTER_CACHE_MAX_ENTRIES.key,
+      CometConf.COMET_PARQUET_FOOTER_CACHE_MAX_ENTRIES.get())
   }
 
   def getDatetimeRebaseSpec(
@@ -32,7 +32,7 @@ import org.apache.spark.sql.catalyst.InternalRow
 import org.apache.spark.sql.catalyst.util.RebaseDateTime.RebaseSpec
 import org.apache.spark.sql.connector.read.InputPartition
 import org.apache.spark.sql.connector.read.PartitionReader
-import org.apache.spark.sql.execution.datasources.{FilePartition, PartitionedFile}
+import org.apache.spark.sql.execution.datasources.{DataSourceUtils, FilePartition, PartitionedFile}
 import org.apache.spark.sql.execution.datasources.parquet.ParquetOptions
 import org.apache.spark.sql.execution.datasources.v2.FilePartitionReaderFactory
 import org.apache.spark.sql.execution.metric.SQLMetric
@@ -40,7 +40,7 @@ import org.apache.spark.sql.internal.SQLConf
 import org.apache.spark.sql.internal.SQLConf.LegacyBehaviorPolicy
 import org.apache.spark.sql.sources.Filter
 import org.apache.spark.sql.types.StructType
-import org.apache.spark.sql.vectorized.ColumnarBatch
+import org.apache.spark.sql.vectorized.{ColumnarBatch, ColumnVector}
 import org.apache.spark.util.SerializableConfiguration
 
 import org.apache.comet.{CometConf, CometRuntimeException}
@@ -53,7 +53,9 @@ case class CometParquetPartitionReaderFactory(
     partitionSchema: StructType,
     filters: Array[Filter],
     options: ParquetOptions,
-    metrics: Map[String, SQLMetric])
+    metrics: Map[String, SQLMetric],
+    pushedAggregation: Option[CometParquetAggregation] = None,
+    pushedLimit: Option[Int] = None)
     extends FilePartitionReaderFactory
     with ShimSQLConf
     with Logging {
@@ -70,7 +72,7 @@ case class CometParquetPartitionReaderFactory(
   private val parquetFilterPushDown = sqlConf.parquetFilterPushDown
 
   // Comet specific configurations
-  private val batchSize = CometConf.COMET_BATCH_SIZE.get(sqlConf)
+  private val batchSize = CometParquetFileFormat.batchCapacity(sqlConf, pushedLimit)
 
   // This is only called at executor on a Broadcast variable, so we don't want it to be
   // materialized at driver.
@@ -89,7 +91,7 @@ case class CometParquetPartitionReaderFactory(
   override def supportColumnarReads(partition: InputPartition): Boolean = true
 
   override def createColumnarReader(partition: InputPartition): PartitionReader[ColumnarBatch] = {
-    if (preFetchEnabled) {
+    if (preFetchEnabled && pushedAggregation.isEmpty) {
       val filePartition = partition.asInstanceOf[FilePartition]
       val conf = broadcastedConf.value.value
 
@@ -151,6 +153,10 @@ case class CometParquetPartitionReaderFactory(
   }
 
   override def buildColumnarReader(file: PartitionedFile): PartitionReader[ColumnarBatch] = {
+    if (pushedAggregation.isDefined) {
+      return buildAggregateReader(file, pushedAggregation.get)
+    }
+
     val cometReader = if (!preFetchEnabled) {
       // Prefetch is not enabled, create comet reader and initiate it.
       val cometReader = buildCometReader(file)
@@ -173,6 +179,36 @@ case class CometParquetPartitionReaderFactory(
     CometPartitionReader(cometReader)
   }
 
+  /**
+   * Builds the reader returning a single row of the aggregates of the file, which are taken from
+   * the statistics in its footer. Like Spark, nothing is returned for a file without row groups.
+   */
+  private def buildAggregateReader(
+      file: PartitionedFile,
+      aggregation: CometParquetAggregation): PartitionReader[ColumnarBatch] = {
+    val sharedConf = broadcastedConf.value.value
+    val footer = FooterReader.readFooter(sharedConf, file)
+    val readers = if (footer.getBlocks.isEmpty) {
+      Array.empty[ConstantColumnReader]
+    } else {
+      val datetimeRebaseSpec = CometParquetFileFormat.getDatetimeRebaseSpec(
+        file,
+        StructType(aggregation.columns),
+        sharedConf,
+        footer.getFileMetaData,
+        datetimeRebaseModeInRead)
+      val row = aggregation.createAggregateRow(
+        footer,
+        file.filePath.toString,
+        DataSourceUtils.createDateRebaseFuncInRead(datetimeRebaseSpec.mode, "Parquet"),
+        isCaseSensitive)
+      readDataSchema.fields.zipWithIndex.map { case (field, i) =>
+        new ConstantColumnReader(field, 1, row, i, false)
+      }
+    }
+    CometAggregatePartitionReader(readers)
+  }
+
   def getFilter(file: PartitionedFile): (RebaseSpec, ParquetMetadata, Option[FilterPredicate]) = {
     val sharedConf = broadcastedConf.value.value
     val footer = FooterReader.readFooter(sharedConf, file)
@@ -228,4 +264,30 @@ case class CometParquetPartitionReaderFactory(
       reader.close()
     }
   }
+
+  /**
+   * Returns the single row of the constant `readers`, or nothing if there are no readers.
+   */
+  protected case class CometAggregatePartitionReader(readers: Array[ConstantColumnReader])
+      extends PartitionReader[ColumnarBatch] {
+    private var hasNext = readers.nonEmpty
+
+    override def next(): Boolean = {
+      val result = hasNext
+      hasNext = false
+      result
+    }
+
+    override def get(): ColumnarBatch = {
+      val vectors = readers.map { reader =>
+        reader.readBatch(1)
+        reader.currentBatch().asInstanceOf[ColumnVector]
+      }
+      new ColumnarBatch(vectors, 1)
+    }
+
+    override def close(): Unit = {
+      readers.foreach(_.close())
+    }
+  }
 }
@@ -22,6 +22,7 @@ package org.apache.comet.parquet
 import scala.collection.JavaConverters.mapAsScalaMapConverter
 
 import org.apache.hadoop.conf.Configuration
+import org.apache.hadoop.fs.Path
 import org.apache.spark.sql.SparkSession
 import org.apache.spark.sql.connector.read.PartitionReaderFactory
 import org.apache.spark.sql.execution.datasources.parquet.ParquetOptions
@@ -42,14 +43,27 @@ trait CometParquetScan extends FileScan with MetricsSupport {
   def pushedFilters: Array[Filter]
   def options: CaseInsensitiveStringMap
 
+  /** The aggregation pushed down into the scan, which is answered from the file footers */
+  def pushedAggregation: Option[CometParquetAggregation] = None
+
   override def equals(obj: Any): Boolean = obj match {
     case other: CometParquetScan =>
       super.equals(other) && readDataSchema == other.readDataSchema &&
       readPartitionSchema == other.readPartitionSchema &&
-      equivalentFilters(pushedFilters, other.pushedFilters)
+      equivalentFilters(pushedFilters, other.pushedFilters) &&
+      pushedAggregation == other.pushedAggregation
     case _ => false
   }
 
+  // Like Spark, a file is not split if an aggregation is pushed down, since its footer answers
+  // the aggregation of the whole file
+  override def isSplitable(path: Path): Boolean =
+    pushedAggregation.isEmpty && super.isSplitable(path)
+
+  // The schema of a scan with a pushed aggregation is the schema of the aggregates
+  override def readSchema(): StructType =
+    if (pushedAggregation.nonEmpty) readDataSchema else super.readSchema()
+
   override def hashCode(): Int = getClass.hashCode()
 
   override def createReaderFactory(): PartitionReaderFactory = {
@@ -64,12 +78,15 @@ trait CometParquetScan extends FileScan with MetricsSupport {
       readPartitionSchema,
       pushedFilters,
       new ParquetOptions(options.asScala.toMap, sqlConf),
-      metrics)
+      metrics,
+      pushedAggregation)
   }
 }
 
 object CometParquetScan {
-  def apply(scan: ParquetScan): CometParquetScan =
+  def apply(
+      scan: ParquetScan,
+      aggregation: Option[CometParquetAggregation] = None): CometParquetScan =
     new ParquetScan(
       scan.sparkSession,
       scan.hadoopConf,
@@ -80,5 +97,7 @@ object CometParquetScan {
       scan.pushedFilters,
       scan.options,
       partitionFilters = scan.partitionFilters,
-      dataFilters = scan.dataFilters) with CometParquetScan
+      dataFilters = scan.dataFilters) with CometParquetScan {
+      override def pushedAggregation: Option[CometParquetAggregation] = aggregation
+    }
 }
@@ -25,7 +25,7 @@ import java.sql.{Date, Timestamp}
 import java.time.{Duration, Instant, LocalDate, Period}
 import java.util.Locale
 
-import scala.collection.JavaConverters.asScalaBufferConverter
+import scala.collection.JavaConverters.{asScalaBufferConverter, setAsJavaSetConverter}
 
 import org.apache.parquet.column.statistics.{Statistics => ParquetStatistics}
 import org.apache.parquet.filter2.predicate._
@@ -475,6 +475,63 @@ class ParquetFilters(
         FilterApi.gtEq(binaryColumn(n), decimalToByteArray(v.asInstanceOf[JBigDecimal], length))
   }
 
+  // Unlike the range of the values from `makeInPredicate`, Parquet's `in` predicate can also be
+  // evaluated against the dictionary page of a fully dictionary-encoded column chunk, so that
+  // the row group is skipped if none of the values is in the dictionary. It's only available
+  // from Parquet 1.13, otherwise `makeInPredicate` is used.
+  private val makeInSet
+      : PartialFunction[ParquetSchemaType, (Array[String], Array[Any]) => FilterPredicate] = {
+    case ParquetByteType | ParquetShortType | ParquetIntegerType =>
+      (n: Array[String], v: Array[Any]) =>
+        SparkFilterApi.in(intColumn(n), v.map(toIntValue).toSet.asJava)
+    case ParquetLongType =>
+      (n: Array[String], v: Array[Any]) =>
+        SparkFilterApi.in(longColumn(n), v.map(toLongValue).toSet.asJava)
+    case ParquetFloatType =>
+      (n: Array[String], v: Array[Any]) =>
+        SparkFilterApi.in(floatColumn(n), v.map(_.asInstanceOf[JFloat]).toSet.asJava)
+    case ParquetDoubleType =>
+      (n: Array[String], v: Array[Any]) =>
+        SparkFilterApi.in(doubleColumn(n), v.map(_.asInstanceOf[JDouble]).toSet.asJava)
+
+    case ParquetStringType =>
+      (n: Array[String], v: Array[Any]) =>
+        SparkFilterApi.in(
+          binaryColumn(n),
+          v.map(s => Binary.fromString(s.asInstanceOf[String])).toSet.asJava)
+    case ParquetBinaryType =>
+      (n: Array[String], v: Array[Any]) =>
+        SparkFilterApi.in(
+          binaryColumn(n),
+          v.map(b => Binary.fromReusedByteArray(b.asInstanceOf[Array[Byte]])).toSet.asJava)
+    case ParquetDateType if pushDownDate =>
+      (n: Array[String], v: Array[Any]) =>
+        SparkFilterApi.in(intColumn(n), v.map(dateToDays(_): Integer).toSet.asJava)
+    case ParquetTimestampMicrosType if pushDownTimestamp =>
+      (n: Array[String], v: Array[Any]) =>
+        SparkFilterApi.in(longColumn(n), v.map(timestampToMicros).toSet.asJava)
+    case ParquetTimestampMillisType if pushDownTimestamp =>
+      (n: Array[String], v: Array[Any]) =>
+        SparkFilterApi.in(longColumn(n), v.map(timestampToMillis).toSet.asJava)
+
+    case ParquetSchemaType(_: DecimalLogicalTypeAnnotation, INT32, _) if pushDownDecimal =>
+      (n: Array[String], v: Array[Any]) =>
+        SparkFilterApi.in(
+          intColumn(n),
+          v.map(d => decimalToInt32(d.asInstanceOf[JBigDecimal])).toSet.asJava)
+    case ParquetSchemaType(_: DecimalLogicalTypeAnnotation, INT64, _) if pushDownDecimal =>
+      (n: Array[String], v: Array[Any]) =>
+        SparkFilterApi.in(
+          longColumn(n),
+          v.map(d => decimalToInt64(d.asInstanceOf[JBigDecimal])).toSet.asJava)
+    case ParquetSchemaType(_: DecimalLogicalTypeAnnotation, FIXED_LEN_BYTE_ARRAY, length)
+        if pushDownDecimal =>
+      (n: Array[String], v: Array[Any]) =>
+        SparkFilterApi.in(
+          binaryColumn(n),
+          v.map(d => decimalToByteArray(d.asInstanceOf[JBigDecimal], length)).toSet.asJava)
+  }
+
   private val makeInPredicate: PartialFunction[
     ParquetSchemaType,
     (Array[String], Array[Any], ParquetStatistics[_]) => FilterPredicate] = {
@@ -799,15 +856,19 @@ class ParquetFilters(
         } else if (canPartialPushDownConjuncts) {
           val primitiveType = schema.getColumnDescription(fieldNames).getPrimitiveType
           val statistics: ParquetStatistics[_] = ParquetStatistics.createStats(primitiveType)
+          def makeIn(values: Array[Any]): Option[FilterPredicate] =
+            makeInSet
+              .lift(fieldType)
+              .filter(_ => SparkFilterApi.isInSupported)
+              .map(_(fieldNames, values))
+              .orElse(makeInPredicate.lift(fieldType).map(_(fieldNames, values, statistics)))
           if (values.contains(null)) {
             Seq(
               makeEq.lift(fieldType).map(_(fieldNames, null)),
-              makeInPredicate
-                .lift(fieldType)
-                .map(_(fieldNames, values.filter(_ != null), statistics))).flatten
+              makeIn(values.filter(_ != null))).flatten
               .reduceLeftOption(FilterApi.or)
           } else {
-            makeInPredicate.lift(fieldType).map(_(fieldNames, values, statistics))
+            makeIn(values)
           }
         } else {
           None
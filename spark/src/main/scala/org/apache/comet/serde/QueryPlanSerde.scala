@@ -19,30 +19,39 @@
 
 package org.apache.comet.serde
 
+import java.net.URI
+
 import scala.collection.JavaConverters._
 
+import org.apache.hadoop.fs.Path
+import org.apache.hadoop.io.compress.CompressionCodecFactory
+
 import org.apache.spark.internal.Logging
 import org.apache.spark.sql.catalyst.expressions._
-import org.apache.spark.sql.catalyst.expressions.aggregate.{AggregateExpression, Average, BitAndAgg, BitOrAgg, BitXorAgg, Count, CovPopulation, CovSample, Final, First, Last, Max, Min, Partial, Sum, VariancePop, VarianceSamp}
+import org.apache.spark.sql.catalyst.expressions.aggregate.{AggregateExpression, ApproximatePercentile, Average, BitAndAgg, BitOrAgg, BitXorAgg, CollectList, CollectSet, Count, CovPopulation, CovSample, Final, First, Last, Max, Min, Partial, Percentile, Sum, VariancePop, VarianceSamp}
 import org.apache.spark.sql.catalyst.expressions.objects.StaticInvoke
 import org.apache.spark.sql.catalyst.optimizer.{BuildRight, NormalizeNaNAndZero}
 import org.apache.spark.sql.catalyst.plans._
-import org.apache.spark.sql.catalyst.plans.physical.{HashPartitioning, Partitioning, SinglePartition}
-import org.apache.spark.sql.catalyst.util.CharVarcharCodegenUtils
+import org.apache.spark.sql.catalyst.plans.physical.{HashPartitioning, Partitioning, RoundRobinPartitioning, SinglePartition}
+import org.apache.spark.sql.catalyst.util.{ArrayData, CaseInsensitiveMap, CharVarcharCodegenUtils}
 import org.apache.spark.sql.comet.{CometBroadcastExchangeExec, CometRowToColumnarExec, CometSinkPlaceHolder, DecimalPrecision}
+import org.apache.spark.sql.comet.execution.arrow.CometArrowConverters
 import org.apache.spark.sql.comet.execution.shuffle.CometShuffleExchangeExec
 import org.apache.spark.sql.execution
 import org.apache.spark.sql.execution._
-import org.apache.spark.sql.execution.adaptive.{BroadcastQueryStageExec, ShuffleQueryStageExec}
-import org.apache.spark.sql.execution.aggregate.HashAggregateExec
+import org.apache.spark.sql.execution.adaptive.{AQEShuffleReadExec, BroadcastQueryStageExec, ShuffleQueryStageExec}
+import org.apache.spark.sql.execution.aggregate.{BaseAggregateExec, HashAggregateExec, ObjectHashAggregateExec}
+import org.apache.spark.sql.execution.datasources.FilePartition
+import org.apache.spark.sql.execution.datasources.csv.CSVFileFormat
+import org.apache.spark.sql.execution.datasources.json.JsonFileFormat
 import org.apache.spark.sql.execution.exchange.{BroadcastExchangeExec, ReusedExchangeExec, ShuffleExchangeExec}
 import org.apache.spark.sql.execution.joins.{BroadcastHashJoinExec, HashJoin, ShuffledHashJoinExec, SortMergeJoinExec}
 import org.apache.spark.sql.internal.SQLConf
 import org.apache.spark.sql.types._
-import org.apache.spark.unsafe.types.UTF8String
+import org.apache.spark.unsafe.types.{CalendarInterval, UTF8String}
 
-import org.apache.comet.CometConf
-import org.apache.comet.CometSparkSessionExtensions.{isCometOperatorEnabled, isCometScan, isSpark32, isSpark34Plus, withInfo}
+import org.apache.comet.{CometConf, CometRuntimeException, NativeBase}
+import org.apache.comet.CometSparkSessionExtensions.{isCometOperatorEnabled, isCometScan, isSchemaSupported, isSpark32, isSpark34Plus, withInfo}
 import org.apache.comet.serde.ExprOuterClass.{AggExpr, DataType => ProtoDataType, Expr, ScalarFunc}
 import org.apache.comet.serde.ExprOuterClass.DataType.{DataTypeInfo, DecimalInfo, ListInfo, MapInfo, StructInfo}
 import org.apache.comet.serde.OperatorOuterClass.{AggregateMode => CometAggregateMode, JoinType, Operator}
@@ -56,13 +65,15 @@ object QueryPlanSerde extends Logging with ShimQueryPlanSerde {
     logWarning(s"Comet native execution is disabled due to: $reason")
   }
 
+  // `TimestampNTZType` is private in Spark 3.2.
+  private def isTimestampNTZType(dt: DataType): Boolean = dt.typeName == "timestamp_ntz"
+
   def supportedDataType(dt: DataType): Boolean = dt match {
     case _: ByteType | _: ShortType | _: IntegerType | _: LongType | _: FloatType |
         _: DoubleType | _: StringType | _: BinaryType | _: TimestampType | _: DecimalType |
         _: DateType | _: BooleanType | _: NullType =>
       true
-    // `TimestampNTZType` is private in Spark 3.2.
-    case dt if dt.typeName == "timestamp_ntz" => true
+    case dt if isTimestampNTZType(dt) => true
     case dt =>
       emitWarning(s"unsupported Spark data type: $dt")
       false
@@ -86,7 +97,7 @@ object QueryPlanSerde extends Logging with ShimQueryPlanSerde {
       case _: BinaryType => 8
       case _: TimestampType => 9
       case _: DecimalType => 10
-      case dt if dt.typeName == "timestamp_ntz" => 11
+      case dt if isTimestampNTZType(dt) => 11
       case _: DateType => 12
       case _: NullType => 13
       case _: ArrayType => 14
@@ -187,6 +198,7 @@ object QueryPlanSerde extends Logging with ShimQueryPlanSerde {
   private def minMaxDataTypeSupported(dt: DataType): Boolean = {
     dt match {
       case _: NumericType | DateType | TimestampType | BooleanType => true
+      case dt if isTimestampNTZType(dt) => true
       case _ => false
     }
   }
@@ -203,7 +215,8 @@ object QueryPlanSerde extends Logging with ShimQueryPlanSerde {
       inputs: Seq[Attribute],
       binding: Boolean): Option[AggExpr] = {
     aggExpr.aggregateFunction match {
-      case s @ Sum(child, _) if sumDataTypeSupported(s.dataType) && isLegacyMode(s) =>
+      case s @ Sum(child, _)
+          if sumDataTypeSupported(s.dataType) && (isLegacyMode(s) || isTryMode(s)) =>
         val childExpr = exprToProto(child, inputs, binding)
         val dataType = serializeDataType(s.dataType)
 
@@ -212,6 +225,8 @@ object QueryPlanSerde extends Logging with ShimQueryPlanSerde {
           sumBuilder.setChild(childExpr.get)
           sumBuilder.setDatatype(dataType.get)
           sumBuilder.setFailOnError(getFailOnError(s))
+          sumBuilder.setTryMode(isTryMode(s))
+          sumBuilder.setSequential(CometConf.COMET_EXEC_SEQUENTIAL_FLOAT_AGG_ENABLED.get())
 
           Some(
             ExprOuterClass.AggExpr
@@ -226,7 +241,9 @@ object QueryPlanSerde extends Logging with ShimQueryPlanSerde {
           }
           None
         }
-      case s @ Average(child, _) if avgDataTypeSupported(s.dataType) && isLegacyMode(s) =>
+      // The sum of `try_avg` is a double, or a decimal which is null on overflow like legacy mode
+      case s @ Average(child, _)
+          if avgDataTypeSupported(s.dataType) && (isLegacyMode(s) || isTryMode(s)) =>
         val childExpr = exprToProto(child, inputs, binding)
         val dataType = serializeDataType(s.dataType)
 
@@ -249,6 +266,7 @@ object QueryPlanSerde extends Logging with ShimQueryPlanSerde {
           builder.setDatatype(dataType.get)
           builder.setFailOnError(getFailOnError(s))
           builder.setSumDatatype(sumDataType.get)
+          builder.setSequential(CometConf.COMET_EXEC_SEQUENTIAL_FLOAT_AGG_ENABLED.get())
 
           Some(
             ExprOuterClass.AggExpr
@@ -505,6 +523,140 @@ object QueryPlanSerde extends Logging with ShimQueryPlanSerde {
           withInfo(aggExpr, child)
           None
         }
+      case collect @ (_: CollectList | _: CollectSet) =>
+        val child = collect.children.head
+        val childExpr = exprToProto(child, inputs, binding)
+        val dataType = serializeDataType(child.dataType)
+
+        // The aggregation buffer is serialized natively in the same format as Spark, which is
+        // only implemented for the element types below.
+        val supportedElementType = child.dataType match {
+          case BooleanType | ByteType | ShortType | IntegerType | LongType | FloatType |
+              DoubleType | DateType | TimestampType | StringType | BinaryType =>
+            true
+          case t: DecimalType => t.precision <= Decimal.MAX_LONG_DIGITS
+          case _ => false
+        }
+
+        if (supportedElementType && childExpr.isDefined && dataType.isDefined) {
+          val builder = ExprOuterClass.AggExpr.newBuilder()
+          collect match {
+            case _: CollectList =>
+              builder.setCollectList(
+                ExprOuterClass.CollectList
+                  .newBuilder()
+                  .setChild(childExpr.get)
+                  .setDatatype(dataType.get))
+            case _ =>
+              builder.setCollectSet(
+                ExprOuterClass.CollectSet
+                  .newBuilder()
+                  .setChild(childExpr.get)
+                  .setDatatype(dataType.get))
+          }
+          Some(builder.build())
+        } else {
+          withInfo(aggExpr, s"Unsupported element type ${child.dataType}", child)
+          None
+        }
+      case ap: ApproximatePercentile
+          if ap.percentageExpression.foldable && ap.accuracyExpression.foldable =>
+        val childExpr = exprToProto(ap.child, inputs, binding)
+        val dataType = serializeDataType(ap.child.dataType)
+
+        // The double percentiles are converted to the input type natively only for the types
+        // below
+        val supportedType = ap.child.dataType match {
+          case ByteType | ShortType | IntegerType | LongType | FloatType | DoubleType => true
+          case _ => false
+        }
+        val percentages = ap.percentageExpression.eval() match {
+          case p: Double => Some((false, Seq(p)))
+          case a: ArrayData => Some((true, a.toDoubleArray().toSeq))
+          case _ => None
+        }
+        val accuracy = ap.accuracyExpression.eval() match {
+          case n: java.lang.Number => Some(n.longValue())
+          case _ => None
+        }
+
+        if (!supportedType) {
+          withInfo(aggExpr, s"Unsupported data type ${ap.child.dataType}", ap.child)
+          None
+        } else if (childExpr.isDefined && dataType.isDefined && percentages.isDefined &&
+          accuracy.isDefined) {
+          val (returnArray, values) = percentages.get
+          val builder = ExprOuterClass.ApproxPercentile
+            .newBuilder()
+            .setChild(childExpr.get)
+            .setDatatype(dataType.get)
+            .setReturnArray(returnArray)
+            .setRelativeError(1.0 / accuracy.get)
+            .addAllPercentages(values.map(Double.box).asJava)
+          Some(ExprOuterClass.AggExpr.newBuilder().setApproxPercentile(builder).build())
+        } else {
+          withInfo(aggExpr, ap.children: _*)
+          None
+        }
+      case p: Percentile if p.percentageExpression.foldable =>
+        val childExpr = exprToProto(p.child, inputs, binding)
+        val frequencyExpr = exprToProto(p.frequencyExpression, inputs, binding)
+        val dataType = serializeDataType(p.child.dataType)
+
+        // The values are serialized natively in the same format as Spark only for the fixed-width
+        // types below
+        val supportedType = p.child.dataType match {
+          case ByteType | ShortType | IntegerType | LongType | FloatType | DoubleType => true
+          case t: DecimalType => t.precision <= Decimal.MAX_LONG_DIGITS
+          case _ => false
+        }
+        val percentages = p.percentageExpression.eval() match {
+          case d: Double => Some((false, Seq(d)))
+          case a: ArrayData => Some((true, a.toDoubleArray().toSeq))
+          case _ => None
+        }
+
+        if (!supportedType) {
+          withInfo(aggExpr, s"Unsupported data type ${p.child.dataType}", p.child)
+          None
+        } else if (isReversedPercentile(p)) {
+          withInfo(aggExpr, "Descending percentile_cont is not supported")
+          None
+        } else if (childExpr.isDefined && frequencyExpr.isDefined && dataType.isDefined &&
+          percentages.isDefined) {
+          val (returnArray, values) = percentages.get
+          val builder = ExprOuterClass.Percentile
+            .newBuilder()
+            .setChild(childExpr.get)
+            .setFrequency(frequencyExpr.get)
+            .setDatatype(dataType.get)
+            .setReturnArray(returnArray)
+            .addAllPercentages(values.map(Double.box).asJava)
+          Some(ExprOuterClass.AggExpr.newBuilder().setPercentile(builder).build())
+        } else {
+          withInfo(aggExpr, p.children: _*)
+          None
+        }
+      case bf if isBloomFilterAggregate(bf) =>
+        // The child is the 64-bit hash of the key of a runtime filter, e.g., `xxhash64(key)`
+        val child = bf.children.head
+        val childExpr = exprToProto(child, inputs, binding)
+        val (expectedNumItems, numBits) = getBloomFilterAggregateSize(bf)
+
+        if (child.dataType != LongType) {
+          withInfo(aggExpr, s"Unsupported data type ${child.dataType}", child)
+          None
+        } else if (childExpr.isDefined) {
+          val builder = ExprOuterClass.BloomFilterAgg
+            .newBuilder()
+            .setChild(childExpr.get)
+            .setExpectedNumItems(expectedNumItems)
+            .setNumBits(numBits)
+          Some(ExprOuterClass.AggExpr.newBuilder().setBloomFilterAgg(builder).build())
+        } else {
+          withInfo(aggExpr, child)
+          None
+        }
       case fn =>
         val msg = s"unsupported Spark aggregate function: ${fn.prettyName}"
         emitWarning(msg)
@@ -591,6 +743,9 @@ object QueryPlanSerde extends Logging with ShimQueryPlanSerde {
                 // https://github.com/apache/datafusion-comet/issues/328
                 withInfo(expr, s"${CometConf.COMET_CAST_STRING_TO_TIMESTAMP.key} is disabled")
                 false
+              case (DataTypes.StringType, dt) if isTimestampNTZType(dt) =>
+                withInfo(expr, s"Cast from $StringType to $dt is not supported")
+                false
               case _ => true
             }
             if (supportedCast) {
@@ -614,6 +769,7 @@ object QueryPlanSerde extends Logging with ShimQueryPlanSerde {
             addBuilder.setLeft(leftExpr.get)
             addBuilder.setRight(rightExpr.get)
             addBuilder.setFailOnError(getFailOnError(add))
+            addBuilder.setAllowPrecisionLoss(SQLConf.get.decimalOperationsAllowPrecisionLoss)
             serializeDataType(add.dataType).foreach { t =>
               addBuilder.setReturnType(t)
             }
@@ -641,6 +797,7 @@ object QueryPlanSerde extends Logging with ShimQueryPlanSerde {
             builder.setLeft(leftExpr.get)
             builder.setRight(rightExpr.get)
             builder.setFailOnError(getFailOnError(sub))
+            builder.setAllowPrecisionLoss(SQLConf.get.decimalOperationsAllowPrecisionLoss)
             serializeDataType(sub.dataType).foreach { t =>
               builder.setReturnType(t)
             }
@@ -669,6 +826,7 @@ object QueryPlanSerde extends Logging with ShimQueryPlanSerde {
             builder.setLeft(leftExpr.get)
             builder.setRight(rightExpr.get)
             builder.setFailOnError(getFailOnError(mul))
+            builder.setAllowPrecisionLoss(SQLConf.get.decimalOperationsAllowPrecisionLoss)
             serializeDataType(mul.dataType).foreach { t =>
               builder.setReturnType(t)
             }
@@ -695,16 +853,14 @@ object QueryPlanSerde extends Logging with ShimQueryPlanSerde {
         case div @ Divide(left, right, _)
             if supportedDataType(left.dataType) && !decimalBeforeSpark34(left.dataType) =>
           val leftExpr = exprToProtoInternal(left, inputs)
-          // Datafusion now throws an exception for dividing by zero
-          // See https://github.com/apache/arrow-datafusion/pull/6792
-          // For now, use NullIf to swap zeros with nulls.
-          val rightExpr = exprToProtoInternal(nullIfWhenPrimitive(right), inputs)
+          val rightExpr = exprToProtoInternal(right, inputs)
 
           if (leftExpr.isDefined && rightExpr.isDefined) {
             val builder = ExprOuterClass.Divide.newBuilder()
             builder.setLeft(leftExpr.get)
             builder.setRight(rightExpr.get)
             builder.setFailOnError(getFailOnError(div))
+            builder.setAllowPrecisionLoss(SQLConf.get.decimalOperationsAllowPrecisionLoss)
             serializeDataType(div.dataType).foreach { t =>
               builder.setReturnType(t)
             }
@@ -727,16 +883,49 @@ object QueryPlanSerde extends Logging with ShimQueryPlanSerde {
           }
           None
 
+        case div @ IntegralDivide(left, right, _)
+            if supportedDataType(left.dataType) && !decimalBeforeSpark34(left.dataType) =>
+          val leftExpr = exprToProtoInternal(left, inputs)
+          val rightExpr = exprToProtoInternal(right, inputs)
+
+          if (leftExpr.isDefined && rightExpr.isDefined) {
+            val builder = ExprOuterClass.IntegralDivide.newBuilder()
+            builder.setLeft(leftExpr.get)
+            builder.setRight(rightExpr.get)
+            builder.setFailOnError(getFailOnError(div))
+            serializeDataType(div.dataType).foreach { t =>
+              builder.setReturnType(t)
+            }
+
+            Some(
+              ExprOuterClass.Expr
+                .newBuilder()
+                .setIntegralDivide(builder)
+                .build())
+          } else {
+            withInfo(div, left, right)
+            None
+          }
+        case div @ IntegralDivide(left, _, _) =>
+          if (!supportedDataType(left.dataType)) {
+            withInfo(div, s"Unsupported datatype ${left.dataType}")
+          }
+          if (decimalBeforeSpark34(left.dataType)) {
+            withInfo(div, "Decimal support requires Spark 3.4 or later")
+          }
+          None
+
         case rem @ Remainder(left, right, _)
             if supportedDataType(left.dataType) && !decimalBeforeSpark34(left.dataType) =>
           val leftExpr = exprToProtoInternal(left, inputs)
-          val rightExpr = exprToProtoInternal(nullIfWhenPrimitive(right), inputs)
+          val rightExpr = exprToProtoInternal(right, inputs)
 
           if (leftExpr.isDefined && rightExpr.isDefined) {
             val builder = ExprOuterClass.Remainder.newBuilder()
             builder.setLeft(leftExpr.get)
             builder.setRight(rightExpr.get)
             builder.setFailOnError(getFailOnError(rem))
+            builder.setAllowPrecisionLoss(SQLConf.get.decimalOperationsAllowPrecisionLoss)
             serializeDataType(rem.dataType).foreach { t =>
               builder.setReturnType(t)
             }
@@ -929,6 +1118,8 @@ object QueryPlanSerde extends Logging with ShimQueryPlanSerde {
               case _: StringType =>
                 exprBuilder.setStringVal(value.asInstanceOf[UTF8String].toString)
               case _: TimestampType => exprBuilder.setLongVal(value.asInstanceOf[Long])
+              case dt if isTimestampNTZType(dt) =>
+                exprBuilder.setLongVal(value.asInstanceOf[Long])
               case _: DecimalType =>
                 // Pass decimal literal as bytes.
                 val unscaled = value.asInstanceOf[Decimal].toBigDecimal.underlying.unscaledValue
@@ -1100,9 +1291,7 @@ object QueryPlanSerde extends Logging with ShimQueryPlanSerde {
           if (childExpr.isDefined) {
             val builder = ExprOuterClass.Hour.newBuilder()
             builder.setChild(childExpr.get)
-
-            val timeZone = timeZoneId.getOrElse("UTC")
-            builder.setTimezone(timeZone)
+            builder.setTimezone(timeFieldZone(child, timeZoneId))
 
             Some(
               ExprOuterClass.Expr
@@ -1120,9 +1309,7 @@ object QueryPlanSerde extends Logging with ShimQueryPlanSerde {
           if (childExpr.isDefined) {
             val builder = ExprOuterClass.Minute.newBuilder()
             builder.setChild(childExpr.get)
-
-            val timeZone = timeZoneId.getOrElse("UTC")
-            builder.setTimezone(timeZone)
+            builder.setTimezone(timeFieldZone(child, timeZoneId))
 
             Some(
               ExprOuterClass.Expr
@@ -1181,9 +1368,7 @@ object QueryPlanSerde extends Logging with ShimQueryPlanSerde {
           if (childExpr.isDefined) {
             val builder = ExprOuterClass.Second.newBuilder()
             builder.setChild(childExpr.get)
-
-            val timeZone = timeZoneId.getOrElse("UTC")
-            builder.setTimezone(timeZone)
+            builder.setTimezone(timeFieldZone(child, timeZoneId))
 
             Some(
               ExprOuterClass.Expr
@@ -1629,7 +1814,17 @@ object QueryPlanSerde extends Logging with ShimQueryPlanSerde {
 
         case Md5(child) =>
           val childExpr = exprToProtoInternal(child, inputs)
-          val optExpr = scalarExprToProto("md5", childExpr)
+          val optExpr = scalarExprToProtoWithReturnType("md5", StringType, childExpr)
+          optExprWithInfo(optExpr, expr, child)
+
+        case Sha1(child) =>
+          val childExpr = exprToProtoInternal(child, inputs)
+          val optExpr = scalarExprToProtoWithReturnType("sha1", StringType, childExpr)
+          optExprWithInfo(optExpr, expr, child)
+
+        case Crc32(child) =>
+          val childExpr = exprToProtoInternal(child, inputs)
+          val optExpr = scalarExprToProtoWithReturnType("crc32", LongType, childExpr)
           optExprWithInfo(optExpr, expr, child)
 
         case OctetLength(child) =>
@@ -1638,6 +1833,12 @@ object QueryPlanSerde extends Logging with ShimQueryPlanSerde {
           val optExpr = scalarExprToProto("octet_length", childExpr)
           optExprWithInfo(optExpr, expr, castExpr)
 
+        case Reverse(child) if child.dataType.isInstanceOf[ArrayType] =>
+          val childExpr = exprToProtoInternal(child, inputs)
+          val optExpr =
+            scalarExprToProtoWithReturnType("array_reverse", child.dataType, childExpr)
+          optExprWithInfo(optExpr, expr, child)
+
         case Reverse(child) =>
           val castExpr = Cast(child, StringType)
           val childExpr = exprToProtoInternal(castExpr, inputs)
@@ -1657,7 +1858,7 @@ object QueryPlanSerde extends Logging with ShimQueryPlanSerde {
           val rightCast = Cast(times, LongType)
           val leftExpr = exprToProtoInternal(leftCast, inputs)
           val rightExpr = exprToProtoInternal(rightCast, inputs)
-          val optExpr = scalarExprToProto("repeat", leftExpr, rightExpr)
+          val optExpr = scalarExprToProtoWithReturnType("repeat", StringType, leftExpr, rightExpr)
           optExprWithInfo(optExpr, expr, leftCast, rightCast)
 
         case StringReplace(src, search, replace) =>
@@ -1825,19 +2026,19 @@ object QueryPlanSerde extends Logging with ShimQueryPlanSerde {
           }
 
         case In(value, list) =>
-          in(expr, value, list, inputs, false)
+          in(expr, value, list, inputs, negate = false, inSet = false)
 
         case InSet(value, hset) =>
           val valueDataType = value.dataType
           val list = hset.map { setVal =>
             Literal(setVal, valueDataType)
           }.toSeq
-          // Change `InSet` to `In` expression
-          // We do Spark `InSet` optimization in native (DataFusion) side.
-          in(expr, value, list, inputs, false)
+          // Change `InSet` to `In` expression, whose floating-point values are compared by their
+          // bits like the hash set of `InSet`, and which is looked up in a hash set in native side
+          in(expr, value, list, inputs, negate = false, inSet = true)
 
         case Not(In(value, list)) =>
-          in(expr, value, list, inputs, true)
+          in(expr, value, list, inputs, negate = true, inSet = false)
 
         case Not(child) =>
           val childExpr = exprToProtoInternal(child, inputs)
@@ -1908,7 +2109,7 @@ object QueryPlanSerde extends Logging with ShimQueryPlanSerde {
             None
           }
 
-        case KnownFloatingPointNormalized(NormalizeNaNAndZero(expr)) =>
+        case FloatingPointNormalized(expr) =>
           val dataType = serializeDataType(expr.dataType)
           if (dataType.isEmpty) {
             withInfo(expr, s"Unsupported datatype ${expr.dataType}")
@@ -1936,6 +2137,26 @@ object QueryPlanSerde extends Logging with ShimQueryPlanSerde {
             .setDatatype(dataType.get)
           Some(ExprOuterClass.Expr.newBuilder().setSubquery(builder).build())
 
+        case DynamicPruningExpression(child) =>
+          // Dynamic pruning filters not pushed into the scan are evaluated like other predicates
+          val childExpr = exprToProtoInternal(child, inputs)
+          optExprWithInfo(childExpr, expr, child)
+
+        case in: InSubqueryExec if supportedDataType(in.child.dataType) =>
+          // The values are retrieved by native code when creating the native plan, after the
+          // subquery is executed
+          val childExpr = exprToProtoInternal(in.child, inputs)
+          if (childExpr.isDefined) {
+            val builder = ExprOuterClass.InSubquery
+              .newBuilder()
+              .setChild(childExpr.get)
+              .setId(in.exprId.id)
+            Some(ExprOuterClass.Expr.newBuilder().setInSubquery(builder).build())
+          } else {
+            withInfo(expr, in.child)
+            None
+          }
+
         case UnscaledValue(child) =>
           val childExpr = exprToProtoInternal(child, inputs)
           val optExpr = scalarExprToProtoWithReturnType("unscaled_value", LongType, childExpr)
@@ -1949,6 +2170,86 @@ object QueryPlanSerde extends Logging with ShimQueryPlanSerde {
             childExpr)
           optExprWithInfo(optExpr, expr, child)
 
+        case _: SparkPartitionID =>
+          Some(
+            ExprOuterClass.Expr
+              .newBuilder()
+              .setSparkPartitionId(ExprOuterClass.SparkPartitionId.newBuilder())
+              .build())
+
+        case _: Rand | _: MonotonicallyIncreasingID =>
+          // See `projectedNondeterministicToProto`
+          withInfo(expr, s"${expr.prettyName} is only supported as a whole projected column")
+          None
+
+        case _: Uuid =>
+          // Spark's `uuid` draws from a Mersenne Twister seeded per partition, which is not
+          // implemented natively
+          withInfo(expr, "uuid is not supported natively and falls back to Spark")
+          None
+
+        // The `window` and `session_window` columns of time-bucketed aggregations are structs
+        // created from the timestamps converted to microseconds, see Spark's `TimeWindowing` and
+        // `SessionWindowing`
+        case struct: CreateNamedStruct =>
+          val values = struct.valExprs.map(exprToProtoInternal(_, inputs))
+          if (values.forall(_.isDefined)) {
+            val builder = ExprOuterClass.CreateNamedStruct
+              .newBuilder()
+              .addAllValues(values.map(_.get).asJava)
+              .addAllNames(struct.names.map(_.toString).asJava)
+            Some(ExprOuterClass.Expr.newBuilder().setCreateNamedStruct(builder).build())
+          } else {
+            withInfo(expr, struct.valExprs: _*)
+            None
+          }
+
+        case GetStructField(child, ordinal, _) =>
+          val childExpr = exprToProtoInternal(child, inputs)
+          if (childExpr.isDefined) {
+            val builder = ExprOuterClass.GetStructField
+              .newBuilder()
+              .setChild(childExpr.get)
+              .setOrdinal(ordinal)
+            Some(ExprOuterClass.Expr.newBuilder().setGetStructField(builder).build())
+          } else {
+            withInfo(expr, child)
+            None
+          }
+
+        case PreciseTimestampConversion(child, _, toType) =>
+          val childExpr = exprToProtoInternal(child, inputs)
+          val dataType = serializeDataType(toType)
+          if (childExpr.isDefined && dataType.isDefined) {
+            val builder = ExprOuterClass.PreciseTimestampConversion
+              .newBuilder()
+              .setChild(childExpr.get)
+              .setDatatype(dataType.get)
+            Some(ExprOuterClass.Expr.newBuilder().setPreciseTimestampConversion(builder).build())
+          } else {
+            withInfo(expr, child)
+            None
+          }
+
+        case TimeAdd(start, interval, _) if interval.foldable =>
+          // Only intervals of a fixed number of microseconds are supported, e.g., the gap of a
+          // session window, as the length of a month or a day depends on the time zone
+          val micros = interval.eval() match {
+            case i: CalendarInterval if i.months == 0 && i.days == 0 => Some(i.microseconds)
+            case i: Long if interval.dataType.isInstanceOf[DayTimeIntervalType] => Some(i)
+            case _ => None
+          }
+          micros match {
+            case Some(micros) =>
+              val startMicros = PreciseTimestampConversion(start, start.dataType, LongType)
+              val endMicros = Add(startMicros, Literal(micros))
+              val end = PreciseTimestampConversion(endMicros, LongType, start.dataType)
+              exprToProtoInternal(end, inputs)
+            case None =>
+              withInfo(expr, s"Unsupported interval $interval")
+              None
+          }
+
         case b @ BinaryExpression(_, _) if isBloomFilterMightContain(b) =>
           val bloomFilter = b.left
           val value = b.right
@@ -1983,6 +2284,32 @@ object QueryPlanSerde extends Logging with ShimQueryPlanSerde {
           // the seed is put at the end of the arguments
           scalarExprToProtoWithReturnType("murmur3_hash", IntegerType, exprs :+ seedExpr: _*)
 
+        case XxHash64(children, seed) =>
+          val firstUnSupportedInput = children.find(c => !supportedDataType(c.dataType))
+          if (firstUnSupportedInput.isDefined) {
+            withInfo(expr, s"Unsupported datatype ${firstUnSupportedInput.get.dataType}")
+            return None
+          }
+          val exprs = children.map(exprToProtoInternal(_, inputs))
+          val seedBuilder = ExprOuterClass.Literal
+            .newBuilder()
+            .setDatatype(serializeDataType(LongType).get)
+            .setLongVal(seed)
+          val seedExpr = Some(ExprOuterClass.Expr.newBuilder().setLiteral(seedBuilder).build())
+          // the seed is put at the end of the arguments
+          scalarExprToProtoWithReturnType("xxhash64", LongType, exprs :+ seedExpr: _*)
+
+        case HiveHash(children) =>
+          // Unlike a timestamp, a timestamp without time zone is hashed as a long by `HiveHash`
+          val firstUnSupportedInput = children.find(c =>
+            !supportedDataType(c.dataType) || isTimestampNTZType(c.dataType))
+          if (firstUnSupportedInput.isDefined) {
+            withInfo(expr, s"Unsupported datatype ${firstUnSupportedInput.get.dataType}")
+            return None
+          }
+          val exprs = children.map(exprToProtoInternal(_, inputs))
+          scalarExprToProtoWithReturnType("hive_hash", IntegerType, exprs: _*)
+
         case Sha2(left, numBits) =>
           if (!numBits.foldable) {
             withInfo(expr, "non literal numBits is not supported")
@@ -2006,6 +2333,9 @@ object QueryPlanSerde extends Logging with ShimQueryPlanSerde {
             scalarExprToProtoWithReturnType(algorithm, StringType, childExpr)
           }
 
+        case tagging: TaggingExpression =>
+          exprToProtoInternal(tagging.child, inputs)
+
         case _ =>
           withInfo(expr, s"${expr.prettyName} is not supported", expr.children: _*)
           None
@@ -2023,10 +2353,10 @@ object QueryPlanSerde extends Logging with ShimQueryPlanSerde {
       if (trimStr.isDefined) {
         val trimCast = Cast(trimStr.get, StringType)
         val trimExpr = exprToProtoInternal(trimCast, inputs)
-        val optExpr = scalarExprToProto(trimType, srcExpr, trimExpr)
+        val optExpr = scalarExprToProtoWithReturnType(trimType, StringType, srcExpr, trimExpr)
         optExprWithInfo(optExpr, expr, null, srcCast, trimCast)
       } else {
-        val optExpr = scalarExprToProto(trimType, srcExpr)
+        val optExpr = scalarExprToProtoWithReturnType(trimType, StringType, srcExpr)
         optExprWithInfo(optExpr, expr, null, srcCast)
       }
     }
@@ -2036,7 +2366,8 @@ object QueryPlanSerde extends Logging with ShimQueryPlanSerde {
         value: Expression,
         list: Seq[Expression],
         inputs: Seq[Attribute],
-        negate: Boolean): Option[Expr] = {
+        negate: Boolean,
+        inSet: Boolean): Option[Expr] = {
       val valueExpr = exprToProtoInternal(value, inputs)
       val listExprs = list.map(exprToProtoInternal(_, inputs))
       if (valueExpr.isDefined && listExprs.forall(_.isDefined)) {
@@ -2044,6 +2375,7 @@ object QueryPlanSerde extends Logging with ShimQueryPlanSerde {
         builder.setInValue(valueExpr.get)
         builder.addAllLists(listExprs.map(_.get).asJava)
         builder.setNegated(negate)
+        builder.setInSet(inSet)
         Some(
           ExprOuterClass.Expr
             .newBuilder()
@@ -2091,17 +2423,137 @@ object QueryPlanSerde extends Logging with ShimQueryPlanSerde {
     Some(ExprOuterClass.Expr.newBuilder().setScalarFunc(builder).build())
   }
 
-  def isPrimitive(expression: Expression): Boolean = expression.dataType match {
-    case _: ByteType | _: ShortType | _: IntegerType | _: LongType | _: FloatType |
-        _: DoubleType | _: TimestampType | _: DateType | _: BooleanType | _: DecimalType =>
-      true
-    case _ => false
+  /**
+   * The original keys of the join keys if they are all rewritten from `EqualNullSafe` by Spark,
+   * i.e., `coalesce(key, default)` and `isnull(key)` for each key, so a hash join can compare
+   * them with null equal to null instead. A sort merge join can't, as its children are sorted by
+   * the rewritten keys.
+   */
+  private def nullSafeJoinKeys(
+      leftKeys: Seq[Expression],
+      rightKeys: Seq[Expression]): Option[(Seq[Expression], Seq[Expression])] = {
+    val keys = leftKeys.zip(rightKeys).grouped(2).map {
+      case Seq(
+            (Coalesce(Seq(left, _: Literal)), Coalesce(Seq(right, _: Literal))),
+            (IsNull(leftIsNull), IsNull(rightIsNull)))
+          if left.semanticEquals(leftIsNull) && right.semanticEquals(rightIsNull) =>
+        Some((left, right))
+      case _ => None
+    }.toSeq
+    if (keys.nonEmpty && keys.forall(_.isDefined)) Some(keys.flatten.unzip) else None
+  }
+
+  /**
+   * The band condition `lower <= value <= upper` within the given join condition, where the value
+   * refers to the left side only and the bounds to the right side only, e.g., `a.ts BETWEEN
+   * b.start AND b.end`. A value with both bounds is preferred, but a single bound is a band too.
+   * Floating point values aren't supported, as natively -0.0 is less than 0.0.
+   */
+  private def bandCondition(
+      condition: Expression,
+      left: Seq[Attribute],
+      right: Seq[Attribute]): Option[OperatorOuterClass.BandCondition] = {
+    def refersTo(expr: Expression, output: Seq[Attribute]): Boolean =
+      expr.references.nonEmpty && expr.references.subsetOf(AttributeSet(output))
+    def conjuncts(expr: Expression): Seq[Expression] = expr match {
+      case And(l, r) => conjuncts(l) ++ conjuncts(r)
+      case e => Seq(e)
+    }
+    def supportedBandType(dt: DataType): Boolean = dt match {
+      case _: ByteType | _: ShortType | _: IntegerType | _: LongType | _: DecimalType |
+          _: DateType | _: TimestampType | _: StringType =>
+        true
+      case dt => isTimestampNTZType(dt)
+    }
+
+    // The (value, bound, whether the bound is a lower one) of the comparisons
+    val bounds = conjuncts(condition)
+      .flatMap {
+        case _: EqualTo | _: EqualNullSafe => None
+        case c: BinaryComparison =>
+          val lower = c.isInstanceOf[GreaterThan] || c.isInstanceOf[GreaterThanOrEqual]
+          if (refersTo(c.left, left) && refersTo(c.right, right)) {
+            Some((c.left, c.right, lower))
+          } else if (refersTo(c.left, right) && refersTo(c.right, left)) {
+            Some((c.right, c.left, !lower))
+          } else {
+            None
+          }
+        case _ => None
+      }
+      .filter { case (value, bound, _) =>
+        value.dataType == bound.dataType && supportedBandType(value.dataType)
+      }
+
+    val byValue = bounds.map(_._1.canonicalized).distinct.map { value =>
+      bounds.filter(_._1.canonicalized == value)
+    }
+    byValue
+      .find(b => b.exists(_._3) && b.exists(!_._3))
+      .orElse(byValue.headOption)
+      .flatMap { b =>
+        val value = exprToProto(b.head._1, left)
+        val lower = b.find(_._3).map(bound => exprToProto(bound._2, right))
+        val upper = b.find(!_._3).map(bound => exprToProto(bound._2, right))
+        if (value.isDefined && lower.forall(_.isDefined) && upper.forall(_.isDefined)) {
+          val builder = OperatorOuterClass.BandCondition.newBuilder().setValue(value.get)
+          lower.flatten.foreach(builder.setLower)
+          upper.flatten.foreach(builder.setUpper)
+          Some(builder.build())
+        } else {
+          None
+        }
+      }
+  }
+
+  /**
+   * Extracts the expression whose floating point values are normalized by the expressions of
+   * Spark's `NormalizeFloatingNumbers`, i.e., NaNs are canonical and -0.0 is 0.0. The fields of a
+   * non-null struct and the elements of an array are normalized too, which is what natively
+   * `NormalizeNaNAndZero` does for any data type.
+   */
+  private object FloatingPointNormalized {
+    def unapply(expr: Expression): Option[Expression] = expr match {
+      case KnownFloatingPointNormalized(NormalizeNaNAndZero(child)) => Some(child)
+      case KnownFloatingPointNormalized(
+            If(IsNull(child), Literal(null, _), struct: CreateNamedStruct))
+          if struct.valExprs.zipWithIndex.forall { case (value, i) =>
+            isNormalized(value, GetStructField(child, i))
+          } =>
+        Some(child)
+      case KnownFloatingPointNormalized(
+            ArrayTransform(child, LambdaFunction(function, Seq(element), _)))
+          if isNormalized(function, element) =>
+        Some(child)
+      case _ => None
+    }
+
+    private def isNormalized(expr: Expression, child: Expression): Boolean = expr match {
+      case FloatingPointNormalized(normalized) => normalized.semanticEquals(child)
+      case _ => expr.semanticEquals(child)
+    }
   }
 
-  def nullIfWhenPrimitive(expression: Expression): Expression = if (isPrimitive(expression)) {
-    new NullIf(expression, Literal.default(expression.dataType)).child
-  } else {
-    expression
+  /**
+   * The time zone to extract the time fields of `child` in, e.g., `hour`. Like Spark, the fields
+   * of a timestamp without time zone are extracted in UTC regardless of the session time zone.
+   */
+  private def timeFieldZone(child: Expression, timeZoneId: Option[String]): String =
+    if (isTimestampNTZType(child.dataType)) "UTC" else timeZoneId.getOrElse("UTC")
+
+  /**
+   * Serializes the distinct non-null values of an IN subquery as `InSubqueryValues`, which are
+   * retrieved by native code when creating the native plan.
+   */
+  def serializeInSubqueryValues(in: InSubqueryExec): Array[Byte] = {
+    val values = in.values().getOrElse {
+      throw new CometRuntimeException(s"The result of subquery ${in.exprId.id} is not available")
+    }
+    val builder = ExprOuterClass.InSubqueryValues.newBuilder()
+    values.filter(_ != null).distinct.foreach { value =>
+      builder.addValues(exprToProto(Literal(value, in.child.dataType), Seq.empty).get.getLiteral)
+    }
+    builder.build().toByteArray
   }
 
   /**
@@ -2116,13 +2568,51 @@ object QueryPlanSerde extends Logging with ShimQueryPlanSerde {
    *   The converted Comet native operator for the input `op`, or `None` if the `op` cannot be
    *   converted to a native operator.
    */
+  /**
+   * Serializes the nondeterministic expressions which are whole projected columns. Like Spark,
+   * the native side derives their values from the seed, the partition index and the number of
+   * rows evaluated before, so that task retries produce the same output. They are not supported
+   * inside other expressions, which may skip evaluating them for some rows unlike Spark, e.g.,
+   * conditional expressions.
+   */
+  private def projectedNondeterministicToProto(expr: NamedExpression): Option[Expr] = {
+    val child = expr match {
+      case Alias(child, _) => child
+      case other => other
+    }
+    child match {
+      case rand: Rand =>
+        val seed = rand.child match {
+          case Literal(seed: Long, _) => Some(seed)
+          case Literal(seed: Int, _) => Some(seed.toLong)
+          case Literal(null, _) => Some(0L)
+          case _ => None
+        }
+        seed.map { seed =>
+          ExprOuterClass.Expr
+            .newBuilder()
+            .setRand(ExprOuterClass.Rand.newBuilder().setSeed(seed))
+            .build()
+        }
+      case _: MonotonicallyIncreasingID =>
+        Some(
+          ExprOuterClass.Expr
+            .newBuilder()
+            .setMonotonicallyIncreasingId(ExprOuterClass.MonotonicallyIncreasingId.newBuilder())
+            .build())
+      case _ => None
+    }
+  }
+
   def operator2Proto(op: SparkPlan, childOp: Operator*): Option[Operator] = {
     val result = OperatorOuterClass.Operator.newBuilder()
     childOp.foreach(result.addChildren)
 
     op match {
       case ProjectExec(projectList, child) if isCometOperatorEnabled(op.conf, "project") =>
-        val exprs = projectList.map(exprToProto(_, child.output))
+        val exprs = projectList.map { expr =>
+          projectedNondeterministicToProto(expr).orElse(exprToProto(expr, child.output))
+        }
 
         if (exprs.forall(_.isDefined) && childOp.nonEmpty) {
           val projectBuilder = OperatorOuterClass.Projection
@@ -2208,24 +2698,50 @@ object QueryPlanSerde extends Logging with ShimQueryPlanSerde {
           None
         }
 
-      case HashAggregateExec(
-            _,
-            _,
-            _,
-            groupingExpressions,
-            aggregateExpressions,
-            aggregateAttributes,
-            _,
-            resultExpressions,
-            child) if isCometOperatorEnabled(op.conf, "aggregate") =>
-        if (groupingExpressions.isEmpty && aggregateExpressions.isEmpty) {
-          withInfo(op, "No group by or aggregation")
+      case GenerateExec(generator: ExplodeBase, requiredChildOutput, outer, _, child)
+          if isCometOperatorEnabled(op.conf, "generate") =>
+        val supportedType = generator.child.dataType match {
+          case ArrayType(elementType, _) => supportedDataType(elementType)
+          case MapType(keyType, valueType, _) =>
+            supportedDataType(keyType) && supportedDataType(valueType)
+          case _ => false
+        }
+        if (!supportedType) {
+          withInfo(op, s"Unsupported generator input type ${generator.child.dataType}")
           return None
         }
 
-        // Aggregate expressions with filter are not supported yet.
-        if (aggregateExpressions.exists(_.filter.isDefined)) {
-          withInfo(op, "Aggregate expression with filter is not supported")
+        val generatorExpr = exprToProto(generator.child, child.output)
+        if (generatorExpr.isDefined && childOp.nonEmpty) {
+          val requiredOutput = requiredChildOutput.map { attr =>
+            Integer.valueOf(child.output.indexWhere(_.exprId == attr.exprId))
+          }
+          val generateBuilder = OperatorOuterClass.Generate
+            .newBuilder()
+            .setChild(generatorExpr.get)
+            .addAllRequiredChildOutput(requiredOutput.asJava)
+            .setPosition(generator.position)
+            .setOuter(outer)
+          Some(result.setGenerate(generateBuilder).build())
+        } else {
+          withInfo(op, generator.child)
+          None
+        }
+
+      // `ObjectHashAggregateExec` is used by Spark for aggregate functions with variable-size
+      // aggregation buffers like `collect_list`, which are supported by native hash aggregation.
+      case aggregate: BaseAggregateExec
+          if (aggregate.isInstanceOf[HashAggregateExec] ||
+            aggregate.isInstanceOf[ObjectHashAggregateExec]) &&
+            isCometOperatorEnabled(op.conf, "aggregate") =>
+        val groupingExpressions = aggregate.groupingExpressions
+        val aggregateExpressions = aggregate.aggregateExpressions
+        val aggregateAttributes = aggregate.aggregateAttributes
+        val resultExpressions = aggregate.resultExpressions
+        val child = aggregate.child
+
+        if (groupingExpressions.isEmpty && aggregateExpressions.isEmpty) {
+          withInfo(op, "No group by or aggregation")
           return None
         }
 
@@ -2284,8 +2800,22 @@ object QueryPlanSerde extends Logging with ShimQueryPlanSerde {
           // `output` is only used when `binding` is true (i.e., non-Final)
           val output = child.output
 
-          val aggExprs =
-            aggregateExpressions.map(aggExprToProto(_, output, binding))
+          // The filters of aggregate expressions, e.g., `COUNT(*) FILTER (WHERE ...)`, are only
+          // evaluated by partial aggregation, as the rows are already filtered in final mode.
+          val aggExprs = aggregateExpressions.map { aggExpr =>
+            aggExprToProto(aggExpr, output, binding).flatMap { proto =>
+              aggExpr.filter match {
+                case Some(filter) if mode == CometAggregateMode.Partial =>
+                  exprToProto(filter, output)
+                    .map(proto.toBuilder.setFilter(_).build())
+                    .orElse {
+                      withInfo(aggExpr, filter)
+                      None
+                    }
+                case _ => Some(proto)
+              }
+            }
+          }
           if (childOp.nonEmpty && groupingExprs.forall(_.isDefined) &&
             aggExprs.forall(_.isDefined)) {
             val hashAggBuilder = OperatorOuterClass.HashAggregate.newBuilder()
@@ -2323,7 +2853,14 @@ object QueryPlanSerde extends Logging with ShimQueryPlanSerde {
           return None
         }
 
-        if (join.buildSide == BuildRight) {
+        // `NOT IN` subquery with a single nullable key, whose build side is the right side. It is
+        // executed by a dedicated native operator.
+        val nullAwareAntiJoin = join match {
+          case b: BroadcastHashJoinExec => b.isNullAwareAntiJoin
+          case _ => false
+        }
+
+        if (join.buildSide == BuildRight && !nullAwareAntiJoin) {
           // DataFusion HashJoin assumes build side is always left.
           // TODO: support BuildRight
           withInfo(join, "BuildRight is not supported")
@@ -2352,8 +2889,13 @@ object QueryPlanSerde extends Logging with ShimQueryPlanSerde {
             return None
         }
 
-        val leftKeys = join.leftKeys.map(exprToProto(_, join.left.output))
-        val rightKeys = join.rightKeys.map(exprToProto(_, join.right.output))
+        // Null-safe join keys are compared natively with null equal to null, rather than by the
+        // keys Spark rewrites them to
+        val nullSafeKeys = nullSafeJoinKeys(join.leftKeys, join.rightKeys)
+        val (joinLeftKeys, joinRightKeys) =
+          nullSafeKeys.getOrElse((join.leftKeys, join.rightKeys))
+        val leftKeys = joinLeftKeys.map(exprToProto(_, join.left.output))
+        val rightKeys = joinRightKeys.map(exprToProto(_, join.right.output))
 
         if (leftKeys.forall(_.isDefined) &&
           rightKeys.forall(_.isDefined) &&
@@ -2363,6 +2905,8 @@ object QueryPlanSerde extends Logging with ShimQueryPlanSerde {
             .setJoinType(joinType)
             .addAllLeftJoinKeys(leftKeys.map(_.get).asJava)
             .addAllRightJoinKeys(rightKeys.map(_.get).asJava)
+            .setNullAwareAntiJoin(nullAwareAntiJoin)
+            .setNullEqualsNull(nullSafeKeys.isDefined)
           condition.foreach(joinBuilder.setCondition)
           Some(result.setHashJoin(joinBuilder).build())
         } else {
@@ -2391,25 +2935,54 @@ object QueryPlanSerde extends Logging with ShimQueryPlanSerde {
           }
         }
 
-        // TODO: Support SortMergeJoin with join condition after new DataFusion release
-        if (join.condition.isDefined) {
-          withInfo(op, "Sort merge join with a join condition is not supported")
-          return None
+        // A join condition is only supported with a band condition within it, e.g.,
+        // `a.ts BETWEEN b.start AND b.end`, by which the rows with the same join keys are joined
+        // with a sliding window
+        val band = join.condition.map { cond =>
+          if (!NativeBase.hasCapability(NativeBase.CAPABILITY_BAND_JOIN)) {
+            withInfo(op, "The native library doesn't support sort merge joins with a condition")
+            return None
+          }
+          bandCondition(cond, join.left.output, join.right.output).getOrElse {
+            withInfo(op, s"Sort merge join without a band condition in its condition $cond")
+            return None
+          }
+        }
+        val condition = join.condition.map { cond =>
+          val condProto = exprToProto(cond, join.left.output ++ join.right.output)
+          if (condProto.isEmpty) {
+            withInfo(join, cond)
+            return None
+          }
+          condProto.get
         }
 
         val joinType = join.joinType match {
           case Inner => JoinType.Inner
           case LeftOuter => JoinType.LeftOuter
-          case RightOuter => JoinType.RightOuter
-          case FullOuter => JoinType.FullOuter
+          case RightOuter if band.isEmpty => JoinType.RightOuter
+          case FullOuter if band.isEmpty => JoinType.FullOuter
           case LeftSemi => JoinType.LeftSemi
           case LeftAnti => JoinType.LeftAnti
           case _ =>
-            // Spark doesn't support other join types
+            // Spark doesn't support other join types, and the right rows joined by a band
+            // condition aren't tracked
             withInfo(op, s"Unsupported join type ${join.joinType}")
             return None
         }
 
+        // DataFusion sort merge join doesn't support comparing nested join keys
+        val nestedKeys = join.leftKeys.filter { key =>
+          key.dataType match {
+            case _: StructType | _: ArrayType | _: MapType => true
+            case _ => false
+          }
+        }
+        if (nestedKeys.nonEmpty) {
+          withInfo(op, s"Unsupported nested join keys: ${nestedKeys.mkString(", ")}")
+          return None
+        }
+
         val leftKeys = join.leftKeys.map(exprToProto(_, join.left.output))
         val rightKeys = join.rightKeys.map(exprToProto(_, join.right.output))
 
@@ -2426,6 +2999,8 @@ object QueryPlanSerde extends Logging with ShimQueryPlanSerde {
             .addAllSortOptions(sortOptions.map(_.get).asJava)
             .addAllLeftJoinKeys(leftKeys.map(_.get).asJava)
             .addAllRightJoinKeys(rightKeys.map(_.get).asJava)
+          condition.foreach(joinBuilder.setCondition)
+          band.foreach(joinBuilder.setBand)
           Some(result.setSortMergeJoin(joinBuilder).build())
         } else {
           val allExprs: Seq[Expression] = join.leftKeys ++ join.rightKeys
@@ -2437,6 +3012,33 @@ object QueryPlanSerde extends Logging with ShimQueryPlanSerde {
         withInfo(join, "SortMergeJoin is not enabled")
         None
 
+      case scan: LocalTableScanExec if isCometOperatorEnabled(op.conf, "local_table_scan") =>
+        val scanTypes = scan.output.flatten { attr =>
+          serializeDataType(attr.dataType)
+        }
+
+        if (scan.output.isEmpty) {
+          withInfo(op, "LocalTableScan without output columns is not supported")
+          None
+        } else if (scanTypes.length == scan.output.length && isSchemaSupported(scan.schema)) {
+          // The rows are shipped within the plan, which is read by a single native task
+          val data = CometArrowConverters.toArrowStream(
+            scan.rows,
+            scan.schema,
+            op.conf.sessionLocalTimeZone)
+          val scanBuilder = OperatorOuterClass.LocalTableScan
+            .newBuilder()
+            .addAllFields(scanTypes.asJava)
+            .setData(com.google.protobuf.ByteString.copyFrom(data))
+          Some(result.setLocalTableScan(scanBuilder).build())
+        } else {
+          withInfo(op, s"Unsupported LocalTableScan data types: ${scan.schema}")
+          None
+        }
+
+      case scan: FileSourceScanExec if nativeFileFormat(scan).isDefined =>
+        fileScanToProto(scan, nativeFileFormat(scan).get).map(result.setFileScan(_).build())
+
       case op if isCometSink(op) =>
         // These operators are source of Comet native execution chain
         val scanBuilder = OperatorOuterClass.Scan.newBuilder()
@@ -2461,6 +3063,11 @@ object QueryPlanSerde extends Logging with ShimQueryPlanSerde {
           None
         }
 
+      case op: SampleExec =>
+        // Spark's Bernoulli and Poisson samplers are not implemented natively
+        withInfo(op, "Sample is not supported natively and falls back to Spark")
+        None
+
       case op =>
         // Emit warning if:
         //  1. it is not Spark shuffle operator, which is handled separately
@@ -2480,6 +3087,108 @@ object QueryPlanSerde extends Logging with ShimQueryPlanSerde {
    * `CometSinkPlaceHolder` later in `CometSparkSessionExtensions` after `operator2proto` is
    * called.
    */
+  /**
+   * Returns the name of the native file format reading the files of the given scan, if it is
+   * enabled by `spark.comet.scan.nativeFormats`.
+   */
+  private def nativeFileFormat(scan: FileSourceScanExec): Option[String] = {
+    val format = scan.relation.fileFormat match {
+      case _: CSVFileFormat => Some("csv")
+      case _: JsonFileFormat => Some("json")
+      case _ => None
+    }
+    format.filter(CometConf.COMET_SCAN_NATIVE_FORMATS.get(scan.conf).contains(_))
+  }
+
+  /**
+   * Serializes the given scan of CSV or JSON files into a native file scan. Only the scans of
+   * non-partitioned and non-bucketed tables of primitive columns, with the options the native
+   * readers follow, are supported.
+   */
+  private def fileScanToProto(
+      scan: FileSourceScanExec,
+      format: String): Option[OperatorOuterClass.FileScan] = {
+    val options = CaseInsensitiveMap(scan.relation.options)
+    // The options the native readers follow, besides the paths of the files
+    val supportedOptions = format match {
+      case "csv" => Set("path", "paths", "header", "inferSchema", "sep", "delimiter")
+      case "json" => Set("path", "paths")
+    }
+    val unsupportedOptions = options.keys.filterNot { key =>
+      supportedOptions.exists(_.equalsIgnoreCase(key))
+    }
+    val delimiter = options.getOrElse("sep", options.getOrElse("delimiter", ","))
+    val dataSchema = scan.relation.dataSchema
+    val primitiveTypes = dataSchema.forall { field =>
+      field.dataType match {
+        case BooleanType | ByteType | ShortType | IntegerType | LongType | FloatType |
+            DoubleType | StringType =>
+          true
+        case _ => false
+      }
+    }
+
+    if (!NativeBase.hasCapability(NativeBase.CAPABILITY_NATIVE_FILE_SCAN)) {
+      withInfo(scan, "The native library doesn't support native file scans")
+      None
+    } else if (scan.relation.partitionSchema.nonEmpty || scan.bucketedScan) {
+      withInfo(scan, "Native file scans of partitioned or bucketed tables are not supported")
+      None
+    } else if (!primitiveTypes || scan.output.isEmpty) {
+      withInfo(scan, s"Unsupported native file scan data types: $dataSchema")
+      None
+    } else if (unsupportedOptions.nonEmpty) {
+      withInfo(scan, s"Unsupported native file scan options: ${unsupportedOptions.mkString(",")}")
+      None
+    } else if (delimiter.length != 1 || delimiter.charAt(0) > 127) {
+      withInfo(scan, s"Unsupported CSV delimiter: $delimiter")
+      None
+    } else {
+      // The splits of the files are planned by Spark, so that the scan reads the same rows in
+      // the same partitions
+      val partitions = scan.inputRDDs().head.partitions.collect { case p: FilePartition => p }
+      val codecs =
+        new CompressionCodecFactory(scan.relation.sparkSession.sparkContext.hadoopConfiguration)
+      val files = partitions.flatMap(_.files).map(file => new URI(file.filePath.toString))
+      if (files.exists(uri => uri.getScheme != null && uri.getScheme != "file")) {
+        withInfo(scan, "Native file scans only read local files")
+        None
+      } else if (files.exists(uri => codecs.getCodec(new Path(uri)) != null)) {
+        withInfo(scan, "Native file scans of compressed files are not supported")
+        None
+      } else {
+        val scanBuilder = OperatorOuterClass.FileScan
+          .newBuilder()
+          .setFormat(format)
+          .addAllNames(dataSchema.fieldNames.toSeq.asJava)
+          .addAllFields(dataSchema.map(f => serializeDataType(f.dataType).get).asJava)
+        scan.output.foreach { attr =>
+          scanBuilder.addProjection(dataSchema.fieldIndex(attr.name))
+        }
+        partitions.foreach { partition =>
+          val partitionBuilder = OperatorOuterClass.FilePartition.newBuilder()
+          partition.files.foreach { file =>
+            partitionBuilder.addFiles(
+              OperatorOuterClass.FileSplit
+                .newBuilder()
+                .setPath(new URI(file.filePath.toString).getPath)
+                .setStart(file.start)
+                .setLength(file.length))
+          }
+          scanBuilder.addPartitions(partitionBuilder)
+        }
+        // The filters are evaluated again by Spark, so the ones not supported natively are
+        // skipped
+        scan.dataFilters.flatMap(exprToProto(_, scan.output)).foreach(scanBuilder.addFilters)
+        if (format == "csv") {
+          scanBuilder.putOptions("header", options.getOrElse("header", "false").toLowerCase)
+          scanBuilder.putOptions("delimiter", delimiter)
+        }
+        Some(scanBuilder.build())
+      }
+    }
+  }
+
   private def isCometSink(op: SparkPlan): Boolean = {
     op match {
       case s if isCometScan(s) => true
@@ -2491,8 +3200,11 @@ object QueryPlanSerde extends Logging with ShimQueryPlanSerde {
       case _: ShuffleExchangeExec => true
       case ShuffleQueryStageExec(_, _: CometShuffleExchangeExec, _) => true
       case ShuffleQueryStageExec(_, ReusedExchangeExec(_, _: CometShuffleExchangeExec), _) => true
+      case AQEShuffleReadExec(CometSinkPlaceHolder(_, _: ShuffleQueryStageExec, _), _) => true
       case _: TakeOrderedAndProjectExec => true
       case BroadcastQueryStageExec(_, _: CometBroadcastExchangeExec, _) => true
+      case BroadcastQueryStageExec(_, ReusedExchangeExec(_, _: CometBroadcastExchangeExec), _) =>
+        true
       case _: BroadcastExchangeExec => true
       case _ => false
     }
@@ -2518,6 +3230,7 @@ object QueryPlanSerde extends Logging with ShimQueryPlanSerde {
           _: DoubleType | _: StringType | _: BinaryType | _: TimestampType | _: DecimalType |
           _: DateType | _: BooleanType =>
         true
+      case dt if isTimestampNTZType(dt) => true
       case StructType(fields) =>
         fields.forall(f => supportedDataType(f.dataType))
       case ArrayType(ArrayType(_, _), _) => false // TODO: nested array is not supported
@@ -2557,6 +3270,7 @@ object QueryPlanSerde extends Logging with ShimQueryPlanSerde {
           _: DoubleType | _: StringType | _: BinaryType | _: TimestampType | _: DecimalType |
           _: DateType | _: BooleanType =>
         true
+      case dt if isTimestampNTZType(dt) => true
       case _ =>
         // Native shuffle doesn't support struct/array yet
         false
@@ -2574,6 +3288,7 @@ object QueryPlanSerde extends Logging with ShimQueryPlanSerde {
         case HashPartitioning(expressions, _) =>
           (expressions.map(QueryPlanSerde.exprToProto(_, inputs)).forall(_.isDefined), null)
         case SinglePartition => (true, null)
+        case RoundRobinPartitioning(_) => (true, null)
         case other =>
           val msg = s"unsupported Spark partitioning: ${other.getClass.getName}"
           emitWarning(msg)
@@ -43,8 +43,9 @@ import org.apache.spark.util.io.ChunkedByteBuffer
 
 import com.google.common.base.Objects
 
-import org.apache.comet.CometRuntimeException
+import org.apache.comet.{CometConf, CometRuntimeException, Native, NativeBase}
 import org.apache.comet.shims.ShimCometBroadcastExchangeExec
+import org.apache.comet.vector.NativeUtil
 
 /**
  * A [[CometBroadcastExchangeExec]] collects, transforms and finally broadcasts the result of a
@@ -97,16 +98,6 @@ case class CometBroadcastExchangeExec(originalPlan: SparkPlan, child: SparkPlan)
   @transient
   private lazy val maxBroadcastRows = 512000000
 
-  private var numPartitions: Option[Int] = None
-
-  def setNumPartitions(numPartitions: Int): CometBroadcastExchangeExec = {
-    this.numPartitions = Some(numPartitions)
-    this
-  }
-  def getNumPartitions(): Int = {
-    numPartitions.getOrElse(child.executeColumnar().getNumPartitions)
-  }
-
   @transient
   override lazy val relationFuture: Future[broadcast.Broadcast[Any]] = {
     SQLExecution.withThreadLocalCaptured[broadcast.Broadcast[Any]](
@@ -121,10 +112,10 @@ case class CometBroadcastExchangeExec(originalPlan: SparkPlan, child: SparkPlan)
         val beforeCollect = System.nanoTime()
 
         val countsAndBytes = child match {
-          case c: CometPlan => CometExec.getByteArrayRdd(c).collect()
+          case c: CometPlan => getByteArrayRdd(c).collect()
           case AQEShuffleReadExec(s: ShuffleQueryStageExec, _)
               if s.plan.isInstanceOf[CometPlan] =>
-            CometExec.getByteArrayRdd(s.plan.asInstanceOf[CometPlan]).collect()
+            getByteArrayRdd(s.plan.asInstanceOf[CometPlan]).collect()
           case AQEShuffleReadExec(s: ShuffleQueryStageExec, _) =>
             throw new CometRuntimeException(
               "Child of CometBroadcastExchangeExec should be CometExec, " +
@@ -198,6 +189,34 @@ case class CometBroadcastExchangeExec(originalPlan: SparkPlan, child: SparkPlan)
     }
   }
 
+  /**
+   * Whether the broadcasted batches are serialized natively. This is decided once, so the
+   * batches are always deserialized in the format they are serialized.
+   */
+  @transient
+  private lazy val nativeSerialization: Boolean =
+    CometConf.COMET_EXEC_BROADCAST_NATIVE_SERIALIZATION_ENABLED.get(conf)
+
+  /**
+   * The minimum size of the broadcasted batches to map them into memory on executors, or None if
+   * they are not mapped.
+   */
+  @transient
+  private lazy val mmapThreshold: Option[Long] =
+    if (nativeSerialization && CometConf.COMET_EXEC_BROADCAST_MMAP_ENABLED.get(conf)) {
+      Some(CometConf.COMET_EXEC_BROADCAST_MMAP_THRESHOLD.get(conf))
+    } else {
+      None
+    }
+
+  private def getByteArrayRdd(cometPlan: CometPlan): RDD[(Long, ChunkedByteBuffer)] = {
+    if (nativeSerialization) {
+      CometExec.getNativeByteArrayRdd(cometPlan)
+    } else {
+      CometExec.getByteArrayRdd(cometPlan)
+    }
+  }
+
   override protected def doPrepare(): Unit = {
     // Materialize the future.
     relationFuture
@@ -214,10 +233,24 @@ case class CometBroadcastExchangeExec(originalPlan: SparkPlan, child: SparkPlan)
     ColumnarToRowExec(this).executeCollect()
 
   // This is basically for unit test only, called by `executeCollect` indirectly.
-  override protected def doExecuteColumnar(): RDD[ColumnarBatch] = {
+  override protected def doExecuteColumnar(): RDD[ColumnarBatch] =
+    executeColumnar(child.executeColumnar().getNumPartitions)
+
+  /**
+   * Reads the broadcast batches in `numPartitions` partitions, so that they can be zipped with
+   * the other inputs of a native block. The broadcast is computed only once, and shared by all
+   * the consumers of this exchange, including the ones reusing it with different numbers of
+   * partitions.
+   */
+  def executeColumnar(numPartitions: Int): RDD[ColumnarBatch] = executeQuery {
     val broadcasted = executeBroadcast[Array[ChunkedByteBuffer]]()
 
-    new CometBatchRDD(sparkContext, getNumPartitions(), broadcasted)
+    new CometBatchRDD(
+      sparkContext,
+      numPartitions,
+      broadcasted,
+      if (nativeSerialization) Some(output.length) else None,
+      mmapThreshold)
   }
 
   override protected[sql] def doExecuteBroadcast[T](): broadcast.Broadcast[T] = {
@@ -271,11 +304,19 @@ object CometBroadcastExchangeExec {
  *   number of partitions
  * @param value
  *   the broadcasted batches which are serialized into an array of [[ChunkedByteBuffer]]s
+ * @param nativeNumCols
+ *   the number of columns of the batches if they are serialized natively, or None if they are
+ *   serialized by JVM
+ * @param mmapThreshold
+ *   the minimum size of natively serialized batches to map them into memory shared by the tasks
+ *   of each executor, or None if they are not mapped
  */
 class CometBatchRDD(
     sc: SparkContext,
     numPartitions: Int,
-    value: broadcast.Broadcast[Array[ChunkedByteBuffer]])
+    value: broadcast.Broadcast[Array[ChunkedByteBuffer]],
+    nativeNumCols: Option[Int] = None,
+    mmapThreshold: Option[Long] = None)
     extends RDD[ColumnarBatch](sc, Nil) {
 
   override def getPartitions: Array[Partition] = (0 until numPartitions).toArray.map { i =>
@@ -284,8 +325,22 @@ class CometBatchRDD(
 
   override def compute(split: Partition, context: TaskContext): Iterator[ColumnarBatch] = {
     val partition = split.asInstanceOf[CometBatchPartition]
-    partition.value.value.toIterator
-      .flatMap(CometExec.decodeBatches(_, this.getClass.getSimpleName))
+    nativeNumCols match {
+      case Some(numCols) =>
+        val native = new Native()
+        val nativeUtil = new NativeUtil()
+        val buffers = partition.value.value
+        if (mmapThreshold.exists(buffers.map(_.size).sum >= _) &&
+          NativeBase.hasCapability(NativeBase.CAPABILITY_MAPPED_BROADCAST)) {
+          CometExec.decodeMappedBatches(partition.value, numCols, native, nativeUtil)
+        } else {
+          buffers.toIterator
+            .flatMap(CometExec.decodeBatchesNatively(_, numCols, native, nativeUtil))
+        }
+      case None =>
+        partition.value.value.toIterator
+          .flatMap(CometExec.decodeBatches(_, this.getClass.getSimpleName))
+    }
   }
 }
 
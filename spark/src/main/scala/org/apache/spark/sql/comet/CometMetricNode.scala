@@ -79,6 +79,32 @@ object CometMetricNode {
         "total time (in ms) spent in this operator"))
   }
 
+  /**
+   * SQL metrics of the data crossing the JVM/native boundary of a native plan. They are reported
+   * on the root operator of the plan.
+   */
+  def jniMetrics(sc: SparkContext): Map[String, SQLMetric] = {
+    Map(
+      "jni_import_batches" ->
+        SQLMetrics.createMetric(sc, "number of batches imported from JVM"),
+      "jni_import_bytes" -> SQLMetrics.createSizeMetric(sc, "size of batches imported from JVM"),
+      "jni_export_batches" -> SQLMetrics.createMetric(sc, "number of batches exported to JVM"),
+      "jni_export_bytes" -> SQLMetrics.createSizeMetric(sc, "size of batches exported to JVM"),
+      "jni_callbacks" -> SQLMetrics.createMetric(sc, "number of calls from native into JVM"),
+      "jni_time" -> SQLMetrics.createNanoTimingMetric(
+        sc,
+        "total time (in ms) spent crossing the JVM/native boundary"))
+  }
+
+  /**
+   * SQL metrics of the spill files of a native plan, reported on the root operator of the plan.
+   */
+  def spillMetrics(sc: SparkContext): Map[String, SQLMetric] = {
+    Map(
+      "spill_disk_used" ->
+        SQLMetrics.createSizeMetric(sc, "peak disk space used by native spill files"))
+  }
+
   /**
    * SQL Metrics for DataFusion HashJoin
    */
@@ -101,6 +127,19 @@ object CometMetricNode {
       "join_time" -> SQLMetrics.createNanoTimingMetric(sc, "Total time for joining"))
   }
 
+  /**
+   * SQL Metrics for the native file scan
+   */
+  def fileScanMetrics(sc: SparkContext): Map[String, SQLMetric] = {
+    Map(
+      "output_rows" -> SQLMetrics.createMetric(sc, "number of output rows"),
+      "files_opened" -> SQLMetrics.createMetric(sc, "number of files opened"),
+      "parts_pruned" ->
+        SQLMetrics.createMetric(sc, "number of parts of files skipped by their metadata"),
+      "open_time" -> SQLMetrics.createNanoTimingMetric(sc, "total time (in ms) opening files"),
+      "read_time" -> SQLMetrics.createNanoTimingMetric(sc, "total time (in ms) reading files"))
+  }
+
   /**
    * Creates a [[CometMetricNode]] from a [[CometPlan]].
    */
@@ -0,0 +1,223 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one
+ * or more contributor license agreements.  See the NOTICE file
+ * distributed with this work for additional information
+ * regarding copyright ownership.  The ASF licenses this file
+ * to you under the Apache License, Version 2.0 (the
+ * "License"); you may not use this file except in compliance
+ * with the License.  You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+ * KIND, either express or implied.  See the License for the
+ * specific language governing permissions and limitations
+ * under the License.
+ */
+
+package org.apache.spark.sql.comet
+
+import scala.collection.mutable
+
+import org.json4s.JsonAST.JValue
+import org.json4s.JsonDSL._
+import org.json4s.jackson.JsonMethods.{compact, render}
+
+import org.apache.spark.internal.Logging
+import org.apache.spark.scheduler.SparkListenerEvent
+import org.apache.spark.sql.catalyst.trees.TreeNode
+import org.apache.spark.sql.execution.{InputAdapter, QueryExecution, SparkPlan, WholeStageCodegenExec}
+import org.apache.spark.sql.execution.adaptive.{AdaptiveSparkPlanExec, QueryStageExec}
+import org.apache.spark.sql.execution.exchange.ReusedExchangeExec
+import org.apache.spark.sql.util.QueryExecutionListener
+
+import org.apache.comet.{CometConf, CometExplainInfo}
+
+/**
+ * The metrics of an operator of an executed query.
+ *
+ * @param native
+ *   whether the operator is a Comet operator
+ * @param metrics
+ *   the non-zero SQL metrics of the operator, whose timings are in nanoseconds
+ */
+case class CometOperatorSummary(name: String, native: Boolean, metrics: Map[String, Long])
+
+/**
+ * A compact summary of what Comet did for an executed query: the metrics of its operators, their
+ * totals of rows, bytes, spills and time, and the reasons why operators fell back to Spark.
+ *
+ * @param fallbacks
+ *   the Spark operators which are not converted to Comet, with the reasons attached to them
+ */
+case class CometQuerySummary(
+    funcName: String,
+    succeeded: Boolean,
+    durationMs: Long,
+    operators: Seq[CometOperatorSummary],
+    fallbacks: Seq[(String, Seq[String])],
+    metricTypes: Map[String, String]) {
+
+  def nativeOperators: Seq[CometOperatorSummary] = operators.filter(_.native)
+
+  /** The number of rows produced by the native operators. */
+  def nativeRows: Long =
+    nativeOperators.map(op => op.metrics.getOrElse("output_rows", 0L)).sum
+
+  /** The totals of the size metrics of the native operators, in bytes. */
+  def bytes: Map[String, Long] = totals(nativeOperators, Some(CometQuerySummary.SIZE_TYPE))
+
+  /** The totals of the spill metrics of all the operators. */
+  def spills: Map[String, Long] =
+    totals(operators, None).filter { case (name, _) => name.toLowerCase.contains("spill") }
+
+  /** The totals of the timing metrics of the native operators, in milliseconds. */
+  def time: Map[String, Long] = {
+    val nanos = totals(nativeOperators, Some(CometQuerySummary.NS_TIMING_TYPE)).map {
+      case (name, value) => name -> value / 1000000
+    }
+    totals(nativeOperators, Some(CometQuerySummary.TIMING_TYPE)) ++ nanos
+  }
+
+  /** The totals of the metrics of the given operators by name, of the given type if any. */
+  private def totals(
+      ops: Seq[CometOperatorSummary],
+      metricType: Option[String]): Map[String, Long] = {
+    ops
+      .flatMap(_.metrics)
+      .filter { case (name, _) => metricType.forall(t => metricTypes.get(name).contains(t)) }
+      .groupBy(_._1)
+      .map { case (name, values) => name -> values.map(_._2).sum }
+  }
+
+  def toJson: String = {
+    val json: JValue =
+      ("funcName" -> funcName) ~
+        ("succeeded" -> succeeded) ~
+        ("durationMs" -> durationMs) ~
+        ("nativeOperators" -> nativeOperators.length) ~
+        ("sparkOperators" -> (operators.length - nativeOperators.length)) ~
+        ("nativeRows" -> nativeRows) ~
+        ("bytes" -> bytes) ~
+        ("spills" -> spills) ~
+        ("timeMs" -> time) ~
+        ("fallbacks" -> fallbacks.map { case (name, reasons) =>
+          ("operator" -> name) ~ ("reasons" -> reasons)
+        }) ~
+        ("operators" -> operators.map { op =>
+          ("name" -> op.name) ~ ("native" -> op.native) ~ ("metrics" -> op.metrics)
+        })
+    compact(render(json))
+  }
+}
+
+object CometQuerySummary {
+  // The types of `SQLMetric`, see `SQLMetrics`
+  private val SIZE_TYPE = "size"
+  private val TIMING_TYPE = "timing"
+  private val NS_TIMING_TYPE = "nsTiming"
+
+  /**
+   * Summarizes the given executed plan. The operators of the query stages of adaptive plans are
+   * included, and reused exchanges are only counted once.
+   */
+  def fromPlan(
+      plan: SparkPlan,
+      funcName: String = "",
+      succeeded: Boolean = true,
+      durationMs: Long = 0): CometQuerySummary = {
+    val operators = mutable.ArrayBuffer[CometOperatorSummary]()
+    val fallbacks = mutable.ArrayBuffer[(String, Seq[String])]()
+    val metricTypes = mutable.Map[String, String]()
+
+    def visit(node: TreeNode[_]): Unit = node match {
+      case p: AdaptiveSparkPlanExec => visit(p.executedPlan)
+      case p: QueryStageExec => visit(p.plan)
+      case p: InputAdapter => visit(p.child)
+      case p: WholeStageCodegenExec => visit(p.child)
+      case _: ReusedExchangeExec =>
+      case p: SparkPlan =>
+        val native = p.isInstanceOf[CometPlan]
+        val metrics = p.metrics.collect {
+          case (name, metric) if metric.value != 0 =>
+            metricTypes.getOrElseUpdate(name, metric.metricType)
+            name -> metric.value
+        }
+        operators += CometOperatorSummary(p.nodeName, native, metrics)
+        if (!native) {
+          p.getTagValue(CometExplainInfo.EXTENSION_INFO)
+            .map(_.split("\n").map(_.trim).filter(_.nonEmpty).distinct.toSeq)
+            .filter(_.nonEmpty)
+            .foreach(reasons => fallbacks += p.nodeName -> reasons)
+        }
+        p.innerChildren.foreach {
+          case c: TreeNode[_] => visit(c)
+          case _ =>
+        }
+        p.children.foreach(visit)
+      case _ =>
+    }
+
+    visit(plan)
+    CometQuerySummary(
+      funcName,
+      succeeded,
+      durationMs,
+      operators.toSeq,
+      fallbacks.toSeq,
+      metricTypes.toMap)
+  }
+}
+
+/**
+ * The event of a query execution summary, which is written to the Spark event log.
+ *
+ * @param summary
+ *   the summary in JSON, see [[CometQuerySummary.toJson]]
+ */
+case class CometQuerySummaryEvent(summary: String) extends SparkListenerEvent
+
+/**
+ * Produces a [[CometQuerySummary]] for each completed query, which is retained on the driver and
+ * posted as a [[CometQuerySummaryEvent]]. To enable it, add this class to the config
+ * `spark.sql.queryExecutionListeners`.
+ */
+class CometQuerySummaryListener extends QueryExecutionListener with Logging {
+
+  override def onSuccess(funcName: String, qe: QueryExecution, durationNs: Long): Unit =
+    summarize(funcName, qe, succeeded = true, durationNs / 1000000)
+
+  override def onFailure(funcName: String, qe: QueryExecution, exception: Exception): Unit =
+    summarize(funcName, qe, succeeded = false, 0)
+
+  private def summarize(
+      funcName: String,
+      qe: QueryExecution,
+      succeeded: Boolean,
+      durationMs: Long): Unit = {
+    // Listeners are called on the listener bus thread, without the active session
+    val conf = qe.sparkSession.sessionState.conf
+    val summary = CometQuerySummary.fromPlan(qe.executedPlan, funcName, succeeded, durationMs)
+    CometQuerySummaryListener.add(summary, CometConf.COMET_SUMMARY_RETAINED.get(conf))
+    if (CometConf.COMET_SUMMARY_EVENT_LOG_ENABLED.get(conf)) {
+      qe.sparkSession.sparkContext.listenerBus.post(CometQuerySummaryEvent(summary.toJson))
+    }
+    logDebug(s"Comet query summary: ${summary.toJson}")
+  }
+}
+
+object CometQuerySummaryListener {
+  private val retained = mutable.Queue[CometQuerySummary]()
+
+  /** The latest summaries produced on the driver, from the oldest to the latest. */
+  def summaries: Seq[CometQuerySummary] = retained.synchronized(retained.toList)
+
+  private def add(summary: CometQuerySummary, maxRetained: Int): Unit = retained.synchronized {
+    retained.enqueue(summary)
+    while (retained.length > maxRetained) {
+      retained.dequeue()
+    }
+  }
+}
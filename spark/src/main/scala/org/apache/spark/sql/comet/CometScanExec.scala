@@ -59,7 +59,8 @@ case class CometScanExec(
     dataFilters: Seq[Expression],
     tableIdentifier: Option[TableIdentifier],
     disableBucketedScan: Boolean = false,
-    wrapped: FileSourceScanExec)
+    wrapped: FileSourceScanExec,
+    pushedLimit: Option[Int] = None)
     extends DataSourceScanExec
     with ShimCometScanExec
     with CometPlan {
@@ -140,13 +141,15 @@ case class CometScanExec(
   }
 
   override lazy val metadata: Map[String, String] =
-    if (wrapped == null) Map.empty else wrapped.metadata
+    if (wrapped == null) Map.empty
+    else wrapped.metadata ++ pushedLimit.map("PushedLimit" -> _.toString)
 
   override def verboseStringWithOperatorId(): String = wrapped.verboseStringWithOperatorId()
 
   lazy val inputRDD: RDD[InternalRow] = {
     val options = relation.options +
-      (ShimFileFormat.OPTION_RETURNING_BATCH -> supportsColumnar.toString)
+      (ShimFileFormat.OPTION_RETURNING_BATCH -> supportsColumnar.toString) ++
+      pushedLimit.map(CometParquetFileFormat.OPTION_PUSHED_LIMIT -> _.toString)
     val readFile: (PartitionedFile) => Iterator[InternalRow] =
       relation.fileFormat.buildReaderWithPartitionValues(
         sparkSession = relation.sparkSession,
@@ -215,10 +218,14 @@ case class CometScanExec(
   protected override def doExecuteColumnar(): RDD[ColumnarBatch] = {
     val numOutputRows = longMetric("numOutputRows")
     val scanTime = longMetric("scanTime")
+    val limit = pushedLimit
     inputRDD.asInstanceOf[RDD[ColumnarBatch]].mapPartitionsInternal { batches =>
       new Iterator[ColumnarBatch] {
+        // The number of rows to read before reaching the pushed limit. The scan stops there
+        // without opening the remaining files, and the limit on top takes the rows it needs.
+        private var remainingRows = limit.map(_.toLong).getOrElse(Long.MaxValue)
 
-        override def hasNext: Boolean = {
+        override def hasNext: Boolean = remainingRows > 0 && {
           // The `FileScanRDD` returns an iterator which scans the file during the `hasNext` call.
           val startNs = System.nanoTime()
           val res = batches.hasNext
@@ -229,6 +236,7 @@ case class CometScanExec(
         override def next(): ColumnarBatch = {
           val batch = batches.next()
           numOutputRows += batch.numRows()
+          remainingRows -= batch.numRows()
           batch
         }
       }
@@ -396,7 +404,8 @@ case class CometScanExec(
         relation.partitionSchema,
         pushedDownFilters.toArray,
         new ParquetOptions(CaseInsensitiveMap(relation.options), sqlConf),
-        metrics)
+        metrics,
+        pushedLimit = pushedLimit)
 
       newDataSourceRDD(
         fsRelation.sparkSession.sparkContext,
@@ -434,7 +443,8 @@ case class CometScanExec(
       QueryPlan.normalizePredicates(dataFilters, output),
       None,
       disableBucketedScan,
-      null)
+      null,
+      pushedLimit)
   }
 }
 
@@ -19,8 +19,10 @@
 
 package org.apache.spark.sql.comet.execution.shuffle
 
-import java.nio.{ByteBuffer, ByteOrder}
-import java.nio.file.{Files, Paths}
+import java.io.{BufferedInputStream, DataInputStream, File, FileInputStream}
+import java.nio.ByteBuffer
+import java.nio.file.Files
+import java.util.Locale
 import java.util.function.Supplier
 
 import scala.collection.JavaConverters.asJavaIterableConverter
@@ -31,14 +33,15 @@ import org.apache.spark.internal.config
 import org.apache.spark.rdd.{MapPartitionsRDD, RDD}
 import org.apache.spark.scheduler.MapStatus
 import org.apache.spark.serializer.Serializer
-import org.apache.spark.shuffle.{IndexShuffleBlockResolver, ShuffleWriteMetricsReporter, ShuffleWriteProcessor}
+import org.apache.spark.shuffle.{IndexShuffleBlockResolver, ShuffleBlockPusher, ShuffleWriteMetricsReporter, ShuffleWriteProcessor}
 import org.apache.spark.shuffle.sort.SortShuffleManager
 import org.apache.spark.sql.catalyst.InternalRow
 import org.apache.spark.sql.catalyst.expressions.{Attribute, BoundReference, UnsafeProjection, UnsafeRow}
+import org.apache.spark.sql.catalyst.expressions.aggregate.Partial
 import org.apache.spark.sql.catalyst.expressions.codegen.LazilyGeneratedOrdering
 import org.apache.spark.sql.catalyst.plans.logical.Statistics
 import org.apache.spark.sql.catalyst.plans.physical._
-import org.apache.spark.sql.comet.{CometExec, CometMetricNode, CometPlan}
+import org.apache.spark.sql.comet.{CometExec, CometHashAggregateExec, CometMetricNode, CometPlan}
 import org.apache.spark.sql.execution._
 import org.apache.spark.sql.execution.exchange.{ENSURE_REQUIREMENTS, ShuffleExchangeLike, ShuffleOrigin}
 import org.apache.spark.sql.execution.exchange.ShuffleExchangeExec
@@ -46,12 +49,14 @@ import org.apache.spark.sql.execution.metric.{SQLMetric, SQLMetrics, SQLShuffleR
 import org.apache.spark.sql.internal.SQLConf
 import org.apache.spark.sql.types.StructType
 import org.apache.spark.sql.vectorized.ColumnarBatch
-import org.apache.spark.util.MutablePair
+import org.apache.spark.util.{MutablePair, ThreadUtils, Utils}
 import org.apache.spark.util.collection.unsafe.sort.{PrefixComparators, RecordComparator}
 import org.apache.spark.util.random.XORShiftRandom
 
 import com.google.common.base.Objects
+import com.google.protobuf.ByteString
 
+import org.apache.comet.{CometConf, CometShuffleBlockSink}
 import org.apache.comet.serde.{OperatorOuterClass, PartitioningOuterClass, QueryPlanSerde}
 import org.apache.comet.serde.OperatorOuterClass.Operator
 import org.apache.comet.serde.QueryPlanSerde.serializeDataType
@@ -78,6 +83,15 @@ case class CometShuffleExchangeExec(
     "dataSize" -> SQLMetrics.createSizeMetric(sparkContext, "data size"),
     "shuffleReadElapsedCompute" ->
       SQLMetrics.createNanoTimingMetric(sparkContext, "shuffle read elapsed compute at native"),
+    "spillDiskUsed" ->
+      SQLMetrics.createSizeMetric(sparkContext, "peak disk space used by native spill files"),
+    "spillCount" -> SQLMetrics.createMetric(sparkContext, "number of native spills"),
+    "spilledBytes" -> SQLMetrics.createSizeMetric(sparkContext, "native spill size"),
+    "encodeTime" ->
+      SQLMetrics.createNanoTimingMetric(sparkContext, "shuffle write encode time at native"),
+    "compressTime" ->
+      SQLMetrics.createNanoTimingMetric(sparkContext, "shuffle write compress time at native"),
+    "partitionDataSize" -> SQLMetrics.createSizeMetric(sparkContext, "partition data size"),
     "numPartitions" -> SQLMetrics.createMetric(
       sparkContext,
       "number of partitions")) ++ readMetrics ++ writeMetrics
@@ -110,10 +124,29 @@ case class CometShuffleExchangeExec(
     if (inputRDD.getNumPartitions == 0) {
       Future.successful(null)
     } else {
-      sparkContext.submitMapStage(shuffleDependency)
+      val executionId = sparkContext.getLocalProperty(SQLExecution.EXECUTION_ID_KEY)
+      sparkContext
+        .submitMapStage(shuffleDependency)
+        .map { stats =>
+          postPartitionDataSizes(executionId, stats)
+          stats
+        }(ThreadUtils.sameThread)
     }
   }
 
+  /**
+   * Posts the bytes written into each shuffle partition, which are only known once the map stage
+   * completes, to the `partitionDataSize` metric, so that the Spark UI shows their distribution.
+   */
+  private def postPartitionDataSizes(executionId: String, stats: MapOutputStatistics): Unit = {
+    val partitionDataSize = metrics("partitionDataSize")
+    partitionDataSize.set(stats.bytesByPartitionId.sum)
+    SQLMetrics.postDriverMetricsUpdatedByValue(
+      sparkContext,
+      executionId,
+      stats.bytesByPartitionId.map(partitionDataSize.id -> _).toSeq)
+  }
+
   override def numMappers: Int = shuffleDependency.rdd.getNumPartitions
 
   override def numPartitions: Int = shuffleDependency.partitioner.numPartitions
@@ -140,7 +173,8 @@ case class CometShuffleExchangeExec(
         child.output,
         outputPartitioning,
         serializer,
-        metrics)
+        metrics,
+        CometShuffleExchangeExec.shuffleCombiner(child, outputPartitioning))
       metrics("numPartitions").set(dep.partitioner.numPartitions)
       val executionId = sparkContext.getLocalProperty(SQLExecution.EXECUTION_ID_KEY)
       SQLMetrics.postDriverMetricUpdates(
@@ -221,22 +255,58 @@ object CometShuffleExchangeExec extends ShimCometShuffleExchangeExec {
       outputAttributes: Seq[Attribute],
       outputPartitioning: Partitioning,
       serializer: Serializer,
-      metrics: Map[String, SQLMetric]): ShuffleDependency[Int, ColumnarBatch, ColumnarBatch] = {
+      metrics: Map[String, SQLMetric],
+      combiner: Option[OperatorOuterClass.ShuffleCombiner] = None)
+      : ShuffleDependency[Int, ColumnarBatch, ColumnarBatch] = {
     val dependency = new CometShuffleDependency[Int, ColumnarBatch, ColumnarBatch](
       rdd.map(
         (0, _)
       ), // adding fake partitionId that is always 0 because ShuffleDependency requires it
       serializer = serializer,
       shuffleWriterProcessor =
-        new CometShuffleWriteProcessor(outputPartitioning, outputAttributes, metrics),
+        new CometShuffleWriteProcessor(outputPartitioning, outputAttributes, metrics, combiner),
       shuffleType = CometNativeShuffle,
       partitioner = new Partitioner {
         override def numPartitions: Int = outputPartitioning.numPartitions
         override def getPartition(key: Any): Int = key.asInstanceOf[Int]
-      })
+      },
+      schema = Some(StructType.fromAttributes(outputAttributes)))
     dependency
   }
 
+  /**
+   * Returns the combiner of the rows written by `plan` if it is a native partial aggregation,
+   * whose output is the grouping keys followed by the buffers of the aggregate functions, and
+   * combining is enabled. Like a final aggregation, the combiner merges the buffers of the rows
+   * of the same keys, so it only applies to hash partitioning by some of the keys.
+   */
+  def shuffleCombiner(
+      plan: SparkPlan,
+      partitioning: Partitioning): Option[OperatorOuterClass.ShuffleCombiner] = plan match {
+    case agg: CometHashAggregateExec
+        if CometConf.COMET_EXEC_SHUFFLE_COMBINE_ENABLED.get(agg.conf) &&
+          partitioning.isInstanceOf[HashPartitioning] &&
+          agg.mode.contains(Partial) &&
+          agg.groupingExpressions.nonEmpty &&
+          agg.aggregateExpressions.nonEmpty &&
+          agg.aggregateExpressions.forall(e => e.mode == Partial && !e.isDistinct) =>
+      // Not bound to the input, like the aggregate expressions of a final aggregation
+      val aggExprs = agg.aggregateExpressions.map(
+        QueryPlanSerde.aggExprToProto(_, agg.child.output, binding = false))
+      if (aggExprs.forall(_.isDefined)) {
+        Some(
+          OperatorOuterClass.ShuffleCombiner
+            .newBuilder()
+            .setNumGroupingKeys(agg.groupingExpressions.length)
+            .addAllAggExprs(aggExprs.map(_.get).asJava)
+            .setMaxGroups(CometConf.COMET_EXEC_SHUFFLE_COMBINE_MAX_GROUPS.get(agg.conf))
+            .build())
+      } else {
+        None
+      }
+    case _ => None
+  }
+
   /**
    * This is copied from Spark `ShuffleExchangeExec.needToCopyObjectsBeforeShuffle`. The only
    * difference is that we use `BosonShuffleManager` instead of `SortShuffleManager`.
@@ -444,7 +514,8 @@ object CometShuffleExchangeExec extends ShimCometShuffleExchangeExec {
 class CometShuffleWriteProcessor(
     outputPartitioning: Partitioning,
     outputAttributes: Seq[Attribute],
-    metrics: Map[String, SQLMetric])
+    metrics: Map[String, SQLMetric],
+    combiner: Option[OperatorOuterClass.ShuffleCombiner] = None)
     extends ShuffleWriteProcessor {
 
   private val OFFSET_LENGTH = 8
@@ -464,60 +535,182 @@ class CometShuffleWriteProcessor(
       SparkEnv.get.shuffleManager.shuffleBlockResolver.asInstanceOf[IndexShuffleBlockResolver]
     val dataFile = shuffleBlockResolver.getDataFile(dep.shuffleId, mapId)
     val indexFile = shuffleBlockResolver.getIndexFile(dep.shuffleId, mapId)
-    val tempDataFilename = dataFile.getPath.replace(".data", ".data.tmp")
-    val tempIndexFilename = indexFile.getPath.replace(".index", ".index.tmp")
-    val tempDataFilePath = Paths.get(tempDataFilename)
-    val tempIndexFilePath = Paths.get(tempIndexFilename)
+    // Like Spark's shuffle writers, native writes the map output into unique temporary files,
+    // which the resolver renames to the data and index files on commit. So another attempt of the
+    // same map, e.g., a speculative one, never overwrites the files being written or served.
+    val tempDataFile = Utils.tempFileWith(dataFile)
+    val tempIndexFile = Utils.tempFileWith(indexFile)
+    val tempChecksumFile = new File(tempIndexFile.getPath + ".checksum")
+    // Like Spark's shuffle writers, native computes the checksums of the partitions if enabled,
+    // which are committed as the checksum file of the map output. The ids of the algorithms are
+    // the same as the JVM shuffle writers of Comet.
+    val sparkConf = SparkEnv.get.conf
+    val checksumAlgorithm = if (sparkConf.get(config.SHUFFLE_CHECKSUM_ENABLED)) {
+      sparkConf.get(config.SHUFFLE_CHECKSUM_ALGORITHM).toUpperCase(Locale.ROOT) match {
+        case "CRC32" => Some(0)
+        case "ADLER32" => Some(1)
+        case _ => None
+      }
+    } else {
+      None
+    }
+    // Like Spark's shuffle writers, native encrypts the partitions with the IO encryption key if
+    // enabled, which the reducers decrypt with `SerializerManager.wrapForEncryption`
+    val encryptionKey = SparkEnv.get.securityManager.getIOEncryptionKey()
+
+    try {
+      // Call native shuffle write
+      val nativePlan = getNativePlan(
+        tempDataFile.getPath,
+        tempIndexFile.getPath,
+        checksumAlgorithm.map(tempChecksumFile.getPath -> _),
+        encryptionKey,
+        context.partitionId())
+      executeNativePlan(rdd, nativePlan, context, partition, None)
+
+      val partitionLengths = readPartitionLengths(tempIndexFile, tempDataFile)
+      val checksums = if (checksumAlgorithm.isDefined) {
+        val buffer = ByteBuffer.wrap(Files.readAllBytes(tempChecksumFile.toPath))
+        Array.fill(partitionLengths.length)(buffer.getLong)
+      } else {
+        Array.empty[Long]
+      }
+
+      metrics("dataSize") += tempDataFile.length()
+
+      // commit. The resolver writes the index and checksum files, and renames the temporary data
+      // file, unless another attempt of the map has committed already. Then the files of that
+      // attempt are kept, and `partitionLengths` is updated with their lengths.
+      shuffleBlockResolver.writeMetadataFileAndCommit(
+        dep.shuffleId,
+        mapId,
+        partitionLengths,
+        checksums,
+        tempDataFile)
+
+      // Like Spark's shuffle write processor, pushes the blocks of the committed data file to the
+      // mergers of push-based shuffle. Each pushed block is the framed IPC blocks of a partition,
+      // so the merged blocks, i.e., the blocks of different maps concatenated, are read like the
+      // blocks of a single map.
+      if (dep.shuffleMergeEnabled && dep.getMergerLocs.nonEmpty && !dep.shuffleMergeFinalized) {
+        logInfo(
+          s"Pushing native shuffle blocks of map $mapId to ${dep.getMergerLocs.size} mergers " +
+            s"for shuffle ${dep.shuffleId}")
+        new ShuffleBlockPusher(sparkConf).initiateBlockPush(
+          shuffleBlockResolver.getDataFile(dep.shuffleId, mapId),
+          partitionLengths,
+          dep,
+          partition.index)
+      }
+
+      MapStatus.apply(SparkEnv.get.blockManager.shuffleServerId, partitionLengths, mapId)
+    } finally {
+      // The temporary data file is gone once committed, and the others are never committed as
+      // they are, so only the files of a failed attempt are left here
+      Seq(tempDataFile, tempIndexFile, tempChecksumFile).foreach { file =>
+        if (file.exists() && !file.delete()) {
+          logError(s"Error while deleting temporary shuffle file ${file.getAbsolutePath}")
+        }
+      }
+    }
+  }
 
+  /**
+   * Reads the partition lengths from the index file written by native, which is laid out like
+   * the index files of `IndexShuffleBlockResolver`, i.e., the big-endian offsets of the
+   * partitions in the data file, starting at 0 and ending at the length of the data file.
+   */
+  private def readPartitionLengths(indexFile: File, dataFile: File): Array[Long] = {
+    val numPartitions = outputPartitioning.numPartitions
+    if (indexFile.length() != (numPartitions + 1L) * OFFSET_LENGTH) {
+      throw new IllegalStateException(
+        s"Expected ${numPartitions + 1} offsets in shuffle index file ${indexFile.getPath}, " +
+          s"but its length is ${indexFile.length()}")
+    }
+    val in = new DataInputStream(new BufferedInputStream(new FileInputStream(indexFile)))
+    val offsets =
+      try {
+        Array.fill(numPartitions + 1)(in.readLong())
+      } finally {
+        in.close()
+      }
+    if (offsets.head != 0 || offsets.last != dataFile.length()) {
+      throw new IllegalStateException(
+        s"Shuffle index file ${indexFile.getPath} doesn't match data file ${dataFile.getPath}: " +
+          s"offsets from ${offsets.head} to ${offsets.last}, but ${dataFile.length()} bytes")
+    }
+    Array.tabulate(numPartitions)(i => offsets(i + 1) - offsets(i))
+  }
+
+  /**
+   * Writes the output partitions of the map task into `sink` instead of the local shuffle files.
+   * This is for the shuffle writers of remote shuffle services, e.g., Celeborn or Uniffle, whose
+   * sinks forward the blocks to the service. The caller commits the map output to the service.
+   */
+  def writeToSink(
+      rdd: RDD[_],
+      context: TaskContext,
+      partition: Partition,
+      sink: CometShuffleBlockSink): Unit = {
+    val nativePlan = getNativePlan("", "", None, None, context.partitionId())
+    executeNativePlan(rdd, nativePlan, context, partition, Some(sink))
+    metrics("dataSize") += metrics(SQLShuffleWriteMetricsReporter.SHUFFLE_BYTES_WRITTEN).value
+  }
+
+  private def executeNativePlan(
+      rdd: RDD[_],
+      nativePlan: Operator,
+      context: TaskContext,
+      partition: Partition,
+      sink: Option[CometShuffleBlockSink]): Unit = {
     // Getting rid of the fake partitionId
     val cometRDD =
       rdd.asInstanceOf[MapPartitionsRDD[_, _]].prev.asInstanceOf[RDD[ColumnarBatch]]
 
-    // Call native shuffle write
-    val nativePlan = getNativePlan(tempDataFilename, tempIndexFilename)
-
-    // Maps native metrics to SQL metrics
+    // Maps native metrics to SQL metrics, which are totals of the task. The written rows are
+    // updated while native consumes the input, and the written bytes once the output is written
+    // at the end of the task. Row counts are not reported per partition. Only the byte sizes of
+    // the partitions of local shuffle files reach AQE, through the map status.
     val nativeSQLMetrics = Map(
       "output_rows" -> metrics(SQLShuffleWriteMetricsReporter.SHUFFLE_RECORDS_WRITTEN),
-      "elapsed_compute" -> metrics("shuffleReadElapsedCompute"))
+      "data_size" -> metrics(SQLShuffleWriteMetricsReporter.SHUFFLE_BYTES_WRITTEN),
+      "write_time" -> metrics(SQLShuffleWriteMetricsReporter.SHUFFLE_WRITE_TIME),
+      "elapsed_compute" -> metrics("shuffleReadElapsedCompute"),
+      "spill_disk_used" -> metrics("spillDiskUsed"),
+      "spill_count" -> metrics("spillCount"),
+      "spilled_bytes" -> metrics("spilledBytes"),
+      "encode_time" -> metrics("encodeTime"),
+      "compress_time" -> metrics("compressTime"))
     val nativeMetrics = CometMetricNode(nativeSQLMetrics)
 
     val rawIter = cometRDD.iterator(partition, context)
-    val cometIter = CometExec.getCometIterator(Seq(rawIter), nativePlan, nativeMetrics)
+    val cometIter = CometExec.getCometIterator(Seq(rawIter), nativePlan, nativeMetrics, sink)
 
     while (cometIter.hasNext) {
       cometIter.next()
     }
 
-    // get partition lengths from shuffle write output index file
-    var offset = 0L
-    val partitionLengths = Files
-      .readAllBytes(tempIndexFilePath)
-      .grouped(OFFSET_LENGTH)
-      .drop(1) // first partition offset is always 0
-      .map(indexBytes => {
-        val partitionOffset =
-          ByteBuffer.wrap(indexBytes).order(ByteOrder.LITTLE_ENDIAN).getLong
-        val partitionLength = partitionOffset - offset
-        offset = partitionOffset
-        partitionLength
-      })
-      .toArray
-
-    // Update Spark metrics from native metrics
-    metrics("dataSize") += Files.size(tempDataFilePath)
-
-    // commit
-    shuffleBlockResolver.writeMetadataFileAndCommit(
-      dep.shuffleId,
-      mapId,
-      partitionLengths,
-      Array.empty, // TODO: add checksums
-      tempDataFilePath.toFile)
-    MapStatus.apply(SparkEnv.get.blockManager.shuffleServerId, partitionLengths, mapId)
+    // The task-level shuffle write metrics are only updated here, as native sets the SQL metrics
+    val taskWriteMetrics = context.taskMetrics().shuffleWriteMetrics
+    taskWriteMetrics.incRecordsWritten(
+      metrics(SQLShuffleWriteMetricsReporter.SHUFFLE_RECORDS_WRITTEN).value)
+    taskWriteMetrics.incBytesWritten(
+      metrics(SQLShuffleWriteMetricsReporter.SHUFFLE_BYTES_WRITTEN).value)
+    taskWriteMetrics.incWriteTime(
+      metrics(SQLShuffleWriteMetricsReporter.SHUFFLE_WRITE_TIME).value)
   }
 
-  def getNativePlan(dataFile: String, indexFile: String): Operator = {
+  /**
+   * Creates the native plan writing the shuffle data and index files, and the checksum file with
+   * the given algorithm, 0 for CRC32 and 1 for Adler32, if `checksumFile` is set. The partitions
+   * are encrypted with `encryptionKey` if it is set.
+   */
+  def getNativePlan(
+      dataFile: String,
+      indexFile: String,
+      checksumFile: Option[(String, Int)],
+      encryptionKey: Option[Array[Byte]],
+      partitionId: Int): Operator = {
     val scanBuilder = OperatorOuterClass.Scan.newBuilder()
     val opBuilder = OperatorOuterClass.Operator.newBuilder()
 
@@ -531,6 +724,22 @@ class CometShuffleWriteProcessor(
       val shuffleWriterBuilder = OperatorOuterClass.ShuffleWriter.newBuilder()
       shuffleWriterBuilder.setOutputDataFile(dataFile)
       shuffleWriterBuilder.setOutputIndexFile(indexFile)
+      // The same codec as `CometBlockStoreShuffleReader` decompresses the blocks with
+      shuffleWriterBuilder.setCodec(ShuffleUtils.shuffleCodecName match {
+        case "lz4" => OperatorOuterClass.CompressionCodec.Lz4
+        case "snappy" => OperatorOuterClass.CompressionCodec.Snappy
+        case _ => OperatorOuterClass.CompressionCodec.Zstd
+      })
+      shuffleWriterBuilder.setCompressionLevel(ShuffleUtils.shuffleZstdLevel)
+      shuffleWriterBuilder.setSortBased(CometConf.COMET_EXEC_SHUFFLE_SORT_BASED_ENABLED.get())
+      checksumFile.foreach { case (file, algorithm) =>
+        shuffleWriterBuilder.setOutputChecksumFile(file)
+        shuffleWriterBuilder.setChecksumAlgorithm(algorithm)
+      }
+      encryptionKey.foreach { key =>
+        shuffleWriterBuilder.setEncryptionKey(ByteString.copyFrom(key))
+      }
+      combiner.foreach(c => shuffleWriterBuilder.setCombiner(c))
 
       outputPartitioning match {
         case _: HashPartitioning =>
@@ -560,6 +769,20 @@ class CometShuffleWriteProcessor(
           shuffleWriterBuilder.setPartitioning(
             partitioningBuilder.setSinglePartition(partitioning).build())
 
+        case RoundRobinPartitioning(numPartitions) =>
+          val partitioning = PartitioningOuterClass.RoundRobinPartition.newBuilder()
+          partitioning.setNumPartitions(numPartitions)
+          // Starts from the same position as Spark, see `prepareJVMShuffleDependency`
+          partitioning.setStartPosition(new XORShiftRandom(partitionId).nextInt(numPartitions))
+          // [SPARK-23207] Sorts the rows before partitioning, so that the partitioning is
+          // deterministic across task retries
+          partitioning.setSortBeforePartitioning(
+            SQLConf.get.sortBeforeRepartition && numPartitions > 1)
+
+          val partitioningBuilder = PartitioningOuterClass.Partitioning.newBuilder()
+          shuffleWriterBuilder.setPartitioning(
+            partitioningBuilder.setRoundRobinPartition(partitioning).build())
+
         case _ =>
           throw new UnsupportedOperationException(
             s"Partitioning $outputPartitioning is not supported.")
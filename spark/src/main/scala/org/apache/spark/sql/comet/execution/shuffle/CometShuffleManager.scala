@@ -19,6 +19,7 @@
 
 package org.apache.spark.sql.comet.execution.shuffle
 
+import java.io.InputStream
 import java.util.concurrent.ConcurrentHashMap
 
 import scala.collection.JavaConverters._
@@ -28,20 +29,23 @@ import org.apache.spark.SparkConf
 import org.apache.spark.SparkEnv
 import org.apache.spark.TaskContext
 import org.apache.spark.internal.{config, Logging}
-import org.apache.spark.internal.config.IO_COMPRESSION_CODEC
 import org.apache.spark.io.CompressionCodec
 import org.apache.spark.shuffle._
 import org.apache.spark.shuffle.api.ShuffleExecutorComponents
 import org.apache.spark.shuffle.sort.{BypassMergeSortShuffleHandle, SerializedShuffleHandle, SortShuffleManager, SortShuffleWriter}
 import org.apache.spark.sql.internal.SQLConf
+import org.apache.spark.sql.vectorized.ColumnarBatch
 import org.apache.spark.util.collection.OpenHashSet
 
-import org.apache.comet.CometConf
+import org.apache.comet.{CometConf, NativeBase}
 
 /**
  * A [[ShuffleManager]] that uses Arrow format to shuffle data.
  */
-class CometShuffleManager(conf: SparkConf) extends ShuffleManager with Logging {
+class CometShuffleManager(conf: SparkConf)
+    extends ShuffleManager
+    with ShuffleBlockRangesReader
+    with Logging {
 
   import CometShuffleManager._
   import SortShuffleManager._
@@ -104,6 +108,37 @@ class CometShuffleManager(conf: SparkConf) extends ShuffleManager with Logging {
     }
   }
 
+  /**
+   * Returns the decoder of the fetched blocks of a Comet native shuffle if the native shuffle
+   * reader is enabled and supported by the native library, which coalesces the decoded batches
+   * if shuffle coalescing is enabled too. The blocks of Comet columnar shuffle are always
+   * deserialized on the JVM. Encrypted blocks are decrypted before they are decoded.
+   */
+  private def nativeDecoder(
+      dependency: ShuffleDependency[_, _, _],
+      context: TaskContext): Option[Iterator[InputStream] => Iterator[ColumnarBatch]] = {
+    dependency match {
+      case dep: CometShuffleDependency[_, _, _]
+          if dep.shuffleType == CometNativeShuffle && dep.schema.isDefined &&
+            CometConf.COMET_EXEC_SHUFFLE_NATIVE_READER_ENABLED.get() &&
+            NativeBase.hasCapability(NativeBase.CAPABILITY_NATIVE_SHUFFLE_READER) =>
+        val numColumns = dep.schema.get.length
+        val codec = ShuffleUtils.shuffleCodecName
+        val coalesce = if (CometConf.COMET_EXEC_SHUFFLE_COALESCE_ENABLED.get() &&
+          NativeBase.hasCapability(NativeBase.CAPABILITY_SHUFFLE_COALESCER)) {
+          val targetRows = CometConf.COMET_EXEC_SHUFFLE_COALESCE_TARGET_ROWS
+            .get()
+            .getOrElse(CometConf.COMET_BATCH_SIZE.get())
+          Some((targetRows, CometConf.COMET_EXEC_SHUFFLE_COALESCE_TARGET_BYTES.get()))
+        } else {
+          None
+        }
+        Some(streams =>
+          new NativeBatchDecoderIterator(streams, context, numColumns, codec, coalesce))
+      case _ => None
+    }
+  }
+
   override def getReader[K, C](
       handle: ShuffleHandle,
       startMapIndex: Int,
@@ -112,45 +147,80 @@ class CometShuffleManager(conf: SparkConf) extends ShuffleManager with Logging {
       endPartition: Int,
       context: TaskContext,
       metrics: ShuffleReadMetricsReporter): ShuffleReader[K, C] = {
+    getBlockRangesReader(
+      handle,
+      Seq(ShuffleBlockRange(startPartition, endPartition, startMapIndex, endMapIndex)),
+      context,
+      metrics)
+  }
+
+  /**
+   * Returns a reader of the blocks of all the given ranges, e.g., the ranges of the partition
+   * specs of AQE read by a partition. The blocks of a Comet shuffle are fetched together, and
+   * decoded as one stream by the native decoder if any, which coalesces the batches across the
+   * ranges. Contiguous blocks are only fetched in batch for a single range.
+   */
+  override def getBlockRangesReader[K, C](
+      handle: ShuffleHandle,
+      ranges: Seq[ShuffleBlockRange],
+      context: TaskContext,
+      metrics: ShuffleReadMetricsReporter): ShuffleReader[K, C] = {
     val baseShuffleHandle = handle.asInstanceOf[BaseShuffleHandle[K, _, C]]
-    val (blocksByAddress, canEnableBatchFetch) =
-      if (baseShuffleHandle.dependency.shuffleMergeEnabled) {
-        val res = SparkEnv.get.mapOutputTracker.getPushBasedShuffleMapSizesByExecutorId(
-          handle.shuffleId,
-          startMapIndex,
-          endMapIndex,
-          startPartition,
-          endPartition)
-        (res.iter, res.enableBatchFetch)
-      } else {
-        val address = SparkEnv.get.mapOutputTracker.getMapSizesByExecutorId(
-          handle.shuffleId,
-          startMapIndex,
-          endMapIndex,
-          startPartition,
-          endPartition)
-        (address, true)
-      }
 
     if (handle.isInstanceOf[CometBypassMergeSortShuffleHandle[_, _]] ||
       handle.isInstanceOf[CometSerializedShuffleHandle[_, _]]) {
+      val blocks = ranges.map { range =>
+        if (baseShuffleHandle.dependency.shuffleMergeEnabled) {
+          val res = SparkEnv.get.mapOutputTracker.getPushBasedShuffleMapSizesByExecutorId(
+            handle.shuffleId,
+            range.startMapIndex,
+            range.endMapIndex,
+            range.startReducerIndex,
+            range.endReducerIndex)
+          (res.iter, res.enableBatchFetch)
+        } else {
+          val address = SparkEnv.get.mapOutputTracker.getMapSizesByExecutorId(
+            handle.shuffleId,
+            range.startMapIndex,
+            range.endMapIndex,
+            range.startReducerIndex,
+            range.endReducerIndex)
+          (address, true)
+        }
+      }
+      val shouldBatchFetch = (ranges, blocks) match {
+        case (Seq(range), Seq((_, canEnableBatchFetch))) =>
+          canEnableBatchFetch &&
+          canUseBatchFetch(range.startReducerIndex, range.endReducerIndex, context)
+        case _ => false
+      }
+
       new CometBlockStoreShuffleReader(
-        handle.asInstanceOf[BaseShuffleHandle[K, _, C]],
-        blocksByAddress,
+        baseShuffleHandle,
+        blocks.iterator.flatMap(_._1),
         context,
         metrics,
-        shouldBatchFetch =
-          canEnableBatchFetch && canUseBatchFetch(startPartition, endPartition, context))
+        shouldBatchFetch = shouldBatchFetch,
+        nativeDecoder = nativeDecoder(baseShuffleHandle.dependency, context))
     } else {
-      // It is a Spark shuffle dependency, so we use Spark Sort Shuffle Reader.
-      sortShuffleManager.getReader(
-        handle,
-        startMapIndex,
-        endMapIndex,
-        startPartition,
-        endPartition,
-        context,
-        metrics)
+      // It is a Spark shuffle dependency, so we use Spark Sort Shuffle Reader for each range.
+      val readers = ranges.map { range =>
+        sortShuffleManager.getReader[K, C](
+          handle,
+          range.startMapIndex,
+          range.endMapIndex,
+          range.startReducerIndex,
+          range.endReducerIndex,
+          context,
+          metrics)
+      }
+      readers match {
+        case Seq(reader) => reader
+        case _ =>
+          new ShuffleReader[K, C] {
+            override def read(): Iterator[Product2[K, C]] = readers.iterator.flatMap(_.read())
+          }
+      }
     }
   }
 
@@ -224,17 +294,7 @@ object CometShuffleManager extends Logging {
     executorComponents
   }
 
-  lazy val compressionCodecForShuffling: CompressionCodec = {
-    val sparkConf = SparkEnv.get.conf
-    val codecName = CometConf.COMET_EXEC_SHUFFLE_CODEC.get(SQLConf.get)
-
-    // only zstd compression is supported at the moment
-    if (codecName != "zstd") {
-      logWarning(
-        s"Overriding config ${IO_COMPRESSION_CODEC}=${codecName} in shuffling, force using zstd")
-    }
-    CompressionCodec.createCodec(sparkConf, "zstd")
-  }
+  def compressionCodecForShuffling: CompressionCodec = ShuffleUtils.compressionCodecForShuffling
 
   def shouldBypassMergeSort(conf: SparkConf, dep: ShuffleDependency[_, _, _]): Boolean = {
     // We cannot bypass sorting if we need to do map-side aggregation.
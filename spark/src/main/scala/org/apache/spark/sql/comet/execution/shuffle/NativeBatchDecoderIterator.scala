@@ -0,0 +1,206 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one
+ * or more contributor license agreements.  See the NOTICE file
+ * distributed with this work for additional information
+ * regarding copyright ownership.  The ASF licenses this file
+ * to you under the Apache License, Version 2.0 (the
+ * "License"); you may not use this file except in compliance
+ * with the License.  You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+ * KIND, either express or implied.  See the License for the
+ * specific language governing permissions and limitations
+ * under the License.
+ */
+
+package org.apache.spark.sql.comet.execution.shuffle
+
+import java.io.{EOFException, IOException, InputStream}
+import java.nio.{ByteBuffer, ByteOrder}
+import java.nio.channels.{Channels, ReadableByteChannel}
+
+import org.apache.spark.TaskContext
+import org.apache.spark.sql.vectorized.ColumnarBatch
+
+import org.apache.comet.Native
+import org.apache.comet.vector.NativeUtil
+
+/**
+ * Reads the blocks of Comet native shuffle from the input streams of fetched shuffle data one
+ * after another, and decodes each of them natively into a ColumnarBatch of `numColumns` columns.
+ * Unlike [[ArrowReaderIterator]], the compressed block is passed to native in a direct buffer,
+ * which is decompressed and decoded into Arrow arrays exported to the JVM without being copied.
+ *
+ * A stream may be a block merged by push-based shuffle, i.e., the blocks of a partition written
+ * by different maps concatenated, whose framed blocks are read one after another all the same.
+ *
+ * If `coalesce` is set to the target rows and bytes, the batches of the blocks of all the streams
+ * are coalesced natively until they reach either of them, instead of returning a small batch for
+ * each block.
+ */
+class NativeBatchDecoderIterator(
+    streams: Iterator[InputStream],
+    taskContext: TaskContext,
+    numColumns: Int,
+    codec: String,
+    coalesce: Option[(Int, Long)] = None)
+    extends Iterator[ColumnarBatch] {
+
+  /** The stream which the blocks are being read from */
+  private var in: InputStream = _
+  private var channel: ReadableByteChannel = _
+  private val lengthBuf = ByteBuffer.allocate(8).order(ByteOrder.LITTLE_ENDIAN)
+  /** The direct buffer the blocks are read into, which grows for larger blocks */
+  private var blockBuf: ByteBuffer = ByteBuffer.allocateDirect(64 * 1024)
+
+  private val native = new Native()
+  private val nativeUtil = new NativeUtil()
+
+  /** The handle of the native coalescer of the batches, if they are coalesced */
+  private var coalescer: Option[Long] = coalesce.map { case (targetRows, targetBytes) =>
+    native.createShuffleCoalescer(codec, targetRows, targetBytes)
+  }
+  /** The number of the coalesced batches ready to be taken from the coalescer */
+  private var readyBatches = 0
+  /** Whether all the blocks are pushed into the coalescer */
+  private var drained = false
+
+  private var batch: Option[ColumnarBatch] = None
+  private var currentBatch: ColumnarBatch = null
+  private var finished = false
+
+  taskContext.addTaskCompletionListener[Unit](_ => close())
+
+  override def hasNext: Boolean = {
+    if (batch.isDefined) {
+      return true
+    }
+
+    // Release the previous batch before decoding the next one
+    if (currentBatch != null) {
+      currentBatch.close()
+      currentBatch = null
+    }
+
+    batch = nextBatch()
+    batch.isDefined
+  }
+
+  override def next(): ColumnarBatch = {
+    if (!hasNext) {
+      throw new NoSuchElementException
+    }
+
+    currentBatch = batch.get
+    batch = None
+    currentBatch
+  }
+
+  private def nextBatch(): Option[ColumnarBatch] = {
+    if (finished) {
+      return None
+    }
+
+    coalescer match {
+      case Some(handle) =>
+        while (readyBatches == 0 && !drained) {
+          val length = readBlock()
+          drained = length < 0
+          readyBatches = native.coalesceShuffleBlock(handle, blockBuf, length)
+        }
+        if (readyBatches == 0) {
+          close()
+          None
+        } else {
+          readyBatches -= 1
+          Some(nativeUtil.importBatch(numColumns, native.nextCoalescedBatch(handle, _, _)))
+        }
+      case None =>
+        val length = readBlock()
+        if (length < 0) {
+          close()
+          None
+        } else {
+          Some(
+            nativeUtil.importBatch(
+              numColumns,
+              native.decodeShuffleBlock(blockBuf, length, codec, _, _)))
+        }
+    }
+  }
+
+  /**
+   * Reads the next non-empty block into `blockBuf`. Returns the length of the block, or -1 if the
+   * end of all the streams is reached.
+   */
+  private def readBlock(): Int = {
+    var length = 0L
+    // Skips empty blocks
+    while (length == 0) {
+      if (in == null) {
+        if (!streams.hasNext) {
+          return -1
+        }
+        in = streams.next()
+        channel = Channels.newChannel(in)
+      }
+
+      // Reads the length of the block. If we reach the end of the stream, we move on to the next
+      // stream, or if we read partial length then the stream is corrupted.
+      lengthBuf.clear()
+      while (lengthBuf.hasRemaining && channel.read(lengthBuf) >= 0) {}
+      if (lengthBuf.hasRemaining) {
+        if (lengthBuf.position() != 0) {
+          throw new EOFException(
+            "Data corrupt: unexpected EOF while reading compressed ipc lengths")
+        }
+        closeStream()
+      } else {
+        lengthBuf.flip()
+        length = lengthBuf.getLong
+        if (length < 0 || length > Int.MaxValue) {
+          throw new IOException(s"Data corrupt: invalid length $length of compressed ipc")
+        }
+      }
+    }
+
+    if (length > blockBuf.capacity()) {
+      blockBuf = ByteBuffer.allocateDirect(math.max(length, blockBuf.capacity() * 2L).toInt)
+    }
+    blockBuf.clear()
+    blockBuf.limit(length.toInt)
+    while (blockBuf.hasRemaining) {
+      if (channel.read(blockBuf) < 0) {
+        throw new EOFException(
+          s"Data corrupt: unexpected EOF while reading compressed ipc of $length bytes")
+      }
+    }
+    length.toInt
+  }
+
+  def close(): Unit =
+    synchronized {
+      finished = true
+      batch.foreach(_.close())
+      batch = None
+      if (currentBatch != null) {
+        currentBatch.close()
+        currentBatch = null
+      }
+      coalescer.foreach(native.releaseShuffleCoalescer)
+      coalescer = None
+      closeStream()
+    }
+
+  private def closeStream(): Unit = {
+    if (in != null) {
+      in.close()
+      in = null
+      channel = null
+    }
+  }
+}
@@ -20,37 +20,43 @@
 package org.apache.spark.sql.comet
 
 import java.io.{ByteArrayOutputStream, DataInputStream}
+import java.nio.{ByteBuffer, ByteOrder}
 import java.nio.channels.Channels
 
+import scala.collection.JavaConverters._
 import scala.collection.mutable
 import scala.collection.mutable.ArrayBuffer
 
 import org.apache.spark.{SparkEnv, TaskContext}
+import org.apache.spark.broadcast.Broadcast
 import org.apache.spark.io.CompressionCodec
 import org.apache.spark.rdd.RDD
 import org.apache.spark.sql.catalyst.InternalRow
-import org.apache.spark.sql.catalyst.expressions.{Attribute, AttributeSet, Expression, NamedExpression, SortOrder}
+import org.apache.spark.sql.catalyst.expressions.{Attribute, AttributeSet, Expression, Generator, NamedExpression, SortOrder}
 import org.apache.spark.sql.catalyst.expressions.aggregate.{AggregateExpression, AggregateMode}
 import org.apache.spark.sql.catalyst.optimizer.{BuildLeft, BuildRight, BuildSide}
 import org.apache.spark.sql.catalyst.plans._
-import org.apache.spark.sql.catalyst.plans.physical.{HashPartitioning, Partitioning, PartitioningCollection, UnknownPartitioning}
+import org.apache.spark.sql.catalyst.plans.physical.{HashPartitioning, Partitioning, PartitioningCollection, SinglePartition, UnknownPartitioning}
 import org.apache.spark.sql.comet.execution.shuffle.{ArrowReaderIterator, CometShuffleExchangeExec}
 import org.apache.spark.sql.comet.plans.PartitioningPreservingUnaryExecNode
 import org.apache.spark.sql.comet.util.Utils
-import org.apache.spark.sql.execution.{BinaryExecNode, ColumnarToRowExec, ExecSubqueryExpression, ExplainUtils, LeafExecNode, ScalarSubquery, SparkPlan, UnaryExecNode}
+import org.apache.spark.sql.execution.{BinaryExecNode, ColumnarToRowExec, ExecSubqueryExpression, ExplainUtils, InSubqueryExec, LeafExecNode, ScalarSubquery, ShufflePartitionSpec, SparkPlan, UnaryExecNode}
 import org.apache.spark.sql.execution.adaptive.{AQEShuffleReadExec, BroadcastQueryStageExec, ShuffleQueryStageExec}
-import org.apache.spark.sql.execution.aggregate.HashAggregateExec
+import org.apache.spark.sql.execution.aggregate.BaseAggregateExec
 import org.apache.spark.sql.execution.exchange.ReusedExchangeExec
 import org.apache.spark.sql.execution.metric.{SQLMetric, SQLMetrics}
 import org.apache.spark.sql.internal.SQLConf
 import org.apache.spark.sql.vectorized.ColumnarBatch
+import org.apache.spark.util.{Utils => SparkUtils}
 import org.apache.spark.util.io.ChunkedByteBuffer
 
 import com.google.common.base.Objects
 
-import org.apache.comet.{CometConf, CometExecIterator, CometRuntimeException}
+import org.apache.comet.{CometConf, CometExecIterator, CometRuntimeException, CometShuffleBlockSink, Native, NativeBase}
+import org.apache.comet.serde.{OperatorOuterClass, PartitioningOuterClass, QueryPlanSerde}
 import org.apache.comet.serde.OperatorOuterClass.Operator
 import org.apache.comet.shims.ShimCometBroadcastHashJoinExec
+import org.apache.comet.vector.NativeUtil
 
 /**
  * A Comet physical operator
@@ -98,6 +104,75 @@ object CometExec {
 
   def newIterId: Long = curId.getAndIncrement()
 
+  /** The `NativeUtil` of each running task exporting batches for `Native.serializeBatch`. */
+  private val taskNativeUtils = new java.util.concurrent.ConcurrentHashMap[Long, NativeUtil]()
+
+  /**
+   * Returns the `NativeUtil` of the current task, which is shared by all the partitions the
+   * task serializes, e.g., the partitions of a coalesced RDD, and dropped once the task ends.
+   */
+  private def taskNativeUtil(): NativeUtil = {
+    val context = TaskContext.get()
+    taskNativeUtils.computeIfAbsent(
+      context.taskAttemptId(),
+      taskAttemptId => {
+        context.addTaskCompletionListener[Unit](_ => taskNativeUtils.remove(taskAttemptId))
+        new NativeUtil()
+      })
+  }
+
+  /**
+   * Returns the id of the exchange read by each of the given inputs of a native plan if other
+   * inputs read the same exchange with the same partitions, e.g., the reused shuffle of a
+   * self-join, or 0 otherwise. The id is 1 plus the index of the first input reading it.
+   */
+  def sharedSourceIds(inputs: Seq[SparkPlan]): Seq[Long] = {
+    if (!CometConf.COMET_EXEC_REUSED_EXCHANGE_SHARED_ENABLED.get() ||
+      !NativeBase.hasCapability(NativeBase.CAPABILITY_SHARED_SCAN)) {
+      return inputs.map(_ => 0L)
+    }
+
+    val sources = inputs.map(shuffleSource)
+    sources.map {
+      case Some((exchange, specs)) =>
+        // Reused exchanges refer to the same instance of the exchange
+        val readers = sources.indices.filter { i =>
+          sources(i).exists { case (e, s) => (e eq exchange) && s == specs }
+        }
+        if (readers.length > 1) readers.head + 1L else 0L
+      case None => 0L
+    }
+  }
+
+  /** The shuffle exchange read by the given input, with the partitions read if coalesced. */
+  private def shuffleSource(input: SparkPlan): Option[(SparkPlan, Seq[ShufflePartitionSpec])] =
+    input match {
+      case e: CometShuffleExchangeExec => Some((e, Nil))
+      case ReusedExchangeExec(_, e: CometShuffleExchangeExec) => Some((e, Nil))
+      case s: ShuffleQueryStageExec => shuffleSource(s.plan)
+      case r @ AQEShuffleReadExec(s: ShuffleQueryStageExec, _) =>
+        shuffleSource(s.plan).map { case (e, _) => (e, r.partitionSpecs) }
+      case _ => None
+    }
+
+  /**
+   * Sets the given source ids, see `sharedSourceIds`, to the scans of the serialized native
+   * plan, which read the inputs in the same order.
+   */
+  def withSourceIds(serializedPlan: Array[Byte], sourceIds: Seq[Long]): Array[Byte] = {
+    val ids = sourceIds.iterator
+    def setSourceIds(op: Operator): Operator = {
+      val builder = op.toBuilder
+      if (op.hasScan) {
+        builder.setScan(op.getScan.toBuilder.setSourceId(ids.next()))
+      }
+      builder.clearChildren()
+      op.getChildrenList.asScala.foreach(child => builder.addChildren(setSourceIds(child)))
+      builder.build()
+    }
+    setSourceIds(Operator.parseFrom(serializedPlan)).toByteArray
+  }
+
   def getCometIterator(
       inputs: Seq[Iterator[ColumnarBatch]],
       nativePlan: Operator): CometExecIterator = {
@@ -107,12 +182,20 @@ object CometExec {
   def getCometIterator(
       inputs: Seq[Iterator[ColumnarBatch]],
       nativePlan: Operator,
-      nativeMetrics: CometMetricNode): CometExecIterator = {
+      nativeMetrics: CometMetricNode,
+      shuffleBlockSink: Option[CometShuffleBlockSink] = None): CometExecIterator = {
     val outputStream = new ByteArrayOutputStream()
     nativePlan.writeTo(outputStream)
     outputStream.close()
     val bytes = outputStream.toByteArray
-    new CometExecIterator(newIterId, inputs, bytes, nativeMetrics)
+    // These plans don't have nondeterministic expressions, which depend on the partition index
+    new CometExecIterator(
+      newIterId,
+      inputs,
+      bytes,
+      nativeMetrics,
+      TaskContext.getPartitionId(),
+      shuffleBlockSink)
   }
 
   /**
@@ -124,6 +207,151 @@ object CometExec {
     }
   }
 
+  /**
+   * Executes this Comet operator and serializes output ColumnarBatch into bytes natively, which
+   * can be decoded by `decodeBatchesNatively`.
+   *
+   * The batches of each partition are serialized by a native plan writing them as the single
+   * partition of a native shuffle writer, which coalesces small batches and compresses them with
+   * zstd into a few blocks, the same as `Native.serializeBatch` serializes a batch into. So each
+   * partition is serialized into a single buffer of one or more blocks. If the output has types
+   * the native plan doesn't support, each batch is serialized by `Native.serializeBatch` instead.
+   */
+  def getNativeByteArrayRdd(cometPlan: CometPlan): RDD[(Long, ChunkedByteBuffer)] = {
+    val serializerPlan = nativeSerializerPlan(cometPlan.output)
+    cometPlan.executeColumnar().mapPartitionsInternal { iter =>
+      serializerPlan match {
+        case Some(plan) => serializeBatchesNatively(iter, plan)
+        case None =>
+          val native = new Native()
+          val nativeUtil = taskNativeUtil()
+          iter.map { batch =>
+            val bytes = nativeUtil.withExportedBatch(batch)(native.serializeBatch)
+            if (bytes.nonEmpty) {
+              (batch.numRows(), new ChunkedByteBuffer(ByteBuffer.wrap(bytes)))
+            } else {
+              (batch.numRows(), new ChunkedByteBuffer(Array.empty[ByteBuffer]))
+            }
+          }
+      }
+    }
+  }
+
+  /**
+   * The native plan of `getNativeByteArrayRdd` serializing the batches of `output` into the sink
+   * of its shuffle writer, or None if any type of `output` is not supported natively.
+   */
+  private def nativeSerializerPlan(output: Seq[Attribute]): Option[Operator] = {
+    val fields = output.flatMap(attr => QueryPlanSerde.serializeDataType(attr.dataType))
+    if (fields.length != output.length) {
+      return None
+    }
+
+    val scan = OperatorOuterClass.Scan.newBuilder().addAllFields(fields.asJava)
+    val partitioning = PartitioningOuterClass.Partitioning
+      .newBuilder()
+      .setSinglePartition(PartitioningOuterClass.SinglePartition.newBuilder())
+    // The codec of the native deserialization of broadcast relations
+    val writer = OperatorOuterClass.ShuffleWriter
+      .newBuilder()
+      .setPartitioning(partitioning)
+      .setCodec(OperatorOuterClass.CompressionCodec.Zstd)
+      .setCompressionLevel(1)
+    Some(
+      Operator
+        .newBuilder()
+        .setShuffleWriter(writer)
+        .addChildren(Operator.newBuilder().setScan(scan))
+        .build())
+  }
+
+  private def serializeBatchesNatively(
+      batches: Iterator[ColumnarBatch],
+      serializerPlan: Operator): Iterator[(Long, ChunkedByteBuffer)] = {
+    var numRows = 0L
+    val blocks = new ArrayBuffer[ByteBuffer]
+    val sink = new CometShuffleBlockSink {
+      override def writeBlock(partitionId: Int, block: Array[Byte]): Unit =
+        blocks += ByteBuffer.wrap(block)
+    }
+    val input = batches.map { batch =>
+      numRows += batch.numRows()
+      batch
+    }
+
+    val cometIter =
+      getCometIterator(Seq(input), serializerPlan, CometMetricNode(Map.empty), Some(sink))
+    // The shuffle writer has no output, it only writes the blocks when the input is consumed
+    while (cometIter.hasNext) {
+      cometIter.next()
+    }
+
+    if (blocks.isEmpty) {
+      Iterator.empty
+    } else {
+      Iterator.single((numRows, new ChunkedByteBuffer(blocks.toArray)))
+    }
+  }
+
+  /**
+   * Decodes the byte arrays serialized by `getNativeByteArrayRdd` back to ColumnarBatches of
+   * `numCols` columns. The serialized blocks are decoded one by one as the batches are consumed.
+   */
+  def decodeBatchesNatively(
+      bytes: ChunkedByteBuffer,
+      numCols: Int,
+      native: Native,
+      nativeUtil: NativeUtil): Iterator[ColumnarBatch] = {
+    if (bytes.size == 0) {
+      return Iterator.empty
+    }
+
+    // Each block is the length of the block in 8 little-endian bytes, followed by the compressed
+    // Arrow IPC stream of a batch
+    val serialized = ByteBuffer.wrap(bytes.toArray).order(ByteOrder.LITTLE_ENDIAN)
+    new Iterator[ColumnarBatch] {
+      override def hasNext: Boolean = serialized.hasRemaining
+
+      override def next(): ColumnarBatch = {
+        if (!hasNext) {
+          throw new NoSuchElementException("No more serialized batch")
+        }
+        val start = serialized.position()
+        val end = start + 8 + serialized.getLong(start).toInt
+        val block = java.util.Arrays.copyOfRange(serialized.array(), start, end)
+        serialized.position(end)
+        nativeUtil.importBatch(numCols, native.deserializeBatch(block, _, _))
+      }
+    }
+  }
+
+  /**
+   * Maps the broadcast relation serialized by `getNativeByteArrayRdd` into memory, which is
+   * shared by the tasks of the executor, and returns its ColumnarBatchs of `numCols` columns
+   * over the mapped memory. The relation is released when the task completes.
+   */
+  def decodeMappedBatches(
+      broadcast: Broadcast[Array[ChunkedByteBuffer]],
+      numCols: Int,
+      native: Native,
+      nativeUtil: NativeUtil): MappedBatchIterator = {
+    val mapped = native.mapBroadcast(broadcast.id, null, null)
+    val handle = if (mapped != 0) {
+      mapped
+    } else {
+      val dir = SparkUtils.getLocalDir(SparkEnv.get.conf)
+      native.mapBroadcast(broadcast.id, broadcast.value.map(_.toArray), dir)
+    }
+    Option(TaskContext.get()).foreach { context =>
+      context.addTaskCompletionListener[Unit](_ => native.releaseMappedBroadcast(handle))
+    }
+
+    val batches = (0 until native.numMappedBatches(handle)).iterator.map { index =>
+      nativeUtil.importBatch(numCols, native.getMappedBatch(handle, index, _, _))
+    }
+    new MappedBatchIterator(handle, batches)
+  }
+
   /**
    * Decodes the byte arrays back to ColumnarBatchs and put them into buffer.
    */
@@ -140,6 +368,20 @@ object CometExec {
   }
 }
 
+/**
+ * The batches of a broadcast relation memory-mapped on the executor.
+ *
+ * @param handle
+ *   the native handle of the mapped relation, valid until the task completes. Native plans
+ *   reading this iterator can read the relation directly by the handle.
+ */
+class MappedBatchIterator(val handle: Long, batches: Iterator[ColumnarBatch])
+    extends Iterator[ColumnarBatch] {
+  override def hasNext: Boolean = batches.hasNext
+
+  override def next(): ColumnarBatch = batches.next()
+}
+
 /**
  * A Comet native physical operator.
  */
@@ -156,17 +398,29 @@ abstract class CometNativeExec extends CometExec {
 
   override protected def doPrepare(): Unit = prepareSubqueries(originalPlan)
 
-  override lazy val metrics: Map[String, SQLMetric] =
+  /**
+   * The metrics of the native operator. The root operator of a native block additionally reports
+   * the metrics of the data crossing the JVM/native boundary and of the spill files of the block.
+   */
+  protected def operatorMetrics: Map[String, SQLMetric] =
     CometMetricNode.baselineMetrics(sparkContext)
 
+  override lazy val metrics: Map[String, SQLMetric] = if (serializedPlanOpt.isDefined) {
+    operatorMetrics ++ CometMetricNode.jniMetrics(sparkContext) ++
+      CometMetricNode.spillMetrics(sparkContext)
+  } else {
+    operatorMetrics
+  }
+
   private def prepareSubqueries(sparkPlan: SparkPlan): Unit = {
     val runningSubqueries = new ArrayBuffer[ExecSubqueryExpression]
 
     sparkPlan.children.foreach(prepareSubqueries)
 
     sparkPlan.expressions.foreach {
-      _.collect { case e: ScalarSubquery =>
-        runningSubqueries += e
+      _.collect {
+        case e: ScalarSubquery => runningSubqueries += e
+        case e: InSubqueryExec => runningSubqueries += e
       }
     }
 
@@ -182,8 +436,9 @@ abstract class CometNativeExec extends CometExec {
     sparkPlan.children.foreach(setSubqueries(planId, _))
 
     sparkPlan.expressions.foreach {
-      _.collect { case sub: ScalarSubquery =>
-        CometScalarSubquery.setSubquery(planId, sub)
+      _.collect {
+        case sub: ScalarSubquery => CometScalarSubquery.setSubquery(planId, sub)
+        case sub: InSubqueryExec => CometScalarSubquery.setSubquery(planId, sub)
       }
     }
   }
@@ -192,8 +447,9 @@ abstract class CometNativeExec extends CometExec {
     sparkPlan.children.foreach(cleanSubqueries(planId, _))
 
     sparkPlan.expressions.foreach {
-      _.collect { case sub: ScalarSubquery =>
-        CometScalarSubquery.removeSubquery(planId, sub)
+      _.collect {
+        case sub: ScalarSubquery => CometScalarSubquery.removeSubquery(planId, sub)
+        case sub: InSubqueryExec => CometScalarSubquery.removeSubquery(planId, sub)
       }
     }
   }
@@ -209,13 +465,46 @@ abstract class CometNativeExec extends CometExec {
         // doesn't support Decimal32 and Decimal64 yet.
         SQLConf.get.setConfString(CometConf.COMET_USE_DECIMAL_128.key, "true")
 
-        val serializedPlanCopy = serializedPlan
         // TODO: support native metrics for all operators.
         val nativeMetrics = CometMetricNode.fromCometPlan(this)
 
-        def createCometExecIter(inputs: Seq[Iterator[ColumnarBatch]]): CometExecIterator = {
-          val it =
-            new CometExecIterator(CometExec.newIterId, inputs, serializedPlanCopy, nativeMetrics)
+        // Collect the input ColumnarBatches from the child operators and create a CometExecIterator
+        // to execute the native plan.
+        val sparkPlans = ArrayBuffer.empty[SparkPlan]
+        val inputs = ArrayBuffer.empty[RDD[ColumnarBatch]]
+
+        foreachUntilCometInput(this)(sparkPlans += _)
+
+        // Local relations and the files of native file scans are read natively, so they are
+        // not inputs. The native block runs in the partitions of the files, or in a single one
+        // for a local relation.
+        val nativeSources = sparkPlans.collect {
+          case _: CometLocalTableScanExec => 1
+          case scan: CometNativeFileScanExec => math.max(scan.numPartitions, 1)
+        }
+        sparkPlans --= sparkPlans.filter {
+          case _: CometLocalTableScanExec | _: CometNativeFileScanExec => true
+          case _ => false
+        }
+
+        // The inputs reading the same exchange, e.g., the reused shuffle of a self-join, are
+        // read once by the scan of the first of them, which shares the batches natively
+        val sourceIds = CometExec.sharedSourceIds(sparkPlans.toSeq)
+        val serializedPlanCopy = if (sourceIds.exists(_ != 0)) {
+          CometExec.withSourceIds(serializedPlan, sourceIds)
+        } else {
+          serializedPlan
+        }
+
+        def createCometExecIter(
+            inputs: Seq[Iterator[ColumnarBatch]],
+            partitionIndex: Int): CometExecIterator = {
+          val it = new CometExecIterator(
+            CometExec.newIterId,
+            inputs,
+            serializedPlanCopy,
+            nativeMetrics,
+            partitionIndex)
 
           setSubqueries(it.id, originalPlan)
 
@@ -229,51 +518,61 @@ abstract class CometNativeExec extends CometExec {
           it
         }
 
-        // Collect the input ColumnarBatches from the child operators and create a CometExecIterator
-        // to execute the native plan.
-        val sparkPlans = ArrayBuffer.empty[SparkPlan]
-        val inputs = ArrayBuffer.empty[RDD[ColumnarBatch]]
-
-        foreachUntilCometInput(this)(sparkPlans += _)
-
         // Find the first non broadcast plan
         val firstNonBroadcastPlan = sparkPlans.zipWithIndex.find {
           case (_: CometBroadcastExchangeExec, _) => false
           case (BroadcastQueryStageExec(_, _: CometBroadcastExchangeExec, _), _) => false
+          case (ReusedExchangeExec(_, _: CometBroadcastExchangeExec), _) => false
           case (BroadcastQueryStageExec(_, _: ReusedExchangeExec, _), _) => false
           case _ => true
         }
 
-        // If the first non broadcast plan is not found, it means all the plans are broadcast plans.
-        // This is not expected, so throw an exception.
-        if (firstNonBroadcastPlan.isEmpty) {
+        if (nativeSources.nonEmpty) {
+          // The partitions of a native source are determined by the source itself, so the native
+          // block can read broadcast relations besides it but no other input.
+          if (firstNonBroadcastPlan.isDefined || nativeSources.distinct.length > 1) {
+            throw new CometRuntimeException(
+              s"Cannot read a native source along with a partitioned input: $this")
+          }
+          if (sparkPlans.isEmpty) {
+            return sparkContext
+              .parallelize(Seq.empty[Int], nativeSources.head)
+              .mapPartitionsWithIndex((index, _) => createCometExecIter(Seq.empty, index))
+          }
+        } else if (firstNonBroadcastPlan.isEmpty) {
+          // If the first non broadcast plan is not found, it means all the plans are broadcast
+          // plans. This is not expected, so throw an exception.
           throw new CometRuntimeException(s"Cannot find the first non broadcast plan: $this")
         }
 
         // If the first non broadcast plan is found, we need to adjust the partition number of
         // the broadcast plans to make sure they have the same partition number as the first non
         // broadcast plan.
-        val firstNonBroadcastPlanRDD = firstNonBroadcastPlan.get._1.executeColumnar()
-        val firstNonBroadcastPlanNumPartitions = firstNonBroadcastPlanRDD.getNumPartitions
+        val firstNonBroadcastPlanRDD = firstNonBroadcastPlan.map(_._1.executeColumnar())
+        val firstNonBroadcastPlanNumPartitions =
+          firstNonBroadcastPlanRDD.map(_.getNumPartitions).getOrElse(nativeSources.head)
 
         // Spark doesn't need to zip Broadcast RDDs, so it doesn't schedule Broadcast RDDs with
         // same partition number. But for Comet, we need to zip them so we need to adjust the
         // partition number of Broadcast RDDs to make sure they have the same partition number.
+        // A reused broadcast is read from the same broadcast variable as the original one.
         sparkPlans.zipWithIndex.foreach { case (plan, idx) =>
           plan match {
+            case _ if sourceIds(idx) != 0 && sourceIds(idx) != idx + 1 =>
+            // Read by the scan of the first input reading the same exchange
             case c: CometBroadcastExchangeExec =>
-              inputs += c.setNumPartitions(firstNonBroadcastPlanNumPartitions).executeColumnar()
+              inputs += c.executeColumnar(firstNonBroadcastPlanNumPartitions)
             case BroadcastQueryStageExec(_, c: CometBroadcastExchangeExec, _) =>
-              inputs += c.setNumPartitions(firstNonBroadcastPlanNumPartitions).executeColumnar()
+              inputs += c.executeColumnar(firstNonBroadcastPlanNumPartitions)
             case ReusedExchangeExec(_, c: CometBroadcastExchangeExec) =>
-              inputs += c.setNumPartitions(firstNonBroadcastPlanNumPartitions).executeColumnar()
+              inputs += c.executeColumnar(firstNonBroadcastPlanNumPartitions)
             case BroadcastQueryStageExec(
                   _,
                   ReusedExchangeExec(_, c: CometBroadcastExchangeExec),
                   _) =>
-              inputs += c.setNumPartitions(firstNonBroadcastPlanNumPartitions).executeColumnar()
+              inputs += c.executeColumnar(firstNonBroadcastPlanNumPartitions)
             case _ if idx == firstNonBroadcastPlan.get._2 =>
-              inputs += firstNonBroadcastPlanRDD
+              inputs += firstNonBroadcastPlanRDD.get
             case _ =>
               val rdd = plan.executeColumnar()
               if (rdd.getNumPartitions != firstNonBroadcastPlanNumPartitions) {
@@ -290,7 +589,7 @@ abstract class CometNativeExec extends CometExec {
           throw new CometRuntimeException(s"No input for CometNativeExec:\n $this")
         }
 
-        ZippedPartitionsRDD(sparkContext, inputs.toSeq)(createCometExecIter(_))
+        ZippedPartitionsRDD(sparkContext, inputs.toSeq)(createCometExecIter)
     }
   }
 
@@ -305,6 +604,8 @@ abstract class CometNativeExec extends CometExec {
    *   - AQEShuffleReadExec - AQE shuffle read node on top of Comet shuffle
    *   - CometShuffleExchangeExec - Comet shuffle exchange node
    *   - CometUnionExec, etc. which executes its children native plan and produces ColumnarBatches
+   *   - CometLocalTableScanExec - Comet local relation node, which is read natively from the plan
+   *   - CometNativeFileScanExec - Comet file scan node, whose files are read natively
    *
    * @param plan
    *   the root of the Comet physical plan tree (e.g., the root of the SparkPlan tree of a query)
@@ -318,7 +619,7 @@ abstract class CometNativeExec extends CometExec {
           _: AQEShuffleReadExec | _: CometShuffleExchangeExec | _: CometUnionExec |
           _: CometTakeOrderedAndProjectExec | _: CometCoalesceExec | _: ReusedExchangeExec |
           _: CometBroadcastExchangeExec | _: BroadcastQueryStageExec |
-          _: CometRowToColumnarExec =>
+          _: CometRowToColumnarExec | _: CometLocalTableScanExec | _: CometNativeFileScanExec =>
         func(plan)
       case _: CometPlan =>
         // Other Comet operators, continue to traverse the tree.
@@ -483,11 +784,11 @@ case class CometSortExec(
 
   override def hashCode(): Int = Objects.hashCode(sortOrder, child)
 
-  override lazy val metrics: Map[String, SQLMetric] =
+  override protected def operatorMetrics: Map[String, SQLMetric] =
     CometMetricNode.baselineMetrics(sparkContext) ++
       Map(
         "spill_count" -> SQLMetrics.createMetric(sparkContext, "number of spills"),
-        "spilled_bytes" -> SQLMetrics.createNanoTimingMetric(sparkContext, "total spilled bytes"))
+        "spilled_bytes" -> SQLMetrics.createSizeMetric(sparkContext, "total spilled bytes"))
 }
 
 case class CometLocalLimitExec(
@@ -505,7 +806,7 @@ case class CometLocalLimitExec(
 
   override def stringArgs: Iterator[Any] = Iterator(limit, child)
 
-  override lazy val metrics: Map[String, SQLMetric] = Map.empty
+  override protected def operatorMetrics: Map[String, SQLMetric] = Map.empty
 
   override def equals(obj: Any): Boolean = {
     obj match {
@@ -575,7 +876,102 @@ case class CometExpandExec(
   override def hashCode(): Int = Objects.hashCode(projections, child)
 
   // TODO: support native Expand metrics
-  override lazy val metrics: Map[String, SQLMetric] = Map.empty
+  override protected def operatorMetrics: Map[String, SQLMetric] = Map.empty
+}
+
+case class CometGenerateExec(
+    override val nativeOp: Operator,
+    override val originalPlan: SparkPlan,
+    generator: Generator,
+    requiredChildOutput: Seq[Attribute],
+    outer: Boolean,
+    generatorOutput: Seq[Attribute],
+    child: SparkPlan,
+    override val serializedPlanOpt: SerializedPlan)
+    extends CometUnaryExec {
+  override def producedAttributes: AttributeSet = AttributeSet(generatorOutput)
+
+  override protected def withNewChildInternal(newChild: SparkPlan): SparkPlan =
+    this.copy(child = newChild)
+
+  override def stringArgs: Iterator[Any] =
+    Iterator(generator, requiredChildOutput, outer, generatorOutput, child)
+
+  override def equals(obj: Any): Boolean = {
+    obj match {
+      case other: CometGenerateExec =>
+        this.generator == other.generator &&
+        this.requiredChildOutput == other.requiredChildOutput &&
+        this.outer == other.outer && this.generatorOutput == other.generatorOutput &&
+        this.child == other.child && this.serializedPlanOpt == other.serializedPlanOpt
+      case _ =>
+        false
+    }
+  }
+
+  override def hashCode(): Int =
+    Objects.hashCode(generator, requiredChildOutput, outer: java.lang.Boolean, child)
+}
+
+/**
+ * Comet physical operator for Spark `LocalTableScanExec`. The rows are shipped within the native
+ * plan, so a native block reading them runs in a single partition.
+ */
+case class CometLocalTableScanExec(
+    override val nativeOp: Operator,
+    override val originalPlan: SparkPlan,
+    rows: Seq[InternalRow],
+    override val serializedPlanOpt: SerializedPlan)
+    extends CometNativeExec
+    with LeafExecNode {
+  override def outputPartitioning: Partitioning = SinglePartition
+
+  override def stringArgs: Iterator[Any] = Iterator(output, rows.length)
+
+  override def equals(obj: Any): Boolean = {
+    obj match {
+      case other: CometLocalTableScanExec =>
+        this.output == other.output && this.rows == other.rows &&
+        this.serializedPlanOpt == other.serializedPlanOpt
+      case _ =>
+        false
+    }
+  }
+
+  override def hashCode(): Int = Objects.hashCode(output, rows)
+}
+
+/**
+ * Comet physical operator for Spark `FileSourceScanExec` of the formats read natively. The files
+ * are read by the native plan, so a native block reading them runs in as many partitions as the
+ * file partitions planned by Spark.
+ */
+case class CometNativeFileScanExec(
+    override val nativeOp: Operator,
+    override val originalPlan: SparkPlan,
+    override val output: Seq[Attribute],
+    numPartitions: Int,
+    override val serializedPlanOpt: SerializedPlan)
+    extends CometNativeExec
+    with LeafExecNode {
+  override def outputPartitioning: Partitioning = UnknownPartitioning(numPartitions)
+
+  override def stringArgs: Iterator[Any] = Iterator(originalPlan.nodeName, output, numPartitions)
+
+  override def equals(obj: Any): Boolean = {
+    obj match {
+      case other: CometNativeFileScanExec =>
+        this.originalPlan == other.originalPlan && this.output == other.output &&
+        this.serializedPlanOpt == other.serializedPlanOpt
+      case _ =>
+        false
+    }
+  }
+
+  override def hashCode(): Int = Objects.hashCode(originalPlan, output)
+
+  override protected def operatorMetrics: Map[String, SQLMetric] =
+    CometMetricNode.fileScanMetrics(sparkContext)
 }
 
 case class CometUnionExec(override val originalPlan: SparkPlan, children: Seq[SparkPlan])
@@ -653,7 +1049,7 @@ case class CometHashAggregateExec(
     Objects.hashCode(groupingExpressions, aggregateExpressions, input, mode, child)
 
   override protected def outputExpressions: Seq[NamedExpression] =
-    originalPlan.asInstanceOf[HashAggregateExec].resultExpressions
+    originalPlan.asInstanceOf[BaseAggregateExec].resultExpressions
 }
 
 case class CometHashJoinExec(
@@ -704,7 +1100,7 @@ case class CometHashJoinExec(
   override def hashCode(): Int =
     Objects.hashCode(leftKeys, rightKeys, condition, buildSide, left, right)
 
-  override lazy val metrics: Map[String, SQLMetric] =
+  override protected def operatorMetrics: Map[String, SQLMetric] =
     CometMetricNode.hashJoinMetrics(sparkContext)
 }
 
@@ -838,7 +1234,7 @@ case class CometBroadcastHashJoinExec(
   override def hashCode(): Int =
     Objects.hashCode(leftKeys, rightKeys, condition, buildSide, left, right)
 
-  override lazy val metrics: Map[String, SQLMetric] =
+  override protected def operatorMetrics: Map[String, SQLMetric] =
     CometMetricNode.hashJoinMetrics(sparkContext)
 }
 
@@ -888,7 +1284,7 @@ case class CometSortMergeJoinExec(
   override def hashCode(): Int =
     Objects.hashCode(leftKeys, rightKeys, condition, left, right)
 
-  override lazy val metrics: Map[String, SQLMetric] =
+  override protected def operatorMetrics: Map[String, SQLMetric] =
     Map(
       "input_batches" -> SQLMetrics.createMetric(sparkContext, "Number of batches consumed"),
       "input_rows" -> SQLMetrics.createMetric(sparkContext, "Number of rows consumed"),
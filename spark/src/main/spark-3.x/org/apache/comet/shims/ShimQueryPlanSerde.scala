@@ -20,7 +20,7 @@
 package org.apache.comet.shims
 
 import org.apache.spark.sql.catalyst.expressions.{BinaryArithmetic, BinaryExpression}
-import org.apache.spark.sql.catalyst.expressions.aggregate.DeclarativeAggregate
+import org.apache.spark.sql.catalyst.expressions.aggregate.{AggregateFunction, DeclarativeAggregate, Percentile}
 
 trait ShimQueryPlanSerde {
   def getFailOnError(b: BinaryArithmetic): Boolean =
@@ -48,23 +48,46 @@ trait ShimQueryPlanSerde {
   // TODO: delete after drop Spark 3.2/3.3 support
   // This method is used to check if the aggregate function is in legacy mode.
   // EvalMode is an enum object in Spark 3.4.
-  def isLegacyMode(aggregate: DeclarativeAggregate): Boolean = {
-    val evalMode = aggregate.getClass.getDeclaredMethods
+  def isLegacyMode(aggregate: DeclarativeAggregate): Boolean =
+    getEvalMode(aggregate).forall("legacy".equalsIgnoreCase)
+
+  // This method is used to check if the aggregate function is in try mode, e.g., `try_sum`.
+  def isTryMode(aggregate: DeclarativeAggregate): Boolean =
+    getEvalMode(aggregate).exists("try".equalsIgnoreCase)
+
+  private def getEvalMode(aggregate: DeclarativeAggregate): Option[String] =
+    aggregate.getClass.getDeclaredMethods
       .flatMap(m =>
         m.getName match {
-          case "evalMode" => Some(m.invoke(aggregate))
+          case "evalMode" => Some(m.invoke(aggregate).toString)
           case _ => None
         })
-
-    if (evalMode.isEmpty) {
-      true
-    } else {
-      "legacy".equalsIgnoreCase(evalMode.head.toString)
-    }
-  }
+      .headOption
 
   // TODO: delete after drop Spark 3.2 support
   def isBloomFilterMightContain(binary: BinaryExpression): Boolean = {
     binary.getClass.getName == "org.apache.spark.sql.catalyst.expressions.BloomFilterMightContain"
   }
+
+  // TODO: delete after drop Spark 3.2 support
+  def isBloomFilterAggregate(aggregate: AggregateFunction): Boolean = {
+    aggregate.getClass.getName ==
+      "org.apache.spark.sql.catalyst.expressions.aggregate.BloomFilterAggregate"
+  }
+
+  // The expected number of items and the number of bits of a `BloomFilterAggregate`, which are
+  // capped by the runtime bloom filter configs of Spark.
+  def getBloomFilterAggregateSize(aggregate: AggregateFunction): (Long, Long) = {
+    def sizeOf(name: String): Long =
+      aggregate.getClass.getMethod(name).invoke(aggregate).asInstanceOf[Long]
+    (sizeOf("estimatedNumItems"), sizeOf("numBits"))
+  }
+
+  // TODO: delete after drop Spark 3.2 support
+  // Whether the percentile is computed in descending order, i.e., `percentile_cont` with
+  // `WITHIN GROUP (ORDER BY ... DESC)`, which is only available since Spark 3.3.
+  def isReversedPercentile(percentile: Percentile): Boolean =
+    percentile.getClass.getMethods
+      .find(_.getName == "reverse")
+      .exists(_.invoke(percentile).asInstanceOf[Boolean])
 }
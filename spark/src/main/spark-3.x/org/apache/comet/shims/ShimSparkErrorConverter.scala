@@ -0,0 +1,67 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one
+ * or more contributor license agreements.  See the NOTICE file
+ * distributed with this work for additional information
+ * regarding copyright ownership.  The ASF licenses this file
+ * to you under the Apache License, Version 2.0 (the
+ * "License"); you may not use this file except in compliance
+ * with the License.  You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+ * KIND, either express or implied.  See the License for the
+ * specific language governing permissions and limitations
+ * under the License.
+ */
+
+package org.apache.comet.shims
+package org.apache.comet.shims
+
+import scala.collection.JavaConverters._
+
+import org.apache.spark.SPARK_VERSION_SHORT
+
+import org.apache.comet.CometSparkException
+
+trait ShimSparkErrorConverter {
+
+  // TODO: remove after dropping Spark 3.2 and 3.3 support and directly create the exceptions
+  /**
+   * Converts the error raised natively with an error class to the Spark exception of the error
+   * class, so that it can't be told from the error raised by Spark. Before Spark 3.4, whose error
+   * classes and message parameters differ, the native error is kept.
+   */
+  protected def toSparkException(e: CometSparkException): Throwable = {
+    val exceptionClass = e.getErrorClass match {
+      case "DIVIDE_BY_ZERO" | "CAST_OVERFLOW" | "ARITHMETIC_OVERFLOW" |
+          "NUMERIC_VALUE_OUT_OF_RANGE" =>
+        "org.apache.spark.SparkArithmeticException"
+      case "CAST_INVALID_INPUT" => "org.apache.spark.SparkNumberFormatException"
+      case "INVALID_ARRAY_INDEX" => "org.apache.spark.SparkArrayIndexOutOfBoundsException"
+      case _ => return e
+    }
+    if (SPARK_VERSION_SHORT < "3.4") {
+      return e
+    }
+    // (errorClass: String, messageParameters: Map[String, String], context: Array[QueryContext],
+    // summary: String)
+    Class
+      .forName(exceptionClass)
+      .getDeclaredConstructors
+      .find { c =>
+        val types = c.getParameterTypes
+        types.length == 4 && types(0) == classOf[String] &&
+        types(1) == classOf[Map[_, _]] && types(2).isArray
+      }
+      .map { c =>
+        val contextType = c.getParameterTypes()(2).getComponentType
+        val context = java.lang.reflect.Array.newInstance(contextType, 0)
+        val messageParameters = e.getMessageParameters.asScala.toMap
+        c.newInstance(e.getErrorClass, messageParameters, context, "").asInstanceOf[Throwable]
+      }
+      .getOrElse(e)
+  }
+}
@@ -62,10 +62,29 @@ class CometCastSuite extends CometTestBase with AdaptiveSparkPlanHelper {
   test("cast string to bool") {
     val testValues =
       (Seq("TRUE", "True", "true", "FALSE", "False", "false", "1", "0", "", null) ++
+        Seq(" yes\t", "\u0001no\u007f", "\u00a0true") ++
         generateStrings("truefalseTRUEFALSEyesno10" + whitespaceChars, 8)).toDF("a")
     castTest(testValues, DataTypes.BooleanType)
   }
 
+  test("cast bool to string") {
+    castAnswerTest(Seq(Some(true), Some(false), None).toDF("a"), DataTypes.StringType)
+  }
+
+  test("cast date to string") {
+    val dates = Seq("1970-01-01", "2020-02-29", "1582-10-04", "0001-01-01", "9999-12-31", null)
+      .toDF("a")
+      .select(col("a").cast(DataTypes.DateType).as("a"))
+    castAnswerTest(dates, DataTypes.StringType)
+  }
+
+  test("cast binary to string") {
+    // An invalid UTF-8 byte is replaced with U+FFFD like Spark
+    val values =
+      Seq("abc".getBytes, "\u4e2d\u6587".getBytes, Array.empty[Byte], Array[Byte](-1, 97), null)
+    castAnswerTest(values.toDF("a"), DataTypes.StringType)
+  }
+
   ignore("cast string to byte") {
     castTest(generateStrings(numericPattern, 8).toDF("a"), DataTypes.ByteType)
   }
@@ -116,6 +135,66 @@ class CometCastSuite extends CometTestBase with AdaptiveSparkPlanHelper {
     }
   }
 
+  test("cast numeric to timestamp") {
+    val longs = Seq(0L, 1L, -1L, 1700000000L, Long.MaxValue, Long.MinValue).map(Option(_)) :+ None
+    castAnswerTest(longs.toDF("a"), DataTypes.TimestampType)
+    val ints = Seq(Some(0), Some(-86400), Some(Int.MaxValue), None)
+    castAnswerTest(ints.toDF("a"), DataTypes.TimestampType)
+    castAnswerTest(Seq(0.0, 1.5, -1.5, 1700000000.123456).toDF("a"), DataTypes.TimestampType)
+    castAnswerTest(
+      Seq(Double.NaN, Double.PositiveInfinity).toDF("a"),
+      DataTypes.TimestampType,
+      Some("CAST_INVALID_INPUT"))
+    castAnswerTest(
+      Seq(1.0e20, -1.0e20).toDF("a"),
+      DataTypes.TimestampType,
+      Some("CAST_OVERFLOW"))
+  }
+
+  test("cast timestamp to numeric") {
+    val timestamps = Seq(
+      "1970-01-01 00:00:00",
+      "1969-12-31 23:59:59.5",
+      "2023-11-14 22:13:20.123456",
+      "9999-12-31 23:59:59.999999").map(java.sql.Timestamp.valueOf)
+    val input = timestamps.toDF("a")
+    Seq(DataTypes.LongType, DataTypes.DoubleType, DataTypes.FloatType).foreach { toType =>
+      castAnswerTest(input, toType)
+    }
+    Seq(DataTypes.IntegerType, DataTypes.ShortType, DataTypes.ByteType).foreach { toType =>
+      castAnswerTest(input, toType, Some("CAST_OVERFLOW"))
+    }
+  }
+
+  private def castAnswerTest(
+      input: DataFrame,
+      toType: DataType,
+      ansiError: Option[String] = None): Unit = {
+    withTempPath { dir =>
+      val data = roundtripParquet(input, dir).coalesce(1)
+      data.createOrReplaceTempView("t")
+
+      withSQLConf((SQLConf.ANSI_ENABLED.key, "false")) {
+        checkSparkAnswer(data.withColumn("converted", col("a").cast(toType)))
+        checkSparkAnswer(spark.sql(s"select try_cast(a as ${toType.sql}) from t"))
+      }
+
+      withSQLConf(
+        (SQLConf.ANSI_ENABLED.key, "true"),
+        (CometConf.COMET_ANSI_MODE_ENABLED.key, "true")) {
+        val df = data.withColumn("converted", col("a").cast(toType))
+        ansiError match {
+          case Some(error) =>
+            val (_, actual) = checkSparkThrows(df)
+            assert(actual.getMessage.contains(error))
+          case None =>
+            checkSparkAnswer(df)
+        }
+        checkSparkAnswer(spark.sql(s"select try_cast(a as ${toType.sql}) from t"))
+      }
+    }
+  }
+
   private def generateFloats(): DataFrame = {
     val r = new Random(0)
     Range(0, dataSize).map(_ => r.nextFloat()).toDF("a")
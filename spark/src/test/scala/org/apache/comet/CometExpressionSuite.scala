@@ -20,9 +20,12 @@
 package org.apache.comet
 
 import org.apache.hadoop.fs.Path
-import org.apache.spark.sql.{CometTestBase, DataFrame, Row}
+import org.apache.spark.SparkThrowable
+import org.apache.spark.sql.{Column, CometTestBase, DataFrame, Row}
+import org.apache.spark.sql.catalyst.expressions.{HiveHash, Reverse}
+import org.apache.spark.sql.comet.CometProjectExec
 import org.apache.spark.sql.execution.adaptive.AdaptiveSparkPlanHelper
-import org.apache.spark.sql.functions.expr
+import org.apache.spark.sql.functions.{col, expr}
 import org.apache.spark.sql.internal.SQLConf
 import org.apache.spark.sql.internal.SQLConf.SESSION_LOCAL_TIMEZONE
 import org.apache.spark.sql.types.{Decimal, DecimalType}
@@ -204,6 +207,13 @@ class CometExpressionSuite extends CometTestBase with AdaptiveSparkPlanHelper {
     }
   }
 
+  test("string_space with negative and null lengths") {
+    val data = Seq(Some(3), Some(-1), None, Some(0))
+    withParquetTable(data.map(Tuple1(_)), "tbl") {
+      checkSparkAnswerAndOperator("SELECT space(_1) FROM tbl")
+    }
+  }
+
   test("hour, minute, second") {
     Seq(true, false).foreach { dictionaryEnabled =>
       withTempDir { dir =>
@@ -652,6 +662,41 @@ class CometExpressionSuite extends CometTestBase with AdaptiveSparkPlanHelper {
     }
   }
 
+  test("integral divide and remainder (ANSI disable)") {
+    withSQLConf(SQLConf.ANSI_ENABLED.key -> "false") {
+      val data = Seq(
+        (7L, 2L, 7, -2, 7.5, 2.0),
+        (-7L, 0L, -7, 0, -7.5, 0.0),
+        (Long.MinValue, -1L, Int.MinValue, -1, -0.0, -0.0))
+      withParquetTable(data, "tbl") {
+        checkSparkAnswerAndOperator(
+          "SELECT _1 div _2, _3 div _4, _1 % _2, _3 % _4, _5 % _6, _5 / _6 FROM tbl")
+      }
+    }
+  }
+
+  test("divide by zero (ANSI enable)") {
+    withSQLConf(
+      SQLConf.ANSI_ENABLED.key -> "true",
+      CometConf.COMET_ANSI_MODE_ENABLED.key -> "true") {
+      withParquetTable(Seq((1, 0, 1L, 0L)), "tbl") {
+        Seq("_1 / _2", "_1 % _2", "_3 div _4").foreach { expr =>
+          val (expected, actual) = checkSparkThrows(sql(s"SELECT $expr FROM tbl"))
+          if (isSpark34Plus) {
+            assert(expected.getMessage.contains("[DIVIDE_BY_ZERO]"))
+            // The native error is converted to the Spark exception of the error class
+            assert(actual.getClass == expected.getClass)
+            val sparkError = expected.asInstanceOf[SparkThrowable]
+            val cometError = actual.asInstanceOf[SparkThrowable]
+            assert(cometError.getErrorClass == sparkError.getErrorClass)
+            assert(cometError.getSqlState == sparkError.getSqlState)
+          }
+          assert(actual.getMessage.contains("[DIVIDE_BY_ZERO] Division by zero"))
+        }
+      }
+    }
+  }
+
   test("decimals arithmetic and comparison") {
     // TODO: enable Spark 3.2 & 3.3 tests after supporting decimal reminder operation
     assume(isSpark34Plus)
@@ -981,6 +1026,28 @@ class CometExpressionSuite extends CometTestBase with AdaptiveSparkPlanHelper {
     }
   }
 
+  test("trim with trim characters") {
+    Seq(false, true).foreach { dictionary =>
+      withSQLConf("parquet.enable.dictionary" -> dictionary.toString) {
+        val table = "test"
+        withTable(table) {
+          sql(s"create table $table(col string, chars string) using parquet")
+          sql(s"""insert into $table values
+            ('xyxSparkSQLyxy', 'xy'), ('  \tSpark SQL\t  ', ' '), ('天地Spark天', '天'),
+            ('😁Spark😁😁', '😁S'), ('SparkSQL', ''), (null, 'x'), ('SparkSQL', null)""")
+
+          Seq("BOTH", "LEADING", "TRAILING").foreach { side =>
+            checkSparkAnswerAndOperator(s"SELECT trim($side 'xy' FROM col) FROM $table")
+            checkSparkAnswerAndOperator(s"SELECT trim($side '天😁' FROM col) FROM $table")
+            checkSparkAnswerAndOperator(s"SELECT trim($side chars FROM col) FROM $table")
+            checkSparkAnswerAndOperator(s"SELECT trim($side FROM col) FROM $table")
+          }
+          checkSparkAnswerAndOperator(s"SELECT btrim(col, chars) FROM $table")
+        }
+      }
+    }
+  }
+
   test("md5") {
     Seq(false, true).foreach { dictionary =>
       withSQLConf("parquet.enable.dictionary" -> dictionary.toString) {
@@ -995,6 +1062,27 @@ class CometExpressionSuite extends CometTestBase with AdaptiveSparkPlanHelper {
     }
   }
 
+  test("crc32, md5, sha1 and sha2") {
+    Seq(false, true).foreach { dictionary =>
+      withSQLConf("parquet.enable.dictionary" -> dictionary.toString) {
+        val table = "test"
+        withTable(table) {
+          sql(s"create table $table(col string, b binary) using parquet")
+          sql(s"""
+             |insert into $table values
+             |('test1', X'00FF'), ('test1', X''), ('苹果手机', NULL), (NULL, X'00FF'), ('', X'')
+             |""".stripMargin)
+          checkSparkAnswerAndOperator(
+            s"""
+               |select crc32(col), md5(col), sha1(col), sha2(col, 256), sha2(col, 512),
+               |crc32(b), md5(b), sha1(b), sha2(b, 224), sha2(b, 384), sha1(cast(null as binary))
+               |from $table
+               |""".stripMargin)
+        }
+      }
+    }
+  }
+
   test("string concat_ws") {
     Seq(false, true).foreach { dictionary =>
       withSQLConf("parquet.enable.dictionary" -> dictionary.toString) {
@@ -1020,6 +1108,7 @@ class CometExpressionSuite extends CometTestBase with AdaptiveSparkPlanHelper {
           sql(s"create table $table(id int, name varchar(20)) using parquet")
           sql(s"insert into $table values(1, 'James'), (2, 'Smith'), (3, 'Smith')")
           checkSparkAnswerAndOperator(s"SELECT repeat(name, 3) FROM $table")
+          checkSparkAnswerAndOperator(s"SELECT repeat(name, id - 2), repeat(name, 0) FROM $table")
         }
       }
     }
@@ -1040,6 +1129,27 @@ class CometExpressionSuite extends CometTestBase with AdaptiveSparkPlanHelper {
     }
   }
 
+  test("reverse arrays") {
+    withSQLConf(CometConf.COMET_EXEC_SHUFFLE_ENABLED.key -> "true") {
+      // Groups with only null values collect empty arrays
+      val data = (0 until 100).map(i => (i % 5, if (i % 5 == 4 || i % 7 == 0) None else Some(i)))
+      withParquetTable(data, "tbl") {
+        // The order of collected values depends on the order of shuffle blocks, so the reversed
+        // arrays are checked against the collected ones of the same query
+        val df =
+          sql("SELECT a, reverse(a) FROM (SELECT collect_list(_2) AS a FROM tbl GROUP BY _1)")
+        df.collect().foreach { row =>
+          assert(row.getSeq[Int](1) == row.getSeq[Int](0).reverse)
+        }
+        assert(find(df.queryExecution.executedPlan) {
+          case p: CometProjectExec =>
+            p.projectList.exists(_.find(_.isInstanceOf[Reverse]).isDefined)
+          case _ => false
+        }.isDefined)
+      }
+    }
+  }
+
   test("EqualNullSafe should preserve comet filter") {
     Seq("true", "false").foreach(b =>
       withParquetTable(
@@ -1115,6 +1225,47 @@ class CometExpressionSuite extends CometTestBase with AdaptiveSparkPlanHelper {
     }
   }
 
+  test("in/not in large literal lists") {
+    Seq(false, true).foreach { dictionary =>
+      withSQLConf("parquet.enable.dictionary" -> dictionary.toString) {
+        val table = "test"
+        withTable(table) {
+          sql(s"create table $table(s string, d decimal(10, 2), dt date) using parquet")
+          sql(
+            s"insert into $table values('v1', 1.50, date '2024-01-01'), " +
+              "('v12', 3.00, date '2024-01-15'), ('x', -1.50, date '2023-12-31'), " +
+              "(NULL, NULL, NULL), ('v5', 0.00, date '2024-01-20')")
+
+          val strings = (0 until 20).map(i => s"'v$i'").mkString(", ")
+          val decimals = (0 until 20).map(i => s"${i * 1.5}").mkString(", ")
+          val dates = (0 until 20).map(i => s"date '2024-01-01' + $i").mkString(", ")
+          checkSparkAnswerAndOperator(
+            s"SELECT s in ($strings), d in ($decimals), dt in ($dates) FROM $table")
+          checkSparkAnswerAndOperator(
+            s"SELECT s not in ($strings), d not in ($decimals, NULL) FROM $table")
+          checkSparkAnswerAndOperator(s"SELECT * FROM $table WHERE s in ($strings, NULL)")
+        }
+      }
+    }
+  }
+
+  test("in/not in large literal lists of doubles") {
+    val table = "test"
+    withTable(table) {
+      sql(s"create table $table(d double) using parquet")
+      sql(s"insert into $table values(0.0D), (-0.0D), (double('NaN')), (3.0D), (0.5D), (NULL)")
+
+      // Spark only converts `In` to `InSet` for more than 10 literals, and unlike `In`, `InSet`
+      // doesn't match -0.0 with 0.0
+      Seq(10, 11).foreach { size =>
+        val doubles = (0 until size).map(i => s"${i}.0D").mkString(", ")
+        checkSparkAnswerAndOperator(s"SELECT d, d in ($doubles) FROM $table")
+        checkSparkAnswerAndOperator(s"SELECT d, d in (-0.0D, double('NaN'), $doubles) FROM $table")
+        checkSparkAnswerAndOperator(s"SELECT d, d not in ($doubles) FROM $table")
+      }
+    }
+  }
+
   test("case_when") {
     Seq(false, true).foreach { dictionary =>
       withSQLConf("parquet.enable.dictionary" -> dictionary.toString) {
@@ -1139,6 +1290,20 @@ class CometExpressionSuite extends CometTestBase with AdaptiveSparkPlanHelper {
     }
   }
 
+  test("case_when evaluates values only on the rows matched by their conditions") {
+    withSQLConf(
+      SQLConf.ANSI_ENABLED.key -> "true",
+      CometConf.COMET_ANSI_MODE_ENABLED.key -> "true") {
+      withParquetTable(Seq((10L, 2L), (20L, 0L), (30L, 3L), (40L, 0L)), "tbl") {
+        // `_1 div _2` fails in ANSI mode if it is evaluated on the rows where `_2` is 0
+        checkSparkAnswerAndOperator(
+          "SELECT CASE WHEN _2 = 0 THEN -1 WHEN _1 > 15 THEN _1 div _2 ELSE _1 END FROM tbl")
+        checkSparkAnswerAndOperator(
+          "SELECT CASE WHEN _2 != 0 THEN _1 div _2 END, CASE WHEN _2 > 5 THEN 0 END FROM tbl")
+      }
+    }
+  }
+
   test("not") {
     Seq(false, true).foreach { dictionary =>
       withSQLConf("parquet.enable.dictionary" -> dictionary.toString) {
@@ -1288,6 +1453,31 @@ class CometExpressionSuite extends CometTestBase with AdaptiveSparkPlanHelper {
     }
   }
 
+  test("Decimal binary ops with mixed precisions and integral operands") {
+    assume(isSpark34Plus)
+    Seq(true, false).foreach { allowPrecisionLoss =>
+      withSQLConf(
+        "spark.sql.decimalOperations.allowPrecisionLoss" -> allowPrecisionLoss.toString) {
+        val prepareQuery =
+          """
+            |select cast(c1 as decimal(10, 2)) c1, cast(c2 as decimal(38, 10)) c2, i, l
+            |from values (12.34, 123456789.0123456789, 7, 3000000000),
+            |  (-0.5, -0.0000000001, -2, -1), (null, 1, null, 5) as t(c1, c2, i, l)
+            |""".stripMargin
+        testSingleLineQuery(
+          prepareQuery,
+          "select a, typeof(a), b, typeof(b), c, typeof(c) from " +
+            "(select c1 + c2 a, c1 - i b, c2 * 3 c from tbl)",
+          s"add_sub_mul (allowPrecisionLoss = $allowPrecisionLoss)")
+        testSingleLineQuery(
+          prepareQuery,
+          "select a, typeof(a), b, typeof(b), c, typeof(c) from " +
+            "(select c2 / c1 a, c1 / i b, c2 % l c from tbl)",
+          s"div_rem (allowPrecisionLoss = $allowPrecisionLoss)")
+      }
+    }
+  }
+
   test("Decimal random number tests") {
     assume(isSpark34Plus) // Only Spark 3.4+ has the fix for SPARK-45786
     val rand = scala.util.Random
@@ -1419,6 +1609,8 @@ class CometExpressionSuite extends CometTestBase with AdaptiveSparkPlanHelper {
                |select
                |md5(col), md5(cast(a as string)), md5(cast(b as string)),
                |hash(col), hash(col, 1), hash(col, 0), hash(col, a, b), hash(b, a, col),
+               |xxhash64(col), xxhash64(col, 1), xxhash64(col, 0), xxhash64(col, a, b),
+               |xxhash64(b, a, col),
                |sha2(col, 0), sha2(col, 256), sha2(col, 224), sha2(col, 384), sha2(col, 512), sha2(col, 128)
                |from test
                |""".stripMargin)
@@ -1427,4 +1619,51 @@ class CometExpressionSuite extends CometTestBase with AdaptiveSparkPlanHelper {
     }
   }
 
+  test("hive hash") {
+    Seq(true, false).foreach { dictionary =>
+      withSQLConf("parquet.enable.dictionary" -> dictionary.toString) {
+        val table = "test"
+        withTable(table) {
+          sql(
+            s"create table $table(col string, a int, b double, c decimal(10, 2), d timestamp) " +
+              "using parquet")
+          sql(s"""
+             |insert into $table values
+             |('Spark SQL  ', 10, 1.2, 1.50, timestamp'2024-01-01 00:00:00.5'),
+             |(NULL, NULL, NULL, NULL, NULL), ('', 0, -0.0, 0, timestamp'1969-12-31 23:59:59.1'),
+             |('苹果手机', NULL, double('NaN'), -12345.67, timestamp'1970-01-01 00:00:00')
+             |""".stripMargin)
+          // `HiveHash` is not a SQL function
+          val columns = Seq("col", "a", "b", "c", "d").map(col(_).expr)
+          checkSparkAnswerAndOperator(
+            spark
+              .table(table)
+              .select(
+                columns.map(c => new Column(HiveHash(Seq(c)))) :+
+                  new Column(HiveHash(columns)): _*))
+        }
+      }
+    }
+  }
+
+  test("rand, monotonically_increasing_id and spark_partition_id") {
+    withTempPath { dir =>
+      val path = dir.getCanonicalPath
+      spark.range(3000).repartition(3).write.parquet(path)
+      withSQLConf(CometConf.COMET_BATCH_SIZE.key -> "100") {
+        spark.read.parquet(path).createOrReplaceTempView("tbl")
+        // The values only depend on the seeds, the partition and the rows before, like Spark
+        checkSparkAnswerAndOperator(
+          "SELECT id, rand(42) AS r, rand(7), monotonically_increasing_id(), " +
+            "spark_partition_id() + 1 FROM tbl WHERE id % 3 != 0")
+
+        // Not supported natively inside other expressions
+        checkSparkAnswer("SELECT id, if(id > 10, rand(42), 0.0) FROM tbl")
+
+        // Sampling falls back to Spark
+        checkSparkAnswer(spark.table("tbl").sample(0.3, 42))
+      }
+    }
+  }
+
 }
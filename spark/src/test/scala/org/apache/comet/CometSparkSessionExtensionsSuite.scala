@@ -61,6 +61,23 @@ class CometSparkSessionExtensionsSuite extends CometTestBase {
     }
   }
 
+  test("Spark types supported by the native Parquet reader") {
+    Seq(
+      IntegerType -> true,
+      new StructType().add("f1", IntegerType).add("f2", StringType) -> true,
+      new StructType().add("f1", new StructType().add("f2", DecimalType(10, 2))) -> true,
+      new StructType() -> false,
+      new StructType().add("f1", ArrayType(IntegerType)) -> true,
+      ArrayType(ArrayType(new StructType().add("f1", StringType))) -> true,
+      ArrayType(MapType(keyType = IntegerType, valueType = BinaryType)) -> true,
+      MapType(keyType = IntegerType, valueType = BinaryType) -> true,
+      MapType(keyType = StringType, valueType = new StructType()) -> false,
+      MapType(keyType = StringType, valueType = CalendarIntervalType) -> false).foreach {
+      case (dt, expected) =>
+        assert(CometSparkSessionExtensions.isScanTypeSupported(dt) == expected)
+    }
+  }
+
   test("isCometEnabled") {
     val conf = new SQLConf
 
@@ -84,6 +101,22 @@ class CometSparkSessionExtensionsSuite extends CometTestBase {
     NativeBase.setLoaded(true)
   }
 
+  test("native ABI version and capabilities") {
+    assert(NativeBase.isLoaded)
+    Seq(
+      NativeBase.CAPABILITY_NATIVE_SHUFFLE_READER,
+      NativeBase.CAPABILITY_MAPPED_BROADCAST,
+      NativeBase.CAPABILITY_RESOURCE_PROFILE,
+      NativeBase.CAPABILITY_OPERATOR_OFFLOAD,
+      NativeBase.CAPABILITY_NATIVE_FILE_SCAN,
+      NativeBase.CAPABILITY_SHUFFLE_ENCRYPTION,
+      NativeBase.CAPABILITY_SHARED_SCAN,
+      NativeBase.CAPABILITY_BAND_JOIN).foreach { capability =>
+      assert(NativeBase.hasCapability(capability), capability)
+    }
+    assert(!NativeBase.hasCapability("unknown"))
+  }
+
   test("Arrow properties") {
     NativeBase.setLoaded(false)
     NativeBase.load()
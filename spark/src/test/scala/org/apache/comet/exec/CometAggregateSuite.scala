@@ -19,6 +19,8 @@
 
 package org.apache.comet.exec
 
+import java.sql.Timestamp
+
 import scala.util.Random
 
 import org.apache.hadoop.fs.Path
@@ -26,7 +28,7 @@ import org.apache.parquet.example.data.simple.SimpleGroup
 import org.apache.parquet.schema.MessageTypeParser
 import org.apache.spark.sql.{CometTestBase, DataFrame, Row}
 import org.apache.spark.sql.catalyst.optimizer.EliminateSorts
-import org.apache.spark.sql.comet.CometHashAggregateExec
+import org.apache.spark.sql.comet.{CometHashAggregateExec, CometProjectExec}
 import org.apache.spark.sql.execution.adaptive.AdaptiveSparkPlanHelper
 import org.apache.spark.sql.functions.{count_distinct, sum}
 import org.apache.spark.sql.internal.SQLConf
@@ -438,6 +440,33 @@ class CometAggregateSuite extends CometTestBase with AdaptiveSparkPlanHelper {
     }
   }
 
+  test("group-by and join keys of NaNs with different bits, -0.0 and 0.0") {
+    // Like Spark, the NaNs of any bits are normalized to the same one, and -0.0 to 0.0
+    val nans = Seq(
+      Double.NaN,
+      java.lang.Double.longBitsToDouble(0x7ff8000000000001L),
+      java.lang.Double.longBitsToDouble(0xfff8000000000000L))
+    Seq(true, false).foreach { dictionaryEnabled =>
+      val data: Seq[(Double, Float, Int)] = (nans ++ Seq(-0.0, 0.0, 1.0)).zipWithIndex.map {
+        case (d, i) => (d, d.toFloat, i)
+      }
+      withParquetTable(data, "tbl", dictionaryEnabled) {
+        checkSparkAnswer("SELECT _1, COUNT(*), SUM(_3) FROM tbl GROUP BY _1")
+        checkSparkAnswer("SELECT _2, COUNT(*), SUM(_3) FROM tbl GROUP BY _2")
+        checkSparkAnswer("SELECT COUNT(DISTINCT _1), COUNT(DISTINCT _2) FROM tbl")
+        checkSparkAnswer("SELECT hash(_1), hash(_2), xxhash64(_1), xxhash64(_2) FROM tbl")
+        checkSparkAnswer(
+          "SELECT a._1, COUNT(*) FROM tbl a JOIN tbl b ON a._1 = b._1 AND a._2 = b._2 " +
+            "GROUP BY a._1")
+        // The struct keys are normalized field by field, and the null structs are kept
+        checkSparkAnswer(
+          "SELECT s, COUNT(*) FROM " +
+            "(SELECT IF(_3 = 0, NULL, named_struct('d', _1, 'i', _3 % 2)) AS s FROM tbl) " +
+            "GROUP BY s")
+      }
+    }
+  }
+
   test("SUM/MIN/MAX/AVG on decimal") {
     Seq(true, false).foreach { dictionaryEnabled =>
       withTempDir { dir =>
@@ -1157,6 +1186,187 @@ class CometAggregateSuite extends CometTestBase with AdaptiveSparkPlanHelper {
     }
   }
 
+  test("collect_list and collect_set") {
+    withSQLConf(CometConf.COMET_EXEC_SHUFFLE_ENABLED.key -> "true") {
+      Seq(true, false).foreach { dictionaryEnabled =>
+        val data = (0 until 100).map(i => (i % 5, if (i % 7 == 0) None else Some(i % 10)))
+        withParquetTable(data, "tbl", dictionaryEnabled) {
+          // The order of collected values depends on the order of shuffle blocks
+          def normalize(rows: Seq[Row]): Seq[(Int, Seq[Int], Seq[Int])] =
+            rows
+              .map(r => (r.getInt(0), r.getSeq[Int](1).sorted, r.getSeq[Int](2).sorted))
+              .sortBy(_._1)
+
+          val query = "SELECT _1, collect_list(_2), collect_set(_2) FROM tbl GROUP BY _1"
+          val expected = withSQLConf(CometConf.COMET_ENABLED.key -> "false") {
+            normalize(sql(query).collect())
+          }
+          val df = sql(query)
+          assert(normalize(df.collect()) == expected)
+          assert(getNumCometHashAggregate(df) == 2)
+        }
+      }
+    }
+  }
+
+  test("approx_percentile") {
+    withSQLConf(CometConf.COMET_EXEC_SHUFFLE_ENABLED.key -> "true") {
+      Seq(true, false).foreach { dictionaryEnabled =>
+        val data = (0 until 1000).map(i => (i % 5, if (i % 7 == 0) None else Some(i % 100)))
+        withParquetTable(data, "tbl", dictionaryEnabled) {
+          checkSparkAnswerAndNumOfAggregates(
+            "SELECT _1, percentile_approx(_2, 0.5), approx_percentile(_2, array(0.1, 0.5, 0.9))" +
+              " FROM tbl GROUP BY _1",
+            2)
+          checkSparkAnswerAndNumOfAggregates(
+            "SELECT percentile_approx(cast(_2 AS double), 0.25, 100), " +
+              "percentile_approx(_2, 0.5) FILTER (WHERE _2 IS NULL) FROM tbl",
+            2)
+        }
+      }
+    }
+  }
+
+  test("approx_percentile with partial and final aggregations in Comet and Spark") {
+    val data = (0 until 1000).map(i => (i % 5, if (i % 7 == 0) None else Some(i % 100)))
+    withParquetTable(data, "tbl") {
+      val query = "SELECT _1, percentile_approx(_2, array(0.25, 0.5)) FROM tbl GROUP BY _1"
+      // Comet partial aggregation finalized by Spark
+      withSQLConf(CometConf.COMET_EXEC_SHUFFLE_ENABLED.key -> "false") {
+        checkSparkAnswerAndNumOfAggregates(query, 1)
+      }
+      // Spark partial aggregation finalized by Comet
+      withSQLConf(
+        CometConf.COMET_SCAN_ENABLED.key -> "false",
+        CometConf.COMET_EXEC_SHUFFLE_ENABLED.key -> "true",
+        CometConf.COMET_COLUMNAR_SHUFFLE_ENABLED.key -> "true") {
+        checkSparkAnswerAndNumOfAggregates(query, 1)
+      }
+    }
+  }
+
+  test("percentile") {
+    withSQLConf(CometConf.COMET_EXEC_SHUFFLE_ENABLED.key -> "true") {
+      Seq(true, false).foreach { dictionaryEnabled =>
+        val data = (0 until 1000).map(i =>
+          (i % 5, if (i % 7 == 0) None else Some(i % 100), i % 3, (i % 10) * 0.5))
+        withParquetTable(data, "tbl", dictionaryEnabled) {
+          checkSparkAnswerAndNumOfAggregates(
+            "SELECT _1, percentile(_2, 0.5), percentile(_2, array(0.1, 0.5, 0.9)) " +
+              "FROM tbl GROUP BY _1",
+            2)
+          checkSparkAnswerAndNumOfAggregates(
+            "SELECT percentile(_4, 0.25), percentile(cast(_4 AS decimal(10, 2)), 0.3, _3), " +
+              "percentile(_2, 0.5) FILTER (WHERE _2 IS NULL) FROM tbl",
+            2)
+        }
+      }
+    }
+  }
+
+  test("percentile with partial and final aggregations in Comet and Spark") {
+    val data = (0 until 1000).map(i => (i % 5, if (i % 7 == 0) None else Some(i % 100)))
+    withParquetTable(data, "tbl") {
+      val query = "SELECT _1, percentile(_2, array(0.25, 0.5)) FROM tbl GROUP BY _1"
+      // Comet partial aggregation finalized by Spark
+      withSQLConf(CometConf.COMET_EXEC_SHUFFLE_ENABLED.key -> "false") {
+        checkSparkAnswerAndNumOfAggregates(query, 1)
+      }
+      // Spark partial aggregation finalized by Comet
+      withSQLConf(
+        CometConf.COMET_SCAN_ENABLED.key -> "false",
+        CometConf.COMET_EXEC_SHUFFLE_ENABLED.key -> "true",
+        CometConf.COMET_COLUMNAR_SHUFFLE_ENABLED.key -> "true") {
+        checkSparkAnswerAndNumOfAggregates(query, 1)
+      }
+    }
+  }
+
+  test("sequential sum and avg of doubles") {
+    withSQLConf(
+      CometConf.COMET_EXEC_SHUFFLE_ENABLED.key -> "true",
+      CometConf.COMET_EXEC_SEQUENTIAL_FLOAT_AGG_ENABLED.key -> "true") {
+      // The sums depend on the order of the additions, so they are the same as Spark's only if
+      // the values are added in the same order
+      val data = (0 until 1000).map(i =>
+        (i % 3, if (i % 7 == 0) None else Some(if (i % 100 == 0) 1e16 * (i % 3 - 1) else 0.1)))
+      withParquetTable(data, "tbl") {
+        checkSparkAnswerAndNumOfAggregates(
+          "SELECT sum(_2), avg(_2), sum(_2) FILTER (WHERE _1 = 1) FROM tbl",
+          2)
+        checkSparkAnswerAndNumOfAggregates("SELECT _1, sum(_2), avg(_2) FROM tbl GROUP BY _1", 2)
+      }
+    }
+  }
+
+  test("aggregate FILTER clauses and count_if") {
+    withSQLConf(CometConf.COMET_EXEC_SHUFFLE_ENABLED.key -> "true") {
+      Seq(true, false).foreach { dictionaryEnabled =>
+        val data = (0 until 100).map(i => (i % 5, if (i % 7 == 0) None else Some(i % 10)))
+        withParquetTable(data, "tbl", dictionaryEnabled) {
+          checkSparkAnswerAndNumOfAggregates(
+            "SELECT _1, count(*) FILTER (WHERE _2 > 3), sum(_2) FILTER (WHERE _2 % 2 = 0)," +
+              " max(_2), min(_2) FILTER (WHERE _2 IS NULL) FROM tbl GROUP BY _1",
+            2)
+          checkSparkAnswerAndNumOfAggregates(
+            "SELECT count_if(_2 > 3), count_if(_2 IS NULL), avg(_2) FILTER (WHERE _1 = 2)" +
+              " FROM tbl",
+            2)
+        }
+      }
+    }
+  }
+
+  test("try_sum and try_avg") {
+    // `try_sum` and `try_avg` are `Sum` and `Average` in TRY mode since Spark 3.4
+    assume(isSpark34Plus)
+    withSQLConf(CometConf.COMET_EXEC_SHUFFLE_ENABLED.key -> "true") {
+      val data = Seq((0, Long.MaxValue, BigDecimal("9" * 20)), (0, 1L, BigDecimal(1))) ++
+        (0 until 100).map(i => (i % 5 + 1, i.toLong, BigDecimal(i)))
+      withParquetTable(data, "tbl") {
+        checkSparkAnswerAndNumOfAggregates(
+          "SELECT _1, try_sum(_2), try_sum(_3), try_avg(_2), try_avg(_3) FROM tbl GROUP BY _1",
+          2)
+        checkSparkAnswerAndNumOfAggregates("SELECT try_sum(_2), try_sum(_3) FROM tbl", 2)
+        // Overflows before merging
+        checkSparkAnswer(
+          sql("SELECT * FROM tbl").repartitionByRange(2, $"_2").selectExpr("try_sum(_2)"))
+      }
+    }
+  }
+
+  test("time windows and session windows") {
+    withSQLConf(
+      CometConf.COMET_EXEC_SHUFFLE_ENABLED.key -> "true",
+      CometConf.COMET_COLUMNAR_SHUFFLE_ENABLED.key -> "true") {
+      Seq(true, false).foreach { dictionaryEnabled =>
+        // Timestamps over a few days around the epoch, and nulls
+        val data = (0 until 200).map { i =>
+          val ts = if (i % 17 == 0) None else Some(new Timestamp((i * 97L - 5000) * 60000))
+          (ts, i % 3, i)
+        }
+        withParquetTable(data, "tbl", dictionaryEnabled) {
+          // Tumbling windows with a start offset, and sliding windows
+          checkSparkAnswerAndNumOfAggregates(
+            "SELECT window, _2, count(*), sum(_3) FROM tbl" +
+              " GROUP BY window(_1, '1 hour', '1 hour', '10 minutes'), _2",
+            2)
+          checkSparkAnswerAndNumOfAggregates(
+            "SELECT window.start, window.end, sum(_3) FROM tbl" +
+              " GROUP BY window(_1, '1 hour', '20 minutes')",
+            2)
+
+          // The sessions are merged by Spark, but their windows are created natively
+          val df = sql(
+            "SELECT _2, session_window.start, session_window.end, count(*) FROM tbl" +
+              " GROUP BY _2, session_window(_1, '2 hours')")
+          checkSparkAnswer(df)
+          assert(find(df.queryExecution.executedPlan)(_.isInstanceOf[CometProjectExec]).isDefined)
+        }
+      }
+    }
+  }
+
   protected def checkSparkAnswerAndNumOfAggregates(query: String, numAggregates: Int): Unit = {
     val df = sql(query)
     checkSparkAnswer(df)
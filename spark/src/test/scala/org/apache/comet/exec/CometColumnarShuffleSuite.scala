@@ -1055,6 +1055,28 @@ class CometShuffleEncryptionSuite extends CometTestBase {
       }
     }
   }
+
+  test("comet native shuffle with encryption") {
+    withParquetTable((0 until 1000).map(i => (i % 17, i.toLong, s"str$i")), "tbl") {
+      Seq(true, false).foreach { nativeReader =>
+        Seq(true, false).foreach { sortBased =>
+          withSQLConf(
+            CometConf.COMET_EXEC_ENABLED.key -> "true",
+            CometConf.COMET_EXEC_SHUFFLE_ENABLED.key -> "true",
+            CometConf.COMET_COLUMNAR_SHUFFLE_ENABLED.key -> "false",
+            CometConf.COMET_EXEC_SHUFFLE_NATIVE_READER_ENABLED.key -> nativeReader.toString,
+            CometConf.COMET_EXEC_SHUFFLE_SORT_BASED_ENABLED.key -> sortBased.toString) {
+            // The partitions written natively are decrypted like the ones of Spark
+            Seq(1, 10, 201).foreach { numPartitions =>
+              val shuffled = sql("SELECT * FROM tbl").repartition(numPartitions, $"_1")
+              checkSparkAnswer(shuffled)
+              checkCometExchange(shuffled, 1, true)
+            }
+          }
+        }
+      }
+    }
+  }
 }
 
 class CometShuffleManagerSuite extends CometTestBase {
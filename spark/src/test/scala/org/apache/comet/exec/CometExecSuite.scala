@@ -35,10 +35,10 @@ import org.apache.spark.sql.catalyst.TableIdentifier
 import org.apache.spark.sql.catalyst.catalog.{BucketSpec, CatalogStatistics, CatalogTable}
 import org.apache.spark.sql.catalyst.expressions.Hex
 import org.apache.spark.sql.catalyst.expressions.aggregate.AggregateMode
-import org.apache.spark.sql.comet.{CometBroadcastExchangeExec, CometBroadcastHashJoinExec, CometCollectLimitExec, CometFilterExec, CometHashAggregateExec, CometHashJoinExec, CometProjectExec, CometRowToColumnarExec, CometScanExec, CometSortExec, CometSortMergeJoinExec, CometTakeOrderedAndProjectExec}
+import org.apache.spark.sql.comet.{CometBroadcastExchangeExec, CometBroadcastHashJoinExec, CometCollectLimitExec, CometFilterExec, CometGenerateExec, CometHashAggregateExec, CometHashJoinExec, CometLocalTableScanExec, CometNativeFileScanExec, CometProjectExec, CometQuerySummaryListener, CometRowToColumnarExec, CometScanExec, CometSortExec, CometSortMergeJoinExec, CometTakeOrderedAndProjectExec}
 import org.apache.spark.sql.comet.execution.shuffle.{CometColumnarShuffle, CometShuffleExchangeExec}
 import org.apache.spark.sql.execution.{CollectLimitExec, ProjectExec, SQLExecution, UnionExec}
-import org.apache.spark.sql.execution.exchange.{BroadcastExchangeExec, ShuffleExchangeExec}
+import org.apache.spark.sql.execution.exchange.{BroadcastExchangeExec, ReusedExchangeExec, ShuffleExchangeExec}
 import org.apache.spark.sql.execution.joins.{BroadcastNestedLoopJoinExec, CartesianProductExec, SortMergeJoinExec}
 import org.apache.spark.sql.execution.window.WindowExec
 import org.apache.spark.sql.expressions.Window
@@ -182,6 +182,123 @@ class CometExecSuite extends CometTestBase {
     }
   }
 
+  test("query execution summary") {
+    withSQLConf(CometConf.COMET_SUMMARY_RETAINED.key -> "2") {
+      withParquetTable((0 until 100).map(i => (i, i % 5)), "tbl") {
+        val listener = new CometQuerySummaryListener
+        val df = sql(
+          "SELECT _1 + 1, cast(make_interval(_1, _2, 0, 0, 0, 0, 0) as string) FROM tbl" +
+            " WHERE _2 > 1")
+        df.collect()
+        (1 to 3).foreach(_ => listener.onSuccess("collect", df.queryExecution, 5000000L))
+        assert(CometQuerySummaryListener.summaries.length == 2)
+
+        val summary = CometQuerySummaryListener.summaries.last
+        assert(summary.succeeded && summary.durationMs == 5)
+        assert(summary.nativeOperators.nonEmpty)
+        assert(summary.nativeRows >= 60)
+        assert(summary.fallbacks.exists { case (operator, reasons) =>
+          operator == "Project" && reasons.contains("make_interval is not supported")
+        })
+
+        val json = summary.toJson
+        assert(json.contains("\"funcName\":\"collect\""))
+        assert(json.contains("\"reasons\":[\"make_interval is not supported\"]"))
+      }
+    }
+  }
+
+  test("CometBroadcastExchangeExec: native serialization") {
+    assume(isSpark34Plus, "ChunkedByteBuffer is not serializable before Spark 3.4+")
+    val modes = Seq((true, false), (true, true), (false, false))
+    modes.foreach { case (nativeSerialization, mmap) =>
+      withSQLConf(
+        CometConf.COMET_EXEC_BROADCAST_FORCE_ENABLED.key -> "true",
+        CometConf.COMET_EXEC_BROADCAST_NATIVE_SERIALIZATION_ENABLED.key ->
+          nativeSerialization.toString,
+        CometConf.COMET_EXEC_BROADCAST_MMAP_ENABLED.key -> mmap.toString,
+        CometConf.COMET_EXEC_BROADCAST_MMAP_THRESHOLD.key -> "0") {
+        withParquetTable((0 until 100).map(i => (i, s"v$i", i % 3 == 0)), "tbl_a") {
+          withParquetTable((0 until 100).map(i => (i, i + 1)), "tbl_b") {
+            val df = sql(
+              "SELECT /*+ BROADCAST(a) */ a._1, a._2, a._3, b._2" +
+                " FROM tbl_a a JOIN tbl_b b ON a._1 = b._1")
+            checkSparkAnswer(df)
+
+            val nativeBroadcast = find(df.queryExecution.executedPlan) {
+              case _: CometBroadcastExchangeExec => true
+              case _ => false
+            }.get.asInstanceOf[CometBroadcastExchangeExec]
+            val numParts = nativeBroadcast.executeColumnar().getNumPartitions
+
+            val rows = nativeBroadcast.executeCollect().toSeq
+            val rowContents =
+              rows.map(row => (row.getInt(0), row.getString(1), row.getBoolean(2))).sorted
+            val expected = (0 until numParts)
+              .flatMap(_ => (0 until 100).map(i => (i, s"v$i", i % 3 == 0)))
+              .sorted
+            assert(rowContents === expected)
+          }
+        }
+      }
+    }
+  }
+
+  test("CometBroadcastExchangeExec: native serialization of multiple batches") {
+    assume(isSpark34Plus, "ChunkedByteBuffer is not serializable before Spark 3.4+")
+    Seq("7", "1000").foreach { batchSize =>
+      withSQLConf(
+        CometConf.COMET_BATCH_SIZE.key -> batchSize,
+        CometConf.COMET_EXEC_BROADCAST_FORCE_ENABLED.key -> "true",
+        CometConf.COMET_EXEC_BROADCAST_NATIVE_SERIALIZATION_ENABLED.key -> "true") {
+        withParquetTable((0 until 100).map(i => (i, s"v$i", i.toDouble)), "tbl_a") {
+          withParquetTable((0 until 100).map(i => (i, i + 1)), "tbl_b") {
+            val df = sql(
+              "SELECT /*+ BROADCAST(a) */ a._1, a._2, a._3, b._2" +
+                " FROM tbl_a a JOIN tbl_b b ON a._1 = b._1")
+            checkSparkAnswer(df)
+
+            val nativeBroadcast = find(df.queryExecution.executedPlan) {
+              case _: CometBroadcastExchangeExec => true
+              case _ => false
+            }.get.asInstanceOf[CometBroadcastExchangeExec]
+            // The small batches of each partition are coalesced into fewer serialized blocks,
+            // which are decoded back to all the rows
+            val numParts = nativeBroadcast.executeColumnar().getNumPartitions
+            val rows = nativeBroadcast.executeCollect().toSeq
+            val rowContents =
+              rows.map(row => (row.getInt(0), row.getString(1), row.getDouble(2))).sorted
+            val expected = (0 until numParts)
+              .flatMap(_ => (0 until 100).map(i => (i, s"v$i", i.toDouble)))
+              .sorted
+            assert(rowContents === expected)
+          }
+        }
+      }
+    }
+  }
+
+  test("CometBroadcastExchangeExec: shared hash table") {
+    assume(isSpark34Plus, "ChunkedByteBuffer is not serializable before Spark 3.4+")
+    withSQLConf(
+      CometConf.COMET_EXEC_BROADCAST_FORCE_ENABLED.key -> "true",
+      CometConf.COMET_EXEC_BROADCAST_MMAP_ENABLED.key -> "true",
+      CometConf.COMET_EXEC_BROADCAST_MMAP_THRESHOLD.key -> "0",
+      CometConf.COMET_EXEC_BROADCAST_SHARED_HASH_TABLE_ENABLED.key -> "true") {
+      withParquetTable((0 until 100).map(i => (i, s"v$i")), "tbl_a") {
+        withParquetTable((0 until 1000).map(i => (i % 150, i)), "tbl_b") {
+          val joinTypes = Seq("JOIN", "RIGHT JOIN", "LEFT JOIN")
+          joinTypes.foreach { joinType =>
+            val df = sql(
+              "SELECT /*+ BROADCAST(a) */ a._1, a._2, b._2" +
+                s" FROM tbl_a a $joinType tbl_b b ON a._1 = b._1 AND b._2 % 7 != 0")
+            checkSparkAnswer(df)
+          }
+        }
+      }
+    }
+  }
+
   test("CometBroadcastExchangeExec: empty broadcast") {
     assume(isSpark34Plus, "ChunkedByteBuffer is not serializable before Spark 3.4+")
     withSQLConf(CometConf.COMET_EXEC_BROADCAST_FORCE_ENABLED.key -> "true") {
@@ -355,6 +472,36 @@ class CometExecSuite extends CometTestBase {
     }
   }
 
+  test("Comet native metrics: JVM/native boundary") {
+    withSQLConf(
+      CometConf.COMET_EXEC_ENABLED.key -> "true",
+      CometConf.COMET_EXEC_ALL_OPERATOR_ENABLED.key -> "true") {
+      withParquetTable((0 until 5).map(i => (i, i + 1)), "tbl") {
+        val df = sql("SELECT _1 + 1, _2 + 2 FROM tbl WHERE _1 > 3")
+        df.collect()
+
+        // The boundary metrics are reported on the root of the native plan only
+        val filterMetrics = find(df.queryExecution.executedPlan) {
+          case _: CometFilterExec => true
+          case _ => false
+        }.map(_.metrics).get
+        assert(!filterMetrics.contains("jni_import_batches"))
+
+        val metrics = find(df.queryExecution.executedPlan) {
+          case _: CometProjectExec => true
+          case _ => false
+        }.map(_.metrics).get
+
+        assert(metrics("jni_import_batches").value == 1L)
+        assert(metrics("jni_import_bytes").value > 0L)
+        assert(metrics("jni_export_batches").value == 1L)
+        assert(metrics("jni_export_bytes").value > 0L)
+        assert(metrics("jni_callbacks").value >= 2L)
+        assert(metrics("jni_time").value > 0L)
+      }
+    }
+  }
+
   test("Comet native metrics: HashJoin") {
     withParquetTable((0 until 5).map(i => (i, i + 1)), "t1") {
       withParquetTable((0 until 5).map(i => (i, i + 1)), "t2") {
@@ -484,6 +631,36 @@ class CometExecSuite extends CometTestBase {
     }
   }
 
+  test("reused exchange and subquery read by native plans") {
+    Seq("true", "false").foreach { shared =>
+      withSQLConf(
+        SQLConf.EXCHANGE_REUSE_ENABLED.key -> "true",
+        SQLConf.AUTO_BROADCASTJOIN_THRESHOLD.key -> "-1",
+        SQLConf.ADAPTIVE_AUTO_BROADCASTJOIN_THRESHOLD.key -> "-1",
+        CometConf.COMET_EXEC_SHUFFLE_ENABLED.key -> "true",
+        CometConf.COMET_EXEC_REUSED_EXCHANGE_SHARED_ENABLED.key -> shared,
+        CometConf.COMET_BATCH_SIZE.key -> "10") {
+        withParquetTable((0 until 1000).map(i => (i % 100, i)), "tbl") {
+          // Both sides of the self-join read the same shuffle
+          val df = sql("""
+              |SELECT a._1, a.s, b.s
+              |FROM (SELECT _1, sum(_2) s FROM tbl GROUP BY _1) a
+              |JOIN (SELECT _1, sum(_2) s FROM tbl GROUP BY _1) b ON a._1 = b._1
+              |""".stripMargin)
+          checkSparkAnswer(df)
+          assert(collect(df.queryExecution.executedPlan) { case r: ReusedExchangeExec =>
+            r
+          }.nonEmpty)
+
+          checkSparkAnswer(sql("""
+              |SELECT _1, _2 FROM tbl
+              |WHERE _2 > (SELECT max(_1) FROM tbl) AND _1 < (SELECT max(_1) FROM tbl) - 90
+              |""".stripMargin))
+        }
+      }
+    }
+  }
+
   test("expand operator") {
     val data1 = (0 until 1000)
       .map(_ % 5) // reduce value space to trigger dictionary encoding
@@ -498,6 +675,134 @@ class CometExecSuite extends CometTestBase {
     }
   }
 
+  test("generate operator") {
+    withSQLConf(CometConf.COMET_EXEC_SHUFFLE_ENABLED.key -> "true") {
+      // Groups with only null values collect empty arrays
+      val data = (0 until 100).map(i => (i % 5, if (i % 5 == 4 || i % 7 == 0) None else Some(i)))
+      withParquetTable(data, "tbl") {
+        val arrays = "SELECT _1, collect_list(_2) AS a FROM tbl GROUP BY _1"
+        // The order of collected values depends on the order of shuffle blocks, so only the
+        // positions are checked for `posexplode`
+        val queries = Seq("explode", "explode_outer").map { generator =>
+          s"SELECT _1, $generator(a) FROM ($arrays)"
+        } ++ Seq("posexplode", "posexplode_outer").map { generator =>
+          s"SELECT _1, pos FROM (SELECT _1, $generator(a) FROM ($arrays))"
+        } :+ s"SELECT t._1, e.pos FROM ($arrays) t LATERAL VIEW OUTER posexplode(a) e AS pos, col"
+
+        queries.foreach { query =>
+          val df = sql(query)
+          checkSparkAnswer(df)
+          assert(find(df.queryExecution.executedPlan) {
+            case _: CometGenerateExec => true
+            case _ => false
+          }.isDefined)
+        }
+      }
+    }
+  }
+
+  test("timestamp_ntz") {
+    assume(isSpark34Plus, "Parquet timestamps without time zone are inferred in Spark 3.4+")
+    Seq(true, false).foreach { dictionaryEnabled =>
+      withTempDir { dir =>
+        val path = new Path(dir.toURI.toString, "timestamp_ntz.parquet")
+        makeRawTimeParquetFile(path, dictionaryEnabled = dictionaryEnabled, 10000)
+        withSQLConf(
+          SESSION_LOCAL_TIMEZONE.key -> "Asia/Kathmandu",
+          CometConf.COMET_EXEC_SHUFFLE_ENABLED.key -> "true") {
+          withParquetTable(path.toString, "timetbl") {
+            // `_3` and `_5` are timestamps without time zone
+            checkSparkAnswerAndOperator(
+              "SELECT hour(_3), minute(_3), second(_3), hour(_5), minute(_5), second(_5), " +
+                "cast(_5 AS timestamp), cast(_4 AS timestamp_ntz), hash(_5) FROM timetbl")
+            checkSparkAnswerAndOperator(
+              "SELECT _5 FROM timetbl WHERE _5 > TIMESTAMP_NTZ '1970-01-01 00:00:01'")
+            checkSparkAnswerAndOperator(
+              "SELECT _5, count(*), min(_3), max(_3) FROM timetbl GROUP BY _5")
+          }
+        }
+      }
+    }
+  }
+
+  test("native LocalTableScan") {
+    withSQLConf(CometConf.COMET_EXEC_SHUFFLE_ENABLED.key -> "true") {
+      withParquetTable((0 until 100).map(i => (i, i % 5)), "tbl") {
+        // The local relation is broadcasted, or shuffled for sort merge join
+        Seq("10485760", "-1").foreach { threshold =>
+          withSQLConf(SQLConf.AUTO_BROADCASTJOIN_THRESHOLD.key -> threshold) {
+            val df = sql(
+              "SELECT tbl._1, dim.name FROM tbl " +
+                "JOIN VALUES (0, 'a'), (2, 'b'), (4, NULL) AS dim(id, name) ON tbl._2 = dim.id")
+            checkSparkAnswerAndOperator(df, Seq(classOf[CometLocalTableScanExec]))
+          }
+        }
+
+        // A local relation not consumed by Comet operators is read by Spark
+        val df = sql("SELECT * FROM VALUES (0, 'a'), (2, 'b') AS dim(id, name)")
+        checkSparkAnswer(df)
+        assert(stripAQEPlan(df.queryExecution.executedPlan).collect {
+          case s: CometLocalTableScanExec => s
+        }.isEmpty)
+      }
+    }
+  }
+
+  test("native file scan of CSV and JSON files") {
+    withTempPath { dir =>
+      val data = (0 until 1000).map(i => (i, if (i % 7 == 0) null else s"s${i % 10}", i * 1.5))
+      val df = data.toDF("a", "b", "c").repartition(3)
+      df.write.option("header", "true").option("sep", "|").csv(s"$dir/csv")
+      df.write.json(s"$dir/json")
+
+      withSQLConf(
+        CometConf.COMET_SCAN_NATIVE_FORMATS.key -> "csv,json",
+        // Splits the files, so that some lines span two splits
+        SQLConf.FILES_MAX_PARTITION_BYTES.key -> "1000") {
+        val csv = spark.read
+          .schema("a INT, b STRING, c DOUBLE")
+          .option("header", "true")
+          .option("sep", "|")
+          .csv(s"$dir/csv")
+        val json = spark.read.schema("c DOUBLE, a INT, b STRING").json(s"$dir/json")
+        Seq(csv, json).foreach { df =>
+          df.createOrReplaceTempView("t")
+          checkSparkAnswerAndOperator(sql("SELECT * FROM t"))
+          checkSparkAnswerAndOperator(sql("SELECT b, sum(a) FROM t WHERE a > 100 GROUP BY b"))
+          checkSparkAnswerAndOperator(sql("SELECT c FROM t WHERE b = 's3' ORDER BY c"))
+          assert(stripAQEPlan(sql("SELECT * FROM t").queryExecution.executedPlan).collect {
+            case s: CometNativeFileScanExec => s
+          }.nonEmpty)
+        }
+
+        // Unsupported options fall back to Spark
+        val df = spark.read.schema("a INT").option("mode", "FAILFAST").csv(s"$dir/csv")
+        checkSparkAnswer(df)
+        assert(stripAQEPlan(df.queryExecution.executedPlan).collect {
+          case s: CometNativeFileScanExec => s
+        }.isEmpty)
+      }
+    }
+  }
+
+  test("grouping() and grouping_id() with ROLLUP and CUBE") {
+    val data = (0 until 100).map(i => (i % 3, if (i % 7 == 0) None else Some(i % 4), i))
+    withParquetTable(data, "tbl") {
+      // Spark resolves `grouping` and `grouping_id` into bitwise expressions on the grouping id
+      // produced by `Expand`, which are evaluated by native aggregation and projection
+      Seq("ROLLUP", "CUBE").foreach { groupingAnalytics =>
+        val df = sql(
+          "SELECT _1, _2, grouping(_1), grouping(_2), grouping_id(), grouping_id(_2, _1)," +
+            s" SUM(_3) FROM tbl GROUP BY $groupingAnalytics(_1, _2)")
+        checkSparkAnswerAndOperator(df)
+      }
+      val df = sql(
+        "SELECT _1, _2, SUM(_3) FROM tbl GROUP BY _1, _2 GROUPING SETS ((_1), (_2), ())" +
+          " HAVING grouping_id() > 0")
+      checkSparkAnswerAndOperator(df)
+    }
+  }
+
   test("multiple distinct multiple columns sets") {
     withTable("agg2") {
       val data2 = Seq[(Integer, Integer, Integer)](
@@ -646,6 +951,24 @@ class CometExecSuite extends CometTestBase {
     }
   }
 
+  test("sort with null ordering") {
+    val data = Seq(Some(3), None, Some(1), Some(-2), None).zipWithIndex.map { case (v, i) =>
+      (v, if (i % 2 == 0) Some(s"s$i") else None)
+    }
+    withParquetTable(data, "tbl") {
+      Seq("ASC NULLS FIRST", "ASC NULLS LAST", "DESC NULLS FIRST", "DESC NULLS LAST").foreach {
+        order =>
+          val df = sql(s"SELECT * FROM tbl SORT BY _1 $order, _2 $order")
+          checkSparkAnswerAndOperator(df)
+          // `SORT BY` doesn't require a global order, so compares the order of rows here
+          val expected = withSQLConf(CometConf.COMET_ENABLED.key -> "false") {
+            sql(s"SELECT * FROM tbl SORT BY _1 $order, _2 $order").collect().toSeq
+          }
+          assert(df.collect().toSeq == expected)
+      }
+    }
+  }
+
   test("global sort (columnar shuffle only)") {
     withSQLConf(
       SQLConf.ADAPTIVE_EXECUTION_ENABLED.key -> "false",
@@ -757,6 +1080,26 @@ class CometExecSuite extends CometTestBase {
     }
   }
 
+  test("limit pushed into scan") {
+    withParquetTable((0 until 1000).map(i => (i, i + 1)), "tbl") {
+      Seq("true", "false").foreach { enabled =>
+        withSQLConf(CometConf.COMET_SCAN_LIMIT_PUSHDOWN_ENABLED.key -> enabled) {
+          val df = sql("SELECT _1, _2 + 1 FROM tbl LIMIT 3")
+          checkSparkAnswer(df)
+          val scan = stripAQEPlan(df.queryExecution.executedPlan).collectFirst {
+            case scan: CometScanExec => scan
+          }.get
+          if (enabled.toBoolean) {
+            assert(scan.pushedLimit.contains(3))
+            assert(scan.metrics("numOutputRows").value <= 3)
+          } else {
+            assert(scan.pushedLimit.isEmpty)
+          }
+        }
+      }
+    }
+  }
+
   test("empty-column input (read schema is empty)") {
     withTable("t1") {
       Seq((1, true), (2, false))
@@ -23,7 +23,9 @@ import org.scalactic.source.Position
 import org.scalatest.Tag
 
 import org.apache.spark.sql.CometTestBase
-import org.apache.spark.sql.comet.{CometBroadcastExchangeExec, CometBroadcastHashJoinExec}
+import org.apache.spark.sql.comet.{CometBroadcastExchangeExec, CometBroadcastHashJoinExec, CometSortMergeJoinExec}
+import org.apache.spark.sql.execution.adaptive.{AQEShuffleReadExec, BroadcastQueryStageExec}
+import org.apache.spark.sql.execution.exchange.ReusedExchangeExec
 import org.apache.spark.sql.internal.SQLConf
 
 import org.apache.comet.CometConf
@@ -100,6 +102,94 @@ class CometJoinSuite extends CometTestBase {
     }
   }
 
+  test("Broadcast null-aware anti join") {
+    assume(isSpark34Plus, "ChunkedByteBuffer is not serializable before Spark 3.4+")
+    withSQLConf(
+      CometConf.COMET_BATCH_SIZE.key -> "100",
+      SQLConf.OPTIMIZE_NULL_AWARE_ANTI_JOIN.key -> "true") {
+      val values = (0 until 100).map(i => if (i % 7 == 0) None else Some(i % 20))
+      withParquetTable(values.map(Tuple1(_)), "tbl_a") {
+        withParquetTable((0 until 10).map(i => (Some(i * 3), i)), "tbl_b") {
+          withParquetTable((0 until 10).map(i => (Option.empty[Int], i)), "tbl_c") {
+            // No null in the subquery: the rows with a null key are excluded
+            val df1 = sql("SELECT * FROM tbl_a WHERE _1 NOT IN (SELECT _1 FROM tbl_b)")
+            checkSparkAnswerAndOperator(
+              df1,
+              Seq(classOf[CometBroadcastExchangeExec], classOf[CometBroadcastHashJoinExec]))
+
+            // A null in the subquery: no row is returned
+            val df2 = sql(
+              "SELECT * FROM tbl_a WHERE _1 NOT IN (SELECT _1 FROM tbl_b UNION ALL " +
+                "SELECT _1 FROM tbl_c WHERE _2 = 0)")
+            checkSparkAnswerAndOperator(
+              df2,
+              Seq(classOf[CometBroadcastExchangeExec], classOf[CometBroadcastHashJoinExec]))
+
+            // An empty subquery: every row is returned, including the ones with a null key
+            val df3 =
+              sql("SELECT * FROM tbl_a WHERE _1 NOT IN (SELECT _1 FROM tbl_b WHERE _2 < 0)")
+            checkSparkAnswerAndOperator(
+              df3,
+              Seq(classOf[CometBroadcastExchangeExec], classOf[CometBroadcastHashJoinExec]))
+          }
+        }
+      }
+    }
+  }
+
+  test("Broadcast HashJoin with reused broadcast exchange") {
+    assume(isSpark34Plus, "ChunkedByteBuffer is not serializable before Spark 3.4+")
+    Seq("true", "false").foreach { aqeEnabled =>
+      withSQLConf(
+        SQLConf.ADAPTIVE_EXECUTION_ENABLED.key -> aqeEnabled,
+        SQLConf.EXCHANGE_REUSE_ENABLED.key -> "true") {
+        withParquetTable((0 until 1000).map(i => (i, i % 5)), "tbl_a") {
+          withParquetTable((0 until 10).map(i => (i % 5, i + 2)), "tbl_b") {
+            // Both joins broadcast the same relation, which is reused by the second one
+            val df = sql(
+              "SELECT /*+ BROADCAST(tbl_b) */ * FROM tbl_a JOIN tbl_b " +
+                "ON tbl_a._2 = tbl_b._1 WHERE tbl_a._1 < 500 UNION ALL " +
+                "SELECT /*+ BROADCAST(tbl_b) */ * FROM tbl_a JOIN tbl_b " +
+                "ON tbl_a._2 = tbl_b._1 WHERE tbl_a._1 >= 500")
+            checkSparkAnswer(df)
+
+            val plan = stripAQEPlan(df.queryExecution.executedPlan)
+            assert(plan.collect {
+              case r: ReusedExchangeExec => r
+              case BroadcastQueryStageExec(_, r: ReusedExchangeExec, _) => r
+            }.nonEmpty)
+            assert(plan.collect { case j: CometBroadcastHashJoinExec => j }.length == 2)
+          }
+        }
+      }
+    }
+  }
+
+  test("SortMergeJoin with skewed partitions split by AQE") {
+    withSQLConf(
+      SQLConf.ADAPTIVE_EXECUTION_ENABLED.key -> "true",
+      SQLConf.SKEW_JOIN_ENABLED.key -> "true",
+      SQLConf.SKEW_JOIN_SKEWED_PARTITION_THRESHOLD.key -> "100",
+      SQLConf.ADVISORY_PARTITION_SIZE_IN_BYTES.key -> "100",
+      SQLConf.COALESCE_PARTITIONS_MIN_PARTITION_NUM.key -> "1",
+      SQLConf.AUTO_BROADCASTJOIN_THRESHOLD.key -> "-1",
+      SQLConf.ADAPTIVE_AUTO_BROADCASTJOIN_THRESHOLD.key -> "-1") {
+      // Most of the rows of `tbl_a` have the key 0
+      withParquetTable((0 until 1000).map(i => (if (i < 900) 0 else i, i)), "tbl_a") {
+        withParquetTable((0 until 100).map(i => (i % 10, i)), "tbl_b") {
+          val df = sql("SELECT * FROM tbl_a JOIN tbl_b ON tbl_a._1 = tbl_b._1")
+          checkSparkAnswer(df)
+
+          val plan = stripAQEPlan(df.queryExecution.executedPlan)
+          assert(plan.collectFirst {
+            case r: AQEShuffleReadExec if r.hasSkewedPartition => r
+          }.isDefined)
+          assert(plan.collectFirst { case j: CometSortMergeJoinExec => j }.isDefined)
+        }
+      }
+    }
+  }
+
   test("HashJoin without join filter") {
     withSQLConf(
       SQLConf.PREFER_SORTMERGEJOIN.key -> "false",
@@ -154,6 +244,35 @@ class CometJoinSuite extends CometTestBase {
     }
   }
 
+  test("HashJoin with null-safe equality join keys") {
+    withSQLConf(
+      SQLConf.PREFER_SORTMERGEJOIN.key -> "false",
+      SQLConf.ADAPTIVE_AUTO_BROADCASTJOIN_THRESHOLD.key -> "-1",
+      SQLConf.AUTO_BROADCASTJOIN_THRESHOLD.key -> "-1") {
+      val a = (0 until 10).map(i => (i, if (i % 3 == 0) None else Some(i % 5)))
+      val b = (0 until 10).map(i => (if (i % 4 == 0) None else Some(i % 10), i + 2))
+      withParquetTable(a, "tbl_a") {
+        withParquetTable(b, "tbl_b") {
+          // Null keys match each other
+          checkSparkAnswerAndOperator(sql("SELECT /*+ SHUFFLE_HASH(tbl_a) */ * FROM tbl_a " +
+            "JOIN tbl_b ON tbl_a._2 <=> tbl_b._1"))
+          checkSparkAnswerAndOperator(sql("SELECT /*+ SHUFFLE_HASH(tbl_a) */ * FROM tbl_a " +
+            "FULL JOIN tbl_b ON tbl_a._2 <=> tbl_b._1 AND tbl_a._1 <=> tbl_b._2"))
+          // Null keys don't match each other for the keys compared by `=`
+          checkSparkAnswerAndOperator(sql("SELECT /*+ SHUFFLE_HASH(tbl_a) */ * FROM tbl_a " +
+            "JOIN tbl_b ON tbl_a._2 <=> tbl_b._1 AND tbl_a._1 = tbl_b._2"))
+        }
+      }
+    }
+
+    withParquetTable((0 until 10).map(i => (i, if (i % 3 == 0) None else Some(i % 5))), "tbl_a") {
+      withParquetTable((0 until 10).map(i => (if (i % 4 == 0) None else Some(i), i)), "tbl_b") {
+        checkSparkAnswerAndOperator(
+          sql("SELECT /*+ BROADCAST(tbl_a) */ * FROM tbl_a JOIN tbl_b ON tbl_a._2 <=> tbl_b._1"))
+      }
+    }
+  }
+
   test("HashJoin with join filter") {
     withSQLConf(
       SQLConf.PREFER_SORTMERGEJOIN.key -> "false",
@@ -186,7 +305,31 @@ class CometJoinSuite extends CometTestBase {
     }
   }
 
-  // TODO: Add a test for SortMergeJoin with join filter after new DataFusion release
+  test("HashJoin with dictionary encoded build side") {
+    withSQLConf(
+      CometConf.COMET_EXEC_DICTIONARY_ENCODING_ENABLED.key -> "true",
+      SQLConf.PREFER_SORTMERGEJOIN.key -> "false",
+      SQLConf.ADAPTIVE_AUTO_BROADCASTJOIN_THRESHOLD.key -> "-1",
+      SQLConf.AUTO_BROADCASTJOIN_THRESHOLD.key -> "-1") {
+      val countries = Seq("us", "fr", "jp", null)
+      withParquetTable((0 until 1000).map(i => (i % 10, countries(i % 4), s"name$i")), "tbl_a") {
+        withParquetTable((0 until 100).map(i => (i % 10, countries(i % 3))), "tbl_b") {
+          // The low-cardinality columns of the build side are encoded, except the ones of the
+          // join keys and the join filter
+          checkSparkAnswerAndOperator(
+            sql(
+              "SELECT /*+ SHUFFLE_HASH(tbl_a) */ * FROM tbl_a JOIN tbl_b ON tbl_a._1 = tbl_b._1"))
+          checkSparkAnswerAndOperator(
+            sql(
+              "SELECT /*+ SHUFFLE_HASH(tbl_a) */ * FROM tbl_a RIGHT JOIN tbl_b " +
+                "ON tbl_a._1 = tbl_b._1 AND tbl_a._2 <> tbl_b._2"))
+          // The shuffled rows are encoded as well
+          checkSparkAnswerAndOperator(sql("SELECT _2, count(_3) FROM tbl_a GROUP BY _2"))
+        }
+      }
+    }
+  }
+
   test("SortMergeJoin without join filter") {
     withSQLConf(
       SQLConf.ADAPTIVE_AUTO_BROADCASTJOIN_THRESHOLD.key -> "-1",
@@ -232,4 +375,50 @@ class CometJoinSuite extends CometTestBase {
       }
     }
   }
+
+  test("SortMergeJoin with band condition") {
+    withSQLConf(
+      SQLConf.ADAPTIVE_AUTO_BROADCASTJOIN_THRESHOLD.key -> "-1",
+      SQLConf.AUTO_BROADCASTJOIN_THRESHOLD.key -> "-1",
+      CometConf.COMET_BATCH_SIZE.key -> "7") {
+      // The events and sessions of users, with nulls in the keys and bounds
+      val events = (0 until 200).map { i =>
+        (if (i % 37 == 0) None else Some(i % 5), Some(i.toLong * 3), s"e$i")
+      }
+      val sessions = (0 until 40).map { i =>
+        val start = i.toLong * 15
+        val user = if (i % 13 == 0) None else Some(i % 5)
+        (user, if (i % 11 == 0) None else Some(start), start + 40)
+      }
+      withParquetTable(events, "events") {
+        withParquetTable(sessions, "sessions") {
+          Seq("JOIN", "LEFT JOIN", "LEFT SEMI JOIN", "LEFT ANTI JOIN").foreach { join =>
+            val df = sql(s"""
+                |SELECT * FROM events $join sessions
+                |ON events._1 = sessions._1 AND events._2 BETWEEN sessions._2 AND sessions._3
+                |""".stripMargin)
+            checkSparkAnswerAndOperator(df)
+          }
+
+          // A single bound, with another condition evaluated within the band
+          checkSparkAnswerAndOperator(sql("""
+              |SELECT * FROM events JOIN sessions
+              |ON events._1 = sessions._1 AND sessions._3 > events._2 AND events._3 != 'e42'
+              |""".stripMargin))
+
+          // Not a band condition
+          val df = sql("""
+              |SELECT * FROM events JOIN sessions
+              |ON events._1 = sessions._1 AND events._2 + sessions._3 > 100
+              |""".stripMargin)
+          checkSparkAnswer(df)
+          assert(
+            find(df.queryExecution.executedPlan) {
+              case _: CometSortMergeJoinExec => true
+              case _ => false
+            }.isEmpty)
+        }
+      }
+    }
+  }
 }
@@ -24,9 +24,11 @@ import org.scalatest.Tag
 
 import org.apache.hadoop.fs.Path
 import org.apache.spark.sql.{CometTestBase, DataFrame}
-import org.apache.spark.sql.comet.execution.shuffle.CometShuffleExchangeExec
-import org.apache.spark.sql.execution.adaptive.AdaptiveSparkPlanHelper
-import org.apache.spark.sql.functions.col
+import org.apache.spark.sql.comet.execution.shuffle.{CometShuffledBatchRDD, CometShuffleExchangeExec}
+import org.apache.spark.sql.execution.{CoalescedPartitionSpec, PartialReducerPartitionSpec, ShufflePartitionSpec}
+import org.apache.spark.sql.execution.adaptive.{AdaptiveSparkPlanHelper, AQEShuffleReadExec, ShuffleQueryStageExec}
+import org.apache.spark.sql.functions.{col, spark_partition_id}
+import org.apache.spark.sql.internal.SQLConf
 
 import org.apache.comet.CometConf
 import org.apache.comet.CometSparkSessionExtensions.isSpark34Plus
@@ -108,6 +110,164 @@ class CometNativeShuffleSuite extends CometTestBase with AdaptiveSparkPlanHelper
     }
   }
 
+  test("native shuffle: round-robin partitioning") {
+    withParquetTable((0 until 100).map(i => (i % 7, (i + 1).toLong)), "tbl") {
+      Seq(true, false).foreach { sortBeforeRepartition =>
+        withSQLConf(SQLConf.SORT_BEFORE_REPARTITION.key -> sortBeforeRepartition.toString) {
+          val shuffled = sql("SELECT * FROM tbl").repartition(10)
+          checkShuffleAnswer(shuffled, 1)
+        }
+      }
+
+      // Each row goes to the same partition as Spark if the rows are not sorted first
+      withSQLConf(SQLConf.SORT_BEFORE_REPARTITION.key -> "false") {
+        val shuffled = sql("SELECT * FROM tbl")
+          .repartition(3)
+          .withColumn("p", spark_partition_id())
+        checkSparkAnswer(shuffled)
+      }
+    }
+  }
+
+  test("native shuffle: compression codecs") {
+    withParquetTable((0 until 1000).map(i => (i % 13, i.toString)), "tbl") {
+      Seq("lz4", "snappy", "zstd").foreach { codec =>
+        withSQLConf(CometConf.COMET_EXEC_SHUFFLE_CODEC.key -> codec) {
+          val shuffled = sql("SELECT * FROM tbl").repartition(10, $"_1")
+          checkShuffleAnswer(shuffled, 1)
+        }
+      }
+    }
+  }
+
+  test("native shuffle: sort-based") {
+    withSQLConf(CometConf.COMET_EXEC_SHUFFLE_SORT_BASED_ENABLED.key -> "true") {
+      withParquetTable((0 until 1000).map(i => (i % 13, i.toString)), "tbl") {
+        Seq(1, 10, 200).foreach { numPartitions =>
+          val shuffled = sql("SELECT * FROM tbl").repartition(numPartitions, $"_1")
+          checkShuffleAnswer(shuffled, 1)
+        }
+        checkShuffleAnswer(sql("SELECT * FROM tbl").repartition(10), 1)
+      }
+    }
+  }
+
+  test("native shuffle: unified memory") {
+    Seq("true", "false").foreach { enabled =>
+      withSQLConf(CometConf.COMET_EXEC_SHUFFLE_UNIFIED_MEMORY_ENABLED.key -> enabled) {
+        withParquetTable((0 until 1000).map(i => (i % 13, i.toString)), "tbl") {
+          val shuffled = sql("SELECT * FROM tbl").repartition(10, $"_1")
+          checkShuffleAnswer(shuffled, 1)
+        }
+      }
+    }
+  }
+
+  test("native shuffle: native reader") {
+    withSQLConf(CometConf.COMET_EXEC_SHUFFLE_NATIVE_READER_ENABLED.key -> "true") {
+      withParquetTable((0 until 1000).map(i => (i % 13, i.toString)), "tbl") {
+        Seq("lz4", "snappy", "zstd").foreach { codec =>
+          withSQLConf(CometConf.COMET_EXEC_SHUFFLE_CODEC.key -> codec) {
+            val shuffled = sql("SELECT * FROM tbl").repartition(10, $"_1")
+            checkShuffleAnswer(shuffled, 1)
+          }
+        }
+        val df = sql("SELECT _1, count(_2) FROM tbl GROUP BY _1")
+        checkShuffleAnswer(df, 1, checkNativeOperators = true)
+      }
+    }
+  }
+
+  test("native shuffle: native reader coalescing") {
+    withSQLConf(
+      CometConf.COMET_EXEC_SHUFFLE_NATIVE_READER_ENABLED.key -> "true",
+      SQLConf.ADAPTIVE_EXECUTION_ENABLED.key -> "false") {
+      withParquetTable((0 until 1000).map(i => (i % 13, i.toString)), "tbl") {
+        Seq(true, false).foreach { coalesce =>
+          withSQLConf(CometConf.COMET_EXEC_SHUFFLE_COALESCE_ENABLED.key -> coalesce.toString) {
+            // Each reducer fetches a small block from each of the 10 maps
+            val shuffled = sql("SELECT * FROM tbl").repartition(10).repartition(2, $"_1")
+            checkShuffleAnswer(shuffled, 2)
+
+            val exchange = collect(shuffled.queryExecution.executedPlan) {
+              case s: CometShuffleExchangeExec => s
+            }.head
+            val numBatches =
+              exchange.executeColumnar().mapPartitions(iter => Iterator(iter.size)).collect()
+            if (coalesce) {
+              assert(numBatches.forall(_ == 1), numBatches.mkString(", "))
+            } else {
+              assert(numBatches.sum > numBatches.length, numBatches.mkString(", "))
+            }
+          }
+        }
+      }
+    }
+  }
+
+  test("native shuffle: native reader of AQE partition specs") {
+    withSQLConf(
+      CometConf.COMET_EXEC_SHUFFLE_NATIVE_READER_ENABLED.key -> "true",
+      SQLConf.ADAPTIVE_EXECUTION_ENABLED.key -> "true",
+      SQLConf.SKEW_JOIN_ENABLED.key -> "true",
+      SQLConf.SKEW_JOIN_SKEWED_PARTITION_THRESHOLD.key -> "100",
+      SQLConf.ADVISORY_PARTITION_SIZE_IN_BYTES.key -> "100",
+      SQLConf.COALESCE_PARTITIONS_MIN_PARTITION_NUM.key -> "1",
+      SQLConf.AUTO_BROADCASTJOIN_THRESHOLD.key -> "-1",
+      SQLConf.ADAPTIVE_AUTO_BROADCASTJOIN_THRESHOLD.key -> "-1") {
+      // Most of the rows of `tbl_a` have the key 0, whose partition is split by map ranges
+      withParquetTable((0 until 1000).map(i => (if (i < 900) 0 else i, i)), "tbl_a") {
+        withParquetTable((0 until 100).map(i => (i % 10, i)), "tbl_b") {
+          val df = sql("SELECT * FROM tbl_a JOIN tbl_b ON tbl_a._1 = tbl_b._1")
+          checkSparkAnswer(df)
+          val reads = collect(df.queryExecution.executedPlan) { case r: AQEShuffleReadExec => r }
+          assert(reads.exists(_.hasSkewedPartition))
+          val stages = collect(df.queryExecution.executedPlan) { case s: ShuffleQueryStageExec =>
+            s.shuffle
+          }
+          assert(stages.nonEmpty && stages.forall(_.isInstanceOf[CometShuffleExchangeExec]))
+        }
+      }
+    }
+  }
+
+  test("native shuffle: native reader of several block ranges") {
+    withSQLConf(
+      CometConf.COMET_EXEC_SHUFFLE_NATIVE_READER_ENABLED.key -> "true",
+      SQLConf.ADAPTIVE_EXECUTION_ENABLED.key -> "false") {
+      withParquetTable((0 until 1000).map(i => (i % 13, i.toString)), "tbl") {
+        Seq(true, false).foreach { coalesce =>
+          withSQLConf(CometConf.COMET_EXEC_SHUFFLE_COALESCE_ENABLED.key -> coalesce.toString) {
+            val shuffled = sql("SELECT * FROM tbl").repartition(10).repartition(4, $"_1")
+            val exchange = collect(shuffled.queryExecution.executedPlan) {
+              case s: CometShuffleExchangeExec => s
+            }.head
+            val rows = exchange
+              .executeColumnar()
+              .mapPartitions(iter => Iterator(iter.map(_.numRows()).sum))
+              .collect()
+
+            // The reducer 2 is split by ranges of maps read with other reducers
+            val specs: Array[Seq[ShufflePartitionSpec]] = Array(
+              Seq(CoalescedPartitionSpec(0, 2), PartialReducerPartitionSpec(2, 0, 4, 0)),
+              Seq(PartialReducerPartitionSpec(2, 4, exchange.numMappers, 0)),
+              Seq(CoalescedPartitionSpec(3, 4)))
+            val stitched =
+              new CometShuffledBatchRDD(exchange.shuffleDependency, exchange.metrics, specs)
+                .mapPartitions(iter => Iterator(iter.map(_.numRows()).toList))
+                .collect()
+            assert(stitched.map(_.sum).sum == rows.sum)
+            assert(stitched(2).sum == rows(3))
+            if (coalesce) {
+              // The batches of all the ranges are coalesced together
+              assert(stitched.forall(_.length == 1), stitched.mkString(", "))
+            }
+          }
+        }
+      }
+    }
+  }
+
   test("native shuffle with dictionary of binary") {
     Seq("true", "false").foreach { dictionaryEnabled =>
       withParquetTable(
@@ -153,6 +313,30 @@ class CometNativeShuffleSuite extends CometTestBase with AdaptiveSparkPlanHelper
     }
   }
 
+  test("grouped aggregate: combine partial aggregation in native shuffle") {
+    // Partial aggregation is skipped, so that it emits the same keys for each batch
+    withSQLConf(
+      CometConf.COMET_BATCH_SIZE.key -> "10",
+      CometConf.COMET_EXEC_PARTIAL_AGG_SKIP_ENABLED.key -> "true",
+      CometConf.COMET_EXEC_PARTIAL_AGG_SKIP_PROBE_ROWS.key -> "10",
+      CometConf.COMET_EXEC_PARTIAL_AGG_SKIP_RATIO_THRESHOLD.key -> "0.1") {
+      withParquetTable((0 until 1000).map(i => (i % 7, i.toLong)), "tbl") {
+        val recordsWritten = Seq(false, true).map { combine =>
+          withSQLConf(CometConf.COMET_EXEC_SHUFFLE_COMBINE_ENABLED.key -> combine.toString) {
+            val df = sql("SELECT _1, count(_2), sum(_2), max(_2), avg(_2) FROM tbl GROUP BY _1")
+            checkShuffleAnswer(df, 1, checkNativeOperators = true)
+            find(df.queryExecution.executedPlan) {
+              case _: CometShuffleExchangeExec => true
+              case _ => false
+            }.map(_.metrics("shuffleRecordsWritten").value).get
+          }
+        }
+        assert(recordsWritten(1) < recordsWritten(0))
+        assert(recordsWritten(1) <= 7 * spark.table("tbl").rdd.getNumPartitions)
+      }
+    }
+  }
+
   test("native shuffle metrics") {
     withParquetTable((0 until 5).map(i => (i, (i + 1).toLong)), "tbl") {
       val df = sql("SELECT * FROM tbl").sortWithinPartitions($"_1".desc)
@@ -169,6 +353,13 @@ class CometNativeShuffleSuite extends CometTestBase with AdaptiveSparkPlanHelper
 
       assert(metrics.contains("shuffleRecordsWritten"))
       assert(metrics("shuffleRecordsWritten").value == 5L)
+      assert(metrics("shuffleBytesWritten").value > 0L)
+      assert(metrics("shuffleBytesWritten").value == metrics("dataSize").value)
+      assert(metrics("shuffleWriteTime").value > 0L)
+      assert(metrics("encodeTime").value > 0L)
+      assert(metrics("compressTime").value > 0L)
+      assert(metrics("spillCount").value == 0L)
+      assert(metrics("spilledBytes").value == 0L)
     }
   }
 
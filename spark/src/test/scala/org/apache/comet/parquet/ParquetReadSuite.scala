@@ -32,6 +32,7 @@ import org.scalatest.Tag
 import org.apache.hadoop.conf.Configuration
 import org.apache.hadoop.fs.Path
 import org.apache.parquet.example.data.simple.SimpleGroup
+import org.apache.parquet.io.api.Binary
 import org.apache.parquet.schema.MessageTypeParser
 import org.apache.spark.SparkException
 import org.apache.spark.sql.CometTestBase
@@ -241,6 +242,39 @@ abstract class ParquetReadSuite extends CometTestBase {
     }
   }
 
+  test("timestamps written with different output timestamp types") {
+    import testImplicits._
+
+    // The files of a table can be written with different `outputTimestampType`s, e.g., by
+    // different writers over time, and are all read as micro-seconds
+    val timestamps = Seq(
+      "1970-01-01 00:00:00",
+      "2020-01-01 01:02:03.123456",
+      "1901-01-01 00:00:00.001",
+      "9999-12-31 23:59:59.999999")
+    withTempPath { path =>
+      Seq("INT96", "TIMESTAMP_MICROS", "TIMESTAMP_MILLIS").foreach { outputType =>
+        withSQLConf(
+          SQLConf.PARQUET_OUTPUT_TIMESTAMP_TYPE.key -> outputType,
+          SQLConf.PARQUET_INT96_REBASE_MODE_IN_WRITE.key -> "CORRECTED",
+          SQLConf.PARQUET_REBASE_MODE_IN_WRITE.key -> "CORRECTED") {
+          (timestamps :+ null)
+            .toDF("ts")
+            .select($"ts".cast("timestamp").as("ts"))
+            .repartition(1)
+            .write
+            .mode("append")
+            .parquet(path.getCanonicalPath)
+        }
+      }
+      withSQLConf(
+        SQLConf.PARQUET_INT96_REBASE_MODE_IN_READ.key -> "CORRECTED",
+        SQLConf.PARQUET_REBASE_MODE_IN_READ.key -> "CORRECTED") {
+        checkSparkAnswer(spark.read.parquet(path.getCanonicalPath))
+      }
+    }
+  }
+
   test("batch paging on basic types") {
     Seq(1, 2, 4, 9).foreach { batchSize =>
       withSQLConf(CometConf.COMET_BATCH_SIZE.key -> batchSize.toString) {
@@ -812,6 +846,217 @@ abstract class ParquetReadSuite extends CometTestBase {
     }
   }
 
+  test("nested struct columns") {
+    Seq(true, false).foreach { dictionaryEnabled =>
+      withTempPath { dir =>
+        val path = dir.getCanonicalPath
+        // Nulls at every level: the outer structs, the inner structs and their fields
+        val data = (0 until 1000).map { i =>
+          val inner =
+            if (i % 5 == 0) None else Some((i.toLong, if (i % 3 == 0) null else s"s${i % 7}"))
+          val outer = if (i % 11 == 0) None else Some((i % 4, inner))
+          (i, outer)
+        }
+        data
+          .toDF("id", "s")
+          .write
+          .option("parquet.enable.dictionary", dictionaryEnabled.toString)
+          .parquet(path)
+
+        val df = spark.read.parquet(path)
+        checkSparkAnswer(df)
+        assert(df.queryExecution.executedPlan.find {
+          case _: CometScanExec | _: CometBatchScanExec => true
+          case _ => false
+        }.isDefined)
+        checkSparkAnswer(df.select("s._2._2", "id"))
+        checkSparkAnswer(df.where("s._2 IS NULL").select("id", "s._1"))
+
+        // The fields missing in the files are null, and so are the structs of missing fields only
+        val schema = "id INT, s STRUCT<_1: INT, _3: STRUCT<a: INT>>, t STRUCT<b: STRING>"
+        checkSparkAnswer(spark.read.schema(schema).parquet(path))
+      }
+    }
+  }
+
+  test("list columns") {
+    Seq(true, false).foreach { dictionaryEnabled =>
+      Seq(true, false).foreach { legacyFormat =>
+        withSQLConf(SQLConf.PARQUET_WRITE_LEGACY_FORMAT.key -> legacyFormat.toString) {
+          withTempPath { dir =>
+            val path = dir.getCanonicalPath
+            // Null and empty lists and null elements, in nested lists and lists of structs too
+            val data = (0 until 1000).map { i =>
+              val ints =
+                if (i % 7 == 0) None
+                else Some((0 until i % 4).map(j => if ((i + j) % 5 == 0) None else Some(i + j)))
+              val nested =
+                if (i % 11 == 0) None
+                else Some((0 until i % 3).map(j => (0 until j).map(k => s"s${(i + k) % 6}")))
+              val structs =
+                if (i % 13 == 0) None
+                else Some((0 until i % 3).map(j => Some((j, Seq(i.toLong))).filter(_ => j != 1)))
+              (i, ints, nested, structs)
+            }
+            data
+              .toDF("id", "ints", "nested", "structs")
+              .write
+              .option("parquet.enable.dictionary", dictionaryEnabled.toString)
+              .parquet(path)
+
+            val df = spark.read.parquet(path)
+            checkSparkAnswer(df)
+            assert(df.queryExecution.executedPlan.find {
+              case _: CometScanExec | _: CometBatchScanExec => true
+              case _ => false
+            }.isDefined)
+            checkSparkAnswer(df.select("id", "structs._2"))
+            checkSparkAnswer(df.where("size(ints) > 1").select("nested", "id"))
+          }
+        }
+      }
+    }
+  }
+
+  test("legacy list layouts") {
+    Seq(true, false).foreach { dictionaryEnabled =>
+      def makeRawParquetFile(path: Path): Unit = {
+        // A repeated field without a list, and a list of structs in the 2-level layout
+        val schemaStr =
+          """message root {
+            |  repeated INT32 a;
+            |  optional group b (LIST) {
+            |    repeated group array {
+            |      required INT32 x;
+            |      optional BINARY y (UTF8);
+            |    }
+            |  }
+            |}
+        """.stripMargin
+        val schema = MessageTypeParser.parseMessageType(schemaStr)
+
+        val writer = createParquetWriter(schema, path, dictionaryEnabled)
+
+        (0 until 100).foreach { n =>
+          val record = new SimpleGroup(schema)
+          (0 until n % 3).foreach(i => record.add(0, n + i))
+          if (n % 4 != 0) {
+            val list = record.addGroup(1)
+            (0 until n % 5).foreach { i =>
+              val element = list.addGroup(0)
+              element.add(0, i)
+              if (i % 2 == 0) element.add(1, s"y$n")
+            }
+          }
+          writer.write(record)
+        }
+        writer.close()
+      }
+
+      withTempDir { dir =>
+        val path = new Path(dir.toURI.toString, "part-r-0.parquet")
+        makeRawParquetFile(path)
+        readParquetFile(path.toString) { df =>
+          checkAnswer(
+            df,
+            (0 until 100).map { n =>
+              val b =
+                if (n % 4 == 0) null
+                else (0 until n % 5).map(i => Row(i, if (i % 2 == 0) s"y$n" else null))
+              Row((0 until n % 3).map(n + _), b)
+            })
+        }
+      }
+    }
+  }
+
+  test("map columns") {
+    Seq(true, false).foreach { dictionaryEnabled =>
+      Seq(true, false).foreach { legacyFormat =>
+        withSQLConf(SQLConf.PARQUET_WRITE_LEGACY_FORMAT.key -> legacyFormat.toString) {
+          withTempPath { dir =>
+            val path = dir.getCanonicalPath
+            // Null and empty maps and null values, in maps of structs and lists of maps too
+            val data = (0 until 1000).map { i =>
+              val ints =
+                if (i % 7 == 0) None
+                else Some((0 until i % 4).map(j => s"k$j" -> Some(i + j).filter(_ % 5 > 0)).toMap)
+              val structs =
+                if (i % 11 == 0) None
+                else Some((0 until i % 3).map(j => j.toLong -> (s"s${i % 6}", Seq(i))).toMap)
+              val nested =
+                if (i % 13 == 0) None
+                else Some((0 until i % 3).map(j => (0 until j).map(k => k -> s"v${i % 4}").toMap))
+              (i, ints, structs, nested)
+            }
+            data
+              .toDF("id", "ints", "structs", "nested")
+              .write
+              .option("parquet.enable.dictionary", dictionaryEnabled.toString)
+              .parquet(path)
+
+            val df = spark.read.parquet(path)
+            checkSparkAnswer(df)
+            assert(df.queryExecution.executedPlan.find {
+              case _: CometScanExec | _: CometBatchScanExec => true
+              case _ => false
+            }.isDefined)
+            checkSparkAnswer(df.select("id", "ints.k1"))
+            checkSparkAnswer(df.where("size(structs) > 1").select("nested", "id"))
+          }
+        }
+      }
+    }
+  }
+
+  test("legacy map layouts") {
+    Seq(true, false).foreach { dictionaryEnabled =>
+      def makeRawParquetFile(path: Path): Unit = {
+        // A map annotated as a key-value group, as written by old Parquet writers
+        val schemaStr =
+          """message root {
+            |  optional group m (MAP_KEY_VALUE) {
+            |    repeated group map {
+            |      required INT32 key;
+            |      optional BINARY value (UTF8);
+            |    }
+            |  }
+            |}
+        """.stripMargin
+        val schema = MessageTypeParser.parseMessageType(schemaStr)
+
+        val writer = createParquetWriter(schema, path, dictionaryEnabled)
+
+        (0 until 100).foreach { n =>
+          val record = new SimpleGroup(schema)
+          if (n % 4 != 0) {
+            val map = record.addGroup(0)
+            (0 until n % 5).foreach { i =>
+              val entry = map.addGroup(0)
+              entry.add(0, i)
+              if (i % 2 == 0) entry.add(1, s"v$n")
+            }
+          }
+          writer.write(record)
+        }
+        writer.close()
+      }
+
+      withTempDir { dir =>
+        val path = new Path(dir.toURI.toString, "part-r-0.parquet")
+        makeRawParquetFile(path)
+        readParquetFile(path.toString) { df =>
+          checkAnswer(
+            df,
+            (0 until 100).map { n =>
+              if (n % 4 == 0) Row(null)
+              else Row((0 until n % 5).map(i => i -> (if (i % 2 == 0) s"v$n" else null)).toMap)
+            })
+        }
+      }
+    }
+  }
+
   test("unsigned int supported") {
     Seq(true, false).foreach { dictionaryEnabled =>
       def makeRawParquetFile(path: Path): Unit = {
@@ -951,6 +1196,73 @@ abstract class ParquetReadSuite extends CometTestBase {
     }
   }
 
+  test("DELTA_BINARY_PACKED encoding") {
+    Seq(true, false).foreach { dictionaryEnabled =>
+      withTempPath { dir =>
+        val path = dir.getCanonicalPath
+        // The v2 writer encodes the INT32 and INT64 columns with DELTA_BINARY_PACKED, also when
+        // the dictionary grows too large, in which case the later pages fall back to it
+        val data = (0 until 10000).map { i =>
+          val value = if (i % 2 == 0) i * 1000003L else -i * 7L
+          val int = if (i % 17 == 0) None else Some(value.toInt)
+          val long = if (i % 19 == 0) Long.MinValue else value * 1000000007L
+          val date = new java.sql.Date(value)
+          (i.toByte, i.toShort, int, long, date, new java.sql.Timestamp(value))
+        }
+        data
+          .toDF("byte", "short", "int", "long", "date", "ts")
+          .withColumn("dec9", $"int".cast(DecimalType(9, 2)))
+          .withColumn("dec18", ($"long" / 1000).cast(DecimalType(18, 3)))
+          .repartition(1)
+          .write
+          .option("parquet.writer.version", "v2")
+          .option("parquet.enable.dictionary", dictionaryEnabled.toString)
+          .option("parquet.dictionary.page.size", "1024")
+          .parquet(path)
+
+        val df = spark.read.parquet(path)
+        checkSparkAnswer(df)
+        assert(df.queryExecution.executedPlan.find {
+          case _: CometScanExec | _: CometBatchScanExec => true
+          case _ => false
+        }.isDefined)
+        checkSparkAnswer(df.where("int > 100").select("long", "ts", "dec9"))
+      }
+    }
+  }
+
+  test("DELTA_BYTE_ARRAY encoding") {
+    Seq(true, false).foreach { dictionaryEnabled =>
+      withTempPath { dir =>
+        val path = dir.getCanonicalPath
+        // The v2 writer encodes the BYTE_ARRAY and FIXED_LEN_BYTE_ARRAY columns with
+        // DELTA_BYTE_ARRAY, whose values share prefixes with the previous ones
+        val data = (0 until 10000).map { i =>
+          val str = if (i % 13 == 0) None else Some(s"prefix/${i / 10}/" + "x" * (i % 7))
+          val bin = if (i % 11 == 0) Array.emptyByteArray else s"${i % 100}-$i".getBytes
+          (str, bin, BigDecimal(i) * 1000000007L / 3)
+        }
+        data
+          .toDF("str", "bin", "dec")
+          .withColumn("dec", $"dec".cast(DecimalType(25, 5)))
+          .repartition(1)
+          .write
+          .option("parquet.writer.version", "v2")
+          .option("parquet.enable.dictionary", dictionaryEnabled.toString)
+          .option("parquet.dictionary.page.size", "1024")
+          .parquet(path)
+
+        val df = spark.read.parquet(path)
+        checkSparkAnswer(df)
+        assert(df.queryExecution.executedPlan.find {
+          case _: CometScanExec | _: CometBatchScanExec => true
+          case _ => false
+        }.isDefined)
+        checkSparkAnswer(df.where("str like '%/99/%'").select("dec", "bin"))
+      }
+    }
+  }
+
   test("schema evolution") {
     Seq(true, false).foreach { enableSchemaEvolution =>
       Seq(true, false).foreach { useDictionary =>
@@ -1010,6 +1322,51 @@ abstract class ParquetReadSuite extends CometTestBase {
     }
   }
 
+  test("skip row groups by dictionary with IN predicates") {
+    // The values of the IN list are more than `spark.sql.parquet.pushdown.inFilterThreshold` and
+    // within the min/max of the row group, so only the dictionary tells that none of them match
+    val values = (1 to 21 by 2).mkString(", ")
+    withParquetTable((0 until 10000).map(i => (i % 10 * 2, i.toString)), "tbl") {
+      val df = sql(s"SELECT * FROM tbl WHERE _1 IN ($values)")
+      assert(df.collect().isEmpty)
+      val scans = df.queryExecution.executedPlan collect {
+        case s: CometScanExec => s
+        case s: CometBatchScanExec => s
+      }
+      assert(scans.head.metrics("ParquetRowGroups").value == 0)
+      assert(scans.head.metrics("ParquetRowGroupsSkipped").value == 1)
+      assert(scans.head.metrics("ParquetRowsSkipped").value == 10000)
+
+      checkSparkAnswer(s"SELECT * FROM tbl WHERE _1 IN (0, $values)")
+      checkSparkAnswer(s"SELECT * FROM tbl WHERE _1 IN (NULL, 0, $values)")
+    }
+  }
+
+  test("skip row groups by Bloom filters") {
+    withTempPath { dir =>
+      (0 until 1000)
+        .map(i => (i * 2, i.toString))
+        .toDF("a", "b")
+        .coalesce(1)
+        .write
+        .option("parquet.bloom.filter.enabled#a", "true")
+        .option("parquet.enable.dictionary", "false")
+        .parquet(dir.getCanonicalPath)
+
+      // 501 is within the min/max of the row group, so only the Bloom filter tells it is absent
+      val df = spark.read.parquet(dir.getCanonicalPath).where("a = 501")
+      assert(df.collect().isEmpty)
+      val scans = df.queryExecution.executedPlan collect {
+        case s: CometScanExec => s
+        case s: CometBatchScanExec => s
+      }
+      assert(scans.head.metrics("ParquetRowGroups").value == 0)
+      assert(scans.head.metrics("ParquetRowGroupsSkipped").value == 1)
+      assert(scans.head.metrics("ParquetRowGroupsSkippedByBloomFilter").value == 1)
+      assert(scans.head.metrics("ParquetRowsSkipped").value == 1000)
+    }
+  }
+
   test("read dictionary encoded decimals written as FIXED_LEN_BYTE_ARRAY") {
     // In this test, data is encoded using Parquet page v2 format, but with PLAIN encoding
     checkAnswer(
@@ -1124,6 +1481,39 @@ abstract class ParquetReadSuite extends CometTestBase {
     }
   }
 
+  test("UTF-8 validation of string columns") {
+    // Both "\xff" and the halves of "é" are invalid, even though the bytes of the latter are
+    // valid all together
+    val values = Seq("abc".getBytes, Array(0xff.toByte), Array(0xc3.toByte), Array(0xa9.toByte))
+    Seq(true, false).foreach { dictionaryEnabled =>
+      withTempDir { dir =>
+        val schema =
+          MessageTypeParser.parseMessageType("message root { optional binary _1 (UTF8); }")
+        values.indices.foreach { i =>
+          val path = new Path(dir.toURI.toString, s"part-$i.parquet")
+          val writer = createParquetWriter(schema, path, dictionaryEnabled = dictionaryEnabled)
+          Seq(values.head, values(i)).foreach { bytes =>
+            val record = new SimpleGroup(schema)
+            record.add(0, Binary.fromConstantByteArray(bytes))
+            writer.write(record)
+          }
+          writer.close()
+
+          val df = spark.read.parquet(path.toString)
+          if (i == 0) {
+            checkSparkAnswer(df)
+          } else {
+            val exception = intercept[SparkException](df.collect())
+            assert(exception.getMessage.contains("invalid UTF-8"))
+          }
+          withSQLConf(CometConf.COMET_PARQUET_UTF8_VALIDATION_ENABLED.key -> "false") {
+            checkSparkAnswer(spark.read.parquet(path.toString))
+          }
+        }
+      }
+    }
+  }
+
   test("row group skipping doesn't overflow when reading into larger type") {
     assume(isSpark34Plus)
 
@@ -0,0 +1,292 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one
+ * or more contributor license agreements.  See the NOTICE file
+ * distributed with this work for additional information
+ * regarding copyright ownership.  The ASF licenses this file
+ * to you under the Apache License, Version 2.0 (the
+ * "License"); you may not use this file except in compliance
+ * with the License.  You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+ * KIND, either express or implied.  See the License for the
+ * specific language governing permissions and limitations
+ * under the License.
+ */
+
+package org.apache.spark.sql.comet
+
+import java.io.{File, FileOutputStream}
+import java.nio.channels.Channels
+import java.nio.file.Files
+
+import scala.collection.JavaConverters._
+import scala.util.Random
+
+import org.apache.arrow.vector.VectorSchemaRoot
+import org.apache.arrow.vector.ipc.ArrowStreamWriter
+import org.apache.spark.sql.{Column, CometTestBase, DataFrame, Row}
+import org.apache.spark.sql.catalyst.InternalRow
+import org.apache.spark.sql.catalyst.expressions._
+import org.apache.spark.sql.comet.execution.arrow.CometArrowConverters
+import org.apache.spark.sql.comet.util.Utils
+import org.apache.spark.sql.internal.SQLConf
+import org.apache.spark.sql.types._
+import org.apache.spark.unsafe.types.UTF8String
+
+import org.apache.comet.CometConf
+import org.apache.comet.serde.ExprOuterClass.Expr
+import org.apache.comet.serde.QueryPlanSerde
+
+/**
+ * Generates golden fixtures for the differential expression fuzzing in the native
+ * `execution::datafusion::fuzz` tests.
+ *
+ * Random expression trees are evaluated on random data by Spark with Comet disabled. For each
+ * expression supported by Comet, the serialized Comet `Expr` is written to `<name>.expr`, and
+ * the input data together with the Spark result (in the last column `expected`) is written to
+ * `<name>.arrow` as an Arrow IPC stream. The random cases are named `case_<n>`. The edge cases in
+ * [[edgeCases]], e.g., integral overflow, are evaluated on [[edgeRows]] and named after them.
+ *
+ * This only runs when `comet.fuzz.fixtureDir` system property is set, e.g.,
+ * {{{
+ *   ./mvnw test -Dsuites=org.apache.spark.sql.comet.CometExpressionFuzzSuite \
+ *     -Dcomet.fuzz.fixtureDir=$PWD/core/testdata/fuzz
+ * }}}
+ */
+class CometExpressionFuzzSuite extends CometTestBase {
+  private val fixtureDir = sys.props.get("comet.fuzz.fixtureDir")
+  private val numCases = sys.props.getOrElse("comet.fuzz.numCases", "100").toInt
+  private val seed = sys.props.getOrElse("comet.fuzz.seed", "42").toLong
+
+  private val numRows = 256
+  private val maxDepth = 4
+
+  private val schema = StructType(
+    Seq(
+      StructField("c0", BooleanType),
+      StructField("c1", IntegerType),
+      StructField("c2", LongType),
+      StructField("c3", DoubleType),
+      StructField("c4", StringType)))
+  private val numericTypes = Seq(IntegerType, LongType, DoubleType)
+
+  /** The input of the edge cases, with extreme values, NaN, -0.0 and multi-byte characters. */
+  private val edgeRows = Seq(
+    Row(true, 1, 7L, 1.5, "abcdef"),
+    Row(false, -1000, -7L, -0.0, " 12 "),
+    Row(null, Int.MaxValue, Long.MaxValue, Double.NaN, "中é"),
+    Row(true, Int.MinValue, Long.MinValue, Double.PositiveInfinity, ""),
+    Row(false, null, null, null, null),
+    Row(true, 0, 0L, -123.9, "Zé0 b"),
+    Row(null, 999, 1000L, 1e10, "a"),
+    Row(false, 42, -1L, 123.9, "ab"))
+
+  /** The named edge cases over the columns `c0` to `c4` of [[edgeRows]]. */
+  private def edgeCases(attributes: Seq[Attribute]): Seq[(String, Expression)] = {
+    val (c0, c1, c2, c3, c4) =
+      (attributes(0), attributes(1), attributes(2), attributes(3), attributes(4))
+    Seq(
+      "add_int32_overflow" -> Add(c1, c1),
+      "subtract_int64_overflow" -> Subtract(c2, Literal(7L)),
+      "multiply_double" -> Multiply(c3, c3),
+      "substring_chars" -> Substring(c4, Literal(2), Literal(3)),
+      "if_is_null" -> If(IsNull(c1), Literal(-1), c1),
+      "equal_null_safe_bool" -> EqualNullSafe(c0, Literal(true)),
+      "not_bool" -> Not(c0),
+      "cast_int32_to_string" -> Cast(c1, StringType, Some("UTC")),
+      "cast_int64_to_double" -> Cast(c2, DoubleType, Some("UTC")))
+  }
+
+  test("generate golden fixtures for native expression fuzzing") {
+    assume(fixtureDir.isDefined, "comet.fuzz.fixtureDir is not set")
+
+    val dir = new File(fixtureDir.get)
+    dir.mkdirs()
+
+    val random = new Random(seed)
+    val rows = (0 until numRows).map(_ => randomRow(random))
+    val df = spark.createDataFrame(spark.sparkContext.parallelize(rows, 1), schema)
+    val attributes = df.queryExecution.analyzed.output
+
+    var generated = 0
+    var attempts = 0
+    while (generated < numCases && attempts < numCases * 10) {
+      attempts += 1
+      val dataType = schema.fields(random.nextInt(schema.length)).dataType
+      val expr = randomExpr(random, attributes, dataType, maxDepth)
+
+      // Skip expressions which are not supported by Comet
+      if (generateFixture(dir, df, attributes, f"case_$generated%04d", expr)) {
+        generated += 1
+      }
+    }
+
+    val edgeDf = spark.createDataFrame(spark.sparkContext.parallelize(edgeRows, 1), schema)
+    val edgeAttributes = edgeDf.queryExecution.analyzed.output
+    edgeCases(edgeAttributes).foreach { case (name, expr) =>
+      assert(generateFixture(dir, edgeDf, edgeAttributes, name, expr), s"$name is not supported")
+    }
+  }
+
+  /**
+   * Evaluates `expr` on `df` with Spark and writes the fixture `name` if Comet supports `expr`.
+   * Returns whether the fixture is written.
+   */
+  private def generateFixture(
+      dir: File,
+      df: DataFrame,
+      attributes: Seq[Attribute],
+      name: String,
+      expr: Expression): Boolean = {
+    QueryPlanSerde.exprToProto(expr, attributes) match {
+      case Some(proto) =>
+        withSQLConf(CometConf.COMET_ENABLED.key -> "false") {
+          val result = df.select(df.columns.map(df.col) :+ new Column(expr).as("expected"): _*)
+          val resultRows = result.queryExecution.toRdd.map(_.copy()).collect()
+          writeFixture(dir, name, proto, result.schema, resultRows)
+        }
+        true
+      case None => false
+    }
+  }
+
+  private def writeFixture(
+      dir: File,
+      name: String,
+      expr: Expr,
+      schema: StructType,
+      rows: Array[InternalRow]): Unit = {
+    Files.write(new File(dir, s"$name.expr").toPath, expr.toByteArray)
+
+    val batches = new CometArrowConverters.ArrowBatchIterator(
+      rows.iterator,
+      schema,
+      rows.length.max(1),
+      SQLConf.get.sessionLocalTimeZone,
+      null)
+    val out = new FileOutputStream(new File(dir, s"$name.arrow"))
+    try {
+      batches.foreach { batch =>
+        val (vectors, _) = Utils.getBatchFieldVectors(batch)
+        val root = new VectorSchemaRoot(vectors.asJava)
+        val writer = new ArrowStreamWriter(root, null, Channels.newChannel(out))
+        writer.start()
+        writer.writeBatch()
+        writer.end()
+      }
+    } finally {
+      out.close()
+    }
+  }
+
+  private def randomRow(random: Random): Row = {
+    def maybeNull(value: => Any): Any = if (random.nextDouble() < 0.1) null else value
+
+    Row(
+      maybeNull(random.nextBoolean()),
+      maybeNull(random.nextInt(10) match {
+        case 0 => Int.MaxValue
+        case 1 => Int.MinValue
+        case _ => random.nextInt(2000) - 1000
+      }),
+      maybeNull(random.nextInt(10) match {
+        case 0 => Long.MaxValue
+        case 1 => Long.MinValue
+        case _ => random.nextInt(2000) - 1000L
+      }),
+      maybeNull(random.nextInt(10) match {
+        case 0 => Double.NaN
+        case 1 => -0.0
+        case 2 => Double.PositiveInfinity
+        case _ => random.nextDouble() * 2000 - 1000
+      }),
+      maybeNull(randomString(random)))
+  }
+
+  private def randomString(random: Random): String = {
+    val chars = Seq('a', 'b', 'Z', '0', ' ', 'é', '中')
+    (0 until random.nextInt(8)).map(_ => chars(random.nextInt(chars.length))).mkString
+  }
+
+  private def randomLiteral(random: Random, dataType: DataType): Expression = {
+    if (random.nextDouble() < 0.1) {
+      Literal(null, dataType)
+    } else {
+      dataType match {
+        case BooleanType => Literal(random.nextBoolean())
+        case IntegerType => Literal(random.nextInt(200) - 100)
+        case LongType => Literal(random.nextInt(200) - 100L)
+        case DoubleType => Literal(random.nextDouble() * 200 - 100)
+        case _ => Literal(UTF8String.fromString(randomString(random)), StringType)
+      }
+    }
+  }
+
+  /** Generates a random expression returning `dataType` over the given attributes. */
+  private def randomExpr(
+      random: Random,
+      attributes: Seq[Attribute],
+      dataType: DataType,
+      depth: Int): Expression = {
+    def gen(dt: DataType): Expression = randomExpr(random, attributes, dt, depth - 1)
+
+    if (depth == 0 || random.nextDouble() < 0.2) {
+      return if (random.nextDouble() < 0.7) {
+        attributes.find(_.dataType == dataType).get
+      } else {
+        randomLiteral(random, dataType)
+      }
+    }
+
+    // Expressions valid for all types
+    random.nextInt(10) match {
+      case 0 => return If(gen(BooleanType), gen(dataType), gen(dataType))
+      case 1 =>
+        val branches = (0 until random.nextInt(3) + 1).map(_ => (gen(BooleanType), gen(dataType)))
+        return CaseWhen(branches, Some(gen(dataType)))
+      case _ =>
+    }
+
+    dataType match {
+      case BooleanType =>
+        val operandType = schema.fields(random.nextInt(schema.length)).dataType
+        random.nextInt(9) match {
+          case 0 => EqualTo(gen(operandType), gen(operandType))
+          case 1 => LessThan(gen(operandType), gen(operandType))
+          case 2 => GreaterThanOrEqual(gen(operandType), gen(operandType))
+          case 3 => EqualNullSafe(gen(operandType), gen(operandType))
+          case 4 => And(gen(BooleanType), gen(BooleanType))
+          case 5 => Or(gen(BooleanType), gen(BooleanType))
+          case 6 => Not(gen(BooleanType))
+          case 7 => IsNull(gen(operandType))
+          case _ => IsNotNull(gen(operandType))
+        }
+      case IntegerType | LongType | DoubleType =>
+        random.nextInt(6) match {
+          case 0 => Add(gen(dataType), gen(dataType))
+          case 1 => Subtract(gen(dataType), gen(dataType))
+          case 2 => Multiply(gen(dataType), gen(dataType))
+          case 3 => UnaryMinus(gen(dataType))
+          case 4 => Abs(gen(dataType))
+          case _ =>
+            val fromType = numericTypes(random.nextInt(numericTypes.length))
+            Cast(gen(fromType), dataType, Some("UTC"))
+        }
+      case _ =>
+        random.nextInt(2) match {
+          case 0 =>
+            Substring(
+              gen(StringType),
+              Literal(random.nextInt(7) - 3),
+              Literal(random.nextInt(5)))
+          case _ =>
+            val fromType = schema.fields(random.nextInt(schema.length)).dataType
+            Cast(gen(fromType), StringType, Some("UTC"))
+        }
+    }
+  }
+}
@@ -0,0 +1,109 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one
+ * or more contributor license agreements.  See the NOTICE file
+ * distributed with this work for additional information
+ * regarding copyright ownership.  The ASF licenses this file
+ * to you under the Apache License, Version 2.0 (the
+ * "License"); you may not use this file except in compliance
+ * with the License.  You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+ * KIND, either express or implied.  See the License for the
+ * specific language governing permissions and limitations
+ * under the License.
+ */
+
+package org.apache.spark.sql.comet
+
+import java.io.File
+import java.nio.file.Files
+
+import org.apache.spark.sql.CometTestBase
+import org.apache.spark.sql.internal.SQLConf
+
+import org.apache.comet.serde.OperatorOuterClass.Operator
+
+/**
+ * Golden-file tests of native plan serialization.
+ *
+ * For each query shape in the corpus, the serialized native plans of the Comet native blocks are
+ * compared with the golden files `<query>_<n>.pb` in `core/testdata/plans`. The native test
+ * `execution::datafusion::golden_plans` deserializes the same files and checks the native
+ * operator trees they are planned into, so a change on either side which silently breaks the
+ * plan protocol between the plugin and the native library fails one of the two tests.
+ *
+ * To re-generate the golden files after an intended change, run:
+ * {{{
+ *   SPARK_GENERATE_GOLDEN_FILES=1 ./mvnw test -Dsuites=org.apache.spark.sql.comet.CometPlanSerdeSuite
+ *   cd core && SPARK_GENERATE_GOLDEN_FILES=1 cargo test golden_plans
+ * }}}
+ */
+class CometPlanSerdeSuite extends CometTestBase {
+  private val goldenDir: File = getWorkspaceFilePath("core", "testdata", "plans").toFile
+
+  private val regenerateGoldenFiles: Boolean = System.getenv("SPARK_GENERATE_GOLDEN_FILES") == "1"
+
+  /**
+   * Query shapes over table `tbl_a`, each planned into a single Comet native block. Shapes which
+   * aren't planned into native blocks, e.g., a scan only or a limit, aren't covered.
+   */
+  private val queries: Seq[(String, String)] = Seq(
+    ("project_filter", "SELECT _1 + 1, _4 FROM tbl_a WHERE _1 > 10"),
+    ("sort", "SELECT * FROM tbl_a ORDER BY _1 DESC, _4 ASC NULLS LAST"))
+
+  /** Returns the serialized plans of the native blocks in the plan of the given query. */
+  private def serializedPlans(query: String): Seq[Array[Byte]] = {
+    val plan = sql(query).queryExecution.executedPlan
+    plan.collect {
+      case op: CometNativeExec if op.serializedPlanOpt.isDefined => op.serializedPlanOpt.plan.get
+    }
+  }
+
+  private def withTable(f: => Unit): Unit = {
+    val rows = (0 until 100).map(i => (i, i % 5, i.toLong * 3, if (i % 7 == 0) null else s"s$i"))
+    withParquetTable(rows, "tbl_a") {
+      f
+    }
+  }
+
+  test("golden plans exist") {
+    assume(!regenerateGoldenFiles)
+    assert(
+      Option(goldenDir.listFiles()).exists(_.exists(_.getName.endsWith(".pb"))),
+      s"No golden plans found in $goldenDir")
+  }
+
+  queries.foreach { case (name, query) =>
+    test(s"golden plan: $name") {
+      withTable {
+        withSQLConf(SQLConf.ADAPTIVE_EXECUTION_ENABLED.key -> "false") {
+          val plans = serializedPlans(query)
+          assert(plans.nonEmpty, s"No Comet native block found in the plan of: $query")
+
+          plans.zipWithIndex.foreach { case (bytes, i) =>
+            // The plan must be parsable by the current protobuf definitions
+            Operator.parseFrom(bytes)
+
+            val file = new File(goldenDir, s"${name}_$i.pb")
+            if (regenerateGoldenFiles) {
+              goldenDir.mkdirs()
+              Files.write(file.toPath, bytes)
+            } else {
+              assert(
+                file.exists(),
+                s"Missing golden plan $file, run with SPARK_GENERATE_GOLDEN_FILES=1 to create it")
+              assert(
+                Files.readAllBytes(file.toPath).sameElements(bytes),
+                s"Serialized native plan of $name differs from golden plan $file:\n" +
+                  Operator.parseFrom(bytes))
+            }
+          }
+        }
+      }
+    }
+  }
+}
@@ -0,0 +1,81 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one
+ * or more contributor license agreements.  See the NOTICE file
+ * distributed with this work for additional information
+ * regarding copyright ownership.  The ASF licenses this file
+ * to you under the Apache License, Version 2.0 (the
+ * "License"); you may not use this file except in compliance
+ * with the License.  You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+ * KIND, either express or implied.  See the License for the
+ * specific language governing permissions and limitations
+ * under the License.
+ */
+
+package org.apache.spark.sql.comet.execution.shuffle
+
+import java.io.{DataInputStream, FileInputStream}
+
+import org.apache.spark.SparkEnv
+import org.apache.spark.shuffle.IndexShuffleBlockResolver
+import org.apache.spark.sql.CometTestBase
+import org.apache.spark.sql.execution.adaptive.AdaptiveSparkPlanHelper
+
+import org.apache.comet.CometConf
+
+// This test checks that the map outputs of native shuffle are the files of Spark's
+// `IndexShuffleBlockResolver`, which is only visible under package `spark`, so that they can be
+// served by the external shuffle service and migrated on decommission like Spark's.
+class CometNativeShuffleFilesSuite extends CometTestBase with AdaptiveSparkPlanHelper {
+  import testImplicits._
+
+  test("native shuffle: map output files of IndexShuffleBlockResolver") {
+    withSQLConf(
+      CometConf.COMET_EXEC_ENABLED.key -> "true",
+      CometConf.COMET_COLUMNAR_SHUFFLE_ENABLED.key -> "false",
+      CometConf.COMET_EXEC_SHUFFLE_ENABLED.key -> "true") {
+      withParquetTable((0 until 100).map(i => (i % 7, i.toString)), "tbl") {
+        val shuffled = sql("SELECT * FROM tbl").repartition(10, $"_1")
+        checkSparkAnswer(shuffled)
+
+        val shuffleId = collectFirst(shuffled.queryExecution.executedPlan) {
+          case exchange: CometShuffleExchangeExec => exchange.shuffleDependency.shuffleId
+        }.get
+        val resolver = SparkEnv.get.shuffleManager.shuffleBlockResolver
+          .asInstanceOf[IndexShuffleBlockResolver]
+        val mapIds = resolver.getStoredShuffles().filter(_.shuffleId == shuffleId).map(_.mapId)
+        assert(mapIds.nonEmpty)
+        mapIds.foreach { mapId =>
+          // Like the index files written by the resolver, the offsets are big-endian longs from 0
+          // to the length of the data file
+          val indexFile = resolver.getIndexFile(shuffleId, mapId)
+          val in = new DataInputStream(new FileInputStream(indexFile))
+          val offsets =
+            try {
+              Array.fill((indexFile.length() / 8).toInt)(in.readLong())
+            } finally {
+              in.close()
+            }
+          assert(offsets.length == 11)
+          assert(offsets.head == 0)
+          assert(offsets.sliding(2).forall { case Array(start, end) => start <= end })
+          assert(offsets.last == resolver.getDataFile(shuffleId, mapId).length())
+        }
+
+        // The temporary files are renamed or deleted once the map outputs are committed
+        val fileNames = SparkEnv.get.blockManager.diskBlockManager.getAllFiles().map(_.getName)
+        val mapOutputFile = s"shuffle_${shuffleId}_\\d+_0\\.(data|index|checksum\\.\\w+)"
+        assert(
+          fileNames
+            .filter(_.startsWith(s"shuffle_${shuffleId}_"))
+            .forall(_.matches(mapOutputFile)),
+          fileNames)
+      }
+    }
+  }
+}
@@ -23,6 +23,7 @@ import org.apache.spark.sql.{Column, CometTestBase}
 import org.apache.spark.sql.execution.adaptive.AdaptiveSparkPlanHelper
 import org.apache.spark.sql.catalyst.FunctionIdentifier
 import org.apache.spark.sql.catalyst.expressions.{BloomFilterMightContain, Expression, ExpressionInfo}
+import org.apache.spark.sql.catalyst.expressions.aggregate.BloomFilterAggregate
 import org.apache.spark.sql.functions.{col, lit}
 import org.apache.spark.util.sketch.BloomFilter
 
@@ -33,6 +34,7 @@ class CometExpression3_3PlusSuite extends CometTestBase with AdaptiveSparkPlanHe
   import testImplicits._
 
   val func_might_contain = new FunctionIdentifier("might_contain")
+  val func_bloom_filter_agg = new FunctionIdentifier("bloom_filter_agg")
 
   override def beforeAll(): Unit = {
     super.beforeAll()
@@ -40,10 +42,18 @@ class CometExpression3_3PlusSuite extends CometTestBase with AdaptiveSparkPlanHe
     spark.sessionState.functionRegistry.registerFunction(func_might_contain,
       new ExpressionInfo(classOf[BloomFilterMightContain].getName, "might_contain"),
       (children: Seq[Expression]) => BloomFilterMightContain(children.head, children(1)))
+    // Register 'bloom_filter_agg' to builtin.
+    spark.sessionState.functionRegistry.registerFunction(func_bloom_filter_agg,
+      new ExpressionInfo(classOf[BloomFilterAggregate].getName, "bloom_filter_agg"),
+      (children: Seq[Expression]) => children.size match {
+        case 1 => new BloomFilterAggregate(children.head)
+        case 3 => new BloomFilterAggregate(children.head, children(1), children(2))
+      })
   }
 
   override def afterAll(): Unit = {
     spark.sessionState.functionRegistry.dropFunction(func_might_contain)
+    spark.sessionState.functionRegistry.dropFunction(func_bloom_filter_agg)
     super.afterAll()
   }
 
@@ -95,6 +105,31 @@ class CometExpression3_3PlusSuite extends CometTestBase with AdaptiveSparkPlanHe
     }
   }
 
+  test("test BloomFilterAggregate") {
+    val table = "test"
+
+    withSQLConf(CometConf.COMET_EXEC_SHUFFLE_ENABLED.key -> "true") {
+      withTable(table) {
+        sql(s"create table $table(col1 long, col2 string) using parquet")
+        sql(s"insert into $table values (201, 'a'), (null, 'b'), (-1, null), (42, 'a'), (7, 'c')")
+        // The Bloom filters built natively are the same as Spark's
+        checkSparkAnswerAndOperator(
+          s"""
+            |SELECT bloom_filter_agg(col1), bloom_filter_agg(xxhash64(col2), 100L, 1000L),
+            |       bloom_filter_agg(col1) FILTER (WHERE col1 > 1000)
+            |       FROM $table
+            |""".stripMargin)
+        checkSparkAnswerAndOperator(
+          s"""
+            |SELECT col1, might_contain(
+            |         (SELECT bloom_filter_agg(xxhash64(col2)) FROM $table WHERE col1 > 0),
+            |         xxhash64(col2))
+            |       FROM $table
+            |""".stripMargin)
+      }
+    }
+  }
+
   private def bloomFilterFromRandomInput(expectedItems: Long, expectedBits: Long): (Seq[Long], Array[Byte]) = {
     val bf = BloomFilter.create(expectedItems, expectedBits)
     val longs = (0 until expectedItems.toInt).map(_ => Random.nextLong())
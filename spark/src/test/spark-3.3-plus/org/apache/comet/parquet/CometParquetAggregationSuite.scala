@@ -0,0 +1,69 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one
+ * or more contributor license agreements.  See the NOTICE file
+ * distributed with this work for additional information
+ * regarding copyright ownership.  The ASF licenses this file
+ * to you under the Apache License, Version 2.0 (the
+ * "License"); you may not use this file except in compliance
+ * with the License.  You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+ * KIND, either express or implied.  See the License for the
+ * specific language governing permissions and limitations
+ * under the License.
+ */
+
+package org.apache.comet.parquet
+
+import org.apache.spark.sql.{CometTestBase, DataFrame}
+import org.apache.spark.sql.comet.CometBatchScanExec
+import org.apache.spark.sql.execution.adaptive.AdaptiveSparkPlanHelper
+import org.apache.spark.sql.internal.SQLConf
+
+class CometParquetAggregationSuite extends CometTestBase with AdaptiveSparkPlanHelper {
+
+  private def pushedAggregations(df: DataFrame): Seq[Option[CometParquetAggregation]] =
+    collect(df.queryExecution.executedPlan) { case scan: CometBatchScanExec =>
+      scan.wrapped.scan.asInstanceOf[CometParquetScan].pushedAggregation
+    }
+
+  test("count, min and max pushed down into Parquet scan") {
+    withTempPath { dir =>
+      val path = dir.getCanonicalPath
+      spark
+        .range(10000)
+        .selectExpr(
+          "cast(id as int) as a",
+          "if(id % 7 = 0, null, id * 2) as b",
+          "cast(id % 100 - 50 as tinyint) as c",
+          "id / -3.0d as d",
+          "date_add(date'2020-01-01', cast(id as int)) as e",
+          "cast(null as int) as f")
+        .repartition(3)
+        .write
+        .option("parquet.block.size", "16384")
+        .parquet(path)
+
+      withSQLConf(
+        SQLConf.USE_V1_SOURCE_LIST.key -> "",
+        SQLConf.PARQUET_AGGREGATE_PUSHDOWN_ENABLED.key -> "true") {
+        spark.read.parquet(path).createOrReplaceTempView("tbl")
+
+        val df = sql(
+          "SELECT count(*), count(b), count(f), min(a), max(a), min(b), max(b), min(c), " +
+            "max(c), min(d), max(d), min(e), max(e) FROM tbl")
+        checkSparkAnswer(df)
+        assert(pushedAggregations(df).exists(_.isDefined))
+
+        // Not pushed down by Spark with filters on data columns
+        val filtered = sql("SELECT count(*), max(a) FROM tbl WHERE b > 100")
+        checkSparkAnswer(filtered)
+        assert(pushedAggregations(filtered).forall(_.isEmpty))
+      }
+    }
+  }
+}